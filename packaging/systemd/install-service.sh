@@ -7,6 +7,68 @@ GREEN='\033[0;32m'
 YELLOW='\033[1;33m'
 NC='\033[0m' # No Color
 
+usage() {
+    echo "Usage: $0 [--sandbox] [--run-as USER]"
+    echo ""
+    echo "  --sandbox      Confine the daemon to its sync and data directories"
+    echo "                 (Landlock) and filter its system calls (seccomp)"
+    echo "  --run-as USER  System-wide install only: start as root and switch"
+    echo "                 to USER, instead of using a per-user instance"
+}
+
+SANDBOX=false
+RUN_AS=""
+
+while [ $# -gt 0 ]; do
+    case "$1" in
+        --sandbox)
+            SANDBOX=true
+            ;;
+        --run-as)
+            RUN_AS="$2"
+            if [ -z "$RUN_AS" ]; then
+                usage
+                exit 1
+            fi
+            shift
+            ;;
+        -h|--help)
+            usage
+            exit 0
+            ;;
+        *)
+            usage
+            exit 1
+            ;;
+    esac
+    shift
+done
+
+# Copy a unit file, applying the requested options
+install_unit() {
+    local src="$1"
+    local dest="$2"
+
+    cp "$src" "$dest"
+
+    if [ "$SANDBOX" = true ]; then
+        sed -i \
+            -e 's|^ExecStart=\(.*\)$|ExecStart=\1 --sandbox|' \
+            -e 's|^NoNewPrivileges=true$|NoNewPrivileges=true\nSystemCallArchitectures=native\nSystemCallFilter=@system-service landlock_create_ruleset landlock_add_rule landlock_restrict_self\nSystemCallErrorNumber=EPERM|' \
+            "$dest"
+    fi
+
+    if [ -n "$RUN_AS" ]; then
+        local home
+        home="$(getent passwd "$RUN_AS" | cut -d: -f6)"
+        sed -i \
+            -e '/^User=/d' \
+            -e "s|^ExecStart=\\(.*\\)\$|ExecStart=\\1 --run-as $RUN_AS|" \
+            -e "s|%h|$home|g" \
+            "$dest"
+    fi
+}
+
 echo -e "${GREEN}Proton Drive Sync - Systemd Service Installer${NC}"
 echo "================================================"
 echo ""
@@ -44,8 +106,13 @@ if [ "$SYSTEM_INSTALL" = true ]; then
     # System-wide installation
     echo "Installing system-wide service..."
 
+    if [ -n "$RUN_AS" ] && ! getent passwd "$RUN_AS" > /dev/null; then
+        echo -e "${RED}Error: Unknown user: $RUN_AS${NC}"
+        exit 1
+    fi
+
     # Copy service file
-    cp "$SCRIPT_DIR/proton-drive-sync.service" /etc/systemd/system/
+    install_unit "$SCRIPT_DIR/proton-drive-sync.service" /etc/systemd/system/proton-drive-sync.service
     systemctl daemon-reload
 
     echo ""
@@ -64,8 +131,13 @@ else
     # Create user systemd directory if it doesn't exist
     mkdir -p "$HOME/.config/systemd/user"
 
+    if [ -n "$RUN_AS" ]; then
+        echo -e "${RED}Error: --run-as only applies to a system-wide installation${NC}"
+        exit 1
+    fi
+
     # Copy service file
-    cp "$SCRIPT_DIR/proton-drive-sync@.service" "$HOME/.config/systemd/user/"
+    install_unit "$SCRIPT_DIR/proton-drive-sync@.service" "$HOME/.config/systemd/user/proton-drive-sync@.service"
 
     # Reload systemd
     systemctl --user daemon-reload
@@ -0,0 +1,164 @@
+//! Detection of heavy build-artifact directories
+//!
+//! Finds dependency and build output directories (node_modules, target,
+//! virtualenvs, caches, ...) so they can be excluded before the first scan.
+
+use std::path::{Path, PathBuf};
+
+/// Directory names that are always artifacts
+const ARTIFACT_DIRS: &[&str] = &[
+    "node_modules",
+    "__pycache__",
+    ".venv",
+    ".tox",
+    ".mypy_cache",
+    ".pytest_cache",
+    ".ruff_cache",
+    ".gradle",
+    ".next",
+    ".nuxt",
+    ".parcel-cache",
+    ".turbo",
+];
+
+/// Directory names that are artifacts only next to a matching project marker
+const CONDITIONAL_ARTIFACT_DIRS: &[(&str, &[&str])] = &[
+    ("target", &["Cargo.toml", "pom.xml"]),
+    (
+        "build",
+        &[
+            "build.gradle",
+            "build.gradle.kts",
+            "CMakeLists.txt",
+            "setup.py",
+            "pyproject.toml",
+        ],
+    ),
+    ("dist", &["package.json", "setup.py", "pyproject.toml"]),
+];
+
+/// Check if a directory looks like a build artifact
+pub fn is_artifact_dir(path: &Path) -> bool {
+    let name = match path.file_name().and_then(|n| n.to_str()) {
+        Some(name) => name,
+        None => return false,
+    };
+
+    if ARTIFACT_DIRS.contains(&name) {
+        return true;
+    }
+
+    // Virtualenvs are recognised by their config file rather than a sibling
+    if name == "venv" {
+        return path.join("pyvenv.cfg").is_file();
+    }
+
+    let parent = match path.parent() {
+        Some(parent) => parent,
+        None => return false,
+    };
+
+    CONDITIONAL_ARTIFACT_DIRS
+        .iter()
+        .find(|(dir, _)| *dir == name)
+        .is_some_and(|(_, markers)| markers.iter().any(|m| parent.join(m).is_file()))
+}
+
+/// Find artifact directories under a root
+///
+/// Detected directories are not descended into, so nested artifacts
+/// (e.g. node_modules inside node_modules) are reported once.
+pub fn detect_artifact_dirs(root: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+
+    let mut entries = walkdir::WalkDir::new(root)
+        .follow_links(false)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| e.file_type().is_dir() && e.file_name() != ".git");
+
+    while let Some(entry) = entries.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+
+        if is_artifact_dir(entry.path()) {
+            found.push(entry.path().to_path_buf());
+            entries.skip_current_dir();
+        }
+    }
+
+    found
+}
+
+/// Exclude globs covering an artifact directory and its contents
+pub fn artifact_globs(dir: &Path) -> Vec<String> {
+    let escaped = glob::Pattern::escape(&dir.to_string_lossy());
+    vec![escaped.clone(), format!("{}/**", escaped)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_artifact_dirs() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        fs::create_dir_all(root.join("web/node_modules/pkg/node_modules")).unwrap();
+        fs::write(root.join("web/package.json"), "{}").unwrap();
+        fs::create_dir_all(root.join("web/dist")).unwrap();
+        fs::create_dir_all(root.join("crate/target/debug")).unwrap();
+        fs::write(root.join("crate/Cargo.toml"), "").unwrap();
+        fs::create_dir_all(root.join("photos/target")).unwrap();
+        fs::create_dir_all(root.join("py/venv")).unwrap();
+        fs::write(root.join("py/venv/pyvenv.cfg"), "").unwrap();
+        fs::create_dir_all(root.join("py/src/__pycache__")).unwrap();
+
+        let mut found = detect_artifact_dirs(root);
+        found.sort();
+
+        let mut expected = vec![
+            root.join("crate/target"),
+            root.join("py/src/__pycache__"),
+            root.join("py/venv"),
+            root.join("web/dist"),
+            root.join("web/node_modules"),
+        ];
+        expected.sort();
+
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_conditional_dirs_need_marker() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::create_dir_all(root.join("venv")).unwrap();
+
+        assert!(!is_artifact_dir(&root.join("build")));
+        assert!(!is_artifact_dir(&root.join("venv")));
+
+        fs::write(root.join("CMakeLists.txt"), "").unwrap();
+        assert!(is_artifact_dir(&root.join("build")));
+    }
+
+    #[test]
+    fn test_artifact_globs() {
+        let globs = artifact_globs(Path::new("/home/user/web/node_modules"));
+        let matches = |p: &str| {
+            globs
+                .iter()
+                .any(|g| glob::Pattern::new(g).unwrap().matches(p))
+        };
+
+        assert!(matches("/home/user/web/node_modules"));
+        assert!(matches("/home/user/web/node_modules/pkg/index.js"));
+        assert!(!matches("/home/user/web/src/index.js"));
+    }
+}
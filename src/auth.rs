@@ -3,8 +3,9 @@
 //! Implements SRP (Secure Remote Password) authentication protocol
 
 use crate::error::{Error, Result};
-use crate::types::{AddressData, Session};
+use crate::types::{AccountInfo, AddressData, Session};
 use bcrypt::{hash, verify, DEFAULT_COST};
+use chrono::{DateTime, Utc};
 use rand::Rng;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -30,6 +31,12 @@ const KEYS_ENDPOINT: &str = "/core/v4/keys";
 /// Addresses endpoint
 const ADDRESSES_ENDPOINT: &str = "/core/v4/addresses";
 
+/// User info endpoint
+const USERS_ENDPOINT: &str = "/core/v4/users";
+
+/// Subscription endpoint
+const SUBSCRIPTION_ENDPOINT: &str = "/payments/v4/subscription";
+
 /// SRP authentication request
 #[derive(Debug, Serialize)]
 struct SrpAuthRequest {
@@ -56,6 +63,8 @@ struct SrpAuthResponse {
     refresh_token: String,
     #[serde(rename = "UID")]
     uid: String,
+    #[serde(rename = "ExpiresIn", default)]
+    expires_in: Option<i64>,
 }
 
 /// Auth info response
@@ -88,6 +97,8 @@ struct SessionForkResponse {
     refresh_token: String,
     #[serde(rename = "UID")]
     uid: String,
+    #[serde(rename = "ExpiresIn", default)]
+    expires_in: Option<i64>,
 }
 
 /// Session refresh response
@@ -100,7 +111,6 @@ struct SessionRefreshResponse {
     #[serde(rename = "RefreshToken")]
     refresh_token: String,
     #[serde(rename = "ExpiresIn")]
-    #[allow(dead_code)]
     expires_in: i64,
 }
 
@@ -140,6 +150,7 @@ struct KeySaltingData {
 struct AddressesResponse {
     #[serde(rename = "Code")]
     code: i32,
+    #[serde(rename = "Addresses")]
     addresses: Vec<AddressApiData>,
 }
 
@@ -149,11 +160,66 @@ struct AddressApiData {
     #[serde(rename = "ID")]
     #[allow(dead_code)]
     id: String,
+    #[serde(rename = "Email")]
     email: String,
     #[serde(rename = "ReceiveKey")]
     receive_key: Option<String>,
 }
 
+/// User info response
+#[derive(Debug, Deserialize)]
+struct UserResponse {
+    #[serde(rename = "Code")]
+    code: i32,
+    #[serde(rename = "User")]
+    user: UserApiData,
+}
+
+/// User API data
+#[derive(Debug, Deserialize)]
+struct UserApiData {
+    #[serde(rename = "Email")]
+    email: String,
+    #[serde(rename = "UsedSpace")]
+    #[serde(default)]
+    used_space: u64,
+    #[serde(rename = "MaxSpace")]
+    #[serde(default)]
+    max_space: u64,
+    /// Bitmask of subscribed products, 0 on a free account
+    #[serde(rename = "Subscribed")]
+    #[serde(default)]
+    subscribed: i32,
+}
+
+/// Subscription response
+#[derive(Debug, Deserialize)]
+struct SubscriptionResponse {
+    #[serde(rename = "Code")]
+    code: i32,
+    #[serde(rename = "Subscription")]
+    subscription: SubscriptionApiData,
+}
+
+/// Subscription API data
+#[derive(Debug, Deserialize)]
+struct SubscriptionApiData {
+    #[serde(rename = "Plans")]
+    #[serde(default)]
+    plans: Vec<PlanApiData>,
+}
+
+/// Plan or add-on of a subscription
+#[derive(Debug, Deserialize)]
+struct PlanApiData {
+    #[serde(rename = "Title")]
+    title: String,
+    /// 1 for a plan, 0 for an add-on
+    #[serde(rename = "Type")]
+    #[serde(default)]
+    plan_type: i32,
+}
+
 /// Authentication manager
 pub struct AuthManager {
     client: Client,
@@ -223,6 +289,7 @@ impl AuthManager {
             refresh_token: response.refresh_token,
             key_password: None,
             primary_key: None,
+            expires_at: response.expires_in.map(expiry_from_now),
         })
     }
 
@@ -384,6 +451,7 @@ impl AuthManager {
             refresh_token: fork_response.refresh_token,
             key_password: session.key_password.clone(),
             primary_key: session.primary_key.clone(),
+            expires_at: fork_response.expires_in.map(expiry_from_now),
         })
     }
 
@@ -402,11 +470,13 @@ impl AuthManager {
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            return Err(Error::Auth(format!(
-                "Session refresh failed: {}",
-                response.status()
-            )));
+        // Only a rejected refresh token is an auth error; an outage is not
+        let status = response.status();
+        if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(Error::Auth(format!("Session refresh failed: {}", status)));
+        }
+        if !status.is_success() {
+            return Err(Error::api_failed("Session refresh", status));
         }
 
         let refresh_response: SessionRefreshResponse = response.json().await?;
@@ -424,6 +494,7 @@ impl AuthManager {
             refresh_token: refresh_response.refresh_token,
             key_password: session.key_password.clone(),
             primary_key: session.primary_key.clone(),
+            expires_at: Some(expiry_from_now(refresh_response.expires_in)),
         })
     }
 
@@ -475,17 +546,21 @@ impl AuthManager {
             .send()
             .await?;
 
-        if !response.status().is_success() {
+        // Only a rejected session is an auth error; callers refresh on those
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
             return Err(Error::Auth(format!(
                 "Get addresses failed: {}",
                 response.status()
             )));
         }
+        if !response.status().is_success() {
+            return Err(Error::api_failed("Get addresses", response.status()));
+        }
 
         let addresses_response: AddressesResponse = response.json().await?;
 
         if addresses_response.code != 1000 {
-            return Err(Error::Auth(format!(
+            return Err(Error::api(format!(
                 "Get addresses error code: {}",
                 addresses_response.code
             )));
@@ -501,6 +576,85 @@ impl AuthManager {
             .collect())
     }
 
+    /// Get the account's email, plan and storage usage
+    pub async fn get_user(&self, session: &Session) -> Result<AccountInfo> {
+        let url = format!("{}{}", self.api_base, USERS_ENDPOINT);
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", session.access_token))
+            .send()
+            .await?;
+
+        // Only a rejected session is an auth error; callers refresh on those
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(Error::Auth(format!(
+                "Get user failed: {}",
+                response.status()
+            )));
+        }
+        if !response.status().is_success() {
+            return Err(Error::api_failed("Get user", response.status()));
+        }
+
+        let user_response: UserResponse = response.json().await?;
+
+        if user_response.code != 1000 {
+            return Err(Error::api(format!(
+                "Get user error code: {}",
+                user_response.code
+            )));
+        }
+
+        let user = user_response.user;
+        let plan = if user.subscribed == 0 {
+            Some("Free".to_string())
+        } else {
+            // The plan name is only a nicety; the usage figures matter more
+            self.get_plan(session).await.ok().flatten()
+        };
+
+        Ok(AccountInfo {
+            email: user.email,
+            plan,
+            used_space: user.used_space,
+            max_space: user.max_space,
+        })
+    }
+
+    /// Get the title of the account's paid plan
+    async fn get_plan(&self, session: &Session) -> Result<Option<String>> {
+        let url = format!("{}{}", self.api_base, SUBSCRIPTION_ENDPOINT);
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", session.access_token))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(Error::api_failed("Get subscription", response.status()));
+        }
+
+        let subscription_response: SubscriptionResponse = response.json().await?;
+
+        if subscription_response.code != 1000 {
+            return Err(Error::api(format!(
+                "Get subscription error code: {}",
+                subscription_response.code
+            )));
+        }
+
+        Ok(subscription_response
+            .subscription
+            .plans
+            .into_iter()
+            .find(|p| p.plan_type == 1)
+            .map(|p| p.title))
+    }
+
     /// Unlock key (decrypt private key)
     pub fn unlock_key(&self, encrypted_key: &str, _key_password: &str) -> Result<String> {
         // Simplified key decryption
@@ -515,6 +669,11 @@ impl AuthManager {
     }
 }
 
+/// Expiry time of a token the server says is valid for `expires_in` seconds
+fn expiry_from_now(expires_in: i64) -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::seconds(expires_in)
+}
+
 impl Default for AuthManager {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,81 @@
+//! Short-lived in-process caching of computed values
+//!
+//! Status aggregates are requested by every dashboard client, the CLI over
+//! IPC and the live job feed, often several times a second. Caching them for
+//! a moment keeps those readers from each hitting the database.
+
+use crate::error::Result;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// How long status aggregates are served from the cache
+pub const STATUS_CACHE_TTL: Duration = Duration::from_secs(2);
+
+/// A value recomputed at most once per time-to-live
+///
+/// Concurrent callers of an expired value wait for a single refresh instead
+/// of each computing it.
+pub struct Cached<T> {
+    ttl: Duration,
+    entry: Mutex<Option<(Instant, T)>>,
+}
+
+impl<T: Clone> Cached<T> {
+    /// Create an empty cache
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: Mutex::new(None),
+        }
+    }
+
+    /// Get the cached value, recomputing it if it has expired
+    ///
+    /// Errors are returned to the caller and not cached.
+    pub async fn get_or_refresh<F, Fut>(&self, refresh: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut entry = self.entry.lock().await;
+
+        if let Some((at, value)) = entry.as_ref() {
+            if at.elapsed() < self.ttl {
+                return Ok(value.clone());
+            }
+        }
+
+        let value = refresh().await?;
+        *entry = Some((Instant::now(), value.clone()));
+
+        Ok(value)
+    }
+
+    /// Drop the cached value so the next read recomputes it
+    pub async fn invalidate(&self) {
+        *self.entry.lock().await = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_refreshes_after_ttl() {
+        let cache = Cached::new(Duration::from_millis(50));
+        let calls = AtomicUsize::new(0);
+        let compute = || async { Ok(calls.fetch_add(1, Ordering::SeqCst)) };
+
+        assert_eq!(cache.get_or_refresh(compute).await.unwrap(), 0);
+        assert_eq!(cache.get_or_refresh(compute).await.unwrap(), 0);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(cache.get_or_refresh(compute).await.unwrap(), 1);
+
+        cache.invalidate().await;
+        assert_eq!(cache.get_or_refresh(compute).await.unwrap(), 2);
+    }
+}
@@ -1,47 +1,126 @@
 //! Authentication CLI command
 
 use crate::auth::AuthManager;
+use crate::cli::jobs::format_age;
+use crate::cli::output::print_json;
+use crate::credentials::{save_session, SessionStore};
 use crate::db::Db;
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::ipc::{send_request, IpcRequest};
 use crate::paths::get_data_dir;
-use crate::types::Session;
+use crate::types::{CredentialStore, Session};
+use chrono::{DateTime, Local, Utc};
 use clap::Subcommand;
 use inquire::{Password, Text};
-use keyring::Entry;
+use serde::Serialize;
+use std::future::Future;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 
 /// Authentication command
 #[derive(Subcommand, Debug)]
 pub enum AuthCommand {
     /// Authenticate with Proton
-    Login,
+    Login {
+        /// Email or username (prompted for if omitted)
+        #[arg(long)]
+        username: Option<String>,
+        /// Read the password from this file instead of prompting
+        #[arg(long, conflicts_with = "password_stdin")]
+        password_file: Option<PathBuf>,
+        /// Read the password from standard input instead of prompting
+        #[arg(long)]
+        password_stdin: bool,
+    },
     /// Logout and clear credentials
     Logout,
+    /// Check the stored session against the Proton API
+    Status {
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// State of the stored session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionState {
+    /// No credentials are stored
+    NotLoggedIn,
+    /// Credentials are stored but can't be read, e.g. a wrong passphrase
+    Unreadable,
+    /// The API accepted the session
+    Valid,
+    /// The API rejected the session and it could not be refreshed
+    Expired,
+    /// The API could not be reached or failed, so the session is untested
+    Unverified,
+}
+
+/// Result of `auth status`
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthStatus {
+    pub state: SessionState,
+    pub credential_store: CredentialStore,
+    /// Primary email address of the account
+    pub email: Option<String>,
+    /// When the access token expires, if known
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Whether the session had to be refreshed to be accepted
+    pub refreshed: bool,
+    pub error: Option<String>,
 }
 
 impl AuthCommand {
     /// Run the auth command
     pub async fn run(self) -> Result<()> {
-        match self {
-            Self::Login => self.login().await,
+        match &self {
+            Self::Login {
+                username,
+                password_file,
+                password_stdin,
+            } => {
+                self.login(username.clone(), password_file.as_deref(), *password_stdin)
+                    .await
+            }
             Self::Logout => self.logout().await,
+            Self::Status { json } => self.status(*json).await,
         }
     }
 
     /// Login to Proton
-    async fn login(&self) -> Result<()> {
+    async fn login(
+        &self,
+        username: Option<String>,
+        password_file: Option<&Path>,
+        password_stdin: bool,
+    ) -> Result<()> {
         println!("Proton Drive Authentication");
         println!("============================");
         println!();
 
         // Get username
-        let username = Text::new("Email or username:")
-            .prompt()
-            .map_err(|e| crate::error::Error::Auth(format!("Prompt error: {}", e)))?;
+        let username = match username {
+            Some(username) => username,
+            None => Text::new("Email or username:")
+                .prompt()
+                .map_err(|e| Error::Auth(format!("Prompt error: {}", e)))?,
+        };
 
         // Get password
-        let password = Password::new("Password:")
-            .prompt()
-            .map_err(|e| crate::error::Error::Auth(format!("Prompt error: {}", e)))?;
+        let password =
+            if let Some(path) = password_file {
+                read_password(std::fs::File::open(path).map_err(|e| {
+                    Error::Auth(format!("Could not read {}: {}", path.display(), e))
+                })?)?
+            } else if password_stdin {
+                read_password(std::io::stdin())?
+            } else {
+                Password::new("Password:")
+                    .prompt()
+                    .map_err(|e| Error::Auth(format!("Prompt error: {}", e)))?
+            };
 
         println!();
         println!("Authenticating...");
@@ -55,10 +134,8 @@ impl AuthCommand {
         // Check for 2FA
         // In a full implementation, you'd prompt for 2FA code here
 
-        // Store credentials in keyring
-        let entry = Entry::new("proton-drive-sync", "credentials")?;
-        let credential_json = serde_json::to_string(&session)?;
-        entry.set_password(&credential_json)?;
+        // Store credentials in the keyring or credential file
+        save_session(&session)?;
 
         println!("✓ Credentials saved securely");
 
@@ -77,26 +154,306 @@ impl AuthCommand {
     async fn logout(&self) -> Result<()> {
         println!("Clearing Proton credentials...");
 
-        // Remove credentials from keyring
-        let entry = Entry::new("proton-drive-sync", "credentials")?;
-        let _ = entry.delete_credential();
+        crate::credentials::delete_session()?;
 
         println!("✓ Credentials cleared");
 
         Ok(())
     }
+
+    /// Report whether the stored session works
+    ///
+    /// Fails when the user has to log in again, so scripts can tell.
+    async fn status(&self, json: bool) -> Result<()> {
+        let credential_store = crate::config::ConfigManager::load_blocking()?.credential_store;
+        let mut status = AuthStatus {
+            state: SessionState::NotLoggedIn,
+            credential_store,
+            email: None,
+            expires_at: None,
+            refreshed: false,
+            error: None,
+        };
+
+        if crate::credentials::has_session() {
+            match load_session() {
+                Ok(sessions) => {
+                    status = check_session(
+                        &AuthManager::new(),
+                        sessions.get(),
+                        credential_store,
+                        refresh_through_daemon(),
+                    )
+                    .await;
+                }
+                Err(e) => {
+                    status.state = SessionState::Unreadable;
+                    status.error = Some(e.to_string());
+                }
+            }
+        }
+
+        if json {
+            print_json(&status)?;
+        } else {
+            print_status(&status);
+        }
+
+        match status.state {
+            SessionState::Valid | SessionState::Unverified => Ok(()),
+            SessionState::NotLoggedIn | SessionState::Expired => Err(Error::Auth(
+                "Run `proton-drive-sync auth login` to log in".to_string(),
+            )),
+            SessionState::Unreadable => Err(Error::Auth(
+                "Stored credentials could not be read".to_string(),
+            )),
+        }
+    }
+}
+
+/// Ask the API for the account's addresses
+///
+/// A rejected session is not refreshed here, as that would spend the refresh
+/// token a running daemon holds. `daemon_refresh` has the daemon refresh it
+/// instead and yields the new session, or `None` if no daemon is running.
+async fn check_session(
+    auth: &AuthManager,
+    session: Session,
+    credential_store: CredentialStore,
+    daemon_refresh: impl Future<Output = Result<Option<Session>>>,
+) -> AuthStatus {
+    let mut status = AuthStatus {
+        state: SessionState::Valid,
+        credential_store,
+        email: None,
+        expires_at: session.expires_at,
+        refreshed: false,
+        error: None,
+    };
+
+    let addresses = match auth.get_addresses(&session).await {
+        Err(Error::Auth(e)) => match daemon_refresh.await {
+            Ok(Some(refreshed)) => {
+                status.refreshed = true;
+                status.expires_at = refreshed.expires_at;
+                auth.get_addresses(&refreshed).await
+            }
+            Ok(None) => Err(Error::Auth(format!(
+                "{}; `proton-drive-sync start` refreshes the session if it can still be refreshed",
+                e
+            ))),
+            Err(e @ Error::Auth(_)) => Err(e),
+            Err(e) => {
+                status.state = SessionState::Unverified;
+                status.error = Some(e.to_string());
+                return status;
+            }
+        },
+        result => result,
+    };
+
+    match addresses {
+        Ok(addresses) => status.email = addresses.into_iter().next().map(|a| a.email),
+        Err(Error::Auth(e)) => {
+            status.state = SessionState::Expired;
+            status.error = Some(e);
+        }
+        Err(e) => {
+            status.state = SessionState::Unverified;
+            status.error = Some(e.to_string());
+        }
+    }
+    status
+}
+
+/// Have the running daemon refresh the session, and read the new one back
+///
+/// Returns `None` if no daemon is running.
+async fn refresh_through_daemon() -> Result<Option<Session>> {
+    match send_request(&IpcRequest::RefreshSession).await? {
+        None => Ok(None),
+        Some(response) if response.ok => Ok(Some(load_session()?.get())),
+        Some(response) => {
+            let message = response
+                .message
+                .unwrap_or_else(|| "Session refresh failed".to_string());
+            Err(if response.session_expired {
+                Error::Auth(message)
+            } else {
+                Error::Sync(message)
+            })
+        }
+    }
+}
+
+/// Print `auth status` for humans
+fn print_status(status: &AuthStatus) {
+    let store = match status.credential_store {
+        CredentialStore::Keyring => "keyring",
+        CredentialStore::File => "encrypted file",
+    };
+
+    match status.state {
+        SessionState::NotLoggedIn => {
+            println!("Not logged in (no credentials in the {})", store);
+            return;
+        }
+        SessionState::Unreadable => println!("✗ Credentials in the {} can't be read", store),
+        SessionState::Valid => println!("✓ Logged in"),
+        SessionState::Expired => println!("✗ Session expired; log in again"),
+        SessionState::Unverified => println!("? Logged in, but the session could not be checked"),
+    }
+
+    if let Some(email) = &status.email {
+        println!("  Account: {}", email);
+    }
+    println!("  Credentials: {}", store);
+    if let Some(expires_at) = status.expires_at {
+        let local = expires_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S");
+        let left = expires_at - Utc::now();
+        if left > chrono::Duration::zero() {
+            println!(
+                "  Access token expires: {} (in {})",
+                local,
+                format_age(left)
+            );
+        } else {
+            println!(
+                "  Access token expired: {} ({} ago)",
+                local,
+                format_age(-left)
+            );
+        }
+    }
+    if status.refreshed {
+        println!("  The session was refreshed");
+    }
+    if let Some(error) = &status.error {
+        println!("  Error: {}", error);
+    }
 }
 
-/// Load session from keyring
-pub fn load_session() -> Result<Session> {
-    let entry = Entry::new("proton-drive-sync", "credentials")?;
-    let credential_json = entry.get_password()?;
-    let session: Session = serde_json::from_str(&credential_json)?;
-    Ok(session)
+/// Read a password: the first line of `reader`, without its line ending
+fn read_password(mut reader: impl Read) -> Result<String> {
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+
+    let password = content.lines().next().unwrap_or_default().to_string();
+    if password.is_empty() {
+        return Err(Error::Auth("The password is empty".to_string()));
+    }
+    Ok(password)
+}
+
+/// Load the session from the keyring or credential file
+///
+/// Refreshed sessions are saved back to where it was loaded from.
+pub fn load_session() -> Result<SessionStore> {
+    crate::credentials::load_session()
 }
 
 /// Check if user is authenticated
 pub fn is_authenticated() -> bool {
-    let entry = Entry::new("proton-drive-sync", "credentials");
-    entry.ok().and_then(|e| e.get_password().ok()).is_some()
+    crate::credentials::has_session()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn session(access_token: &str) -> Session {
+        Session {
+            uid: "test_uid".to_string(),
+            access_token: access_token.to_string(),
+            refresh_token: "test_refresh".to_string(),
+            key_password: None,
+            primary_key: None,
+            expires_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_session() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/core/v4/addresses"))
+            .and(header("Authorization", "Bearer good_token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "Addresses": [{"ID": "addr-1", "Email": "user@proton.me", "ReceiveKey": null}]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/core/v4/addresses"))
+            .and(header("Authorization", "Bearer expired_token"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+        // The CLI never refreshes the session itself
+        Mock::given(method("POST"))
+            .and(path("/core/v4/auth/refresh"))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let auth = AuthManager::with_api_base(server.uri());
+        let check = |token: &str, refresh: Result<Option<Session>>| {
+            check_session(
+                &auth,
+                session(token),
+                CredentialStore::Keyring,
+                async move { refresh },
+            )
+        };
+
+        let status = check("good_token", Ok(None)).await;
+        assert_eq!(status.state, SessionState::Valid);
+        assert_eq!(status.email.as_deref(), Some("user@proton.me"));
+
+        // No daemon to refresh it
+        let status = check("expired_token", Ok(None)).await;
+        assert_eq!(status.state, SessionState::Expired);
+        assert!(status.email.is_none());
+
+        // Refreshed by the daemon
+        let status = check("expired_token", Ok(Some(session("good_token")))).await;
+        assert_eq!(status.state, SessionState::Valid);
+        assert!(status.refreshed);
+
+        let rejected = Err(Error::Auth("Session refresh failed: 400".to_string()));
+        assert_eq!(
+            check("expired_token", rejected).await.state,
+            SessionState::Expired
+        );
+        let failed = Err(Error::Sync("Failed to save".to_string()));
+        assert_eq!(
+            check("expired_token", failed).await.state,
+            SessionState::Unverified
+        );
+
+        let unreachable = AuthManager::with_api_base("http://127.0.0.1:1".to_string());
+        let status = check_session(
+            &unreachable,
+            session("good_token"),
+            CredentialStore::Keyring,
+            async { Ok(None) },
+        )
+        .await;
+        assert_eq!(status.state, SessionState::Unverified);
+    }
+
+    #[test]
+    fn test_read_password() {
+        assert_eq!(read_password(&b"hunter2\n"[..]).unwrap(), "hunter2");
+        assert_eq!(
+            read_password(&b"pass word\r\nextra"[..]).unwrap(),
+            "pass word"
+        );
+        assert!(read_password(&b"\n"[..]).is_err());
+    }
 }
@@ -1,19 +1,62 @@
 //! Config CLI command
 
-use crate::config::ConfigManager;
+use crate::cli::auth::load_session;
+use crate::cli::output::print_json;
+use crate::config::{nested_sync_dirs, ConfigFormat, ConfigManager};
+use crate::db::Db;
 use crate::error::Result;
+use crate::ipc::{send_request, IpcRequest};
+use crate::paths::get_data_dir;
+use crate::preset::PRESETS;
+use crate::proton::ProtonClient;
+use crate::remote_index::RemoteIndex;
+use crate::remote_root::{check_remote_root, RootStatus};
+use crate::timing::ConfigDuration;
+use crate::trash;
+use crate::types::{CredentialStore, RetryLimit, SyncDir, TrashLocation};
 use clap::Subcommand;
-use inquire::Text;
+use inquire::{Confirm, Text};
+use std::path::{Path, PathBuf};
 
 /// Config command
 #[derive(Subcommand, Debug)]
 pub enum ConfigCommand {
     /// Show current configuration
-    Show,
+    Show {
+        /// Print configuration as JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Add a sync directory
-    AddDir,
+    AddDir {
+        /// Local path to sync (prompted for if omitted)
+        #[arg(long)]
+        source: Option<String>,
+        /// Remote Proton Drive path (prompted for if omitted)
+        #[arg(long)]
+        remote: Option<String>,
+        /// Allow the directory to be inside another sync directory, or contain one
+        #[arg(long)]
+        allow_nested: bool,
+    },
     /// Remove a sync directory
-    RemoveDir,
+    RemoveDir {
+        /// Sync directory number, as listed by `config show`
+        #[arg(long, conflicts_with = "source")]
+        index: Option<usize>,
+        /// Local path of the sync directory
+        #[arg(long)]
+        source: Option<String>,
+    },
+    /// Add exclude globs
+    AddExclude {
+        /// Glob to exclude; may be repeated
+        #[arg(long = "glob", required = true)]
+        globs: Vec<String>,
+        /// Only exclude matches under this path
+        #[arg(long, default_value = "/")]
+        path: String,
+    },
     /// Set sync concurrency
     SetConcurrency {
         /// Number of concurrent uploads
@@ -24,6 +67,95 @@ pub enum ConfigCommand {
         /// Behavior: trash or permanent
         behavior: String,
     },
+    /// Turn anonymized usage metrics on or off
+    SetTelemetry {
+        /// on or off
+        state: String,
+        /// Endpoint that receives the reports
+        #[arg(long)]
+        endpoint: Option<String>,
+    },
+    /// Print the usage report that would be sent
+    TelemetryPreview,
+    /// Turn low-power mode on or off
+    SetLowPower {
+        /// on or off
+        state: String,
+    },
+    /// Import exclude patterns from a repository's .gitignore files
+    ImportGitignore {
+        /// Path to the repository root
+        repo_path: PathBuf,
+    },
+    /// Apply or compare settings presets
+    Preset {
+        #[command(subcommand)]
+        command: PresetCommand,
+    },
+    /// Re-link a sync directory whose remote folder was renamed or moved
+    RelinkDir {
+        /// Sync directory number, as listed by `config show`
+        index: usize,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Adopt a sync directory that was moved to a new local path
+    MoveDir {
+        /// Sync directory number, as listed by `config show`
+        index: usize,
+        /// Where the directory lives now
+        new_path: PathBuf,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Write the config file's settings to stdout or a file
+    Export {
+        /// Output format (defaults to the file extension, or JSON)
+        #[arg(long, value_enum)]
+        format: Option<ConfigFormat>,
+        /// File to write instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Rewrite the config file as JSON or TOML
+    Convert {
+        /// Format to convert to
+        #[arg(value_enum)]
+        format: ConfigFormat,
+    },
+    /// Replace all settings with those of an exported config
+    Import {
+        /// Exported config file
+        path: PathBuf,
+        /// Input format (defaults to the file extension, or JSON)
+        #[arg(long, value_enum)]
+        format: Option<ConfigFormat>,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+/// Preset subcommands
+#[derive(Subcommand, Debug)]
+pub enum PresetCommand {
+    /// List available presets
+    List,
+    /// Apply a preset's settings
+    Apply {
+        /// Preset name: paranoid, fast or minimal
+        name: String,
+    },
+    /// Show settings that deviate from a preset
+    Diff {
+        /// Preset to compare against (defaults to the last applied one)
+        name: Option<String>,
+        /// Print deviations as JSON
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 impl ConfigCommand {
@@ -32,22 +164,63 @@ impl ConfigCommand {
         let mut config = ConfigManager::new().await?;
 
         match &self {
-            Self::Show => self.show(&config).await,
-            Self::AddDir => self.add_dir(&mut config).await,
-            Self::RemoveDir => self.remove_dir(&mut config).await,
+            Self::Show { json } => self.show(&config, *json).await,
+            Self::AddDir {
+                source,
+                remote,
+                allow_nested,
+            } => {
+                self.add_dir(&mut config, source.clone(), remote.clone(), *allow_nested)
+                    .await
+            }
+            Self::RemoveDir { index, source } => {
+                self.remove_dir(&mut config, *index, source.as_deref())
+                    .await
+            }
+            Self::AddExclude { globs, path } => {
+                self.add_exclude(&mut config, path, globs.clone()).await
+            }
             Self::SetConcurrency { value } => self.set_concurrency(&mut config, *value).await,
             Self::SetDeleteBehavior { behavior } => {
                 self.set_delete_behavior(&mut config, behavior).await
             }
+            Self::SetTelemetry { state, endpoint } => {
+                self.set_telemetry(&mut config, state, endpoint.clone())
+                    .await
+            }
+            Self::TelemetryPreview => self.telemetry_preview(&config).await,
+            Self::SetLowPower { state } => self.set_low_power(&mut config, state).await,
+            Self::ImportGitignore { repo_path } => {
+                self.import_gitignore(&mut config, repo_path).await
+            }
+            Self::Preset { command } => self.preset(&mut config, command).await,
+            Self::RelinkDir { index, yes } => self.relink_dir(&mut config, *index, *yes).await,
+            Self::MoveDir {
+                index,
+                new_path,
+                yes,
+            } => self.move_dir(&mut config, *index, new_path, *yes).await,
+            Self::Export { format, output } => {
+                self.export(&config, *format, output.as_deref()).await
+            }
+            Self::Convert { format } => self.convert(&mut config, *format).await,
+            Self::Import { path, format, yes } => {
+                self.import(&mut config, path, *format, *yes).await
+            }
         }
     }
 
     /// Show configuration
-    async fn show(&self, config: &ConfigManager) -> Result<()> {
+    async fn show(&self, config: &ConfigManager, json: bool) -> Result<()> {
         let cfg = config.get();
 
+        if json {
+            return print_json(cfg);
+        }
+
         println!("Proton Drive Sync Configuration");
         println!("================================");
+        println!("File: {}", config.path().display());
         println!();
 
         println!("Sync Directories:");
@@ -60,11 +233,139 @@ impl ConfigCommand {
         }
 
         println!();
+        if let Some(preset) = &cfg.preset {
+            println!("Preset: {}", preset);
+        }
         println!("Concurrency: {}", cfg.sync_concurrency);
         println!("Remote Delete Behavior: {:?}", cfg.remote_delete_behavior);
+        if cfg.low_power {
+            println!(
+                "Change Detection: {:?} (low power; configured: {:?})",
+                cfg.effective_change_detection(),
+                cfg.change_detection
+            );
+        } else {
+            println!("Change Detection: {:?}", cfg.change_detection);
+        }
+        println!(
+            "Verify Uploads: {}",
+            if cfg.verify_uploads { "on" } else { "off" }
+        );
+        match cfg.max_upload_memory {
+            Some(mib) => println!("Max Upload Memory: {} MiB", mib),
+            None => println!("Max Upload Memory: unlimited"),
+        }
+        println!("Small File Threshold: {} MiB", cfg.small_file_threshold);
+        println!("Event Settle Window: {} ms", cfg.debounce_ms);
+        println!("Local Trash Retention: {} days", cfg.trash_retention_days);
+        match cfg.trash_location {
+            TrashLocation::SyncDir => println!("Local Trash: in each sync directory"),
+            TrashLocation::DataDir => {
+                println!("Local Trash: {}", trash::local_trash_dir()?.display())
+            }
+        }
+        println!("History Retention: {} days", cfg.history_retention_days);
+        println!(
+            "Prune Remote: {}",
+            if cfg.prune_remote { "on" } else { "off" }
+        );
+        println!("Remote Naming: {:?}", cfg.remote_naming);
+        println!(
+            "Inhibit Sleep During Upload: {}",
+            if cfg.inhibit_sleep_during_upload {
+                "on"
+            } else {
+                "off"
+            }
+        );
+        println!("Storage Warning: {}%", cfg.storage_warning_percent);
+        println!("Conflict Policy: {:?}", cfg.conflict_policy);
+        println!(
+            "Temporary File Patterns: {}",
+            cfg.temp_file_patterns.patterns().join(", ")
+        );
+        if !cfg.mime_overrides.is_empty() {
+            println!("Content Type Overrides:");
+            for (extension, mime) in &cfg.mime_overrides {
+                println!("  .{} -> {}", extension.trim_start_matches('.'), mime);
+            }
+        }
+
+        println!("Low Power: {}", if cfg.low_power { "on" } else { "off" });
+
+        let schedule = crate::sync::Schedule::for_config(cfg);
+        let duration = |d| ConfigDuration(d).to_string();
+        println!(
+            "Timing: poll {}, reconcile {}, config reload {}, keep synced jobs {}",
+            duration(schedule.job_poll),
+            duration(schedule.reconcile),
+            duration(schedule.config_reload),
+            duration(cfg.timing.completed_job_retention())
+        );
+        println!(
+            "Reconcile: skipped above {} pending jobs, scheduled {}",
+            cfg.timing.reconcile_busy_threshold(),
+            cfg.timing
+                .reconcile_cron
+                .as_ref()
+                .map_or("never".to_string(), |cron| format!("at \"{}\"", cron))
+        );
+
+        println!(
+            "Logs: {} MiB per file, {} MiB in total, kept {} days",
+            cfg.logs.max_file_size, cfg.logs.max_total_size, cfg.logs.retention_days
+        );
+
+        let retry = &cfg.retry;
+        let limit = |limit: &RetryLimit| match limit {
+            RetryLimit::Count(n) => n.to_string(),
+            RetryLimit::Unlimited(_) => "unlimited".to_string(),
+        };
+        println!(
+            "Retries: {} (backoff {}s to {}s, jitter {}%)",
+            limit(&retry.max_retries),
+            retry.base_delay_secs,
+            retry.max_delay_secs,
+            retry.jitter_percent
+        );
+        for (class, rule) in &retry.overrides {
+            println!(
+                "  {}: {} (backoff {}s to {}s, jitter {}%)",
+                class,
+                limit(rule.max_retries.as_ref().unwrap_or(&retry.max_retries)),
+                rule.base_delay_secs.unwrap_or(retry.base_delay_secs),
+                rule.max_delay_secs.unwrap_or(retry.max_delay_secs),
+                rule.jitter_percent.unwrap_or(retry.jitter_percent)
+            );
+        }
 
         println!();
         println!("Dashboard: {}:{}", cfg.dashboard_host, cfg.dashboard_port);
+        match (&cfg.credential_store, &cfg.credential_key_file) {
+            (CredentialStore::Keyring, _) => println!("Credentials: keyring"),
+            (CredentialStore::File, Some(key_file)) => {
+                println!("Credentials: encrypted file (key file {})", key_file)
+            }
+            (CredentialStore::File, None) => println!("Credentials: encrypted file (passphrase)"),
+        }
+
+        let overrides: Vec<&str> = config.overrides().names().collect();
+        if !overrides.is_empty() {
+            println!("Environment Overrides: {}", overrides.join(", "));
+        }
+        let unknown = config.overrides().unknown();
+        if !unknown.is_empty() {
+            println!(
+                "Ignored Environment Variables: {} (not config settings)",
+                unknown.join(", ")
+            );
+        }
+
+        match (&cfg.telemetry, &cfg.telemetry_endpoint) {
+            (true, Some(endpoint)) => println!("Telemetry: on ({})", endpoint),
+            (true, None) => println!("Telemetry: on (no endpoint configured)"),
+            (false, _) => println!("Telemetry: off"),
+        }
 
         if !cfg.exclude_patterns.is_empty() {
             println!();
@@ -81,14 +382,56 @@ impl ConfigCommand {
     }
 
     /// Add a sync directory
-    async fn add_dir(&self, config: &mut ConfigManager) -> Result<()> {
-        let source = Text::new("Local path to sync:")
-            .prompt()
-            .map_err(|e| crate::error::Error::Config(format!("Prompt error: {}", e)))?;
+    async fn add_dir(
+        &self,
+        config: &mut ConfigManager,
+        source: Option<String>,
+        remote: Option<String>,
+        allow_nested: bool,
+    ) -> Result<()> {
+        let source = match source {
+            Some(source) => source,
+            None => Text::new("Local path to sync:")
+                .prompt()
+                .map_err(|e| crate::error::Error::Config(format!("Prompt error: {}", e)))?,
+        };
+
+        let remote = match remote {
+            Some(remote) => remote,
+            None => Text::new("Remote Proton Drive path:")
+                .prompt()
+                .map_err(|e| crate::error::Error::Config(format!("Prompt error: {}", e)))?,
+        };
 
-        let remote = Text::new("Remote Proton Drive path:")
-            .prompt()
-            .map_err(|e| crate::error::Error::Config(format!("Prompt error: {}", e)))?;
+        let mut sync_dirs = config.file_config().sync_dirs.clone();
+        if sync_dirs
+            .iter()
+            .any(|d| same_path(&d.source_path, &source) && d.remote_root == remote)
+        {
+            println!("✓ {} -> {} is already a sync directory", source, remote);
+            return Ok(());
+        }
+
+        sync_dirs.push(SyncDir {
+            source_path: source.clone(),
+            remote_root: remote.clone(),
+            case_insensitive: None,
+        });
+
+        let nested = nested_sync_dirs(&sync_dirs);
+        if !nested.is_empty() {
+            if !allow_nested {
+                println!("Sync directories would overlap:");
+                for line in &nested {
+                    println!("  {}", line);
+                }
+                return Err(crate::error::Error::Config(
+                    "Files there would be uploaded twice. Re-run with --allow-nested to sync them only to the innermost directory's remote path.".to_string(),
+                ));
+            }
+
+            config.set_allow_nested(true).await?;
+        }
 
         config.add_sync_dir(source, remote).await?;
 
@@ -98,34 +441,82 @@ impl ConfigCommand {
     }
 
     /// Remove a sync directory
-    async fn remove_dir(&self, config: &mut ConfigManager) -> Result<()> {
-        let cfg = config.get();
+    ///
+    /// Without `index` or `source`, asks which one to remove.
+    async fn remove_dir(
+        &self,
+        config: &mut ConfigManager,
+        index: Option<usize>,
+        source: Option<&str>,
+    ) -> Result<()> {
+        let cfg = config.file_config();
 
-        if cfg.sync_dirs.is_empty() {
-            println!("No sync directories configured.");
-            return Ok(());
-        }
+        let index = match (index, source) {
+            (Some(index), _) => index
+                .checked_sub(1)
+                .filter(|i| *i < cfg.sync_dirs.len())
+                .ok_or_else(|| {
+                    crate::error::Error::Config(format!("Invalid sync directory index: {}", index))
+                })?,
+            (None, Some(source)) => cfg
+                .sync_dirs
+                .iter()
+                .position(|d| same_path(&d.source_path, source))
+                .ok_or_else(|| {
+                    crate::error::Error::Config(format!("No sync directory at {}", source))
+                })?,
+            (None, None) => {
+                if cfg.sync_dirs.is_empty() {
+                    println!("No sync directories configured.");
+                    return Ok(());
+                }
 
-        let options: Vec<String> = cfg
-            .sync_dirs
-            .iter()
-            .map(|d| format!("{} -> {}", d.source_path, d.remote_root))
-            .collect();
+                let options: Vec<String> = cfg
+                    .sync_dirs
+                    .iter()
+                    .map(|d| format!("{} -> {}", d.source_path, d.remote_root))
+                    .collect();
 
-        let selected = inquire::Select::new("Select sync directory to remove:", options)
-            .prompt()
-            .map_err(|e| crate::error::Error::Config(format!("Prompt error: {}", e)))?;
+                let selected = inquire::Select::new("Select sync directory to remove:", options)
+                    .prompt()
+                    .map_err(|e| crate::error::Error::Config(format!("Prompt error: {}", e)))?;
 
-        let index = cfg
-            .sync_dirs
-            .iter()
-            .position(|d| format!("{} -> {}", d.source_path, d.remote_root) == selected)
-            .unwrap();
+                cfg.sync_dirs
+                    .iter()
+                    .position(|d| format!("{} -> {}", d.source_path, d.remote_root) == selected)
+                    .unwrap()
+            }
+        };
 
+        let removed = cfg.sync_dirs[index].clone();
         config.remove_sync_dir(index).await?;
 
-        println!("✓ Removed sync directory");
+        println!(
+            "✓ Removed sync directory {} -> {}",
+            removed.source_path, removed.remote_root
+        );
+
+        Ok(())
+    }
+
+    /// Add exclude globs scoped to a path
+    async fn add_exclude(
+        &self,
+        config: &mut ConfigManager,
+        path: &str,
+        globs: Vec<String>,
+    ) -> Result<()> {
+        if let Some(glob) = globs.iter().find(|g| glob::Pattern::new(g).is_err()) {
+            return Err(crate::error::Error::Config(format!(
+                "Invalid glob pattern: {}",
+                glob
+            )));
+        }
 
+        config
+            .extend_exclude_pattern(path.to_string(), globs.clone())
+            .await?;
+        println!("✓ Excluding {} under {}", globs.join(", "), path);
         Ok(())
     }
 
@@ -151,4 +542,640 @@ impl ConfigCommand {
         println!("✓ Set remote delete behavior to {:?}", behavior_value);
         Ok(())
     }
+
+    /// Run a preset subcommand
+    async fn preset(&self, config: &mut ConfigManager, command: &PresetCommand) -> Result<()> {
+        let name = match command {
+            PresetCommand::List => {
+                for preset in PRESETS {
+                    println!("  {:<10} {}", preset.name, preset.description);
+                }
+                return Ok(());
+            }
+            PresetCommand::Apply { name } => Some(name),
+            PresetCommand::Diff { name, .. } => name.as_ref().or(config.get().preset.as_ref()),
+        };
+
+        let Some(name) = name else {
+            println!("No preset applied yet. Pass a preset name or run `proton-drive-sync config preset apply <name>`.");
+            return Ok(());
+        };
+
+        let Some(preset) = crate::preset::find(name) else {
+            let names: Vec<&str> = PRESETS.iter().map(|p| p.name).collect();
+            println!(
+                "Unknown preset '{}'. Available presets: {}",
+                name,
+                names.join(", ")
+            );
+            return Ok(());
+        };
+
+        if let PresetCommand::Diff { json, .. } = command {
+            let diff = preset.diff(config.get());
+
+            if *json {
+                return print_json(&diff);
+            }
+
+            if diff.is_empty() {
+                println!("✓ Configuration matches preset '{}'", preset.name);
+            } else {
+                println!("Deviations from preset '{}':", preset.name);
+                for setting in &diff {
+                    println!(
+                        "  {}: {} (preset: {})",
+                        setting.setting, setting.current, setting.preset
+                    );
+                }
+            }
+            return Ok(());
+        }
+
+        config.apply_preset(preset).await?;
+        println!("✓ Applied preset '{}'", preset.name);
+        println!("  {}", preset.description);
+        Ok(())
+    }
+
+    /// Set telemetry
+    async fn set_low_power(&self, config: &mut ConfigManager, state: &str) -> Result<()> {
+        let enabled = match state.to_lowercase().as_str() {
+            "on" => true,
+            "off" => false,
+            _ => {
+                println!("Invalid state. Use 'on' or 'off'.");
+                return Ok(());
+            }
+        };
+
+        config.set_low_power(enabled).await?;
+
+        if enabled {
+            println!("✓ Low-power mode enabled");
+        } else {
+            println!("✓ Low-power mode disabled");
+        }
+        println!("Restart the daemon for the new poll intervals to take effect");
+
+        Ok(())
+    }
+
+    async fn set_telemetry(
+        &self,
+        config: &mut ConfigManager,
+        state: &str,
+        endpoint: Option<String>,
+    ) -> Result<()> {
+        let enabled = match state.to_lowercase().as_str() {
+            "on" => true,
+            "off" => false,
+            _ => {
+                println!("Invalid state. Use 'on' or 'off'.");
+                return Ok(());
+            }
+        };
+
+        config.set_telemetry(enabled, endpoint).await?;
+
+        if !enabled {
+            println!("✓ Telemetry disabled");
+            return Ok(());
+        }
+
+        println!("✓ Telemetry enabled");
+        match &config.get().telemetry_endpoint {
+            Some(endpoint) => println!("Reports are sent daily to {}", endpoint),
+            None => println!("No endpoint configured yet; set one with --endpoint <url>"),
+        }
+        println!("Preview the report with: proton-drive-sync config telemetry-preview");
+
+        Ok(())
+    }
+
+    /// Print the telemetry report
+    async fn telemetry_preview(&self, config: &ConfigManager) -> Result<()> {
+        // Initialize database
+        let data_dir = get_data_dir()?;
+        let db_path = data_dir.join("proton-drive-sync.db");
+        let Some(db) = Db::open_existing_read_only(db_path).await? else {
+            println!("No database yet: the daemon has not run, so there is nothing to report");
+            return Ok(());
+        };
+
+        let report = crate::telemetry::collect(&db, config.get()).await?;
+        print_json(&report)
+    }
+
+    /// Import .gitignore rules as exclude patterns
+    async fn import_gitignore(&self, config: &mut ConfigManager, repo_path: &Path) -> Result<()> {
+        let repo = crate::paths::normalize_path(repo_path)?;
+        let import = crate::protonignore::import_gitignore(&repo)?;
+
+        if import.globs.is_empty() {
+            println!("No .gitignore rules found in {}", repo.display());
+            return Ok(());
+        }
+
+        let globs = import.globs.len();
+        config
+            .set_exclude_pattern(repo.to_string_lossy().to_string(), import.globs)
+            .await?;
+
+        println!(
+            "✓ Imported {} glob(s) from {} .gitignore file(s) for {}",
+            globs,
+            import.files,
+            repo.display()
+        );
+
+        if !import.skipped.is_empty() {
+            println!();
+            println!("Skipped negated rules (not supported by exclude patterns):");
+            for rule in &import.skipped {
+                println!("  {}", rule);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Re-link a sync directory to its moved remote root
+    async fn relink_dir(&self, config: &mut ConfigManager, index: usize, yes: bool) -> Result<()> {
+        let sync_dir = index
+            .checked_sub(1)
+            .and_then(|i| config.file_config().sync_dirs.get(i))
+            .cloned()
+            .ok_or_else(|| {
+                crate::error::Error::Config(format!("Invalid sync directory index: {}", index))
+            })?;
+
+        // Initialize database
+        let data_dir = get_data_dir()?;
+        let db_path = data_dir.join("proton-drive-sync.db");
+        let db = Db::new(db_path).await?;
+
+        let client = ProtonClient::with_sessions(load_session()?);
+        let remote_index = RemoteIndex::new(&client, &db);
+        remote_index.refresh().await?;
+
+        let (node_uid, current_path) = match check_remote_root(&remote_index, &db, &sync_dir)
+            .await?
+        {
+            RootStatus::Moved {
+                node_uid,
+                current_path,
+            } => (node_uid, current_path),
+            RootStatus::Linked => {
+                println!(
+                    "✓ {} is already linked to {}",
+                    sync_dir.source_path, sync_dir.remote_root
+                );
+                return Ok(());
+            }
+            RootStatus::Unbound => {
+                println!(
+                    "{} has not been uploaded to {} yet; nothing to re-link.",
+                    sync_dir.source_path, sync_dir.remote_root
+                );
+                return Ok(());
+            }
+            RootStatus::Missing => {
+                println!(
+                    "The remote folder for {} no longer exists; it will be recreated at {} on the next sync.",
+                    sync_dir.source_path, sync_dir.remote_root
+                );
+                return Ok(());
+            }
+        };
+
+        println!("Remote folder for {} was moved:", sync_dir.source_path);
+        println!("  {} -> {}", sync_dir.remote_root, current_path);
+
+        if !yes {
+            let confirmed = Confirm::new("Re-link to the new location?")
+                .with_default(true)
+                .prompt()
+                .map_err(|e| crate::error::Error::Config(format!("Prompt error: {}", e)))?;
+
+            if !confirmed {
+                return Ok(());
+            }
+        }
+
+        let moved = db
+            .relink_remote_paths(&sync_dir.remote_root, &current_path)
+            .await?;
+        db.set_remote_root(&sync_dir.source_path, &current_path, &node_uid)
+            .await?;
+        config
+            .set_remote_root(index - 1, current_path.clone())
+            .await?;
+
+        println!(
+            "✓ Re-linked {} to {} ({} node mapping(s) updated)",
+            sync_dir.source_path, current_path, moved
+        );
+        println!("Jobs blocked while the folder was moved can be retried from the dashboard.");
+
+        Ok(())
+    }
+
+    /// Carry a sync directory's state over to its new local location
+    async fn move_dir(
+        &self,
+        config: &mut ConfigManager,
+        index: usize,
+        new_path: &Path,
+        yes: bool,
+    ) -> Result<()> {
+        let sync_dir = index
+            .checked_sub(1)
+            .and_then(|i| config.file_config().sync_dirs.get(i))
+            .cloned()
+            .ok_or_else(|| {
+                crate::error::Error::Config(format!("Invalid sync directory index: {}", index))
+            })?;
+
+        // The watcher would see the move as deletes and queue them meanwhile
+        if send_request(&IpcRequest::Status).await?.is_some() {
+            return Err(crate::error::Error::Config(
+                "Stop the sync daemon before moving a sync directory".to_string(),
+            ));
+        }
+
+        if !new_path.is_dir() {
+            return Err(crate::error::Error::Config(format!(
+                "{} is not a directory",
+                new_path.display()
+            )));
+        }
+        let new_source = crate::paths::canonical(new_path)?
+            .to_string_lossy()
+            .into_owned();
+
+        if new_source == sync_dir.source_path {
+            println!("✓ {} is already the sync directory's path", new_source);
+            return Ok(());
+        }
+
+        let mut sync_dirs = config.file_config().sync_dirs.clone();
+        sync_dirs[index - 1].source_path = new_source.clone();
+        let nested = nested_sync_dirs(&sync_dirs);
+        if !nested.is_empty() && !config.file_config().allow_nested_sync_dirs {
+            println!("Sync directories would overlap:");
+            for line in &nested {
+                println!("  {}", line);
+            }
+            return Err(crate::error::Error::Config(
+                "Files there would be uploaded twice. Pick a path outside the other sync directories.".to_string(),
+            ));
+        }
+
+        println!(
+            "Sync directory {} -> {}",
+            sync_dir.source_path, sync_dir.remote_root
+        );
+        println!("  will be synced from {}", new_source);
+
+        if !yes {
+            let confirmed = Confirm::new("Move it without re-uploading?")
+                .with_default(true)
+                .prompt()
+                .map_err(|e| crate::error::Error::Config(format!("Prompt error: {}", e)))?;
+
+            if !confirmed {
+                return Ok(());
+            }
+        }
+
+        // Initialize database
+        let data_dir = get_data_dir()?;
+        let db_path = data_dir.join("proton-drive-sync.db");
+        let db = Db::new(db_path).await?;
+
+        let moved = db
+            .move_local_root(&sync_dir.source_path, &new_source)
+            .await?;
+        config
+            .set_source_path(index - 1, new_source.clone())
+            .await?;
+
+        println!(
+            "✓ Moved {} to {} ({} file state(s), {} node mapping(s) updated)",
+            sync_dir.source_path, new_source, moved.file_states, moved.node_mappings
+        );
+        if moved.jobs_dropped > 0 {
+            println!(
+                "  Dropped {} queued job(s) for the old location",
+                moved.jobs_dropped
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Export the config file's settings
+    async fn export(
+        &self,
+        config: &ConfigManager,
+        format: Option<ConfigFormat>,
+        output: Option<&Path>,
+    ) -> Result<()> {
+        let format = format
+            .or_else(|| output.and_then(ConfigFormat::from_path))
+            .unwrap_or(ConfigFormat::Json);
+        // Overrides belong to this machine's environment, not the export
+        let content = format.to_string(config.file_config())?;
+
+        match output {
+            Some(path) => {
+                tokio::fs::write(path, content).await?;
+                eprintln!("✓ Exported configuration to {}", path.display());
+            }
+            None => println!("{}", content.trim_end()),
+        }
+
+        Ok(())
+    }
+
+    /// Convert the config file to another format
+    async fn convert(&self, config: &mut ConfigManager, format: ConfigFormat) -> Result<()> {
+        if config.format() == format && config.path().exists() {
+            println!("✓ {} is already in that format", config.path().display());
+            return Ok(());
+        }
+
+        let backup = config.convert(format).await?;
+        println!("✓ Converted configuration to {}", config.path().display());
+        if let Some(backup) = backup {
+            println!("  The old file was kept as {}", backup.display());
+        }
+        Ok(())
+    }
+
+    /// Import settings from an exported config
+    async fn import(
+        &self,
+        config: &mut ConfigManager,
+        path: &Path,
+        format: Option<ConfigFormat>,
+        yes: bool,
+    ) -> Result<()> {
+        let format = format
+            .or_else(|| ConfigFormat::from_path(path))
+            .unwrap_or(ConfigFormat::Json);
+        let content = tokio::fs::read_to_string(path).await?;
+        let imported = format
+            .parse(&content)
+            .map_err(|e| crate::error::Error::Config(format!("{}: {}", path.display(), e)))?;
+
+        println!("Importing configuration from {}", path.display());
+        println!("Sync Directories:");
+        let mut missing = 0;
+        for dir in &imported.sync_dirs {
+            if Path::new(&dir.source_path).is_dir() {
+                println!("  {} -> {}", dir.source_path, dir.remote_root);
+            } else {
+                missing += 1;
+                println!(
+                    "  {} -> {} (missing on this machine)",
+                    dir.source_path, dir.remote_root
+                );
+            }
+        }
+
+        if !yes {
+            let confirmed = Confirm::new("Replace the current configuration?")
+                .with_default(false)
+                .prompt()
+                .map_err(|e| crate::error::Error::Config(format!("Prompt error: {}", e)))?;
+
+            if !confirmed {
+                return Ok(());
+            }
+        }
+
+        config.replace(imported).await?;
+
+        println!("✓ Imported configuration");
+        if missing > 0 {
+            println!("Point missing sync directories at their new location with `proton-drive-sync config move-dir` before starting the daemon.");
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether two local paths name the same directory
+///
+/// Compares components, so trailing slashes and `.` segments don't matter and
+/// relative paths are taken from the current directory.
+fn same_path(a: &str, b: &str) -> bool {
+    match (std::path::absolute(a), std::path::absolute(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => Path::new(a) == Path::new(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EnvOverrides;
+    use crate::types::Config;
+    use tempfile::TempDir;
+
+    fn sync_dir(source: &str, remote: &str) -> SyncDir {
+        SyncDir {
+            source_path: source.to_string(),
+            remote_root: remote.to_string(),
+            case_insensitive: None,
+        }
+    }
+
+    fn manager(temp_dir: &TempDir, sync_dirs: Vec<SyncDir>) -> ConfigManager {
+        let config = Config {
+            sync_dirs,
+            ..Config::default()
+        };
+        ConfigManager::with_config(temp_dir.path().join("config.json"), config)
+    }
+
+    fn sources(config: &ConfigManager) -> Vec<&str> {
+        config
+            .get()
+            .sync_dirs
+            .iter()
+            .map(|d| d.source_path.as_str())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_add_dir_flags() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = manager(&temp_dir, vec![sync_dir("/home/me/Docs", "/Docs")]);
+        let command = ConfigCommand::AddDir {
+            source: None,
+            remote: None,
+            allow_nested: false,
+        };
+        let add = |source: &str, remote: &str| (Some(source.to_string()), Some(remote.to_string()));
+
+        // Adding the same directory again is a no-op
+        let (source, remote) = add("/home/me/Docs/", "/Docs");
+        command
+            .add_dir(&mut config, source, remote, false)
+            .await
+            .unwrap();
+        assert_eq!(sources(&config), vec!["/home/me/Docs"]);
+
+        // Nested only with --allow-nested
+        let (source, remote) = add("/home/me/Docs/Work", "/Work");
+        assert!(command
+            .add_dir(&mut config, source.clone(), remote.clone(), false)
+            .await
+            .is_err());
+        assert_eq!(sources(&config), vec!["/home/me/Docs"]);
+        command
+            .add_dir(&mut config, source, remote, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            sources(&config),
+            vec!["/home/me/Docs", "/home/me/Docs/Work"]
+        );
+        assert!(config.get().allow_nested_sync_dirs);
+    }
+
+    #[tokio::test]
+    async fn test_remove_dir_by_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let dirs = vec![sync_dir("/home/me/A", "/A"), sync_dir("/home/me/B", "/B")];
+        let mut config = manager(&temp_dir, dirs);
+        let command = ConfigCommand::RemoveDir {
+            index: None,
+            source: None,
+        };
+
+        // Numbers start at 1, as listed by `config show`
+        for index in [0, 3] {
+            assert!(command
+                .remove_dir(&mut config, Some(index), None)
+                .await
+                .is_err());
+        }
+        assert_eq!(sources(&config), vec!["/home/me/A", "/home/me/B"]);
+
+        command
+            .remove_dir(&mut config, Some(2), None)
+            .await
+            .unwrap();
+        assert_eq!(sources(&config), vec!["/home/me/A"]);
+    }
+
+    #[tokio::test]
+    async fn test_remove_dir_by_source() {
+        let temp_dir = TempDir::new().unwrap();
+        let dirs = vec![
+            sync_dir("/", "/Root"),
+            sync_dir("/home/me/A/", "/A"),
+            sync_dir("/home/me/B", "/B"),
+        ];
+        let mut config = manager(&temp_dir, dirs);
+        let command = ConfigCommand::RemoveDir {
+            index: None,
+            source: None,
+        };
+
+        assert!(command
+            .remove_dir(&mut config, None, Some("/home/me/C"))
+            .await
+            .is_err());
+
+        // Trailing slashes on either side don't matter
+        command
+            .remove_dir(&mut config, None, Some("/home/me/B/"))
+            .await
+            .unwrap();
+        command
+            .remove_dir(&mut config, None, Some("/home/me/A"))
+            .await
+            .unwrap();
+        assert_eq!(sources(&config), vec!["/"]);
+
+        command
+            .remove_dir(&mut config, None, Some("/"))
+            .await
+            .unwrap();
+        assert!(sources(&config).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dir_commands_edit_the_config_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let dirs = vec![sync_dir("/home/me/A", "/A"), sync_dir("/home/me/B", "/B")];
+        let overrides = EnvOverrides::from_vars([(
+            "PDS_SYNC_DIRS".to_string(),
+            r#"[{"source_path": "/srv/C", "remote_root": "/C"}]"#.to_string(),
+        )]);
+        let mut config = manager(&temp_dir, dirs).with_overrides(overrides).unwrap();
+        assert_eq!(sources(&config), vec!["/srv/C"]);
+        let command = ConfigCommand::RemoveDir {
+            index: None,
+            source: None,
+        };
+
+        // Indexes and sources refer to the file's sync directories
+        command
+            .remove_dir(&mut config, Some(2), None)
+            .await
+            .unwrap();
+        command
+            .add_dir(
+                &mut config,
+                Some("/srv/C".to_string()),
+                Some("/C".to_string()),
+                false,
+            )
+            .await
+            .unwrap();
+        let file_sources: Vec<(&str, &str)> = config
+            .file_config()
+            .sync_dirs
+            .iter()
+            .map(|d| (d.source_path.as_str(), d.remote_root.as_str()))
+            .collect();
+        assert_eq!(file_sources, vec![("/home/me/A", "/A"), ("/srv/C", "/C")]);
+    }
+
+    #[tokio::test]
+    async fn test_add_exclude_rejects_invalid_globs() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut config = manager(&temp_dir, Vec::new());
+        let command = ConfigCommand::AddExclude {
+            globs: Vec::new(),
+            path: "/".to_string(),
+        };
+
+        let globs = vec!["*.tmp".to_string(), "[unclosed".to_string()];
+        assert!(command.add_exclude(&mut config, "/", globs).await.is_err());
+        assert!(config.get().exclude_patterns.is_empty());
+
+        command
+            .add_exclude(&mut config, "/build", vec!["*.o".to_string()])
+            .await
+            .unwrap();
+        let pattern = &config.get().exclude_patterns[0];
+        assert_eq!(pattern.path, "/build");
+        assert_eq!(pattern.globs, vec!["*.o"]);
+    }
+
+    #[test]
+    fn test_same_path() {
+        assert!(same_path("/home/me/Docs/", "/home/me/Docs"));
+        assert!(same_path("/home/me/./Docs", "/home/me/Docs"));
+        assert!(same_path("/", "/"));
+        assert!(!same_path("/", ""));
+        assert!(!same_path("/home/me/Docs", "/home/me/Docs2"));
+    }
 }
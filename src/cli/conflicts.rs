@@ -0,0 +1,221 @@
+//! Conflicts CLI command
+
+use crate::cli::auth::load_session;
+use crate::cli::output::print_json;
+use crate::config::ConfigManager;
+use crate::db::Db;
+use crate::error::{Error, Result};
+use crate::paths::get_data_dir;
+use crate::processor::JobProcessor;
+use crate::progress::format_bytes;
+use crate::proton::ProtonClient;
+use crate::types::{Conflict, ConflictResolution};
+use crate::watcher::{change_token, FileWatcher};
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+use inquire::{InquireError, Select};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Choices offered for each conflict
+const KEEP_LOCAL: &str = "Keep local   - upload it as a new revision of the remote file";
+const KEEP_REMOTE: &str = "Keep remote  - download it; the local file goes to the trash";
+const KEEP_BOTH: &str = "Keep both    - rename the remote file to a conflicted copy";
+const SKIP: &str = "Skip         - decide later";
+
+/// Conflicts command
+#[derive(Subcommand, Debug)]
+pub enum ConflictsCommand {
+    /// List uploads held back by different remote content, oldest first
+    List {
+        /// Print conflicts as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Go through the conflicts and decide which side to keep
+    Resolve,
+}
+
+/// One side of a conflict, for comparison
+struct Side {
+    size: u64,
+    modified: Option<DateTime<Utc>>,
+    sha256: String,
+}
+
+impl ConflictsCommand {
+    /// Run the conflicts command
+    pub async fn run(self) -> Result<()> {
+        let data_dir = get_data_dir()?;
+
+        match self {
+            Self::List { json } => {
+                let conflicts =
+                    match Db::open_existing_read_only(data_dir.join("proton-drive-sync.db")).await?
+                    {
+                        Some(db) => db.get_conflicts().await?,
+                        None => Vec::new(),
+                    };
+
+                if json {
+                    return print_json(&conflicts);
+                }
+
+                if conflicts.is_empty() {
+                    println!("No conflicts");
+                    return Ok(());
+                }
+
+                for conflict in &conflicts {
+                    println!(
+                        "{}  {}",
+                        conflict.detected_at.format("%Y-%m-%d %H:%M"),
+                        conflict.local_path
+                    );
+                }
+                println!();
+                println!("Resolve them with: proton-drive-sync conflicts resolve");
+                Ok(())
+            }
+            Self::Resolve => resolve(&data_dir).await,
+        }
+    }
+}
+
+/// Ask for a decision on each conflict and apply it
+async fn resolve(data_dir: &Path) -> Result<()> {
+    let config = ConfigManager::new().await?;
+    let cfg = config.get();
+    let db = Db::new(data_dir.join("proton-drive-sync.db")).await?;
+
+    let conflicts = db.get_conflicts().await?;
+    if conflicts.is_empty() {
+        println!("No conflicts");
+        return Ok(());
+    }
+
+    let client = ProtonClient::with_sessions(load_session()?);
+    let processor = JobProcessor::new(
+        db.clone(),
+        ProtonClient::with_sessions(client.sessions().clone()),
+        1,
+        cfg.remote_delete_behavior,
+        cfg.verify_uploads,
+        None,
+        cfg.retry.clone(),
+    )
+    .with_mime_overrides(&cfg.mime_overrides);
+
+    let total = conflicts.len();
+    let mut resolved = 0;
+
+    for (i, conflict) in conflicts.iter().enumerate() {
+        println!();
+        println!("Conflict {} of {}: {}", i + 1, total, conflict.local_path);
+        println!();
+        print_sides(
+            local_side(Path::new(&conflict.local_path)).await,
+            remote_side(&client, conflict).await.unwrap_or_default(),
+        );
+        println!();
+
+        let choice = match Select::new(
+            "Which version do you want to keep?",
+            vec![KEEP_LOCAL, KEEP_REMOTE, KEEP_BOTH, SKIP],
+        )
+        .prompt()
+        {
+            Ok(choice) => choice,
+            Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => break,
+            Err(e) => return Err(Error::Config(format!("Prompt error: {}", e))),
+        };
+
+        let resolution = match choice {
+            KEEP_LOCAL => ConflictResolution::KeepLocal,
+            KEEP_REMOTE => ConflictResolution::KeepRemote,
+            KEEP_BOTH => ConflictResolution::KeepBoth,
+            _ => continue,
+        };
+
+        let local_path = Path::new(&conflict.local_path);
+        let sync_dir = FileWatcher::find_sync_dir(local_path, cfg)?.ok_or_else(|| {
+            Error::InvalidPath(format!(
+                "{} is no longer inside a sync directory",
+                conflict.local_path
+            ))
+        })?;
+
+        let trash =
+            crate::trash::trash_dir_for(cfg.trash_location, Path::new(&sync_dir.source_path))?;
+        if let Err(e) = processor
+            .resolve_conflict(conflict, resolution, &trash)
+            .await
+        {
+            println!("✗ {}", e);
+            continue;
+        }
+
+        // Record the kept content as synced so it is not uploaded again
+        let token = change_token(local_path, cfg.effective_change_detection()).await?;
+        db.update_file_state(&conflict.local_path, &token).await?;
+
+        resolved += 1;
+        println!("✓ Resolved {}", conflict.local_path);
+    }
+
+    println!();
+    println!("Resolved {} of {} conflict(s)", resolved, conflicts.len());
+    Ok(())
+}
+
+/// Size, modification time and hash of the local file, if it still exists
+async fn local_side(path: &Path) -> Option<Side> {
+    let metadata = tokio::fs::metadata(path).await.ok()?;
+    let content = tokio::fs::read(path).await.ok()?;
+
+    Some(Side {
+        size: metadata.len(),
+        modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+        sha256: hex::encode(Sha256::digest(&content)),
+    })
+}
+
+/// Size, upload time and hash of the remote file's current revision
+///
+/// Errors if the remote file can no longer be read; the caller shows it as
+/// missing then.
+async fn remote_side(client: &ProtonClient, conflict: &Conflict) -> Result<Option<Side>> {
+    let revisions = client.list_revisions(&conflict.node_uid).await?;
+    let Some(active) = revisions.into_iter().find(|r| r.active) else {
+        return Ok(None);
+    };
+
+    let content = client
+        .download_revision(&conflict.node_uid, &active.uid)
+        .await?;
+
+    Ok(Some(Side {
+        size: content.len() as u64,
+        modified: active.created_at,
+        sha256: hex::encode(Sha256::digest(&content)),
+    }))
+}
+
+/// Print both sides of a conflict next to each other
+fn print_sides(local: Option<Side>, remote: Option<Side>) {
+    println!("          {:<12} {:<18} SHA-256", "Size", "Modified");
+    for (label, side) in [("Local", local), ("Remote", remote)] {
+        match side {
+            Some(side) => println!(
+                "  {:<7} {:<12} {:<18} {}",
+                label,
+                format_bytes(side.size),
+                side.modified
+                    .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                &side.sha256[..16]
+            ),
+            None => println!("  {:<7} missing", label),
+        }
+    }
+}
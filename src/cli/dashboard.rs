@@ -1,7 +1,11 @@
 //! Dashboard CLI command
 
+use crate::cli::auth::load_session;
 use crate::config::ConfigManager;
+use crate::db::Db;
 use crate::error::Result;
+use crate::paths::get_data_dir;
+use crate::proton::ProtonClient;
 use clap::Parser;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -28,8 +32,17 @@ impl DashboardCommand {
         // Load config
         let config = Arc::new(Mutex::new(ConfigManager::new().await?));
 
+        // Initialize database
+        let data_dir = get_data_dir()?;
+        let db_path = data_dir.join("proton-drive-sync.db");
+        let db = Db::new(db_path.clone()).await?;
+        let reader = Db::open_read_only(db_path).await?;
+
+        // Account info is shown only when logged in
+        let client = load_session().ok().map(ProtonClient::with_sessions);
+
         // Start dashboard server
-        crate::dashboard::start_dashboard(config, self.host, self.port).await?;
+        crate::dashboard::start_dashboard(config, db, reader, client, self.host, self.port).await?;
 
         Ok(())
     }
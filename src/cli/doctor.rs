@@ -0,0 +1,130 @@
+//! Doctor CLI command
+
+use crate::cli::output::print_json;
+use crate::config::ConfigManager;
+use crate::error::Result;
+use crate::limits::{self, FdLimits};
+use clap::Parser;
+use serde::Serialize;
+use std::path::Path;
+
+/// Doctor command options
+#[derive(Parser, Debug)]
+pub struct DoctorCommand {
+    /// Print the report as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Open file limits as inherited and as the daemon raises them
+#[derive(Debug, Clone, Serialize)]
+pub struct FdReport {
+    pub inherited: FdLimits,
+    pub effective: FdLimits,
+    pub max_concurrent_uploads: usize,
+}
+
+/// A sync directory whose source is missing
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingSyncDir {
+    pub source_path: String,
+}
+
+/// Full doctor report
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    /// `None` on platforms without open file limits
+    pub open_files: Option<FdReport>,
+    pub sync_concurrency: usize,
+    pub missing_sync_dirs: Vec<MissingSyncDir>,
+}
+
+impl DoctorCommand {
+    /// Run the doctor command
+    pub async fn run(self) -> Result<()> {
+        let config = ConfigManager::new().await?;
+        let report = Self::collect(&config);
+
+        if self.json {
+            return print_json(&report);
+        }
+
+        Self::print(&report);
+        Ok(())
+    }
+
+    /// Gather the checks
+    fn collect(config: &ConfigManager) -> DoctorReport {
+        let config = config.get();
+
+        // Raising the limit here shows what the daemon will end up with
+        let open_files = limits::fd_limits().and_then(|inherited| {
+            limits::raise_fd_limit().map(|effective| FdReport {
+                inherited,
+                effective,
+                max_concurrent_uploads: effective.max_concurrent_uploads(),
+            })
+        });
+
+        let missing_sync_dirs = config
+            .sync_dirs
+            .iter()
+            .filter(|d| !Path::new(&d.source_path).is_dir())
+            .map(|d| MissingSyncDir {
+                source_path: d.source_path.clone(),
+            })
+            .collect();
+
+        DoctorReport {
+            open_files,
+            sync_concurrency: config.sync_concurrency,
+            missing_sync_dirs,
+        }
+    }
+
+    /// Print the report for humans
+    fn print(report: &DoctorReport) {
+        println!("Open files:");
+        match &report.open_files {
+            Some(fd) => {
+                println!(
+                    "  Inherited limit: {} (hard: {})",
+                    fd.inherited.soft,
+                    format_hard(&fd.inherited)
+                );
+                println!(
+                    "  Effective limit: {} (hard: {})",
+                    fd.effective.soft,
+                    format_hard(&fd.effective)
+                );
+                println!("  Max concurrent uploads: {}", fd.max_concurrent_uploads);
+
+                if fd.max_concurrent_uploads < report.sync_concurrency {
+                    println!(
+                        "  ! sync_concurrency is {}, the daemon will lower it to {}",
+                        report.sync_concurrency, fd.max_concurrent_uploads
+                    );
+                } else {
+                    println!("  ✓ sync_concurrency of {} fits", report.sync_concurrency);
+                }
+            }
+            None => println!("  Not limited on this platform"),
+        }
+
+        println!();
+        println!("Sync directories:");
+        if report.missing_sync_dirs.is_empty() {
+            println!("  ✓ All source directories exist");
+        }
+        for dir in &report.missing_sync_dirs {
+            println!("  ! Missing: {}", dir.source_path);
+        }
+    }
+}
+
+/// Render a hard limit
+fn format_hard(limits: &FdLimits) -> String {
+    limits
+        .hard
+        .map_or_else(|| "unlimited".to_string(), |h| h.to_string())
+}
@@ -0,0 +1,176 @@
+//! History CLI command
+
+use crate::cli::output::print_json;
+use crate::db::Db;
+use crate::error::Result;
+use crate::paths::get_data_dir;
+use crate::progress::format_bytes;
+use crate::types::{DailyDigest, HistoryEntry, HistoryResult, SyncDirection};
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+/// History command options
+#[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct HistoryCommand {
+    #[command(subcommand)]
+    pub command: Option<HistorySubcommand>,
+
+    /// Show operations from the last this many days
+    #[arg(long, default_value_t = 7, value_parser = clap::value_parser!(u32).range(1..))]
+    pub days: u32,
+
+    /// Only show failed operations
+    #[arg(long)]
+    pub failed: bool,
+
+    /// Only show operations on this file or inside this folder
+    #[arg(long)]
+    pub path: Option<PathBuf>,
+
+    /// Maximum number of operations to show
+    #[arg(short = 'n', long, default_value_t = 100)]
+    pub limit: i64,
+
+    /// Print operations as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// History subcommands
+#[derive(Subcommand, Debug)]
+pub enum HistorySubcommand {
+    /// Show the daily digest: files synced, bytes and errors
+    Stats {
+        /// Summarize today so far instead of yesterday
+        #[arg(long)]
+        today: bool,
+
+        /// Print the digest as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+impl HistoryCommand {
+    /// Run the history command
+    pub async fn run(self) -> Result<()> {
+        let data_dir = get_data_dir()?;
+
+        if let Some(HistorySubcommand::Stats { today, json }) = self.command {
+            return print_digest(today, json).await;
+        }
+
+        let entries =
+            match Db::open_existing_read_only(data_dir.join("proton-drive-sync.db")).await? {
+                Some(db) => {
+                    let path = self.path.as_deref().map(std::path::absolute).transpose()?;
+                    let since = chrono::Utc::now() - chrono::Duration::days(self.days.into());
+                    db.get_history(
+                        since,
+                        self.failed,
+                        path.as_deref().map(|p| p.to_string_lossy()).as_deref(),
+                        self.limit,
+                    )
+                    .await?
+                }
+                None => Vec::new(),
+            };
+
+        if self.json {
+            return print_json(&entries);
+        }
+
+        if entries.is_empty() {
+            println!("No sync operations in the last {} days", self.days);
+            return Ok(());
+        }
+
+        for entry in entries.iter().rev() {
+            print_entry(entry);
+        }
+
+        Ok(())
+    }
+}
+
+/// Print the digest of today or yesterday, as logged at midnight
+async fn print_digest(today: bool, json: bool) -> Result<()> {
+    let mut day = chrono::Local::now().date_naive();
+    if !today {
+        day = day.pred_opt().unwrap_or(day);
+    }
+
+    let db_path = get_data_dir()?.join("proton-drive-sync.db");
+    let digest = match Db::open_existing_read_only(db_path).await? {
+        Some(db) => crate::digest::daily_digest(&db, day).await?,
+        None => DailyDigest {
+            day,
+            files: 0,
+            bytes: 0,
+            errors: 0,
+        },
+    };
+
+    if json {
+        return print_json(&digest);
+    }
+
+    println!("{}{}", digest.day, if today { " (so far)" } else { "" });
+    println!("  Files synced: {}", digest.files);
+    println!(
+        "  Transferred:  {}",
+        format_bytes(digest.bytes.max(0) as u64)
+    );
+    println!("  Errors:       {}", digest.errors);
+
+    Ok(())
+}
+
+/// Print one operation for humans
+pub fn print_entry(entry: &HistoryEntry) {
+    let mark = match entry.result {
+        HistoryResult::Synced => "✓",
+        HistoryResult::Retrying => "↻",
+        HistoryResult::Blocked => "✗",
+        HistoryResult::Skipped => "-",
+    };
+    let direction = match entry.direction {
+        SyncDirection::Upload => "↑",
+        SyncDirection::Download => "↓",
+    };
+    let operation = match &entry.old_local_path {
+        Some(_) => "MOVE".to_string(),
+        None => entry.event_type.to_string(),
+    };
+    let size = entry
+        .bytes
+        .map(|b| format!("  {}", format_bytes(b.max(0) as u64)))
+        .unwrap_or_default();
+
+    println!(
+        "{}  {} {} {:<11} {}{}  {}",
+        entry.completed_at.format("%Y-%m-%d %H:%M:%S"),
+        mark,
+        direction,
+        operation,
+        entry.local_path,
+        size,
+        format_duration_ms(entry.duration_ms)
+    );
+    if let Some(old) = &entry.old_local_path {
+        println!("      from {}", old);
+    }
+    if let Some(error) = &entry.error {
+        println!("      {}", error);
+    }
+}
+
+/// Human-readable duration of a single operation
+pub fn format_duration_ms(ms: i64) -> String {
+    if ms < 1000 {
+        format!("{} ms", ms)
+    } else {
+        format!("{:.1} s", ms as f64 / 1000.0)
+    }
+}
@@ -0,0 +1,762 @@
+//! Jobs CLI command
+
+use crate::cli::logs::{latest_log_file, LogEntry};
+use crate::cli::output::print_json;
+use crate::config::ConfigManager;
+use crate::db::Db;
+use crate::error::Result;
+use crate::paths::{get_data_dir, get_log_dir};
+use crate::protonignore::IgnoreRules;
+use crate::telemetry::error_class;
+use crate::types::{
+    ExcludePattern, FileState, NodeMapping, SkipReason, SyncDir, SyncJob, SyncJobStatus,
+};
+use crate::watcher::FileWatcher;
+use chrono::{DateTime, Utc};
+use clap::{ArgGroup, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Maximum log lines included when explaining a job
+const EXPLAIN_LOG_LINES: usize = 20;
+
+/// Export format for job records
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One JSON object per line
+    Jsonl,
+}
+
+/// Job status filter
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusFilter {
+    Pending,
+    Processing,
+    Synced,
+    Blocked,
+    /// Set aside because the file cannot be read
+    Skipped,
+}
+
+impl From<StatusFilter> for SyncJobStatus {
+    fn from(filter: StatusFilter) -> Self {
+        match filter {
+            StatusFilter::Pending => Self::Pending,
+            StatusFilter::Processing => Self::Processing,
+            StatusFilter::Synced => Self::Synced,
+            StatusFilter::Blocked => Self::Blocked,
+            StatusFilter::Skipped => Self::Skipped(SkipReason::PermissionDenied),
+        }
+    }
+}
+
+/// Jobs command
+#[derive(Subcommand, Debug)]
+pub enum JobsCommand {
+    /// List queued, processing and blocked jobs
+    List {
+        /// Only show jobs with this status (synced jobs are hidden otherwise)
+        #[arg(long, value_enum)]
+        status: Option<StatusFilter>,
+
+        /// Only show jobs for this local folder and everything below it
+        #[arg(long)]
+        path: Option<PathBuf>,
+
+        /// Maximum number of jobs to show
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+
+        /// Print jobs as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Export job records
+    Export {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Jsonl)]
+        format: ExportFormat,
+
+        /// Keep running and stream jobs as they complete
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Requeue blocked jobs
+    #[command(group(ArgGroup::new("target").required(true).args(["all", "id"])))]
+    Retry {
+        /// Requeue every blocked job
+        #[arg(long)]
+        all: bool,
+
+        /// Requeue only the blocked job with this ID
+        #[arg(long)]
+        id: Option<i64>,
+
+        /// Reset the retry count so the jobs get a fresh retry budget
+        #[arg(long)]
+        reset_retries: bool,
+    },
+    /// Drop pending and blocked jobs for a local folder
+    Cancel {
+        /// Local folder whose jobs, including those below it, are dropped
+        #[arg(long)]
+        path: PathBuf,
+    },
+    /// Show everything known about a job, for diagnosing failures
+    Explain {
+        /// Job ID
+        id: i64,
+
+        /// Print the diagnosis as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Everything known about one job
+#[derive(Debug, Clone, Serialize)]
+pub struct JobExplanation {
+    pub job: SyncJob,
+    /// Coarse class of the last error
+    pub error_class: Option<&'static str>,
+    pub local_exists: bool,
+    pub file_state: Option<FileState>,
+    /// Node mappings of the job's path and, for moves, its old path
+    pub node_mappings: Vec<NodeMapping>,
+    /// Sync directory the path belongs to
+    pub sync_dir: Option<SyncDir>,
+    /// Configured exclude patterns matching the path
+    pub exclusions: Vec<ExcludePattern>,
+    pub ignored_by_protonignore: bool,
+    /// Recent log lines mentioning the path
+    pub log_lines: Vec<LogEntry>,
+}
+
+impl JobsCommand {
+    /// Run the jobs command
+    pub async fn run(self) -> Result<()> {
+        let data_dir = get_data_dir()?;
+        let db_path = data_dir.join("proton-drive-sync.db");
+
+        match self {
+            Self::List {
+                status,
+                path,
+                limit,
+                json,
+            } => {
+                let Some(db) = open_reader(db_path).await? else {
+                    return Ok(());
+                };
+                let jobs = match path {
+                    Some(path) => {
+                        let status = status.map(SyncJobStatus::from);
+                        let mut jobs = db.get_jobs_under(&local_dir(&path)?).await?;
+                        jobs.retain(|job| status.is_none_or(|s| job.status == s));
+                        jobs.truncate(limit.max(0) as usize);
+                        jobs
+                    }
+                    None => db.list_jobs(status.map(Into::into), limit).await?,
+                };
+
+                if json {
+                    return print_json(&jobs);
+                }
+
+                print_jobs(&jobs, Utc::now());
+                Ok(())
+            }
+            Self::Export { format, follow } => {
+                let Some(db) = open_reader(db_path).await? else {
+                    return Ok(());
+                };
+                export(&db, format, follow).await
+            }
+            Self::Retry {
+                id, reset_retries, ..
+            } => {
+                let db = Db::new(db_path).await?;
+                retry(&db, id, reset_retries).await
+            }
+            Self::Cancel { path } => {
+                let db = Db::new(db_path).await?;
+                let dir = local_dir(&path)?;
+                let cancelled = db.cancel_jobs_under(&dir).await?;
+
+                println!("✓ Cancelled {} job(s) under {}", cancelled, dir);
+                if cancelled > 0 {
+                    println!("  Exclude the folder to keep the next scan from queuing it again");
+                }
+                Ok(())
+            }
+            Self::Explain { id, json } => {
+                let Some(db) = open_reader(db_path).await? else {
+                    return Ok(());
+                };
+                let config = ConfigManager::new().await?;
+
+                let log_dir = get_log_dir()?;
+
+                let Some(explanation) = explain(&db, config.get(), &log_dir, id).await? else {
+                    println!("No job with ID {}", id);
+                    return Ok(());
+                };
+
+                if json {
+                    return print_json(&explanation);
+                }
+
+                print_explanation(&explanation);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Open the database for reading, saying so on stderr if there is none yet
+///
+/// Never creates it, so inspecting jobs on a fresh machine leaves no trace.
+async fn open_reader(db_path: PathBuf) -> Result<Option<Db>> {
+    let db = Db::open_existing_read_only(db_path).await?;
+    if db.is_none() {
+        eprintln!("No database yet: the daemon has not run, so there are no jobs");
+    }
+    Ok(db)
+}
+
+/// Absolute form of a local folder path as stored in jobs
+fn local_dir(path: &Path) -> Result<String> {
+    let path = match crate::paths::canonical(path) {
+        Ok(canonical) => canonical,
+        // The folder may already be gone locally
+        Err(_) => std::path::absolute(path)?,
+    };
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Gather everything known about a job
+async fn explain(
+    db: &Db,
+    config: &crate::types::Config,
+    log_dir: &Path,
+    id: i64,
+) -> Result<Option<JobExplanation>> {
+    let Some(job) = db.get_job(id).await? else {
+        return Ok(None);
+    };
+
+    let path = Path::new(&job.local_path);
+
+    let mut node_mappings = Vec::new();
+    let mut paths = vec![(job.local_path.as_str(), job.remote_path.as_str())];
+    if let (Some(old_local), Some(old_remote)) = (&job.old_local_path, &job.old_remote_path) {
+        paths.push((old_local, old_remote));
+    }
+    for (local, remote) in paths {
+        if let Some(mapping) = db.get_node_mapping(local, remote).await? {
+            node_mappings.push(mapping);
+        }
+    }
+
+    let sync_dir = FileWatcher::find_sync_dir(path, config)?.cloned();
+    let ignored_by_protonignore = match &sync_dir {
+        Some(dir) => IgnoreRules::load(std::slice::from_ref(dir)).is_ignored(path, path.is_dir()),
+        None => false,
+    };
+
+    Ok(Some(JobExplanation {
+        error_class: job.last_error.as_deref().map(error_class),
+        local_exists: path.exists(),
+        file_state: db.get_file_state(&job.local_path).await?,
+        node_mappings,
+        sync_dir,
+        exclusions: config
+            .exclude_patterns
+            .iter()
+            .filter(|p| p.matches(path))
+            .cloned()
+            .collect(),
+        ignored_by_protonignore,
+        log_lines: log_lines_mentioning(log_dir, &job.local_path).await?,
+        job,
+    }))
+}
+
+/// Recent lines of the latest log file that mention a path
+async fn log_lines_mentioning(log_dir: &Path, local_path: &str) -> Result<Vec<LogEntry>> {
+    if !log_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let Some(log_path) = latest_log_file(log_dir).await? else {
+        return Ok(Vec::new());
+    };
+
+    let content = tokio::fs::read_to_string(log_path).await?;
+    let mut lines: Vec<LogEntry> = content
+        .lines()
+        .filter(|line| line.contains(local_path))
+        .map(LogEntry::parse)
+        .collect();
+
+    let start = lines.len().saturating_sub(EXPLAIN_LOG_LINES);
+    Ok(lines.split_off(start))
+}
+
+/// Print a job explanation for humans
+fn print_explanation(explanation: &JobExplanation) {
+    let job = &explanation.job;
+
+    println!("Job #{}: {} {}", job.id, job.event_type, job.status);
+    println!("  Local:   {}", job.local_path);
+    println!("  Remote:  {}", job.remote_path);
+    if let (Some(old_local), Some(old_remote)) = (&job.old_local_path, &job.old_remote_path) {
+        println!("  Moved from: {} ({})", old_local, old_remote);
+    }
+    println!(
+        "  Queued:  {} ({} retries)",
+        job.created_at.to_rfc3339(),
+        job.n_retries
+    );
+    if let Some(source) = job.source {
+        println!("  Queued by: {:?}", source);
+    }
+    if let Some(retry_at) = job.retry_at {
+        println!("  Next retry: {}", retry_at.to_rfc3339());
+    }
+    if let Some(error) = &job.last_error {
+        println!(
+            "  Error:   {} [{}]",
+            error,
+            explanation.error_class.unwrap_or("other")
+        );
+    }
+
+    println!();
+    println!(
+        "Local file: {}",
+        if explanation.local_exists {
+            "exists"
+        } else {
+            "missing"
+        }
+    );
+    match &explanation.file_state {
+        Some(state) => println!(
+            "File state: {} (recorded {})",
+            state.change_token,
+            state.updated_at.to_rfc3339()
+        ),
+        None => println!("File state: none recorded"),
+    }
+
+    println!();
+    if explanation.node_mappings.is_empty() {
+        println!("Node mappings: none");
+    } else {
+        println!("Node mappings:");
+        for mapping in &explanation.node_mappings {
+            println!(
+                "  {} -> node {} (parent {})",
+                mapping.remote_path, mapping.node_uid, mapping.parent_node_uid
+            );
+        }
+    }
+
+    println!();
+    match &explanation.sync_dir {
+        Some(dir) => println!("Sync directory: {} -> {}", dir.source_path, dir.remote_root),
+        None => println!("Sync directory: none (path is outside every sync directory)"),
+    }
+    for pattern in &explanation.exclusions {
+        println!(
+            "  Excluded by pattern under {}: {}",
+            pattern.path,
+            pattern.globs.join(", ")
+        );
+    }
+    if explanation.ignored_by_protonignore {
+        println!("  Excluded by .protonignore");
+    }
+
+    println!();
+    if explanation.log_lines.is_empty() {
+        println!("Log lines: none mention this path");
+    } else {
+        println!("Recent log lines:");
+        for entry in &explanation.log_lines {
+            match (&entry.timestamp, &entry.level) {
+                (Some(timestamp), Some(level)) => {
+                    println!("  {} {} {}", timestamp, level, entry.message)
+                }
+                _ => println!("  {}", entry.message),
+            }
+        }
+    }
+}
+
+/// Print jobs for humans
+fn print_jobs(jobs: &[SyncJob], now: DateTime<Utc>) {
+    if jobs.is_empty() {
+        println!("No jobs found");
+        return;
+    }
+
+    for job in jobs {
+        println!(
+            "#{} {} {} ({} retries, {} old)",
+            job.id,
+            job.status,
+            job.event_type,
+            job.n_retries,
+            format_age(now - job.created_at)
+        );
+        println!("  {}", job.local_path);
+        println!("  -> {}", job.remote_path);
+
+        if let Some(error) = &job.last_error {
+            println!("  Error: {}", error);
+        }
+    }
+}
+
+/// Render a job age compactly, e.g. `3d`, `5h`, `12m`
+pub fn format_age(age: chrono::Duration) -> String {
+    let secs = age.num_seconds().max(0);
+
+    match secs {
+        s if s >= 86_400 => format!("{}d", s / 86_400),
+        s if s >= 3_600 => format!("{}h", s / 3_600),
+        s if s >= 60 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// Requeue blocked jobs
+async fn retry(db: &Db, id: Option<i64>, reset_retries: bool) -> Result<()> {
+    let requeued = db.requeue_blocked_jobs(id, reset_retries).await?;
+
+    match id {
+        Some(id) if requeued == 0 => println!("No blocked job with ID {}", id),
+        Some(id) => println!("✓ Requeued job {}", id),
+        None => println!("✓ Requeued {} blocked job(s)", requeued),
+    }
+
+    Ok(())
+}
+
+/// Position of `--follow` in the completed jobs: the last completion time and job ID written
+type ExportCursor = (DateTime<Utc>, i64);
+
+/// Export jobs to stdout
+async fn export(db: &Db, format: ExportFormat, follow: bool) -> Result<()> {
+    let mut out = std::io::stdout();
+
+    let Some(mut cursor) = export_all(db, &mut out, format).await? else {
+        return Ok(());
+    };
+    if !follow {
+        return Ok(());
+    }
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        ticker.tick().await;
+
+        match export_completed(db, &mut out, format, cursor).await? {
+            Some(next) => cursor = next,
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Write every job, returning the cursor to follow from, or `None` once the reader has gone away
+async fn export_all(
+    db: &Db,
+    out: &mut impl Write,
+    format: ExportFormat,
+) -> Result<Option<ExportCursor>> {
+    let jobs = db.get_all_jobs().await?;
+    let cursor = latest_completion(&jobs).unwrap_or_else(|| (Utc::now(), 0));
+
+    Ok(write_jobs(out, &jobs, format)?.then_some(cursor))
+}
+
+/// Write jobs completed after `cursor`, returning the advanced cursor, or `None` once the reader has gone away
+async fn export_completed(
+    db: &Db,
+    out: &mut impl Write,
+    format: ExportFormat,
+    cursor: ExportCursor,
+) -> Result<Option<ExportCursor>> {
+    let completed = db.get_jobs_completed_after(cursor.0, cursor.1).await?;
+    let next = latest_completion(&completed).map_or(cursor, |last| cursor.max(last));
+
+    Ok(write_jobs(out, &completed, format)?.then_some(next))
+}
+
+/// Write jobs in the requested format, returning false once the reader has gone away
+fn write_jobs(out: &mut impl Write, jobs: &[SyncJob], format: ExportFormat) -> Result<bool> {
+    for job in jobs {
+        let line = match format {
+            ExportFormat::Jsonl => serde_json::to_string(job)?,
+        };
+
+        if let Err(e) = writeln!(out, "{}", line).and_then(|_| out.flush()) {
+            if e.kind() == std::io::ErrorKind::BrokenPipe {
+                return Ok(false);
+            }
+            return Err(e.into());
+        }
+    }
+
+    Ok(true)
+}
+
+/// Latest completion time, with its job's ID, among a set of jobs
+fn latest_completion(jobs: &[SyncJob]) -> Option<ExportCursor> {
+    jobs.iter()
+        .filter_map(|j| j.completed_at.map(|at| (at, j.id)))
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{JobPriority, SyncEvent, SyncEventType};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(chrono::Duration::seconds(-5)), "0s");
+        assert_eq!(format_age(chrono::Duration::seconds(59)), "59s");
+        assert_eq!(format_age(chrono::Duration::minutes(12)), "12m");
+        assert_eq!(format_age(chrono::Duration::hours(5)), "5h");
+        assert_eq!(format_age(chrono::Duration::days(3)), "3d");
+    }
+
+    #[tokio::test]
+    async fn test_export_follows_completed_jobs() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+
+        let mut ids = Vec::new();
+        for path in ["/home/a/one.txt", "/home/a/two.txt"] {
+            let event = SyncEvent {
+                event_type: SyncEventType::CreateFile,
+                local_path: path.to_string(),
+                remote_path: path.replace("/home", "/Remote"),
+                change_token: None,
+                old_local_path: None,
+                old_remote_path: None,
+            };
+            ids.push(
+                db.enqueue_job(&event, JobPriority::Small)
+                    .await
+                    .unwrap()
+                    .unwrap(),
+            );
+        }
+        db.update_job_status(ids[0], SyncJobStatus::Synced, None)
+            .await
+            .unwrap();
+
+        let exported_ids = |out: &[u8]| -> Vec<i64> {
+            String::from_utf8_lossy(out)
+                .lines()
+                .map(|line| serde_json::from_str::<SyncJob>(line).unwrap().id)
+                .collect()
+        };
+
+        let mut out = Vec::new();
+        let cursor = export_all(&db, &mut out, ExportFormat::Jsonl)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(exported_ids(&out), ids);
+        assert_eq!(cursor.1, ids[0]);
+
+        // Nothing new has completed
+        let mut out = Vec::new();
+        let cursor = export_completed(&db, &mut out, ExportFormat::Jsonl, cursor)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(out.is_empty());
+
+        db.update_job_status(ids[1], SyncJobStatus::Synced, None)
+            .await
+            .unwrap();
+        let mut out = Vec::new();
+        let cursor = export_completed(&db, &mut out, ExportFormat::Jsonl, cursor)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(exported_ids(&out), vec![ids[1]]);
+        assert_eq!(cursor.1, ids[1]);
+
+        // Each completion is written once
+        let mut out = Vec::new();
+        export_completed(&db, &mut out, ExportFormat::Jsonl, cursor)
+            .await
+            .unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_jobs_filters() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+
+        let mut ids = Vec::new();
+        for path in ["/home/a/one.txt", "/home/a/two.txt", "/home/b/three.txt"] {
+            let event = SyncEvent {
+                event_type: SyncEventType::CreateFile,
+                local_path: path.to_string(),
+                remote_path: path.replace("/home", "/Remote"),
+                change_token: None,
+                old_local_path: None,
+                old_remote_path: None,
+            };
+            ids.push(
+                db.enqueue_job(&event, JobPriority::Small)
+                    .await
+                    .unwrap()
+                    .unwrap(),
+            );
+        }
+
+        db.update_job_status(ids[0], SyncJobStatus::Synced, None)
+            .await
+            .unwrap();
+        db.update_job_status(ids[1], SyncJobStatus::Blocked, Some("boom"))
+            .await
+            .unwrap();
+
+        let paths = |jobs: Vec<SyncJob>| -> Vec<String> {
+            jobs.into_iter().map(|j| j.local_path).collect()
+        };
+
+        assert_eq!(
+            paths(db.list_jobs(None, 10).await.unwrap()),
+            vec!["/home/a/two.txt", "/home/b/three.txt"]
+        );
+        assert_eq!(
+            paths(db.list_jobs(Some(SyncJobStatus::Synced), 10).await.unwrap()),
+            vec!["/home/a/one.txt"]
+        );
+        assert_eq!(db.list_jobs(None, 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_jobs_under_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+
+        let mut ids = Vec::new();
+        for path in [
+            "/home/a",
+            "/home/a/one.txt",
+            "/home/a/b/two.txt",
+            "/home/ab.txt",
+        ] {
+            let event = SyncEvent {
+                event_type: SyncEventType::CreateFile,
+                local_path: path.to_string(),
+                remote_path: path.replace("/home", "/Remote"),
+                change_token: None,
+                old_local_path: None,
+                old_remote_path: None,
+            };
+            ids.push(
+                db.enqueue_job(&event, JobPriority::Small)
+                    .await
+                    .unwrap()
+                    .unwrap(),
+            );
+        }
+        db.claim_job(ids[2]).await.unwrap().unwrap();
+
+        let paths = |jobs: Vec<SyncJob>| -> Vec<String> {
+            jobs.into_iter().map(|j| j.local_path).collect()
+        };
+
+        assert_eq!(
+            paths(db.get_jobs_under("/home/a/").await.unwrap()),
+            vec!["/home/a", "/home/a/one.txt", "/home/a/b/two.txt"]
+        );
+
+        // Jobs in flight are left alone
+        assert_eq!(db.cancel_jobs_under("/home/a").await.unwrap(), 2);
+        assert_eq!(
+            paths(db.list_jobs(None, 10).await.unwrap()),
+            vec!["/home/a/b/two.txt", "/home/ab.txt"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_explain_job() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let source = temp_dir.path().join("src");
+        let local_path = source.join("notes.tmp").to_string_lossy().into_owned();
+
+        let event = SyncEvent {
+            event_type: SyncEventType::CreateFile,
+            local_path: local_path.clone(),
+            remote_path: "/Remote/notes.tmp".to_string(),
+            change_token: Some("1:2".to_string()),
+            old_local_path: None,
+            old_remote_path: None,
+        };
+        let id = db
+            .enqueue_job(&event, JobPriority::Small)
+            .await
+            .unwrap()
+            .unwrap();
+        db.update_job_status(id, SyncJobStatus::Blocked, Some("Timeout"))
+            .await
+            .unwrap();
+        db.update_file_state(&local_path, "1:2").await.unwrap();
+
+        let log_dir = temp_dir.path().join("logs");
+        std::fs::create_dir(&log_dir).unwrap();
+        std::fs::write(
+            log_dir.join("sync.log"),
+            format!(
+                "2026-01-01T00:00:00Z ERROR Failed to upload {}\n2026-01-01T00:00:01Z INFO unrelated\n",
+                local_path
+            ),
+        )
+        .unwrap();
+
+        let mut config = crate::types::Config::default();
+        config.sync_dirs.push(SyncDir {
+            source_path: source.to_string_lossy().into_owned(),
+            remote_root: "/Remote".to_string(),
+            case_insensitive: None,
+        });
+        config.exclude_patterns.push(ExcludePattern {
+            path: "/".to_string(),
+            globs: vec!["*.tmp".to_string()],
+        });
+
+        let explanation = explain(&db, &config, &log_dir, id).await.unwrap().unwrap();
+        assert_eq!(explanation.job.id, id);
+        assert_eq!(explanation.error_class, Some("timeout"));
+        assert!(!explanation.local_exists);
+        assert_eq!(explanation.file_state.unwrap().change_token, "1:2");
+        assert!(explanation.node_mappings.is_empty());
+        assert_eq!(explanation.sync_dir.unwrap().remote_root, "/Remote");
+        assert_eq!(explanation.exclusions.len(), 1);
+        assert_eq!(explanation.log_lines.len(), 1);
+
+        assert!(explain(&db, &config, &log_dir, id + 1)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
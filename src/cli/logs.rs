@@ -1,9 +1,27 @@
 //! Logs CLI command
 
 use crate::error::Result;
+use crate::logger::{LogFileName, LOG_FILE_NAME};
 use crate::paths::get_log_dir;
+use chrono::{DateTime, FixedOffset, Utc};
 use clap::Parser;
-use std::path::Path;
+use flate2::read::GzDecoder;
+use regex::Regex;
+use serde::Serialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncSeekExt, BufReader};
+use tracing::Level;
+
+/// Log levels written by the tracing formatter
+const LOG_LEVELS: &[&str] = &["TRACE", "DEBUG", "INFO", "WARN", "ERROR"];
+
+/// Crate name that prefixes the targets of the daemon's own log lines
+const CRATE_TARGET: &str = "proton_drive_sync";
+
+/// How often `--follow` checks the log for new lines and a new daily file
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
 
 /// Logs command options
 #[derive(Parser, Debug)]
@@ -15,6 +33,337 @@ pub struct LogsCommand {
     /// Follow log output
     #[arg(short, long)]
     pub follow: bool,
+
+    /// Print one JSON object per log line
+    #[arg(long)]
+    pub json: bool,
+
+    /// Only show lines at this level or more severe (error, warn, info, debug, trace)
+    #[arg(long)]
+    pub level: Option<Level>,
+
+    /// Only show lines from the last period, e.g. `30m`, `2h` or `7d`
+    #[arg(long, value_parser = parse_period)]
+    pub since: Option<chrono::Duration>,
+
+    /// Only show lines matching a regular expression
+    #[arg(long)]
+    pub grep: Option<Regex>,
+
+    /// Only show lines from one part of the daemon, e.g. `processor` or `watcher`
+    #[arg(long)]
+    pub component: Option<String>,
+}
+
+/// Parse a period like `90s`, `30m`, `2h`, `7d` or `1w`
+fn parse_period(s: &str) -> std::result::Result<chrono::Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (amount, unit) = s.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("invalid period '{}', expected e.g. 30m, 2h or 7d", s))?;
+
+    match unit {
+        "s" => Ok(chrono::Duration::seconds(amount)),
+        "m" => Ok(chrono::Duration::minutes(amount)),
+        "h" => Ok(chrono::Duration::hours(amount)),
+        "d" => Ok(chrono::Duration::days(amount)),
+        "w" => Ok(chrono::Duration::weeks(amount)),
+        _ => Err(format!(
+            "invalid period unit in '{}', expected s, m, h, d or w",
+            s
+        )),
+    }
+}
+
+/// A parsed log line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogEntry {
+    pub timestamp: Option<String>,
+    pub level: Option<String>,
+    pub message: String,
+}
+
+impl LogEntry {
+    /// Parse a line written by the file logger
+    ///
+    /// Lines that do not start with a timestamp and level (e.g. wrapped
+    /// output) are kept whole as the message.
+    pub fn parse(line: &str) -> Self {
+        let line = line.trim_end();
+
+        if let Some((timestamp, rest)) = line.split_once(char::is_whitespace) {
+            let rest = rest.trim_start();
+            let (level, message) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+            if LOG_LEVELS.contains(&level)
+                && chrono::DateTime::parse_from_rfc3339(timestamp).is_ok()
+            {
+                return Self {
+                    timestamp: Some(timestamp.to_string()),
+                    level: Some(level.to_string()),
+                    message: message.trim_start().to_string(),
+                };
+            }
+        }
+
+        Self {
+            timestamp: None,
+            level: None,
+            message: line.to_string(),
+        }
+    }
+
+    /// Time the line was logged, if it has a timestamp
+    pub fn time(&self) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_rfc3339(self.timestamp.as_deref()?).ok()
+    }
+
+    /// Module that logged the line, without the crate name, e.g. `processor`
+    pub fn component(&self) -> Option<&str> {
+        let (target, _) = self.message.split_once(": ")?;
+        if target.contains(char::is_whitespace) {
+            return None;
+        }
+        Some(
+            target
+                .strip_prefix(CRATE_TARGET)
+                .and_then(|rest| rest.strip_prefix("::"))
+                .unwrap_or(target),
+        )
+    }
+}
+
+/// Conditions a log line must meet to be shown
+#[derive(Debug, Default)]
+pub struct LogFilter {
+    pub level: Option<Level>,
+    pub since: Option<DateTime<Utc>>,
+    pub grep: Option<Regex>,
+    pub component: Option<String>,
+}
+
+impl LogFilter {
+    /// Whether an entry's level, time and component pass the filter
+    ///
+    /// Lines without a timestamp and level only pass when none of these is
+    /// asked for; callers keep them with the line before.
+    pub fn matches_entry(&self, entry: &LogEntry) -> bool {
+        if let Some(max_level) = self.level {
+            let level = entry.level.as_deref().and_then(|l| l.parse::<Level>().ok());
+            // More verbose levels compare greater
+            if level.is_none_or(|level| level > max_level) {
+                return false;
+            }
+        }
+
+        if let Some(since) = self.since {
+            if entry.time().is_none_or(|time| time < since) {
+                return false;
+            }
+        }
+
+        if let Some(component) = &self.component {
+            let matches = entry.component().is_some_and(|c| {
+                c == component
+                    || c.strip_prefix(component.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            });
+            if !matches {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Whether a raw log line matches the pattern
+    pub fn matches_text(&self, line: &str) -> bool {
+        self.grep.as_ref().is_none_or(|grep| grep.is_match(line))
+    }
+}
+
+/// A log line with the continuation lines that follow it
+#[derive(Debug)]
+struct LogRecord {
+    entry: LogEntry,
+    lines: Vec<String>,
+}
+
+impl LogRecord {
+    /// Whether the record passes the filter
+    ///
+    /// The first line decides on level, time and component, since only it
+    /// carries them; the pattern may match any line.
+    fn matches(&self, filter: &LogFilter) -> bool {
+        filter.matches_entry(&self.entry) && self.lines.iter().any(|line| filter.matches_text(line))
+    }
+}
+
+/// Group a file's lines into records
+fn parse_records(content: &str) -> Vec<LogRecord> {
+    let mut records: Vec<LogRecord> = Vec::new();
+
+    for line in content.lines() {
+        let entry = LogEntry::parse(line);
+        match records.last_mut() {
+            Some(record) if entry.timestamp.is_none() => record.lines.push(line.to_string()),
+            _ => records.push(LogRecord {
+                entry,
+                lines: vec![line.to_string()],
+            }),
+        }
+    }
+
+    records
+}
+
+/// Order of a log file among the others
+///
+/// Files are ordered by the date and part in their names; modification
+/// times are no help, since appending a late line to yesterday's file makes
+/// it look newest. Other `.log` files come first.
+fn log_order(path: &Path) -> (Option<LogFileName>, PathBuf) {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(LogFileName::parse);
+    (name, path.to_path_buf())
+}
+
+/// Find the log files, compressed ones included, oldest first
+pub async fn log_files(log_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = tokio::fs::read_dir(log_dir).await?;
+    let mut log_files = Vec::new();
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let is_log = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(LOG_FILE_NAME))
+            || path.extension().is_some_and(|e| e == "log");
+        if is_log {
+            log_files.push(path);
+        }
+    }
+
+    log_files.sort_by_cached_key(|path| log_order(path));
+    Ok(log_files)
+}
+
+/// Find the most recently written log file
+pub async fn latest_log_file(log_dir: &Path) -> Result<Option<PathBuf>> {
+    Ok(log_files(log_dir).await?.pop())
+}
+
+/// A log file read incrementally as it grows
+struct LogTail {
+    path: PathBuf,
+    reader: BufReader<tokio::fs::File>,
+    /// Bytes read so far, to notice the file being truncated
+    position: u64,
+    /// The start of a line whose newline has not been written yet
+    partial: String,
+}
+
+impl LogTail {
+    /// Open a log file, reading from a byte offset onwards
+    async fn open(path: PathBuf, position: u64) -> Result<Self> {
+        let mut file = tokio::fs::File::open(&path).await?;
+        let position = file.seek(std::io::SeekFrom::Start(position)).await?;
+
+        Ok(Self {
+            path,
+            reader: BufReader::new(file),
+            position,
+            partial: String::new(),
+        })
+    }
+
+    /// Read the complete lines written since the last call
+    async fn read_new_lines(&mut self) -> Result<Vec<String>> {
+        let len = tokio::fs::metadata(&self.path).await?.len();
+        if len < self.position {
+            // Truncated: start over from the beginning
+            *self = Self::open(self.path.clone(), 0).await?;
+        }
+
+        let mut lines = Vec::new();
+        loop {
+            let read = self.reader.read_line(&mut self.partial).await?;
+            if read == 0 {
+                break;
+            }
+            self.position += read as u64;
+
+            if !self.partial.ends_with('\n') {
+                break;
+            }
+            let line = std::mem::take(&mut self.partial);
+            lines.push(line.trim_end_matches(['\r', '\n']).to_string());
+        }
+
+        Ok(lines)
+    }
+}
+
+/// Read a log file, decompressing it if it was gzipped
+async fn read_log_file(path: &Path) -> Result<String> {
+    if path.extension().is_none_or(|e| e != "gz") {
+        return Ok(tokio::fs::read_to_string(path).await?);
+    }
+
+    let compressed = tokio::fs::read(path).await?;
+    let mut content = String::new();
+    GzDecoder::new(compressed.as_slice()).read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// Records read from the log files
+struct Backlog {
+    /// All records, merged in chronological order
+    records: Vec<LogRecord>,
+    /// The newest file and the offset its complete lines were read up to
+    end: Option<(PathBuf, u64)>,
+}
+
+/// Read the records of all log files
+///
+/// A line still being written to the newest file is left out, so that
+/// following the file from the returned offset picks it up whole.
+async fn read_records(log_dir: &Path) -> Result<Backlog> {
+    let mut records = Vec::new();
+    let mut last_time = None;
+    let mut end = None;
+    let files = log_files(log_dir).await?;
+    let newest = files.len().saturating_sub(1);
+    for (index, path) in files.into_iter().enumerate() {
+        let mut content = read_log_file(&path).await?;
+        if index == newest {
+            let offset = if path.extension().is_some_and(|e| e == "gz") {
+                tokio::fs::metadata(&path).await?.len()
+            } else {
+                content.truncate(content.rfind('\n').map_or(0, |i| i + 1));
+                content.len() as u64
+            };
+            end = Some((path, offset));
+        }
+        for record in parse_records(&content) {
+            // Lines without a timestamp sort with the line before them
+            let time = record.entry.time().or(last_time);
+            last_time = time;
+            records.push((time, record));
+        }
+    }
+
+    // Files can overlap around a rotation; the sort is stable
+    records.sort_by_key(|(time, _)| *time);
+    Ok(Backlog {
+        records: records.into_iter().map(|(_, record)| record).collect(),
+        end,
+    })
 }
 
 impl LogsCommand {
@@ -23,69 +372,275 @@ impl LogsCommand {
         let log_dir = get_log_dir()?;
 
         if !log_dir.exists() {
-            println!("No logs found. Has the sync engine been started?");
+            self.notice("No logs found. Has the sync engine been started?");
             return Ok(());
         }
 
-        // Find the latest log file
-        let mut entries = tokio::fs::read_dir(&log_dir).await?;
-        let mut log_files_with_meta = Vec::new();
+        let filter = LogFilter {
+            level: self.level,
+            since: self.since.map(|since| Utc::now() - since),
+            grep: self.grep.clone(),
+            component: self.component.clone(),
+        };
 
-        while let Some(entry) = entries.next_entry().await? {
-            let path = entry.path();
-            if path.extension().map(|e| e == "log").unwrap_or(false) {
-                let modified = entry.metadata().await.ok().and_then(|m| m.modified().ok());
-                log_files_with_meta.push((entry, modified));
-            }
+        if self.follow {
+            self.follow_log(&log_dir, &filter).await?;
+        } else {
+            self.show_log_tail(&read_records(&log_dir).await?.records, &filter)?;
         }
 
-        log_files_with_meta.sort_by_key(|(_, m)| *m);
+        Ok(())
+    }
 
-        if let Some((latest, _)) = log_files_with_meta.last() {
-            let log_path = latest.path();
+    /// Show the last matching lines across all log files
+    fn show_log_tail(&self, records: &[LogRecord], filter: &LogFilter) -> Result<()> {
+        if records.is_empty() {
+            self.notice("No log files found.");
+            return Ok(());
+        }
 
-            if self.follow {
-                self.follow_log(&log_path).await?;
-            } else {
-                self.show_log_tail(&log_path, self.lines).await?;
+        let matching: Vec<&LogRecord> = records.iter().filter(|r| r.matches(filter)).collect();
+        let start = matching.len().saturating_sub(self.lines);
+
+        for record in &matching[start..] {
+            for line in &record.lines {
+                self.print_line(line)?;
             }
-        } else {
-            println!("No log files found.");
         }
 
         Ok(())
     }
 
-    /// Show tail of log file
-    async fn show_log_tail(&self, path: &Path, lines: usize) -> Result<()> {
-        let content = tokio::fs::read_to_string(path).await?;
-
-        let log_lines: Vec<&str> = content.lines().collect();
-        let start = if log_lines.len() > lines {
-            log_lines.len() - lines
-        } else {
-            0
+    /// Show the last lines, then print new ones as they are logged
+    ///
+    /// Switches to the next daily file once the current one is read to the
+    /// end, and runs until interrupted with Ctrl+C.
+    async fn follow_log(&self, log_dir: &Path, filter: &LogFilter) -> Result<()> {
+        // Tail from where the backlog ended, so lines logged while it was
+        // read are neither lost nor shown twice
+        let backlog = read_records(log_dir).await?;
+        self.show_log_tail(&backlog.records, filter)?;
+        let mut tail = match backlog.end {
+            Some((path, offset)) => Some(LogTail::open(path, offset).await?),
+            None => None,
         };
 
-        for line in log_lines.iter().skip(start) {
-            println!("{}", line);
+        let ctrl_c = tokio::signal::ctrl_c();
+        tokio::pin!(ctrl_c);
+        // Continuation lines follow the decision for the line they belong to
+        let mut showing = None;
+
+        loop {
+            if let Some(tail) = &mut tail {
+                for line in tail.read_new_lines().await? {
+                    let entry = LogEntry::parse(&line);
+                    let show = match showing {
+                        Some(show) if entry.timestamp.is_none() => show,
+                        _ => filter.matches_entry(&entry) && filter.matches_text(&line),
+                    };
+                    showing = Some(show);
+                    if show {
+                        self.print_line(&line)?;
+                    }
+                }
+            }
+
+            let current = tail.as_ref().map(|t| t.path.clone());
+            let next = latest_log_file(log_dir).await?.filter(|latest| {
+                current
+                    .as_ref()
+                    .is_none_or(|current| log_order(latest) > log_order(current))
+            });
+            if let Some(path) = next {
+                tracing::debug!("Following new log file {}", path.display());
+                tail = Some(LogTail::open(path, 0).await?);
+                continue;
+            }
+
+            tokio::select! {
+                _ = &mut ctrl_c => return Ok(()),
+                _ = tokio::time::sleep(FOLLOW_POLL_INTERVAL) => {}
+            }
         }
+    }
 
+    /// Print a log line as text or JSON
+    fn print_line(&self, line: &str) -> Result<()> {
+        if self.json {
+            println!("{}", serde_json::to_string(&LogEntry::parse(line))?);
+        } else {
+            println!("{}", line);
+        }
         Ok(())
     }
 
-    /// Follow log file
-    async fn follow_log(&self, path: &Path) -> Result<()> {
-        use tokio::io::{AsyncBufReadExt, BufReader};
+    /// Print an informational message, keeping stdout clean in JSON mode
+    fn notice(&self, message: &str) {
+        if self.json {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+    }
+}
 
-        let file = tokio::fs::File::open(path).await?;
-        let reader = BufReader::new(file);
-        let mut lines = reader.lines();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        while let Ok(Some(line)) = lines.next_line().await {
-            println!("{}", line);
-        }
+    #[test]
+    fn test_parse_log_line() {
+        let entry = LogEntry::parse(
+            "2024-05-01T10:00:00.123456Z  INFO proton_drive_sync::sync: src/sync.rs:80: Sync engine started",
+        );
+        assert_eq!(
+            entry.timestamp.as_deref(),
+            Some("2024-05-01T10:00:00.123456Z")
+        );
+        assert_eq!(entry.level.as_deref(), Some("INFO"));
+        assert_eq!(
+            entry.message,
+            "proton_drive_sync::sync: src/sync.rs:80: Sync engine started"
+        );
+    }
 
-        Ok(())
+    #[test]
+    fn test_parse_period() {
+        assert_eq!(parse_period("90s"), Ok(chrono::Duration::seconds(90)));
+        assert_eq!(parse_period("2h"), Ok(chrono::Duration::hours(2)));
+        assert_eq!(parse_period("7d"), Ok(chrono::Duration::days(7)));
+        assert_eq!(parse_period("1w"), Ok(chrono::Duration::weeks(1)));
+        assert!(parse_period("2 hours").is_err());
+        assert!(parse_period("h").is_err());
+    }
+
+    #[test]
+    fn test_filter_by_level_component_and_time() {
+        let entry = LogEntry::parse(
+            "2024-05-01T10:00:00Z  WARN proton_drive_sync::cli::queue: src/cli/queue.rs:9: Retrying",
+        );
+        assert_eq!(entry.component(), Some("cli::queue"));
+
+        let filter = |level, component: &str, since: &str| LogFilter {
+            level,
+            since: Some(since.parse().unwrap()),
+            grep: None,
+            component: Some(component.to_string()),
+        };
+        assert!(filter(Some(Level::WARN), "cli", "2024-05-01T09:00:00Z").matches_entry(&entry));
+        assert!(filter(None, "cli::queue", "2024-05-01T10:00:00Z").matches_entry(&entry));
+        assert!(!filter(Some(Level::ERROR), "cli", "2024-05-01T09:00:00Z").matches_entry(&entry));
+        assert!(!filter(None, "cl", "2024-05-01T09:00:00Z").matches_entry(&entry));
+        assert!(!filter(None, "cli", "2024-05-01T11:00:00Z").matches_entry(&entry));
+    }
+
+    #[tokio::test]
+    async fn test_records_are_merged_across_rotations() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("proton-drive-sync.log.2024-05-02"),
+            "2024-05-02T00:00:01Z  INFO proton_drive_sync::sync: second\n",
+        )
+        .unwrap();
+        let mut compressed = flate2::write::GzEncoder::new(
+            std::fs::File::create(temp_dir.path().join("proton-drive-sync.log.2024-05-01.gz"))
+                .unwrap(),
+            flate2::Compression::default(),
+        );
+        std::io::Write::write_all(
+            &mut compressed,
+            b"2024-05-01T23:59:59Z ERROR proton_drive_sync::processor: first\n  caused by: timeout\n",
+        )
+        .unwrap();
+        compressed.finish().unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), "not a log\n").unwrap();
+
+        let records = read_records(temp_dir.path()).await.unwrap().records;
+        let lines: Vec<&str> = records
+            .iter()
+            .flat_map(|record| record.lines.iter().map(String::as_str))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "2024-05-01T23:59:59Z ERROR proton_drive_sync::processor: first",
+                "  caused by: timeout",
+                "2024-05-02T00:00:01Z  INFO proton_drive_sync::sync: second",
+            ]
+        );
+
+        let filter = LogFilter {
+            grep: Some(Regex::new("timeout").unwrap()),
+            ..Default::default()
+        };
+        assert!(records[0].matches(&filter));
+        assert!(!records[1].matches(&filter));
+    }
+
+    #[tokio::test]
+    async fn test_tail_reads_complete_lines_as_they_are_written() {
+        use std::io::Write;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("proton-drive-sync.log.2024-05-01");
+        std::fs::write(&path, "old line\n").unwrap();
+        let append = |text: &str| {
+            let mut file = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap();
+            file.write_all(text.as_bytes()).unwrap();
+        };
+
+        let mut tail = LogTail::open(path.clone(), 9).await.unwrap();
+        assert!(tail.read_new_lines().await.unwrap().is_empty());
+
+        append("first\nsec");
+        assert_eq!(tail.read_new_lines().await.unwrap(), ["first"]);
+        append("ond\n");
+        assert_eq!(tail.read_new_lines().await.unwrap(), ["second"]);
+
+        // Truncated and rewritten
+        std::fs::write(&path, "new\n").unwrap();
+        assert_eq!(tail.read_new_lines().await.unwrap(), ["new"]);
+    }
+
+    #[tokio::test]
+    async fn test_tail_follows_from_the_end_of_the_backlog() {
+        use std::io::Write;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("proton-drive-sync.log.2024-05-01");
+        std::fs::write(
+            &path,
+            "2024-05-01T10:00:00Z  INFO a: first\n2024-05-01T10:00:01Z",
+        )
+        .unwrap();
+
+        let backlog = read_records(temp_dir.path()).await.unwrap();
+        assert_eq!(backlog.records.len(), 1);
+        let (tail_path, offset) = backlog.end.unwrap();
+        assert_eq!(tail_path, path);
+
+        // Written after the backlog was read
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"  INFO a: second\n").unwrap();
+
+        let mut tail = LogTail::open(tail_path, offset).await.unwrap();
+        assert_eq!(
+            tail.read_new_lines().await.unwrap(),
+            ["2024-05-01T10:00:01Z  INFO a: second"]
+        );
+    }
+
+    #[test]
+    fn test_parse_unstructured_line() {
+        let entry = LogEntry::parse("    at some continuation line");
+        assert_eq!(entry.timestamp, None);
+        assert_eq!(entry.level, None);
+        assert_eq!(entry.message, "    at some continuation line");
     }
 }
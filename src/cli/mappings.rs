@@ -0,0 +1,225 @@
+//! Mappings CLI command
+
+use crate::cli::auth::load_session;
+use crate::cli::output::print_json;
+use crate::config::ConfigManager;
+use crate::db::Db;
+use crate::error::{Error, Result};
+use crate::paths::get_data_dir;
+use crate::proton::ProtonClient;
+use crate::types::{
+    Config, HistoryEntry, HistoryResult, NodeMapping, RemoteDeleteBehavior, SyncDirection,
+    SyncEventType,
+};
+use clap::Subcommand;
+use inquire::Confirm;
+use std::path::Path;
+
+/// Mappings command
+#[derive(Subcommand, Debug)]
+pub enum MappingsCommand {
+    /// Find mappings of remote nodes whose local file or folder is gone
+    Orphans {
+        #[command(subcommand)]
+        command: OrphansCommand,
+    },
+}
+
+/// Orphaned mappings subcommands
+#[derive(Subcommand, Debug)]
+pub enum OrphansCommand {
+    /// List mappings whose local file or folder no longer exists
+    List {
+        /// Print orphaned mappings as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Forget orphaned mappings, keeping the remote files unless asked
+    Prune {
+        /// Also delete the remote files and folders, following
+        /// `remote_delete_behavior`
+        #[arg(long)]
+        delete_remote: bool,
+
+        /// Skip the confirmation prompt
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+impl MappingsCommand {
+    /// Run the mappings command
+    pub async fn run(self) -> Result<()> {
+        let db_path = get_data_dir()?.join("proton-drive-sync.db");
+
+        match self {
+            Self::Orphans {
+                command: OrphansCommand::List { json },
+            } => {
+                let config = ConfigManager::new().await?;
+                let orphans = match Db::open_existing_read_only(db_path).await? {
+                    Some(db) => {
+                        db.get_orphan_node_mappings(&sync_roots(config.get()))
+                            .await?
+                    }
+                    None => Vec::new(),
+                };
+
+                if json {
+                    return print_json(&orphans);
+                }
+
+                if orphans.is_empty() {
+                    println!("No orphaned mappings");
+                    return Ok(());
+                }
+
+                print_orphans(&orphans);
+                println!();
+                println!("Forget them with: proton-drive-sync mappings orphans prune");
+                Ok(())
+            }
+            Self::Orphans {
+                command: OrphansCommand::Prune { delete_remote, yes },
+            } => {
+                let Some(db) = Db::open_existing(db_path).await? else {
+                    println!("No orphaned mappings");
+                    return Ok(());
+                };
+                prune(&db, delete_remote, yes).await
+            }
+        }
+    }
+}
+
+/// Source paths of the configured sync directories
+fn sync_roots(config: &Config) -> Vec<&Path> {
+    config
+        .sync_dirs
+        .iter()
+        .map(|d| Path::new(&d.source_path))
+        .collect()
+}
+
+/// Forget orphaned mappings, deleting their remote nodes first if asked
+async fn prune(db: &Db, delete_remote: bool, yes: bool) -> Result<()> {
+    let config = ConfigManager::new().await?;
+    let orphans = db
+        .get_orphan_node_mappings(&sync_roots(config.get()))
+        .await?;
+    if orphans.is_empty() {
+        println!("No orphaned mappings");
+        return Ok(());
+    }
+
+    print_orphans(&orphans);
+    println!();
+
+    if !yes {
+        let question = if delete_remote {
+            format!(
+                "Delete these {} remote file(s) and folder(s) and forget them?",
+                orphans.len()
+            )
+        } else {
+            format!(
+                "Forget these {} mapping(s)? The remote files are kept.",
+                orphans.len()
+            )
+        };
+        let confirmed = Confirm::new(&question)
+            .with_default(false)
+            .prompt()
+            .map_err(|e| Error::Config(format!("Prompt error: {}", e)))?;
+
+        if !confirmed {
+            println!("Cancelled");
+            return Ok(());
+        }
+    }
+
+    let remote = if delete_remote {
+        Some((
+            ProtonClient::with_sessions(load_session()?),
+            config.get().remote_delete_behavior,
+        ))
+    } else {
+        None
+    };
+
+    // Parents sort before their contents, so a deleted folder covers them
+    let mut deleted_folders: Vec<&str> = Vec::new();
+    let mut pruned = 0;
+
+    for orphan in &orphans {
+        if let Some((client, behavior)) = &remote {
+            let covered = deleted_folders
+                .iter()
+                .any(|folder| Path::new(&orphan.remote_path).starts_with(folder));
+
+            if !covered {
+                let started = std::time::Instant::now();
+                let result = match behavior {
+                    RemoteDeleteBehavior::Trash => client.delete_node(&orphan.node_uid).await,
+                    RemoteDeleteBehavior::Permanent => {
+                        client.delete_node_permanent(&orphan.node_uid).await
+                    }
+                };
+
+                match result {
+                    Ok(()) => {
+                        db.record_history(&HistoryEntry {
+                            id: 0,
+                            event_type: SyncEventType::Delete,
+                            direction: SyncDirection::Upload,
+                            local_path: orphan.local_path.clone(),
+                            remote_path: orphan.remote_path.clone(),
+                            old_local_path: None,
+                            bytes: None,
+                            duration_ms: started.elapsed().as_millis() as i64,
+                            result: HistoryResult::Synced,
+                            error: None,
+                            completed_at: chrono::Utc::now(),
+                        })
+                        .await?;
+                    }
+                    // Already gone remotely; only the mapping is left
+                    Err(e) if e.http_status() == Some(404) => {}
+                    Err(e) => {
+                        println!("✗ {}: {}", orphan.remote_path, e);
+                        continue;
+                    }
+                }
+
+                if orphan.is_directory {
+                    deleted_folders.push(&orphan.remote_path);
+                }
+            }
+        }
+
+        db.delete_node_mapping(&orphan.local_path, &orphan.remote_path)
+            .await?;
+        db.delete_file_state(&orphan.local_path).await?;
+        pruned += 1;
+    }
+
+    println!(
+        "✓ Pruned {} of {} orphaned mapping(s)",
+        pruned,
+        orphans.len()
+    );
+    Ok(())
+}
+
+/// Print orphaned mappings, one per line
+fn print_orphans(orphans: &[NodeMapping]) {
+    for orphan in orphans {
+        println!(
+            "{}  {}{}",
+            orphan.updated_at.format("%Y-%m-%d %H:%M"),
+            orphan.local_path,
+            if orphan.is_directory { "/" } else { "" }
+        );
+        println!("      -> {}", orphan.remote_path);
+    }
+}
@@ -2,26 +2,51 @@
 
 pub mod auth;
 pub mod config;
+pub mod conflicts;
 pub mod dashboard;
+pub mod doctor;
+pub mod history;
+pub mod jobs;
 pub mod logs;
+pub mod mappings;
+pub mod output;
 pub mod pause;
+pub mod queue;
 pub mod reconcile;
+pub mod report;
 pub mod reset;
 pub mod resume;
 pub mod setup;
+pub mod share;
 pub mod start;
+pub mod stats;
 pub mod status;
 pub mod stop;
+pub mod trash;
+pub mod verify;
+pub mod versions;
 
 pub use auth::AuthCommand;
 pub use config::ConfigCommand;
+pub use conflicts::ConflictsCommand;
 pub use dashboard::DashboardCommand;
+pub use doctor::DoctorCommand;
+pub use history::HistoryCommand;
+pub use jobs::JobsCommand;
 pub use logs::LogsCommand;
+pub use mappings::MappingsCommand;
 pub use pause::PauseCommand;
+pub use queue::QueueCommand;
 pub use reconcile::ReconcileCommand;
+pub use report::ReportCommand;
 pub use reset::ResetCommand;
 pub use resume::ResumeCommand;
 pub use setup::SetupCommand;
+pub use share::ShareCommand;
 pub use start::StartCommand;
+pub use stats::StatsCommand;
 pub use status::StatusCommand;
 pub use stop::StopCommand;
+pub use trash::TrashCommand;
+pub use verify::VerifyCommand;
+pub use versions::VersionsCommand;
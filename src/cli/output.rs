@@ -0,0 +1,10 @@
+//! Machine-readable command output
+
+use crate::error::Result;
+use serde::Serialize;
+
+/// Print a value as pretty-printed JSON
+pub fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
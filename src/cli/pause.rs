@@ -1,8 +1,7 @@
 //! Pause CLI command
 
-use crate::db::Db;
-use crate::error::Result;
-use crate::paths::get_data_dir;
+use crate::error::{Error, Result};
+use crate::ipc::{send_request, IpcRequest};
 use clap::Parser;
 
 /// Pause command options
@@ -12,18 +11,17 @@ pub struct PauseCommand {}
 impl PauseCommand {
     /// Run the pause command
     pub async fn run(self) -> Result<()> {
-        // Initialize database
-        let data_dir = get_data_dir()?;
-        let db_path = data_dir.join("proton-drive-sync.db");
-        let db = Db::new(db_path).await?;
-
-        // Send pause signal
-        db.send_signal("pause").await?;
-
-        // Set paused flag
-        db.set_flag("paused").await?;
-
-        println!("Sync paused");
+        match send_request(&IpcRequest::Pause).await? {
+            Some(response) if response.ok => println!("Sync paused"),
+            Some(response) => {
+                return Err(Error::Sync(
+                    response
+                        .message
+                        .unwrap_or_else(|| "Pause failed".to_string()),
+                ))
+            }
+            None => println!("Sync daemon is not running"),
+        }
 
         Ok(())
     }
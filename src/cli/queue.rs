@@ -0,0 +1,156 @@
+//! Queue CLI command
+
+use crate::cli::auth::load_session;
+use crate::cli::history::{format_duration_ms, print_entry};
+use crate::config::ConfigManager;
+use crate::db::Db;
+use crate::error::Result;
+use crate::paths::get_data_dir;
+use crate::progress::format_bytes;
+use crate::sync::SyncEngine;
+use crate::types::{HistoryEntry, HistoryResult};
+use chrono::{Local, Utc};
+use clap::Subcommand;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Mutex;
+
+/// Queue command
+#[derive(Subcommand, Debug)]
+pub enum QueueCommand {
+    /// Process queued jobs in the foreground, without starting the daemon
+    Drain {
+        /// Also wait for failed jobs to retry, until none is left to retry
+        #[arg(long)]
+        until_empty: bool,
+    },
+}
+
+/// Outcome of the jobs processed by `queue drain`
+#[derive(Debug, Default, PartialEq, Eq)]
+struct DrainSummary {
+    processed: usize,
+    synced: usize,
+    bytes: u64,
+    blocked: usize,
+    skipped: usize,
+}
+
+impl DrainSummary {
+    /// Count a finished job; retries are counted when they end for good
+    fn add(&mut self, entry: &HistoryEntry) {
+        self.processed += 1;
+        match entry.result {
+            HistoryResult::Synced => {
+                self.synced += 1;
+                self.bytes += entry.bytes.unwrap_or(0).max(0) as u64;
+            }
+            HistoryResult::Blocked => self.blocked += 1,
+            HistoryResult::Skipped => self.skipped += 1,
+            HistoryResult::Retrying => {}
+        }
+    }
+}
+
+impl QueueCommand {
+    /// Run the queue command
+    pub async fn run(self) -> Result<()> {
+        match self {
+            QueueCommand::Drain { until_empty } => drain(until_empty).await,
+        }
+    }
+}
+
+/// Process the queue, printing each job as it finishes and a summary
+async fn drain(until_empty: bool) -> Result<()> {
+    let session = load_session()?;
+    let data_dir = get_data_dir()?;
+    let db = Db::new(data_dir.join("proton-drive-sync.db")).await?;
+    let config = Arc::new(Mutex::new(ConfigManager::new().await?));
+    let engine = SyncEngine::new(db.clone(), config, session).await?;
+
+    let started = Instant::now();
+    let mut summary = DrainSummary::default();
+    let waiting = loop {
+        engine
+            .drain(|entry| {
+                print_entry(entry);
+                summary.add(entry);
+            })
+            .await?;
+
+        let next_retry = db.get_next_retry().await?;
+        let Some((count, due)) = next_retry.filter(|_| until_empty) else {
+            break next_retry;
+        };
+        println!(
+            "Waiting until {} to retry {} job(s)...",
+            due.with_timezone(&Local).format("%H:%M:%S"),
+            count
+        );
+        tokio::time::sleep((due - Utc::now()).to_std().unwrap_or_default()).await;
+    };
+
+    println!();
+    println!(
+        "Processed {} job(s) in {}",
+        summary.processed,
+        format_duration_ms(started.elapsed().as_millis() as i64)
+    );
+    println!(
+        "  Synced:  {} ({})",
+        summary.synced,
+        format_bytes(summary.bytes)
+    );
+    println!("  Blocked: {}", summary.blocked);
+    println!("  Skipped: {}", summary.skipped);
+    if let Some((count, due)) = waiting {
+        println!(
+            "  Waiting for a retry: {} (next at {})",
+            count,
+            due.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{SyncDirection, SyncEventType};
+
+    #[test]
+    fn test_drain_summary_counts_final_outcomes() {
+        let entry = |result, bytes| HistoryEntry {
+            id: 0,
+            event_type: SyncEventType::CreateFile,
+            direction: SyncDirection::Upload,
+            local_path: "/home/notes.txt".to_string(),
+            remote_path: "/notes.txt".to_string(),
+            old_local_path: None,
+            bytes,
+            duration_ms: 10,
+            result,
+            error: None,
+            completed_at: Utc::now(),
+        };
+
+        let mut summary = DrainSummary::default();
+        summary.add(&entry(HistoryResult::Synced, Some(1024)));
+        summary.add(&entry(HistoryResult::Synced, None));
+        summary.add(&entry(HistoryResult::Retrying, Some(2048)));
+        summary.add(&entry(HistoryResult::Blocked, Some(2048)));
+
+        assert_eq!(
+            summary,
+            DrainSummary {
+                processed: 4,
+                synced: 2,
+                bytes: 1024,
+                blocked: 1,
+                skipped: 0,
+            }
+        );
+    }
+}
@@ -1,24 +1,51 @@
 //! Reconcile CLI command
 
 use crate::cli::auth::load_session;
+use crate::cli::output::print_json;
 use crate::config::ConfigManager;
 use crate::db::Db;
 use crate::error::Result;
 use crate::paths::get_data_dir;
 use crate::sync::SyncEngine;
 use clap::Parser;
+use serde::Serialize;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 /// Reconcile command options
 #[derive(Parser, Debug)]
-pub struct ReconcileCommand {}
+pub struct ReconcileCommand {
+    /// Also look for files deleted locally while the daemon was stopped
+    #[arg(long)]
+    pub prune: bool,
+
+    /// Queue remote deletes for the files `--prune` finds, instead of listing them
+    #[arg(long, requires = "prune")]
+    pub confirm: bool,
+
+    /// Print the result as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Result of a reconciliation run
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileReport {
+    pub changes: usize,
+    /// Remote paths of files and folders deleted locally
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<Vec<String>>,
+    /// Whether remote deletes were queued for `deleted`
+    pub deletes_queued: bool,
+}
 
 impl ReconcileCommand {
     /// Run the reconcile command
     pub async fn run(self) -> Result<()> {
-        println!("Running reconciliation scan...");
-        println!();
+        if !self.json {
+            println!("Running reconciliation scan...");
+            println!();
+        }
 
         // Load session
         let session = load_session()?;
@@ -30,6 +57,7 @@ impl ReconcileCommand {
 
         // Load config
         let config = Arc::new(Mutex::new(ConfigManager::new().await?));
+        let prune_remote = config.lock().await.get().prune_remote;
 
         // Create sync engine
         let engine = SyncEngine::new(db.clone(), config.clone(), session).await?;
@@ -37,10 +65,59 @@ impl ReconcileCommand {
         // Run reconciliation
         let count = engine.reconcile().await?;
 
+        // With `prune_remote` set the daemon prunes on its own, so do the same
+        let queue_deletes = self.confirm || prune_remote;
+        let deleted = if self.prune || prune_remote {
+            Some(engine.prune(!queue_deletes).await?)
+        } else {
+            None
+        };
+
+        if self.json {
+            return print_json(&ReconcileReport {
+                changes: count,
+                deleted: deleted
+                    .as_ref()
+                    .map(|d| d.iter().map(|m| m.remote_path.clone()).collect()),
+                deletes_queued: queue_deletes && deleted.as_ref().is_some_and(|d| !d.is_empty()),
+            });
+        }
+
         println!();
         println!("Reconciliation complete!");
         println!("Detected {} changes", count);
 
+        let Some(deleted) = deleted else {
+            return Ok(());
+        };
+        println!();
+        if deleted.is_empty() {
+            println!("✓ No locally deleted files left on the remote");
+            return Ok(());
+        }
+
+        for mapping in &deleted {
+            let kind = if mapping.is_directory {
+                "folder"
+            } else {
+                "file"
+            };
+            println!("  {} ({})", mapping.remote_path, kind);
+        }
+        println!();
+        if queue_deletes {
+            println!(
+                "✓ Queued remote deletes for {} file(s) and folder(s)",
+                deleted.len()
+            );
+        } else {
+            println!(
+                "{} file(s) and folder(s) were deleted locally but are still on the remote",
+                deleted.len()
+            );
+            println!("Delete them remotely with: proton-drive-sync reconcile --prune --confirm");
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,130 @@
+//! Report CLI command
+
+use crate::crash::{diagnostic_files, queue_summary};
+use crate::error::Result;
+use crate::paths::get_crash_dir;
+use chrono::Utc;
+use clap::Subcommand;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::path::{Path, PathBuf};
+
+/// Report command
+#[derive(Subcommand, Debug)]
+pub enum ReportCommand {
+    /// Pack crash reports and diagnostics into a tarball for a bug report
+    Pack {
+        /// Output file (defaults to proton-drive-sync-report-<time>.tar.gz)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+impl ReportCommand {
+    /// Run the report command
+    pub async fn run(self) -> Result<()> {
+        match self {
+            Self::Pack { output } => Self::pack(output).await,
+        }
+    }
+
+    /// Write the report archive
+    async fn pack(output: Option<PathBuf>) -> Result<()> {
+        let output = output.unwrap_or_else(|| {
+            PathBuf::from(format!(
+                "proton-drive-sync-report-{}.tar.gz",
+                Utc::now().format("%Y%m%d-%H%M%S")
+            ))
+        });
+
+        let crash_dir = get_crash_dir()?;
+        let queue = queue_summary().await;
+
+        let bundles = write_archive(&output, &crash_dir, diagnostic_files(queue))?;
+
+        println!("✓ Wrote {}", output.display());
+        println!("  {} crash report(s) plus current diagnostics", bundles);
+        println!(
+            "Secrets and your home directory path are redacted; review the archive before sharing."
+        );
+
+        Ok(())
+    }
+}
+
+/// Write crash bundles and current diagnostics into a gzipped tarball
+///
+/// Returns the number of crash bundles included.
+fn write_archive(
+    output: &Path,
+    crash_dir: &Path,
+    diagnostics: Vec<(&'static str, String)>,
+) -> Result<usize> {
+    let file = std::fs::File::create(output)?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+    let mut bundles = 0;
+    if crash_dir.is_dir() {
+        for entry in std::fs::read_dir(crash_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                archive
+                    .append_dir_all(Path::new("crashes").join(entry.file_name()), entry.path())?;
+                bundles += 1;
+            }
+        }
+    }
+
+    for (name, content) in diagnostics {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(Utc::now().timestamp() as u64);
+        header.set_cksum();
+        archive.append_data(
+            &mut header,
+            Path::new("current").join(name),
+            content.as_bytes(),
+        )?;
+    }
+
+    archive.into_inner()?.finish()?;
+
+    Ok(bundles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_archive() {
+        let temp_dir = TempDir::new().unwrap();
+        let crash_dir = temp_dir.path().join("crashes");
+        fs::create_dir_all(crash_dir.join("crash-1")).unwrap();
+        fs::write(crash_dir.join("crash-1/panic.txt"), "boom").unwrap();
+
+        let output = temp_dir.path().join("report.tar.gz");
+        let bundles = write_archive(
+            &output,
+            &crash_dir,
+            vec![("system.txt", "version: test\n".to_string())],
+        )
+        .unwrap();
+        assert_eq!(bundles, 1);
+
+        let mut archive = tar::Archive::new(GzDecoder::new(fs::File::open(&output).unwrap()));
+        let mut names: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+
+        assert!(names.contains(&"crashes/crash-1/panic.txt".to_string()));
+        assert!(names.contains(&"current/system.txt".to_string()));
+    }
+}
@@ -1,5 +1,6 @@
 //! Reset CLI command
 
+use crate::config::ConfigFormat;
 use crate::db::Db;
 use crate::error::Result;
 use crate::paths::get_data_dir;
@@ -42,19 +43,22 @@ impl ResetCommand {
         if db_path.exists() {
             let db = Db::new(db_path.clone()).await?;
 
-            // Send stop signal
-            db.send_signal("stop").await.ok();
+            // Ask a running daemon to stop
+            crate::ipc::send_request(&crate::ipc::IpcRequest::Stop)
+                .await
+                .ok();
 
             // Clear flags
             db.clear_flag("running").await.ok();
             db.clear_flag("paused").await.ok();
+            db.close().await;
 
             println!("✓ Sync engine stopped");
         }
 
         if self.purge {
             // Remove database
-            tokio::fs::remove_file(&db_path).await.ok();
+            Db::delete_files(&db_path).await.ok();
             println!("✓ Database cleared");
 
             // Remove configuration
@@ -62,18 +66,19 @@ impl ResetCommand {
                 .map(|d| d.join("proton-drive-sync"))
                 .unwrap_or_default();
 
-            let config_file = config_dir.join("config.json");
-            tokio::fs::remove_file(&config_file).await.ok();
+            for format in [ConfigFormat::Json, ConfigFormat::Toml] {
+                let config_file = config_dir.join(format.file_name());
+                tokio::fs::remove_file(&config_file).await.ok();
+            }
             println!("✓ Configuration cleared");
 
             // Remove credentials
-            let entry = keyring::Entry::new("proton-drive-sync", "credentials")?;
-            let _ = entry.delete_credential();
+            crate::credentials::delete_session()?;
             println!("✓ Credentials cleared");
         } else {
             // Just clear the database (keep config and credentials)
             if db_path.exists() {
-                tokio::fs::remove_file(&db_path).await?;
+                Db::delete_files(&db_path).await?;
                 println!("✓ Sync history cleared");
             }
         }
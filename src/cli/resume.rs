@@ -1,8 +1,7 @@
 //! Resume CLI command
 
-use crate::db::Db;
-use crate::error::Result;
-use crate::paths::get_data_dir;
+use crate::error::{Error, Result};
+use crate::ipc::{send_request, IpcRequest};
 use clap::Parser;
 
 /// Resume command options
@@ -12,18 +11,17 @@ pub struct ResumeCommand {}
 impl ResumeCommand {
     /// Run the resume command
     pub async fn run(self) -> Result<()> {
-        // Initialize database
-        let data_dir = get_data_dir()?;
-        let db_path = data_dir.join("proton-drive-sync.db");
-        let db = Db::new(db_path).await?;
-
-        // Send resume signal
-        db.send_signal("resume").await?;
-
-        // Clear paused flag
-        db.clear_flag("paused").await?;
-
-        println!("Sync resumed");
+        match send_request(&IpcRequest::Resume).await? {
+            Some(response) if response.ok => println!("Sync resumed"),
+            Some(response) => {
+                return Err(Error::Sync(
+                    response
+                        .message
+                        .unwrap_or_else(|| "Resume failed".to_string()),
+                ))
+            }
+            None => println!("Sync daemon is not running"),
+        }
 
         Ok(())
     }
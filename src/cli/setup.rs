@@ -1,22 +1,49 @@
 //! Setup CLI command
 
-use crate::config::ConfigManager;
-use crate::error::Result;
-use crate::types::RemoteDeleteBehavior;
+use crate::config::{nested_sync_dirs, ConfigFormat, ConfigManager};
+use crate::error::{Error, Result};
+use crate::types::{Config, RemoteDeleteBehavior, SyncDir};
 use clap::Parser;
 use inquire::{Confirm, Select, Text};
+use std::path::{Path, PathBuf};
 
 /// Setup command options
 #[derive(Parser, Debug)]
 pub struct SetupCommand {
-    /// Skip interactive setup
+    /// Skip interactive setup and configure from flags or --from-file
     #[arg(long)]
     pub non_interactive: bool,
+    /// Config file (JSON or TOML) to set up from; implies --non-interactive
+    #[arg(long, conflicts_with_all = ["source", "remote"])]
+    pub from_file: Option<PathBuf>,
+    /// Local path to sync; may be repeated, paired in order with --remote.
+    /// Implies --non-interactive
+    #[arg(long)]
+    pub source: Vec<String>,
+    /// Remote Proton Drive path for the --source at the same position
+    #[arg(long)]
+    pub remote: Vec<String>,
+    /// Number of concurrent uploads
+    #[arg(long)]
+    pub concurrency: Option<usize>,
+    /// What happens to remote files deleted locally
+    #[arg(long, value_enum)]
+    pub delete_behavior: Option<RemoteDeleteBehavior>,
+    /// Allow sync directories inside one another
+    #[arg(long)]
+    pub allow_nested: bool,
+    /// Replace an existing configuration
+    #[arg(long)]
+    pub overwrite: bool,
 }
 
 impl SetupCommand {
     /// Run the setup command
     pub async fn run(self) -> Result<()> {
+        if self.non_interactive || self.from_file.is_some() || !self.source.is_empty() {
+            return self.run_non_interactive().await;
+        }
+
         if !super::auth::is_authenticated() {
             println!("Please authenticate first:");
             println!("  proton-drive-sync auth login");
@@ -63,10 +90,25 @@ impl SetupCommand {
                 .prompt()
                 .map_err(|e| crate::error::Error::Config(format!("Prompt error: {}", e)))?;
 
-            config.add_sync_dir(source, remote).await?;
-            added_dirs += 1;
+            let mut sync_dirs = config.get().sync_dirs.clone();
+            sync_dirs.push(SyncDir {
+                source_path: source.clone(),
+                remote_root: remote.clone(),
+                case_insensitive: None,
+            });
 
-            println!("✓ Added sync directory");
+            let nested = nested_sync_dirs(&sync_dirs);
+            if nested.is_empty() {
+                config.add_sync_dir(source, remote).await?;
+                added_dirs += 1;
+
+                println!("✓ Added sync directory");
+            } else {
+                for line in &nested {
+                    println!("{}", line);
+                }
+                println!("Skipped: files there would be uploaded twice. Use `proton-drive-sync config add-dir --allow-nested` to add it anyway.");
+            }
 
             let add_more = Confirm::new("Add another sync directory?")
                 .with_default(false)
@@ -124,4 +166,152 @@ impl SetupCommand {
 
         Ok(())
     }
+
+    /// Configure from flags or a config file without prompting
+    async fn run_non_interactive(&self) -> Result<()> {
+        if !super::auth::is_authenticated() {
+            return Err(Error::Config(
+                "Not authenticated. Run `proton-drive-sync auth login` first.".to_string(),
+            ));
+        }
+
+        let mut config = ConfigManager::new().await?;
+        if !config.file_config().sync_dirs.is_empty() && !self.overwrite {
+            return Err(Error::Config(format!(
+                "{} already has sync directories. Pass --overwrite to replace them.",
+                config.path().display()
+            )));
+        }
+
+        let new_config = self.build_config(config.file_config())?;
+        let problems = setup_problems(&new_config);
+        if !problems.is_empty() {
+            for problem in &problems {
+                eprintln!("  {}", problem);
+            }
+            return Err(Error::Config(format!(
+                "Setup failed with {} problem(s); nothing was written",
+                problems.len()
+            )));
+        }
+
+        let added_dirs = new_config.sync_dirs.len();
+        config.replace(new_config).await?;
+
+        println!("✓ Wrote {}", config.path().display());
+        println!("Added {} sync directory(s)", added_dirs);
+        Ok(())
+    }
+
+    /// Settings to write: those of --from-file, or the current ones with the
+    /// flags' sync directories, then the flags' other settings
+    fn build_config(&self, current: &Config) -> Result<Config> {
+        let mut config = match &self.from_file {
+            Some(path) => {
+                let content = std::fs::read_to_string(path).map_err(|e| {
+                    Error::Config(format!("Could not read {}: {}", path.display(), e))
+                })?;
+                ConfigFormat::from_path(path)
+                    .unwrap_or(ConfigFormat::Json)
+                    .parse(&content)?
+            }
+            None => {
+                if self.source.len() != self.remote.len() {
+                    return Err(Error::Config(format!(
+                        "Got {} --source and {} --remote; pass one --remote per --source",
+                        self.source.len(),
+                        self.remote.len()
+                    )));
+                }
+
+                let mut config = current.clone();
+                config.sync_dirs = self
+                    .source
+                    .iter()
+                    .zip(&self.remote)
+                    .map(|(source, remote)| SyncDir {
+                        source_path: source.clone(),
+                        remote_root: remote.clone(),
+                        case_insensitive: None,
+                    })
+                    .collect();
+                config
+            }
+        };
+
+        if let Some(concurrency) = self.concurrency {
+            config.sync_concurrency = concurrency;
+        }
+        if let Some(behavior) = self.delete_behavior {
+            config.remote_delete_behavior = behavior;
+        }
+        if self.allow_nested {
+            config.allow_nested_sync_dirs = true;
+        }
+
+        Ok(config)
+    }
+}
+
+/// Reasons a scripted setup can't use `config`
+fn setup_problems(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if config.sync_dirs.is_empty() {
+        problems.push("No sync directories given (use --source and --remote)".to_string());
+    }
+    for dir in &config.sync_dirs {
+        if !Path::new(&dir.source_path).is_dir() {
+            problems.push(format!("{} is not a directory", dir.source_path));
+        }
+        if !dir.remote_root.starts_with('/') {
+            problems.push(format!("Remote path {} must start with /", dir.remote_root));
+        }
+    }
+    if !config.allow_nested_sync_dirs {
+        problems.extend(
+            nested_sync_dirs(&config.sync_dirs)
+                .into_iter()
+                .map(|line| format!("{} (pass --allow-nested to allow this)", line)),
+        );
+    }
+    if config.sync_concurrency == 0 {
+        problems.push("Concurrency must be at least 1".to_string());
+    }
+    if let Err(e) = config.validate() {
+        problems.push(e.to_string());
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_setup_problems() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().to_string_lossy().to_string();
+        let dir = |source: &str, remote: &str| SyncDir {
+            source_path: source.to_string(),
+            remote_root: remote.to_string(),
+            case_insensitive: None,
+        };
+
+        let mut config = Config {
+            sync_dirs: vec![dir(&source, "/My Files/Docs")],
+            ..Default::default()
+        };
+        assert!(setup_problems(&config).is_empty());
+
+        config
+            .sync_dirs
+            .push(dir(&format!("{}/sub", source), "Sub"));
+        config.sync_concurrency = 0;
+        assert_eq!(setup_problems(&config).len(), 4);
+
+        assert_eq!(setup_problems(&Config::default()).len(), 1);
+    }
 }
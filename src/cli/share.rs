@@ -0,0 +1,80 @@
+//! Share CLI command
+
+use crate::cli::auth::load_session;
+use crate::cli::output::print_json;
+use crate::cli::versions::synced_node;
+use crate::config::ConfigManager;
+use crate::db::Db;
+use crate::error::{Error, Result};
+use crate::paths::get_data_dir;
+use crate::proton::ProtonClient;
+use clap::Parser;
+use inquire::Password;
+use std::path::PathBuf;
+
+/// Share command options
+#[derive(Parser, Debug)]
+pub struct ShareCommand {
+    /// Synced local file or folder to share
+    pub path: PathBuf,
+
+    /// Protect the link with a password, entered at a prompt
+    #[arg(long)]
+    pub password: bool,
+
+    /// Make the link stop working after this many days
+    #[arg(long, value_name = "DAYS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub expires_in: Option<u32>,
+
+    /// Print the link as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+impl ShareCommand {
+    /// Run the share command
+    pub async fn run(self) -> Result<()> {
+        let config = ConfigManager::new().await?;
+        let local_path = crate::paths::canonical(&self.path)?;
+
+        let data_dir = get_data_dir()?;
+        let db = Db::new(data_dir.join("proton-drive-sync.db")).await?;
+
+        let (_, mapping) = synced_node(config.get(), &db, &local_path).await?;
+
+        let password = if self.password {
+            let password = Password::new("Link password:")
+                .prompt()
+                .map_err(|e| Error::Config(format!("Prompt error: {}", e)))?;
+            Some(password)
+        } else {
+            None
+        };
+        let expires_at = self
+            .expires_in
+            .map(|days| chrono::Utc::now() + chrono::Duration::days(days.into()));
+
+        let client = ProtonClient::with_sessions(load_session()?);
+        let share = client
+            .create_share_url(&mapping.node_uid, password.as_deref(), expires_at)
+            .await?;
+
+        if self.json {
+            return print_json(&share);
+        }
+
+        println!("✓ Shared {}", mapping.remote_path);
+        println!();
+        println!("  {}", share.url);
+        println!();
+        if share.password_protected {
+            println!("Password protected");
+        }
+        match share.expires_at {
+            Some(at) => println!("Expires {}", at.format("%Y-%m-%d %H:%M")),
+            None => println!("Never expires"),
+        }
+
+        Ok(())
+    }
+}
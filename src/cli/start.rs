@@ -1,17 +1,34 @@
 //! Start CLI command
 
+use crate::artifacts::{artifact_globs, detect_artifact_dirs};
 use crate::cli::auth::load_session;
-use crate::config::ConfigManager;
+use crate::config::{nested_sync_dirs, ConfigManager};
 use crate::db::Db;
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::ipc::IpcServer;
 use crate::paths::get_data_dir;
+use crate::progress::render_bar;
+use crate::sandbox::{apply_sandbox, drop_privileges, SandboxPaths, SandboxStatus};
 use crate::sync::SyncEngine;
 use clap::Parser;
+use inquire::Confirm;
+use std::io::IsTerminal;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::signal;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
 use tracing::info;
 
+/// How often the foreground progress bar is redrawn
+const PROGRESS_REDRAW_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Width of the foreground progress bar
+const PROGRESS_BAR_WIDTH: usize = 30;
+
+/// Return to the start of the line and erase it
+const CLEAR_LINE: &str = "\r\x1b[2K";
+
 /// Start command options
 #[derive(Parser, Debug)]
 pub struct StartCommand {
@@ -22,9 +39,54 @@ pub struct StartCommand {
     /// Enable debug logging
     #[arg(long)]
     pub debug: bool,
+
+    /// Exclude detected build-artifact directories without prompting
+    #[arg(long)]
+    pub auto_exclude_artifacts: bool,
+
+    /// Start even if one sync directory is inside another
+    #[arg(long)]
+    pub allow_nested: bool,
+
+    /// Switch to this user before starting (when started as root)
+    #[arg(long, value_name = "USER")]
+    pub run_as: Option<String>,
+
+    /// Restrict file access to the sync directories and the app's own
+    /// directories (Linux, with Landlock)
+    #[arg(long)]
+    pub sandbox: bool,
 }
 
 impl StartCommand {
+    /// Drop privileges and enter the sandbox, if requested
+    ///
+    /// Must be called before the async runtime starts, while the process
+    /// has a single thread. Logging is not set up yet, so warnings go to
+    /// stderr.
+    pub fn prepare_process(&self) -> Result<()> {
+        if let Some(user) = &self.run_as {
+            drop_privileges(user)?;
+        }
+
+        if self.sandbox {
+            let config = ConfigManager::load_blocking()?;
+            let paths = SandboxPaths::for_config(&config)?;
+
+            match apply_sandbox(&paths)? {
+                SandboxStatus::FullyEnforced => {}
+                SandboxStatus::PartiallyEnforced => {
+                    eprintln!("Warning: the kernel only partly supports the file access sandbox")
+                }
+                SandboxStatus::NotEnforced => {
+                    eprintln!("Warning: the file access sandbox is not supported on this system")
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Run the start command
     pub async fn run(self) -> Result<()> {
         // Load session
@@ -33,13 +95,25 @@ impl StartCommand {
         // Initialize database
         let data_dir = get_data_dir()?;
         let db_path = data_dir.join("proton-drive-sync.db");
-        let db = Db::new(db_path).await?;
+        let db = Db::new(db_path.clone()).await?;
 
         // Load config
-        let config = Arc::new(Mutex::new(ConfigManager::new().await?));
+        let mut config_manager = ConfigManager::new().await?;
+        self.check_nested(&config_manager)?;
+        self.check_artifacts(&db, &mut config_manager).await?;
+        crate::digest::log_startup_banner(&db, &db_path, config_manager.get()).await?;
+        let config = Arc::new(Mutex::new(config_manager));
 
         // Create sync engine
-        let engine = SyncEngine::new(db.clone(), config.clone(), session).await?;
+        let engine = Arc::new(SyncEngine::new(db.clone(), config.clone(), session).await?);
+
+        // Take the engine lock and start the IPC server before the engine
+        // so a second daemon fails fast
+        engine.lock_instance()?;
+        let shutdown = Arc::new(Notify::new());
+        let ipc_handle = IpcServer::new(engine.clone(), shutdown.clone())
+            .start()
+            .await?;
 
         // Start the engine
         engine.start().await?;
@@ -50,6 +124,10 @@ impl StartCommand {
             // Run in foreground - wait for shutdown signal
             info!("Running in foreground. Press Ctrl+C to stop.");
 
+            let progress_handle = std::io::stderr()
+                .is_terminal()
+                .then(|| tokio::spawn(show_progress(db.clone())));
+
             #[cfg(unix)]
             {
                 use signal::unix::{signal, SignalKind};
@@ -63,6 +141,9 @@ impl StartCommand {
                     _ = sigint.recv() => {
                         info!("Received SIGINT, shutting down...");
                     }
+                    _ = shutdown.notified() => {
+                        info!("Received stop request, shutting down...");
+                    }
                 }
             }
 
@@ -76,9 +157,17 @@ impl StartCommand {
                     _ = ctrl_break() => {
                         info!("Received Ctrl+Break, shutting down...");
                     }
+                    _ = shutdown.notified() => {
+                        info!("Received stop request, shutting down...");
+                    }
                 }
             }
 
+            if let Some(handle) = progress_handle {
+                handle.abort();
+                eprint!("{}", CLEAR_LINE);
+            }
+
             engine.stop().await?;
             info!("Shutdown complete");
         } else {
@@ -102,6 +191,118 @@ impl StartCommand {
             }
         }
 
+        ipc_handle.abort();
+        crate::ipc::cleanup();
+
+        Ok(())
+    }
+
+    /// Refuse overlapping sync directories unless nesting was allowed
+    ///
+    /// With nesting allowed, files inside a nested directory sync only to
+    /// the innermost directory's remote path.
+    fn check_nested(&self, config: &ConfigManager) -> Result<()> {
+        let nested = nested_sync_dirs(&config.get().sync_dirs);
+        if nested.is_empty() {
+            return Ok(());
+        }
+
+        if !self.allow_nested && !config.get().allow_nested_sync_dirs {
+            return Err(Error::Config(format!(
+                "Sync directories overlap: {}. Files there would be uploaded twice; pass --allow-nested to sync them only to the innermost directory",
+                nested.join("; ")
+            )));
+        }
+
+        for line in &nested {
+            info!("Nested sync directory: {}", line);
+        }
+
+        Ok(())
+    }
+
+    /// Offer to exclude build-artifact directories once per sync directory
+    async fn check_artifacts(&self, db: &Db, config: &mut ConfigManager) -> Result<()> {
+        let interactive = std::io::stdin().is_terminal();
+        let sync_dirs = config.get().sync_dirs.clone();
+
+        for sync_dir in sync_dirs {
+            let flag = format!("artifacts_checked:{}", sync_dir.source_path);
+            if db.get_flag(&flag).await? {
+                continue;
+            }
+
+            if !self.auto_exclude_artifacts && !interactive {
+                continue;
+            }
+
+            let found = detect_artifact_dirs(Path::new(&sync_dir.source_path));
+
+            if !found.is_empty() {
+                println!(
+                    "Found {} build-artifact directory(s) in {}:",
+                    found.len(),
+                    sync_dir.source_path
+                );
+                for dir in &found {
+                    println!("  {}", dir.display());
+                }
+
+                let exclude = self.auto_exclude_artifacts
+                    || Confirm::new("Exclude these from sync?")
+                        .with_default(true)
+                        .prompt()
+                        .map_err(|e| Error::Config(format!("Prompt error: {}", e)))?;
+
+                if exclude {
+                    let globs = found.iter().flat_map(|d| artifact_globs(d)).collect();
+                    config
+                        .extend_exclude_pattern(sync_dir.source_path.clone(), globs)
+                        .await?;
+                    println!("✓ Excluded {} artifact directory(s)", found.len());
+                }
+                println!();
+            }
+
+            db.set_flag(&flag).await?;
+        }
+
         Ok(())
     }
 }
+
+/// Draw a progress bar for in-flight uploads on stderr until aborted
+async fn show_progress(db: Db) {
+    let mut ticker = tokio::time::interval(PROGRESS_REDRAW_INTERVAL);
+    let mut drawn = false;
+
+    loop {
+        ticker.tick().await;
+
+        let uploads = db.get_job_progress().await.unwrap_or_default();
+
+        let Some(first) = uploads.first() else {
+            if drawn {
+                eprint!("{}", CLEAR_LINE);
+                drawn = false;
+            }
+            continue;
+        };
+
+        let mut line = format!(
+            "{}{} {}",
+            CLEAR_LINE,
+            render_bar(first, PROGRESS_BAR_WIDTH),
+            Path::new(&first.local_path)
+                .file_name()
+                .map(|n| n.to_string_lossy())
+                .unwrap_or_default()
+        );
+        if uploads.len() > 1 {
+            line.push_str(&format!(" (+{} more)", uploads.len() - 1));
+        }
+
+        eprint!("{}", line);
+        drawn = true;
+    }
+}
@@ -0,0 +1,129 @@
+//! Stats CLI command
+
+use crate::cli::output::print_json;
+use crate::config::ConfigManager;
+use crate::db::Db;
+use crate::error::Result;
+use crate::paths::get_data_dir;
+use crate::progress::format_bytes;
+use crate::types::{SyncDirection, TransferStats};
+use chrono::{Local, NaiveDate};
+use clap::Parser;
+use std::collections::BTreeMap;
+
+/// Stats command options
+#[derive(Parser, Debug)]
+pub struct StatsCommand {
+    /// Show transfers of the last this many days, including today
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u32).range(1..))]
+    pub days: u32,
+
+    /// Print daily totals per sync directory as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Files and bytes moved each way
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    up_files: i64,
+    up_bytes: i64,
+    down_files: i64,
+    down_bytes: i64,
+}
+
+impl Totals {
+    fn add(&mut self, stats: &TransferStats) {
+        match stats.direction {
+            SyncDirection::Upload => {
+                self.up_files += stats.files;
+                self.up_bytes += stats.bytes;
+            }
+            SyncDirection::Download => {
+                self.down_files += stats.files;
+                self.down_bytes += stats.bytes;
+            }
+        }
+    }
+}
+
+impl StatsCommand {
+    /// Run the stats command
+    pub async fn run(self) -> Result<()> {
+        let config = ConfigManager::new().await?;
+        let data_dir = get_data_dir()?;
+        let since = Local::now().date_naive() - chrono::Duration::days(i64::from(self.days) - 1);
+
+        let stats = match Db::open_existing(data_dir.join("proton-drive-sync.db")).await? {
+            Some(db) => {
+                // Include transfers since the daemon last aggregated
+                crate::stats::aggregate(&db, &config.get().sync_dirs).await?;
+                db.get_transfer_stats(since).await?
+            }
+            None => Vec::new(),
+        };
+
+        if self.json {
+            return print_json(&stats);
+        }
+
+        if stats.is_empty() {
+            println!("No transfers in the last {} days", self.days);
+            return Ok(());
+        }
+
+        let mut by_day: BTreeMap<NaiveDate, Totals> = BTreeMap::new();
+        let mut by_dir: BTreeMap<&str, Totals> = BTreeMap::new();
+        let mut total = Totals::default();
+        for entry in &stats {
+            by_day.entry(entry.day).or_default().add(entry);
+            by_dir.entry(&entry.sync_dir).or_default().add(entry);
+            total.add(entry);
+        }
+
+        println!("Transfers in the last {} days", self.days);
+        println!();
+        println!("{:<12} {:<24} Downloaded", "Day", "Uploaded");
+        for (day, totals) in &by_day {
+            print_row(&day.to_string(), totals);
+        }
+        print_row("Total", &total);
+
+        println!();
+        println!("By sync directory:");
+        for (dir, totals) in &by_dir {
+            println!("  {}", dir);
+            println!(
+                "    ↑ {}  ↓ {}",
+                format_transfers(totals.up_files, totals.up_bytes),
+                format_transfers(totals.down_files, totals.down_bytes)
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Print one line of the daily table
+fn print_row(label: &str, totals: &Totals) {
+    println!(
+        "{:<12} {:<24} {}",
+        label,
+        format_transfers(totals.up_files, totals.up_bytes),
+        format_transfers(totals.down_files, totals.down_bytes)
+    );
+}
+
+/// Human-readable file count and size, e.g. `12 files, 3.4 MiB`
+fn format_transfers(files: i64, bytes: i64) -> String {
+    if files == 0 {
+        return "-".to_string();
+    }
+
+    format!(
+        "{} {}, {}",
+        files,
+        if files == 1 { "file" } else { "files" },
+        format_bytes(bytes.max(0) as u64)
+    )
+}
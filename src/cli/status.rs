@@ -1,10 +1,21 @@
 //! Status CLI command
 
+use crate::cli::auth::load_session;
+use crate::cli::jobs::format_age;
+use crate::cli::output::print_json;
+use crate::config::ConfigManager;
 use crate::db::Db;
 use crate::error::Result;
+use crate::ipc::{send_request, IpcRequest};
 use crate::paths::get_data_dir;
-use crate::types::SyncJobStatus;
+use crate::progress::{format_bytes, render_bar};
+use crate::proton::ProtonClient;
+use crate::queue::{JobQueue, StatusCounts};
+use crate::sync::{SyncState, HEARTBEAT_INTERVAL};
+use crate::types::{AccountInfo, DaemonHeartbeat, JobProgress};
+use chrono::{DateTime, Local, Utc};
 use clap::Parser;
+use serde::Serialize;
 
 /// Status command options
 #[derive(Parser, Debug)]
@@ -12,59 +23,405 @@ pub struct StatusCommand {
     /// Show detailed output
     #[arg(short, long)]
     pub verbose: bool,
+
+    /// Print status as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Daemon state as seen from the CLI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DaemonState {
+    Stopped,
+    Running,
+    Paused,
+    /// Running, but waiting for the network
+    Offline,
+    /// Still sending heartbeats, but not answering requests
+    Unresponsive,
+    /// Stopped without shutting down, e.g. after a crash
+    Crashed,
+}
+
+/// Heartbeats missed before the daemon is considered gone
+const MISSED_HEARTBEATS: i32 = 3;
+
+/// Work out the daemon's state from its IPC answer and last heartbeat
+///
+/// A clean shutdown removes the heartbeat, so one left behind without a
+/// daemon to answer means the process died.
+fn daemon_state(
+    answered: Option<SyncState>,
+    heartbeat: Option<&DaemonHeartbeat>,
+    now: DateTime<Utc>,
+) -> DaemonState {
+    let max_age =
+        chrono::Duration::from_std(HEARTBEAT_INTERVAL).unwrap_or_default() * MISSED_HEARTBEATS;
+
+    match (answered, heartbeat) {
+        (Some(SyncState::Paused), _) => DaemonState::Paused,
+        (Some(SyncState::Offline), _) => DaemonState::Offline,
+        (Some(_), _) => DaemonState::Running,
+        (None, None) => DaemonState::Stopped,
+        (None, Some(heartbeat)) if heartbeat.is_fresh(now, max_age) => DaemonState::Unresponsive,
+        (None, Some(_)) => DaemonState::Crashed,
+    }
+}
+
+/// Entries skipped in one sync directory
+#[derive(Debug, Clone, Serialize)]
+pub struct IgnoredCount {
+    pub sync_dir: String,
+    pub count: i64,
+}
+
+/// Full status report
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    /// Whether the daemon has ever run on this machine
+    pub initialized: bool,
+    pub daemon: DaemonState,
+    /// The daemon's PID, start time and last heartbeat
+    pub process: Option<DaemonHeartbeat>,
+    /// Seconds since the daemon started, while it is alive
+    pub uptime_secs: Option<i64>,
+    /// When a file was last synced successfully
+    pub last_synced_at: Option<DateTime<Utc>>,
+    /// When the Proton API last answered the daemon successfully
+    pub last_api_contact: Option<DateTime<Utc>>,
+    pub queue: StatusCounts,
+    /// Entries excluded by `.protonignore`
+    pub ignored: Vec<IgnoredCount>,
+    /// File events for temporary and partial files since the watcher started
+    pub temp_events: Vec<IgnoredCount>,
+    pub skipped_roots: Vec<String>,
+    pub uploads: Vec<JobProgress>,
+    /// Logged-in account, if logged in and reachable
+    pub account: Option<AccountInfo>,
+    /// Whether storage usage is at or above `storage_warning_percent`
+    pub storage_warning: bool,
+}
+
+impl StatusReport {
+    /// Report for a machine where the daemon has never run
+    fn uninitialized() -> Self {
+        Self {
+            initialized: false,
+            daemon: DaemonState::Stopped,
+            process: None,
+            uptime_secs: None,
+            last_synced_at: None,
+            last_api_contact: None,
+            queue: StatusCounts::default(),
+            ignored: Vec::new(),
+            temp_events: Vec::new(),
+            skipped_roots: Vec::new(),
+            uploads: Vec::new(),
+            account: None,
+            storage_warning: false,
+        }
+    }
 }
 
 impl StatusCommand {
     /// Run the status command
     pub async fn run(self) -> Result<()> {
-        // Initialize database
         let data_dir = get_data_dir()?;
         let db_path = data_dir.join("proton-drive-sync.db");
-        let db = Db::new(db_path).await?;
 
-        // Check if running
-        let running = db.get_flag("running").await?;
-        let paused = db.get_flag("paused").await?;
+        let mut report = match Db::open_existing_read_only(db_path).await? {
+            Some(db) => Self::collect(&db).await?,
+            None => StatusReport::uninitialized(),
+        };
+
+        if report.initialized {
+            let warning_percent = ConfigManager::new().await?.get().storage_warning_percent;
+            report.account = fetch_account().await;
+            report.storage_warning = report
+                .account
+                .as_ref()
+                .is_some_and(|a| a.is_over(warning_percent));
+        }
+
+        if self.json {
+            return print_json(&report);
+        }
+
+        self.print(&report);
+        Ok(())
+    }
+
+    /// Gather status from the daemon and the database
+    async fn collect(db: &Db) -> Result<StatusReport> {
+        // Ask the daemon for its state
+        let daemon = send_request(&IpcRequest::Status).await?;
+
+        let answered = daemon.and_then(|r| r.status).map(|status| status.state);
+        let process = db.get_heartbeat().await?;
+        let now = Utc::now();
+        let daemon = daemon_state(answered, process.as_ref(), now);
+        let uptime_secs = match daemon {
+            DaemonState::Stopped | DaemonState::Crashed => None,
+            _ => process
+                .as_ref()
+                .map(|p| (now - p.started_at).num_seconds().max(0)),
+        };
+        let last_synced_at = db.get_last_synced_at().await?;
+        let last_api_contact = db.get_last_api_contact().await?;
+
+        let queue = JobQueue::new(db.clone()).get_status_counts().await?;
 
+        let ignored = db
+            .get_ignored_counts()
+            .await?
+            .into_iter()
+            .map(|(sync_dir, count)| IgnoredCount { sync_dir, count })
+            .collect();
+
+        let temp_events = db
+            .get_temp_event_counts()
+            .await?
+            .into_iter()
+            .map(|(sync_dir, count)| IgnoredCount { sync_dir, count })
+            .collect();
+
+        let skipped_roots = db.get_nosync_roots().await?;
+
+        // Progress rows only mean something while the daemon runs
+        let uploads = match daemon {
+            DaemonState::Stopped | DaemonState::Crashed => Vec::new(),
+            _ => db.get_job_progress().await?,
+        };
+
+        Ok(StatusReport {
+            initialized: true,
+            daemon,
+            process,
+            uptime_secs,
+            last_synced_at,
+            last_api_contact,
+            queue,
+            ignored,
+            temp_events,
+            skipped_roots,
+            uploads,
+            account: None,
+            storage_warning: false,
+        })
+    }
+
+    /// Print the report for humans
+    fn print(&self, report: &StatusReport) {
         println!("Proton Drive Sync Status");
         println!("========================");
         println!();
 
-        if !running {
-            println!("Status: Stopped");
+        if !report.initialized {
+            println!("Status: Never initialized");
             println!();
-            println!("Start the sync engine with: proton-drive-sync start");
-            return Ok(());
+            println!("Get started with: proton-drive-sync auth login");
+            return;
         }
 
-        if paused {
-            println!("Status: Paused");
+        if let Some(account) = &report.account {
+            println!("Account: {}", account.email);
+            if let Some(plan) = &account.plan {
+                println!("  Plan: {}", plan);
+            }
+            println!(
+                "  Storage: {} of {} ({:.0}%)",
+                format_bytes(account.used_space),
+                format_bytes(account.max_space),
+                account.usage_percent()
+            );
+            if report.storage_warning {
+                println!("  ⚠ Running out of storage; uploads fail once it is full");
+            }
             println!();
-            println!("Resume with: proton-drive-sync resume");
-        } else {
-            println!("Status: Running");
         }
 
-        println!();
+        let last_synced = format_last(report.last_synced_at);
+        let last_contact = match report.last_api_contact {
+            Some(at) => format!("{} ago", format_age(Utc::now() - at)),
+            None => "never".to_string(),
+        };
 
-        // Get job counts
-        let pending = db.get_job_count(SyncJobStatus::Pending).await? as usize;
-        let processing = db.get_job_count(SyncJobStatus::Processing).await? as usize;
-        let synced = db.get_job_count(SyncJobStatus::Synced).await? as usize;
-        let blocked = db.get_job_count(SyncJobStatus::Blocked).await? as usize;
+        match report.daemon {
+            DaemonState::Stopped => {
+                println!("Status: Stopped");
+                println!("Last synced: {}", last_synced);
+                println!("Last server contact: {}", last_contact);
+                println!();
+                println!("Start the sync engine with: proton-drive-sync start");
+                return;
+            }
+            DaemonState::Crashed => {
+                println!("Status: Stopped unexpectedly");
+                if let Some(process) = &report.process {
+                    println!(
+                        "  Process {} was last seen at {}",
+                        process.pid,
+                        process
+                            .last_seen
+                            .with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M:%S")
+                    );
+                }
+                println!("Last synced: {}", last_synced);
+                println!("Last server contact: {}", last_contact);
+                println!();
+                println!("Check the logs, then restart with: proton-drive-sync start");
+                return;
+            }
+            DaemonState::Unresponsive => {
+                println!("Status: Not responding");
+                println!();
+                println!("The daemon is alive but does not answer; it may be busy or stuck");
+            }
+            DaemonState::Paused => {
+                println!("Status: Paused");
+                println!();
+                println!("Resume with: proton-drive-sync resume");
+            }
+            DaemonState::Offline => {
+                println!("Status: Offline");
+                println!();
+                println!("Waiting for the network; syncing resumes automatically");
+            }
+            DaemonState::Running => println!("Status: Running"),
+        }
+
+        if let Some(process) = &report.process {
+            println!("  PID: {}", process.pid);
+        }
+        if let Some(uptime) = report.uptime_secs {
+            println!(
+                "  Uptime: {}",
+                format_age(chrono::Duration::seconds(uptime))
+            );
+        }
+        println!("  Last synced: {}", last_synced);
+        println!("  Last server contact: {}", last_contact);
+
+        println!();
 
         println!("Queue Status:");
-        println!("  Pending: {}", pending);
-        println!("  Processing: {}", processing);
-        println!("  Synced: {}", synced);
-        println!("  Blocked: {}", blocked);
+        println!("  Pending: {}", report.queue.pending);
+        println!("  Processing: {}", report.queue.processing);
+        println!("  Synced: {}", report.queue.synced);
+        println!("  Blocked: {}", report.queue.blocked);
+        if report.queue.skipped > 0 {
+            println!(
+                "  Skipped: {} (cannot be read; see `proton-drive-sync jobs list --status skipped`)",
+                report.queue.skipped
+            );
+        }
+
+        if !report.uploads.is_empty() {
+            println!();
+            println!("Uploading:");
+            for upload in &report.uploads {
+                println!("  {}", upload.local_path);
+                println!("    {}", render_bar(upload, 30));
+            }
+        }
 
-        if self.verbose && blocked > 0 {
+        if !report.ignored.is_empty() {
+            println!();
+            println!("Ignored by .protonignore:");
+            for ignored in &report.ignored {
+                println!("  {}: {}", ignored.sync_dir, ignored.count);
+            }
+        }
+
+        if !report.temp_events.is_empty() {
+            println!();
+            println!("Temporary file events skipped:");
+            for temp in &report.temp_events {
+                println!("  {}: {}", temp.sync_dir, temp.count);
+            }
+        }
+
+        if !report.skipped_roots.is_empty() {
+            println!();
+            println!("Skipped by opt-out markers (.nosync, CACHEDIR.TAG):");
+            for root in &report.skipped_roots {
+                println!("  {}", root);
+            }
+        }
+
+        if self.verbose && report.queue.blocked > 0 {
             println!();
             println!("Blocked jobs:");
             // In a full implementation, you'd list the blocked jobs with their errors
         }
+    }
+}
 
-        Ok(())
+/// Render a past time with how long ago it was, or "never"
+fn format_last(at: Option<DateTime<Utc>>) -> String {
+    match at {
+        Some(at) => format!(
+            "{} ({} ago)",
+            at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            format_age(Utc::now() - at)
+        ),
+        None => "never".to_string(),
+    }
+}
+
+/// How long to wait for account info before showing status without it
+const ACCOUNT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Look up the logged-in account, if there is a session and the API answers
+///
+/// Status must work offline and logged out, so failures and a slow API only
+/// hide the section.
+async fn fetch_account() -> Option<AccountInfo> {
+    let client = ProtonClient::with_sessions(load_session().ok()?);
+
+    match tokio::time::timeout(ACCOUNT_TIMEOUT, client.get_account_info()).await {
+        Ok(Ok(account)) => Some(account),
+        Ok(Err(e)) => {
+            tracing::debug!("Could not fetch account info: {}", e);
+            None
+        }
+        Err(_) => {
+            tracing::debug!("Account info took longer than {:?}", ACCOUNT_TIMEOUT);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daemon_state_from_heartbeat() {
+        let now = Utc::now();
+        let heartbeat = |age| DaemonHeartbeat {
+            pid: 42,
+            started_at: now - chrono::Duration::hours(2),
+            last_seen: now - age,
+        };
+        let fresh = heartbeat(chrono::Duration::seconds(10));
+        let stale = heartbeat(chrono::Duration::minutes(10));
+
+        assert_eq!(
+            daemon_state(Some(SyncState::Running), Some(&fresh), now),
+            DaemonState::Running
+        );
+        assert_eq!(
+            daemon_state(Some(SyncState::Paused), None, now),
+            DaemonState::Paused
+        );
+        assert_eq!(daemon_state(None, None, now), DaemonState::Stopped);
+        assert_eq!(
+            daemon_state(None, Some(&fresh), now),
+            DaemonState::Unresponsive
+        );
+        assert_eq!(daemon_state(None, Some(&stale), now), DaemonState::Crashed);
     }
 }
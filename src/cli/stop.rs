@@ -1,7 +1,8 @@
 //! Stop CLI command
 
 use crate::db::Db;
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::ipc::{send_request, IpcRequest};
 use crate::paths::get_data_dir;
 use clap::Parser;
 
@@ -12,20 +13,28 @@ pub struct StopCommand {}
 impl StopCommand {
     /// Run the stop command
     pub async fn run(self) -> Result<()> {
-        // Initialize database
-        let data_dir = get_data_dir()?;
-        let db_path = data_dir.join("proton-drive-sync.db");
-        let db = Db::new(db_path).await?;
-
-        // Send stop signal
-        db.send_signal("stop").await?;
-
-        println!("Stop signal sent");
-
-        // Clear running flag
-        db.clear_flag("running").await?;
-
-        println!("Sync engine stopped");
+        match send_request(&IpcRequest::Stop).await? {
+            Some(response) if response.ok => {
+                println!("Sync engine stopped");
+            }
+            Some(response) => {
+                return Err(Error::Sync(
+                    response
+                        .message
+                        .unwrap_or_else(|| "Stop failed".to_string()),
+                ))
+            }
+            None => {
+                // Clear a running flag left behind by a daemon that died
+                let data_dir = get_data_dir()?;
+                let db_path = data_dir.join("proton-drive-sync.db");
+                if let Some(db) = Db::open_existing(db_path).await? {
+                    db.clear_flag("running").await?;
+                }
+
+                println!("Sync daemon is not running");
+            }
+        }
 
         Ok(())
     }
@@ -0,0 +1,426 @@
+//! Trash CLI command
+
+use crate::cli::auth::load_session;
+use crate::cli::output::print_json;
+use crate::config::ConfigManager;
+use crate::db::Db;
+use crate::error::{Error, Result};
+use crate::paths::get_data_dir;
+use crate::proton::{PathUtils, ProtonClient};
+use crate::remote_index::{RemoteIndex, FOLDER_NODE_TYPE};
+use crate::trash::{self, TrashEntry};
+use crate::types::{
+    HistoryEntry, HistoryResult, NodeMapping, SyncDir, SyncDirection, SyncEventType, TrashedNode,
+};
+use crate::watcher::{remote_path_for, write_own_file, FileWatcher};
+use clap::Subcommand;
+use inquire::Confirm;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Trash command
+#[derive(Subcommand, Debug)]
+pub enum TrashCommand {
+    /// List local files moved to the trash, oldest first
+    List {
+        /// Print entries as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// List the Proton Drive trash instead
+        #[arg(long)]
+        remote: bool,
+    },
+    /// Move a trashed file back to where it was
+    Restore {
+        /// Entry ID, as listed by `trash list`; with --remote, the local
+        /// path of a deleted synced file or folder
+        target: String,
+
+        /// Restore from the Proton Drive trash instead
+        #[arg(long)]
+        remote: bool,
+    },
+    /// Permanently delete everything in the trash
+    Empty {
+        /// Empty the Proton Drive trash instead
+        #[arg(long)]
+        remote: bool,
+
+        /// Skip the confirmation prompt
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+/// A node in the Proton Drive trash, with where it used to be
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteTrashEntry {
+    #[serde(flatten)]
+    pub trashed: TrashedNode,
+    /// Remote path before it was trashed, if its folder still exists
+    pub remote_path: Option<String>,
+    /// Matching path inside a sync directory, if any
+    pub local_path: Option<PathBuf>,
+}
+
+impl TrashCommand {
+    /// Run the trash command
+    pub async fn run(self) -> Result<()> {
+        let config = ConfigManager::new().await?;
+        let trash_dirs = trash::all_trash_dirs(
+            config
+                .get()
+                .sync_dirs
+                .iter()
+                .map(|d| Path::new(&d.source_path)),
+        )?;
+
+        match self {
+            Self::List { json, remote: true } => {
+                let entries = list_remote(&config.get().sync_dirs).await?;
+
+                if json {
+                    return print_json(&entries);
+                }
+
+                print_remote_entries(&entries);
+                Ok(())
+            }
+            Self::List {
+                json,
+                remote: false,
+            } => {
+                let mut entries = Vec::new();
+                for trash in &trash_dirs {
+                    entries.extend(trash::list(trash)?);
+                }
+                entries.sort_by_key(|e| e.trashed_at);
+
+                if json {
+                    return print_json(&entries);
+                }
+
+                print_entries(&entries, config.get().trash_retention_days);
+                Ok(())
+            }
+            Self::Restore {
+                target,
+                remote: true,
+            } => restore_remote(&config, Path::new(&target)).await,
+            Self::Restore {
+                target: id,
+                remote: false,
+            } => restore_local(&trash_dirs, &id),
+            Self::Empty { remote, yes } => {
+                let prompt = if remote {
+                    "Permanently delete everything in the Proton Drive trash?"
+                } else {
+                    "Permanently delete everything in the local trash?"
+                };
+
+                if !yes {
+                    let confirmed = Confirm::new(prompt)
+                        .with_default(false)
+                        .prompt()
+                        .map_err(|e| Error::Config(format!("Prompt error: {}", e)))?;
+
+                    if !confirmed {
+                        return Ok(());
+                    }
+                }
+
+                if remote {
+                    ProtonClient::with_sessions(load_session()?)
+                        .empty_trash()
+                        .await?;
+                    println!("✓ Emptied the Proton Drive trash");
+                } else {
+                    let mut deleted = 0;
+                    for trash in &trash_dirs {
+                        deleted += trash::empty(trash)?;
+                    }
+                    println!("✓ Deleted {} trash entries", deleted);
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Move a local trash entry back, whichever of the trash directories holds it
+fn restore_local(trash_dirs: &[PathBuf], id: &str) -> Result<()> {
+    for trash in trash_dirs {
+        if trash::list(trash)?.iter().any(|e| e.id == id) {
+            let restored = trash::restore(trash, id)?;
+            println!("✓ Restored {}", restored.display());
+            return Ok(());
+        }
+    }
+
+    Err(Error::InvalidState(format!("No trash entry {}", id)))
+}
+
+/// List the Proton Drive trash, newest first, with original locations
+async fn list_remote(sync_dirs: &[SyncDir]) -> Result<Vec<RemoteTrashEntry>> {
+    let client = ProtonClient::with_sessions(load_session()?);
+    let db = open_db().await?;
+    let remote_index = RemoteIndex::new(&client, &db);
+
+    let mut trashed = client.list_trash().await?;
+    trashed.sort_by_key(|t| std::cmp::Reverse(t.trashed_at));
+
+    let mut entries = Vec::new();
+    for trashed in trashed {
+        let parent_path = match &trashed.node.parent_uid {
+            Some(parent) => remote_index.resolve_node_path(parent).await?,
+            None => None,
+        };
+        let remote_path = parent_path.map(|p| PathUtils::join(&p, &trashed.node.name));
+
+        // A recorded mapping keeps the local name a `remote_naming` policy changed
+        let local_path = match &remote_path {
+            Some(p) => match db.get_node_mapping_by_remote_path(p).await? {
+                Some(mapping) => Some(PathBuf::from(mapping.local_path)),
+                None => local_path_for(p, sync_dirs),
+            },
+            None => None,
+        };
+
+        entries.push(RemoteTrashEntry {
+            trashed,
+            remote_path,
+            local_path,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Restore a deleted synced file or folder from the Proton Drive trash
+///
+/// A restored file is also written back to its local path; a restored
+/// folder comes back remotely only.
+async fn restore_remote(config: &ConfigManager, path: &Path) -> Result<()> {
+    let local_path = std::path::absolute(path)?;
+    if local_path.exists() {
+        return Err(Error::InvalidState(format!(
+            "{} exists locally; move it away before restoring",
+            local_path.display()
+        )));
+    }
+
+    let sync_dir = FileWatcher::find_sync_dir(&local_path, config.get())?.ok_or_else(|| {
+        Error::InvalidPath(format!(
+            "{} is not inside a sync directory",
+            local_path.display()
+        ))
+    })?;
+    let db = open_db().await?;
+    let remote_path = remote_path_for(
+        &db,
+        &sync_dir.source_path,
+        &sync_dir.remote_root,
+        &local_path,
+        config.get().remote_naming,
+    )
+    .await?;
+    let name = PathUtils::filename(&remote_path);
+    // `trash list` shows remote names escaped for Windows by `local_name`
+    let local_name = local_path
+        .file_name()
+        .map(|n| crate::paths::nfc(&n.to_string_lossy()))
+        .unwrap_or_default();
+
+    let client = ProtonClient::with_sessions(load_session()?);
+    let remote_index = RemoteIndex::new(&client, &db);
+
+    let parent_path = PathUtils::parent(&remote_path).unwrap_or_else(|| "/".to_string());
+    let parent_uid = if PathUtils::normalize(&parent_path) == "/" {
+        client.get_root_id()
+    } else {
+        remote_index
+            .get_node_by_path(&parent_path)
+            .await?
+            .ok_or_else(|| {
+                Error::InvalidState(format!(
+                    "Remote folder {} no longer exists; restore it first",
+                    parent_path
+                ))
+            })?
+            .uid
+    };
+
+    // The same name may have been deleted several times; take the latest
+    let trashed = client
+        .list_trash()
+        .await?
+        .into_iter()
+        .filter(|t| {
+            (t.node.name == name || crate::paths::local_name(&t.node.name) == local_name)
+                && t.node.parent_uid.as_deref() == Some(parent_uid.as_str())
+        })
+        .max_by_key(|t| t.trashed_at)
+        .ok_or_else(|| {
+            Error::InvalidState(format!("{} is not in the Proton Drive trash", remote_path))
+        })?;
+    let node = trashed.node;
+    let remote_path = PathUtils::join(&parent_path, &node.name);
+
+    let started = std::time::Instant::now();
+    client
+        .restore_from_trash(std::slice::from_ref(&node.uid))
+        .await?;
+
+    let is_directory = node.node_type == FOLDER_NODE_TYPE;
+    let mut bytes = None;
+    if is_directory {
+        std::fs::create_dir_all(&local_path)?;
+    } else {
+        let revision = node
+            .active_revision
+            .as_ref()
+            .ok_or_else(|| Error::api(format!("{} has no content to download", remote_path)))?;
+        let content = client.download_revision(&node.uid, &revision.uid).await?;
+        bytes = Some(content.len() as i64);
+
+        if let Some(dir) = local_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Recorded as synced so it is not uploaded again
+        let mode = config.get().effective_change_detection();
+        write_own_file(&db, &local_path, &content, mode).await?;
+    }
+
+    let local = local_path.to_string_lossy().into_owned();
+    db.update_node_mapping(&NodeMapping {
+        local_path: local.clone(),
+        remote_path: remote_path.clone(),
+        node_uid: node.uid,
+        parent_node_uid: parent_uid,
+        is_directory,
+        updated_at: chrono::Utc::now(),
+    })
+    .await?;
+    db.record_history(&HistoryEntry {
+        id: 0,
+        event_type: if is_directory {
+            SyncEventType::CreateDir
+        } else {
+            SyncEventType::CreateFile
+        },
+        direction: SyncDirection::Download,
+        local_path: local.clone(),
+        remote_path: remote_path.clone(),
+        old_local_path: None,
+        bytes,
+        duration_ms: started.elapsed().as_millis() as i64,
+        result: HistoryResult::Synced,
+        error: None,
+        completed_at: chrono::Utc::now(),
+    })
+    .await?;
+
+    println!("✓ Restored {}", remote_path);
+    if is_directory {
+        println!(
+            "  The folder's contents are back in Proton Drive but were not downloaded to {}",
+            local
+        );
+    } else {
+        println!("  and downloaded it to {}", local);
+    }
+
+    Ok(())
+}
+
+/// Open the sync database
+async fn open_db() -> Result<Db> {
+    let data_dir = get_data_dir()?;
+    Db::new(data_dir.join("proton-drive-sync.db")).await
+}
+
+/// Local path a remote path is synced to, if it lies in a sync directory
+fn local_path_for(remote_path: &str, sync_dirs: &[SyncDir]) -> Option<PathBuf> {
+    sync_dirs.iter().find_map(|sync_dir| {
+        let relative = PathUtils::strip_root(&sync_dir.remote_root, remote_path)?;
+        Some(
+            relative
+                .split('/')
+                .filter(|part| !part.is_empty())
+                .fold(PathBuf::from(&sync_dir.source_path), |path, part| {
+                    path.join(crate::paths::local_name(part))
+                }),
+        )
+    })
+}
+
+/// Print trash entries for humans
+fn print_entries(entries: &[TrashEntry], retention_days: u32) {
+    if entries.is_empty() {
+        println!("Trash is empty");
+        return;
+    }
+
+    for entry in entries {
+        println!(
+            "{}  {}  {}",
+            entry.id,
+            entry.trashed_at.format("%Y-%m-%d %H:%M"),
+            entry.original_path.display()
+        );
+    }
+
+    println!();
+    println!(
+        "Entries are deleted after {} days. Restore one with: proton-drive-sync trash restore <id>",
+        retention_days
+    );
+}
+
+/// Print Proton Drive trash entries for humans
+fn print_remote_entries(entries: &[RemoteTrashEntry]) {
+    if entries.is_empty() {
+        println!("Proton Drive trash is empty");
+        return;
+    }
+
+    for entry in entries {
+        let trashed_at = entry
+            .trashed
+            .trashed_at
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let location = match (&entry.local_path, &entry.remote_path) {
+            (Some(local), _) => local.display().to_string(),
+            (None, Some(remote)) => remote.clone(),
+            (None, None) => format!("{} (folder deleted)", entry.trashed.node.name),
+        };
+
+        println!("{}  {}", trashed_at, location);
+    }
+
+    println!();
+    println!("Restore a synced file with: proton-drive-sync trash restore --remote <path>");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_path_for() {
+        let sync_dirs = vec![SyncDir {
+            source_path: "/home/me/Docs".to_string(),
+            remote_root: "/Docs".to_string(),
+            case_insensitive: None,
+        }];
+
+        assert_eq!(
+            local_path_for("/Docs/a/b.txt", &sync_dirs),
+            Some(PathBuf::from("/home/me/Docs/a/b.txt"))
+        );
+        assert_eq!(local_path_for("/Other/b.txt", &sync_dirs), None);
+    }
+}
@@ -0,0 +1,573 @@
+//! Verify CLI command
+
+use crate::cli::auth::load_session;
+use crate::cli::output::print_json;
+use crate::config::ConfigManager;
+use crate::db::Db;
+use crate::error::{Error, Result};
+use crate::paths::{get_data_dir, nfc};
+use crate::proton::{PathUtils, ProtonClient};
+use crate::remote_index::FOLDER_NODE_TYPE;
+use crate::types::{Config, NodeData, NodeMapping, SyncDir, SyncJobStatus};
+use crate::watcher::{remote_path_for, FileScanner};
+use clap::Parser;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Verify command options
+#[derive(Parser, Debug)]
+pub struct VerifyCommand {
+    /// Only check this file or folder inside a sync directory
+    pub path: Option<PathBuf>,
+
+    /// Also download remote files and compare their SHA-256 hashes
+    #[arg(long)]
+    pub hashes: bool,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// How a local entry and its remote counterpart disagree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscrepancyKind {
+    /// Exists locally but not on the remote
+    Missing,
+    /// Exists on the remote or in the mappings but not locally
+    Orphaned,
+    /// Exists on both sides with different type, node or content
+    Mismatched,
+}
+
+/// One difference found between the local tree and the remote
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Discrepancy {
+    pub kind: DiscrepancyKind,
+    pub local_path: Option<String>,
+    pub remote_path: String,
+    pub reason: String,
+}
+
+/// Result of a verification run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerifyReport {
+    /// Local files and folders compared with the remote
+    pub checked: usize,
+    /// Local paths skipped because a queued job is about to sync them
+    pub pending: usize,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl VerifyCommand {
+    /// Run the verify command
+    pub async fn run(self) -> Result<()> {
+        let config = ConfigManager::new().await?;
+        let config = config.get();
+
+        let scope = self.path.as_deref().map(std::path::absolute).transpose()?;
+        let sync_dirs = sync_dirs_in_scope(&config.sync_dirs, scope.as_deref());
+        if sync_dirs.is_empty() {
+            return Err(Error::Config(match &scope {
+                Some(path) => format!("{} is not inside a sync directory", path.display()),
+                None => "No sync directories configured".to_string(),
+            }));
+        }
+
+        let db = Db::open_existing_read_only(get_data_dir()?.join("proton-drive-sync.db")).await?;
+        let client = ProtonClient::with_sessions(load_session()?);
+
+        let mut report = VerifyReport::default();
+        for sync_dir in sync_dirs {
+            if !self.json {
+                println!(
+                    "Verifying {} -> {}",
+                    sync_dir.source_path, sync_dir.remote_root
+                );
+            }
+
+            let dir_scope = scope
+                .clone()
+                .filter(|path| path.starts_with(&sync_dir.source_path))
+                .unwrap_or_else(|| PathBuf::from(&sync_dir.source_path));
+            let dir_report = verify_sync_dir(
+                &client,
+                db.as_ref(),
+                config,
+                sync_dir,
+                &dir_scope,
+                self.hashes,
+            )
+            .await?;
+
+            report.checked += dir_report.checked;
+            report.pending += dir_report.pending;
+            report.discrepancies.extend(dir_report.discrepancies);
+        }
+
+        if self.json {
+            return print_json(&report);
+        }
+
+        println!();
+        for discrepancy in &report.discrepancies {
+            let (label, path) = match discrepancy.kind {
+                DiscrepancyKind::Missing => ("missing", discrepancy.local_path.as_deref()),
+                DiscrepancyKind::Orphaned => ("orphaned", None),
+                DiscrepancyKind::Mismatched => ("mismatched", discrepancy.local_path.as_deref()),
+            };
+            println!(
+                "✗ {:<10} {}: {}",
+                label,
+                path.unwrap_or(&discrepancy.remote_path),
+                discrepancy.reason
+            );
+        }
+
+        if report.discrepancies.is_empty() {
+            println!(
+                "✓ Checked {} file(s) and folder(s), all match",
+                report.checked
+            );
+        } else {
+            println!();
+            println!(
+                "Checked {} file(s) and folder(s), found {} discrepanc(ies)",
+                report.checked,
+                report.discrepancies.len()
+            );
+        }
+        if report.pending > 0 {
+            println!("Skipped {} path(s) with queued changes", report.pending);
+        }
+
+        Ok(())
+    }
+}
+
+/// Sync directories holding `scope` or lying inside it, all of them without one
+///
+/// With identical sources only the first sync directory counts, as in a scan.
+fn sync_dirs_in_scope<'a>(sync_dirs: &'a [SyncDir], scope: Option<&Path>) -> Vec<&'a SyncDir> {
+    sync_dirs
+        .iter()
+        .enumerate()
+        .filter(|(i, dir)| {
+            !sync_dirs[..*i]
+                .iter()
+                .any(|earlier| earlier.source_path == dir.source_path)
+        })
+        .map(|(_, dir)| dir)
+        .filter(|dir| {
+            scope.is_none_or(|scope| {
+                scope.starts_with(&dir.source_path)
+                    || Path::new(&dir.source_path).starts_with(scope)
+            })
+        })
+        .collect()
+}
+
+/// Compare the part of a sync directory under `scope` with the remote
+///
+/// Nothing is changed on either side.
+pub async fn verify_sync_dir(
+    client: &ProtonClient,
+    db: Option<&Db>,
+    config: &Config,
+    sync_dir: &SyncDir,
+    scope: &Path,
+    hashes: bool,
+) -> Result<VerifyReport> {
+    let source = Path::new(&sync_dir.source_path);
+    let remote_scope = match db {
+        Some(db) => {
+            remote_path_for(
+                db,
+                &sync_dir.source_path,
+                &sync_dir.remote_root,
+                scope,
+                config.remote_naming,
+            )
+            .await?
+        }
+        None => PathUtils::from_local_named(
+            &sync_dir.remote_root,
+            scope.strip_prefix(source).unwrap_or(Path::new("")),
+            config.remote_naming,
+        ),
+    };
+    let mut report = VerifyReport::default();
+
+    let Some(root_uid) = remote_root_uid(client, &sync_dir.remote_root).await? else {
+        report.discrepancies.push(Discrepancy {
+            kind: DiscrepancyKind::Missing,
+            local_path: Some(sync_dir.source_path.clone()),
+            remote_path: PathUtils::normalize(&sync_dir.remote_root),
+            reason: "remote root folder does not exist".to_string(),
+        });
+        return Ok(report);
+    };
+
+    let mut remote =
+        list_remote_tree(client, &root_uid, &sync_dir.remote_root, &remote_scope).await?;
+
+    let scope_str = scope.to_string_lossy();
+    let (mappings, queued): (HashMap<String, NodeMapping>, HashSet<String>) = match db {
+        Some(db) => (
+            db.get_node_mappings_under(&scope_str)
+                .await?
+                .into_iter()
+                .filter(|m| Path::new(&m.local_path).starts_with(scope))
+                .map(|m| (m.local_path.clone(), m))
+                .collect(),
+            db.get_jobs_under(&scope_str)
+                .await?
+                .into_iter()
+                .filter(|job| {
+                    matches!(
+                        job.status,
+                        SyncJobStatus::Pending | SyncJobStatus::Processing
+                    )
+                })
+                .flat_map(|job| std::iter::once(job.local_path).chain(job.old_local_path))
+                .collect(),
+        ),
+        None => Default::default(),
+    };
+
+    let mut seen_local = HashSet::new();
+
+    for entry in FileScanner::synced_entries(&sync_dir.source_path, config) {
+        let path = entry.path();
+        if !path.starts_with(scope) {
+            continue;
+        }
+
+        let local_path = path.to_string_lossy().into_owned();
+        let remote_path =
+            PathUtils::from_local_mapped(&remote_scope, scope, path, config.remote_naming, |p| {
+                mappings
+                    .get(p.to_string_lossy().as_ref())
+                    .map(|m| m.remote_path.clone())
+            });
+        seen_local.insert(local_path.clone());
+
+        if queued.contains(&local_path) {
+            report.pending += 1;
+            remote.remove(&nfc(&remote_path));
+            continue;
+        }
+        report.checked += 1;
+
+        let node = remote.remove(&nfc(&remote_path));
+        let is_dir = entry.file_type().is_dir();
+
+        let reason = match node {
+            None => Some((DiscrepancyKind::Missing, "not on the remote".to_string())),
+            Some((_, node)) => {
+                let mapping = mappings.get(&local_path);
+                compare(client, path, is_dir, &node, mapping, hashes)
+                    .await?
+                    .map(|reason| (DiscrepancyKind::Mismatched, reason))
+            }
+        };
+
+        if let Some((kind, reason)) = reason {
+            report.discrepancies.push(Discrepancy {
+                kind,
+                local_path: Some(local_path),
+                remote_path,
+                reason,
+            });
+        }
+    }
+
+    // Whatever is left on the remote has no local counterpart; contents of an
+    // orphaned folder are covered by the folder
+    let mut orphaned: Vec<String> = Vec::new();
+    for (remote_path, node) in remote.into_values() {
+        if orphaned
+            .iter()
+            .any(|folder| Path::new(&remote_path).starts_with(folder))
+        {
+            continue;
+        }
+
+        report.discrepancies.push(Discrepancy {
+            kind: DiscrepancyKind::Orphaned,
+            local_path: None,
+            remote_path: remote_path.clone(),
+            reason: "only on the remote".to_string(),
+        });
+        if node.node_type == FOLDER_NODE_TYPE {
+            orphaned.push(remote_path);
+        }
+    }
+
+    let mut stale: Vec<&NodeMapping> = mappings
+        .values()
+        .filter(|m| !seen_local.contains(&m.local_path) && !queued.contains(&m.local_path))
+        .filter(|m| !Path::new(&m.local_path).try_exists().unwrap_or(true))
+        .collect();
+    stale.sort_by(|a, b| a.local_path.cmp(&b.local_path));
+
+    for mapping in stale {
+        let covered = orphaned
+            .iter()
+            .any(|folder| Path::new(&nfc(&mapping.remote_path)).starts_with(nfc(folder)));
+        if covered {
+            continue;
+        }
+
+        report.discrepancies.push(Discrepancy {
+            kind: DiscrepancyKind::Orphaned,
+            local_path: Some(mapping.local_path.clone()),
+            remote_path: mapping.remote_path.clone(),
+            reason: "mapped, but gone locally and on the remote".to_string(),
+        });
+        if mapping.is_directory {
+            orphaned.push(mapping.remote_path.clone());
+        }
+    }
+
+    Ok(report)
+}
+
+/// Node UID of a remote root folder, if it exists
+async fn remote_root_uid(client: &ProtonClient, remote_root: &str) -> Result<Option<String>> {
+    if PathUtils::normalize(remote_root) == "/" {
+        return Ok(Some(client.get_root_id()));
+    }
+
+    Ok(client
+        .get_node_by_path(&client.get_root_id(), remote_root)
+        .await?
+        .map(|node| node.uid))
+}
+
+/// Live listing of the remote tree below a root, limited to `scope`
+///
+/// Keyed by the NFC form of each node's path, so lookups do not depend on
+/// the normalization a name was uploaded with.
+async fn list_remote_tree(
+    client: &ProtonClient,
+    root_uid: &str,
+    root_path: &str,
+    scope: &str,
+) -> Result<BTreeMap<String, (String, NodeData)>> {
+    let scope = nfc(scope);
+    let mut tree = BTreeMap::new();
+    let mut folders = vec![(root_uid.to_string(), PathUtils::normalize(root_path))];
+
+    while let Some((uid, path)) = folders.pop() {
+        for node in client.list_nodes(&uid).await? {
+            let node_path = PathUtils::join(&path, &node.name);
+            let key = nfc(&node_path);
+
+            let in_scope = Path::new(&key).starts_with(&scope);
+            if node.node_type == FOLDER_NODE_TYPE
+                && (in_scope || Path::new(&scope).starts_with(&key))
+            {
+                folders.push((node.uid.clone(), node_path.clone()));
+            }
+            if in_scope {
+                tree.insert(key, (node_path, node));
+            }
+        }
+    }
+
+    Ok(tree)
+}
+
+/// Why a local entry does not match its remote node, if it does not
+async fn compare(
+    client: &ProtonClient,
+    path: &Path,
+    is_dir: bool,
+    node: &NodeData,
+    mapping: Option<&NodeMapping>,
+    hashes: bool,
+) -> Result<Option<String>> {
+    let remote_is_dir = node.node_type == FOLDER_NODE_TYPE;
+    if is_dir != remote_is_dir {
+        let kind = |dir| if dir { "folder" } else { "file" };
+        return Ok(Some(format!(
+            "local {} but remote {}",
+            kind(is_dir),
+            kind(remote_is_dir)
+        )));
+    }
+
+    if let Some(mapping) = mapping.filter(|m| m.node_uid != node.uid) {
+        return Ok(Some(format!(
+            "mapped to node {}, but the remote path holds node {}",
+            mapping.node_uid, node.uid
+        )));
+    }
+
+    if is_dir {
+        return Ok(None);
+    }
+
+    let local_size = tokio::fs::metadata(path).await?.len();
+    let revision = node.active_revision.as_ref();
+    match revision.and_then(|r| r.size) {
+        Some(remote_size) if remote_size as u64 != local_size => {
+            return Ok(Some(format!(
+                "size differs: {} bytes locally, {} on the remote",
+                local_size, remote_size
+            )));
+        }
+        _ => {}
+    }
+
+    if hashes {
+        let Some(revision) = revision else {
+            return Ok(Some("remote file has no content".to_string()));
+        };
+        let local = hex::encode(Sha256::digest(tokio::fs::read(path).await?));
+        let remote = hex::encode(Sha256::digest(
+            client.download_revision(&node.uid, &revision.uid).await?,
+        ));
+        if local != remote {
+            return Ok(Some("content differs (SHA-256)".to_string()));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Session;
+    use tempfile::TempDir;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn node_json(uid: &str, parent: &str, name: &str, size: Option<i64>) -> serde_json::Value {
+        match size {
+            None => serde_json::json!({
+                "UID": uid,
+                "ParentLinkID": parent,
+                "Name": name,
+                "NodeType": "folder",
+                "State": 1,
+            }),
+            Some(size) => serde_json::json!({
+                "UID": uid,
+                "ParentLinkID": parent,
+                "Name": name,
+                "NodeType": "file",
+                "State": 1,
+                "ActiveRevision": {"ID": format!("{}-rev", uid), "Size": size},
+            }),
+        }
+    }
+
+    async fn mount_children(server: &MockServer, parent: &str, nodes: Vec<serde_json::Value>) {
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/nodes"))
+            .and(query_param("ParentLinkID", parent))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "Nodes": nodes,
+            })))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_verify_sync_dir() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("Docs");
+        std::fs::create_dir_all(source.join("sub")).unwrap();
+        std::fs::write(source.join("same.txt"), "hello").unwrap();
+        std::fs::write(source.join("grown.txt"), "longer now").unwrap();
+        std::fs::write(source.join("sub/new.txt"), "new").unwrap();
+
+        mount_children(
+            &server,
+            "root",
+            vec![node_json("docs", "root", "Docs", None)],
+        )
+        .await;
+        mount_children(
+            &server,
+            "docs",
+            vec![
+                node_json("same", "docs", "same.txt", Some(5)),
+                node_json("grown", "docs", "grown.txt", Some(3)),
+                node_json("sub", "docs", "sub", None),
+                node_json("old", "docs", "old", None),
+            ],
+        )
+        .await;
+        mount_children(&server, "sub", vec![]).await;
+        mount_children(
+            &server,
+            "old",
+            vec![node_json("gone", "old", "gone.txt", Some(1))],
+        )
+        .await;
+
+        let client = ProtonClient::with_api_base(
+            server.uri(),
+            Session {
+                uid: "test_uid".to_string(),
+                access_token: "test_token".to_string(),
+                refresh_token: "test_refresh".to_string(),
+                key_password: None,
+                primary_key: None,
+                expires_at: None,
+            },
+        );
+        let sync_dir = SyncDir {
+            source_path: source.to_string_lossy().into_owned(),
+            remote_root: "/Docs".to_string(),
+            case_insensitive: None,
+        };
+        let config = Config {
+            sync_dirs: vec![sync_dir.clone()],
+            ..Config::default()
+        };
+
+        let report = verify_sync_dir(&client, None, &config, &sync_dir, &source, false)
+            .await
+            .unwrap();
+
+        let found: Vec<(DiscrepancyKind, &str)> = report
+            .discrepancies
+            .iter()
+            .map(|d| (d.kind, d.remote_path.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (DiscrepancyKind::Mismatched, "/Docs/grown.txt"),
+                (DiscrepancyKind::Missing, "/Docs/sub/new.txt"),
+                (DiscrepancyKind::Orphaned, "/Docs/old"),
+            ]
+        );
+        assert_eq!(report.checked, 4);
+
+        // Limited to a folder, the rest of the tree is left alone
+        let report = verify_sync_dir(
+            &client,
+            None,
+            &config,
+            &sync_dir,
+            &source.join("sub"),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.discrepancies.len(), 1);
+        assert_eq!(report.discrepancies[0].kind, DiscrepancyKind::Missing);
+    }
+}
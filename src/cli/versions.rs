@@ -0,0 +1,238 @@
+//! Versions CLI command
+
+use crate::cli::auth::load_session;
+use crate::cli::output::print_json;
+use crate::config::ConfigManager;
+use crate::db::Db;
+use crate::error::{Error, Result};
+use crate::paths::get_data_dir;
+use crate::proton::ProtonClient;
+use crate::types::{
+    Config, HistoryEntry, HistoryResult, NodeMapping, Revision, SyncDir, SyncDirection,
+    SyncEventType,
+};
+use crate::watcher::{remote_path_for, write_own_file, FileWatcher};
+use clap::Parser;
+use inquire::Confirm;
+use std::path::{Path, PathBuf};
+
+/// Versions command options
+#[derive(Parser, Debug)]
+pub struct VersionsCommand {
+    /// Synced local file to show revisions of
+    pub path: PathBuf,
+
+    /// Print revisions as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Download this revision instead of listing
+    #[arg(long, value_name = "REVISION", conflicts_with = "restore")]
+    pub download: Option<String>,
+
+    /// Where to save a downloaded revision (default: <name>.<revision> here)
+    #[arg(long, short, requires = "download")]
+    pub output: Option<PathBuf>,
+
+    /// Make this revision current, remotely and locally
+    #[arg(long, value_name = "REVISION")]
+    pub restore: Option<String>,
+
+    /// Skip the confirmation prompt
+    #[arg(long, short)]
+    pub yes: bool,
+}
+
+impl VersionsCommand {
+    /// Run the versions command
+    pub async fn run(self) -> Result<()> {
+        let config = ConfigManager::new().await?;
+        let local_path = crate::paths::canonical(&self.path)?;
+        let local = local_path.to_string_lossy().into_owned();
+
+        let data_dir = get_data_dir()?;
+        let db = Db::new(data_dir.join("proton-drive-sync.db")).await?;
+
+        let (sync_dir, mapping) = synced_node(config.get(), &db, &local_path).await?;
+        if mapping.is_directory {
+            return Err(Error::InvalidPath(format!(
+                "{} is a directory; only files have revisions",
+                local
+            )));
+        }
+        let node_uid = mapping.node_uid.clone();
+
+        let client = ProtonClient::with_sessions(load_session()?);
+
+        if let Some(revision) = self.download {
+            let content = client.download_revision(&node_uid, &revision).await?;
+            let output = self
+                .output
+                .unwrap_or_else(|| default_output(&local_path, &revision));
+
+            if output.exists() {
+                return Err(Error::InvalidState(format!(
+                    "{} already exists",
+                    output.display()
+                )));
+            }
+            std::fs::write(&output, content)?;
+
+            println!("✓ Saved revision {} to {}", revision, output.display());
+            return Ok(());
+        }
+
+        if let Some(revision) = self.restore {
+            let revisions = client.list_revisions(&node_uid).await?;
+            let target = revisions
+                .iter()
+                .find(|r| r.uid == revision)
+                .ok_or_else(|| Error::InvalidState(format!("No revision {}", revision)))?;
+
+            if target.active {
+                println!("✓ Revision {} is already current", revision);
+                return Ok(());
+            }
+
+            if !self.yes {
+                let confirmed = Confirm::new(&format!(
+                    "Restore {} to the revision from {}? The local copy will be moved to the trash.",
+                    local,
+                    format_time(target)
+                ))
+                .with_default(false)
+                .prompt()
+                .map_err(|e| Error::Config(format!("Prompt error: {}", e)))?;
+
+                if !confirmed {
+                    return Ok(());
+                }
+            }
+
+            // Fetch the content first so a failed download leaves both sides untouched
+            let started = std::time::Instant::now();
+            let content = client.download_revision(&node_uid, &revision).await?;
+            let bytes = content.len() as i64;
+            client.restore_revision(&node_uid, &revision).await?;
+
+            let trash = crate::trash::trash_dir_for(
+                config.get().trash_location,
+                Path::new(&sync_dir.source_path),
+            )?;
+            let trashed = crate::trash::move_to_trash(&trash, &local_path)?;
+
+            // Recorded as synced so the restored content is not uploaded again
+            let mode = config.get().effective_change_detection();
+            write_own_file(&db, &local_path, &content, mode).await?;
+
+            db.record_history(&HistoryEntry {
+                id: 0,
+                event_type: SyncEventType::Update,
+                direction: SyncDirection::Download,
+                local_path: local.clone(),
+                remote_path: mapping.remote_path.clone(),
+                old_local_path: None,
+                bytes: Some(bytes),
+                duration_ms: started.elapsed().as_millis() as i64,
+                result: HistoryResult::Synced,
+                error: None,
+                completed_at: chrono::Utc::now(),
+            })
+            .await?;
+
+            println!("✓ Restored {} to revision {}", local, revision);
+            println!(
+                "  Previous local copy is in the trash as {}: proton-drive-sync trash restore {}",
+                trashed.id, trashed.id
+            );
+            return Ok(());
+        }
+
+        let revisions = client.list_revisions(&node_uid).await?;
+
+        if self.json {
+            return print_json(&revisions);
+        }
+
+        print_revisions(&local, &revisions);
+        Ok(())
+    }
+}
+
+/// Look up the sync directory and remote node of a synced local file or folder
+///
+/// `local_path` must be absolute and canonical.
+pub(crate) async fn synced_node(
+    config: &Config,
+    db: &Db,
+    local_path: &Path,
+) -> Result<(SyncDir, NodeMapping)> {
+    let sync_dir = FileWatcher::find_sync_dir(local_path, config)?.ok_or_else(|| {
+        Error::InvalidPath(format!(
+            "{} is not inside a sync directory",
+            local_path.display()
+        ))
+    })?;
+    let remote_path = remote_path_for(
+        db,
+        &sync_dir.source_path,
+        &sync_dir.remote_root,
+        local_path,
+        config.remote_naming,
+    )
+    .await?;
+    let local = local_path.to_string_lossy();
+
+    let mapping = db
+        .get_node_mapping(&local, &remote_path)
+        .await?
+        .ok_or_else(|| Error::InvalidState(format!("{} has not been synced yet", local)))?;
+
+    Ok((sync_dir.clone(), mapping))
+}
+
+/// Default file name for a downloaded revision: `<name>.<revision>`
+fn default_output(local_path: &Path, revision: &str) -> PathBuf {
+    let name = local_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    PathBuf::from(format!("{}.{}", name, revision))
+}
+
+/// Creation time of a revision for display
+fn format_time(revision: &Revision) -> String {
+    revision
+        .created_at
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "unknown time".to_string())
+}
+
+/// Print revisions for humans
+fn print_revisions(local: &str, revisions: &[Revision]) {
+    println!("Revisions of {}", local);
+    println!();
+
+    if revisions.is_empty() {
+        println!("No revisions found");
+        return;
+    }
+
+    for revision in revisions {
+        let size = revision
+            .size
+            .map(|s| format!("{} bytes", s))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{}  {}  {:>14}{}",
+            revision.uid,
+            format_time(revision),
+            size,
+            if revision.active { "  (current)" } else { "" }
+        );
+    }
+
+    println!();
+    println!("Download one with: proton-drive-sync versions <path> --download <revision>");
+    println!("Restore one with:  proton-drive-sync versions <path> --restore <revision>");
+}
@@ -0,0 +1,62 @@
+//! Scheduling clock
+//!
+//! Retries are stored as a delay from a recorded baseline, not as a bare
+//! wall-clock deadline. Within a run the clock reads the wall time once and
+//! then advances with the monotonic clock, so NTP corrections or a manually
+//! changed system time neither hold retries back nor release them all at
+//! once. Across restarts, a deadline further away than its own delay means
+//! the clock went backwards, and the database rebases it on the new time.
+
+use chrono::{DateTime, Utc};
+use tokio::time::Instant;
+
+/// Wall time that advances monotonically from when it was created
+#[derive(Debug, Clone, Copy)]
+pub struct Clock {
+    wall_base: DateTime<Utc>,
+    started: Instant,
+}
+
+impl Clock {
+    /// Clock starting at the current wall time
+    pub fn new() -> Self {
+        Self::starting_at(Utc::now())
+    }
+
+    /// Clock starting at a given wall time
+    pub fn starting_at(wall_base: DateTime<Utc>) -> Self {
+        Self {
+            wall_base,
+            started: Instant::now(),
+        }
+    }
+
+    /// Current time, unaffected by system clock changes since the start
+    pub fn now(&self) -> DateTime<Utc> {
+        let elapsed = chrono::Duration::from_std(self.started.elapsed()).unwrap_or_default();
+        self.wall_base + elapsed
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_advances_with_monotonic_time() {
+        let base = Utc::now() - chrono::Duration::days(365);
+        let clock = Clock::starting_at(base);
+
+        tokio::time::advance(Duration::from_secs(90)).await;
+
+        // The wall clock is a year ahead; only the elapsed time counts
+        assert_eq!(clock.now(), base + chrono::Duration::seconds(90));
+    }
+}
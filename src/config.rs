@@ -1,19 +1,253 @@
 //! Configuration management
 
 use crate::error::{Error, Result};
-use crate::types::Config;
+use crate::types::{Config, SyncDir};
 use chrono::{DateTime, Utc};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tokio::fs;
+use toml_edit::Item;
+
+/// Prefix of environment variables that override config settings
+pub const ENV_PREFIX: &str = "PDS_";
+
+/// Separates a section from its setting in an environment variable name
+const ENV_SECTION_SEPARATOR: &str = "__";
+
+/// Config settings overridden by `PDS_*` environment variables
+///
+/// `PDS_SYNC_CONCURRENCY=8` sets `sync_concurrency`, and a double
+/// underscore reaches into a section, as in `PDS_TIMING__RECONCILE=15m`.
+/// Values are read as JSON, so numbers, booleans and lists work, except for
+/// settings that hold a string; anything that isn't JSON is a string.
+/// Variables that name no setting are set aside rather than applied.
+#[derive(Debug, Clone, Default)]
+pub struct EnvOverrides {
+    overrides: Vec<(String, String)>,
+    unknown: Vec<String>,
+}
+
+impl EnvOverrides {
+    /// Overrides set in this process's environment
+    pub fn from_env() -> Self {
+        Self::from_vars(std::env::vars())
+    }
+
+    /// Overrides among `vars`, in name order
+    pub fn from_vars(vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mut overrides: Vec<_> = vars
+            .into_iter()
+            .filter(|(name, _)| name.len() > ENV_PREFIX.len() && name.starts_with(ENV_PREFIX))
+            .collect();
+        overrides.sort();
+
+        let (overrides, unknown): (Vec<_>, Vec<_>) = overrides
+            .into_iter()
+            .partition(|(name, raw)| Self::is_setting(name, raw));
+        Self {
+            overrides,
+            unknown: unknown.into_iter().map(|(name, _)| name).collect(),
+        }
+    }
 
-/// Configuration file name
-const CONFIG_FILE: &str = "config.json";
+    /// Names of the variables that override settings
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.overrides.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Names of `PDS_*` variables that name no setting and are ignored
+    pub fn unknown(&self) -> &[String] {
+        &self.unknown
+    }
+
+    /// Whether a variable names a config setting
+    ///
+    /// Settings the config doesn't know are dropped when it is read back.
+    /// A value that doesn't fit its setting still counts, so that applying
+    /// it reports the error.
+    fn is_setting(name: &str, raw: &str) -> bool {
+        let Ok(mut value) = serde_json::to_value(Config::default()) else {
+            return true;
+        };
+        let Ok(path) = Self::set(&mut value, name, raw) else {
+            return true;
+        };
+        let Ok(config) = serde_json::from_value::<Config>(value) else {
+            return true;
+        };
+        serde_json::to_value(config)
+            .map(|value| value.pointer(&format!("/{}", path.join("/"))).is_some())
+            .unwrap_or(true)
+    }
+
+    /// Set the setting a variable names in a serialized config
+    ///
+    /// Returns the path of the setting.
+    fn set(value: &mut serde_json::Value, name: &str, raw: &str) -> Result<Vec<String>> {
+        let invalid = |reason: String| Error::Config(format!("{}: {}", name, reason));
+        let key = name[ENV_PREFIX.len()..].to_lowercase();
+        let path: Vec<String> = key
+            .split(ENV_SECTION_SEPARATOR)
+            .map(str::to_string)
+            .collect();
+        let (setting, sections) = path.split_last().unwrap_or((&key, &[]));
+
+        let mut section = value;
+        for part in sections {
+            section = section
+                .as_object_mut()
+                .ok_or_else(|| invalid(format!("{} is not a section", part)))?
+                .entry(part)
+                .or_insert_with(|| serde_json::Value::Object(Default::default()));
+        }
+        let section = section
+            .as_object_mut()
+            .ok_or_else(|| invalid("not a config section".to_string()))?;
+
+        let parsed = match section.get(setting) {
+            Some(serde_json::Value::String(_)) => None,
+            _ => serde_json::from_str(raw).ok(),
+        };
+        section.insert(
+            setting.clone(),
+            parsed.unwrap_or_else(|| serde_json::Value::String(raw.to_string())),
+        );
+
+        Ok(path)
+    }
+
+    /// The config with every override applied, validated
+    pub fn apply(&self, config: &Config) -> Result<Config> {
+        if self.overrides.is_empty() {
+            return Ok(config.clone());
+        }
+
+        let mut value = serde_json::to_value(config)?;
+        for (name, raw) in &self.overrides {
+            Self::set(&mut value, name, raw)?;
+            serde_json::from_value::<Config>(value.clone())
+                .map_err(|e| Error::Config(format!("{}: {}", name, e)))?;
+        }
+
+        let config: Config = serde_json::from_value(value)?;
+        config
+            .validate()
+            .map_err(|e| Error::Config(format!("Environment overrides: {}", e)))?;
+        Ok(config)
+    }
+}
+
+/// Format of a config file
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Config file name in this format
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Json => "config.json",
+            Self::Toml => "config.toml",
+        }
+    }
+
+    /// Format named by a file's extension, if it names one
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+
+    /// Write a config in this format
+    pub fn to_string(self, config: &Config) -> Result<String> {
+        match self {
+            Self::Json => Ok(serde_json::to_string_pretty(config)?),
+            Self::Toml => toml::to_string_pretty(config)
+                .map_err(|e| Error::Config(format!("Could not write TOML: {}", e))),
+        }
+    }
+
+    /// Rewrite a file in this format to hold `config`
+    ///
+    /// TOML files keep their comments and layout; only changed settings are
+    /// rewritten. Content that can't be parsed is replaced.
+    pub fn update(self, existing: &str, config: &Config) -> Result<String> {
+        let content = self.to_string(config)?;
+        if self == Self::Json {
+            return Ok(content);
+        }
+
+        let (Ok(mut document), Ok(updated)) = (
+            existing.parse::<toml_edit::DocumentMut>(),
+            content.parse::<toml_edit::DocumentMut>(),
+        ) else {
+            return Ok(content);
+        };
+        merge_toml_table(document.as_table_mut(), updated.as_table());
+        Ok(document.to_string())
+    }
+
+    /// Read a config written in this format, validated
+    pub fn parse(self, content: &str) -> Result<Config> {
+        let config: Config = match self {
+            Self::Json => serde_json::from_str(content)?,
+            Self::Toml => toml::from_str(content)
+                .map_err(|e| Error::Config(format!("Invalid TOML: {}", e)))?,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Copy `updated` into `table`, keeping comments around unchanged entries
+fn merge_toml_table(table: &mut toml_edit::Table, updated: &toml_edit::Table) {
+    table.retain(|key, _| updated.contains_key(key));
+
+    for (key, new) in updated.iter() {
+        match (table.get_mut(key), new) {
+            (Some(Item::Table(old)), Item::Table(new)) => merge_toml_table(old, new),
+            (Some(Item::ArrayOfTables(old)), Item::ArrayOfTables(new)) => {
+                while old.len() > new.len() {
+                    old.remove(old.len() - 1);
+                }
+                for (i, new) in new.iter().enumerate() {
+                    match old.get_mut(i) {
+                        Some(old) => merge_toml_table(old, new),
+                        None => old.push(new.clone()),
+                    }
+                }
+            }
+            (Some(Item::Value(old)), Item::Value(new)) => {
+                if old.to_string().trim() != new.to_string().trim() {
+                    let decor = old.decor().clone();
+                    *old = new.clone();
+                    *old.decor_mut() = decor;
+                }
+            }
+            _ => {
+                table.insert(key, new.clone());
+            }
+        }
+    }
+}
 
 /// Config manager with hot-reload support
+///
+/// Keeps the config file's settings apart from the effective ones with
+/// environment overrides applied, so saving never writes an override to the
+/// file.
 #[derive(Debug, Clone)]
 pub struct ConfigManager {
     config_path: PathBuf,
+    format: ConfigFormat,
+    /// Settings as stored in the config file
     config: Config,
+    /// Settings in effect
+    effective: Config,
+    overrides: EnvOverrides,
     last_modified: DateTime<Utc>,
 }
 
@@ -23,25 +257,80 @@ impl ConfigManager {
         let config_dir = Self::get_config_dir()?;
         fs::create_dir_all(&config_dir).await?;
 
-        let config_path = config_dir.join(CONFIG_FILE);
+        let config_path = Self::find_config(&config_dir);
 
         let (config, last_modified) = if config_path.exists() {
             Self::load_config(&config_path).await?
         } else {
             (Config::default(), Utc::now())
         };
+        let overrides = EnvOverrides::from_env();
+        for name in overrides.unknown() {
+            tracing::warn!("Ignoring {}: it does not name a config setting", name);
+        }
+        let effective = overrides.apply(&config)?;
 
         Ok(Self {
+            format: Self::format_of(&config_path),
             config_path,
             config,
+            effective,
+            overrides,
             last_modified,
         })
     }
 
+    /// Create a config manager for a config already in hand
+    ///
+    /// Nothing is read; the config is written to `config_path` only when
+    /// saved.
+    pub fn with_config(config_path: PathBuf, config: Config) -> Self {
+        Self {
+            format: Self::format_of(&config_path),
+            config_path,
+            effective: config.clone(),
+            config,
+            overrides: EnvOverrides::default(),
+            last_modified: Utc::now(),
+        }
+    }
+
+    /// Apply environment overrides on top of the config file's settings
+    pub fn with_overrides(mut self, overrides: EnvOverrides) -> Result<Self> {
+        self.effective = overrides.apply(&self.config)?;
+        self.overrides = overrides;
+        Ok(self)
+    }
+
+    /// Read the config file without an async runtime
+    ///
+    /// For setup that must happen before the runtime starts.
+    pub fn load_blocking() -> Result<Config> {
+        let config_path = Self::config_path()?;
+        let config = if config_path.exists() {
+            let content = std::fs::read_to_string(&config_path)?;
+            Self::format_of(&config_path).parse(&content)?
+        } else {
+            Config::default()
+        };
+
+        EnvOverrides::from_env().apply(&config)
+    }
+
     /// Check for config updates
     pub async fn check_for_updates(&mut self) -> Result<bool> {
         if !self.config_path.exists() {
-            return Ok(false);
+            // The file may have been converted to the other format
+            let Some(config_dir) = self.config_path.parent() else {
+                return Ok(false);
+            };
+            let found = Self::find_config(config_dir);
+            if !found.exists() {
+                return Ok(false);
+            }
+            self.format = Self::format_of(&found);
+            self.config_path = found;
+            self.last_modified = DateTime::<Utc>::MIN_UTC;
         }
 
         let metadata = fs::metadata(&self.config_path).await?;
@@ -49,32 +338,91 @@ impl ConfigManager {
         let modified = DateTime::<Utc>::from(modified);
 
         if modified > self.last_modified {
+            // An invalid file keeps the current config and is reported once
+            self.last_modified = modified;
             let (config, _) = Self::load_config(&self.config_path).await?;
+            self.effective = self.overrides.apply(&config)?;
             self.config = config;
-            self.last_modified = modified;
             return Ok(true);
         }
 
         Ok(false)
     }
 
-    /// Get current config
+    /// Get current config, with environment overrides applied
     pub fn get(&self) -> &Config {
+        &self.effective
+    }
+
+    /// Get the settings stored in the config file
+    pub fn file_config(&self) -> &Config {
         &self.config
     }
 
+    /// Get the environment variables overriding settings
+    pub fn overrides(&self) -> &EnvOverrides {
+        &self.overrides
+    }
+
+    /// Get the format of the config file
+    pub fn format(&self) -> ConfigFormat {
+        self.format
+    }
+
+    /// Get the path of the config file
+    pub fn path(&self) -> &Path {
+        &self.config_path
+    }
+
     /// Save config
-    pub async fn save(&self) -> Result<()> {
-        let json = serde_json::to_string_pretty(&self.config)?;
-        fs::write(&self.config_path, json).await?;
+    pub async fn save(&mut self) -> Result<()> {
+        let existing = fs::read_to_string(&self.config_path)
+            .await
+            .unwrap_or_default();
+        let content = self.format.update(&existing, &self.config)?;
+        fs::write(&self.config_path, content).await?;
+        self.effective = self.overrides.apply(&self.config)?;
         Ok(())
     }
 
+    /// Rewrite the config file in `format`
+    ///
+    /// The old file is kept with a `.bak` suffix. Returns its new path, or
+    /// `None` if there was no file to keep.
+    pub async fn convert(&mut self, format: ConfigFormat) -> Result<Option<PathBuf>> {
+        let config_dir = self
+            .config_path
+            .parent()
+            .ok_or_else(|| Error::Config("Config file has no directory".to_string()))?;
+        let new_path = config_dir.join(format.file_name());
+        let old_path = std::mem::replace(&mut self.config_path, new_path);
+        self.format = format;
+        self.save().await?;
+
+        if old_path == self.config_path || !old_path.exists() {
+            return Ok(None);
+        }
+
+        let mut backup = old_path.clone().into_os_string();
+        backup.push(".bak");
+        let backup = PathBuf::from(backup);
+        fs::rename(&old_path, &backup).await?;
+        Ok(Some(backup))
+    }
+
+    /// Replace every setting in the config file
+    pub async fn replace(&mut self, config: Config) -> Result<()> {
+        config.validate()?;
+        self.config = config;
+        self.save().await
+    }
+
     /// Add a sync directory
     pub async fn add_sync_dir(&mut self, source_path: String, remote_root: String) -> Result<()> {
         self.config.sync_dirs.push(crate::types::SyncDir {
             source_path,
             remote_root,
+            case_insensitive: None,
         });
         self.save().await?;
         Ok(())
@@ -93,6 +441,30 @@ impl ConfigManager {
         Ok(())
     }
 
+    /// Point a sync directory at a different remote root
+    pub async fn set_remote_root(&mut self, index: usize, remote_root: String) -> Result<()> {
+        let sync_dir = self
+            .config
+            .sync_dirs
+            .get_mut(index)
+            .ok_or_else(|| Error::Config(format!("Invalid sync directory index: {}", index)))?;
+        sync_dir.remote_root = remote_root;
+        self.save().await?;
+        Ok(())
+    }
+
+    /// Point a sync directory at a different local source
+    pub async fn set_source_path(&mut self, index: usize, source_path: String) -> Result<()> {
+        let sync_dir = self
+            .config
+            .sync_dirs
+            .get_mut(index)
+            .ok_or_else(|| Error::Config(format!("Invalid sync directory index: {}", index)))?;
+        sync_dir.source_path = source_path;
+        self.save().await?;
+        Ok(())
+    }
+
     /// Set sync concurrency
     pub async fn set_concurrency(&mut self, concurrency: usize) -> Result<()> {
         self.config.sync_concurrency = concurrency;
@@ -110,6 +482,37 @@ impl ConfigManager {
         Ok(())
     }
 
+    /// Apply a settings preset
+    pub async fn apply_preset(&mut self, preset: &crate::preset::Preset) -> Result<()> {
+        preset.apply(&mut self.config);
+        self.save().await?;
+        Ok(())
+    }
+
+    /// Allow or disallow nested sync directories
+    pub async fn set_allow_nested(&mut self, allow: bool) -> Result<()> {
+        self.config.allow_nested_sync_dirs = allow;
+        self.save().await?;
+        Ok(())
+    }
+
+    /// Enable or disable usage metrics, optionally changing the endpoint
+    pub async fn set_telemetry(&mut self, enabled: bool, endpoint: Option<String>) -> Result<()> {
+        self.config.telemetry = enabled;
+        if endpoint.is_some() {
+            self.config.telemetry_endpoint = endpoint;
+        }
+        self.save().await?;
+        Ok(())
+    }
+
+    /// Turn low-power mode on or off
+    pub async fn set_low_power(&mut self, enabled: bool) -> Result<()> {
+        self.config.low_power = enabled;
+        self.save().await?;
+        Ok(())
+    }
+
     /// Add an exclude pattern
     pub async fn add_exclude_pattern(&mut self, path: String, globs: Vec<String>) -> Result<()> {
         self.config
@@ -119,6 +522,55 @@ impl ConfigManager {
         Ok(())
     }
 
+    /// Add or replace the exclude pattern scoped to a path
+    pub async fn set_exclude_pattern(&mut self, path: String, globs: Vec<String>) -> Result<()> {
+        match self
+            .config
+            .exclude_patterns
+            .iter_mut()
+            .find(|p| p.path == path)
+        {
+            Some(existing) => existing.globs = globs,
+            None => self
+                .config
+                .exclude_patterns
+                .push(crate::types::ExcludePattern { path, globs }),
+        }
+        self.save().await?;
+        Ok(())
+    }
+
+    /// Append globs to the exclude pattern scoped to a path, skipping duplicates
+    pub async fn extend_exclude_pattern(&mut self, path: String, globs: Vec<String>) -> Result<()> {
+        let index = match self
+            .config
+            .exclude_patterns
+            .iter()
+            .position(|p| p.path == path)
+        {
+            Some(index) => index,
+            None => {
+                self.config
+                    .exclude_patterns
+                    .push(crate::types::ExcludePattern {
+                        path,
+                        globs: Vec::new(),
+                    });
+                self.config.exclude_patterns.len() - 1
+            }
+        };
+
+        let existing = &mut self.config.exclude_patterns[index].globs;
+        for glob in globs {
+            if !existing.contains(&glob) {
+                existing.push(glob);
+            }
+        }
+
+        self.save().await?;
+        Ok(())
+    }
+
     /// Remove an exclude pattern
     pub async fn remove_exclude_pattern(&mut self, index: usize) -> Result<()> {
         if index >= self.config.exclude_patterns.len() {
@@ -132,6 +584,27 @@ impl ConfigManager {
         Ok(())
     }
 
+    /// Get config file path
+    pub fn config_path() -> Result<PathBuf> {
+        Ok(Self::find_config(&Self::get_config_dir()?))
+    }
+
+    /// Config file in a directory: `config.toml` if it exists, else
+    /// `config.json`
+    fn find_config(config_dir: &Path) -> PathBuf {
+        let toml = config_dir.join(ConfigFormat::Toml.file_name());
+        if toml.exists() {
+            toml
+        } else {
+            config_dir.join(ConfigFormat::Json.file_name())
+        }
+    }
+
+    /// Format of a config file, by extension
+    fn format_of(path: &Path) -> ConfigFormat {
+        ConfigFormat::from_path(path).unwrap_or(ConfigFormat::Json)
+    }
+
     /// Get config directory path
     fn get_config_dir() -> Result<PathBuf> {
         let config_dir = dirs::config_dir()
@@ -143,7 +616,7 @@ impl ConfigManager {
     /// Load config from file
     async fn load_config(path: &PathBuf) -> Result<(Config, DateTime<Utc>)> {
         let content = fs::read_to_string(path).await?;
-        let config: Config = serde_json::from_str(&content)?;
+        let config = Self::format_of(path).parse(&content)?;
 
         let metadata = fs::metadata(path).await?;
         let modified = metadata.modified()?;
@@ -153,9 +626,35 @@ impl ConfigManager {
     }
 }
 
+/// Describe every sync directory whose source is inside another's
+///
+/// Identical sources count as nested. Returns one line per pair, or an
+/// empty list if no sources overlap.
+pub fn nested_sync_dirs(sync_dirs: &[SyncDir]) -> Vec<String> {
+    let mut nested = Vec::new();
+
+    for (i, outer) in sync_dirs.iter().enumerate() {
+        for (j, inner) in sync_dirs.iter().enumerate() {
+            let inside = Path::new(&inner.source_path).starts_with(&outer.source_path);
+            let same = inner.source_path == outer.source_path;
+
+            // Report identical sources once
+            if i != j && inside && (!same || i < j) {
+                nested.push(format!(
+                    "{} (-> {}) is inside {} (-> {})",
+                    inner.source_path, inner.remote_root, outer.source_path, outer.remote_root
+                ));
+            }
+        }
+    }
+
+    nested
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::TrashLocation;
     use std::fs;
     use tempfile::TempDir;
 
@@ -184,6 +683,31 @@ mod tests {
             remote_delete_behavior: crate::types::RemoteDeleteBehavior::Trash,
             dashboard_host: "127.0.0.1".to_string(),
             dashboard_port: 4242,
+            telemetry: false,
+            telemetry_endpoint: None,
+            change_detection: crate::types::ChangeDetection::Quick,
+            verify_uploads: false,
+            preset: None,
+            allow_nested_sync_dirs: false,
+            max_upload_memory: None,
+            low_power: false,
+            small_file_threshold: 8,
+            retry: crate::types::RetryPolicy::default(),
+            debounce_ms: 2000,
+            trash_retention_days: 30,
+            trash_location: TrashLocation::SyncDir,
+            storage_warning_percent: 90,
+            mime_overrides: Default::default(),
+            conflict_policy: Default::default(),
+            temp_file_patterns: Vec::new().into(),
+            history_retention_days: 30,
+            prune_remote: false,
+            remote_naming: Default::default(),
+            inhibit_sleep_during_upload: true,
+            timing: Default::default(),
+            logs: Default::default(),
+            credential_store: Default::default(),
+            credential_key_file: None,
         };
 
         let json = serde_json::to_string_pretty(&test_config).unwrap();
@@ -203,6 +727,7 @@ mod tests {
         config.sync_dirs.push(crate::types::SyncDir {
             source_path: "/local/path".to_string(),
             remote_root: "/remote/path".to_string(),
+            case_insensitive: None,
         });
 
         assert_eq!(config.sync_dirs.len(), 1);
@@ -217,10 +742,12 @@ mod tests {
         config.sync_dirs.push(crate::types::SyncDir {
             source_path: "/local/path1".to_string(),
             remote_root: "/remote/path1".to_string(),
+            case_insensitive: None,
         });
         config.sync_dirs.push(crate::types::SyncDir {
             source_path: "/local/path2".to_string(),
             remote_root: "/remote/path2".to_string(),
+            case_insensitive: None,
         });
 
         assert_eq!(config.sync_dirs.len(), 2);
@@ -254,6 +781,146 @@ mod tests {
         assert_eq!(config.exclude_patterns[0].globs.len(), 2);
     }
 
+    #[test]
+    fn test_env_overrides() {
+        let vars = |vars: &[(&str, &str)]| {
+            EnvOverrides::from_vars(
+                vars.iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string())),
+            )
+        };
+
+        let config = vars(&[
+            ("PDS_SYNC_CONCURRENCY", "8"),
+            ("PDS_DASHBOARD_HOST", "0.0.0.0"),
+            ("PDS_TIMING__RECONCILE", "15m"),
+            ("PDS_SYNC_CONCURENCY", "4"),
+            ("PDS_TIMING__NOPE", "1m"),
+            ("HOME", "/root"),
+        ]);
+        assert_eq!(
+            config.names().collect::<Vec<_>>(),
+            [
+                "PDS_DASHBOARD_HOST",
+                "PDS_SYNC_CONCURRENCY",
+                "PDS_TIMING__RECONCILE"
+            ]
+        );
+        assert_eq!(
+            config.unknown(),
+            ["PDS_SYNC_CONCURENCY", "PDS_TIMING__NOPE"]
+        );
+
+        let config = config.apply(&Config::default()).unwrap();
+        assert_eq!(config.sync_concurrency, 8);
+        assert_eq!(config.dashboard_host, "0.0.0.0");
+        assert_eq!(
+            config.timing.reconcile.map(|d| d.0),
+            Some(std::time::Duration::from_secs(15 * 60))
+        );
+
+        let err = vars(&[("PDS_SYNC_CONCURRENCY", "many")])
+            .apply(&Config::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("PDS_SYNC_CONCURRENCY"));
+    }
+
+    #[test]
+    fn test_config_format_round_trip() {
+        let mut config = Config::default();
+        config.sync_dirs.push(SyncDir {
+            source_path: "/home/user/Docs".to_string(),
+            remote_root: "/Docs".to_string(),
+            case_insensitive: None,
+        });
+        config
+            .mime_overrides
+            .insert("md".to_string(), "text/markdown".to_string());
+
+        for format in [ConfigFormat::Json, ConfigFormat::Toml] {
+            let content = format.to_string(&config).unwrap();
+            let parsed = format.parse(&content).unwrap();
+            assert_eq!(
+                serde_json::to_value(&parsed).unwrap(),
+                serde_json::to_value(&config).unwrap()
+            );
+        }
+
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("backup.TOML")),
+            Some(ConfigFormat::Toml)
+        );
+        assert_eq!(ConfigFormat::from_path(Path::new("backup")), None);
+    }
+
+    #[test]
+    fn test_toml_update_keeps_comments() {
+        let existing = "# Sync settings\nsync_dirs = []\nsync_concurrency = 4 # cores\n\n[logs]\n# Keep two weeks\nretention_days = 14\n";
+        let mut config = ConfigFormat::Toml.parse(existing).unwrap();
+        config.sync_concurrency = 8;
+
+        let updated = ConfigFormat::Toml.update(existing, &config).unwrap();
+        assert!(updated.contains("# Sync settings"));
+        assert!(updated.contains("sync_concurrency = 8 # cores"));
+        assert!(updated.contains("# Keep two weeks\nretention_days = 14"));
+        let parsed = ConfigFormat::Toml.parse(&updated).unwrap();
+        assert_eq!(parsed.sync_concurrency, 8);
+        assert_eq!(parsed.logs.retention_days, 14);
+    }
+
+    #[tokio::test]
+    async fn test_convert_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let json_path = temp_dir.path().join("config.json");
+        let config = Config {
+            sync_concurrency: 6,
+            ..Default::default()
+        };
+
+        let mut manager = ConfigManager::with_config(json_path.clone(), config);
+        manager.save().await.unwrap();
+
+        let backup = manager.convert(ConfigFormat::Toml).await.unwrap();
+        assert_eq!(backup, Some(temp_dir.path().join("config.json.bak")));
+        assert!(!json_path.exists());
+        assert_eq!(manager.format(), ConfigFormat::Toml);
+        assert_eq!(ConfigManager::find_config(temp_dir.path()), manager.path());
+
+        let content = fs::read_to_string(manager.path()).unwrap();
+        assert_eq!(
+            ConfigFormat::Toml.parse(&content).unwrap().sync_concurrency,
+            6
+        );
+    }
+
+    #[test]
+    fn test_nested_sync_dirs() {
+        let dir = |source: &str, remote: &str| SyncDir {
+            source_path: source.to_string(),
+            remote_root: remote.to_string(),
+            case_insensitive: None,
+        };
+
+        assert!(nested_sync_dirs(&[
+            dir("/home/user/Docs", "/Docs"),
+            dir("/home/user/Docs2", "/Docs2"),
+        ])
+        .is_empty());
+
+        assert_eq!(
+            nested_sync_dirs(&[
+                dir("/home/user/Docs/Work", "/Work"),
+                dir("/home/user/Docs", "/Docs"),
+            ]),
+            vec!["/home/user/Docs/Work (-> /Work) is inside /home/user/Docs (-> /Docs)"]
+        );
+
+        assert_eq!(
+            nested_sync_dirs(&[dir("/data", "/A"), dir("/data", "/B")]).len(),
+            1
+        );
+    }
+
     #[tokio::test]
     async fn test_remote_delete_behavior() {
         let config1 = Config {
@@ -263,6 +930,31 @@ mod tests {
             remote_delete_behavior: crate::types::RemoteDeleteBehavior::Trash,
             dashboard_host: "127.0.0.1".to_string(),
             dashboard_port: 4242,
+            telemetry: false,
+            telemetry_endpoint: None,
+            change_detection: crate::types::ChangeDetection::Quick,
+            verify_uploads: false,
+            preset: None,
+            allow_nested_sync_dirs: false,
+            max_upload_memory: None,
+            low_power: false,
+            small_file_threshold: 8,
+            retry: crate::types::RetryPolicy::default(),
+            debounce_ms: 2000,
+            trash_retention_days: 30,
+            trash_location: TrashLocation::SyncDir,
+            storage_warning_percent: 90,
+            mime_overrides: Default::default(),
+            conflict_policy: Default::default(),
+            temp_file_patterns: Vec::new().into(),
+            history_retention_days: 30,
+            prune_remote: false,
+            remote_naming: Default::default(),
+            inhibit_sleep_during_upload: true,
+            timing: Default::default(),
+            logs: Default::default(),
+            credential_store: Default::default(),
+            credential_key_file: None,
         };
 
         let config2 = Config {
@@ -272,6 +964,31 @@ mod tests {
             remote_delete_behavior: crate::types::RemoteDeleteBehavior::Permanent,
             dashboard_host: "127.0.0.1".to_string(),
             dashboard_port: 4242,
+            telemetry: false,
+            telemetry_endpoint: None,
+            change_detection: crate::types::ChangeDetection::Quick,
+            verify_uploads: false,
+            preset: None,
+            allow_nested_sync_dirs: false,
+            max_upload_memory: None,
+            low_power: false,
+            small_file_threshold: 8,
+            retry: crate::types::RetryPolicy::default(),
+            debounce_ms: 2000,
+            trash_retention_days: 30,
+            trash_location: TrashLocation::SyncDir,
+            storage_warning_percent: 90,
+            mime_overrides: Default::default(),
+            conflict_policy: Default::default(),
+            temp_file_patterns: Vec::new().into(),
+            history_retention_days: 30,
+            prune_remote: false,
+            remote_naming: Default::default(),
+            inhibit_sleep_during_upload: true,
+            timing: Default::default(),
+            logs: Default::default(),
+            credential_store: Default::default(),
+            credential_key_file: None,
         };
 
         assert_eq!(
@@ -0,0 +1,91 @@
+//! Network connectivity detection
+//!
+//! Without a network every job fails, and each failure spends one of the
+//! job's retries until it is blocked. The engine instead checks whether the
+//! Proton API is reachable and stops processing while it is not. The check
+//! is a `HEAD` request to the API base through the client's own proxy
+//! settings: cheap, and it fails the same way requests would.
+
+use reqwest::Client;
+use std::time::Duration;
+
+/// How long one probe may take before it counts as a failure
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Consecutive failed probes before the machine counts as offline
+///
+/// A single dropped packet should not pause syncing.
+const FAILURES_BEFORE_OFFLINE: u32 = 2;
+
+/// Whether the server at `url` answers through `client`
+///
+/// Any response counts, even an error status: the network, any proxy and
+/// the server are all up.
+pub async fn probe(client: &Client, url: &str) -> bool {
+    client.head(url).timeout(CHECK_TIMEOUT).send().await.is_ok()
+}
+
+/// Turns individual probe results into an online/offline verdict
+///
+/// Going offline takes several failures in a row; one success is enough to
+/// be back online.
+#[derive(Debug, Default)]
+pub struct ConnectivityMonitor {
+    failures: u32,
+}
+
+impl ConnectivityMonitor {
+    /// Create a monitor that starts out online
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a probe result and return whether the machine is online
+    pub fn record(&mut self, reachable: bool) -> bool {
+        if reachable {
+            self.failures = 0;
+        } else {
+            self.failures = self.failures.saturating_add(1);
+        }
+
+        self.is_online()
+    }
+
+    /// Whether the machine currently counts as online
+    pub fn is_online(&self) -> bool {
+        self.failures < FAILURES_BEFORE_OFFLINE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offline_after_consecutive_failures() {
+        let mut monitor = ConnectivityMonitor::new();
+        assert!(monitor.is_online());
+
+        assert!(monitor.record(false));
+        assert!(monitor.record(true));
+        assert!(monitor.record(false));
+        assert!(!monitor.record(false));
+        assert!(!monitor.record(false));
+
+        assert!(monitor.record(true));
+    }
+
+    #[tokio::test]
+    async fn test_probe_unreachable_server() {
+        let client = Client::new();
+        let server = wiremock::MockServer::start().await;
+
+        // An error status still means the server is reachable
+        assert!(probe(&client, &server.uri()).await);
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        assert!(!probe(&client, &url).await);
+    }
+}
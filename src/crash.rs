@@ -0,0 +1,226 @@
+//! Crash reporting
+//!
+//! A panic hook writes a crash bundle (panic message, backtrace, recent log
+//! tail, sanitized config and queue summary) to `<data dir>/crashes/`.
+//! Bundles are packed into a shareable tarball with `report pack`; secrets
+//! and the home directory are redacted from everything that is written.
+
+use crate::config::{ConfigFormat, ConfigManager};
+use crate::db::Db;
+use crate::error::Result;
+use crate::paths::{get_crash_dir, get_data_dir, get_log_dir};
+use crate::queue::{JobQueue, StatusCounts};
+use chrono::Utc;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Number of log lines included in a report
+const LOG_TAIL_LINES: usize = 200;
+
+/// How long collecting the queue summary may take
+const QUEUE_SUMMARY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Replacement for redacted values
+const REDACTED: &str = "[REDACTED]";
+
+/// Install the panic hook that writes crash bundles
+///
+/// The previous hook still runs afterwards, so the panic is printed as usual.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        match write_crash_bundle(&info.to_string()) {
+            Ok(dir) => eprintln!(
+                "Crash report written to {}\nRun `proton-drive-sync report pack` to create an archive for a bug report.",
+                dir.display()
+            ),
+            Err(e) => eprintln!("Failed to write crash report: {}", e),
+        }
+
+        default_hook(info);
+    }));
+}
+
+/// Write a crash bundle for a panic and return its directory
+fn write_crash_bundle(panic_message: &str) -> Result<PathBuf> {
+    let dir = get_crash_dir()?.join(format!("crash-{}", Utc::now().format("%Y%m%d-%H%M%S%.3f")));
+    std::fs::create_dir_all(&dir)?;
+
+    let thread = std::thread::current();
+    let panic = format!(
+        "thread: {}\n{}\n",
+        thread.name().unwrap_or("<unnamed>"),
+        panic_message
+    );
+    std::fs::write(dir.join("panic.txt"), redact(&panic))?;
+
+    let backtrace = std::backtrace::Backtrace::force_capture();
+    std::fs::write(dir.join("backtrace.txt"), redact(&backtrace.to_string()))?;
+
+    for (name, content) in diagnostic_files(queue_summary_blocking()) {
+        std::fs::write(dir.join(name), content)?;
+    }
+
+    Ok(dir)
+}
+
+/// Diagnostics included in every crash bundle and report archive
+pub fn diagnostic_files(queue: Option<StatusCounts>) -> Vec<(&'static str, String)> {
+    let queue = match queue {
+        Some(counts) => serde_json::to_string_pretty(&counts).unwrap_or_default(),
+        None => "null".to_string(),
+    };
+
+    vec![
+        ("system.txt", system_info()),
+        ("log-tail.txt", redact(&log_tail().unwrap_or_default())),
+        ("config.json", sanitized_config().unwrap_or_default()),
+        ("queue.json", queue),
+    ]
+}
+
+/// Version and platform information
+fn system_info() -> String {
+    format!(
+        "version: {}\nos: {}\narch: {}\ntime: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        Utc::now().to_rfc3339()
+    )
+}
+
+/// Last lines of the most recent log file
+fn log_tail() -> Option<String> {
+    let log_dir = get_log_dir().ok()?;
+
+    let latest = std::fs::read_dir(log_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with("proton-drive-sync.log")
+        })
+        .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())?;
+
+    let content = std::fs::read_to_string(latest.path()).ok()?;
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines.len().saturating_sub(LOG_TAIL_LINES);
+
+    Some(lines[start..].join("\n"))
+}
+
+/// Config file with local paths redacted
+fn sanitized_config() -> Option<String> {
+    let path = ConfigManager::config_path().ok()?;
+    let content = std::fs::read_to_string(&path).ok()?;
+    let value: serde_json::Value = match ConfigFormat::from_path(&path) {
+        Some(ConfigFormat::Toml) => toml::from_str(&content).ok()?,
+        _ => serde_json::from_str(&content).ok()?,
+    };
+    Some(redact(&serde_json::to_string_pretty(&value).ok()?))
+}
+
+/// Queue counts, or `None` if the database cannot be read quickly
+pub async fn queue_summary() -> Option<StatusCounts> {
+    let collect = async {
+        let db_path = get_data_dir().ok()?.join("proton-drive-sync.db");
+        let db = Db::open_existing_read_only(db_path).await.ok()??;
+        JobQueue::new(db).get_status_counts().await.ok()
+    };
+
+    tokio::time::timeout(QUEUE_SUMMARY_TIMEOUT, collect)
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Queue counts collected from inside the panic hook
+///
+/// Runs on a fresh thread with its own runtime, since the panicking thread
+/// may be a runtime worker.
+fn queue_summary_blocking() -> Option<StatusCounts> {
+    std::thread::spawn(|| {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .ok()?;
+        runtime.block_on(queue_summary())
+    })
+    .join()
+    .ok()
+    .flatten()
+}
+
+/// Remove credentials and the user's home directory from text
+pub fn redact(text: &str) -> String {
+    static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+
+    let patterns = PATTERNS.get_or_init(|| {
+        [
+            r"(?i)(bearer\s+)[A-Za-z0-9._~+/=-]+",
+            // Quoted values up to their closing quote, then bare ones
+            r#"(?i)("?(?:access_?token|refresh_?token|password|passphrase|key_?password|primary_?key|private_?key|secret|uid)"?\s*[:=]\s*")(?:[^"\\]|\\.)*"#,
+            r#"(?i)((?:access_?token|refresh_?token|password|passphrase|key_?password|primary_?key|private_?key|secret|uid)"?\s*[:=]\s*)[^"\s,}]+"#,
+        ]
+        .iter()
+        .map(|p| Regex::new(p).expect("valid redaction pattern"))
+        .collect()
+    });
+
+    let mut redacted = text.to_string();
+    for pattern in patterns {
+        redacted = pattern
+            .replace_all(&redacted, format!("${{1}}{}", REDACTED))
+            .into_owned();
+    }
+
+    match dirs::home_dir() {
+        Some(home) => redact_home(&redacted, &home),
+        None => redacted,
+    }
+}
+
+/// Replace the home directory prefix of paths with `~`
+fn redact_home(text: &str, home: &Path) -> String {
+    let home = home.to_string_lossy();
+    if home.len() <= 1 {
+        return text.to_string();
+    }
+    text.replace(home.as_ref(), "~")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_tokens() {
+        let text = r#"Authorization: Bearer abc.def-123 {"access_token":"secret1","refresh_token": "secret2","key_password":"two words \" quoted","name":"ok"} password=hunter2"#;
+        let redacted = redact(text);
+
+        assert!(!redacted.contains("abc.def-123"));
+        assert!(!redacted.contains("secret1"));
+        assert!(!redacted.contains("secret2"));
+        assert!(!redacted.contains("hunter2"));
+        assert!(!redacted.contains("words"));
+        assert!(!redacted.contains("quoted"));
+        assert!(redacted.contains(r#""key_password":"[REDACTED]","#));
+        assert!(redacted.contains(r#""name":"ok""#));
+        assert!(redacted.contains("Bearer [REDACTED]"));
+    }
+
+    #[test]
+    fn test_redact_home() {
+        let text = "/home/alice/Documents -> /My Files/Documents";
+        assert_eq!(
+            redact_home(text, Path::new("/home/alice")),
+            "~/Documents -> /My Files/Documents"
+        );
+    }
+}
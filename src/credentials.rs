@@ -0,0 +1,561 @@
+//! Storage for the session's tokens
+//!
+//! Sessions live in the system keyring by default. Headless machines often
+//! have no keyring, so `"credential_store": "file"` keeps them in a file in
+//! the data directory instead, encrypted with ChaCha20-Poly1305 under a key
+//! derived with Argon2id from a passphrase or the contents of a key file.
+//!
+//! Proton issues a new refresh token with every refresh, so the session in
+//! use is kept in a [`SessionStore`] shared by all clients of a process and
+//! written back to its [`CredentialBackend`] each time it is refreshed.
+
+use crate::config::ConfigManager;
+use crate::error::{Error, Result};
+use crate::paths::get_data_dir;
+use crate::types::{Config, CredentialStore, Session};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use keyring::Entry;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Keyring service holding the session
+const KEYRING_SERVICE: &str = "proton-drive-sync";
+
+/// Keyring entry holding the session
+const KEYRING_USER: &str = "credentials";
+
+/// Encrypted credential file in the data directory
+const CREDENTIALS_FILE: &str = "credentials.enc";
+
+/// Environment variable holding the passphrase for the credential file
+pub const PASSPHRASE_ENV: &str = "PROTON_DRIVE_SYNC_PASSPHRASE";
+
+/// Version of the credential file layout
+const FILE_VERSION: u32 = 1;
+
+/// Attempts at saving a refreshed session before giving up
+const SAVE_ATTEMPTS: u32 = 3;
+
+/// Wait before retrying a failed save, doubled after each attempt
+const SAVE_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Contents of the credential file
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedFile {
+    version: u32,
+    /// Argon2id salt, base64
+    salt: String,
+    /// ChaCha20-Poly1305 nonce, base64
+    nonce: String,
+    /// Encrypted session JSON, base64
+    ciphertext: String,
+}
+
+/// Where sessions are saved: the keyring or the encrypted credential file
+#[derive(Debug)]
+pub enum CredentialBackend {
+    Keyring,
+    File(FileBackend),
+}
+
+/// The encrypted credential file, with the secret it is encrypted with
+pub struct FileBackend {
+    path: PathBuf,
+    secret: Vec<u8>,
+    /// Key derived from the secret, reused so saves skip Argon2id
+    key: std::sync::Mutex<Option<DerivedKey>>,
+}
+
+impl std::fmt::Debug for FileBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileBackend")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Cipher key and the salt it was derived with
+struct DerivedKey {
+    salt: Vec<u8>,
+    key: Key,
+}
+
+impl DerivedKey {
+    /// Derive a key from a secret with a fresh salt
+    fn generate(secret: &[u8]) -> Result<Self> {
+        let mut salt = vec![0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let key = derive_key(secret, &salt)?;
+        Ok(Self { salt, key })
+    }
+}
+
+impl CredentialBackend {
+    /// The backend `config` selects
+    ///
+    /// The credential file's key file or passphrase is read here, once.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match config.credential_store {
+            CredentialStore::Keyring => Ok(Self::Keyring),
+            CredentialStore::File => Ok(Self::File(FileBackend::new(
+                credentials_path()?,
+                file_key(config)?,
+            ))),
+        }
+    }
+
+    /// The backend of the config file
+    pub fn configured() -> Result<Self> {
+        Self::from_config(&ConfigManager::load_blocking()?)
+    }
+
+    /// Save a session
+    pub fn save(&self, session: &Session) -> Result<()> {
+        let json = serde_json::to_string(session)?;
+        match self {
+            Self::Keyring => Entry::new(KEYRING_SERVICE, KEYRING_USER)?.set_password(&json)?,
+            Self::File(file) => file.save(json.as_bytes())?,
+        }
+        Ok(())
+    }
+
+    /// Load the saved session
+    pub fn load(&self) -> Result<Session> {
+        let json = match self {
+            Self::Keyring => Entry::new(KEYRING_SERVICE, KEYRING_USER)?.get_password()?,
+            Self::File(file) => file.load()?,
+        };
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+impl FileBackend {
+    pub fn new(path: PathBuf, secret: Vec<u8>) -> Self {
+        Self {
+            path,
+            secret,
+            key: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Encrypt and write the session JSON
+    fn save(&self, json: &[u8]) -> Result<()> {
+        let mut cached = self.key.lock().unwrap();
+        let key = match cached.take() {
+            Some(key) => key,
+            None => DerivedKey::generate(&self.secret)?,
+        };
+        let content = encrypt(json, &key);
+        *cached = Some(key);
+        write_private(&self.path, &content?)
+    }
+
+    /// Read and decrypt the session JSON
+    fn load(&self) -> Result<String> {
+        let content = std::fs::read_to_string(&self.path).map_err(|_| {
+            Error::Auth(format!(
+                "No credentials in {}. Run `proton-drive-sync auth login` first.",
+                self.path.display()
+            ))
+        })?;
+        let (plaintext, key) = decrypt(&content, &self.secret)?;
+        *self.key.lock().unwrap() = Some(key);
+        String::from_utf8(plaintext)
+            .map_err(|_| Error::Encryption("Credential file is corrupt".to_string()))
+    }
+}
+
+/// The session in use, shared by every client that clones the store
+///
+/// A refresh through one client replaces the tokens for all of them, so none
+/// is left with a refresh token the server no longer accepts.
+#[derive(Debug, Clone)]
+pub struct SessionStore(Arc<SessionStoreInner>);
+
+#[derive(Debug)]
+struct SessionStoreInner {
+    session: RwLock<Session>,
+    /// Held while refreshing, so clients sharing the store refresh once
+    refresh_lock: Mutex<()>,
+    /// Where updates are saved, if anywhere
+    backend: Option<CredentialBackend>,
+    /// Refresh token last loaded from or saved to the backend
+    saved_refresh_token: RwLock<Option<String>>,
+}
+
+impl SessionStore {
+    /// Store whose updates are saved to `backend`
+    pub fn new(session: Session, backend: CredentialBackend) -> Self {
+        Self::with_backend(session, Some(backend))
+    }
+
+    /// Store whose updates are kept in memory only
+    pub fn in_memory(session: Session) -> Self {
+        Self::with_backend(session, None)
+    }
+
+    fn with_backend(session: Session, backend: Option<CredentialBackend>) -> Self {
+        let saved_refresh_token = backend.as_ref().map(|_| session.refresh_token.clone());
+        Self(Arc::new(SessionStoreInner {
+            session: RwLock::new(session),
+            refresh_lock: Mutex::new(()),
+            backend,
+            saved_refresh_token: RwLock::new(saved_refresh_token),
+        }))
+    }
+
+    /// Copy of the current session
+    pub fn get(&self) -> Session {
+        self.0.session.read().unwrap().clone()
+    }
+
+    /// Current access token
+    pub fn access_token(&self) -> String {
+        self.0.session.read().unwrap().access_token.clone()
+    }
+
+    /// Take the refresh lock
+    pub async fn lock_refresh(&self) -> MutexGuard<'_, ()> {
+        self.0.refresh_lock.lock().await
+    }
+
+    /// Put a session saved by another process in effect
+    ///
+    /// Another process refreshing the session spends the refresh token in
+    /// use here, so a saved session with a different refresh token than the
+    /// one last saved or loaded replaces it. Call with the refresh lock
+    /// held. Returns whether the saved session was adopted.
+    pub async fn adopt_saved(&self) -> bool {
+        if self.0.backend.is_none() {
+            return false;
+        }
+
+        let store = self.clone();
+        let loaded = tokio::task::spawn_blocking(move || match &store.0.backend {
+            Some(backend) => backend.load().map(Some),
+            None => Ok(None),
+        })
+        .await;
+        let saved = match loaded {
+            Ok(Ok(Some(saved))) => saved,
+            Ok(Ok(None)) => return false,
+            Ok(Err(e)) => {
+                tracing::debug!("Could not read the saved session: {}", e);
+                return false;
+            }
+            Err(e) => {
+                tracing::debug!("Reading the saved session panicked: {}", e);
+                return false;
+            }
+        };
+
+        let mut last_saved = self.0.saved_refresh_token.write().unwrap();
+        if last_saved.as_deref() == Some(saved.refresh_token.as_str()) {
+            return false;
+        }
+
+        tracing::info!("Using the session refreshed by another process");
+        *last_saved = Some(saved.refresh_token.clone());
+        *self.0.session.write().unwrap() = saved;
+        true
+    }
+
+    /// Put a refreshed session in effect and save it
+    ///
+    /// The new tokens are used right away. Saving is retried with backoff;
+    /// if it still fails, the error is returned and the tokens live on in
+    /// memory until the process exits.
+    pub async fn update(&self, session: Session) -> Result<()> {
+        *self.0.session.write().unwrap() = session.clone();
+        if self.0.backend.is_none() {
+            return Ok(());
+        }
+
+        let mut delay = SAVE_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            let to_save = session.clone();
+            let store = self.clone();
+            let result = tokio::task::spawn_blocking(move || match &store.0.backend {
+                Some(backend) => backend.save(&to_save),
+                None => Ok(()),
+            })
+            .await
+            .map_err(|e| Error::Auth(format!("Saving the session panicked: {}", e)))
+            .and_then(|result| result);
+
+            match result {
+                Ok(()) => {
+                    *self.0.saved_refresh_token.write().unwrap() = Some(session.refresh_token);
+                    return Ok(());
+                }
+                Err(e) if attempt >= SAVE_ATTEMPTS => return Err(e),
+                Err(e) => {
+                    tracing::debug!(
+                        "Failed to save refreshed session (attempt {}): {}",
+                        attempt,
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Save a session to the configured store, as after logging in
+pub fn save_session(session: &Session) -> Result<()> {
+    CredentialBackend::configured()?.save(session)
+}
+
+/// Load the session from the configured store
+///
+/// Refreshed sessions are saved back to the same store.
+pub fn load_session() -> Result<SessionStore> {
+    let backend = CredentialBackend::configured()?;
+    let session = backend.load()?;
+    Ok(SessionStore::new(session, backend))
+}
+
+/// Remove the session from both stores
+pub fn delete_session() -> Result<()> {
+    if let Ok(entry) = Entry::new(KEYRING_SERVICE, KEYRING_USER) {
+        let _ = entry.delete_credential();
+    }
+
+    let path = credentials_path()?;
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Whether the configured store holds a session
+///
+/// The credential file only has to exist; it isn't decrypted.
+pub fn has_session() -> bool {
+    let Ok(config) = ConfigManager::load_blocking() else {
+        return false;
+    };
+
+    match config.credential_store {
+        CredentialStore::Keyring => Entry::new(KEYRING_SERVICE, KEYRING_USER)
+            .ok()
+            .and_then(|e| e.get_password().ok())
+            .is_some(),
+        CredentialStore::File => credentials_path().is_ok_and(|p| p.exists()),
+    }
+}
+
+/// Path of the encrypted credential file
+pub fn credentials_path() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join(CREDENTIALS_FILE))
+}
+
+/// Secret the credential file is encrypted with: the key file's contents,
+/// or the passphrase from the environment
+fn file_key(config: &Config) -> Result<Vec<u8>> {
+    if let Some(key_file) = &config.credential_key_file {
+        let key = std::fs::read(key_file).map_err(|e| {
+            Error::Auth(format!(
+                "Could not read credential key file {}: {}",
+                key_file, e
+            ))
+        })?;
+        if key.is_empty() {
+            return Err(Error::Auth(format!(
+                "Credential key file {} is empty",
+                key_file
+            )));
+        }
+        return Ok(key);
+    }
+
+    match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) if !passphrase.is_empty() => Ok(passphrase.into_bytes()),
+        _ => Err(Error::Auth(format!(
+            "The file credential store needs a passphrase in {} or a \"credential_key_file\"",
+            PASSPHRASE_ENV
+        ))),
+    }
+}
+
+/// Encrypt `plaintext` into the credential file format, with a fresh nonce
+fn encrypt(plaintext: &[u8], key: &DerivedKey) -> Result<String> {
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let cipher = ChaCha20Poly1305::new(&key.key);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| Error::Encryption("Could not encrypt credentials".to_string()))?;
+
+    Ok(serde_json::to_string_pretty(&EncryptedFile {
+        version: FILE_VERSION,
+        salt: BASE64.encode(&key.salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })?)
+}
+
+/// Decrypt the contents of a credential file
+///
+/// Returns the key it was encrypted with, for saving again.
+fn decrypt(content: &str, secret: &[u8]) -> Result<(Vec<u8>, DerivedKey)> {
+    let corrupt = || Error::Encryption("Credential file is corrupt".to_string());
+
+    let file: EncryptedFile = serde_json::from_str(content).map_err(|_| corrupt())?;
+    if file.version != FILE_VERSION {
+        return Err(Error::Encryption(format!(
+            "Unsupported credential file version {}",
+            file.version
+        )));
+    }
+    let salt = BASE64.decode(&file.salt).map_err(|_| corrupt())?;
+    let nonce = BASE64.decode(&file.nonce).map_err(|_| corrupt())?;
+    let ciphertext = BASE64.decode(&file.ciphertext).map_err(|_| corrupt())?;
+    if nonce.len() != 12 {
+        return Err(corrupt());
+    }
+
+    let key = derive_key(secret, &salt)?;
+    let plaintext = ChaCha20Poly1305::new(&key)
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| {
+            Error::Auth("Could not decrypt credentials: wrong passphrase or key file".to_string())
+        })?;
+    Ok((plaintext, DerivedKey { salt, key }))
+}
+
+/// Derive the cipher key from a secret with Argon2id
+fn derive_key(secret: &[u8], salt: &[u8]) -> Result<Key> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(secret, salt, &mut key)
+        .map_err(|e| Error::Encryption(format!("Key derivation failed: {}", e)))?;
+    Ok(key)
+}
+
+/// Write a file only its owner can read
+///
+/// The content goes to a temporary file that then replaces `path`, so a
+/// crash never leaves a half-written file behind.
+fn write_private(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(&tmp_path)?;
+    std::io::Write::write_all(&mut file, content.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(token: &str) -> Session {
+        Session {
+            uid: "test_uid".to_string(),
+            access_token: token.to_string(),
+            refresh_token: format!("{}_refresh", token),
+            key_password: None,
+            primary_key: None,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let key = DerivedKey::generate(b"correct horse").unwrap();
+        let content = encrypt(b"{\"uid\":\"abc\"}", &key).unwrap();
+        assert!(!content.contains("abc"));
+        let (plaintext, derived) = decrypt(&content, b"correct horse").unwrap();
+        assert_eq!(plaintext, b"{\"uid\":\"abc\"}");
+        assert_eq!(derived.salt, key.salt);
+
+        let Err(err) = decrypt(&content, b"wrong horse") else {
+            panic!("decrypted with the wrong secret");
+        };
+        assert!(err.to_string().contains("wrong passphrase"));
+
+        assert!(decrypt("not json", b"correct horse").is_err());
+    }
+
+    #[test]
+    fn test_file_backend_reuses_its_key() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(CREDENTIALS_FILE);
+        let backend = FileBackend::new(path.clone(), b"correct horse".to_vec());
+        let backend = CredentialBackend::File(backend);
+
+        backend.save(&session("first")).unwrap();
+        let first: EncryptedFile =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        backend.save(&session("second")).unwrap();
+        let second: EncryptedFile =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(first.salt, second.salt);
+        assert_ne!(first.nonce, second.nonce);
+
+        let reopened = FileBackend::new(path, b"correct horse".to_vec());
+        assert_eq!(
+            CredentialBackend::File(reopened)
+                .load()
+                .unwrap()
+                .access_token,
+            "second"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_store_is_shared() {
+        let store = SessionStore::in_memory(session("old"));
+        let shared = store.clone();
+        store.update(session("new")).await.unwrap();
+
+        assert_eq!(shared.access_token(), "new");
+        assert_eq!(shared.get().refresh_token, "new_refresh");
+    }
+
+    #[test]
+    fn test_write_private() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("data").join(CREDENTIALS_FILE);
+        write_private(&path, "secret").unwrap();
+        write_private(&path, "rotated").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "rotated");
+        assert!(!path.with_extension("enc.tmp").exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
@@ -1,62 +1,483 @@
 //! Web dashboard for Proton Drive Sync
 
+use crate::cache::{Cached, STATUS_CACHE_TTL};
 use crate::config::ConfigManager;
+use crate::db::Db;
 use crate::error::Result;
+use crate::ipc::{send_request, IpcRequest, IpcResponse};
+use crate::proton::ProtonClient;
+use crate::queue::{JobQueue, StatusCounts};
+use crate::sync::SyncState;
+use crate::types::{AccountInfo, JobProgress, SyncJobStatus};
 use axum::{
-    extract::State,
-    response::{Html, IntoResponse, Json},
-    routing::get,
+    extract::{Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Json,
+    },
+    routing::{get, post},
     Router,
 };
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use std::convert::Infallible;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
-use tracing::info;
+use tracing::{info, warn};
+
+/// How often the live job feed polls the database
+const EVENT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Maximum job updates sent per poll
+const EVENT_BATCH_SIZE: i64 = 200;
+
+/// Maximum blocked jobs listed in the dashboard
+const BLOCKED_JOBS_LIMIT: i64 = 100;
+
+/// Maximum sync history entries listed in the dashboard
+const HISTORY_LIMIT: i64 = 500;
+
+/// Header carrying the action token on requests that change state
+const ACTION_TOKEN_HEADER: &str = "x-dashboard-token";
+
+/// Placeholder in the dashboard page replaced by the action token
+const ACTION_TOKEN_PLACEHOLDER: &str = "__ACTION_TOKEN__";
+
+/// Badge colors for each queue state
+const BADGE_OK: &str = "#4c1";
+const BADGE_BUSY: &str = "#dfb317";
+const BADGE_ERROR: &str = "#e05d44";
+const BADGE_UNKNOWN: &str = "#9f9f9f";
+
+/// Age of the last successful API call above which `/readyz` fails
+///
+/// Remote changes are pulled every few minutes even when nothing is queued,
+/// so a running engine stays well within it.
+const READY_MAX_CONTACT_AGE: Duration = Duration::from_secs(15 * 60);
+
+/// How long account info is served before asking the API again
+///
+/// Storage usage changes slowly, and the status endpoint is polled often.
+const ACCOUNT_CACHE_TTL: Duration = Duration::from_secs(300);
 
 /// Dashboard state
 #[derive(Clone)]
 pub struct DashboardState {
     pub config: Arc<Mutex<ConfigManager>>,
+    /// Handle for the few endpoints that modify jobs
+    pub db: Db,
+    /// Read-only handle for status queries
+    pub reader: Db,
+    /// Queue over the read-only handle, sharing cached status counts
+    pub queue: JobQueue,
+    /// Status summary shared by all clients
+    pub status: Arc<Cached<serde_json::Value>>,
+    /// API client for account info, if logged in
+    pub client: Option<Arc<ProtonClient>>,
+    /// Account info shared by all clients
+    pub account: Arc<Cached<Option<AccountInfo>>>,
+    /// Secret the dashboard page sends back with pause, resume and retry
+    ///
+    /// Generated per server start and only readable from the page itself, so
+    /// other sites can't forge those requests.
+    pub action_token: Arc<str>,
 }
 
 /// Start the dashboard server
 pub async fn start_dashboard(
     config: Arc<Mutex<ConfigManager>>,
+    db: Db,
+    reader: Db,
+    client: Option<ProtonClient>,
     host: String,
     port: u16,
 ) -> Result<()> {
-    let state = DashboardState { config };
+    let state = DashboardState {
+        config,
+        db,
+        queue: JobQueue::new(reader.clone()),
+        reader,
+        status: Arc::new(Cached::new(STATUS_CACHE_TTL)),
+        client: client.map(Arc::new),
+        account: Arc::new(Cached::new(ACCOUNT_CACHE_TTL)),
+        action_token: new_action_token().into(),
+    };
+
+    let listener = tokio::net::TcpListener::bind(format!("{}:{}", host, port)).await?;
+    info!("Dashboard listening on http://{}:{}", host, port);
+
+    axum::serve(listener, router(state)).await?;
+
+    Ok(())
+}
 
-    let app = Router::new()
+/// Random token for [`DashboardState::action_token`]
+fn new_action_token() -> String {
+    let bytes: [u8; 32] = rand::random();
+    hex::encode(bytes)
+}
+
+/// Routes of the dashboard
+fn router(state: DashboardState) -> Router {
+    let actions = Router::new()
+        .route("/api/pause", post(pause_sync))
+        .route("/api/resume", post(resume_sync))
+        .route("/api/jobs/:id/retry", post(retry_job))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_action_token,
+        ));
+
+    Router::new()
         .route("/", get(index))
+        .route("/history", get(history_page))
+        .route("/api/history", get(get_history))
+        .route("/api/stats", get(get_stats))
+        .route("/healthz", get(get_health))
+        .route("/readyz", get(get_ready))
         .route("/api/status", get(get_status))
+        .route("/api/badge.svg", get(get_badge))
         .route("/api/config", get(get_config))
-        .with_state(state);
+        .route("/api/events", get(job_events))
+        .route("/api/jobs/blocked", get(get_blocked_jobs))
+        .merge(actions)
+        .with_state(state)
+}
 
-    let listener = tokio::net::TcpListener::bind(format!("{}:{}", host, port)).await?;
-    info!("Dashboard listening on http://{}:{}", host, port);
+/// Reject requests that don't carry the action token
+async fn require_action_token(
+    State(state): State<DashboardState>,
+    request: Request,
+    next: Next,
+) -> axum::response::Response {
+    let token = request
+        .headers()
+        .get(ACTION_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok());
 
-    axum::serve(listener, app).await?;
+    if token != Some(&*state.action_token) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(IpcResponse::error("Missing or invalid dashboard token")),
+        )
+            .into_response();
+    }
 
-    Ok(())
+    next.run(request).await
 }
 
 /// Index page handler
-async fn index() -> Html<&'static str> {
-    Html(DASHBOARD_HTML)
+async fn index(State(state): State<DashboardState>) -> Html<String> {
+    Html(DASHBOARD_HTML.replace(ACTION_TOKEN_PLACEHOLDER, &state.action_token))
+}
+
+/// History page handler
+async fn history_page() -> Html<&'static str> {
+    Html(HISTORY_HTML)
+}
+
+/// Filters for the history API
+#[derive(Debug, serde::Deserialize)]
+struct HistoryQuery {
+    #[serde(default = "default_history_days")]
+    days: u32,
+    #[serde(default)]
+    failed: bool,
+}
+
+fn default_history_days() -> u32 {
+    7
+}
+
+/// Sync history API handler
+async fn get_history(
+    State(state): State<DashboardState>,
+    Query(query): Query<HistoryQuery>,
+) -> impl IntoResponse {
+    let since = Utc::now() - chrono::Duration::days(query.days.into());
+
+    match state
+        .reader
+        .get_history(since, query.failed, None, HISTORY_LIMIT)
+        .await
+    {
+        Ok(entries) => (StatusCode::OK, Json(serde_json::json!(entries))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        ),
+    }
+}
+
+/// Period for the transfer statistics API
+#[derive(Debug, serde::Deserialize)]
+struct StatsQuery {
+    #[serde(default = "default_stats_days")]
+    days: u32,
+}
+
+fn default_stats_days() -> u32 {
+    30
+}
+
+/// Transfer statistics API handler
+///
+/// Aggregates first, so the chart includes transfers since the daemon's
+/// last reconciliation.
+async fn get_stats(
+    State(state): State<DashboardState>,
+    Query(query): Query<StatsQuery>,
+) -> impl IntoResponse {
+    let sync_dirs = state.config.lock().await.get().sync_dirs.clone();
+    if let Err(e) = crate::stats::aggregate(&state.db, &sync_dirs).await {
+        warn!("Error aggregating transfer statistics: {}", e);
+    }
+
+    let since = chrono::Local::now().date_naive()
+        - chrono::Duration::days(i64::from(query.days.max(1)) - 1);
+
+    match state.reader.get_transfer_stats(since).await {
+        Ok(stats) => (StatusCode::OK, Json(serde_json::json!(stats))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        ),
+    }
 }
 
 /// Status API handler
 async fn get_status(State(state): State<DashboardState>) -> impl IntoResponse {
+    let status = state.status.get_or_refresh(|| status_summary(&state)).await;
+
+    match status {
+        Ok(status) => (StatusCode::OK, Json(status)),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        ),
+    }
+}
+
+/// Engine, database and API health, as served by the probes
+#[derive(Debug, serde::Serialize)]
+struct HealthReport {
+    /// Engine state, or `stopped` when the daemon does not answer
+    engine: serde_json::Value,
+    /// `ok`, or the error the database returned
+    database: String,
+    last_api_contact: Option<DateTime<Utc>>,
+    last_api_contact_age_secs: Option<i64>,
+    /// Why the daemon is not ready; empty when it is
+    problems: Vec<String>,
+}
+
+/// Check the engine, database and last API contact
+async fn health_report(state: &DashboardState) -> HealthReport {
+    let engine = match send_request(&IpcRequest::Status).await {
+        Ok(Some(response)) => response.status.map(|s| s.state),
+        _ => None,
+    };
+
+    let database = state.reader.ping().await;
+    let last_api_contact = match &database {
+        Ok(()) => state.reader.get_last_api_contact().await.ok().flatten(),
+        Err(_) => None,
+    };
+    let age = last_api_contact.map(|at| Utc::now() - at);
+
+    HealthReport {
+        engine: engine
+            .and_then(|s| serde_json::to_value(s).ok())
+            .unwrap_or_else(|| serde_json::json!("stopped")),
+        problems: readiness_problems(engine, database.as_ref().err(), age),
+        database: match database {
+            Ok(()) => "ok".to_string(),
+            Err(e) => e.to_string(),
+        },
+        last_api_contact,
+        last_api_contact_age_secs: age.map(|age| age.num_seconds()),
+    }
+}
+
+/// Reasons the daemon is not ready to sync
+fn readiness_problems(
+    engine: Option<SyncState>,
+    database_error: Option<&crate::error::Error>,
+    last_contact_age: Option<chrono::Duration>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    match engine {
+        Some(SyncState::Running) => {}
+        Some(state) => problems.push(format!("engine is {:?}", state).to_lowercase()),
+        None => problems.push("engine is not running".to_string()),
+    }
+
+    if let Some(e) = database_error {
+        problems.push(format!("database unavailable: {}", e));
+    }
+
+    let max_age = chrono::Duration::from_std(READY_MAX_CONTACT_AGE).unwrap_or_default();
+    match last_contact_age {
+        None => problems.push("no successful Proton API call yet".to_string()),
+        Some(age) if age > max_age => problems.push(format!(
+            "last successful Proton API call was {} minutes ago",
+            age.num_minutes()
+        )),
+        Some(_) => {}
+    }
+
+    problems
+}
+
+/// Liveness probe
+///
+/// Fails only when the database does not answer; the engine and the API
+/// are reported but checked by `/readyz`.
+async fn get_health(State(state): State<DashboardState>) -> impl IntoResponse {
+    let report = health_report(&state).await;
+    let status = if report.database == "ok" {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, [(header::CACHE_CONTROL, "no-cache")], Json(report))
+}
+
+/// Readiness probe
+///
+/// Succeeds while the engine is running, the database answers and the
+/// Proton API answered recently.
+async fn get_ready(State(state): State<DashboardState>) -> impl IntoResponse {
+    let report = health_report(&state).await;
+    let status = if report.problems.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, [(header::CACHE_CONTROL, "no-cache")], Json(report))
+}
+
+/// Status badge handler
+///
+/// Renders the queue state as an SVG badge for embedding in other pages.
+async fn get_badge(State(state): State<DashboardState>) -> impl IntoResponse {
+    let (message, color) = match state.queue.cached_status_counts().await {
+        Ok(counts) => badge_status(&counts),
+        Err(e) => {
+            warn!("Error getting queue counts for the badge: {}", e);
+            ("Unknown".to_string(), BADGE_UNKNOWN)
+        }
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, "image/svg+xml"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        badge_svg("Proton Drive", &message, color),
+    )
+}
+
+/// Badge message and color for the queue counts
+///
+/// Blocked jobs need attention, so they win over waiting ones.
+fn badge_status(counts: &StatusCounts) -> (String, &'static str) {
+    let waiting = counts.pending + counts.processing;
+    if counts.blocked > 0 {
+        ("Error".to_string(), BADGE_ERROR)
+    } else if waiting > 0 {
+        (format!("{} pending", waiting), BADGE_BUSY)
+    } else {
+        ("In Sync".to_string(), BADGE_OK)
+    }
+}
+
+/// Render a two-part badge in the common flat style
+///
+/// Text widths are estimated from the character count, which is close
+/// enough for the short ASCII labels used here.
+fn badge_svg(label: &str, message: &str, color: &str) -> String {
+    let text_width = |text: &str| text.chars().count() * 7 + 10;
+    let label_width = text_width(label);
+    let message_width = text_width(message);
+    let width = label_width + message_width;
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}"><title>{label}: {message}</title><linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient><clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath><g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#s)"/></g><g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11"><text x="{label_x}" y="14">{label}</text><text x="{message_x}" y="14">{message}</text></g></svg>"##,
+        label_x = label_width / 2,
+        message_x = label_width + message_width / 2,
+    )
+}
+
+/// Summarize the config, daemon state and account for the status API
+async fn status_summary(state: &DashboardState) -> Result<serde_json::Value> {
     let cfg = state.config.lock().await;
     let config = cfg.get().clone();
+    drop(cfg);
+
+    let daemon = match send_request(&IpcRequest::Status).await {
+        Ok(Some(response)) => response
+            .status
+            .and_then(|s| serde_json::to_value(s.state).ok())
+            .unwrap_or(serde_json::Value::Null),
+        _ => serde_json::json!("stopped"),
+    };
+
+    let account = state
+        .account
+        .get_or_refresh(|| account_info(state.client.clone()))
+        .await?;
+    let storage_warning = account
+        .as_ref()
+        .is_some_and(|a| a.is_over(config.storage_warning_percent));
+
+    let first_sync: Vec<serde_json::Value> = state
+        .reader
+        .get_first_sync_summaries()
+        .await?
+        .iter()
+        .map(|summary| {
+            serde_json::json!({
+                "sync_dir": summary.sync_dir,
+                "completed_at": summary.completed_at,
+                "description": crate::first_sync::describe(summary),
+            })
+        })
+        .collect();
 
     let status = serde_json::json!({
         "sync_dirs": config.sync_dirs.len(),
         "concurrency": config.sync_concurrency,
         "remote_delete_behavior": config.remote_delete_behavior,
+        "daemon": daemon,
+        "account": account,
+        "storage_warning": storage_warning,
+        "first_sync": first_sync,
+        "last_api_contact": state.reader.get_last_api_contact().await?,
     });
 
-    Json(status)
+    Ok(status)
+}
+
+/// Fetch account info, caching a failure as "unknown" rather than retrying it
+async fn account_info(client: Option<Arc<ProtonClient>>) -> Result<Option<AccountInfo>> {
+    let Some(client) = client else {
+        return Ok(None);
+    };
+
+    match client.get_account_info().await {
+        Ok(account) => Ok(Some(account)),
+        Err(e) => {
+            warn!("Could not fetch account info: {}", e);
+            Ok(None)
+        }
+    }
 }
 
 /// Config API handler
@@ -66,12 +487,171 @@ async fn get_config(State(state): State<DashboardState>) -> impl IntoResponse {
     Json(config)
 }
 
+/// Pause API handler
+async fn pause_sync(State(state): State<DashboardState>) -> impl IntoResponse {
+    let response = forward_to_daemon(IpcRequest::Pause).await;
+    state.status.invalidate().await;
+    response
+}
+
+/// Resume API handler
+async fn resume_sync(State(state): State<DashboardState>) -> impl IntoResponse {
+    let response = forward_to_daemon(IpcRequest::Resume).await;
+    state.status.invalidate().await;
+    response
+}
+
+/// Send a control request to the daemon and map the outcome to an HTTP response
+async fn forward_to_daemon(request: IpcRequest) -> (StatusCode, Json<IpcResponse>) {
+    match send_request(&request).await {
+        Ok(Some(response)) if response.ok => (StatusCode::OK, Json(response)),
+        Ok(Some(response)) => (StatusCode::INTERNAL_SERVER_ERROR, Json(response)),
+        Ok(None) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(IpcResponse::error("Sync daemon is not running")),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(IpcResponse::error(e.to_string())),
+        ),
+    }
+}
+
+/// Blocked jobs API handler
+async fn get_blocked_jobs(State(state): State<DashboardState>) -> impl IntoResponse {
+    match state
+        .reader
+        .get_jobs_by_status(SyncJobStatus::Blocked, BLOCKED_JOBS_LIMIT)
+        .await
+    {
+        Ok(jobs) => (StatusCode::OK, Json(serde_json::json!(jobs))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        ),
+    }
+}
+
+/// Retry API handler for a blocked job
+async fn retry_job(
+    State(state): State<DashboardState>,
+    Path(id): Path<i64>,
+) -> (StatusCode, Json<IpcResponse>) {
+    match state.db.retry_blocked_job(id).await {
+        Ok(true) => (StatusCode::OK, Json(IpcResponse::ok())),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(IpcResponse::error(format!("No blocked job with ID {}", id))),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(IpcResponse::error(e.to_string())),
+        ),
+    }
+}
+
+/// Live job feed handler
+///
+/// Streams a `job` event for every job state change, a `progress` event
+/// whenever the queue counts change and an `uploads` event whenever the
+/// progress of in-flight uploads changes.
+async fn job_events(
+    State(state): State<DashboardState>,
+) -> Sse<impl Stream<Item = std::result::Result<Event, Infallible>>> {
+    let feed = JobFeed::new(state.reader, state.queue);
+
+    let stream = futures::stream::unfold(feed, |mut feed| async move {
+        let events = feed.next_events().await;
+        Some((futures::stream::iter(events.into_iter().map(Ok)), feed))
+    })
+    .flatten();
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Polling state for one live feed subscriber
+struct JobFeed {
+    db: Db,
+    queue: JobQueue,
+    /// Update time and ID of the last job sent
+    cursor: (DateTime<Utc>, i64),
+    last_counts: Option<StatusCounts>,
+    last_uploads: Option<Vec<JobProgress>>,
+    started: bool,
+}
+
+impl JobFeed {
+    fn new(db: Db, queue: JobQueue) -> Self {
+        Self {
+            db,
+            queue,
+            cursor: (Utc::now(), i64::MAX),
+            last_counts: None,
+            last_uploads: None,
+            started: false,
+        }
+    }
+
+    /// Wait for the next poll and collect the events it produces
+    async fn next_events(&mut self) -> Vec<Event> {
+        if self.started {
+            tokio::time::sleep(EVENT_POLL_INTERVAL).await;
+        }
+        self.started = true;
+
+        let mut events = Vec::new();
+
+        match self
+            .db
+            .get_jobs_updated_after(self.cursor.0, self.cursor.1, EVENT_BATCH_SIZE)
+            .await
+        {
+            Ok(jobs) => {
+                for job in jobs {
+                    if let Some(updated_at) = job.updated_at {
+                        self.cursor = self.cursor.max((updated_at, job.id));
+                    }
+                    if let Ok(event) = Event::default().event("job").json_data(&job) {
+                        events.push(event);
+                    }
+                }
+            }
+            Err(e) => warn!("Error polling job updates: {}", e),
+        }
+
+        match self.queue.cached_status_counts().await {
+            Ok(counts) if self.last_counts.as_ref() != Some(&counts) => {
+                if let Ok(event) = Event::default().event("progress").json_data(&counts) {
+                    events.push(event);
+                }
+                self.last_counts = Some(counts);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Error polling queue counts: {}", e),
+        }
+
+        match self.db.get_job_progress().await {
+            Ok(uploads) if self.last_uploads.as_ref() != Some(&uploads) => {
+                if let Ok(event) = Event::default().event("uploads").json_data(&uploads) {
+                    events.push(event);
+                }
+                self.last_uploads = Some(uploads);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Error polling upload progress: {}", e),
+        }
+
+        events
+    }
+}
+
 /// Dashboard HTML
 pub const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
 <html lang="en">
 <head>
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <meta name="action-token" content="__ACTION_TOKEN__">
     <title>Proton Drive Sync</title>
     <style>
         * { margin: 0; padding: 0; box-sizing: border-box; }
@@ -137,14 +717,128 @@ pub const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
             color: #999;
             margin: 0 0.5rem;
         }
+        .activity-item {
+            display: flex;
+            align-items: center;
+            gap: 0.75rem;
+            padding: 0.4rem 0;
+            border-bottom: 1px solid #eee;
+            font-size: 0.875rem;
+        }
+        .activity-item:last-child {
+            border-bottom: none;
+        }
+        .activity-time {
+            color: #999;
+            font-family: monospace;
+        }
+        .badge {
+            display: inline-block;
+            min-width: 6rem;
+            text-align: center;
+            padding: 0.1rem 0.5rem;
+            border-radius: 4px;
+            font-size: 0.75rem;
+            font-weight: bold;
+            text-transform: uppercase;
+        }
+        .badge-Pending { background: #eef; color: #558; }
+        .badge-Processing { background: #fff4d6; color: #a70; }
+        .badge-Synced { background: #e3f7e8; color: #1a7f37; }
+        .badge-Blocked { background: #fde8e8; color: #c00; }
+        .controls {
+            margin-top: 1rem;
+        }
+        .button {
+            background: #6d4aff;
+            color: white;
+            border: none;
+            border-radius: 4px;
+            padding: 0.4rem 1rem;
+            font-size: 0.875rem;
+            cursor: pointer;
+        }
+        .button:hover {
+            background: #5a3ae0;
+        }
+        .button:disabled {
+            background: #ccc;
+            cursor: default;
+        }
+        .button-small {
+            padding: 0.2rem 0.6rem;
+            font-size: 0.75rem;
+            margin-left: auto;
+        }
+        .job-error {
+            color: #c00;
+            font-size: 0.75rem;
+        }
+        .upload {
+            padding: 0.5rem 0;
+            border-bottom: 1px solid #eee;
+        }
+        .upload-bar {
+            height: 6px;
+            margin-top: 0.25rem;
+            background: #eee;
+            border-radius: 3px;
+            overflow: hidden;
+        }
+        .upload-bar-fill {
+            height: 100%;
+            background: #6d4aff;
+        }
+        .upload-bytes {
+            color: #666;
+            font-size: 0.75rem;
+        }
+        .message {
+            color: #c00;
+            font-size: 0.875rem;
+            margin-left: 0.5rem;
+        }
+        .chart {
+            display: flex;
+            align-items: flex-end;
+            gap: 2px;
+            height: 120px;
+            border-bottom: 1px solid #ddd;
+        }
+        .chart-day {
+            flex: 1;
+            display: flex;
+            flex-direction: column;
+            justify-content: flex-end;
+            height: 100%;
+        }
+        .chart-up { background: #6d4aff; }
+        .chart-down { background: #1a7f37; }
+        .chart-legend {
+            color: #666;
+            font-size: 0.75rem;
+            margin-top: 0.5rem;
+        }
+        .banner {
+            display: flex;
+            align-items: center;
+            background: #e3f7e8;
+            color: #1a7f37;
+            border-radius: 8px;
+            padding: 0.75rem 1rem;
+            margin-bottom: 1.5rem;
+        }
     </style>
 </head>
 <body>
     <div class="container">
         <div class="header">
             <h1>Proton Drive Sync Dashboard</h1>
+            <a href="/history">History</a>
         </div>
 
+        <div id="first-sync-banners"></div>
+
         <div class="card">
             <h2>Status</h2>
             <div class="stat">
@@ -155,6 +849,70 @@ pub const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
                 <div class="stat-value" id="concurrency">-</div>
                 <div class="stat-label">Concurrency</div>
             </div>
+            <div class="stat">
+                <div class="stat-value" id="daemon-state">-</div>
+                <div class="stat-label">Daemon</div>
+            </div>
+            <div class="stat">
+                <div class="stat-value" id="last-contact">-</div>
+                <div class="stat-label">Last Server Contact</div>
+            </div>
+            <div class="stat">
+                <div class="stat-value" id="storage">-</div>
+                <div class="stat-label" id="account">Storage</div>
+            </div>
+            <div class="controls">
+                <button class="button" id="pause-button" onclick="control('pause')">Pause</button>
+                <button class="button" id="resume-button" onclick="control('resume')">Resume</button>
+                <span class="message" id="control-message"></span>
+            </div>
+        </div>
+
+        <div class="card">
+            <h2>Queue</h2>
+            <div class="stat">
+                <div class="stat-value" id="count-pending">-</div>
+                <div class="stat-label">Queued</div>
+            </div>
+            <div class="stat">
+                <div class="stat-value" id="count-processing">-</div>
+                <div class="stat-label">Processing</div>
+            </div>
+            <div class="stat">
+                <div class="stat-value" id="count-synced">-</div>
+                <div class="stat-label">Synced</div>
+            </div>
+            <div class="stat">
+                <div class="stat-value" id="count-blocked">-</div>
+                <div class="stat-label">Blocked</div>
+            </div>
+        </div>
+
+        <div class="card">
+            <h2>Uploads</h2>
+            <div id="upload-list">
+                <p style="color: #999;">No uploads in progress</p>
+            </div>
+        </div>
+
+        <div class="card">
+            <h2>Data Transferred (30 days)</h2>
+            <div class="chart" id="stats-chart"></div>
+            <div class="chart-legend" id="stats-legend">Loading...</div>
+        </div>
+
+        <div class="card">
+            <h2>Blocked Jobs</h2>
+            <div id="blocked-list">
+                Loading...
+            </div>
+        </div>
+
+        <div class="card">
+            <h2>Activity</h2>
+            <div id="activity" data-empty="true">
+                <p style="color: #999;">Waiting for sync activity...</p>
+            </div>
         </div>
 
         <div class="card">
@@ -173,11 +931,131 @@ pub const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
 
                 document.getElementById('sync-dirs-count').textContent = data.sync_dirs;
                 document.getElementById('concurrency').textContent = data.concurrency;
+                document.getElementById('daemon-state').textContent = data.daemon || '-';
+                document.getElementById('last-contact').textContent =
+                    data.last_api_contact ? `${formatAge(new Date(data.last_api_contact))} ago` : 'never';
+
+                showFirstSync(data.first_sync || []);
+
+                if (data.account) {
+                    const account = data.account;
+                    const percent = account.max_space > 0
+                        ? Math.round(account.used_space * 100 / account.max_space)
+                        : 0;
+                    const storage = document.getElementById('storage');
+                    storage.textContent = `${formatBytes(account.used_space)} / ${formatBytes(account.max_space)} (${percent}%)`;
+                    storage.style.color = data.storage_warning ? '#c00' : '';
+                    document.getElementById('account').textContent =
+                        `Storage · ${account.email}` + (account.plan ? ` · ${account.plan}` : '');
+                }
+
+                const running = data.daemon !== 'stopped';
+                document.getElementById('pause-button').disabled = !running || data.daemon === 'paused';
+                document.getElementById('resume-button').disabled = !running || data.daemon !== 'paused';
             } catch (error) {
                 console.error('Error loading status:', error);
             }
         }
 
+        function showFirstSync(milestones) {
+            const dismissed = JSON.parse(localStorage.getItem('dismissed-first-sync') || '[]');
+            const banners = document.getElementById('first-sync-banners');
+            banners.innerHTML = '';
+
+            for (const milestone of milestones) {
+                const key = `${milestone.sync_dir}@${milestone.completed_at}`;
+                if (dismissed.includes(key)) continue;
+
+                const banner = document.createElement('div');
+                banner.className = 'banner';
+                const text = document.createElement('span');
+                text.textContent = `✓ First sync of ${milestone.sync_dir} complete: ${milestone.description}. It's now safe to rely on the remote copy.`;
+                const button = document.createElement('button');
+                button.className = 'button button-small';
+                button.textContent = 'Dismiss';
+                button.onclick = () => {
+                    dismissed.push(key);
+                    localStorage.setItem('dismissed-first-sync', JSON.stringify(dismissed));
+                    banner.remove();
+                };
+                banner.append(text, button);
+                banners.append(banner);
+            }
+        }
+
+        async function postAction(url) {
+            const token = document.querySelector('meta[name="action-token"]').content;
+            const response = await fetch(url, {
+                method: 'POST',
+                headers: { 'X-Dashboard-Token': token },
+            });
+            const data = await response.json();
+            if (!data.ok) {
+                throw new Error(data.message || `Request failed (${response.status})`);
+            }
+        }
+
+        async function control(action) {
+            const message = document.getElementById('control-message');
+            message.textContent = '';
+            try {
+                await postAction(`/api/${action}`);
+            } catch (error) {
+                message.textContent = error.message;
+            }
+            loadStatus();
+        }
+
+        async function retryJob(id, button) {
+            button.disabled = true;
+            try {
+                await postAction(`/api/jobs/${id}/retry`);
+            } catch (error) {
+                button.disabled = false;
+                button.title = error.message;
+            }
+            loadBlocked();
+        }
+
+        async function loadBlocked() {
+            try {
+                const response = await fetch('/api/jobs/blocked');
+                const jobs = await response.json();
+
+                const list = document.getElementById('blocked-list');
+                list.innerHTML = '';
+
+                if (jobs.length === 0) {
+                    list.innerHTML = '<p style="color: #999;">No blocked jobs</p>';
+                    return;
+                }
+
+                for (const job of jobs) {
+                    const item = document.createElement('div');
+                    item.className = 'activity-item';
+
+                    const details = document.createElement('div');
+                    const path = document.createElement('div');
+                    path.className = 'sync-dir-path';
+                    path.textContent = job.local_path;
+                    const error = document.createElement('div');
+                    error.className = 'job-error';
+                    error.textContent = job.last_error || '';
+                    details.append(path, error);
+
+                    const button = document.createElement('button');
+                    button.className = 'button button-small';
+                    button.textContent = 'Retry';
+                    button.onclick = () => retryJob(job.id, button);
+
+                    item.append(details, button);
+                    list.append(item);
+                }
+            } catch (error) {
+                console.error('Error loading blocked jobs:', error);
+            }
+        }
+
         async function loadConfig() {
             try {
                 const response = await fetch('/api/config');
@@ -202,8 +1080,173 @@ pub const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
             }
         }
 
+        const STATUS_LABELS = {
+            Pending: 'queued',
+            Processing: 'processing',
+            Synced: 'synced',
+            Blocked: 'blocked',
+        };
+
+        function addActivity(job) {
+            const list = document.getElementById('activity');
+            if (list.dataset.empty) {
+                list.innerHTML = '';
+                delete list.dataset.empty;
+            }
+
+            const item = document.createElement('div');
+            item.className = 'activity-item';
+
+            const time = document.createElement('span');
+            time.className = 'activity-time';
+            time.textContent = new Date(job.updated_at || Date.now()).toLocaleTimeString();
+
+            const badge = document.createElement('span');
+            badge.className = `badge badge-${job.status}`;
+            badge.textContent = STATUS_LABELS[job.status] || job.status;
+
+            const path = document.createElement('span');
+            path.className = 'sync-dir-path';
+            path.textContent = job.local_path;
+            if (job.last_error) {
+                path.title = job.last_error;
+            }
+
+            item.append(time, badge, path);
+            list.prepend(item);
+
+            while (list.children.length > 50) {
+                list.lastChild.remove();
+            }
+        }
+
+        function formatBytes(bytes) {
+            const units = ['B', 'KiB', 'MiB', 'GiB', 'TiB'];
+            let unit = 0;
+            while (bytes >= 1024 && unit < units.length - 1) {
+                bytes /= 1024;
+                unit++;
+            }
+            return unit === 0 ? `${bytes} B` : `${bytes.toFixed(1)} ${units[unit]}`;
+        }
+
+        function formatAge(date) {
+            const secs = Math.max(0, Math.floor((Date.now() - date.getTime()) / 1000));
+            if (secs >= 86400) return `${Math.floor(secs / 86400)}d`;
+            if (secs >= 3600) return `${Math.floor(secs / 3600)}h`;
+            if (secs >= 60) return `${Math.floor(secs / 60)}m`;
+            return `${secs}s`;
+        }
+
+        function showUploads(uploads) {
+            const list = document.getElementById('upload-list');
+            list.innerHTML = '';
+
+            if (uploads.length === 0) {
+                list.innerHTML = '<p style="color: #999;">No uploads in progress</p>';
+                return;
+            }
+
+            for (const upload of uploads) {
+                const percent = upload.total_bytes > 0
+                    ? Math.min(100, Math.floor(100 * upload.bytes_sent / upload.total_bytes))
+                    : 100;
+
+                const item = document.createElement('div');
+                item.className = 'upload';
+
+                const path = document.createElement('div');
+                path.className = 'sync-dir-path';
+                path.textContent = upload.local_path;
+
+                const bar = document.createElement('div');
+                bar.className = 'upload-bar';
+                const fill = document.createElement('div');
+                fill.className = 'upload-bar-fill';
+                fill.style.width = `${percent}%`;
+                bar.append(fill);
+
+                const bytes = document.createElement('div');
+                bytes.className = 'upload-bytes';
+                bytes.textContent = `${percent}% · ${formatBytes(upload.bytes_sent)} of ${formatBytes(upload.total_bytes)}`;
+
+                item.append(path, bar, bytes);
+                list.append(item);
+            }
+        }
+
+        async function loadStats() {
+            try {
+                const response = await fetch('/api/stats?days=30');
+                const stats = await response.json();
+
+                const days = [];
+                for (let i = 29; i >= 0; i--) {
+                    const date = new Date();
+                    date.setDate(date.getDate() - i);
+                    const key = `${date.getFullYear()}-${String(date.getMonth() + 1).padStart(2, '0')}-${String(date.getDate()).padStart(2, '0')}`;
+                    days.push({ key, up: 0, down: 0 });
+                }
+
+                let totalUp = 0;
+                let totalDown = 0;
+                for (const entry of stats) {
+                    const day = days.find(d => d.key === entry.day);
+                    if (entry.direction === 'upload') {
+                        totalUp += entry.bytes;
+                        if (day) day.up += entry.bytes;
+                    } else {
+                        totalDown += entry.bytes;
+                        if (day) day.down += entry.bytes;
+                    }
+                }
+
+                const max = Math.max(1, ...days.map(d => d.up + d.down));
+                const chart = document.getElementById('stats-chart');
+                chart.innerHTML = '';
+                for (const day of days) {
+                    const column = document.createElement('div');
+                    column.className = 'chart-day';
+                    column.title = `${day.key}: ↑ ${formatBytes(day.up)} · ↓ ${formatBytes(day.down)}`;
+
+                    const down = document.createElement('div');
+                    down.className = 'chart-down';
+                    down.style.height = `${100 * day.down / max}%`;
+                    const up = document.createElement('div');
+                    up.className = 'chart-up';
+                    up.style.height = `${100 * day.up / max}%`;
+
+                    column.append(down, up);
+                    chart.append(column);
+                }
+
+                document.getElementById('stats-legend').textContent =
+                    `Uploaded ${formatBytes(totalUp)} (purple) · Downloaded ${formatBytes(totalDown)} (green)`;
+            } catch (error) {
+                console.error('Error loading stats:', error);
+            }
+        }
+
+        const events = new EventSource('/api/events');
+        events.addEventListener('uploads', (e) => showUploads(JSON.parse(e.data)));
+        events.addEventListener('job', (e) => addActivity(JSON.parse(e.data)));
+        let lastBlocked = null;
+        events.addEventListener('progress', (e) => {
+            const counts = JSON.parse(e.data);
+            if (counts.blocked !== lastBlocked) {
+                lastBlocked = counts.blocked;
+                loadBlocked();
+            }
+            document.getElementById('count-pending').textContent = counts.pending;
+            document.getElementById('count-processing').textContent = counts.processing;
+            document.getElementById('count-synced').textContent = counts.synced;
+            document.getElementById('count-blocked').textContent = counts.blocked;
+        });
+
         loadStatus();
         loadConfig();
+        loadStats();
+        setInterval(loadStats, 60000);
 
         // Refresh every 5 seconds
         setInterval(() => {
@@ -214,3 +1257,283 @@ pub const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
 </body>
 </html>
 "#;
+
+pub const HISTORY_HTML: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Sync History - Proton Drive Sync</title>
+    <style>
+        * { margin: 0; padding: 0; box-sizing: border-box; }
+        body {
+            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
+            background: #f5f5f5;
+        }
+        .container {
+            max-width: 1200px;
+            margin: 0 auto;
+            padding: 2rem;
+        }
+        .header, .card {
+            background: white;
+            border-radius: 8px;
+            padding: 1.5rem 2rem;
+            margin-bottom: 1.5rem;
+            box-shadow: 0 1px 3px rgba(0,0,0,0.1);
+        }
+        .header h1 {
+            color: #6d4aff;
+            font-size: 1.5rem;
+        }
+        .filters {
+            display: flex;
+            gap: 1rem;
+            align-items: center;
+            margin-bottom: 1rem;
+            font-size: 0.875rem;
+        }
+        table {
+            width: 100%;
+            border-collapse: collapse;
+            font-size: 0.875rem;
+        }
+        th, td {
+            text-align: left;
+            padding: 0.4rem 0.5rem;
+            border-bottom: 1px solid #eee;
+            vertical-align: top;
+        }
+        th { color: #666; font-weight: normal; }
+        .path { font-family: monospace; word-break: break-all; }
+        .error { color: #c00; font-size: 0.75rem; }
+        .result-synced { color: #1a7f37; }
+        .result-retrying { color: #a70; }
+        .result-blocked { color: #c00; }
+        .muted { color: #999; }
+    </style>
+</head>
+<body>
+    <div class="container">
+        <div class="header">
+            <h1>Sync History</h1>
+            <a href="/">Dashboard</a>
+        </div>
+
+        <div class="card">
+            <div class="filters">
+                <label>Last
+                    <select id="days" onchange="loadHistory()">
+                        <option value="1">day</option>
+                        <option value="7" selected>7 days</option>
+                        <option value="30">30 days</option>
+                        <option value="90">90 days</option>
+                    </select>
+                </label>
+                <label><input type="checkbox" id="failed" onchange="loadHistory()"> Failures only</label>
+            </div>
+            <table>
+                <thead>
+                    <tr><th>Time</th><th>Result</th><th>Operation</th><th>Path</th><th>Size</th><th>Duration</th></tr>
+                </thead>
+                <tbody id="history"></tbody>
+            </table>
+            <p class="muted" id="empty" hidden>No sync operations in this period</p>
+        </div>
+    </div>
+
+    <script>
+        function formatBytes(bytes) {
+            const units = ['B', 'KiB', 'MiB', 'GiB', 'TiB'];
+            let unit = 0;
+            while (bytes >= 1024 && unit < units.length - 1) {
+                bytes /= 1024;
+                unit++;
+            }
+            return unit === 0 ? `${bytes} B` : `${bytes.toFixed(1)} ${units[unit]}`;
+        }
+
+        function cell(text, className) {
+            const td = document.createElement('td');
+            td.textContent = text;
+            if (className) td.className = className;
+            return td;
+        }
+
+        async function loadHistory() {
+            const days = document.getElementById('days').value;
+            const failed = document.getElementById('failed').checked;
+            try {
+                const response = await fetch(`/api/history?days=${days}&failed=${failed}`);
+                const entries = await response.json();
+
+                const body = document.getElementById('history');
+                body.innerHTML = '';
+                document.getElementById('empty').hidden = entries.length > 0;
+
+                for (const entry of entries) {
+                    const row = document.createElement('tr');
+                    const operation = entry.old_local_path ? 'Move' : entry.event_type;
+                    const direction = entry.direction === 'download' ? '↓' : '↑';
+
+                    const path = cell(entry.local_path, 'path');
+                    if (entry.old_local_path) {
+                        const from = document.createElement('div');
+                        from.className = 'muted';
+                        from.textContent = `from ${entry.old_local_path}`;
+                        path.append(from);
+                    }
+                    if (entry.error) {
+                        const error = document.createElement('div');
+                        error.className = 'error';
+                        error.textContent = entry.error;
+                        path.append(error);
+                    }
+
+                    row.append(
+                        cell(new Date(entry.completed_at).toLocaleString(), 'muted'),
+                        cell(entry.result, `result-${entry.result}`),
+                        cell(`${direction} ${operation}`),
+                        path,
+                        cell(entry.bytes == null ? '' : formatBytes(entry.bytes)),
+                        cell(entry.duration_ms < 1000
+                            ? `${entry.duration_ms} ms`
+                            : `${(entry.duration_ms / 1000).toFixed(1)} s`),
+                    );
+                    body.append(row);
+                }
+            } catch (error) {
+                console.error('Error loading history:', error);
+            }
+        }
+
+        loadHistory();
+    </script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Serve the dashboard on a free port, returning its base URL
+    async fn serve(temp_dir: &TempDir, action_token: &str) -> String {
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let config = ConfigManager::with_config(
+            temp_dir.path().join("config.json"),
+            crate::types::Config::default(),
+        );
+        let state = DashboardState {
+            config: Arc::new(Mutex::new(config)),
+            db: db.clone(),
+            queue: JobQueue::new(db.clone()),
+            reader: db,
+            status: Arc::new(Cached::new(STATUS_CACHE_TTL)),
+            client: None,
+            account: Arc::new(Cached::new(ACCOUNT_CACHE_TTL)),
+            action_token: action_token.into(),
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_actions_require_token() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = serve(&temp_dir, "secret-token").await;
+        let client = reqwest::Client::new();
+
+        // A cross-site form post carries no token
+        for path in ["/api/pause", "/api/resume", "/api/jobs/1/retry"] {
+            let response = client
+                .post(format!("{}{}", base, path))
+                .header(header::ORIGIN, "https://evil.example")
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", path);
+        }
+
+        let response = client
+            .post(format!("{}/api/jobs/1/retry", base))
+            .header(ACTION_TOKEN_HEADER, "wrong-token")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // With the token the request reaches the handler
+        let response = client
+            .post(format!("{}/api/jobs/1/retry", base))
+            .header(ACTION_TOKEN_HEADER, "secret-token")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_index_embeds_token() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = serve(&temp_dir, "secret-token").await;
+
+        let page = reqwest::get(format!("{}/", base))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(page.contains(r#"<meta name="action-token" content="secret-token">"#));
+        assert!(!page.contains(ACTION_TOKEN_PLACEHOLDER));
+        assert_ne!(new_action_token(), new_action_token());
+    }
+
+    #[test]
+    fn test_badge_status() {
+        let mut counts = StatusCounts {
+            synced: 10,
+            ..StatusCounts::default()
+        };
+        assert_eq!(badge_status(&counts), ("In Sync".to_string(), BADGE_OK));
+
+        counts.pending = 3;
+        counts.processing = 1;
+        assert_eq!(badge_status(&counts), ("4 pending".to_string(), BADGE_BUSY));
+
+        counts.blocked = 1;
+        assert_eq!(badge_status(&counts), ("Error".to_string(), BADGE_ERROR));
+
+        let svg = badge_svg("Proton Drive", "4 pending", BADGE_BUSY);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(">4 pending</text>"));
+        assert!(svg.contains(BADGE_BUSY));
+    }
+
+    #[test]
+    fn test_readiness_problems() {
+        let recent = Some(chrono::Duration::minutes(1));
+        assert!(readiness_problems(Some(SyncState::Running), None, recent).is_empty());
+
+        assert_eq!(
+            readiness_problems(Some(SyncState::Paused), None, recent),
+            ["engine is paused"]
+        );
+        assert_eq!(
+            readiness_problems(None, None, None),
+            ["engine is not running", "no successful Proton API call yet"]
+        );
+        assert_eq!(
+            readiness_problems(
+                Some(SyncState::Running),
+                None,
+                Some(chrono::Duration::minutes(40))
+            ),
+            ["last successful Proton API call was 40 minutes ago"]
+        );
+    }
+}
@@ -1,15 +1,70 @@
 //! Database module for SQLite operations
 
+use crate::clock::Clock;
 use crate::error::{Error, Result};
-use crate::types::{FileState, NodeMapping, SyncEvent, SyncEventType, SyncJob, SyncJobStatus};
-use chrono::{DateTime, Utc};
-use sqlx::{sqlite::SqliteConnectOptions, Row, SqlitePool};
-use std::path::PathBuf;
+use crate::types::{
+    Conflict, DaemonHeartbeat, FileState, FirstSyncSummary, HistoryEntry, HistoryResult,
+    JobPriority, JobProgress, JobSource, NodeData, NodeMapping, RevisionData, ScanCheckpoint,
+    SkipReason, SyncDirection, SyncEvent, SyncEventType, SyncJob, SyncJobStatus, TransferStats,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow},
+    Row, SqlitePool,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Connections in the read-only pool
+const READ_POOL_SIZE: u32 = 2;
+
+/// Tables with their columns, and indexes, as `(table, column)` and `(index, "")`
+///
+/// Compared against a freshly migrated database to tell whether an existing
+/// one needs migrating, so the check can't fall behind `run_migrations`.
+const SCHEMA_OBJECTS_QUERY: &str = r#"
+    SELECT m.name, p.name FROM sqlite_master m, pragma_table_info(m.name) p
+    WHERE m.type = 'table'
+    UNION ALL
+    SELECT name, '' FROM sqlite_master WHERE type = 'index'
+"#;
+
+/// Schema objects of a freshly migrated database, built once per process
+static CURRENT_SCHEMA: tokio::sync::OnceCell<HashSet<(String, String)>> =
+    tokio::sync::OnceCell::const_new();
+
+/// Columns and constraints of the `sync_jobs` table
+///
+/// Shared by its creation and by the rebuild in
+/// [`Db::allow_skipped_status`].
+const SYNC_JOBS_SCHEMA: &str = r#"(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_type TEXT NOT NULL CHECK(event_type IN ('CREATE_FILE', 'CREATE_DIR', 'UPDATE', 'DELETE')),
+    local_path TEXT NOT NULL,
+    remote_path TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'PENDING' CHECK(status IN ('PENDING', 'PROCESSING', 'SYNCED', 'BLOCKED', 'SKIPPED')),
+    retry_at DATETIME,
+    n_retries INTEGER DEFAULT 0,
+    last_error TEXT,
+    change_token TEXT,
+    old_local_path TEXT,
+    old_remote_path TEXT,
+    priority INTEGER NOT NULL DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME,
+    completed_at DATETIME,
+    retry_due_ms INTEGER,
+    retry_delay_ms INTEGER,
+    skip_reason TEXT,
+    source TEXT
+)"#;
 
 /// Database connection pool
 #[derive(Clone)]
 pub struct Db {
     pool: SqlitePool,
+    /// Time source for retry scheduling
+    clock: Clock,
 }
 
 impl Db {
@@ -20,21 +75,108 @@ impl Db {
             tokio::fs::create_dir_all(parent).await?;
         }
 
+        // WAL lets readers proceed while a writer holds the database
         let options = SqliteConnectOptions::new()
             .filename(&db_path)
-            .create_if_missing(true);
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal);
 
         let pool = SqlitePool::connect_with(options).await?;
 
         // Run migrations manually
         Self::run_migrations(&pool).await?;
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            clock: Clock::new(),
+        })
+    }
+
+    /// Use another clock for retry scheduling
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Open a read-only handle for status queries
+    ///
+    /// Uses a separate small pool with `query_only` set, so status readers
+    /// never queue behind writers such as a running reconcile. The database
+    /// must exist; an older one is migrated first.
+    pub async fn open_read_only(db_path: PathBuf) -> Result<Self> {
+        if !db_path.exists() {
+            return Err(Error::FileNotFound(db_path));
+        }
+
+        let options = SqliteConnectOptions::new()
+            .filename(&db_path)
+            .pragma("query_only", "ON");
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(READ_POOL_SIZE)
+            .connect_with(options)
+            .await?;
+
+        if !Self::is_current(&pool).await? {
+            Self::new(db_path).await?.close().await;
+        }
+
+        Ok(Self {
+            pool,
+            clock: Clock::new(),
+        })
+    }
+
+    /// Open the database only if it already exists
+    ///
+    /// Returns `Ok(None)` on a machine where the daemon never ran, without
+    /// creating the data directory or an empty database.
+    pub async fn open_existing(db_path: PathBuf) -> Result<Option<Self>> {
+        if !db_path.exists() {
+            return Ok(None);
+        }
+
+        Self::new(db_path).await.map(Some)
+    }
+
+    /// Open a read-only handle only if the database already exists
+    pub async fn open_existing_read_only(db_path: PathBuf) -> Result<Option<Self>> {
+        if !db_path.exists() {
+            return Ok(None);
+        }
+
+        Self::open_read_only(db_path).await.map(Some)
+    }
+
+    /// Close all connections
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    /// Delete a database file together with its WAL and shared-memory files
+    pub async fn delete_files(db_path: &Path) -> Result<()> {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = db_path.as_os_str().to_owned();
+            path.push(suffix);
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
     }
 
     /// Run database migrations
     async fn run_migrations(pool: &SqlitePool) -> Result<()> {
         // Create tables
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS sync_jobs {}",
+            SYNC_JOBS_SCHEMA
+        ))
+        .execute(pool)
+        .await?;
+
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS signals (
@@ -48,24 +190,6 @@ impl Db {
                 created_at DATETIME DEFAULT CURRENT_TIMESTAMP
             );
 
-            CREATE TABLE IF NOT EXISTS sync_jobs (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                event_type TEXT NOT NULL CHECK(event_type IN ('CREATE_FILE', 'CREATE_DIR', 'UPDATE', 'DELETE')),
-                local_path TEXT NOT NULL,
-                remote_path TEXT NOT NULL,
-                status TEXT NOT NULL DEFAULT 'PENDING' CHECK(status IN ('PENDING', 'PROCESSING', 'SYNCED', 'BLOCKED')),
-                retry_at DATETIME,
-                n_retries INTEGER DEFAULT 0,
-                last_error TEXT,
-                change_token TEXT,
-                old_local_path TEXT,
-                old_remote_path TEXT,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            );
-
-            CREATE INDEX IF NOT EXISTS idx_sync_jobs_status ON sync_jobs(status, created_at);
-            CREATE INDEX IF NOT EXISTS idx_sync_jobs_retry_at ON sync_jobs(retry_at);
-
             CREATE TABLE IF NOT EXISTS processing_queue (
                 local_path TEXT PRIMARY KEY,
                 started_at DATETIME DEFAULT CURRENT_TIMESTAMP
@@ -90,7 +214,192 @@ impl Db {
             );
 
             CREATE INDEX IF NOT EXISTS idx_node_mapping_local ON node_mapping(local_path);
+            CREATE INDEX IF NOT EXISTS idx_node_mapping_local_nocase
+                ON node_mapping(local_path COLLATE NOCASE);
             CREATE INDEX IF NOT EXISTS idx_node_mapping_remote ON node_mapping(remote_path);
+
+            CREATE TABLE IF NOT EXISTS ignore_stats (
+                sync_dir TEXT PRIMARY KEY,
+                ignored_count INTEGER NOT NULL DEFAULT 0,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS nosync_roots (
+                local_path TEXT PRIMARY KEY,
+                sync_dir TEXT NOT NULL,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS remote_roots (
+                source_path TEXT PRIMARY KEY,
+                remote_root TEXT NOT NULL,
+                node_uid TEXT NOT NULL,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS remote_nodes (
+                uid TEXT PRIMARY KEY,
+                parent_uid TEXT,
+                name TEXT NOT NULL,
+                node_type TEXT NOT NULL,
+                size INTEGER,
+                revision_uid TEXT,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_remote_nodes_parent ON remote_nodes(parent_uid, name);
+
+            CREATE TABLE IF NOT EXISTS remote_listed_folders (
+                uid TEXT PRIMARY KEY
+            );
+
+            CREATE TABLE IF NOT EXISTS job_progress (
+                job_id INTEGER PRIMARY KEY,
+                local_path TEXT NOT NULL,
+                bytes_sent INTEGER NOT NULL,
+                total_bytes INTEGER NOT NULL,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS remote_event_cursor (
+                id INTEGER PRIMARY KEY CHECK(id = 1),
+                event_id TEXT NOT NULL,
+                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS sync_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                event_type TEXT NOT NULL,
+                direction TEXT NOT NULL,
+                local_path TEXT NOT NULL,
+                remote_path TEXT NOT NULL,
+                old_local_path TEXT,
+                bytes INTEGER,
+                duration_ms INTEGER NOT NULL,
+                result TEXT NOT NULL,
+                error TEXT,
+                completed_at DATETIME NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_sync_history_completed_at ON sync_history(completed_at);
+
+            CREATE TABLE IF NOT EXISTS transfer_stats (
+                day TEXT NOT NULL,
+                sync_dir TEXT NOT NULL,
+                direction TEXT NOT NULL,
+                files INTEGER NOT NULL DEFAULT 0,
+                bytes INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (day, sync_dir, direction)
+            );
+
+            CREATE TABLE IF NOT EXISTS transfer_stats_cursor (
+                id INTEGER PRIMARY KEY CHECK(id = 1),
+                history_id INTEGER NOT NULL
+            );
+
+            INSERT OR IGNORE INTO transfer_stats_cursor (id, history_id) VALUES (1, 0);
+
+            CREATE TABLE IF NOT EXISTS conflicts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                job_id INTEGER NOT NULL,
+                local_path TEXT NOT NULL UNIQUE,
+                remote_path TEXT NOT NULL,
+                node_uid TEXT NOT NULL,
+                parent_uid TEXT NOT NULL,
+                detected_at DATETIME NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS folder_creations (
+                parent_uid TEXT NOT NULL,
+                name TEXT NOT NULL,
+                remote_path TEXT NOT NULL,
+                started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (parent_uid, name)
+            );
+
+            CREATE TABLE IF NOT EXISTS daemon_heartbeat (
+                id INTEGER PRIMARY KEY CHECK(id = 1),
+                pid INTEGER NOT NULL,
+                started_at DATETIME NOT NULL,
+                last_seen DATETIME NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS api_contact (
+                id INTEGER PRIMARY KEY CHECK(id = 1),
+                last_contact DATETIME NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS scan_checkpoints (
+                sync_dir TEXT PRIMARY KEY,
+                generation INTEGER NOT NULL,
+                started_at DATETIME NOT NULL,
+                completed_at DATETIME
+            );
+
+            CREATE TABLE IF NOT EXISTS scan_completed_dirs (
+                sync_dir TEXT NOT NULL,
+                dir TEXT NOT NULL,
+                PRIMARY KEY (sync_dir, dir)
+            );
+
+            CREATE TABLE IF NOT EXISTS expected_changes (
+                local_path TEXT PRIMARY KEY,
+                change_token TEXT NOT NULL,
+                expected_at DATETIME NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS first_sync (
+                sync_dir TEXT PRIMARY KEY,
+                started_at DATETIME NOT NULL,
+                scanned_at DATETIME,
+                completed_at DATETIME,
+                files INTEGER,
+                bytes INTEGER,
+                errors INTEGER
+            );
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // Columns added after the initial schema
+        Self::add_column_if_missing(pool, "sync_jobs", "completed_at", "DATETIME").await?;
+        Self::add_column_if_missing(pool, "sync_jobs", "updated_at", "DATETIME").await?;
+        Self::add_column_if_missing(pool, "sync_jobs", "priority", "INTEGER NOT NULL DEFAULT 1")
+            .await?;
+        // Retry deadline and delay in milliseconds, see `crate::clock`
+        Self::add_column_if_missing(pool, "sync_jobs", "retry_due_ms", "INTEGER").await?;
+        Self::add_column_if_missing(pool, "sync_jobs", "retry_delay_ms", "INTEGER").await?;
+        Self::add_column_if_missing(pool, "sync_jobs", "skip_reason", "TEXT").await?;
+        Self::add_column_if_missing(pool, "sync_jobs", "source", "TEXT").await?;
+        Self::allow_skipped_status(pool).await?;
+        Self::add_column_if_missing(
+            pool,
+            "ignore_stats",
+            "temp_events",
+            "INTEGER NOT NULL DEFAULT 0",
+        )
+        .await?;
+
+        // Retries scheduled before the columns existed keep their deadline
+        sqlx::query(
+            r#"
+            UPDATE sync_jobs
+            SET retry_due_ms = CAST(strftime('%s', retry_at) AS INTEGER) * 1000,
+                retry_delay_ms = 0
+            WHERE retry_at IS NOT NULL AND retry_due_ms IS NULL
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_sync_jobs_status ON sync_jobs(status, created_at);
+            CREATE INDEX IF NOT EXISTS idx_sync_jobs_retry_at ON sync_jobs(retry_at);
+            CREATE INDEX IF NOT EXISTS idx_sync_jobs_updated_at ON sync_jobs(updated_at);
+            CREATE INDEX IF NOT EXISTS idx_sync_jobs_priority ON sync_jobs(status, priority, created_at);
+            CREATE INDEX IF NOT EXISTS idx_sync_jobs_local_path ON sync_jobs(local_path);
             "#,
         )
         .execute(pool)
@@ -99,6 +408,96 @@ impl Db {
         Ok(())
     }
 
+    /// Check whether a database has every table, column and index the
+    /// migrations create
+    async fn is_current(pool: &SqlitePool) -> Result<bool> {
+        let current = CURRENT_SCHEMA
+            .get_or_try_init(|| async {
+                // One connection, since each opens its own in-memory database
+                let fresh = SqlitePoolOptions::new()
+                    .max_connections(1)
+                    .connect("sqlite::memory:")
+                    .await?;
+                Self::run_migrations(&fresh).await?;
+                let objects = Self::schema_objects(&fresh).await;
+                fresh.close().await;
+                objects
+            })
+            .await?;
+
+        Ok(current.is_subset(&Self::schema_objects(pool).await?))
+    }
+
+    async fn schema_objects(pool: &SqlitePool) -> Result<HashSet<(String, String)>> {
+        let rows: Vec<(String, String)> =
+            sqlx::query_as(SCHEMA_OBJECTS_QUERY).fetch_all(pool).await?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Rebuild `sync_jobs` if its status check predates skipped jobs
+    ///
+    /// SQLite cannot change a check constraint in place, so the jobs are
+    /// copied into a new table. Its indexes are created again afterwards.
+    async fn allow_skipped_status(pool: &SqlitePool) -> Result<()> {
+        let schema: String = sqlx::query_scalar(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'sync_jobs'",
+        )
+        .fetch_one(pool)
+        .await?;
+
+        if schema.contains("'SKIPPED'") {
+            return Ok(());
+        }
+
+        let columns = format!("{}, priority, retry_due_ms, retry_delay_ms", JOB_COLUMNS);
+        let mut tx = pool.begin().await?;
+        sqlx::query(&format!("CREATE TABLE sync_jobs_new {}", SYNC_JOBS_SCHEMA))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&format!(
+            "INSERT INTO sync_jobs_new ({columns}) SELECT {columns} FROM sync_jobs"
+        ))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DROP TABLE sync_jobs")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("ALTER TABLE sync_jobs_new RENAME TO sync_jobs")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Add a column to an existing table if it is not there yet
+    async fn add_column_if_missing(
+        pool: &SqlitePool,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<()> {
+        let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+            .fetch_all(pool)
+            .await?;
+
+        let exists = columns.iter().any(|row| {
+            row.try_get::<String, _>("name")
+                .is_ok_and(|name| name == column)
+        });
+
+        if !exists {
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, column, definition
+            ))
+            .execute(pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
     /// Get the underlying pool
     pub fn pool(&self) -> &SqlitePool {
         &self.pool
@@ -161,11 +560,215 @@ impl Db {
     // === Sync job operations ===
 
     /// Enqueue a sync job
-    pub async fn enqueue_job(&self, job: &SyncEvent) -> Result<i64> {
-        let result = sqlx::query(
-            "INSERT INTO sync_jobs (event_type, local_path, remote_path, status, change_token, old_local_path, old_remote_path)
-             VALUES (?, ?, ?, ?, ?, ?, ?)",
-        )
+    ///
+    /// An event for a path that already has a pending job is merged into
+    /// that job instead of queuing another (see [`collapse_events`]). Returns
+    /// the ID of the job carrying the event, or `None` if the event cancelled
+    /// the pending job.
+    pub async fn enqueue_job(&self, job: &SyncEvent, priority: JobPriority) -> Result<Option<i64>> {
+        self.enqueue_job_from(job, priority, JobSource::Engine)
+            .await
+    }
+
+    /// Enqueue a sync job, recording what queued it
+    ///
+    /// A merged event keeps the source of the job it was merged into.
+    pub async fn enqueue_job_from(
+        &self,
+        job: &SyncEvent,
+        priority: JobPriority,
+        source: JobSource,
+    ) -> Result<Option<i64>> {
+        let mut tx = self.pool.begin().await?;
+        let id = Self::enqueue_job_in(&mut tx, job, priority, source).await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    /// Enqueue many sync jobs in one transaction, in order
+    ///
+    /// Jobs for paths with a pending job, or touched earlier in the batch, are
+    /// merged one by one as in [`Db::enqueue_job_from`]; the others are
+    /// inserted with multi-row inserts.
+    pub async fn enqueue_jobs_batch(
+        &self,
+        jobs: &[(SyncEvent, JobPriority)],
+        source: JobSource,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        // Paths that already have a pending job
+        let mut contended = HashSet::new();
+        let paths: Vec<&str> = jobs
+            .iter()
+            .filter(|(job, _)| job.old_local_path.is_none())
+            .map(|(job, _)| job.local_path.as_str())
+            .collect();
+        for chunk in paths.chunks(BATCH_LOOKUP_SIZE) {
+            let placeholders = (1..=chunk.len())
+                .map(|i| format!("?{}", i))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let sql = format!(
+                "SELECT local_path, old_local_path FROM sync_jobs
+                 WHERE status = 'PENDING'
+                   AND (local_path IN ({0}) OR old_local_path IN ({0}))",
+                placeholders
+            );
+            let mut query = sqlx::query(&sql);
+            for path in chunk {
+                query = query.bind(*path);
+            }
+            for row in query.fetch_all(&mut *tx).await? {
+                contended.insert(row.try_get::<String, _>("local_path")?);
+                if let Some(old) = row.try_get::<Option<String>, _>("old_local_path")? {
+                    contended.insert(old);
+                }
+            }
+        }
+
+        // A job for a path an earlier one touched must merge too
+        let mut inserts: Vec<(&SyncEvent, JobPriority)> = Vec::new();
+        for (job, priority) in jobs {
+            let merges = job.old_local_path.is_none() && contended.contains(&job.local_path);
+            contended.insert(job.local_path.clone());
+            contended.extend(job.old_local_path.clone());
+            if !merges {
+                inserts.push((job, *priority));
+                if inserts.len() == BATCH_INSERT_SIZE {
+                    Self::insert_jobs(&mut tx, &inserts, source).await?;
+                    inserts.clear();
+                }
+                continue;
+            }
+
+            Self::insert_jobs(&mut tx, &inserts, source).await?;
+            inserts.clear();
+            Self::enqueue_job_in(&mut tx, job, *priority, source).await?;
+        }
+        Self::insert_jobs(&mut tx, &inserts, source).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Insert pending jobs with one multi-row insert
+    async fn insert_jobs(
+        conn: &mut sqlx::SqliteConnection,
+        jobs: &[(&SyncEvent, JobPriority)],
+        source: JobSource,
+    ) -> Result<()> {
+        if jobs.is_empty() {
+            return Ok(());
+        }
+
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+            "INSERT INTO sync_jobs (event_type, local_path, remote_path, status, change_token, old_local_path, old_remote_path, priority, source, updated_at) ",
+        );
+        query.push_values(jobs, |mut row, (job, priority)| {
+            row.push_bind(job.event_type.to_string())
+                .push_bind(&job.local_path)
+                .push_bind(&job.remote_path)
+                .push_bind(SyncJobStatus::Pending.to_string())
+                .push_bind(&job.change_token)
+                .push_bind(&job.old_local_path)
+                .push_bind(&job.old_remote_path)
+                .push_bind(*priority as i64)
+                .push_bind(source.to_string())
+                .push(DB_NOW);
+        });
+        query.build().execute(&mut *conn).await?;
+
+        Ok(())
+    }
+
+    /// Enqueue a sync job inside a transaction
+    async fn enqueue_job_in(
+        conn: &mut sqlx::SqliteConnection,
+        job: &SyncEvent,
+        priority: JobPriority,
+        source: JobSource,
+    ) -> Result<Option<i64>> {
+        // Only the latest pending job touching the path may absorb the event;
+        // merging into an earlier one would reorder it around a move
+        let latest = if job.old_local_path.is_none() {
+            sqlx::query(
+                r#"
+                SELECT id, event_type, local_path, old_local_path
+                FROM sync_jobs
+                WHERE status = 'PENDING' AND (local_path = ?1 OR old_local_path = ?1)
+                ORDER BY id DESC
+                LIMIT 1
+                "#,
+            )
+            .bind(&job.local_path)
+            .fetch_optional(&mut *conn)
+            .await?
+        } else {
+            None
+        };
+
+        if let Some(row) = latest {
+            let id: i64 = row.try_get("id")?;
+            let local_path: String = row.try_get("local_path")?;
+            let old_local_path: Option<String> = row.try_get("old_local_path")?;
+            let event_type: String = row.try_get("event_type")?;
+
+            if local_path == job.local_path && old_local_path.is_none() {
+                let has_remote: bool = sqlx::query_scalar(
+                    "SELECT EXISTS(SELECT 1 FROM node_mapping WHERE local_path = ?)",
+                )
+                .bind(&job.local_path)
+                .fetch_one(&mut *conn)
+                .await?;
+
+                match collapse_events(
+                    parse_sync_event_type(&event_type),
+                    job.event_type,
+                    has_remote,
+                ) {
+                    Some(Collapsed::Into(event_type)) => {
+                        let priority = match event_type {
+                            SyncEventType::CreateDir | SyncEventType::Delete => {
+                                JobPriority::Metadata
+                            }
+                            _ => priority,
+                        };
+
+                        sqlx::query(&format!(
+                            "UPDATE sync_jobs
+                             SET event_type = ?, remote_path = ?, change_token = ?, priority = ?, updated_at = {}
+                             WHERE id = ?",
+                            DB_NOW
+                        ))
+                        .bind(event_type.to_string())
+                        .bind(&job.remote_path)
+                        .bind(&job.change_token)
+                        .bind(priority as i64)
+                        .bind(id)
+                        .execute(&mut *conn)
+                        .await?;
+
+                        return Ok(Some(id));
+                    }
+                    Some(Collapsed::Cancelled) => {
+                        sqlx::query("DELETE FROM sync_jobs WHERE id = ?")
+                            .bind(id)
+                            .execute(&mut *conn)
+                            .await?;
+
+                        return Ok(None);
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        let result = sqlx::query(&format!(
+            "INSERT INTO sync_jobs (event_type, local_path, remote_path, status, change_token, old_local_path, old_remote_path, priority, source, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, {})",
+            DB_NOW
+        ))
         .bind(job.event_type.to_string())
         .bind(&job.local_path)
         .bind(&job.remote_path)
@@ -173,102 +776,486 @@ impl Db {
         .bind(&job.change_token)
         .bind(&job.old_local_path)
         .bind(&job.old_remote_path)
-        .execute(&self.pool)
+        .bind(priority as i64)
+        .bind(source.to_string())
+        .execute(&mut *conn)
         .await?;
 
-        Ok(result.last_insert_rowid())
+        Ok(Some(result.last_insert_rowid()))
     }
 
-    /// Get pending jobs
-    pub async fn get_pending_jobs(&self, limit: i64) -> Result<Vec<SyncJob>> {
-        let rows = sqlx::query(
+    /// Find a job the watcher queued for the same change to a file
+    ///
+    /// Matches jobs with the same path and change token that are queued,
+    /// processing or waiting for a retry, or were synced within `window`.
+    pub async fn find_watcher_job(
+        &self,
+        local_path: &str,
+        change_token: &str,
+        window: chrono::Duration,
+    ) -> Result<Option<i64>> {
+        let id = sqlx::query_scalar::<_, i64>(
             r#"
-            SELECT id, event_type, local_path, remote_path,
-                   status, retry_at, n_retries, last_error,
-                   change_token, old_local_path, old_remote_path, created_at
-            FROM sync_jobs
-            WHERE status = 'PENDING'
-               OR (status = 'PROCESSING' AND retry_at < datetime('now'))
-            ORDER BY created_at ASC
-            LIMIT ?
+            SELECT id FROM sync_jobs
+            WHERE local_path = ? AND change_token = ? AND source = ?
+              AND (status IN ('PENDING', 'PROCESSING')
+                   OR (status = 'SYNCED'
+                       AND completed_at >= datetime('now', '-' || ? || ' seconds')))
+            ORDER BY id DESC
+            LIMIT 1
             "#,
         )
-        .bind(limit)
-        .fetch_all(&self.pool)
+        .bind(local_path)
+        .bind(change_token)
+        .bind(JobSource::Watcher.to_string())
+        .bind(window.num_seconds())
+        .fetch_optional(&self.pool)
         .await?;
 
-        let jobs = rows
-            .into_iter()
-            .map(|row| {
-                let event_type_str: String = row.try_get("event_type").map_err(Error::Database)?;
-                let status_str: String = row.try_get("status").map_err(Error::Database)?;
-
-                Ok(SyncJob {
-                    id: row.try_get("id").map_err(Error::Database)?,
-                    event_type: parse_sync_event_type(&event_type_str),
-                    local_path: row.try_get("local_path").map_err(Error::Database)?,
-                    remote_path: row.try_get("remote_path").map_err(Error::Database)?,
-                    status: parse_sync_job_status(&status_str),
-                    retry_at: row.try_get("retry_at").ok(),
-                    n_retries: row.try_get("n_retries").map_err(Error::Database)?,
-                    last_error: row.try_get("last_error").ok(),
-                    change_token: row.try_get("change_token").ok(),
-                    old_local_path: row.try_get("old_local_path").ok(),
-                    old_remote_path: row.try_get("old_remote_path").ok(),
-                    created_at: row.try_get("created_at").map_err(Error::Database)?,
-                })
-            })
-            .collect::<Result<Vec<SyncJob>>>()?;
-
-        Ok(jobs)
+        Ok(id)
     }
 
-    /// Update job status
-    pub async fn update_job_status(
+    /// Drop pending jobs made redundant by a rename
+    ///
+    /// Platforms that report a rename as a pair of events also queue a delete
+    /// of the old path and an upload of the new one; the move job replaces
+    /// both.
+    pub async fn cancel_rename_halves(
         &self,
-        id: i64,
-        status: SyncJobStatus,
-        error: Option<&str>,
-    ) -> Result<()> {
-        sqlx::query("UPDATE sync_jobs SET status = ?, last_error = ? WHERE id = ?")
-            .bind(status.to_string())
-            .bind(error)
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
-    }
+        old_local_path: &str,
+        new_local_path: &str,
+    ) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM sync_jobs
+            WHERE status = 'PENDING'
+              AND old_local_path IS NULL
+              AND ((event_type = 'DELETE' AND local_path = ?)
+                OR (event_type = 'UPDATE' AND local_path = ?))
+            "#,
+        )
+        .bind(old_local_path)
+        .bind(new_local_path)
+        .execute(&self.pool)
+        .await?;
 
-    /// Mark job as processing
-    pub async fn mark_job_processing(&self, id: i64) -> Result<()> {
-        sqlx::query("UPDATE sync_jobs SET status = ?, retry_at = NULL WHERE id = ?")
-            .bind(SyncJobStatus::Processing.to_string())
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
+        Ok(result.rows_affected())
     }
 
-    /// Increment job retry count
-    pub async fn increment_job_retry(&self, id: i64, retry_at: DateTime<Utc>) -> Result<()> {
-        sqlx::query("UPDATE sync_jobs SET n_retries = n_retries + 1, retry_at = ? WHERE id = ?")
-            .bind(retry_at)
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
-    }
+    /// Get pending jobs, and jobs whose retry delay has passed
+    pub async fn get_pending_jobs(&self, limit: i64) -> Result<Vec<SyncJob>> {
+        let now_ms = self.clock.now().timestamp_millis();
 
-    /// Delete completed jobs
-    pub async fn delete_completed_jobs(&self, older_than: chrono::Duration) -> Result<u64> {
-        let result = sqlx::query(
-            "DELETE FROM sync_jobs WHERE status = 'SYNCED' AND created_at < datetime('now', '-' || ? || ' seconds')",
+        // A deadline further away than its delay was set before the clock
+        // went backwards; count the delay from now instead
+        sqlx::query(
+            r#"
+            UPDATE sync_jobs
+            SET retry_due_ms = ?1 + retry_delay_ms
+            WHERE status = 'PROCESSING' AND retry_due_ms > ?1 + retry_delay_ms
+            "#,
         )
-        .bind(older_than.num_seconds())
+        .bind(now_ms)
         .execute(&self.pool)
         .await?;
 
-        Ok(result.rows_affected())
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM sync_jobs j
+            WHERE (status = 'PENDING'
+                   OR (status = 'PROCESSING' AND retry_due_ms <= ?2))
+              -- Jobs for the same path still run in the order they were queued
+              AND NOT EXISTS (
+                  SELECT 1 FROM sync_jobs e
+                  WHERE e.id < j.id
+                    AND e.status IN ('PENDING', 'PROCESSING')
+                    AND (e.local_path IN (j.local_path, j.old_local_path)
+                         OR e.old_local_path IN (j.local_path, j.old_local_path))
+              )
+            ORDER BY priority ASC, created_at ASC, id ASC
+            LIMIT ?1
+            "#,
+            JOB_COLUMNS
+        ))
+        .bind(limit)
+        .bind(now_ms)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(row_to_job).collect()
+    }
+
+    /// Get a job by ID
+    pub async fn get_job(&self, id: i64) -> Result<Option<SyncJob>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM sync_jobs WHERE id = ?",
+            JOB_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(row_to_job).transpose()
+    }
+
+    /// Get all jobs ordered by ID
+    pub async fn get_all_jobs(&self) -> Result<Vec<SyncJob>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM sync_jobs ORDER BY id ASC",
+            JOB_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(row_to_job).collect()
+    }
+
+    /// Get jobs that finished (synced or blocked) after the given completion
+    ///
+    /// Jobs are ordered by completion time then ID, and the cursor is the
+    /// last pair seen, so jobs completing in the same millisecond are all
+    /// returned exactly once.
+    pub async fn get_jobs_completed_after(
+        &self,
+        after: DateTime<Utc>,
+        after_id: i64,
+    ) -> Result<Vec<SyncJob>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM sync_jobs WHERE (completed_at, id) > (?, ?) ORDER BY completed_at ASC, id ASC",
+            JOB_COLUMNS
+        ))
+        .bind(format_db_datetime(after))
+        .bind(after_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(row_to_job).collect()
+    }
+
+    /// Get jobs whose state changed after the given time, oldest change first
+    ///
+    /// Jobs changed at the same time are ordered by ID, so a caller that got
+    /// only part of them passes the last ID to continue with the rest.
+    pub async fn get_jobs_updated_after(
+        &self,
+        after: DateTime<Utc>,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<SyncJob>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM sync_jobs WHERE (updated_at, id) > (?, ?) ORDER BY updated_at ASC, id ASC LIMIT ?",
+            JOB_COLUMNS
+        ))
+        .bind(format_db_datetime(after))
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(row_to_job).collect()
+    }
+
+    /// Get jobs with the given status, most recently updated first
+    pub async fn get_jobs_by_status(
+        &self,
+        status: SyncJobStatus,
+        limit: i64,
+    ) -> Result<Vec<SyncJob>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM sync_jobs WHERE status = ? ORDER BY updated_at DESC, id DESC LIMIT ?",
+            JOB_COLUMNS
+        ))
+        .bind(status.to_string())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(row_to_job).collect()
+    }
+
+    /// List jobs, oldest first
+    ///
+    /// Without a status filter, synced jobs are left out.
+    pub async fn list_jobs(
+        &self,
+        status: Option<SyncJobStatus>,
+        limit: i64,
+    ) -> Result<Vec<SyncJob>> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {} FROM sync_jobs
+            WHERE (CASE WHEN ?1 IS NULL THEN status != 'SYNCED' ELSE status = ?1 END)
+            ORDER BY created_at ASC, id ASC
+            LIMIT ?2
+            "#,
+            JOB_COLUMNS
+        ))
+        .bind(status.map(|s| s.to_string()))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(row_to_job).collect()
+    }
+
+    /// Get unsynced jobs for a local directory and everything below it, oldest first
+    ///
+    /// Matches whole path components: `/home/a` does not match `/home/ab`.
+    pub async fn get_jobs_under(&self, dir: &str) -> Result<Vec<SyncJob>> {
+        let (dir, children) = subtree_prefix(dir);
+
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {} FROM sync_jobs
+            WHERE status != 'SYNCED'
+              AND (local_path = ?1 OR substr(local_path, 1, length(?2)) = ?2)
+            ORDER BY created_at ASC, id ASC
+            "#,
+            JOB_COLUMNS
+        ))
+        .bind(dir)
+        .bind(children)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(row_to_job).collect()
+    }
+
+    /// Drop pending and blocked jobs for a local directory and everything below it
+    ///
+    /// Jobs already claimed for processing are left to finish. Returns the
+    /// number of jobs dropped.
+    pub async fn cancel_jobs_under(&self, dir: &str) -> Result<u64> {
+        let (dir, children) = subtree_prefix(dir);
+
+        let result = sqlx::query(
+            r#"
+            DELETE FROM sync_jobs
+            WHERE status IN ('PENDING', 'BLOCKED')
+              AND (local_path = ?1 OR substr(local_path, 1, length(?2)) = ?2)
+            "#,
+        )
+        .bind(dir)
+        .bind(children)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Requeue a blocked job with a fresh retry budget
+    ///
+    /// Returns false if no blocked job has the given ID.
+    pub async fn retry_blocked_job(&self, id: i64) -> Result<bool> {
+        Ok(self.requeue_blocked_jobs(Some(id), true).await? > 0)
+    }
+
+    /// Requeue blocked jobs, or only the one with the given ID
+    ///
+    /// With `reset_retries` the jobs get a fresh retry budget; otherwise they
+    /// keep their retry count. Returns the number of jobs requeued.
+    pub async fn requeue_blocked_jobs(&self, id: Option<i64>, reset_retries: bool) -> Result<u64> {
+        let result = sqlx::query(&format!(
+            r#"
+            UPDATE sync_jobs
+            SET status = 'PENDING',
+                n_retries = CASE WHEN ?1 THEN 0 ELSE n_retries END,
+                retry_at = NULL,
+                retry_due_ms = NULL,
+                retry_delay_ms = NULL,
+                last_error = NULL,
+                completed_at = NULL,
+                updated_at = {}
+            WHERE status = 'BLOCKED' AND (?2 IS NULL OR id = ?2)
+            "#,
+            DB_NOW
+        ))
+        .bind(reset_retries)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Requeue a skipped job whose path can be read again
+    ///
+    /// The job gets a fresh retry budget and the path's current change
+    /// token. Returns false if no skipped job has the given ID.
+    pub async fn requeue_skipped_job(&self, id: i64, change_token: &str) -> Result<bool> {
+        let result = sqlx::query(&format!(
+            r#"
+            UPDATE sync_jobs
+            SET status = 'PENDING',
+                n_retries = 0,
+                retry_at = NULL,
+                retry_due_ms = NULL,
+                retry_delay_ms = NULL,
+                last_error = NULL,
+                skip_reason = NULL,
+                change_token = ?2,
+                updated_at = {}
+            WHERE status = 'SKIPPED' AND id = ?1
+            "#,
+            DB_NOW
+        ))
+        .bind(id)
+        .bind(change_token)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Forget the skipped jobs of a path, e.g. once it is gone or readable again
+    pub async fn delete_skipped_jobs(&self, local_path: &str) -> Result<()> {
+        sqlx::query("DELETE FROM sync_jobs WHERE status = 'SKIPPED' AND local_path = ?")
+            .bind(local_path)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Update job status
+    pub async fn update_job_status(
+        &self,
+        id: i64,
+        status: SyncJobStatus,
+        error: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(&format!(
+            r#"
+            UPDATE sync_jobs
+            SET status = ?1,
+                last_error = ?2,
+                skip_reason = ?4,
+                updated_at = {now},
+                completed_at = CASE WHEN ?1 IN ('SYNCED', 'BLOCKED')
+                                    THEN {now}
+                                    ELSE NULL END
+            WHERE id = ?3
+            "#,
+            now = DB_NOW
+        ))
+        .bind(status.to_string())
+        .bind(error)
+        .bind(id)
+        .bind(match status {
+            SyncJobStatus::Skipped(reason) => Some(reason.to_string()),
+            _ => None,
+        })
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Claim a job for processing, returning it as it now stands
+    ///
+    /// The job is marked as processing in the same statement, so events
+    /// queued from then on no longer merge into it. Returns `None` if the
+    /// job was cancelled, merged away or claimed in the meantime.
+    pub async fn claim_job(&self, id: i64) -> Result<Option<SyncJob>> {
+        let row = sqlx::query(&format!(
+            r#"
+            UPDATE sync_jobs
+            SET status = 'PROCESSING', retry_at = NULL, retry_due_ms = NULL, retry_delay_ms = NULL,
+                updated_at = {}
+            WHERE id = ?1
+              AND (status = 'PENDING' OR (status = 'PROCESSING' AND retry_due_ms <= ?2))
+            RETURNING {}
+            "#,
+            DB_NOW, JOB_COLUMNS
+        ))
+        .bind(id)
+        .bind(self.clock.now().timestamp_millis())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(row_to_job).transpose()
+    }
+
+    /// Requeue jobs claimed by a previous run that never finished them
+    pub async fn requeue_claimed_jobs(&self) -> Result<u64> {
+        let result = sqlx::query(&format!(
+            "UPDATE sync_jobs SET status = 'PENDING', updated_at = {} WHERE status = 'PROCESSING' AND retry_due_ms IS NULL",
+            DB_NOW
+        ))
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Mark job as processing
+    pub async fn mark_job_processing(&self, id: i64) -> Result<()> {
+        sqlx::query(&format!(
+            "UPDATE sync_jobs SET status = ?, retry_at = NULL, retry_due_ms = NULL, retry_delay_ms = NULL, updated_at = {} WHERE id = ?",
+            DB_NOW
+        ))
+        .bind(SyncJobStatus::Processing.to_string())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Increment job retry count and schedule the retry after a delay
+    ///
+    /// Returns when the retry is due, for display.
+    pub async fn increment_job_retry(
+        &self,
+        id: i64,
+        delay: std::time::Duration,
+    ) -> Result<DateTime<Utc>> {
+        let delay = chrono::Duration::from_std(delay)?;
+        let retry_at = self.clock.now() + delay;
+
+        sqlx::query(&format!(
+            r#"
+            UPDATE sync_jobs
+            SET n_retries = n_retries + 1,
+                retry_at = ?,
+                retry_due_ms = ?,
+                retry_delay_ms = ?,
+                updated_at = {}
+            WHERE id = ?
+            "#,
+            DB_NOW
+        ))
+        .bind(retry_at)
+        .bind(retry_at.timestamp_millis())
+        .bind(delay.num_milliseconds())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(retry_at)
+    }
+
+    /// Count jobs waiting for a retry, with when the first one is due
+    pub async fn get_next_retry(&self) -> Result<Option<(i64, DateTime<Utc>)>> {
+        let (count, due_ms) = sqlx::query_as::<_, (i64, Option<i64>)>(
+            r#"
+            SELECT COUNT(*), MIN(retry_due_ms)
+            FROM sync_jobs
+            WHERE status = 'PROCESSING' AND retry_due_ms IS NOT NULL
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(due_ms
+            .and_then(DateTime::from_timestamp_millis)
+            .map(|due| (count, due)))
+    }
+
+    /// Delete completed jobs
+    pub async fn delete_completed_jobs(&self, older_than: chrono::Duration) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM sync_jobs WHERE status = 'SYNCED' AND created_at < datetime('now', '-' || ? || ' seconds')",
+        )
+        .bind(older_than.num_seconds())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
     }
 
     /// Get job count by status
@@ -365,39 +1352,91 @@ impl Db {
         local_path: &str,
         remote_path: &str,
     ) -> Result<Option<NodeMapping>> {
-        let row = sqlx::query(
+        let row = sqlx::query(&format!(
             r#"
-            SELECT local_path, remote_path, node_uid, parent_node_uid, is_directory, updated_at
+            SELECT {}
             FROM node_mapping
             WHERE local_path = ? AND remote_path = ?
             "#,
-        )
+            NODE_MAPPING_COLUMNS
+        ))
         .bind(local_path)
         .bind(remote_path)
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(row.map(|r| {
-            let local_path: String = r
-                .try_get("local_path")
-                .unwrap_or_else(|_| local_path.to_string());
-            let remote_path: String = r
-                .try_get("remote_path")
-                .unwrap_or_else(|_| remote_path.to_string());
-            let node_uid: String = r.try_get("node_uid").unwrap_or_default();
-            let parent_node_uid: String = r.try_get("parent_node_uid").unwrap_or_default();
-            let is_directory: bool = r.try_get("is_directory").unwrap_or(false);
-            let updated_at: DateTime<Utc> = r.try_get("updated_at").unwrap_or_else(|_| Utc::now());
+        Ok(row.map(|r| row_to_node_mapping(&r)))
+    }
 
-            NodeMapping {
-                local_path,
-                remote_path,
-                node_uid,
-                parent_node_uid,
-                is_directory,
-                updated_at,
-            }
-        }))
+    /// Node mapping for a local path, matching ASCII letters in either case
+    ///
+    /// For case-insensitive filesystems, where `Report.txt` and `report.txt`
+    /// name the same file. An exact match is preferred.
+    pub async fn get_node_mapping_ignoring_case(
+        &self,
+        local_path: &str,
+    ) -> Result<Option<NodeMapping>> {
+        let row = sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM node_mapping
+            WHERE local_path = ?1 COLLATE NOCASE
+            ORDER BY local_path = ?1 DESC
+            LIMIT 1
+            "#,
+            NODE_MAPPING_COLUMNS
+        ))
+        .bind(local_path)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| row_to_node_mapping(&r)))
+    }
+
+    /// Stored change tokens of the given local paths, by local path
+    pub async fn get_change_tokens(
+        &self,
+        local_paths: &[String],
+    ) -> Result<HashMap<String, String>> {
+        if local_paths.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+            "SELECT local_path, change_token FROM file_state WHERE local_path IN (",
+        );
+        let mut paths = query.separated(", ");
+        for path in local_paths {
+            paths.push_bind(path);
+        }
+        query.push(")");
+
+        let rows: Vec<(String, String)> = query.build_query_as().fetch_all(&self.pool).await?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Remote paths the given local paths are synced to, by local path
+    ///
+    /// A path with several mappings, as during a move, gets the newest.
+    pub async fn get_remote_paths(
+        &self,
+        local_paths: &[String],
+    ) -> Result<HashMap<String, String>> {
+        if local_paths.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut query = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+            "SELECT local_path, remote_path FROM node_mapping WHERE local_path IN (",
+        );
+        let mut paths = query.separated(", ");
+        for path in local_paths {
+            paths.push_bind(path);
+        }
+        query.push(") ORDER BY updated_at");
+
+        let rows: Vec<(String, String)> = query.build_query_as().fetch_all(&self.pool).await?;
+        Ok(rows.into_iter().collect())
     }
 
     /// Update node mapping
@@ -432,42 +1471,143 @@ impl Db {
 
     /// Get all node mappings for a path prefix
     pub async fn get_node_mappings_under(&self, path_prefix: &str) -> Result<Vec<NodeMapping>> {
-        let rows = sqlx::query(
+        let rows = sqlx::query(&format!(
             r#"
-            SELECT local_path, remote_path, node_uid, parent_node_uid, is_directory, updated_at
+            SELECT {}
             FROM node_mapping
             WHERE local_path LIKE ? || '%'
             "#,
-        )
+            NODE_MAPPING_COLUMNS
+        ))
         .bind(path_prefix)
         .fetch_all(&self.pool)
         .await?;
 
+        let mappings = rows.into_iter().map(|r| row_to_node_mapping(&r)).collect();
+
+        Ok(mappings)
+    }
+
+    /// Node mappings under `roots` whose local file or folder no longer exists
+    ///
+    /// Paths with a pending or running job are left out, since that job
+    /// settles the mapping itself. A path whose existence cannot be checked
+    /// counts as existing, and nothing under a root that is missing, e.g. an
+    /// unmounted drive, or outside every root is listed.
+    pub async fn get_orphan_node_mappings(&self, roots: &[&Path]) -> Result<Vec<NodeMapping>> {
+        let roots: Vec<&Path> = roots.iter().copied().filter(|r| r.is_dir()).collect();
+
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM node_mapping
+            WHERE local_path NOT IN (
+                SELECT local_path FROM sync_jobs WHERE status IN ('PENDING', 'PROCESSING')
+                UNION
+                SELECT old_local_path FROM sync_jobs
+                WHERE status IN ('PENDING', 'PROCESSING') AND old_local_path IS NOT NULL
+            )
+            ORDER BY local_path
+            "#,
+            NODE_MAPPING_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
         let mappings = rows
             .into_iter()
-            .map(|r| {
-                let local_path: String = r.try_get("local_path").unwrap_or_default();
-                let remote_path: String = r.try_get("remote_path").unwrap_or_default();
-                let node_uid: String = r.try_get("node_uid").unwrap_or_default();
-                let parent_node_uid: String = r.try_get("parent_node_uid").unwrap_or_default();
-                let is_directory: bool = r.try_get("is_directory").unwrap_or(false);
-                let updated_at: DateTime<Utc> =
-                    r.try_get("updated_at").unwrap_or_else(|_| Utc::now());
-
-                NodeMapping {
-                    local_path,
-                    remote_path,
-                    node_uid,
-                    parent_node_uid,
-                    is_directory,
-                    updated_at,
-                }
+            .map(|r| row_to_node_mapping(&r))
+            .filter(|m| {
+                let local = Path::new(&m.local_path);
+                roots.iter().any(|root| local.starts_with(root))
+                    && !local.try_exists().unwrap_or(true)
             })
             .collect();
 
         Ok(mappings)
     }
 
+    /// Get the node mapping for a remote path
+    pub async fn get_node_mapping_by_remote_path(
+        &self,
+        remote_path: &str,
+    ) -> Result<Option<NodeMapping>> {
+        let row = sqlx::query(&format!(
+            r#"
+            SELECT {}
+            FROM node_mapping
+            WHERE remote_path = ?
+            LIMIT 1
+            "#,
+            NODE_MAPPING_COLUMNS
+        ))
+        .bind(remote_path)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| row_to_node_mapping(&r)))
+    }
+
+    /// Re-key a moved node and everything below it
+    ///
+    /// Rewrites the local and remote path prefixes of node mappings and file
+    /// states, and points the moved node at its new parent. Returns the number
+    /// of node mappings updated.
+    pub async fn move_node_mappings(
+        &self,
+        old_local: &str,
+        new_local: &str,
+        old_remote: &str,
+        new_remote: &str,
+        new_parent_uid: &str,
+    ) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let separator = std::path::MAIN_SEPARATOR.to_string();
+
+        let mappings = sqlx::query(
+            r#"
+            UPDATE OR REPLACE node_mapping
+            SET local_path = ?2 || substr(local_path, length(?1) + 1),
+                remote_path = ?4 || substr(remote_path, length(?3) + 1),
+                updated_at = datetime('now')
+            WHERE (local_path = ?1 OR substr(local_path, 1, length(?1) + 1) = ?1 || ?5)
+              AND (remote_path = ?3 OR substr(remote_path, 1, length(?3) + 1) = ?3 || '/')
+            "#,
+        )
+        .bind(old_local)
+        .bind(new_local)
+        .bind(old_remote)
+        .bind(new_remote)
+        .bind(&separator)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "UPDATE node_mapping SET parent_node_uid = ? WHERE local_path = ? AND remote_path = ?",
+        )
+        .bind(new_parent_uid)
+        .bind(new_local)
+        .bind(new_remote)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE OR REPLACE file_state
+            SET local_path = ?2 || substr(local_path, length(?1) + 1)
+            WHERE local_path = ?1 OR substr(local_path, 1, length(?1) + 1) = ?1 || ?3
+            "#,
+        )
+        .bind(old_local)
+        .bind(new_local)
+        .bind(&separator)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(mappings.rows_affected())
+    }
+
     // === Processing queue operations ===
 
     /// Add to processing queue
@@ -499,26 +1639,2036 @@ impl Db {
 
         Ok(result.rows_affected())
     }
-}
 
-/// Helper function to parse SyncEventType from string
-fn parse_sync_event_type(s: &str) -> SyncEventType {
-    match s {
-        "CREATE_FILE" => SyncEventType::CreateFile,
-        "CREATE_DIR" => SyncEventType::CreateDir,
-        "UPDATE" => SyncEventType::Update,
-        "DELETE" => SyncEventType::Delete,
-        _ => SyncEventType::Update, // Default fallback
+    // === Upload progress operations ===
+
+    /// Record how far an upload has got
+    pub async fn set_job_progress(
+        &self,
+        job_id: i64,
+        local_path: &str,
+        bytes_sent: u64,
+        total_bytes: u64,
+    ) -> Result<()> {
+        sqlx::query(&format!(
+            r#"
+            INSERT INTO job_progress (job_id, local_path, bytes_sent, total_bytes, updated_at)
+            VALUES (?, ?, ?, ?, {})
+            ON CONFLICT(job_id) DO UPDATE SET
+                bytes_sent = excluded.bytes_sent,
+                total_bytes = excluded.total_bytes,
+                updated_at = excluded.updated_at
+            "#,
+            DB_NOW
+        ))
+        .bind(job_id)
+        .bind(local_path)
+        .bind(bytes_sent as i64)
+        .bind(total_bytes as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
     }
-}
 
-/// Helper function to parse SyncJobStatus from string
-fn parse_sync_job_status(s: &str) -> SyncJobStatus {
-    match s {
-        "PENDING" => SyncJobStatus::Pending,
-        "PROCESSING" => SyncJobStatus::Processing,
-        "SYNCED" => SyncJobStatus::Synced,
-        "BLOCKED" => SyncJobStatus::Blocked,
+    /// Forget an upload's progress once it has finished
+    pub async fn clear_job_progress(&self, job_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM job_progress WHERE job_id = ?")
+            .bind(job_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Forget progress left behind by uploads that were interrupted
+    pub async fn clear_all_job_progress(&self) -> Result<()> {
+        sqlx::query("DELETE FROM job_progress")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Get the progress of all in-flight uploads, oldest job first
+    pub async fn get_job_progress(&self) -> Result<Vec<JobProgress>> {
+        let rows = sqlx::query(
+            "SELECT job_id, local_path, bytes_sent, total_bytes FROM job_progress ORDER BY job_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(JobProgress {
+                    job_id: row.try_get("job_id")?,
+                    local_path: row.try_get("local_path")?,
+                    bytes_sent: row.try_get::<i64, _>("bytes_sent")? as u64,
+                    total_bytes: row.try_get::<i64, _>("total_bytes")? as u64,
+                })
+            })
+            .collect()
+    }
+
+    // === Ignore stats operations ===
+
+    /// Record how many entries `.protonignore` excluded in a sync directory
+    pub async fn set_ignored_count(&self, sync_dir: &str, count: i64) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO ignore_stats (sync_dir, ignored_count, updated_at)
+            VALUES (?1, ?2, datetime('now'))
+            ON CONFLICT(sync_dir) DO UPDATE SET
+                ignored_count = ?2,
+                updated_at = datetime('now')
+            "#,
+        )
+        .bind(sync_dir)
+        .bind(count)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Add to the file events for temporary files skipped in a sync directory
+    pub async fn add_temp_events(&self, sync_dir: &str, count: i64) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO ignore_stats (sync_dir, temp_events, updated_at)
+            VALUES (?1, ?2, datetime('now'))
+            ON CONFLICT(sync_dir) DO UPDATE SET
+                temp_events = temp_events + ?2,
+                updated_at = datetime('now')
+            "#,
+        )
+        .bind(sync_dir)
+        .bind(count)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Start counting skipped temporary file events from zero
+    pub async fn reset_temp_events(&self) -> Result<()> {
+        sqlx::query("UPDATE ignore_stats SET temp_events = 0")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Get skipped temporary file events per sync directory, where there were any
+    pub async fn get_temp_event_counts(&self) -> Result<Vec<(String, i64)>> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            "SELECT sync_dir, temp_events FROM ignore_stats WHERE temp_events > 0 ORDER BY sync_dir",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Get ignored entry counts per sync directory
+    pub async fn get_ignored_counts(&self) -> Result<Vec<(String, i64)>> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            "SELECT sync_dir, ignored_count FROM ignore_stats ORDER BY sync_dir",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Replace the opted-out directories recorded for a sync directory
+    pub async fn set_nosync_roots(&self, sync_dir: &str, roots: &[String]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM nosync_roots WHERE sync_dir = ?")
+            .bind(sync_dir)
+            .execute(&mut *tx)
+            .await?;
+
+        for root in roots {
+            sqlx::query(
+                "INSERT OR REPLACE INTO nosync_roots (local_path, sync_dir, updated_at) VALUES (?, ?, datetime('now'))",
+            )
+            .bind(root)
+            .bind(sync_dir)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get opted-out directories found by the last scans
+    pub async fn get_nosync_roots(&self) -> Result<Vec<String>> {
+        let rows = sqlx::query_as::<_, (String,)>(
+            "SELECT local_path FROM nosync_roots ORDER BY local_path",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.0).collect())
+    }
+
+    // === First sync milestone operations ===
+
+    /// Start tracking the initial upload of a sync directory, once
+    ///
+    /// A directory that already has synced files was mirrored before
+    /// milestones were tracked, and is recorded as complete without a summary.
+    pub async fn begin_first_sync(&self, sync_dir: &str) -> Result<()> {
+        let (dir, children) = subtree_prefix(sync_dir);
+
+        sqlx::query(&format!(
+            r#"
+            INSERT OR IGNORE INTO first_sync (sync_dir, started_at, completed_at)
+            SELECT ?1, {0}, CASE WHEN EXISTS (
+                SELECT 1 FROM node_mapping
+                WHERE local_path = ?1 OR substr(local_path, 1, length(?2)) = ?2
+            ) THEN {0} END
+            "#,
+            DB_NOW
+        ))
+        .bind(dir)
+        .bind(children)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record that a sync directory has been fully scanned at least once
+    ///
+    /// Until then an empty queue only means the scan has not queued anything yet.
+    pub async fn mark_first_scan(&self, sync_dir: &str) -> Result<()> {
+        self.begin_first_sync(sync_dir).await?;
+
+        sqlx::query(&format!(
+            "UPDATE first_sync SET scanned_at = COALESCE(scanned_at, {}) WHERE sync_dir = ?",
+            DB_NOW
+        ))
+        .bind(sync_dir.trim_end_matches(['/', std::path::MAIN_SEPARATOR]))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get sync directories scanned but still in their initial upload, with start times
+    pub async fn get_first_syncs_in_progress(&self) -> Result<Vec<(String, DateTime<Utc>)>> {
+        let rows = sqlx::query_as::<_, (String, DateTime<Utc>)>(
+            r#"
+            SELECT sync_dir, started_at FROM first_sync
+            WHERE scanned_at IS NOT NULL AND completed_at IS NULL
+            ORDER BY sync_dir
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// Count jobs under a directory still waiting or running, and those blocked
+    pub async fn count_open_jobs_under(&self, dir: &str) -> Result<(i64, i64)> {
+        let (dir, children) = subtree_prefix(dir);
+
+        let row = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT
+                COALESCE(SUM(status IN ('PENDING', 'PROCESSING')), 0),
+                COALESCE(SUM(status = 'BLOCKED'), 0)
+            FROM sync_jobs
+            WHERE local_path = ?1 OR substr(local_path, 1, length(?2)) = ?2
+            "#,
+        )
+        .bind(dir)
+        .bind(children)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Get the local paths of all synced files under a directory
+    pub async fn get_synced_files_under(&self, dir: &str) -> Result<Vec<String>> {
+        let (dir, children) = subtree_prefix(dir);
+
+        let rows = sqlx::query_as::<_, (String,)>(
+            r#"
+            SELECT local_path FROM node_mapping
+            WHERE is_directory = 0
+              AND (local_path = ?1 OR substr(local_path, 1, length(?2)) = ?2)
+            "#,
+        )
+        .bind(dir)
+        .bind(children)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.0).collect())
+    }
+
+    /// Record a sync directory's initial upload as complete
+    pub async fn complete_first_sync(&self, summary: &FirstSyncSummary) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE first_sync SET completed_at = ?, files = ?, bytes = ?, errors = ?
+            WHERE sync_dir = ?
+            "#,
+        )
+        .bind(summary.completed_at)
+        .bind(summary.files)
+        .bind(summary.bytes)
+        .bind(summary.errors)
+        .bind(&summary.sync_dir)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get the summaries of completed initial uploads, newest first
+    pub async fn get_first_sync_summaries(&self) -> Result<Vec<FirstSyncSummary>> {
+        let rows = sqlx::query_as::<_, (String, DateTime<Utc>, DateTime<Utc>, i64, i64, i64)>(
+            r#"
+            SELECT sync_dir, started_at, completed_at, files, bytes, errors FROM first_sync
+            WHERE completed_at IS NOT NULL AND files IS NOT NULL
+            ORDER BY completed_at DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(
+                |(sync_dir, started_at, completed_at, files, bytes, errors)| FirstSyncSummary {
+                    sync_dir,
+                    started_at,
+                    completed_at,
+                    files,
+                    bytes,
+                    errors,
+                },
+            )
+            .collect())
+    }
+
+    // === Remote root operations ===
+
+    /// Get the remote root path and node UID recorded for a sync directory
+    pub async fn get_remote_root(&self, source_path: &str) -> Result<Option<(String, String)>> {
+        let row = sqlx::query_as::<_, (String, String)>(
+            "SELECT remote_root, node_uid FROM remote_roots WHERE source_path = ?",
+        )
+        .bind(source_path)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Record the remote root path and node UID of a sync directory
+    pub async fn set_remote_root(
+        &self,
+        source_path: &str,
+        remote_root: &str,
+        node_uid: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO remote_roots (source_path, remote_root, node_uid, updated_at) VALUES (?, ?, ?, datetime('now'))",
+        )
+        .bind(source_path)
+        .bind(remote_root)
+        .bind(node_uid)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Forget the recorded remote root of a sync directory
+    pub async fn clear_remote_root(&self, source_path: &str) -> Result<()> {
+        sqlx::query("DELETE FROM remote_roots WHERE source_path = ?")
+            .bind(source_path)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Rewrite remote paths under `old_root` to live under `new_root`
+    ///
+    /// Updates node mappings and unfinished jobs; returns the number of
+    /// node mappings moved.
+    pub async fn relink_remote_paths(&self, old_root: &str, new_root: &str) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        let under_root = "(remote_path = ?1 OR substr(remote_path, 1, length(?1) + 1) = ?1 || '/')";
+
+        let mappings = sqlx::query(&format!(
+            "UPDATE node_mapping SET remote_path = ?2 || substr(remote_path, length(?1) + 1), updated_at = datetime('now') WHERE {}",
+            under_root
+        ))
+        .bind(old_root)
+        .bind(new_root)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(&format!(
+            "UPDATE sync_jobs SET remote_path = ?2 || substr(remote_path, length(?1) + 1) WHERE status IN ('PENDING', 'PROCESSING') AND {}",
+            under_root
+        ))
+        .bind(old_root)
+        .bind(new_root)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(mappings.rows_affected())
+    }
+
+    /// Point everything recorded under a local sync directory at its new location
+    ///
+    /// Rewrites the path prefix of file states, node mappings, opt-out markers
+    /// and the directory's remote root and ignore stats in one transaction.
+    /// Pending and blocked jobs under the old location are dropped: they
+    /// describe paths that no longer exist, and the next scan queues whatever
+    /// actually changed.
+    pub async fn move_local_root(&self, old_root: &str, new_root: &str) -> Result<MovedRoot> {
+        let mut tx = self.pool.begin().await?;
+        let separator = std::path::MAIN_SEPARATOR.to_string();
+
+        let under_root = |column: &str| {
+            format!(
+                "({0} = ?1 OR substr({0}, 1, length(?1) + 1) = ?1 || ?3)",
+                column
+            )
+        };
+
+        let file_states = sqlx::query(&format!(
+            "UPDATE file_state SET local_path = ?2 || substr(local_path, length(?1) + 1) WHERE {}",
+            under_root("local_path")
+        ))
+        .bind(old_root)
+        .bind(new_root)
+        .bind(&separator)
+        .execute(&mut *tx)
+        .await?;
+
+        let node_mappings = sqlx::query(&format!(
+            "UPDATE node_mapping SET local_path = ?2 || substr(local_path, length(?1) + 1), updated_at = datetime('now') WHERE {}",
+            under_root("local_path")
+        ))
+        .bind(old_root)
+        .bind(new_root)
+        .bind(&separator)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(&format!(
+            "UPDATE nosync_roots SET local_path = ?2 || substr(local_path, length(?1) + 1), sync_dir = ?2 WHERE {}",
+            under_root("local_path")
+        ))
+        .bind(old_root)
+        .bind(new_root)
+        .bind(&separator)
+        .execute(&mut *tx)
+        .await?;
+
+        let jobs_dropped = sqlx::query(&format!(
+            "DELETE FROM sync_jobs WHERE status IN ('PENDING', 'BLOCKED') AND {}",
+            under_root("local_path")
+        ))
+        .bind(old_root)
+        .bind(new_root)
+        .bind(&separator)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE remote_roots SET source_path = ?2 WHERE source_path = ?1")
+            .bind(old_root)
+            .bind(new_root)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("UPDATE ignore_stats SET sync_dir = ?2 WHERE sync_dir = ?1")
+            .bind(old_root)
+            .bind(new_root)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("UPDATE first_sync SET sync_dir = ?2 WHERE sync_dir = ?1")
+            .bind(old_root)
+            .bind(new_root)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(MovedRoot {
+            file_states: file_states.rows_affected(),
+            node_mappings: node_mappings.rows_affected(),
+            jobs_dropped: jobs_dropped.rows_affected(),
+        })
+    }
+
+    // === Sync history ===
+
+    /// Record a completed or failed sync operation
+    pub async fn record_history(&self, entry: &HistoryEntry) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO sync_history
+                (event_type, direction, local_path, remote_path, old_local_path,
+                 bytes, duration_ms, result, error, completed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(entry.event_type.to_string())
+        .bind(direction_str(entry.direction))
+        .bind(&entry.local_path)
+        .bind(&entry.remote_path)
+        .bind(&entry.old_local_path)
+        .bind(entry.bytes)
+        .bind(entry.duration_ms)
+        .bind(entry.result.to_string())
+        .bind(&entry.error)
+        .bind(entry.completed_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Get sync history since a time, newest first
+    ///
+    /// With `failed_only` only failed operations are returned; with
+    /// `local_dir` only operations on that path or below it.
+    pub async fn get_history(
+        &self,
+        since: DateTime<Utc>,
+        failed_only: bool,
+        local_dir: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<HistoryEntry>> {
+        let (dir, prefix) = match local_dir {
+            Some(dir) => {
+                let (dir, prefix) = subtree_prefix(dir);
+                (Some(dir), Some(prefix))
+            }
+            None => (None, None),
+        };
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, event_type, direction, local_path, remote_path, old_local_path,
+                   bytes, duration_ms, result, error, completed_at
+            FROM sync_history
+            WHERE completed_at >= ?1
+              AND (NOT ?2 OR result != 'SYNCED')
+              AND (?3 IS NULL OR local_path = ?3 OR substr(local_path, 1, length(?4)) = ?4)
+            ORDER BY completed_at DESC, id DESC
+            LIMIT ?5
+            "#,
+        )
+        .bind(since)
+        .bind(failed_only)
+        .bind(dir)
+        .bind(prefix)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(row_to_history).collect()
+    }
+
+    /// Count synced files, their bytes and failed attempts in a time range
+    ///
+    /// The range includes `from` and excludes `to`. Folder creations are not
+    /// counted as files.
+    pub async fn get_history_totals(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<(i64, i64, i64)> {
+        let totals = sqlx::query_as::<_, (i64, i64, i64)>(
+            r#"
+            SELECT
+                COALESCE(SUM(result = 'SYNCED' AND event_type != 'CREATE_DIR'), 0),
+                COALESCE(SUM(CASE WHEN result = 'SYNCED' THEN bytes END), 0),
+                COALESCE(SUM(result != 'SYNCED'), 0)
+            FROM sync_history
+            WHERE completed_at >= ? AND completed_at < ?
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(totals)
+    }
+
+    /// Get sync history recorded after an entry ID, oldest first
+    pub async fn get_history_after(&self, after_id: i64, limit: i64) -> Result<Vec<HistoryEntry>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, event_type, direction, local_path, remote_path, old_local_path,
+                   bytes, duration_ms, result, error, completed_at
+            FROM sync_history
+            WHERE id > ?
+            ORDER BY id ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(row_to_history).collect()
+    }
+
+    /// Delete sync history older than the given age
+    pub async fn prune_history(&self, older_than: chrono::Duration) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM sync_history WHERE completed_at < ?")
+            .bind(Utc::now() - older_than)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    // === Transfer statistics ===
+
+    /// Get the last sync history entry included in the transfer statistics
+    pub async fn get_transfer_stats_cursor(&self) -> Result<i64> {
+        let history_id =
+            sqlx::query_scalar("SELECT history_id FROM transfer_stats_cursor WHERE id = 1")
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(history_id.unwrap_or(0))
+    }
+
+    /// Add transfers to the statistics and move the cursor from one history
+    /// entry to another
+    ///
+    /// Returns `false` without adding anything if the cursor is no longer at
+    /// `from_id`, because another process aggregated the same entries.
+    pub async fn add_transfer_stats(
+        &self,
+        stats: &[TransferStats],
+        from_id: i64,
+        to_id: i64,
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let moved = sqlx::query(
+            "UPDATE transfer_stats_cursor SET history_id = ? WHERE id = 1 AND history_id = ?",
+        )
+        .bind(to_id)
+        .bind(from_id)
+        .execute(&mut *tx)
+        .await?;
+        if moved.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        for entry in stats {
+            sqlx::query(
+                r#"
+                INSERT INTO transfer_stats (day, sync_dir, direction, files, bytes)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT(day, sync_dir, direction) DO UPDATE SET
+                    files = files + excluded.files,
+                    bytes = bytes + excluded.bytes
+                "#,
+            )
+            .bind(entry.day.to_string())
+            .bind(&entry.sync_dir)
+            .bind(direction_str(entry.direction))
+            .bind(entry.files)
+            .bind(entry.bytes)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Get transfer statistics from a day on, ordered by day and sync directory
+    pub async fn get_transfer_stats(&self, since: NaiveDate) -> Result<Vec<TransferStats>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT day, sync_dir, direction, files, bytes
+            FROM transfer_stats
+            WHERE day >= ?
+            ORDER BY day ASC, sync_dir ASC, direction DESC
+            "#,
+        )
+        .bind(since.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let day: String = row.try_get("day")?;
+                let direction: String = row.try_get("direction")?;
+
+                Ok(TransferStats {
+                    day: day.parse().map_err(|e| {
+                        Error::InvalidState(format!("Bad transfer stats day {}: {}", day, e))
+                    })?,
+                    sync_dir: row.try_get("sync_dir")?,
+                    direction: parse_direction(&direction),
+                    files: row.try_get("files")?,
+                    bytes: row.try_get("bytes")?,
+                })
+            })
+            .collect()
+    }
+
+    // === Conflicts ===
+
+    /// Record a conflict, replacing an earlier one for the same local file
+    pub async fn record_conflict(&self, conflict: &Conflict) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO conflicts
+                (job_id, local_path, remote_path, node_uid, parent_uid, detected_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(local_path) DO UPDATE SET
+                job_id = excluded.job_id,
+                remote_path = excluded.remote_path,
+                node_uid = excluded.node_uid,
+                parent_uid = excluded.parent_uid,
+                detected_at = excluded.detected_at
+            "#,
+        )
+        .bind(conflict.job_id)
+        .bind(&conflict.local_path)
+        .bind(&conflict.remote_path)
+        .bind(&conflict.node_uid)
+        .bind(&conflict.parent_uid)
+        .bind(conflict.detected_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Get unresolved conflicts, oldest first
+    pub async fn get_conflicts(&self) -> Result<Vec<Conflict>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, job_id, local_path, remote_path, node_uid, parent_uid, detected_at
+            FROM conflicts
+            ORDER BY detected_at ASC, id ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(Conflict {
+                    id: row.try_get("id")?,
+                    job_id: row.try_get("job_id")?,
+                    local_path: row.try_get("local_path")?,
+                    remote_path: row.try_get("remote_path")?,
+                    node_uid: row.try_get("node_uid")?,
+                    parent_uid: row.try_get("parent_uid")?,
+                    detected_at: row.try_get("detected_at")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Remove a resolved conflict
+    pub async fn delete_conflict(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM conflicts WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // === Folder creation ledger ===
+
+    /// Record that a folder is about to be created remotely
+    ///
+    /// If the process dies before [`Db::finish_folder_creation`], the entry
+    /// tells the next run that the folder may exist despite the index.
+    pub async fn begin_folder_creation(
+        &self,
+        parent_uid: &str,
+        name: &str,
+        remote_path: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO folder_creations (parent_uid, name, remote_path, started_at) VALUES (?, ?, ?, datetime('now'))",
+        )
+        .bind(parent_uid)
+        .bind(name)
+        .bind(remote_path)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Record that a folder creation is settled, whether or not it happened
+    pub async fn finish_folder_creation(&self, parent_uid: &str, name: &str) -> Result<()> {
+        sqlx::query("DELETE FROM folder_creations WHERE parent_uid = ? AND name = ?")
+            .bind(parent_uid)
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Check whether a folder creation was started and never settled
+    pub async fn is_folder_creation_pending(&self, parent_uid: &str, name: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM folder_creations WHERE parent_uid = ? AND name = ?",
+        )
+        .bind(parent_uid)
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    /// Get unsettled folder creations as (parent UID, name, remote path)
+    pub async fn get_pending_folder_creations(&self) -> Result<Vec<(String, String, String)>> {
+        let rows = sqlx::query_as::<_, (String, String, String)>(
+            "SELECT parent_uid, name, remote_path FROM folder_creations ORDER BY started_at ASC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    // === Expected changes ===
+
+    /// Record a change the sync engine made to a local file itself
+    ///
+    /// The watcher drops events that leave the file with this change token,
+    /// so downloaded content is not uploaded straight back.
+    pub async fn expect_change(&self, local_path: &str, change_token: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO expected_changes (local_path, change_token, expected_at) VALUES (?, ?, datetime('now'))",
+        )
+        .bind(local_path)
+        .bind(change_token)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Get the change token expected for a path, if recorded within `max_age`
+    pub async fn get_expected_change(
+        &self,
+        local_path: &str,
+        max_age: chrono::Duration,
+    ) -> Result<Option<String>> {
+        let token = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT change_token FROM expected_changes
+            WHERE local_path = ? AND expected_at >= datetime('now', '-' || ? || ' seconds')
+            "#,
+        )
+        .bind(local_path)
+        .bind(max_age.num_seconds())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Delete expected changes recorded more than `max_age` ago
+    ///
+    /// Returns the number of entries deleted.
+    pub async fn expire_expected_changes(&self, max_age: chrono::Duration) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM expected_changes WHERE expected_at < datetime('now', '-' || ? || ' seconds')",
+        )
+        .bind(max_age.num_seconds())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // === Remote node index operations ===
+
+    /// Get an indexed remote node
+    pub async fn get_remote_node(&self, uid: &str) -> Result<Option<NodeData>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM remote_nodes WHERE uid = ?",
+            REMOTE_NODE_COLUMNS
+        ))
+        .bind(uid)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(row_to_remote_node))
+    }
+
+    /// Get the indexed children of a folder
+    ///
+    /// Returns `None` unless the folder's complete listing has been indexed.
+    pub async fn get_remote_children(&self, parent_uid: &str) -> Result<Option<Vec<NodeData>>> {
+        let listed: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM remote_listed_folders WHERE uid = ?")
+                .bind(parent_uid)
+                .fetch_one(&self.pool)
+                .await?;
+
+        if listed == 0 {
+            return Ok(None);
+        }
+
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM remote_nodes WHERE parent_uid = ? ORDER BY name ASC",
+            REMOTE_NODE_COLUMNS
+        ))
+        .bind(parent_uid)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(Some(rows.iter().map(row_to_remote_node).collect()))
+    }
+
+    /// Add or update an indexed remote node
+    pub async fn upsert_remote_node(&self, node: &NodeData) -> Result<()> {
+        Self::upsert_remote_node_with(&self.pool, node).await
+    }
+
+    /// Replace the indexed children of a folder with a complete listing
+    pub async fn set_remote_children(&self, parent_uid: &str, children: &[NodeData]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM remote_nodes WHERE parent_uid = ?")
+            .bind(parent_uid)
+            .execute(&mut *tx)
+            .await?;
+
+        for child in children {
+            Self::upsert_remote_node_with(&mut *tx, child).await?;
+        }
+
+        sqlx::query("INSERT OR IGNORE INTO remote_listed_folders (uid) VALUES (?)")
+            .bind(parent_uid)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Remove an indexed remote node and everything below it
+    pub async fn delete_remote_node(&self, uid: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let subtree = r#"
+            WITH RECURSIVE subtree(uid) AS (
+                SELECT ?1
+                UNION
+                SELECT n.uid FROM remote_nodes n JOIN subtree s ON n.parent_uid = s.uid
+            )
+        "#;
+
+        sqlx::query(&format!(
+            "{} DELETE FROM remote_listed_folders WHERE uid IN (SELECT uid FROM subtree)",
+            subtree
+        ))
+        .bind(uid)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(&format!(
+            "{} DELETE FROM remote_nodes WHERE uid IN (SELECT uid FROM subtree)",
+            subtree
+        ))
+        .bind(uid)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Drop the whole remote index, including the event cursor
+    pub async fn clear_remote_index(&self) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM remote_nodes; DELETE FROM remote_listed_folders; DELETE FROM remote_event_cursor;",
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Check that the database answers a query
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    // === Daemon heartbeat ===
+
+    /// Record that the daemon started at `started_at` is still alive
+    pub async fn record_heartbeat(&self, pid: u32, started_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO daemon_heartbeat (id, pid, started_at, last_seen) VALUES (1, ?, ?, ?)",
+        )
+        .bind(pid as i64)
+        .bind(started_at)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Get the daemon's last heartbeat; none after a clean shutdown
+    pub async fn get_heartbeat(&self) -> Result<Option<DaemonHeartbeat>> {
+        let row = sqlx::query_as::<_, (i64, DateTime<Utc>, DateTime<Utc>)>(
+            "SELECT pid, started_at, last_seen FROM daemon_heartbeat WHERE id = 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|(pid, started_at, last_seen)| DaemonHeartbeat {
+            pid: pid as u32,
+            started_at,
+            last_seen,
+        }))
+    }
+
+    /// Remove the heartbeat when the daemon shuts down cleanly
+    pub async fn clear_heartbeat(&self) -> Result<()> {
+        sqlx::query("DELETE FROM daemon_heartbeat")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Record when the Proton API last answered successfully
+    pub async fn set_last_api_contact(&self, at: DateTime<Utc>) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO api_contact (id, last_contact) VALUES (1, ?)")
+            .bind(at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Get when the Proton API last answered successfully
+    pub async fn get_last_api_contact(&self) -> Result<Option<DateTime<Utc>>> {
+        let last_contact = sqlx::query_scalar("SELECT last_contact FROM api_contact WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(last_contact)
+    }
+
+    // === Scan checkpoints ===
+
+    /// Start a scan of a sync directory, or resume an unfinished one
+    ///
+    /// An unfinished generation started within `max_age` is returned with
+    /// the folders it completed; otherwise a new generation starts with
+    /// none, since the tree may have changed too much to trust them.
+    pub async fn begin_scan(
+        &self,
+        sync_dir: &str,
+        max_age: chrono::Duration,
+    ) -> Result<ScanCheckpoint> {
+        let mut tx = self.pool.begin().await?;
+
+        let row = sqlx::query_as::<_, (i64, DateTime<Utc>, Option<DateTime<Utc>>)>(
+            "SELECT generation, started_at, completed_at FROM scan_checkpoints WHERE sync_dir = ?",
+        )
+        .bind(sync_dir)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let checkpoint = match row {
+            Some((generation, started_at, None)) if started_at > Utc::now() - max_age => {
+                let completed_dirs =
+                    sqlx::query_scalar("SELECT dir FROM scan_completed_dirs WHERE sync_dir = ?")
+                        .bind(sync_dir)
+                        .fetch_all(&mut *tx)
+                        .await?;
+                ScanCheckpoint {
+                    generation,
+                    completed_dirs: completed_dirs.into_iter().collect(),
+                }
+            }
+            previous => {
+                let generation = previous.map_or(1, |(generation, _, _)| generation + 1);
+                sqlx::query(
+                    "INSERT OR REPLACE INTO scan_checkpoints (sync_dir, generation, started_at, completed_at)
+                     VALUES (?, ?, ?, NULL)",
+                )
+                .bind(sync_dir)
+                .bind(generation)
+                .bind(Utc::now())
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query("DELETE FROM scan_completed_dirs WHERE sync_dir = ?")
+                    .bind(sync_dir)
+                    .execute(&mut *tx)
+                    .await?;
+                ScanCheckpoint {
+                    generation,
+                    completed_dirs: Default::default(),
+                }
+            }
+        };
+
+        tx.commit().await?;
+        Ok(checkpoint)
+    }
+
+    /// Record that a scan visited every file in folders of a sync directory
+    pub async fn complete_scan_dirs(&self, sync_dir: &str, dirs: &[String]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for dir in dirs {
+            sqlx::query("INSERT OR IGNORE INTO scan_completed_dirs (sync_dir, dir) VALUES (?, ?)")
+                .bind(sync_dir)
+                .bind(dir)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Mark the current scan generation of a sync directory complete
+    pub async fn finish_scan(&self, sync_dir: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE scan_checkpoints SET completed_at = ? WHERE sync_dir = ?")
+            .bind(Utc::now())
+            .bind(sync_dir)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM scan_completed_dirs WHERE sync_dir = ?")
+            .bind(sync_dir)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get when a file was last synced successfully
+    pub async fn get_last_synced_at(&self) -> Result<Option<DateTime<Utc>>> {
+        let completed_at = sqlx::query_scalar(
+            "SELECT completed_at FROM sync_history WHERE result = 'SYNCED' ORDER BY completed_at DESC LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(completed_at)
+    }
+
+    /// Get the last applied remote event ID
+    pub async fn get_remote_event_cursor(&self) -> Result<Option<String>> {
+        let event_id = sqlx::query_scalar("SELECT event_id FROM remote_event_cursor WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(event_id)
+    }
+
+    /// Set the last applied remote event ID
+    pub async fn set_remote_event_cursor(&self, event_id: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO remote_event_cursor (id, event_id, updated_at) VALUES (1, ?, datetime('now'))",
+        )
+        .bind(event_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Upsert a remote node on a pool or inside a transaction
+    async fn upsert_remote_node_with<'e, E>(executor: E, node: &NodeData) -> Result<()>
+    where
+        E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+    {
+        let revision = node.active_revision.as_ref();
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO remote_nodes
+            (uid, parent_uid, name, node_type, size, revision_uid, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, datetime('now'))
+            "#,
+        )
+        .bind(&node.uid)
+        .bind(&node.parent_uid)
+        .bind(&node.name)
+        .bind(&node.node_type)
+        .bind(revision.and_then(|r| r.size))
+        .bind(revision.map(|r| &r.uid))
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Columns selected when loading a node mapping
+const NODE_MAPPING_COLUMNS: &str =
+    "local_path, remote_path, node_uid, parent_node_uid, is_directory, updated_at";
+
+/// Helper function to build a NodeMapping from a row selected with `NODE_MAPPING_COLUMNS`
+fn row_to_node_mapping(row: &SqliteRow) -> NodeMapping {
+    NodeMapping {
+        local_path: row.try_get("local_path").unwrap_or_default(),
+        remote_path: row.try_get("remote_path").unwrap_or_default(),
+        node_uid: row.try_get("node_uid").unwrap_or_default(),
+        parent_node_uid: row.try_get("parent_node_uid").unwrap_or_default(),
+        is_directory: row.try_get("is_directory").unwrap_or(false),
+        updated_at: row.try_get("updated_at").unwrap_or_else(|_| Utc::now()),
+    }
+}
+
+/// Columns selected when loading an indexed remote node
+const REMOTE_NODE_COLUMNS: &str = "uid, parent_uid, name, node_type, size, revision_uid";
+
+/// Helper function to build a NodeData from a row selected with `REMOTE_NODE_COLUMNS`
+fn row_to_remote_node(row: &SqliteRow) -> NodeData {
+    let revision_uid: Option<String> = row.try_get("revision_uid").unwrap_or_default();
+    let size: Option<i64> = row.try_get("size").unwrap_or_default();
+
+    NodeData {
+        uid: row.try_get("uid").unwrap_or_default(),
+        parent_uid: row.try_get("parent_uid").unwrap_or_default(),
+        name: row.try_get("name").unwrap_or_default(),
+        node_type: row.try_get("node_type").unwrap_or_default(),
+        media_type: None,
+        active_revision: revision_uid.map(|uid| RevisionData {
+            uid,
+            size,
+            manifest_signature: None,
+        }),
+    }
+}
+
+/// Result of merging a new event into a pending job for the same path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Collapsed {
+    /// The pending job now carries this event type
+    Into(SyncEventType),
+    /// The events cancel out; the pending job is dropped
+    Cancelled,
+}
+
+/// Merge a new event into a pending job for the same path
+///
+/// Returns `None` when both must run, such as a directory replacing a file.
+/// A create followed by a delete cancels out unless the path already has a
+/// remote node.
+fn collapse_events(
+    pending: SyncEventType,
+    new: SyncEventType,
+    has_remote: bool,
+) -> Option<Collapsed> {
+    use SyncEventType::*;
+
+    let collapsed = match (pending, new) {
+        (CreateFile, CreateFile | Update) => Collapsed::Into(CreateFile),
+        (CreateDir, CreateDir | Update) => Collapsed::Into(CreateDir),
+        (CreateFile | CreateDir, Delete) if !has_remote => Collapsed::Cancelled,
+        (CreateFile | CreateDir | Update | Delete, Delete) => Collapsed::Into(Delete),
+        (Update | Delete, CreateFile | Update) => Collapsed::Into(Update),
+        _ => return None,
+    };
+
+    Some(collapsed)
+}
+
+/// Columns selected when loading a full job row
+const JOB_COLUMNS: &str = "id, event_type, local_path, remote_path, status, retry_at, n_retries, \
+     last_error, change_token, old_local_path, old_remote_path, created_at, updated_at, completed_at, \
+     skip_reason, source";
+
+/// Paths looked up per query when enqueueing a batch of jobs
+const BATCH_LOOKUP_SIZE: usize = 500;
+
+/// Jobs per multi-row insert, well within SQLite's limit on bound values
+const BATCH_INSERT_SIZE: usize = 500;
+
+/// SQL expression for the current time with millisecond precision
+const DB_NOW: &str = "strftime('%Y-%m-%d %H:%M:%f', 'now')";
+
+/// Format a timestamp the way SQLite's `strftime('%Y-%m-%d %H:%M:%f')` does
+fn format_db_datetime(dt: DateTime<Utc>) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+}
+
+/// Helper function to build a SyncJob from a row selected with `JOB_COLUMNS`
+fn row_to_job(row: &SqliteRow) -> Result<SyncJob> {
+    let event_type_str: String = row.try_get("event_type").map_err(Error::Database)?;
+    let status_str: String = row.try_get("status").map_err(Error::Database)?;
+    let skip_reason: Option<String> = row.try_get("skip_reason").ok().flatten();
+    let source: Option<String> = row.try_get("source").ok().flatten();
+
+    Ok(SyncJob {
+        id: row.try_get("id").map_err(Error::Database)?,
+        event_type: parse_sync_event_type(&event_type_str),
+        local_path: row.try_get("local_path").map_err(Error::Database)?,
+        remote_path: row.try_get("remote_path").map_err(Error::Database)?,
+        status: parse_sync_job_status(&status_str, skip_reason.as_deref()),
+        retry_at: row.try_get("retry_at").ok().flatten(),
+        n_retries: row.try_get("n_retries").map_err(Error::Database)?,
+        last_error: row.try_get("last_error").ok().flatten(),
+        change_token: row.try_get("change_token").ok().flatten(),
+        old_local_path: row.try_get("old_local_path").ok().flatten(),
+        old_remote_path: row.try_get("old_remote_path").ok().flatten(),
+        created_at: row.try_get("created_at").map_err(Error::Database)?,
+        updated_at: row.try_get("updated_at").ok().flatten(),
+        completed_at: row.try_get("completed_at").ok().flatten(),
+        source: source.as_deref().and_then(JobSource::parse),
+    })
+}
+
+/// What [`Db::move_local_root`] changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MovedRoot {
+    pub file_states: u64,
+    pub node_mappings: u64,
+    pub jobs_dropped: u64,
+}
+
+/// A directory path without trailing separators, and the prefix its children start with
+fn subtree_prefix(dir: &str) -> (String, String) {
+    let dir = dir.trim_end_matches(['/', std::path::MAIN_SEPARATOR]);
+    (
+        dir.to_string(),
+        format!("{}{}", dir, std::path::MAIN_SEPARATOR),
+    )
+}
+
+/// Convert a `sync_history` row
+fn row_to_history(row: &SqliteRow) -> Result<HistoryEntry> {
+    let event_type: String = row.try_get("event_type").map_err(Error::Database)?;
+    let direction: String = row.try_get("direction").map_err(Error::Database)?;
+    let result: String = row.try_get("result").map_err(Error::Database)?;
+
+    Ok(HistoryEntry {
+        id: row.try_get("id").map_err(Error::Database)?,
+        event_type: parse_sync_event_type(&event_type),
+        direction: parse_direction(&direction),
+        local_path: row.try_get("local_path").map_err(Error::Database)?,
+        remote_path: row.try_get("remote_path").map_err(Error::Database)?,
+        old_local_path: row.try_get("old_local_path").ok().flatten(),
+        bytes: row.try_get("bytes").ok().flatten(),
+        duration_ms: row.try_get("duration_ms").map_err(Error::Database)?,
+        result: match result.as_str() {
+            "SYNCED" => HistoryResult::Synced,
+            "RETRYING" => HistoryResult::Retrying,
+            "SKIPPED" => HistoryResult::Skipped,
+            _ => HistoryResult::Blocked,
+        },
+        error: row.try_get("error").ok().flatten(),
+        completed_at: row.try_get("completed_at").map_err(Error::Database)?,
+    })
+}
+
+/// Stored form of a sync direction
+fn direction_str(direction: SyncDirection) -> &'static str {
+    match direction {
+        SyncDirection::Upload => "UPLOAD",
+        SyncDirection::Download => "DOWNLOAD",
+    }
+}
+
+/// Parse a stored sync direction
+fn parse_direction(s: &str) -> SyncDirection {
+    match s {
+        "DOWNLOAD" => SyncDirection::Download,
+        _ => SyncDirection::Upload,
+    }
+}
+
+/// Helper function to parse SyncEventType from string
+fn parse_sync_event_type(s: &str) -> SyncEventType {
+    match s {
+        "CREATE_FILE" => SyncEventType::CreateFile,
+        "CREATE_DIR" => SyncEventType::CreateDir,
+        "UPDATE" => SyncEventType::Update,
+        "DELETE" => SyncEventType::Delete,
+        _ => SyncEventType::Update, // Default fallback
+    }
+}
+
+/// Helper function to parse SyncJobStatus from string
+fn parse_sync_job_status(s: &str, skip_reason: Option<&str>) -> SyncJobStatus {
+    match s {
+        "PENDING" => SyncJobStatus::Pending,
+        "PROCESSING" => SyncJobStatus::Processing,
+        "SYNCED" => SyncJobStatus::Synced,
+        "BLOCKED" => SyncJobStatus::Blocked,
+        "SKIPPED" => SyncJobStatus::Skipped(
+            skip_reason
+                .and_then(SkipReason::parse)
+                .unwrap_or(SkipReason::PermissionDenied),
+        ),
         _ => SyncJobStatus::Pending, // Default fallback
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{JobPriority, NodeMapping, SyncEvent};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_open_existing_does_not_create() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("data").join("test.db");
+
+        assert!(Db::open_existing(db_path.clone()).await.unwrap().is_none());
+        assert!(Db::open_existing_read_only(db_path.clone())
+            .await
+            .unwrap()
+            .is_none());
+        assert!(!db_path.parent().unwrap().exists());
+
+        Db::new(db_path.clone()).await.unwrap().close().await;
+        assert!(Db::open_existing_read_only(db_path)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_jobs_completed_after_cursor() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+
+        let mut ids = Vec::new();
+        for name in ["a", "b", "c"] {
+            let event = SyncEvent {
+                event_type: SyncEventType::CreateFile,
+                local_path: format!("/home/{}", name),
+                remote_path: format!("/{}", name),
+                change_token: None,
+                old_local_path: None,
+                old_remote_path: None,
+            };
+            let id = db.enqueue_job(&event, JobPriority::Small).await.unwrap();
+            ids.push(id.unwrap());
+        }
+        for id in &ids[..2] {
+            db.update_job_status(*id, SyncJobStatus::Synced, None)
+                .await
+                .unwrap();
+        }
+
+        // Both jobs completed in the same millisecond
+        let at = Utc::now();
+        sqlx::query("UPDATE sync_jobs SET completed_at = ? WHERE completed_at IS NOT NULL")
+            .bind(format_db_datetime(at))
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let completed_ids = |jobs: Vec<SyncJob>| jobs.into_iter().map(|j| j.id).collect::<Vec<_>>();
+        let earlier = at - chrono::Duration::seconds(1);
+        assert_eq!(
+            completed_ids(db.get_jobs_completed_after(earlier, 0).await.unwrap()),
+            ids[..2]
+        );
+        assert_eq!(
+            completed_ids(db.get_jobs_completed_after(at, ids[0]).await.unwrap()),
+            ids[1..2]
+        );
+        assert!(db
+            .get_jobs_completed_after(at, ids[1])
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_jobs_updated_after_cursor() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+
+        let mut ids = Vec::new();
+        for name in ["a", "b", "c"] {
+            let event = SyncEvent {
+                event_type: SyncEventType::CreateFile,
+                local_path: format!("/home/{}", name),
+                remote_path: format!("/{}", name),
+                change_token: None,
+                old_local_path: None,
+                old_remote_path: None,
+            };
+            let id = db.enqueue_job(&event, JobPriority::Small).await.unwrap();
+            ids.push(id.unwrap());
+        }
+
+        // All jobs changed in the same millisecond, read two at a time
+        let at = Utc::now();
+        sqlx::query("UPDATE sync_jobs SET updated_at = ?")
+            .bind(format_db_datetime(at))
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let earlier = at - chrono::Duration::seconds(1);
+        let first = db.get_jobs_updated_after(earlier, 0, 2).await.unwrap();
+        assert_eq!(first.len(), 2);
+        let last = first.last().unwrap().id;
+        let rest = db.get_jobs_updated_after(at, last, 2).await.unwrap();
+        assert_eq!(rest.iter().map(|j| j.id).collect::<Vec<_>>(), ids[2..]);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_rejects_writes() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("data").join("test.db");
+
+        // Never creates a missing database
+        assert!(Db::open_read_only(db_path.clone()).await.is_err());
+        assert!(!db_path.parent().unwrap().exists());
+
+        Db::new(db_path.clone()).await.unwrap().close().await;
+        let reader = Db::open_read_only(db_path).await.unwrap();
+
+        assert!(reader.get_heartbeat().await.unwrap().is_none());
+        assert!(reader.record_heartbeat(1, Utc::now()).await.is_err());
+        assert!(sqlx::query("DELETE FROM sync_jobs")
+            .execute(&reader.pool)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_only_migrates_stale_schema() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+
+        // A database from before the heartbeat table and the job source column
+        let db = Db::new(db_path.clone()).await.unwrap();
+        assert!(Db::is_current(&db.pool).await.unwrap());
+        sqlx::query("DROP TABLE daemon_heartbeat")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query("ALTER TABLE sync_jobs DROP COLUMN source")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        assert!(!Db::is_current(&db.pool).await.unwrap());
+        db.close().await;
+
+        let reader = Db::open_read_only(db_path).await.unwrap();
+        assert!(Db::is_current(&reader.pool).await.unwrap());
+        assert!(reader.get_heartbeat().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_batch_enqueue_merges_like_single_jobs() {
+        let temp_dir = TempDir::new().unwrap();
+        let event = |event_type, name: &str| SyncEvent {
+            event_type,
+            local_path: format!("/home/{}", name),
+            remote_path: format!("/{}", name),
+            change_token: None,
+            old_local_path: None,
+            old_remote_path: None,
+        };
+        let batch: Vec<(SyncEvent, JobPriority)> = [
+            event(SyncEventType::Update, "pending.txt"),
+            event(SyncEventType::Update, "new.txt"),
+            event(SyncEventType::Update, "twice.txt"),
+            event(SyncEventType::Delete, "twice.txt"),
+            event(SyncEventType::CreateDir, "docs"),
+        ]
+        .into_iter()
+        .map(|event| (event, JobPriority::Small))
+        .collect();
+
+        let mut queues = Vec::new();
+        for (i, batched) in [false, true].into_iter().enumerate() {
+            let db = Db::new(temp_dir.path().join(format!("{}.db", i)))
+                .await
+                .unwrap();
+            db.enqueue_job(
+                &event(SyncEventType::CreateFile, "pending.txt"),
+                JobPriority::Small,
+            )
+            .await
+            .unwrap();
+
+            if batched {
+                db.enqueue_jobs_batch(&batch, JobSource::Scanner)
+                    .await
+                    .unwrap();
+            } else {
+                for (event, priority) in &batch {
+                    db.enqueue_job_from(event, *priority, JobSource::Scanner)
+                        .await
+                        .unwrap();
+                }
+            }
+
+            let queue: Vec<_> = db
+                .get_all_jobs()
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|job| (job.event_type, job.local_path, job.status, job.source))
+                .collect();
+            queues.push(queue);
+        }
+
+        assert_eq!(queues[0].len(), 4);
+        assert_eq!(queues[0], queues[1]);
+    }
+
+    #[tokio::test]
+    async fn test_claimed_jobs_take_no_merges() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let event = |event_type| SyncEvent {
+            event_type,
+            local_path: "/home/a.txt".to_string(),
+            remote_path: "/a.txt".to_string(),
+            change_token: None,
+            old_local_path: None,
+            old_remote_path: None,
+        };
+
+        let id = db
+            .enqueue_job(&event(SyncEventType::CreateFile), JobPriority::Small)
+            .await
+            .unwrap()
+            .unwrap();
+        let job = db.claim_job(id).await.unwrap().unwrap();
+        assert_eq!(job.status, SyncJobStatus::Processing);
+        assert!(db.claim_job(id).await.unwrap().is_none());
+
+        // A delete no longer cancels the claimed create but queues behind it
+        db.enqueue_job(&event(SyncEventType::Delete), JobPriority::Small)
+            .await
+            .unwrap();
+        let jobs = db.get_all_jobs().await.unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[1].event_type, SyncEventType::Delete);
+
+        // A claim left over from a previous run is queued again
+        assert_eq!(db.requeue_claimed_jobs().await.unwrap(), 1);
+        assert!(db.claim_job(id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_jobs_for_a_path_run_in_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+
+        // A rename of a.txt to b.txt, then a new a.txt
+        let moved = SyncEvent {
+            event_type: SyncEventType::Update,
+            local_path: "/home/b.txt".to_string(),
+            remote_path: "/b.txt".to_string(),
+            change_token: None,
+            old_local_path: Some("/home/a.txt".to_string()),
+            old_remote_path: Some("/a.txt".to_string()),
+        };
+        let created = SyncEvent {
+            event_type: SyncEventType::CreateFile,
+            local_path: "/home/a.txt".to_string(),
+            remote_path: "/a.txt".to_string(),
+            change_token: None,
+            old_local_path: None,
+            old_remote_path: None,
+        };
+        let move_id = db
+            .enqueue_job(&moved, JobPriority::Metadata)
+            .await
+            .unwrap()
+            .unwrap();
+        db.enqueue_job(&created, JobPriority::Small).await.unwrap();
+
+        let ready = |db: Db| async move {
+            db.get_pending_jobs(10)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|j| j.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ready(db.clone()).await, [move_id]);
+
+        // The new file still waits while the rename runs
+        db.mark_job_processing(move_id).await.unwrap();
+        assert!(ready(db.clone()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_orphan_node_mappings() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let local = |name: &str| temp_dir.path().join(name).to_string_lossy().to_string();
+        std::fs::write(temp_dir.path().join("kept.txt"), "x").unwrap();
+
+        for name in ["kept.txt", "gone.txt", "deleting.txt"] {
+            db.update_node_mapping(&NodeMapping {
+                local_path: local(name),
+                remote_path: format!("/{}", name),
+                node_uid: name.to_string(),
+                parent_node_uid: "root".to_string(),
+                is_directory: false,
+                updated_at: Utc::now(),
+            })
+            .await
+            .unwrap();
+        }
+
+        // A queued delete takes care of its own mapping
+        db.enqueue_job(
+            &SyncEvent {
+                event_type: SyncEventType::Delete,
+                local_path: local("deleting.txt"),
+                remote_path: "/deleting.txt".to_string(),
+                change_token: None,
+                old_local_path: None,
+                old_remote_path: None,
+            },
+            JobPriority::Metadata,
+        )
+        .await
+        .unwrap();
+
+        let orphans: Vec<String> = db
+            .get_orphan_node_mappings(&[temp_dir.path()])
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.remote_path)
+            .collect();
+        assert_eq!(orphans, ["/gone.txt"]);
+
+        // Nothing is an orphan under a missing root or outside every root
+        let missing = temp_dir.path().join("unmounted");
+        assert!(db
+            .get_orphan_node_mappings(&[missing.as_path()])
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(db.get_orphan_node_mappings(&[]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_move_local_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let sep = std::path::MAIN_SEPARATOR;
+        let old = format!("{0}home{0}Docs", sep);
+        let new = format!("{0}mnt{0}data{0}Docs", sep);
+        let file = format!("{}{}notes.txt", old, sep);
+        let sibling = format!("{}2{}other.txt", old, sep);
+
+        for path in [&old, &file, &sibling] {
+            db.update_file_state(path, "1:2").await.unwrap();
+            db.update_node_mapping(&NodeMapping {
+                local_path: path.clone(),
+                remote_path: format!("/Docs/{}", path.len()),
+                node_uid: "uid".to_string(),
+                parent_node_uid: "root".to_string(),
+                is_directory: false,
+                updated_at: Utc::now(),
+            })
+            .await
+            .unwrap();
+        }
+        db.set_remote_root(&old, "/Docs", "root-uid").await.unwrap();
+        db.enqueue_job(
+            &SyncEvent {
+                event_type: SyncEventType::Delete,
+                local_path: file.clone(),
+                remote_path: "/Docs/notes.txt".to_string(),
+                change_token: None,
+                old_local_path: None,
+                old_remote_path: None,
+            },
+            JobPriority::Metadata,
+        )
+        .await
+        .unwrap();
+
+        let moved = db.move_local_root(&old, &new).await.unwrap();
+        assert_eq!(
+            moved,
+            MovedRoot {
+                file_states: 2,
+                node_mappings: 2,
+                jobs_dropped: 1,
+            }
+        );
+
+        let moved_file = format!("{}{}notes.txt", new, sep);
+        assert!(db.get_file_state(&moved_file).await.unwrap().is_some());
+        assert!(db.get_file_state(&file).await.unwrap().is_none());
+        // A directory that merely shares the prefix is untouched
+        assert!(db.get_file_state(&sibling).await.unwrap().is_some());
+        assert_eq!(
+            db.get_remote_root(&new).await.unwrap().map(|r| r.0),
+            Some("/Docs".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_schedule_survives_clock_jumps() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let start = Utc::now();
+        let at =
+            |offset: chrono::Duration| db.clone().with_clock(Clock::starting_at(start + offset));
+
+        let id = db
+            .enqueue_job(
+                &SyncEvent {
+                    event_type: SyncEventType::CreateFile,
+                    local_path: "/home/notes.txt".to_string(),
+                    remote_path: "/notes.txt".to_string(),
+                    change_token: None,
+                    old_local_path: None,
+                    old_remote_path: None,
+                },
+                JobPriority::Small,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        db.mark_job_processing(id).await.unwrap();
+        at(chrono::Duration::zero())
+            .increment_job_retry(id, std::time::Duration::from_secs(60))
+            .await
+            .unwrap();
+
+        let (waiting, next) = db.get_next_retry().await.unwrap().unwrap();
+        assert_eq!(waiting, 1);
+        assert!(
+            next.timestamp_millis() >= (start + chrono::Duration::seconds(60)).timestamp_millis()
+        );
+
+        let due = |db: Db| async move { !db.get_pending_jobs(10).await.unwrap().is_empty() };
+
+        assert!(!due(at(chrono::Duration::seconds(59))).await);
+        assert!(due(at(chrono::Duration::seconds(61))).await);
+
+        // After the clock goes back a day the retry waits its delay, not a day
+        assert!(!due(at(chrono::Duration::days(-1))).await);
+        assert!(
+            !due(at(
+                chrono::Duration::days(-1) + chrono::Duration::seconds(30)
+            ))
+            .await
+        );
+        assert!(
+            due(at(
+                chrono::Duration::days(-1) + chrono::Duration::seconds(61)
+            ))
+            .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_is_replaced_and_cleared() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let started_at = Utc::now() - chrono::Duration::hours(1);
+
+        assert_eq!(db.get_heartbeat().await.unwrap(), None);
+
+        db.record_heartbeat(41, started_at).await.unwrap();
+        db.record_heartbeat(42, started_at).await.unwrap();
+        let heartbeat = db.get_heartbeat().await.unwrap().unwrap();
+        assert_eq!(heartbeat.pid, 42);
+        assert_eq!(heartbeat.started_at, started_at);
+        assert!(heartbeat.last_seen > started_at);
+
+        db.clear_heartbeat().await.unwrap();
+        assert_eq!(db.get_heartbeat().await.unwrap(), None);
+
+        assert_eq!(db.get_last_api_contact().await.unwrap(), None);
+        db.set_last_api_contact(started_at).await.unwrap();
+        assert_eq!(db.get_last_api_contact().await.unwrap(), Some(started_at));
+    }
+
+    #[tokio::test]
+    async fn test_expected_changes_expire() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let max_age = chrono::Duration::minutes(5);
+
+        assert_eq!(
+            db.get_expected_change("/home/a.txt", max_age)
+                .await
+                .unwrap(),
+            None
+        );
+        db.expect_change("/home/a.txt", "1:2").await.unwrap();
+        db.expect_change("/home/a.txt", "3:4").await.unwrap();
+        assert_eq!(
+            db.get_expected_change("/home/a.txt", max_age)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("3:4")
+        );
+
+        sqlx::query("UPDATE expected_changes SET expected_at = datetime('now', '-10 minutes')")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            db.get_expected_change("/home/a.txt", max_age)
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(db.expire_expected_changes(max_age).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sync_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let sep = std::path::MAIN_SEPARATOR;
+        let entry = |path: &str, result, days_ago| HistoryEntry {
+            id: 0,
+            event_type: SyncEventType::CreateFile,
+            direction: SyncDirection::Upload,
+            local_path: format!("{0}home{0}{1}", sep, path),
+            remote_path: format!("/{}", path),
+            old_local_path: None,
+            bytes: Some(5),
+            duration_ms: 120,
+            result,
+            error: (result != HistoryResult::Synced).then(|| "Timeout".to_string()),
+            completed_at: Utc::now() - chrono::Duration::days(days_ago),
+        };
+
+        db.record_history(&entry("Docs/a.txt", HistoryResult::Synced, 0))
+            .await
+            .unwrap();
+        db.record_history(&entry("Docs/b.txt", HistoryResult::Blocked, 1))
+            .await
+            .unwrap();
+        db.record_history(&entry("Docs2/c.txt", HistoryResult::Retrying, 2))
+            .await
+            .unwrap();
+        db.record_history(&entry("Docs/old.txt", HistoryResult::Synced, 40))
+            .await
+            .unwrap();
+
+        let week_ago = Utc::now() - chrono::Duration::days(7);
+        let paths = |entries: Vec<HistoryEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.remote_path).collect()
+        };
+
+        assert_eq!(
+            paths(db.get_history(week_ago, false, None, 10).await.unwrap()),
+            ["/Docs/a.txt", "/Docs/b.txt", "/Docs2/c.txt"]
+        );
+        assert_eq!(
+            paths(db.get_history(week_ago, true, None, 10).await.unwrap()),
+            ["/Docs/b.txt", "/Docs2/c.txt"]
+        );
+        // A sibling folder sharing the prefix is not included
+        let docs = format!("{0}home{0}Docs", sep);
+        assert_eq!(
+            paths(
+                db.get_history(week_ago, false, Some(&docs), 10)
+                    .await
+                    .unwrap()
+            ),
+            ["/Docs/a.txt", "/Docs/b.txt"]
+        );
+
+        assert_eq!(
+            db.prune_history(chrono::Duration::days(30)).await.unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_skipped_jobs_after_schema_rebuild() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+
+        // A database from before jobs could be skipped
+        let options = SqliteConnectOptions::new()
+            .filename(&db_path)
+            .create_if_missing(true);
+        let pool = SqlitePool::connect_with(options).await.unwrap();
+        sqlx::query(
+            r#"
+            CREATE TABLE sync_jobs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                event_type TEXT NOT NULL CHECK(event_type IN ('CREATE_FILE', 'CREATE_DIR', 'UPDATE', 'DELETE')),
+                local_path TEXT NOT NULL,
+                remote_path TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'PENDING' CHECK(status IN ('PENDING', 'PROCESSING', 'SYNCED', 'BLOCKED')),
+                retry_at DATETIME,
+                n_retries INTEGER DEFAULT 0,
+                last_error TEXT,
+                change_token TEXT,
+                old_local_path TEXT,
+                old_remote_path TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            INSERT INTO sync_jobs (event_type, local_path, remote_path, n_retries)
+            VALUES ('UPDATE', '/home/a.txt', '/a.txt', 2);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool.close().await;
+
+        let db = Db::new(db_path).await.unwrap();
+        let job = db.get_job(1).await.unwrap().unwrap();
+        assert_eq!(job.local_path, "/home/a.txt");
+        assert_eq!(job.n_retries, 2);
+
+        let skipped = SyncJobStatus::Skipped(SkipReason::PermissionDenied);
+        db.update_job_status(1, skipped, Some("IO error: Permission denied"))
+            .await
+            .unwrap();
+        assert_eq!(db.get_job(1).await.unwrap().unwrap().status, skipped);
+        assert_eq!(db.get_job_count(skipped).await.unwrap(), 1);
+
+        // New jobs continue after the copied ones
+        let id = db
+            .enqueue_job(
+                &SyncEvent {
+                    event_type: SyncEventType::CreateFile,
+                    local_path: "/home/b.txt".to_string(),
+                    remote_path: "/b.txt".to_string(),
+                    change_token: None,
+                    old_local_path: None,
+                    old_remote_path: None,
+                },
+                JobPriority::Small,
+            )
+            .await
+            .unwrap();
+        assert_eq!(id, Some(2));
+
+        assert!(db.requeue_skipped_job(1, "1:5").await.unwrap());
+        let job = db.get_job(1).await.unwrap().unwrap();
+        assert_eq!(job.status, SyncJobStatus::Pending);
+        assert_eq!(job.n_retries, 0);
+        assert_eq!(job.change_token.as_deref(), Some("1:5"));
+        assert!(!db.requeue_skipped_job(1, "1:5").await.unwrap());
+
+        db.update_job_status(1, skipped, None).await.unwrap();
+        db.delete_skipped_jobs("/home/a.txt").await.unwrap();
+        assert!(db.get_job(1).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,234 @@
+//! Coalescing of rapid file system events
+//!
+//! Editors often write a file several times per save, and build tools create
+//! and delete scratch files within moments. Events are held per path until
+//! the path has been quiet for the settle window, then reduced to the net
+//! change: whether the path existed before the burst and whether it exists
+//! now. A path that never goes quiet, like a log file being appended to, is
+//! released after [`MAX_HOLD`] anyway so its changes still reach the queue.
+
+use notify::EventKind;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often the watcher checks for settled paths
+pub const DEBOUNCE_TICK: Duration = Duration::from_millis(250);
+
+/// Longest a path is held after its first event, however busy it stays
+pub const MAX_HOLD: Duration = Duration::from_secs(60);
+
+/// Events seen for a path since it last settled
+#[derive(Debug, Clone, Copy)]
+struct PendingChange {
+    /// Whether the path existed before the first event of the burst
+    existed_before: bool,
+    first_event: Instant,
+    last_event: Instant,
+}
+
+/// Net change to a path after a burst of events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetChange {
+    /// The path is new
+    Created,
+    /// The path existed before and still does
+    Modified,
+    /// The path existed before and is gone
+    Removed,
+    /// The path was created and removed again; nothing to sync
+    Transient,
+}
+
+impl NetChange {
+    /// Reduce a burst to its net change given whether the path exists now
+    pub fn of(existed_before: bool, exists: bool) -> Self {
+        match (existed_before, exists) {
+            (false, true) => Self::Created,
+            (true, true) => Self::Modified,
+            (true, false) => Self::Removed,
+            (false, false) => Self::Transient,
+        }
+    }
+}
+
+/// Holds events per path until they settle
+pub struct Debouncer {
+    window: Duration,
+    pending: HashMap<PathBuf, PendingChange>,
+}
+
+impl Debouncer {
+    /// Create a debouncer with the given settle window
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Record an event for a path
+    ///
+    /// Returns `false` for event kinds that don't change anything.
+    pub fn push(&mut self, path: &Path, kind: &EventKind, now: Instant) -> bool {
+        let existed_before = match kind {
+            EventKind::Create(_) => false,
+            EventKind::Modify(_) | EventKind::Remove(_) => true,
+            _ => return false,
+        };
+
+        self.pending
+            .entry(path.to_path_buf())
+            .and_modify(|change| change.last_event = now)
+            .or_insert(PendingChange {
+                existed_before,
+                first_event: now,
+                last_event: now,
+            });
+
+        true
+    }
+
+    /// Remove a path's pending events, returning whether it existed before them
+    pub fn take(&mut self, path: &Path) -> Option<bool> {
+        self.pending
+            .remove(path)
+            .map(|change| change.existed_before)
+    }
+
+    /// Remove and return paths that have been quiet for the settle window,
+    /// or held for [`MAX_HOLD`]
+    ///
+    /// Each path comes with whether it existed before its burst of events.
+    pub fn take_settled(&mut self, now: Instant) -> Vec<(PathBuf, bool)> {
+        let max_hold = self.window.max(MAX_HOLD);
+        let settled: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, change)| {
+                now.duration_since(change.last_event) >= self.window
+                    || now.duration_since(change.first_event) >= max_hold
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        self.take_paths(settled)
+    }
+
+    /// Remove and return every held path, settled or not
+    pub fn take_all(&mut self) -> Vec<(PathBuf, bool)> {
+        let paths: Vec<PathBuf> = self.pending.keys().cloned().collect();
+        self.take_paths(paths)
+    }
+
+    fn take_paths(&mut self, paths: Vec<PathBuf>) -> Vec<(PathBuf, bool)> {
+        let mut settled: Vec<(PathBuf, bool)> = paths
+            .into_iter()
+            .filter_map(|path| self.take(&path).map(|existed| (path, existed)))
+            .collect();
+
+        // Parents before children, so directories are created first
+        settled.sort();
+        settled
+    }
+
+    /// Number of paths with pending events
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether no events are pending
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, ModifyKind, RemoveKind};
+
+    const WINDOW: Duration = Duration::from_secs(2);
+
+    #[test]
+    fn test_bursts_settle_after_quiet_window() {
+        let mut debouncer = Debouncer::new(WINDOW);
+        let start = Instant::now();
+        let path = Path::new("/sync/notes.txt");
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+
+        assert!(debouncer.push(path, &modify, start));
+        assert!(debouncer.push(path, &modify, start + Duration::from_secs(1)));
+        assert_eq!(debouncer.len(), 1);
+
+        // Each event restarts the window
+        assert!(debouncer
+            .take_settled(start + Duration::from_secs(2))
+            .is_empty());
+        assert_eq!(
+            debouncer.take_settled(start + Duration::from_secs(3)),
+            vec![(path.to_path_buf(), true)]
+        );
+        assert!(debouncer.is_empty());
+    }
+
+    #[test]
+    fn test_first_event_decides_prior_existence() {
+        let mut debouncer = Debouncer::new(WINDOW);
+        let now = Instant::now();
+        let created = Path::new("/sync/b/new.txt");
+        let removed = Path::new("/sync/a/old.txt");
+
+        debouncer.push(created, &EventKind::Create(CreateKind::File), now);
+        debouncer.push(created, &EventKind::Remove(RemoveKind::File), now);
+        debouncer.push(removed, &EventKind::Remove(RemoveKind::File), now);
+        debouncer.push(removed, &EventKind::Create(CreateKind::File), now);
+        assert!(!debouncer.push(
+            created,
+            &EventKind::Access(notify::event::AccessKind::Any),
+            now
+        ));
+
+        assert_eq!(
+            debouncer.take_settled(now + WINDOW),
+            vec![
+                (removed.to_path_buf(), true),
+                (created.to_path_buf(), false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_busy_paths_are_released_after_max_hold() {
+        let mut debouncer = Debouncer::new(WINDOW);
+        let start = Instant::now();
+        let busy = Path::new("/sync/app.log");
+        let quiet = Path::new("/sync/notes.txt");
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+
+        debouncer.push(quiet, &modify, start);
+        let mut now = start;
+        while now < start + MAX_HOLD {
+            debouncer.push(busy, &modify, now);
+            now += Duration::from_secs(1);
+        }
+        assert_eq!(
+            debouncer.take_settled(now),
+            vec![(busy.to_path_buf(), true), (quiet.to_path_buf(), true)]
+        );
+
+        // Stopping takes everything, however recent
+        debouncer.push(busy, &modify, now);
+        assert!(debouncer.take_settled(now).is_empty());
+        assert_eq!(debouncer.take_all(), vec![(busy.to_path_buf(), true)]);
+        assert!(debouncer.is_empty());
+    }
+
+    #[test]
+    fn test_net_change() {
+        assert_eq!(NetChange::of(false, true), NetChange::Created);
+        assert_eq!(NetChange::of(true, true), NetChange::Modified);
+        assert_eq!(NetChange::of(true, false), NetChange::Removed);
+        assert_eq!(NetChange::of(false, false), NetChange::Transient);
+    }
+}
@@ -0,0 +1,220 @@
+//! Startup banner and daily digest
+//!
+//! Daemon logs roll over daily and are kept for a long time. A banner at
+//! startup and a digest of the previous day at midnight give each log file
+//! landmarks to search for, with the numbers as structured fields.
+
+use crate::db::Db;
+use crate::error::Result;
+use crate::queue::JobQueue;
+use crate::types::{Config, DailyDigest};
+use chrono::{DateTime, Days, Local, NaiveDate, TimeZone, Utc};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tracing::info;
+
+/// Log the startup banner
+pub async fn log_startup_banner(db: &Db, db_path: &Path, config: &Config) -> Result<()> {
+    let db_bytes = std::fs::metadata(db_path).map(|m| m.len()).unwrap_or(0);
+    let queue_depth = JobQueue::new(db.clone()).get_status_counts().await?.pending;
+
+    info!(
+        version = env!("CARGO_PKG_VERSION"),
+        config_hash = %config_hash(config),
+        sync_dirs = config.sync_dirs.len(),
+        db_bytes,
+        queue_depth,
+        "Proton Drive Sync {} starting",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    Ok(())
+}
+
+/// Short hash of the configuration, to tell apart runs with different settings
+pub fn config_hash(config: &Config) -> String {
+    let json = serde_json::to_vec(config).unwrap_or_default();
+    hex::encode(&Sha256::digest(&json)[..6])
+}
+
+/// Summarize the sync history of a local calendar day
+pub async fn daily_digest(db: &Db, day: NaiveDate) -> Result<DailyDigest> {
+    let next_day = day.checked_add_days(Days::new(1)).unwrap_or(day);
+    let (files, bytes, errors) = db
+        .get_history_totals(local_midnight(day), local_midnight(next_day))
+        .await?;
+
+    Ok(DailyDigest {
+        day,
+        files,
+        bytes,
+        errors,
+    })
+}
+
+/// Log a daily digest
+pub fn log_digest(digest: &DailyDigest) {
+    info!(
+        day = %digest.day,
+        files = digest.files,
+        bytes = digest.bytes,
+        errors = digest.errors,
+        "Daily digest for {}: {} files synced, {} transferred, {} errors",
+        digest.day,
+        digest.files,
+        crate::progress::format_bytes(digest.bytes.max(0) as u64),
+        digest.errors
+    );
+}
+
+/// Log the digest of the day that just ended, every midnight
+pub async fn run_daily_digest(db: Db) {
+    loop {
+        let now = Local::now();
+        let wait = (next_midnight(now) - now).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+
+        let Some(yesterday) = Local::now().date_naive().pred_opt() else {
+            continue;
+        };
+        match daily_digest(&db, yesterday).await {
+            Ok(digest) => log_digest(&digest),
+            Err(e) => tracing::warn!("Could not build the daily digest: {}", e),
+        }
+    }
+}
+
+/// Start of a local calendar day
+///
+/// Where a DST change skips midnight, the day starts at the first existing
+/// time after it.
+fn local_midnight(day: NaiveDate) -> DateTime<Utc> {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+
+    (0..4)
+        .find_map(|hours| {
+            Local
+                .from_local_datetime(&(midnight + chrono::Duration::hours(hours)))
+                .earliest()
+        })
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_else(|| midnight.and_utc())
+}
+
+/// First midnight after a point in time
+fn next_midnight(now: DateTime<Local>) -> DateTime<Local> {
+    let tomorrow = now
+        .date_naive()
+        .checked_add_days(Days::new(1))
+        .unwrap_or(now.date_naive());
+
+    local_midnight(tomorrow).with_timezone(&Local)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{HistoryEntry, HistoryResult, SyncDirection, SyncEventType};
+    use tempfile::TempDir;
+
+    fn entry(
+        event_type: SyncEventType,
+        bytes: Option<i64>,
+        result: HistoryResult,
+        completed_at: DateTime<Utc>,
+    ) -> HistoryEntry {
+        HistoryEntry {
+            id: 0,
+            event_type,
+            direction: SyncDirection::Upload,
+            local_path: "/home/docs/a.txt".to_string(),
+            remote_path: "/Docs/a.txt".to_string(),
+            old_local_path: None,
+            bytes,
+            duration_ms: 10,
+            result,
+            error: None,
+            completed_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_daily_digest() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+
+        let day = NaiveDate::from_ymd_opt(2026, 10, 15).unwrap();
+        let start = local_midnight(day);
+        let hour = chrono::Duration::hours(1);
+
+        for e in [
+            entry(
+                SyncEventType::CreateFile,
+                Some(100),
+                HistoryResult::Synced,
+                start,
+            ),
+            entry(
+                SyncEventType::Update,
+                Some(50),
+                HistoryResult::Synced,
+                start + hour,
+            ),
+            entry(
+                SyncEventType::Delete,
+                None,
+                HistoryResult::Synced,
+                start + hour,
+            ),
+            entry(
+                SyncEventType::CreateDir,
+                None,
+                HistoryResult::Synced,
+                start + hour,
+            ),
+            entry(
+                SyncEventType::Update,
+                Some(7),
+                HistoryResult::Retrying,
+                start + hour,
+            ),
+            // The previous and next day are left out
+            entry(
+                SyncEventType::CreateFile,
+                Some(9),
+                HistoryResult::Synced,
+                start - hour,
+            ),
+            entry(
+                SyncEventType::CreateFile,
+                Some(9),
+                HistoryResult::Blocked,
+                start + chrono::Duration::hours(24),
+            ),
+        ] {
+            db.record_history(&e).await.unwrap();
+        }
+
+        assert_eq!(
+            daily_digest(&db, day).await.unwrap(),
+            DailyDigest {
+                day,
+                files: 3,
+                bytes: 150,
+                errors: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_next_midnight() {
+        let now = Local.with_ymd_and_hms(2026, 10, 16, 15, 30, 0).unwrap();
+        let midnight = next_midnight(now);
+
+        assert_eq!(
+            midnight.date_naive(),
+            NaiveDate::from_ymd_opt(2026, 10, 17).unwrap()
+        );
+        assert!(midnight > now);
+    }
+}
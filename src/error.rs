@@ -1,6 +1,7 @@
 //! Error types for Proton Drive Sync
 
 use std::path::PathBuf;
+use std::sync::OnceLock;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -18,8 +19,12 @@ pub enum Error {
     #[error("Authentication error: {0}")]
     Auth(String),
 
-    #[error("Proton API error: {0}")]
-    ProtonApi(String),
+    #[error("Proton API error: {message}")]
+    ProtonApi {
+        message: String,
+        /// HTTP status of the failed request, if it got a response
+        status: Option<u16>,
+    },
 
     #[error("HTTP request error: {0}")]
     Http(#[from] reqwest::Error),
@@ -45,6 +50,12 @@ pub enum Error {
     #[error("Invalid state: {0}")]
     InvalidState(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Storage quota exceeded: {0}")]
+    QuotaExceeded(String),
+
     #[error("Timeout")]
     Timeout,
 
@@ -53,6 +64,9 @@ pub enum Error {
 
     #[error("Watch error: {0}")]
     Watch(String),
+
+    #[error("Sandbox error: {0}")]
+    Sandbox(String),
 }
 
 impl From<keyring::Error> for Error {
@@ -78,3 +92,259 @@ impl From<chrono::OutOfRangeError> for Error {
         Error::InvalidState(format!("Duration out of range: {}", err))
     }
 }
+
+impl Error {
+    /// An API error without an HTTP status, such as an error code in a response
+    pub fn api(message: impl Into<String>) -> Self {
+        Self::ProtonApi {
+            message: message.into(),
+            status: None,
+        }
+    }
+
+    /// An API request the server answered with a failure status
+    pub fn api_failed(request: &str, status: reqwest::StatusCode) -> Self {
+        Self::ProtonApi {
+            message: format!("{} failed: {}", request, status),
+            status: Some(status.as_u16()),
+        }
+    }
+
+    /// Whether retrying can never make the operation succeed
+    ///
+    /// Network failures, timeouts, server errors and rate limiting are
+    /// transient. Requests the server rejects as invalid (4xx) and problems
+    /// with the local path or configuration are permanent.
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            Self::Config(_)
+            | Self::InvalidPath(_)
+            | Self::FileNotFound(_)
+            | Self::Encryption(_) => true,
+            Self::Http(_) | Self::ProtonApi { .. } => {
+                self.http_status().is_some_and(is_permanent_status)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a local file or folder could not be read for lack of permission
+    ///
+    /// Such paths are skipped until a scan finds them readable.
+    pub fn is_permission_denied(&self) -> bool {
+        matches!(self, Self::Io(e) if e.kind() == std::io::ErrorKind::PermissionDenied)
+    }
+
+    /// HTTP status of a failed API request, if the error came from one
+    pub fn http_status(&self) -> Option<u16> {
+        match self {
+            Self::Http(e) => e.status().map(|s| s.as_u16()),
+            Self::ProtonApi { status, .. } => *status,
+            _ => None,
+        }
+    }
+
+    /// Coarse class of the error for the retry policy and telemetry
+    ///
+    /// Carries none of the error's details.
+    pub fn class(&self) -> &'static str {
+        if self.http_status() == Some(429) {
+            return "rate_limited";
+        }
+
+        match self {
+            Self::Config(_) => "config",
+            Self::Database(_) => "database",
+            Self::Io(_) if self.is_permission_denied() => "permission_denied",
+            Self::Io(_) => "io",
+            Self::Auth(_) => "auth",
+            Self::ProtonApi { .. } => "proton_api",
+            Self::Http(_) => "http",
+            Self::Json(_) => "json",
+            Self::Keyring(_) => "keyring",
+            Self::Sync(_) => "sync",
+            Self::FileNotFound(_) => "file_not_found",
+            Self::InvalidPath(_) => "invalid_path",
+            Self::Encryption(_) => "encryption",
+            Self::InvalidState(_) => "invalid_state",
+            Self::Conflict(_) => "conflict",
+            Self::QuotaExceeded(_) => "quota_exceeded",
+            Self::Timeout => "timeout",
+            Self::Cancelled => "cancelled",
+            Self::Watch(_) => "watch",
+            Self::Sandbox(_) => "sandbox",
+        }
+    }
+
+    /// Class of an error from its message, such as a job's last error
+    ///
+    /// Each variant is recognized by the text its message starts with, so
+    /// a stored message gets the class of the error it was written from.
+    pub fn class_of(message: &str) -> &'static str {
+        static PREFIXES: OnceLock<Vec<(String, &'static str)>> = OnceLock::new();
+
+        if message.contains(RATE_LIMITED) {
+            return "rate_limited";
+        }
+
+        let prefixes = PREFIXES.get_or_init(|| {
+            Self::examples()
+                .iter()
+                .map(|e| {
+                    let text = e.to_string();
+                    let prefix = match text.find(": ") {
+                        Some(end) => text[..end + 2].to_string(),
+                        None => text,
+                    };
+                    (prefix, e.class())
+                })
+                .collect()
+        });
+
+        match prefixes
+            .iter()
+            .find(|(prefix, _)| message.starts_with(prefix))
+        {
+            Some((_, "io")) if message.contains(PERMISSION_DENIED) => "permission_denied",
+            Some((_, class)) => class,
+            None => "other",
+        }
+    }
+
+    /// One error of each variant, for [`Error::class_of`]
+    fn examples() -> Vec<Error> {
+        let http = reqwest::Client::new()
+            .get("not a url")
+            .build()
+            .expect_err("invalid URL");
+        let json = serde_json::from_str::<()>("").expect_err("empty JSON");
+
+        vec![
+            Self::Config(String::new()),
+            Self::Database(sqlx::Error::PoolClosed),
+            Self::Io(std::io::Error::other("")),
+            Self::Auth(String::new()),
+            Self::api(""),
+            Self::Http(http),
+            Self::Json(json),
+            Self::Keyring(String::new()),
+            Self::Sync(String::new()),
+            Self::FileNotFound(PathBuf::new()),
+            Self::InvalidPath(String::new()),
+            Self::Encryption(String::new()),
+            Self::InvalidState(String::new()),
+            Self::Conflict(String::new()),
+            Self::QuotaExceeded(String::new()),
+            Self::Timeout,
+            Self::Cancelled,
+            Self::Watch(String::new()),
+            Self::Sandbox(String::new()),
+        ]
+    }
+
+    /// What to do about a permanent error
+    pub fn hint(&self) -> &'static str {
+        match (self, self.http_status()) {
+            (Self::InvalidPath(_), _) => {
+                "Rename or exclude the file, then run `proton-drive-sync jobs retry`"
+            }
+            (Self::FileNotFound(_), _) => "The file no longer exists locally",
+            (Self::Io(_), _) if self.is_permission_denied() => {
+                "Make it readable; it is picked up again at the next scan"
+            }
+            (Self::Config(_), _) => {
+                "Fix the configuration, then run `proton-drive-sync jobs retry`"
+            }
+            (Self::Encryption(_), _) => {
+                "Log in again with `proton-drive-sync auth login`, then run `proton-drive-sync jobs retry`"
+            }
+            (_, Some(403)) => "The account may not write to this folder",
+            (_, Some(404)) => {
+                "The remote folder no longer exists; check `proton-drive-sync config show`"
+            }
+            (_, Some(413)) => "The file is too large for Proton Drive",
+            _ => {
+                "Proton rejected the request; check the file name and size, then run `proton-drive-sync jobs retry`"
+            }
+        }
+    }
+}
+
+/// Text the API client writes for a rate-limited request
+const RATE_LIMITED: &str = "429 Too Many Requests";
+
+/// Text of an I/O error for lack of permission
+const PERMISSION_DENIED: &str = "Permission denied";
+
+/// Client errors that retrying cannot fix
+///
+/// Authentication is refreshed by the client, and timeouts, conflicts,
+/// locks and rate limits clear up by themselves.
+fn is_permanent_status(status: u16) -> bool {
+    (400..500).contains(&status) && !matches!(status, 401 | 408 | 409 | 423 | 425 | 429)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_permanent_errors() {
+        let permanent = [
+            Error::api_failed("Create file", StatusCode::BAD_REQUEST),
+            Error::api_failed("Create file", StatusCode::PAYLOAD_TOO_LARGE),
+            Error::api_failed("List revisions", StatusCode::NOT_FOUND),
+            Error::InvalidPath("name too long".to_string()),
+            Error::Config("bad".to_string()),
+        ];
+        for e in &permanent {
+            assert!(e.is_permanent(), "{}", e);
+        }
+
+        let transient = [
+            Error::api_failed("Create file", StatusCode::INTERNAL_SERVER_ERROR),
+            Error::api_failed("Create file", StatusCode::TOO_MANY_REQUESTS),
+            Error::api_failed("Delete", StatusCode::REQUEST_TIMEOUT),
+            Error::api("Create file error code: 2501"),
+            // A status in a message is not the request's status
+            Error::Sync("HTTP 400 Bad Request: {\"Code\": 2001}".to_string()),
+            Error::Timeout,
+        ];
+        for e in &transient {
+            assert!(!e.is_permanent(), "{}", e);
+        }
+
+        assert_eq!(
+            Error::api_failed("Create file", StatusCode::PAYLOAD_TOO_LARGE).hint(),
+            "The file is too large for Proton Drive"
+        );
+    }
+
+    #[test]
+    fn test_class_of_message_matches_variant() {
+        for e in Error::examples() {
+            assert_eq!(Error::class_of(&e.to_string()), e.class(), "{}", e);
+        }
+
+        let denied = Error::from(std::io::Error::from_raw_os_error(13));
+        assert_eq!(denied.class(), "permission_denied");
+        assert_eq!(Error::class_of(&denied.to_string()), "permission_denied");
+        assert_eq!(
+            Error::class_of("Proton API error: Delete failed: 429 Too Many Requests"),
+            "rate_limited"
+        );
+        assert_eq!(Error::class_of("something unexpected"), "other");
+    }
+
+    #[test]
+    fn test_permission_denied() {
+        let denied = Error::from(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+        assert!(denied.is_permission_denied());
+        assert!(!denied.is_permanent());
+        assert!(denied.hint().contains("readable"));
+
+        let missing = Error::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert!(!missing.is_permission_denied());
+    }
+}
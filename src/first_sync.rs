@@ -0,0 +1,209 @@
+//! First sync milestone
+//!
+//! Until a sync directory's initial upload has finished, the remote copy is
+//! incomplete and deleting local files would lose data. The engine notes
+//! when each sync directory is first scanned, and once no jobs under it are
+//! waiting or running it records a one-time summary and announces it in the
+//! log, as a desktop notification and on the dashboard.
+
+use crate::db::Db;
+use crate::error::Result;
+use crate::progress::format_bytes;
+use crate::types::FirstSyncSummary;
+use chrono::Utc;
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// How often sync directories in their initial upload are checked
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Record every initial upload that has finished since the last check
+///
+/// Returns the summaries of the newly completed ones.
+pub async fn complete_finished(db: &Db) -> Result<Vec<FirstSyncSummary>> {
+    let mut completed = Vec::new();
+
+    for (sync_dir, started_at) in db.get_first_syncs_in_progress().await? {
+        let (open, blocked) = db.count_open_jobs_under(&sync_dir).await?;
+        if open > 0 {
+            continue;
+        }
+
+        let files = db.get_synced_files_under(&sync_dir).await?;
+        let bytes = files
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|meta| meta.len() as i64)
+            .sum();
+
+        let summary = FirstSyncSummary {
+            sync_dir,
+            started_at,
+            completed_at: Utc::now(),
+            files: files.len() as i64,
+            bytes,
+            errors: blocked,
+        };
+        db.complete_first_sync(&summary).await?;
+        completed.push(summary);
+    }
+
+    Ok(completed)
+}
+
+/// One-line description of a completed initial upload
+pub fn describe(summary: &FirstSyncSummary) -> String {
+    let duration = (summary.completed_at - summary.started_at)
+        .to_std()
+        .unwrap_or_default();
+
+    format!(
+        "{} files ({}) mirrored in {}, {} errors",
+        summary.files,
+        format_bytes(summary.bytes.max(0) as u64),
+        format_duration(duration),
+        summary.errors
+    )
+}
+
+/// Announce a completed initial upload in the log and as a desktop notification
+pub async fn announce(summary: &FirstSyncSummary) {
+    let description = describe(summary);
+    info!(
+        "First sync of {} complete: {}",
+        summary.sync_dir, description
+    );
+
+    let title = format!("First sync of {} complete", summary.sync_dir);
+    notify_desktop(&title, &description).await;
+}
+
+/// Show a desktop notification, if the platform has a way to
+///
+/// Headless machines have no notification daemon, so failures are ignored.
+async fn notify_desktop(title: &str, body: &str) {
+    #[cfg(target_os = "linux")]
+    let mut command = {
+        let mut command = tokio::process::Command::new("notify-send");
+        command.arg(title).arg(body);
+        command
+    };
+
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut command = tokio::process::Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification \"{}\" with title \"{}\"",
+            escape(body),
+            escape(title)
+        ));
+        command
+    };
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        debug!("Desktop notifications are not supported here: {}", title);
+        let _ = body;
+        return;
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    match command
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .await
+    {
+        Ok(status) if status.success() => {}
+        Ok(status) => debug!("Desktop notification failed: {}", status),
+        Err(e) => debug!("Desktop notification unavailable: {}", e),
+    }
+}
+
+/// Human-readable duration, to the minute once past an hour
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{NodeMapping, SyncEvent, SyncEventType};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_completes_once_queue_drains() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let root = temp_dir.path().join("Docs");
+        std::fs::create_dir_all(&root).unwrap();
+        let file = root.join("notes.txt");
+        std::fs::write(&file, "hello").unwrap();
+        let root = root.to_string_lossy().into_owned();
+        let file = file.to_string_lossy().into_owned();
+
+        // Nothing happens before the first scan has finished
+        db.begin_first_sync(&root).await.unwrap();
+        assert!(complete_finished(&db).await.unwrap().is_empty());
+
+        let job = db
+            .enqueue_job(
+                &SyncEvent {
+                    event_type: SyncEventType::CreateFile,
+                    local_path: file.clone(),
+                    remote_path: "/Docs/notes.txt".to_string(),
+                    change_token: None,
+                    old_local_path: None,
+                    old_remote_path: None,
+                },
+                crate::types::JobPriority::Small,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        db.mark_first_scan(&root).await.unwrap();
+        assert!(complete_finished(&db).await.unwrap().is_empty());
+
+        db.update_job_status(job, crate::types::SyncJobStatus::Synced, None)
+            .await
+            .unwrap();
+        db.update_node_mapping(&NodeMapping {
+            local_path: file,
+            remote_path: "/Docs/notes.txt".to_string(),
+            node_uid: "uid".to_string(),
+            parent_node_uid: "root".to_string(),
+            is_directory: false,
+            updated_at: Utc::now(),
+        })
+        .await
+        .unwrap();
+
+        let completed = complete_finished(&db).await.unwrap();
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].files, 1);
+        assert_eq!(completed[0].bytes, 5);
+        assert_eq!(completed[0].errors, 0);
+        assert_eq!(db.get_first_sync_summaries().await.unwrap(), completed);
+
+        // The milestone is announced only once
+        assert!(complete_finished(&db).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(125)), "2m 5s");
+        assert_eq!(
+            format_duration(Duration::from_secs(3 * 3600 + 120)),
+            "3h 2m"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Sleep inhibition during active uploads
+//!
+//! On Linux, a systemd inhibitor lock in delay mode holds off suspend and
+//! shutdown so a transfer is not cut off halfway. The lock belongs to a
+//! `systemd-inhibit` child process that runs `cat` on a pipe: killing the
+//! child releases it, and so does the daemon exiting for any reason, since
+//! `cat` ends once its stdin is closed.
+
+use std::time::Duration;
+use tracing::debug;
+#[cfg(target_os = "linux")]
+use tracing::{info, warn};
+
+/// How often the queue is checked to take or release the lock
+pub const INHIBIT_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Whether the lock should be held
+///
+/// It is taken once jobs are in flight and kept until no job is pending
+/// either, so the short gaps between jobs don't release and retake it.
+/// Jobs waiting for a retry don't count.
+pub fn wanted(held: bool, in_flight: usize, pending: usize) -> bool {
+    in_flight > 0 || (held && pending > 0)
+}
+
+/// A sleep and shutdown inhibitor lock, taken and released on demand
+#[derive(Default)]
+pub struct SleepInhibitor {
+    child: Option<tokio::process::Child>,
+    /// Set once `systemd-inhibit` failed, so it isn't retried on every check
+    unavailable: bool,
+}
+
+impl SleepInhibitor {
+    /// Create an inhibitor without taking the lock
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the lock is currently held
+    pub fn is_held(&self) -> bool {
+        self.child.is_some()
+    }
+
+    /// Take or release the lock
+    pub async fn set(&mut self, held: bool) {
+        if held {
+            self.check_alive();
+            if self.child.is_none() && !self.unavailable {
+                self.acquire();
+            }
+        } else {
+            self.release().await;
+        }
+    }
+
+    /// Notice an inhibitor that exited on its own, e.g. without logind
+    fn check_alive(&mut self) {
+        let Some(child) = &mut self.child else {
+            return;
+        };
+        if let Ok(Some(status)) = child.try_wait() {
+            debug!("Sleep inhibitor exited: {}", status);
+            self.child = None;
+            self.unavailable = true;
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn acquire(&mut self) {
+        let spawned = tokio::process::Command::new("systemd-inhibit")
+            .args([
+                "--what=sleep:shutdown",
+                "--mode=delay",
+                "--who=proton-drive-sync",
+                "--why=Uploading files to Proton Drive",
+                "cat",
+            ])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .spawn();
+
+        match spawned {
+            Ok(child) => {
+                info!("Delaying sleep and shutdown while uploading");
+                self.child = Some(child);
+            }
+            Err(e) => {
+                warn!("Cannot delay sleep during uploads: {}", e);
+                self.unavailable = true;
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn acquire(&mut self) {
+        debug!("Sleep inhibition is only supported on Linux");
+        self.unavailable = true;
+    }
+
+    async fn release(&mut self) {
+        let Some(mut child) = self.child.take() else {
+            return;
+        };
+        if let Err(e) = child.kill().await {
+            debug!("Failed to stop sleep inhibitor: {}", e);
+        }
+        #[cfg(target_os = "linux")]
+        info!("Sleep and shutdown are no longer delayed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_kept_until_the_queue_is_idle() {
+        assert!(!wanted(false, 0, 0));
+        assert!(!wanted(false, 0, 3));
+        assert!(wanted(false, 1, 3));
+        // Between two jobs
+        assert!(wanted(true, 0, 3));
+        assert!(!wanted(true, 0, 0));
+    }
+}
@@ -0,0 +1,85 @@
+//! Single-instance lock for the sync engine
+//!
+//! Two engines working on the same database upload files twice and fight
+//! over the queue. A running engine holds an exclusive lock on a file in the
+//! data directory; the operating system releases it when the process exits,
+//! even after a crash, so a lock file left behind never blocks a new start.
+//! The owner's process ID is kept in a file next to it for error messages,
+//! since a locked file cannot be read on Windows.
+
+use crate::error::{Error, Result};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+
+/// An exclusive lock held until dropped
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+impl InstanceLock {
+    /// Take the lock, failing if another process holds it
+    pub fn acquire(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let owner = std::fs::read_to_string(pid_path(path))
+                    .ok()
+                    .and_then(|pid| pid.trim().parse::<u32>().ok())
+                    .map(|pid| format!(" (pid {})", pid))
+                    .unwrap_or_default();
+                return Err(Error::InvalidState(format!(
+                    "Sync engine is already running{}",
+                    owner
+                )));
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+
+        std::fs::write(pid_path(path), std::process::id().to_string())?;
+
+        Ok(Self { _file: file })
+    }
+}
+
+/// File holding the process ID of the lock's owner
+fn pid_path(lock_path: &Path) -> PathBuf {
+    lock_path.with_extension("pid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_second_lock_names_the_owner() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("engine.lock");
+
+        let lock = InstanceLock::acquire(&path).unwrap();
+        let err = InstanceLock::acquire(&path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            Error::InvalidState(format!(
+                "Sync engine is already running (pid {})",
+                std::process::id()
+            ))
+            .to_string()
+        );
+
+        drop(lock);
+        InstanceLock::acquire(&path).unwrap();
+    }
+}
@@ -0,0 +1,352 @@
+//! IPC between CLI commands and the running daemon
+//!
+//! The daemon listens on a Unix domain socket (Unix) or a named pipe
+//! (Windows). Each connection carries one newline-delimited JSON request
+//! followed by one JSON response.
+
+use crate::error::{Error, Result};
+use crate::paths::get_ipc_socket_path;
+use crate::sync::{SyncEngine, SyncStatus};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::Notify;
+use tracing::{debug, error, info};
+
+/// How long a client waits for the daemon to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Request sent to the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum IpcRequest {
+    Ping,
+    Status,
+    Pause,
+    Resume,
+    Stop,
+    /// Refresh the session, for a CLI command whose token was rejected
+    RefreshSession,
+}
+
+/// Response returned by the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpcResponse {
+    pub ok: bool,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub status: Option<SyncStatus>,
+    /// Whether a failed request failed because the session was rejected
+    #[serde(default)]
+    pub session_expired: bool,
+}
+
+impl IpcResponse {
+    /// Successful response
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            message: None,
+            status: None,
+            session_expired: false,
+        }
+    }
+
+    /// Successful response carrying the engine status
+    pub fn with_status(status: SyncStatus) -> Self {
+        Self {
+            status: Some(status),
+            ..Self::ok()
+        }
+    }
+
+    /// Failed response
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            message: Some(message.into()),
+            status: None,
+            session_expired: false,
+        }
+    }
+
+    /// Failed response for a session the API rejected
+    pub fn session_expired(message: impl Into<String>) -> Self {
+        Self {
+            session_expired: true,
+            ..Self::error(message)
+        }
+    }
+}
+
+/// IPC server run by the daemon
+pub struct IpcServer {
+    engine: Arc<SyncEngine>,
+    shutdown: Arc<Notify>,
+}
+
+impl IpcServer {
+    /// Create a new IPC server; `shutdown` is notified when a stop request arrives
+    pub fn new(engine: Arc<SyncEngine>, shutdown: Arc<Notify>) -> Self {
+        Self { engine, shutdown }
+    }
+
+    /// Bind the endpoint and start accepting connections
+    pub async fn start(self) -> Result<tokio::task::JoinHandle<()>> {
+        let server = Arc::new(self);
+        let handle = server.listen().await?;
+        info!(
+            "IPC server listening on {}",
+            get_ipc_socket_path()?.display()
+        );
+        Ok(handle)
+    }
+
+    #[cfg(unix)]
+    async fn listen(self: &Arc<Self>) -> Result<tokio::task::JoinHandle<()>> {
+        let path = get_ipc_socket_path()?;
+
+        if path.exists() {
+            if send_request(&IpcRequest::Ping).await?.is_some() {
+                return Err(Error::InvalidState(
+                    "Another sync daemon is already running".to_string(),
+                ));
+            }
+            // Left behind by a daemon that did not shut down cleanly
+            std::fs::remove_file(&path)?;
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let listener = tokio::net::UnixListener::bind(&path)?;
+        let server = self.clone();
+
+        Ok(tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let server = server.clone();
+                        tokio::spawn(async move { server.serve(stream).await });
+                    }
+                    Err(e) => {
+                        error!("IPC accept error: {}", e);
+                        break;
+                    }
+                }
+            }
+        }))
+    }
+
+    #[cfg(windows)]
+    async fn listen(self: &Arc<Self>) -> Result<tokio::task::JoinHandle<()>> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        let pipe_name = get_ipc_socket_path()?;
+        let mut pipe = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(&pipe_name)
+            .map_err(|_| {
+                Error::InvalidState("Another sync daemon is already running".to_string())
+            })?;
+        let server = self.clone();
+
+        Ok(tokio::spawn(async move {
+            loop {
+                if let Err(e) = pipe.connect().await {
+                    error!("IPC accept error: {}", e);
+                    break;
+                }
+
+                let connected = pipe;
+                pipe = match ServerOptions::new().create(&pipe_name) {
+                    Ok(p) => p,
+                    Err(e) => {
+                        error!("IPC pipe error: {}", e);
+                        break;
+                    }
+                };
+
+                let server = server.clone();
+                tokio::spawn(async move { server.serve(connected).await });
+            }
+        }))
+    }
+
+    /// Serve a single connection
+    async fn serve<S: AsyncRead + AsyncWrite + Unpin>(&self, stream: S) {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+
+        if let Err(e) = reader.read_line(&mut line).await {
+            debug!("IPC read error: {}", e);
+            return;
+        }
+
+        let (response, stop) = match serde_json::from_str::<IpcRequest>(line.trim()) {
+            Ok(request) => {
+                let stop = matches!(request, IpcRequest::Stop);
+                (self.handle(request).await, stop)
+            }
+            Err(e) => (IpcResponse::error(format!("Invalid request: {}", e)), false),
+        };
+
+        if let Err(e) = write_message(reader.get_mut(), &response).await {
+            debug!("IPC write error: {}", e);
+        }
+
+        if stop {
+            self.shutdown.notify_one();
+        }
+    }
+
+    /// Dispatch a request to the engine
+    async fn handle(&self, request: IpcRequest) -> IpcResponse {
+        debug!("IPC request: {:?}", request);
+
+        let result = match request {
+            IpcRequest::Ping | IpcRequest::Stop => Ok(IpcResponse::ok()),
+            IpcRequest::Status => self.engine.get_status().await.map(IpcResponse::with_status),
+            IpcRequest::Pause => self.engine.pause().await.map(|_| IpcResponse::ok()),
+            IpcRequest::Resume => self.engine.resume().await.map(|_| IpcResponse::ok()),
+            IpcRequest::RefreshSession => match self.engine.refresh_session().await {
+                Ok(()) => Ok(IpcResponse::ok()),
+                Err(Error::Auth(message)) => Ok(IpcResponse::session_expired(message)),
+                Err(e) => Err(e),
+            },
+        };
+
+        result.unwrap_or_else(|e| IpcResponse::error(e.to_string()))
+    }
+}
+
+/// Send a request to the daemon
+///
+/// Returns `Ok(None)` if no daemon is listening.
+pub async fn send_request(request: &IpcRequest) -> Result<Option<IpcResponse>> {
+    let stream = match connect().await? {
+        Some(stream) => stream,
+        None => return Ok(None),
+    };
+
+    tokio::time::timeout(REQUEST_TIMEOUT, exchange(stream, request))
+        .await
+        .map_err(|_| Error::Timeout)?
+        .map(Some)
+}
+
+#[cfg(unix)]
+async fn connect() -> Result<Option<tokio::net::UnixStream>> {
+    let path = get_ipc_socket_path()?;
+
+    match tokio::net::UnixStream::connect(&path).await {
+        Ok(stream) => Ok(Some(stream)),
+        Err(e) if is_not_listening(&e) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(windows)]
+async fn connect() -> Result<Option<tokio::net::windows::named_pipe::NamedPipeClient>> {
+    use tokio::net::windows::named_pipe::ClientOptions;
+
+    let pipe_name = get_ipc_socket_path()?;
+
+    match ClientOptions::new().open(&pipe_name) {
+        Ok(client) => Ok(Some(client)),
+        Err(e) if is_not_listening(&e) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Check if a connect error means there is no daemon on the other end
+fn is_not_listening(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused
+    )
+}
+
+/// Write a request and read back the response
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: S,
+    request: &IpcRequest,
+) -> Result<IpcResponse> {
+    let mut reader = BufReader::new(stream);
+    write_message(reader.get_mut(), request).await?;
+
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+
+    if line.is_empty() {
+        return Err(Error::InvalidState(
+            "Daemon closed the connection without responding".to_string(),
+        ));
+    }
+
+    Ok(serde_json::from_str(line.trim())?)
+}
+
+/// Write a single JSON message followed by a newline
+async fn write_message<W: AsyncWrite + Unpin, T: Serialize>(
+    writer: &mut W,
+    message: &T,
+) -> Result<()> {
+    let mut json = serde_json::to_string(message)?;
+    json.push('\n');
+    writer.write_all(json.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Remove the socket file on shutdown
+pub fn cleanup() {
+    #[cfg(unix)]
+    if let Ok(path) = get_ipc_socket_path() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_wire_format() {
+        let json = serde_json::to_string(&IpcRequest::Pause).unwrap();
+        assert_eq!(json, r#"{"command":"pause"}"#);
+
+        let request: IpcRequest = serde_json::from_str(r#"{"command":"stop"}"#).unwrap();
+        assert!(matches!(request, IpcRequest::Stop));
+    }
+
+    #[test]
+    fn test_response_defaults() {
+        let response: IpcResponse = serde_json::from_str(r#"{"ok":true}"#).unwrap();
+        assert!(response.ok);
+        assert!(response.message.is_none());
+        assert!(response.status.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_exchange_round_trip() {
+        let (client, server) = tokio::io::duplex(1024);
+
+        let server_task = tokio::spawn(async move {
+            let mut reader = BufReader::new(server);
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            assert_eq!(line.trim(), r#"{"command":"ping"}"#);
+            write_message(reader.get_mut(), &IpcResponse::ok())
+                .await
+                .unwrap();
+        });
+
+        let response = exchange(client, &IpcRequest::Ping).await.unwrap();
+        assert!(response.ok);
+        server_task.await.unwrap();
+    }
+}
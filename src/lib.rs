@@ -2,18 +2,46 @@
 //!
 //! A CLI tool to sync local directories to Proton Drive cloud storage.
 
+pub mod artifacts;
 pub mod auth;
+pub mod cache;
 pub mod cli;
+pub mod clock;
 pub mod config;
+pub mod connectivity;
+pub mod crash;
+pub mod credentials;
 pub mod dashboard;
 pub mod db;
+pub mod debounce;
+pub mod digest;
 pub mod error;
+pub mod first_sync;
+pub mod inhibit;
+pub mod instance;
+pub mod ipc;
+pub mod limits;
 pub mod logger;
+pub mod mime;
+pub mod nosync;
 pub mod paths;
+pub mod preset;
 pub mod processor;
+pub mod progress;
 pub mod proton;
+pub mod protonignore;
 pub mod queue;
+pub mod rate_limit;
+pub mod remote_index;
+pub mod remote_root;
+pub mod sandbox;
+pub mod stats;
+pub mod suspend;
 pub mod sync;
+pub mod telemetry;
+pub mod temp_files;
+pub mod timing;
+pub mod trash;
 pub mod types;
 pub mod watcher;
 
@@ -0,0 +1,144 @@
+//! File descriptor limits
+//!
+//! Large scans combined with many concurrent uploads can exhaust the open
+//! file limit. The daemon raises its soft limit towards the hard limit at
+//! startup and caps upload concurrency to what the effective limit allows.
+
+use serde::Serialize;
+
+/// Soft limit the daemon tries to reach at startup
+pub const DESIRED_FD_LIMIT: u64 = 8192;
+
+/// Descriptors kept free for the database, sockets, logs and the watcher
+const RESERVED_FDS: u64 = 128;
+
+/// Descriptors one upload may hold: the file plus its HTTPS connection, with headroom
+const FDS_PER_UPLOAD: u64 = 4;
+
+/// Open file limits of the current process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FdLimits {
+    pub soft: u64,
+    /// `None` if the hard limit is unlimited
+    pub hard: Option<u64>,
+}
+
+impl FdLimits {
+    /// Number of uploads that can run at once without exhausting the soft limit
+    pub fn max_concurrent_uploads(&self) -> usize {
+        let available = self.soft.saturating_sub(RESERVED_FDS) / FDS_PER_UPLOAD;
+        available.clamp(1, usize::MAX as u64) as usize
+    }
+
+    /// Cap a requested upload concurrency to what the limits allow
+    pub fn cap_concurrency(&self, requested: usize) -> usize {
+        requested.min(self.max_concurrent_uploads())
+    }
+}
+
+/// Current open file limits
+///
+/// Returns `None` on platforms without `RLIMIT_NOFILE`.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast, clippy::useless_conversion)]
+pub fn fd_limits() -> Option<FdLimits> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+
+    // SAFETY: getrlimit only writes to the struct we pass in
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return None;
+    }
+
+    let to_limit = |value: libc::rlim_t| (value != libc::RLIM_INFINITY).then_some(value as u64);
+
+    Some(FdLimits {
+        soft: to_limit(limit.rlim_cur).unwrap_or(u64::MAX),
+        hard: to_limit(limit.rlim_max),
+    })
+}
+
+/// Current open file limits
+///
+/// Returns `None` on platforms without `RLIMIT_NOFILE`.
+#[cfg(not(unix))]
+pub fn fd_limits() -> Option<FdLimits> {
+    None
+}
+
+/// Raise the soft open file limit towards [`DESIRED_FD_LIMIT`]
+///
+/// The limit is never lowered and never raised past the hard limit. Returns
+/// the limits in effect afterwards.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast, clippy::useless_conversion)]
+pub fn raise_fd_limit() -> Option<FdLimits> {
+    let limits = fd_limits()?;
+    let target = limits
+        .hard
+        .map_or(DESIRED_FD_LIMIT, |hard| hard.min(DESIRED_FD_LIMIT));
+
+    if limits.soft >= target {
+        return Some(limits);
+    }
+
+    let limit = libc::rlimit {
+        rlim_cur: target as libc::rlim_t,
+        rlim_max: limits
+            .hard
+            .map_or(libc::RLIM_INFINITY, |hard| hard as libc::rlim_t),
+    };
+
+    // SAFETY: setrlimit only reads the struct we pass in
+    if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) } != 0 {
+        tracing::debug!(
+            "Could not raise open file limit: {}",
+            std::io::Error::last_os_error()
+        );
+        return Some(limits);
+    }
+
+    fd_limits()
+}
+
+/// Raise the soft open file limit towards [`DESIRED_FD_LIMIT`]
+///
+/// Returns `None` on platforms without `RLIMIT_NOFILE`.
+#[cfg(not(unix))]
+pub fn raise_fd_limit() -> Option<FdLimits> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_concurrent_uploads() {
+        let limits = FdLimits {
+            soft: 256,
+            hard: Some(1024),
+        };
+        assert_eq!(limits.max_concurrent_uploads(), 32);
+        assert_eq!(limits.cap_concurrency(4), 4);
+        assert_eq!(limits.cap_concurrency(64), 32);
+
+        let tiny = FdLimits {
+            soft: 64,
+            hard: Some(64),
+        };
+        assert_eq!(tiny.cap_concurrency(8), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_raise_fd_limit_never_lowers() {
+        let before = fd_limits().unwrap();
+        let after = raise_fd_limit().unwrap();
+
+        assert!(after.soft >= before.soft);
+        assert_eq!(after.hard, before.hard);
+    }
+}
@@ -1,9 +1,305 @@
 //! Logging setup
+//!
+//! The daemon logs to `proton-drive-sync.log.<date>`, starting a new file
+//! each day (UTC) and a numbered part (`<date>.1`, `<date>.2`, ...) when a
+//! file outgrows `logs.max_file_size`. A background task compresses the
+//! files no longer written to and deletes them past the configured limits.
 
-use crate::error::Result;
-use std::path::PathBuf;
+use crate::error::{Error, Result};
+use chrono::{NaiveDate, Utc};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
+/// Name of the log files; the date and part are appended to it
+pub const LOG_FILE_NAME: &str = "proton-drive-sync.log";
+
+/// How often old log files are compressed and expired ones deleted
+pub const LOG_CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const MIB: u64 = 1024 * 1024;
+
+/// Size and age limits for the log files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogLimits {
+    /// MiB a log file may grow to before a new part is started (10)
+    ///
+    /// Takes effect when the daemon restarts.
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size: u64,
+    /// MiB all log files may take together; the oldest are deleted first (100)
+    #[serde(default = "default_max_total_size")]
+    pub max_total_size: u64,
+    /// Days log files are kept (14)
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u32,
+}
+
+fn default_max_file_size() -> u64 {
+    10
+}
+
+fn default_max_total_size() -> u64 {
+    100
+}
+
+fn default_retention_days() -> u32 {
+    14
+}
+
+impl Default for LogLimits {
+    fn default() -> Self {
+        Self {
+            max_file_size: default_max_file_size(),
+            max_total_size: default_max_total_size(),
+            retention_days: default_retention_days(),
+        }
+    }
+}
+
+impl LogLimits {
+    /// Check that every limit leaves room for a log file
+    pub fn validate(&self) -> Result<()> {
+        if self.max_file_size == 0 || self.retention_days == 0 {
+            return Err(Error::Config(
+                "logs.max_file_size and logs.retention_days must be at least 1".to_string(),
+            ));
+        }
+        if self.max_total_size < self.max_file_size {
+            return Err(Error::Config(format!(
+                "logs.max_total_size must be at least logs.max_file_size ({} MiB), got {} MiB",
+                self.max_file_size, self.max_total_size
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A log file name: `proton-drive-sync.log.<date>[.<part>][.gz]`
+///
+/// Ordered by date, then part, which is the order the files were written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LogFileName {
+    pub date: NaiveDate,
+    pub part: u32,
+    pub compressed: bool,
+}
+
+impl LogFileName {
+    /// Parse a file name, returning `None` for other files
+    pub fn parse(name: &str) -> Option<Self> {
+        let rest = name.strip_prefix(LOG_FILE_NAME)?.strip_prefix('.')?;
+        let (rest, compressed) = match rest.strip_suffix(".gz") {
+            Some(rest) => (rest, true),
+            None => (rest, false),
+        };
+        let (date, part) = match rest.split_once('.') {
+            Some((date, part)) => (date, part.parse().ok()?),
+            None => (rest, 0),
+        };
+
+        Some(Self {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?,
+            part,
+            compressed,
+        })
+    }
+
+    /// The file name
+    pub fn file_name(&self) -> String {
+        let mut name = format!("{}.{}", LOG_FILE_NAME, self.date.format("%Y-%m-%d"));
+        if self.part > 0 {
+            name.push_str(&format!(".{}", self.part));
+        }
+        if self.compressed {
+            name.push_str(".gz");
+        }
+        name
+    }
+}
+
+/// List the log files in a directory, oldest first
+fn list_log_files(dir: &Path) -> io::Result<Vec<(LogFileName, PathBuf)>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if let Some(name) = entry.file_name().to_str().and_then(LogFileName::parse) {
+            files.push((name, entry.path()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Log file writer that starts a new file each day and above a size limit
+///
+/// Every write is one whole log line, so lines are never split between files.
+pub struct RollingWriter {
+    dir: PathBuf,
+    max_file_size: u64,
+    name: LogFileName,
+    file: File,
+    size: u64,
+}
+
+impl RollingWriter {
+    /// Continue the newest log file of today, or start one
+    pub fn new(dir: &Path, max_file_size: u64) -> io::Result<Self> {
+        let name = Self::current_name(dir, Utc::now().date_naive())?;
+        let (file, size) = Self::open(dir, &name)?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max_file_size,
+            name,
+            file,
+            size,
+        })
+    }
+
+    /// The file to write to on `date`: the newest part, unless it was compressed
+    fn current_name(dir: &Path, date: NaiveDate) -> io::Result<LogFileName> {
+        let latest = list_log_files(dir)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .rfind(|name| name.date == date);
+
+        Ok(match latest {
+            Some(name) if name.compressed => LogFileName {
+                part: name.part + 1,
+                compressed: false,
+                ..name
+            },
+            Some(name) => name,
+            None => LogFileName {
+                date,
+                part: 0,
+                compressed: false,
+            },
+        })
+    }
+
+    fn open(dir: &Path, name: &LogFileName) -> io::Result<(File, u64)> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(name.file_name()))?;
+        let size = file.metadata()?.len();
+        Ok((file, size))
+    }
+
+    fn roll(&mut self, name: LogFileName) -> io::Result<()> {
+        let (file, size) = Self::open(&self.dir, &name)?;
+        self.name = name;
+        self.file = file;
+        self.size = size;
+        Ok(())
+    }
+}
+
+impl Write for RollingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let today = Utc::now().date_naive();
+        if today != self.name.date {
+            let name = Self::current_name(&self.dir, today)?;
+            self.roll(name)?;
+        }
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_file_size {
+            self.roll(LogFileName {
+                part: self.name.part + 1,
+                ..self.name
+            })?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// What a log cleanup did
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LogCleanup {
+    pub compressed: usize,
+    pub deleted: usize,
+}
+
+/// Compress finished log files and delete those past the limits
+///
+/// The newest file is the one being written and is left alone. Files
+/// started before the retention period are deleted, then the oldest ones
+/// until all of them fit in `max_total_size`.
+pub fn clean_up(dir: &Path, limits: &LogLimits, today: NaiveDate) -> io::Result<LogCleanup> {
+    let mut cleanup = LogCleanup::default();
+    let mut files = list_log_files(dir)?;
+    let Some((_, active)) = files.pop() else {
+        return Ok(cleanup);
+    };
+
+    let cutoff = today - chrono::Duration::days(limits.retention_days.into());
+    let mut total = std::fs::metadata(&active)?.len();
+    let mut kept = Vec::new();
+
+    for (name, path) in files {
+        if name.date < cutoff {
+            std::fs::remove_file(&path)?;
+            cleanup.deleted += 1;
+            continue;
+        }
+
+        let path = if name.compressed {
+            path
+        } else {
+            let target = dir.join(
+                LogFileName {
+                    compressed: true,
+                    ..name
+                }
+                .file_name(),
+            );
+            compress(&path, &target)?;
+            cleanup.compressed += 1;
+            target
+        };
+
+        let size = std::fs::metadata(&path)?.len();
+        total += size;
+        kept.push((path, size));
+    }
+
+    for (path, size) in kept {
+        if total <= limits.max_total_size * MIB {
+            break;
+        }
+        std::fs::remove_file(&path)?;
+        total -= size;
+        cleanup.deleted += 1;
+    }
+
+    Ok(cleanup)
+}
+
+/// Gzip a file into `target`, removing the original
+fn compress(path: &Path, target: &Path) -> io::Result<()> {
+    let partial = target.with_extension("gz.partial");
+    let mut input = File::open(path)?;
+    let mut encoder = GzEncoder::new(File::create(&partial)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+
+    std::fs::rename(&partial, target)?;
+    std::fs::remove_file(path)
+}
+
 /// Initialize logging
 pub fn init(debug: bool) -> Result<()> {
     let env_filter = if debug {
@@ -28,10 +324,10 @@ pub fn init(debug: bool) -> Result<()> {
 }
 
 /// Initialize logging with file output
-pub fn init_with_file(log_dir: PathBuf, debug: bool) -> Result<()> {
+pub fn init_with_file(log_dir: PathBuf, debug: bool, limits: &LogLimits) -> Result<()> {
     std::fs::create_dir_all(&log_dir)?;
 
-    let file_appender = tracing_appender::rolling::daily(&log_dir, "proton-drive-sync.log");
+    let file_appender = RollingWriter::new(&log_dir, limits.max_file_size * MIB)?;
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
 
     let env_filter = if debug {
@@ -66,3 +362,118 @@ pub fn init_with_file(log_dir: PathBuf, debug: bool) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_log_file_names_sort_in_write_order() {
+        let names = [
+            "proton-drive-sync.log.2024-05-01.gz",
+            "proton-drive-sync.log.2024-05-01.2",
+            "proton-drive-sync.log.2024-05-01.10",
+            "proton-drive-sync.log.2024-05-02",
+        ];
+        let parsed: Vec<LogFileName> = names
+            .iter()
+            .map(|name| LogFileName::parse(name).unwrap())
+            .collect();
+
+        assert!(parsed.windows(2).all(|pair| pair[0] < pair[1]));
+        for (name, parsed) in names.iter().zip(&parsed) {
+            assert_eq!(parsed.file_name(), *name);
+        }
+
+        assert_eq!(
+            LogFileName::parse("proton-drive-sync.log.2024-05-01.gz.partial"),
+            None
+        );
+        assert_eq!(LogFileName::parse("other.log.2024-05-01"), None);
+    }
+
+    #[test]
+    fn test_writer_starts_a_new_part_above_the_size_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut writer = RollingWriter::new(temp_dir.path(), 12).unwrap();
+
+        writer.write_all(b"line one\n").unwrap();
+        writer.write_all(b"line two\n").unwrap();
+        writer.write_all(b"3\n").unwrap();
+        writer.flush().unwrap();
+
+        let files: Vec<(u32, String)> = list_log_files(temp_dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|(name, path)| (name.part, std::fs::read_to_string(path).unwrap()))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                (0, "line one\n".to_string()),
+                (1, "line two\n3\n".to_string())
+            ]
+        );
+
+        // A restarted writer continues the newest part
+        let writer = RollingWriter::new(temp_dir.path(), 12).unwrap();
+        assert_eq!(writer.name.part, 1);
+    }
+
+    #[test]
+    fn test_clean_up_compresses_and_deletes_old_logs() {
+        let temp_dir = TempDir::new().unwrap();
+        let write = |name: &str, size: usize| {
+            std::fs::write(temp_dir.path().join(name), vec![b'x'; size]).unwrap();
+        };
+        write("proton-drive-sync.log.2024-04-01", 10);
+        write("proton-drive-sync.log.2024-05-01", 2 * MIB as usize);
+        write("proton-drive-sync.log.2024-05-02", 10);
+        write("proton-drive-sync.log.2024-05-03", 10);
+        write("proton-drive-sync.log.2024-05-03.1", 2 * MIB as usize);
+
+        let limits = LogLimits {
+            max_file_size: 1,
+            max_total_size: 3,
+            retention_days: 14,
+        };
+        let cleanup = clean_up(temp_dir.path(), &limits, date("2024-05-03")).unwrap();
+
+        // The April file expired; the rest were compressed, the newest left alone
+        assert_eq!(
+            cleanup,
+            LogCleanup {
+                compressed: 3,
+                deleted: 1,
+            }
+        );
+        let names: Vec<String> = list_log_files(temp_dir.path())
+            .unwrap()
+            .iter()
+            .map(|(name, _)| name.file_name())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "proton-drive-sync.log.2024-05-01.gz",
+                "proton-drive-sync.log.2024-05-02.gz",
+                "proton-drive-sync.log.2024-05-03.gz",
+                "proton-drive-sync.log.2024-05-03.1",
+            ]
+        );
+
+        // Over the total size: the oldest compressed files go first
+        let limits = LogLimits {
+            max_total_size: 2,
+            ..limits
+        };
+        let cleanup = clean_up(temp_dir.path(), &limits, date("2024-05-03")).unwrap();
+        assert_eq!(cleanup.deleted, 3);
+        assert_eq!(list_log_files(temp_dir.path()).unwrap().len(), 1);
+    }
+}
@@ -44,24 +44,98 @@ enum Commands {
     Reset(cli::ResetCommand),
     /// View logs
     Logs(cli::LogsCommand),
+    /// Inspect and export sync jobs
+    Jobs {
+        #[command(subcommand)]
+        command: cli::JobsCommand,
+    },
+    /// Process the job queue without running the daemon
+    Queue {
+        #[command(subcommand)]
+        command: cli::QueueCommand,
+    },
+    /// Package crash reports for bug reports
+    Report {
+        #[command(subcommand)]
+        command: cli::ReportCommand,
+    },
+    /// Inspect and restore local files moved to the trash
+    #[command(visible_alias = "local-trash")]
+    Trash {
+        #[command(subcommand)]
+        command: cli::TrashCommand,
+    },
+    /// List, download and restore remote revisions of a synced file
+    Versions(cli::VersionsCommand),
+    /// Compare local files with the remote without changing anything
+    Verify(cli::VerifyCommand),
+    /// Create a public link to a synced file or folder
+    Share(cli::ShareCommand),
+    /// Show completed and failed sync operations
+    History(cli::HistoryCommand),
+    /// Show how much data was transferred per day and sync directory
+    Stats(cli::StatsCommand),
+    /// List and resolve uploads held back by conflicting remote content
+    Conflicts {
+        #[command(subcommand)]
+        command: cli::ConflictsCommand,
+    },
+    /// Find and clean up mappings of remote nodes whose local files are gone
+    Mappings {
+        #[command(subcommand)]
+        command: cli::MappingsCommand,
+    },
     /// Start web dashboard
     Dashboard(cli::DashboardCommand),
+    /// Check the environment for common problems
+    Doctor(cli::DoctorCommand),
     /// Interactive setup wizard
     Setup(cli::SetupCommand),
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+impl Commands {
+    /// Commands that only inspect state and must not create it
+    fn is_inspection(&self) -> bool {
+        matches!(
+            self,
+            Commands::Status(_) | Commands::Logs(_) | Commands::Stop(_) | Commands::History(_)
+        )
+    }
+}
+
+fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    // Initialize logger
+    // Privileges and the sandbox only cover every thread if they are set up
+    // before the runtime starts its worker threads
+    if let Commands::Start(cmd) = &cli.command {
+        if let Err(e) = cmd.prepare_process() {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run(cli))
+}
+
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    // Initialize logger; inspection commands don't create the log directory
     let log_dir = proton_drive_sync::paths::get_log_dir()?;
     if cli.debug {
         proton_drive_sync::logger::init(true)?;
-    } else {
-        proton_drive_sync::logger::init_with_file(log_dir, false)?;
+    } else if !cli.command.is_inspection() || log_dir.exists() {
+        let limits = proton_drive_sync::config::ConfigManager::load_blocking()
+            .map(|config| config.logs)
+            .unwrap_or_default();
+        proton_drive_sync::logger::init_with_file(log_dir, false, &limits)?;
     }
 
+    // Write a crash bundle if anything panics
+    proton_drive_sync::crash::install_panic_hook();
+
     // Run command
     let result = match cli.command {
         Commands::Auth { command } => command.run().await,
@@ -74,7 +148,19 @@ async fn main() -> anyhow::Result<()> {
         Commands::Reconcile(cmd) => cmd.run().await,
         Commands::Reset(cmd) => cmd.run().await,
         Commands::Logs(cmd) => cmd.run().await,
+        Commands::Jobs { command } => command.run().await,
+        Commands::Queue { command } => command.run().await,
+        Commands::Report { command } => command.run().await,
+        Commands::Trash { command } => command.run().await,
+        Commands::Versions(cmd) => cmd.run().await,
+        Commands::Verify(cmd) => cmd.run().await,
+        Commands::Share(cmd) => cmd.run().await,
+        Commands::History(cmd) => cmd.run().await,
+        Commands::Stats(cmd) => cmd.run().await,
+        Commands::Conflicts { command } => command.run().await,
+        Commands::Mappings { command } => command.run().await,
         Commands::Dashboard(cmd) => cmd.run().await,
+        Commands::Doctor(cmd) => cmd.run().await,
         Commands::Setup(cmd) => cmd.run().await,
     };
 
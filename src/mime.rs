@@ -0,0 +1,80 @@
+//! Content type detection for uploads
+//!
+//! The extension decides first: a user override from `"mime_overrides"`,
+//! then the well-known extension table. Files whose extension says nothing
+//! (or that have none) are identified by their leading magic bytes, and only
+//! then fall back to `application/octet-stream`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Content type used when nothing else identifies a file
+pub const FALLBACK_MIME_TYPE: &str = "application/octet-stream";
+
+/// Detect the content type of a file about to be uploaded
+///
+/// `overrides` maps lowercase extensions, without the dot, to content types.
+pub fn detect(path: &Path, content: &[u8], overrides: &BTreeMap<String, String>) -> String {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+
+    if let Some(mime) = extension.as_ref().and_then(|e| overrides.get(e)) {
+        return mime.clone();
+    }
+
+    if let Some(mime) = mime_guess::from_path(path).first() {
+        return mime.to_string();
+    }
+
+    if let Some(kind) = infer::get(content) {
+        return kind.mime_type().to_string();
+    }
+
+    FALLBACK_MIME_TYPE.to_string()
+}
+
+/// Normalize override keys to what [`detect`] looks up
+///
+/// Users may write `".MD"` as well as `"md"`.
+pub fn normalize_overrides(overrides: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    overrides
+        .iter()
+        .map(|(ext, mime)| (ext.trim_start_matches('.').to_lowercase(), mime.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG_HEADER: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0];
+
+    #[test]
+    fn test_detect_order() {
+        let overrides = normalize_overrides(&BTreeMap::from([(
+            ".MD".to_string(),
+            "text/x-markdown".to_string(),
+        )]));
+
+        // Overrides beat the extension table, case-insensitively
+        assert_eq!(
+            detect(Path::new("notes.Md"), b"# hi", &overrides),
+            "text/x-markdown"
+        );
+        assert_eq!(detect(Path::new("page.html"), b"", &overrides), "text/html");
+
+        // Magic bytes identify files the extension does not
+        assert_eq!(
+            detect(Path::new("scan"), PNG_HEADER, &overrides),
+            "image/png"
+        );
+        assert_eq!(
+            detect(Path::new("export.zzz"), PNG_HEADER, &overrides),
+            "image/png"
+        );
+
+        assert_eq!(
+            detect(Path::new("blob"), b"\x00\x01\x02", &overrides),
+            FALLBACK_MIME_TYPE
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Per-directory opt-out markers
+//!
+//! A directory containing a `.nosync` file, or a valid `CACHEDIR.TAG`
+//! (Cache Directory Tagging Specification), is skipped entirely together
+//! with everything below it.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Marker file that opts a directory out of syncing
+pub const NOSYNC_MARKER: &str = ".nosync";
+
+/// Cache directory tag file name
+pub const CACHEDIR_TAG: &str = "CACHEDIR.TAG";
+
+/// Header a `CACHEDIR.TAG` file must start with to be honoured
+const CACHEDIR_TAG_SIGNATURE: &[u8] = b"Signature: 8a477f597d28d172789f06886806bc55";
+
+/// Check if a directory carries an opt-out marker
+pub fn has_marker(dir: &Path) -> bool {
+    dir.join(NOSYNC_MARKER).is_file() || is_cachedir_tag(&dir.join(CACHEDIR_TAG))
+}
+
+/// Check if a path is one of the marker files
+pub fn is_marker_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|n| n == NOSYNC_MARKER || n == CACHEDIR_TAG)
+}
+
+/// Find the opted-out directory containing a path, if any
+///
+/// Only directories between `root` and `path` (inclusive) are checked.
+pub fn find_marked_root(root: &Path, path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .take_while(|dir| dir.starts_with(root))
+        .filter(|dir| has_marker(dir))
+        .last()
+        .map(Path::to_path_buf)
+}
+
+/// Check that a `CACHEDIR.TAG` file starts with the spec signature
+fn is_cachedir_tag(path: &Path) -> bool {
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+
+    let mut header = [0u8; CACHEDIR_TAG_SIGNATURE.len()];
+    file.read_exact(&mut header).is_ok() && header == CACHEDIR_TAG_SIGNATURE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_nosync_marker() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        fs::write(root.join("a/b/.nosync"), "").unwrap();
+
+        assert!(has_marker(&root.join("a/b")));
+        assert!(!has_marker(&root.join("a")));
+
+        let file = root.join("a/b/c/file.txt");
+        assert_eq!(find_marked_root(root, &file), Some(root.join("a/b")));
+        assert_eq!(find_marked_root(root, &root.join("a/other.txt")), None);
+    }
+
+    #[test]
+    fn test_outermost_marker_wins() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/.nosync"), "").unwrap();
+        fs::write(root.join("a/b/.nosync"), "").unwrap();
+
+        assert_eq!(
+            find_marked_root(root, &root.join("a/b/file.txt")),
+            Some(root.join("a"))
+        );
+    }
+
+    #[test]
+    fn test_cachedir_tag_requires_signature() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+
+        fs::write(dir.join(CACHEDIR_TAG), "not a tag").unwrap();
+        assert!(!has_marker(dir));
+
+        fs::write(
+            dir.join(CACHEDIR_TAG),
+            "Signature: 8a477f597d28d172789f06886806bc55\n# cache\n",
+        )
+        .unwrap();
+        assert!(has_marker(dir));
+    }
+
+    #[test]
+    fn test_is_marker_file() {
+        assert!(is_marker_file(Path::new("/sync/dir/.nosync")));
+        assert!(is_marker_file(Path::new("/sync/dir/CACHEDIR.TAG")));
+        assert!(!is_marker_file(Path::new("/sync/dir/notes.txt")));
+    }
+}
@@ -2,6 +2,8 @@
 
 use crate::error::Result;
 use std::path::{Path, PathBuf};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
 
 /// Get data directory
 pub fn get_data_dir() -> Result<PathBuf> {
@@ -32,15 +34,70 @@ pub fn get_log_dir() -> Result<PathBuf> {
     Ok(log_dir.join("proton-drive-sync").join("logs"))
 }
 
+/// Get crash report directory
+pub fn get_crash_dir() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("crashes"))
+}
+
+/// Get the IPC endpoint used to reach the daemon
+///
+/// A Unix domain socket in the data directory on Unix, a named pipe on Windows.
+pub fn get_ipc_socket_path() -> Result<PathBuf> {
+    #[cfg(windows)]
+    {
+        Ok(PathBuf::from(r"\\.\pipe\proton-drive-sync"))
+    }
+
+    #[cfg(not(windows))]
+    {
+        Ok(get_data_dir()?.join("daemon.sock"))
+    }
+}
+
 /// Normalize a path for comparison
 pub fn normalize_path(path: &Path) -> Result<PathBuf> {
-    let canonical = path
-        .canonicalize()
+    let canonical = canonical(path)
         .map_err(|e| crate::error::Error::InvalidPath(format!("{}: {}", path.display(), e)))?;
 
     Ok(canonical)
 }
 
+/// Canonical form of a path, without a Windows verbatim prefix
+///
+/// Windows canonicalizes `C:\Docs` to `\\?\C:\Docs`, while file events and
+/// paths typed by the user have no prefix, so the two would never match.
+pub fn canonical(path: &Path) -> std::io::Result<PathBuf> {
+    Ok(strip_verbatim(&path.canonicalize()?))
+}
+
+/// Remove the `\\?\` prefix from a Windows drive or UNC path
+///
+/// `\\?\C:\Docs` becomes `C:\Docs` and `\\?\UNC\server\share` becomes
+/// `\\server\share`. Other paths, including verbatim paths that have no
+/// plain form such as `\\?\Volume{...}`, are returned as they are.
+pub fn strip_verbatim(path: &Path) -> PathBuf {
+    let Some(s) = path.to_str() else {
+        return path.to_path_buf();
+    };
+
+    if let Some(rest) = s.strip_prefix(r"\\?\UNC\") {
+        return PathBuf::from(format!(r"\\{}", rest));
+    }
+    match s.strip_prefix(r"\\?\") {
+        Some(rest) if is_drive_path(rest) => PathBuf::from(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Whether a path starts with a drive letter, like `C:\` or `d:`
+fn is_drive_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && bytes.get(2).is_none_or(|&b| b == b'\\' || b == b'/')
+}
+
 /// Join paths safely
 pub fn safe_join(base: &Path, path: &str) -> Result<PathBuf> {
     let joined = base.join(path);
@@ -56,11 +113,380 @@ pub fn safe_join(base: &Path, path: &str) -> Result<PathBuf> {
     Ok(normalized)
 }
 
-/// Get relative path from base
+/// Unicode NFC form of a file name or path
+///
+/// macOS and some network filesystems report names in decomposed form
+/// (NFD), so the same name can reach us as different strings. Remote names
+/// and pattern matching use the composed form.
+pub fn nfc(s: &str) -> String {
+    if is_nfc(s) {
+        s.to_string()
+    } else {
+        s.nfc().collect()
+    }
+}
+
+/// Remote name for a local file name
+///
+/// NFC-normalized. Look-alike characters are kept: a user may type `a：b`
+/// on purpose, so only a name known to come from [`local_name`] may be
+/// matched to its original remote name.
+pub fn remote_name(local: &str) -> String {
+    nfc(local)
+}
+
+/// Local file name for a remote name
+///
+/// On Windows, names the filesystem rejects are escaped with
+/// [`encode_windows_name`]; elsewhere every remote name is valid as is.
+pub fn local_name(remote: &str) -> String {
+    if cfg!(windows) {
+        encode_windows_name(remote)
+    } else {
+        remote.to_string()
+    }
+}
+
+/// Name for `naming` [`crate::types::RemoteNaming::StripForbidden`]
+///
+/// Drops control characters and slashes, which Proton Drive rejects, and
+/// trims leading and trailing whitespace and trailing dots. A name with
+/// nothing left becomes `_`.
+pub fn strip_forbidden_name(name: &str) -> String {
+    let kept: String = name
+        .chars()
+        .filter(|c| !c.is_control() && *c != '/' && *c != '\\')
+        .collect();
+    let trimmed = kept.trim().trim_end_matches('.').trim_end();
+
+    if trimmed.is_empty() {
+        "_".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Name for [`crate::types::RemoteNaming::Slugify`]
+///
+/// Accents are dropped (`Résumé` becomes `resume`), other runs of
+/// characters outside ASCII letters and digits become a single `-`, and
+/// everything is lowercased. The extension is kept, slugified the same way.
+pub fn slugify_name(name: &str) -> String {
+    fn slug(part: &str) -> String {
+        let mut slug = String::with_capacity(part.len());
+        for c in part.nfd().filter(|c| !is_combining_mark(*c)) {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c.to_ascii_lowercase());
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        slug.trim_end_matches('-').to_string()
+    }
+
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (name, None),
+    };
+
+    let stem = match slug(stem) {
+        s if s.is_empty() => "_".to_string(),
+        s => s,
+    };
+    match extension.map(slug) {
+        Some(extension) if !extension.is_empty() => format!("{}.{}", stem, extension),
+        _ => stem,
+    }
+}
+
+/// Marks the next character of an escaped name as literal
+const WINDOWS_ESCAPE: char = '\u{201B}';
+
+/// Characters Windows rejects in file names, and the look-alikes standing in for them
+const WINDOWS_STAND_INS: [(char, char); 9] = [
+    ('<', '＜'),
+    ('>', '＞'),
+    (':', '：'),
+    ('"', '＂'),
+    ('\\', '＼'),
+    ('/', '／'),
+    ('|', '｜'),
+    ('?', '？'),
+    ('*', '＊'),
+];
+
+/// Stand-ins for a trailing dot and space, which Windows strips
+const TRAILING_DOT: char = '．';
+const TRAILING_SPACE: char = '␠';
+
+/// Device names Windows reserves, with or without an extension
+const WINDOWS_RESERVED: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Whether Windows reserves a name for a device, like `CON` or `nul.txt`
+pub fn is_reserved_windows_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end_matches(' ');
+    WINDOWS_RESERVED
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+}
+
+/// Escape a file name so Windows accepts it
+///
+/// Characters Windows rejects become full-width look-alikes (`a:b` becomes
+/// `a：b`), control characters become their Unicode control pictures, a
+/// trailing dot or space becomes `．` or `␠`, and the first letter of a
+/// reserved device name becomes full-width (`CON` becomes `ＣON`). Stand-ins
+/// that were already in the name are prefixed with `‛`, so
+/// [`decode_windows_name`] restores every name exactly.
+pub fn encode_windows_name(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let reserved = is_reserved_windows_name(name);
+    let mut encoded = String::with_capacity(name.len());
+
+    for (i, &c) in chars.iter().enumerate() {
+        let last = i + 1 == chars.len();
+
+        if is_windows_stand_in(c) || (i == 0 && is_full_width_reserved(name)) {
+            encoded.push(WINDOWS_ESCAPE);
+            encoded.push(c);
+        } else if i == 0 && reserved {
+            encoded.push(full_width(c));
+        } else if let Some(&(_, stand_in)) = WINDOWS_STAND_INS.iter().find(|(o, _)| *o == c) {
+            encoded.push(stand_in);
+        } else if ('\u{1}'..='\u{1f}').contains(&c) {
+            encoded.push(char::from_u32(0x2400 + c as u32).unwrap_or(c));
+        } else if last && c == '.' {
+            encoded.push(TRAILING_DOT);
+        } else if last && c == ' ' {
+            encoded.push(TRAILING_SPACE);
+        } else {
+            encoded.push(c);
+        }
+    }
+
+    encoded
+}
+
+/// Restore a file name escaped by [`encode_windows_name`]
+pub fn decode_windows_name(name: &str) -> String {
+    let mut decoded = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    let escaped_first = name.starts_with(WINDOWS_ESCAPE);
+
+    while let Some(c) = chars.next() {
+        if c == WINDOWS_ESCAPE {
+            if let Some(literal) = chars.next() {
+                decoded.push(literal);
+                continue;
+            }
+        }
+
+        let original = if let Some(&(original, _)) = WINDOWS_STAND_INS.iter().find(|(_, s)| *s == c)
+        {
+            original
+        } else if ('\u{2401}'..='\u{241f}').contains(&c) {
+            char::from_u32(c as u32 - 0x2400).unwrap_or(c)
+        } else if c == TRAILING_DOT {
+            '.'
+        } else if c == TRAILING_SPACE {
+            ' '
+        } else {
+            c
+        };
+        decoded.push(original);
+    }
+
+    if !escaped_first && is_full_width_reserved(&decoded) {
+        let mut chars = decoded.chars();
+        if let Some(first) = chars.next() {
+            return format!("{}{}", half_width(first), chars.as_str());
+        }
+    }
+    decoded
+}
+
+/// Whether a character stands in for another in escaped names
+fn is_windows_stand_in(c: char) -> bool {
+    c == WINDOWS_ESCAPE
+        || c == TRAILING_DOT
+        || c == TRAILING_SPACE
+        || ('\u{2401}'..='\u{241f}').contains(&c)
+        || WINDOWS_STAND_INS.iter().any(|(_, stand_in)| *stand_in == c)
+}
+
+/// Whether a name starts with a full-width letter and would be a reserved
+/// device name with that letter in ASCII
+fn is_full_width_reserved(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if half_width(c) != c => {
+            is_reserved_windows_name(&format!("{}{}", half_width(c), chars.as_str()))
+        }
+        _ => false,
+    }
+}
+
+/// Offset from an ASCII character to its full-width form
+const FULL_WIDTH_OFFSET: u32 = 0xFEE0;
+
+/// Full-width form of an ASCII letter
+fn full_width(c: char) -> char {
+    if c.is_ascii_alphabetic() {
+        char::from_u32(c as u32 + FULL_WIDTH_OFFSET).unwrap_or(c)
+    } else {
+        c
+    }
+}
+
+/// ASCII form of a full-width letter
+fn half_width(c: char) -> char {
+    match c {
+        'Ａ'..='Ｚ' | 'ａ'..='ｚ' => char::from_u32(c as u32 - FULL_WIDTH_OFFSET).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// NFC form of a path; paths that are not valid UTF-8 are returned as is
+pub fn nfc_path(path: &Path) -> PathBuf {
+    match path.to_str() {
+        Some(s) if !is_nfc(s) => PathBuf::from(nfc(s)),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Get relative path from base, with `/` as the separator on every platform
 pub fn get_relative_path(base: &Path, full_path: &Path) -> Result<String> {
     let relative = full_path
         .strip_prefix(base)
         .map_err(|_| crate::error::Error::InvalidPath("Path not within base".to_string()))?;
 
-    Ok(relative.to_string_lossy().to_string())
+    Ok(crate::proton::PathUtils::from_local("", relative)
+        .trim_start_matches('/')
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Names Windows rejects or alters, and names that look like escapes
+    const HOSTILE_NAMES: [&str; 21] = [
+        "CON",
+        "con.txt",
+        "Nul.tar.gz",
+        "COM1",
+        "lpt9.log",
+        "AUX .md",
+        "notes.",
+        "draft ",
+        "a..",
+        "what?.txt",
+        "a<b>c:d\"e|f*g",
+        "back\\slash",
+        "tab\there",
+        "line\nbreak",
+        "ＣON",
+        "ＣON.txt",
+        "ＣAT",
+        "a：b",
+        "‛quoted",
+        "ends with ‛",
+        "．hidden",
+    ];
+
+    #[test]
+    fn test_reserved_windows_names() {
+        for name in ["CON", "con", "nul.txt", "Com3.tar.gz", "LPT1", "aux .md"] {
+            assert!(is_reserved_windows_name(name), "{}", name);
+        }
+        for name in [
+            "CONSOLE",
+            "console.log",
+            "COM10",
+            "LPT",
+            "xCON",
+            ".con",
+            "ＣON",
+        ] {
+            assert!(!is_reserved_windows_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_encode_windows_name() {
+        assert_eq!(encode_windows_name("plain name.txt"), "plain name.txt");
+        assert_eq!(encode_windows_name("報告書 🎉.pdf"), "報告書 🎉.pdf");
+        assert_eq!(encode_windows_name("CON"), "ＣON");
+        assert_eq!(encode_windows_name("nul.txt"), "ｎul.txt");
+        assert_eq!(encode_windows_name("what?.txt"), "what？.txt");
+        assert_eq!(encode_windows_name("notes."), "notes．");
+        assert_eq!(encode_windows_name("draft "), "draft␠");
+        assert_eq!(encode_windows_name("line\nbreak"), "line\u{240A}break");
+        assert_eq!(encode_windows_name("a：b"), "a‛：b");
+        assert_eq!(encode_windows_name("ＣON"), "‛ＣON");
+    }
+
+    #[test]
+    fn test_windows_names_round_trip() {
+        let invalid = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+        for name in HOSTILE_NAMES {
+            let encoded = encode_windows_name(name);
+            assert_eq!(decode_windows_name(&encoded), name, "{:?}", encoded);
+
+            // Everything encoded is a valid Windows name
+            assert!(!encoded.contains(invalid), "{:?}", encoded);
+            assert!(
+                !encoded.chars().any(|c| c.is_ascii_control()),
+                "{:?}",
+                encoded
+            );
+            assert!(!encoded.ends_with(['.', ' ']), "{:?}", encoded);
+            assert!(!is_reserved_windows_name(&encoded), "{:?}", encoded);
+        }
+    }
+
+    #[test]
+    fn test_local_and_remote_names() {
+        let remote = "CON.txt";
+        let local = local_name(remote);
+        if cfg!(windows) {
+            assert_eq!(local, "ＣON.txt");
+        } else {
+            assert_eq!(local, remote);
+        }
+        assert_eq!(remote_name("会議：議事録.txt"), "会議：議事録.txt");
+        assert_eq!(remote_name("ＣON"), "ＣON");
+        assert_eq!(remote_name("re\u{301}sume\u{301}.md"), "résumé.md");
+    }
+
+    #[test]
+    fn test_strip_verbatim() {
+        let cases = [
+            (r"\\?\C:\Users\me\Docs", r"C:\Users\me\Docs"),
+            (r"\\?\d:\", r"d:\"),
+            (r"\\?\UNC\server\share\Docs", r"\\server\share\Docs"),
+            (r"\\server\share\Docs", r"\\server\share\Docs"),
+            (r"C:\Docs", r"C:\Docs"),
+            (r"\\?\Volume{1234}\Docs", r"\\?\Volume{1234}\Docs"),
+            ("/home/me/Docs", "/home/me/Docs"),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(strip_verbatim(Path::new(path)), PathBuf::from(expected));
+        }
+    }
+
+    #[test]
+    fn test_canonical() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path().join("Docs");
+        std::fs::create_dir(&dir).unwrap();
+
+        let canonical = canonical(&dir.join("..").join("Docs")).unwrap();
+        assert!(!canonical.to_string_lossy().starts_with(r"\\?\"));
+        assert!(canonical.ends_with("Docs"));
+    }
 }
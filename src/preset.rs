@@ -0,0 +1,156 @@
+//! Curated bundles of sync settings
+//!
+//! A preset sets the behavior-related options in one go; sync directories,
+//! exclusions, the dashboard and telemetry are left alone.
+
+use crate::types::{ChangeDetection, Config, RemoteDeleteBehavior};
+use serde::Serialize;
+
+/// A named bundle of settings
+#[derive(Debug, Clone, Copy)]
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub sync_concurrency: usize,
+    pub remote_delete_behavior: RemoteDeleteBehavior,
+    pub change_detection: ChangeDetection,
+    pub verify_uploads: bool,
+}
+
+/// All available presets
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "paranoid",
+        description: "Verify every upload, hash file contents, low concurrency",
+        sync_concurrency: 2,
+        remote_delete_behavior: RemoteDeleteBehavior::Trash,
+        change_detection: ChangeDetection::Full,
+        verify_uploads: true,
+    },
+    Preset {
+        name: "fast",
+        description: "Quick change detection and high concurrency",
+        sync_concurrency: 8,
+        remote_delete_behavior: RemoteDeleteBehavior::Trash,
+        change_detection: ChangeDetection::Quick,
+        verify_uploads: false,
+    },
+    Preset {
+        name: "minimal",
+        description: "One upload at a time with the lightest checks",
+        sync_concurrency: 1,
+        remote_delete_behavior: RemoteDeleteBehavior::Trash,
+        change_detection: ChangeDetection::Quick,
+        verify_uploads: false,
+    },
+];
+
+/// A setting whose value differs from a preset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SettingDiff {
+    pub setting: &'static str,
+    pub preset: String,
+    pub current: String,
+}
+
+/// Find a preset by name
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+}
+
+impl Preset {
+    /// Apply the preset's settings to a config
+    pub fn apply(&self, config: &mut Config) {
+        config.sync_concurrency = self.sync_concurrency;
+        config.remote_delete_behavior = self.remote_delete_behavior;
+        config.change_detection = self.change_detection;
+        config.verify_uploads = self.verify_uploads;
+        config.preset = Some(self.name.to_string());
+    }
+
+    /// Settings in a config that deviate from the preset
+    pub fn diff(&self, config: &Config) -> Vec<SettingDiff> {
+        let settings = [
+            (
+                "sync_concurrency",
+                self.sync_concurrency.to_string(),
+                config.sync_concurrency.to_string(),
+            ),
+            (
+                "remote_delete_behavior",
+                setting_value(&self.remote_delete_behavior),
+                setting_value(&config.remote_delete_behavior),
+            ),
+            (
+                "change_detection",
+                setting_value(&self.change_detection),
+                setting_value(&config.change_detection),
+            ),
+            (
+                "verify_uploads",
+                self.verify_uploads.to_string(),
+                config.verify_uploads.to_string(),
+            ),
+        ];
+
+        settings
+            .into_iter()
+            .filter(|(_, preset, current)| preset != current)
+            .map(|(setting, preset, current)| SettingDiff {
+                setting,
+                preset,
+                current,
+            })
+            .collect()
+    }
+}
+
+/// Render a setting the way it is written in the config file
+fn setting_value<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(s)) => s,
+        Ok(other) => other.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_preset() {
+        assert_eq!(find("paranoid").unwrap().name, "paranoid");
+        assert_eq!(find("FAST").unwrap().name, "fast");
+        assert!(find("reckless").is_none());
+    }
+
+    #[test]
+    fn test_apply_then_diff() {
+        let preset = find("paranoid").unwrap();
+        let mut config = Config::default();
+
+        preset.apply(&mut config);
+        assert_eq!(config.preset.as_deref(), Some("paranoid"));
+        assert!(preset.diff(&config).is_empty());
+
+        config.sync_concurrency = 6;
+        config.change_detection = ChangeDetection::Quick;
+
+        assert_eq!(
+            preset.diff(&config),
+            vec![
+                SettingDiff {
+                    setting: "sync_concurrency",
+                    preset: "2".to_string(),
+                    current: "6".to_string(),
+                },
+                SettingDiff {
+                    setting: "change_detection",
+                    preset: "full".to_string(),
+                    current: "quick".to_string(),
+                },
+            ]
+        );
+    }
+}
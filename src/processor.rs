@@ -2,12 +2,200 @@
 
 use crate::db::Db;
 use crate::error::{Error, Result};
+use crate::progress::{format_bytes, ProgressCounter, PROGRESS_FLUSH_INTERVAL};
 use crate::proton::{PathUtils, ProtonClient};
-use crate::types::{SyncEventType, SyncJob, SyncJobStatus};
-use chrono::{Duration, Utc};
+use crate::remote_index::{RemoteIndex, FOLDER_NODE_TYPE};
+use crate::types::{
+    AccountInfo, ChangeDetection, Conflict, ConflictPolicy, ConflictResolution, HistoryEntry,
+    HistoryResult, JobSource, NodeData, NodeMapping, RetryPolicy, SkipReason, SyncDirection,
+    SyncEvent, SyncEventType, SyncJob, SyncJobStatus,
+};
+use crate::watcher::{change_token, enqueue, write_own_file};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::future::Future;
 use std::path::Path;
-use tokio::sync::Semaphore;
-use tracing::{error, info, warn};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::{debug, error, info, warn};
+
+/// Bytes per memory budget permit
+const MEMORY_PERMIT_BYTES: u64 = 1024;
+
+/// Memory that concurrent uploads may hold at once
+///
+/// Each upload reserves its file size, so the budget admits many small
+/// files together but only a few large ones. A file larger than the whole
+/// budget reserves all of it and runs alone.
+struct MemoryBudget {
+    semaphore: Semaphore,
+    permits: u32,
+}
+
+impl MemoryBudget {
+    /// Create a budget of the given size in MiB
+    fn new(mib: u64) -> Self {
+        let permits = (mib.saturating_mul(1024 * 1024) / MEMORY_PERMIT_BYTES)
+            .clamp(1, u32::MAX as u64) as u32;
+
+        Self {
+            semaphore: Semaphore::new(permits as usize),
+            permits,
+        }
+    }
+
+    /// Wait until an upload of the given size fits in the budget
+    async fn reserve(&self, bytes: u64) -> Result<SemaphorePermit<'_>> {
+        Ok(self.semaphore.acquire_many(self.permits_for(bytes)).await?)
+    }
+
+    /// Wait until `bytes` more fit beside the `held` bytes a job reserved
+    ///
+    /// Never asks for more than the budget has left beside them, so a job
+    /// cannot wait on its own reservation. Returns `None` if nothing is left.
+    async fn reserve_more(&self, held: u64, bytes: u64) -> Result<Option<SemaphorePermit<'_>>> {
+        let wanted = self
+            .permits_for(bytes)
+            .min(self.permits - self.permits_for(held));
+        if wanted == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(self.semaphore.acquire_many(wanted).await?))
+    }
+
+    /// Permits covering the given number of bytes
+    fn permits_for(&self, bytes: u64) -> u32 {
+        bytes
+            .div_ceil(MEMORY_PERMIT_BYTES)
+            .clamp(1, self.permits as u64) as u32
+    }
+}
+
+/// How long the account's storage usage is trusted before asking again
+const QUOTA_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Storage usage of the account, for checking uploads before they start
+///
+/// Usage is fetched at most once per [`QUOTA_CACHE_TTL`]. Uploads that pass
+/// the check hold a reservation while they run, so concurrent uploads cannot
+/// overshoot the quota together, and count as used once they succeed. While
+/// usage cannot be fetched, uploads are allowed.
+#[derive(Default)]
+struct QuotaTracker {
+    usage: tokio::sync::Mutex<Option<(Instant, Option<AccountInfo>)>>,
+    /// Bytes held by uploads still running
+    reserved: AtomicU64,
+    /// Bytes uploaded since usage was last fetched
+    uploaded: AtomicU64,
+}
+
+/// An upload's hold on quota, released when dropped unless the upload succeeded
+struct QuotaReservation<'a> {
+    tracker: &'a QuotaTracker,
+    bytes: u64,
+    uploaded: bool,
+}
+
+impl QuotaReservation<'_> {
+    /// Count the reserved bytes as used
+    fn commit(mut self) {
+        self.uploaded = true;
+    }
+}
+
+impl Drop for QuotaReservation<'_> {
+    fn drop(&mut self) {
+        self.tracker
+            .reserved
+            .fetch_sub(self.bytes, Ordering::SeqCst);
+        if self.uploaded {
+            self.tracker
+                .uploaded
+                .fetch_add(self.bytes, Ordering::SeqCst);
+        }
+    }
+}
+
+impl QuotaTracker {
+    /// Reserve room for an upload, failing if the account has too little
+    async fn reserve(&self, client: &ProtonClient, bytes: u64) -> Result<QuotaReservation<'_>> {
+        let reservation = QuotaReservation {
+            tracker: self,
+            bytes: 0,
+            uploaded: false,
+        };
+        let mut usage = self.usage.lock().await;
+
+        if usage
+            .as_ref()
+            .is_none_or(|(at, _)| at.elapsed() >= QUOTA_CACHE_TTL)
+        {
+            let account = match client.get_account_info().await {
+                Ok(account) => Some(account),
+                Err(e) => {
+                    debug!("Could not fetch storage usage: {}", e);
+                    None
+                }
+            };
+            *usage = Some((Instant::now(), account));
+            self.uploaded.store(0, Ordering::SeqCst);
+        }
+
+        let Some((_, Some(account))) = usage.as_ref() else {
+            return Ok(reservation);
+        };
+        if account.max_space == 0 {
+            return Ok(reservation);
+        }
+
+        let used = account.used_space
+            + self.uploaded.load(Ordering::SeqCst)
+            + self.reserved.load(Ordering::SeqCst);
+        let free = account.max_space.saturating_sub(used);
+        if bytes > free {
+            return Err(Error::QuotaExceeded(format!(
+                "upload needs {} but only {} of {} is free",
+                format_bytes(bytes),
+                format_bytes(free),
+                format_bytes(account.max_space)
+            )));
+        }
+
+        self.reserved.fetch_add(bytes, Ordering::SeqCst);
+        Ok(QuotaReservation {
+            bytes,
+            ..reservation
+        })
+    }
+}
+
+/// Jobs and local paths currently being processed
+#[derive(Default)]
+struct InFlight {
+    jobs: HashSet<i64>,
+    paths: HashSet<String>,
+}
+
+/// A job's hold on its local paths, released when dropped
+struct JobClaim {
+    in_flight: Arc<Mutex<InFlight>>,
+    job_id: i64,
+    paths: Vec<String>,
+}
+
+impl Drop for JobClaim {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        in_flight.jobs.remove(&self.job_id);
+        for path in &self.paths {
+            in_flight.paths.remove(path);
+        }
+    }
+}
 
 /// Job processor
 pub struct JobProcessor {
@@ -17,6 +205,18 @@ pub struct JobProcessor {
     concurrency: usize,
     semaphore: Semaphore,
     remote_delete_behavior: crate::types::RemoteDeleteBehavior,
+    verify_uploads: bool,
+    memory_budget: Option<MemoryBudget>,
+    retry_policy: RetryPolicy,
+    /// Content types by lowercase extension, from `"mime_overrides"`
+    mime_overrides: BTreeMap<String, String>,
+    conflict_policy: ConflictPolicy,
+    /// Follow-up uploads above this many MiB are queued as large files
+    small_file_threshold: u64,
+    /// Mode for change tokens of files written locally
+    change_detection: ChangeDetection,
+    quota: QuotaTracker,
+    in_flight: Arc<Mutex<InFlight>>,
 }
 
 impl JobProcessor {
@@ -26,6 +226,9 @@ impl JobProcessor {
         client: ProtonClient,
         concurrency: usize,
         remote_delete_behavior: crate::types::RemoteDeleteBehavior,
+        verify_uploads: bool,
+        max_upload_memory: Option<u64>,
+        retry_policy: RetryPolicy,
     ) -> Self {
         Self {
             db,
@@ -33,12 +236,109 @@ impl JobProcessor {
             concurrency,
             semaphore: Semaphore::new(concurrency),
             remote_delete_behavior,
+            verify_uploads,
+            memory_budget: max_upload_memory.map(MemoryBudget::new),
+            retry_policy,
+            mime_overrides: BTreeMap::new(),
+            conflict_policy: ConflictPolicy::default(),
+            small_file_threshold: crate::types::default_small_file_threshold(),
+            change_detection: ChangeDetection::default(),
+            quota: QuotaTracker::default(),
+            in_flight: Arc::new(Mutex::new(InFlight::default())),
+        }
+    }
+
+    /// Use these content types for file extensions instead of detecting them
+    pub fn with_mime_overrides(mut self, overrides: &BTreeMap<String, String>) -> Self {
+        self.mime_overrides = crate::mime::normalize_overrides(overrides);
+        self
+    }
+
+    /// Settle uploads whose name is taken by different content this way
+    pub fn with_conflict_policy(mut self, policy: ConflictPolicy) -> Self {
+        self.conflict_policy = policy;
+        self
+    }
+
+    /// Prioritize follow-up uploads by this `"small_file_threshold"`, in MiB
+    pub fn with_small_file_threshold(mut self, mib: u64) -> Self {
+        self.small_file_threshold = mib;
+        self
+    }
+
+    /// Record files written locally with change tokens of this mode
+    pub fn with_change_detection(mut self, mode: ChangeDetection) -> Self {
+        self.change_detection = mode;
+        self
+    }
+
+    /// Process a job in a background task
+    ///
+    /// Returns `false` without spawning if the job, or another job for one
+    /// of its paths, is already being processed; it is picked up again by a
+    /// later poll. It is also skipped if it changed in the database since it
+    /// was read. Spawned jobs wait for an upload slot before starting.
+    pub async fn spawn_job(self: &Arc<Self>, job: SyncJob) -> Result<bool> {
+        let Some(claim) = self.try_claim(&job) else {
+            return Ok(false);
+        };
+        let Some(job) = self.db.claim_job(job.id).await? else {
+            return Ok(false);
+        };
+
+        let processor = self.clone();
+        tokio::spawn(async move {
+            let _claim = claim;
+            if let Err(e) = processor.process_job(&job).await {
+                error!("Error processing job {}: {}", job.id, e);
+            }
+        });
+
+        Ok(true)
+    }
+
+    /// Claim a job and its paths so no other job for them runs concurrently
+    fn try_claim(&self, job: &SyncJob) -> Option<JobClaim> {
+        let paths: Vec<String> = std::iter::once(&job.local_path)
+            .chain(job.old_local_path.as_ref())
+            .cloned()
+            .collect();
+
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight.jobs.contains(&job.id) || paths.iter().any(|p| in_flight.paths.contains(p)) {
+            return None;
         }
+
+        in_flight.jobs.insert(job.id);
+        in_flight.paths.extend(paths.iter().cloned());
+
+        Some(JobClaim {
+            in_flight: self.in_flight.clone(),
+            job_id: job.id,
+            paths,
+        })
     }
 
     /// Process a single job
     pub async fn process_job(&self, job: &SyncJob) -> Result<()> {
+        self.process_job_recorded(job).await?.1
+    }
+
+    /// Process a single job and return its history entry with its outcome
+    ///
+    /// The outer error is for failures around the job, such as the
+    /// database, which leave no history entry.
+    pub async fn process_job_recorded(&self, job: &SyncJob) -> Result<(HistoryEntry, Result<()>)> {
+        let upload_size = Self::upload_size(job).await;
+
+        // Reserve memory before taking an upload slot, so a large file
+        // waiting for memory doesn't hold up smaller ones
+        let _memory = match &self.memory_budget {
+            Some(budget) if upload_size > 0 => Some(budget.reserve(upload_size).await?),
+            _ => None,
+        };
         let _permit = self.semaphore.acquire().await?;
+        let started = std::time::Instant::now();
 
         // Mark job as processing
         self.db.mark_job_processing(job.id).await?;
@@ -46,17 +346,50 @@ impl JobProcessor {
         // Add to processing queue
         self.db.add_to_processing_queue(&job.local_path).await?;
 
-        // Process the job
-        let result = match job.event_type {
-            SyncEventType::CreateFile => self.process_create_file(job).await,
-            SyncEventType::CreateDir => self.process_create_dir(job).await,
-            SyncEventType::Update => self.process_update(job).await,
-            SyncEventType::Delete => self.process_delete(job).await,
+        // Process the job, unless its upload would not fit in the account
+        let quota = match upload_size {
+            0 => Ok(None),
+            bytes => self.quota.reserve(&self.client, bytes).await.map(Some),
+        };
+        let result = match quota {
+            Err(e) => Err(e),
+            Ok(reservation) => {
+                let result = match job.event_type {
+                    SyncEventType::CreateFile => self.process_create_file(job).await,
+                    SyncEventType::CreateDir => self.process_create_dir(job).await,
+                    SyncEventType::Update => match (&job.old_local_path, &job.old_remote_path) {
+                        (Some(old_local), Some(old_remote)) => {
+                            self.process_move(job, old_local, old_remote).await
+                        }
+                        _ => self.process_update(job).await,
+                    },
+                    SyncEventType::Delete => self.process_delete(job).await,
+                };
+                // A failed or cancelled upload drops its reservation
+                if let (Ok(()), Some(reservation)) = (&result, reservation) {
+                    reservation.commit();
+                }
+                result
+            }
         };
 
         // Remove from processing queue
         let _ = self.db.remove_from_processing_queue(&job.local_path).await;
 
+        let mut history = HistoryEntry {
+            id: 0,
+            event_type: job.event_type,
+            direction: SyncDirection::Upload,
+            local_path: job.local_path.clone(),
+            remote_path: job.remote_path.clone(),
+            old_local_path: job.old_local_path.clone(),
+            bytes: (upload_size > 0).then_some(upload_size as i64),
+            duration_ms: started.elapsed().as_millis() as i64,
+            result: HistoryResult::Synced,
+            error: None,
+            completed_at: Utc::now(),
+        };
+
         match result {
             Ok(_) => {
                 // Mark as synced
@@ -68,39 +401,175 @@ impl JobProcessor {
                 if job.event_type != SyncEventType::Delete {
                     if let Some(token) = &job.change_token {
                         let _ = self.db.update_file_state(&job.local_path, token).await;
+                        if Self::uploads_content(job) {
+                            self.requeue_if_changed(job, token).await;
+                        }
                     }
                 } else {
                     let _ = self.db.delete_file_state(&job.local_path).await;
                 }
 
+                self.record_history(&history).await;
+
                 info!("Synced: {} -> {}", job.local_path, job.remote_path);
-                Ok(())
+                Ok((history, Ok(())))
             }
             Err(e) => {
                 error!("Failed to sync {}: {}", job.local_path, e);
 
                 // Check if we should retry
-                if job.n_retries < 5 {
-                    // Calculate retry time with exponential backoff
-                    let retry_delay =
-                        std::time::Duration::from_secs(60 * 2_u64.pow(job.n_retries as u32));
-                    let retry_at = Utc::now() + Duration::from_std(retry_delay).unwrap();
+                let mut message = e.to_string();
 
-                    self.db.increment_job_retry(job.id, retry_at).await?;
-
-                    warn!("Job {} will retry at {}", job.id, retry_at);
-                } else {
-                    // Mark as blocked
+                // An unreadable file waits until a scan finds it readable
+                if e.is_permission_denied() {
+                    message = format!("{}. {}", message, e.hint());
+                    warn!("Skipping {}: {}", job.local_path, message);
                     self.db
-                        .update_job_status(job.id, SyncJobStatus::Blocked, Some(&e.to_string()))
+                        .update_job_status(
+                            job.id,
+                            SyncJobStatus::Skipped(SkipReason::PermissionDenied),
+                            Some(&message),
+                        )
                         .await?;
+                    history.error = Some(message);
+                    history.result = HistoryResult::Skipped;
+                    self.record_history(&history).await;
+                    return Ok((history, Err(e)));
+                }
+
+                let n_retries = job.n_retries.max(0) as u32;
+                // A conflict waits for a decision, so retrying cannot help.
+                // A retry rule configured for the class outranks blocking.
+                let retry_delay = match e {
+                    Error::Conflict(_) => None,
+                    _ if self.retry_policy.overrides.contains_key(e.class()) => {
+                        self.retry_policy.retry_delay(e.class(), n_retries)
+                    }
+                    _ if e.is_permanent() => {
+                        message = format!("{}. {}", message, e.hint());
+                        warn!("Job {} cannot succeed by retrying: {}", job.id, message);
+                        None
+                    }
+                    _ => self.retry_policy.retry_delay(e.class(), n_retries),
+                };
+                history.error = Some(message.clone());
+                match retry_delay {
+                    Some(retry_delay) => {
+                        let retry_at = self.db.increment_job_retry(job.id, retry_delay).await?;
+
+                        warn!("Job {} will retry at {}", job.id, retry_at);
+                        history.result = HistoryResult::Retrying;
+                    }
+                    None => {
+                        // Mark as blocked
+                        self.db
+                            .update_job_status(job.id, SyncJobStatus::Blocked, Some(&message))
+                            .await?;
+                        history.result = HistoryResult::Blocked;
+                    }
                 }
 
-                Err(e)
+                self.record_history(&history).await;
+
+                Ok((history, Err(e)))
             }
         }
     }
 
+    /// Queue the file again if it changed while it was uploading
+    ///
+    /// The upload may have read the old content, the new one or a mix of
+    /// both, so the remote is brought up to date now instead of at the next
+    /// scan. The token is recomputed in the mode that produced the job's.
+    async fn requeue_if_changed(&self, job: &SyncJob, token: &str) {
+        let mode = crate::types::ChangeDetection::of_token(token);
+        let Ok(current) = change_token(Path::new(&job.local_path), mode).await else {
+            // Gone or unreadable; the watcher reports whatever happened to it
+            return;
+        };
+        if current == token {
+            return;
+        }
+
+        let event = SyncEvent {
+            event_type: SyncEventType::Update,
+            local_path: job.local_path.clone(),
+            remote_path: job.remote_path.clone(),
+            change_token: Some(current),
+            old_local_path: None,
+            old_remote_path: None,
+        };
+        match enqueue(
+            &self.db,
+            &event,
+            self.small_file_threshold,
+            JobSource::Engine,
+        )
+        .await
+        {
+            Ok(_) => info!("{} changed during upload, queued it again", job.local_path),
+            Err(e) => warn!("Error requeueing {}: {}", job.local_path, e),
+        }
+    }
+
+    /// Add an operation to the sync history; failures only affect the history
+    async fn record_history(&self, entry: &HistoryEntry) {
+        if let Err(e) = self.db.record_history(entry).await {
+            debug!("Failed to record history of {}: {}", entry.local_path, e);
+        }
+    }
+
+    /// Whether a job uploads file content, rather than only changing the tree
+    fn uploads_content(job: &SyncJob) -> bool {
+        match job.event_type {
+            SyncEventType::CreateFile => true,
+            SyncEventType::Update => job.old_local_path.is_none(),
+            SyncEventType::CreateDir | SyncEventType::Delete => false,
+        }
+    }
+
+    /// Bytes a job will hold in memory while uploading
+    async fn upload_size(job: &SyncJob) -> u64 {
+        if !Self::uploads_content(job) {
+            return 0;
+        }
+
+        tokio::fs::metadata(&job.local_path)
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0)
+    }
+
+    /// Run an upload while recording its progress for status displays
+    async fn track_progress<T>(
+        &self,
+        job: &SyncJob,
+        total_bytes: u64,
+        progress: &ProgressCounter,
+        upload: impl Future<Output = T>,
+    ) -> T {
+        tokio::pin!(upload);
+        let mut ticker = tokio::time::interval(PROGRESS_FLUSH_INTERVAL);
+
+        let result = loop {
+            tokio::select! {
+                result = &mut upload => break result,
+                _ = ticker.tick() => {
+                    if let Err(e) = self
+                        .db
+                        .set_job_progress(job.id, &job.local_path, progress.get(), total_bytes)
+                        .await
+                    {
+                        debug!("Failed to record progress of job {}: {}", job.id, e);
+                    }
+                }
+            }
+        };
+
+        let _ = self.db.clear_job_progress(job.id).await;
+        result
+    }
+
     /// Process create file job
     async fn process_create_file(&self, job: &SyncJob) -> Result<()> {
         let path = Path::new(&job.local_path);
@@ -108,6 +577,7 @@ impl JobProcessor {
         if !path.exists() {
             return Err(Error::FileNotFound(path.to_path_buf()));
         }
+        self.check_name_collision(job).await?;
 
         // Read file content
         let content = tokio::fs::read(path).await?;
@@ -123,31 +593,40 @@ impl JobProcessor {
         let file_name = PathUtils::filename(&job.remote_path);
 
         // Detect mime type
-        let mime_type = mime_guess::from_path(path)
-            .first()
-            .map(|m| m.to_string())
-            .or_else(|| {
-                if path.extension().is_some_and(|e| e == "txt") {
-                    Some("text/plain".to_string())
-                } else {
-                    Some("application/octet-stream".to_string())
-                }
-            });
+        let mime_type = Some(crate::mime::detect(path, &content, &self.mime_overrides));
 
         // Create file
+        let size = content.len();
+        let progress = ProgressCounter::default();
         let result = self
-            .client
-            .create_file(&parent_id, &file_name, content, mime_type.as_deref())
+            .track_progress(
+                job,
+                size as u64,
+                &progress,
+                self.client.create_file(
+                    &parent_id,
+                    &file_name,
+                    content,
+                    mime_type.as_deref(),
+                    Some(&progress),
+                ),
+            )
             .await?;
 
-        if !result.success {
-            return Err(Error::Sync(
-                result.error.unwrap_or_else(|| "Unknown error".to_string()),
-            ));
-        }
+        let node_uid = if result.already_exists {
+            Some(
+                self.resolve_name_conflict(job, &parent_id, &file_name)
+                    .await?,
+            )
+        } else if !result.success {
+            return Err(result.into_error());
+        } else {
+            self.verify_upload(result.node_uid.as_deref(), size).await?;
+            result.node_uid
+        };
 
         // Store node mapping
-        if let Some(node_uid) = result.node_uid {
+        if let Some(node_uid) = node_uid {
             let mapping = crate::types::NodeMapping {
                 local_path: job.local_path.clone(),
                 remote_path: job.remote_path.clone(),
@@ -163,8 +642,299 @@ impl JobProcessor {
         Ok(())
     }
 
+    /// Settle an upload refused because its name is taken remotely
+    ///
+    /// Identical content is adopted as is. Otherwise the conflict policy
+    /// decides: keep both by renaming the remote file to a conflicted copy
+    /// and uploading again, replace it with a new revision, or record the
+    /// conflict for `conflicts resolve`. Returns the node now holding the
+    /// local content.
+    async fn resolve_name_conflict(
+        &self,
+        job: &SyncJob,
+        parent_id: &str,
+        file_name: &str,
+    ) -> Result<String> {
+        let index = RemoteIndex::new(&self.client, &self.db);
+        let existing = index
+            .relist_children(parent_id)
+            .await?
+            .into_iter()
+            .find(|n| PathUtils::same_name(&n.name, file_name))
+            .ok_or_else(|| {
+                Error::Sync(format!(
+                    "{} already exists remotely but could not be found",
+                    job.remote_path
+                ))
+            })?;
+
+        // Renaming a folder to a conflicted copy would move everything in it
+        if existing.node_type == FOLDER_NODE_TYPE {
+            return Err(Error::InvalidPath(format!(
+                "{} is a folder in Proton Drive; rename the local file or the remote folder",
+                job.remote_path
+            )));
+        }
+
+        let path = Path::new(&job.local_path);
+        let content = tokio::fs::read(path).await?;
+
+        if self.has_same_content(&existing, &content).await? {
+            info!("Adopted identical remote file {}", job.remote_path);
+            return Ok(existing.uid);
+        }
+
+        match self.conflict_policy {
+            ConflictPolicy::Ask => {
+                self.db
+                    .record_conflict(&Conflict {
+                        id: 0,
+                        job_id: job.id,
+                        local_path: job.local_path.clone(),
+                        remote_path: job.remote_path.clone(),
+                        node_uid: existing.uid,
+                        parent_uid: parent_id.to_string(),
+                        detected_at: Utc::now(),
+                    })
+                    .await?;
+
+                Err(Error::Conflict(format!(
+                    "{} differs from the remote file; run `proton-drive-sync conflicts resolve`",
+                    job.remote_path
+                )))
+            }
+            ConflictPolicy::Replace => {
+                self.upload_over(
+                    path,
+                    content,
+                    &job.remote_path,
+                    parent_id,
+                    &existing.uid,
+                    false,
+                )
+                .await
+            }
+            ConflictPolicy::KeepBoth => {
+                self.upload_over(
+                    path,
+                    content,
+                    &job.remote_path,
+                    parent_id,
+                    &existing.uid,
+                    true,
+                )
+                .await
+            }
+        }
+    }
+
+    /// Upload a local file in place of different remote content
+    ///
+    /// With `keep_both` the remote node is renamed to a conflicted copy and
+    /// the file uploaded alongside it; otherwise it becomes a new revision
+    /// of the remote node. Returns the node now holding the local content.
+    async fn upload_over(
+        &self,
+        path: &Path,
+        content: Vec<u8>,
+        remote_path: &str,
+        parent_id: &str,
+        existing_uid: &str,
+        keep_both: bool,
+    ) -> Result<String> {
+        let file_name = PathUtils::filename(remote_path);
+        let size = content.len();
+        let mime_type = Some(crate::mime::detect(path, &content, &self.mime_overrides));
+
+        let result = if keep_both {
+            let conflict_name = conflicted_name(&file_name, Utc::now());
+            info!(
+                "Keeping remote {} as {} and uploading the local copy",
+                remote_path, conflict_name
+            );
+            self.client
+                .rename_node(existing_uid, &conflict_name)
+                .await?;
+            RemoteIndex::new(&self.client, &self.db)
+                .relist_children(parent_id)
+                .await?;
+
+            self.client
+                .create_file(parent_id, &file_name, content, mime_type.as_deref(), None)
+                .await?
+        } else {
+            info!("Replacing different remote file {}", remote_path);
+            self.client
+                .create_revision(
+                    existing_uid,
+                    &file_name,
+                    content,
+                    mime_type.as_deref(),
+                    None,
+                )
+                .await?
+        };
+
+        if !result.success {
+            return Err(result.into_error());
+        }
+
+        let node_uid = result
+            .node_uid
+            .ok_or_else(|| Error::Sync("Upload returned no node UID".to_string()))?;
+        self.verify_upload(Some(&node_uid), size).await?;
+
+        Ok(node_uid)
+    }
+
+    /// Apply a decision on a conflict recorded under the `ask` policy
+    ///
+    /// Keeping the remote file downloads it over the local one, which is
+    /// moved to the `trash` directory first. The conflict's blocked job
+    /// is marked synced and the conflict removed.
+    pub async fn resolve_conflict(
+        &self,
+        conflict: &Conflict,
+        resolution: ConflictResolution,
+        trash: &Path,
+    ) -> Result<()> {
+        let path = Path::new(&conflict.local_path);
+        let started = std::time::Instant::now();
+
+        let (node_uid, bytes, direction) = match resolution {
+            ConflictResolution::KeepRemote => {
+                let node = self
+                    .client
+                    .get_node(&conflict.node_uid)
+                    .await?
+                    .ok_or_else(|| {
+                        Error::Sync(format!(
+                            "{} no longer exists remotely",
+                            conflict.remote_path
+                        ))
+                    })?;
+                let revision = node.active_revision.ok_or_else(|| {
+                    Error::Sync(format!("{} has no content remotely", conflict.remote_path))
+                })?;
+
+                // Download first so a failure leaves the local file in place
+                let content = self
+                    .client
+                    .download_revision(&node.uid, &revision.uid)
+                    .await?;
+                if path.exists() {
+                    crate::trash::move_to_trash(trash, path)?;
+                }
+                let bytes = content.len();
+                write_own_file(&self.db, path, &content, self.change_detection).await?;
+
+                info!("Kept remote {} over the local copy", conflict.remote_path);
+                (node.uid, bytes, SyncDirection::Download)
+            }
+            ConflictResolution::KeepLocal | ConflictResolution::KeepBoth => {
+                let content = tokio::fs::read(path).await?;
+                let bytes = content.len();
+                let node_uid = self
+                    .upload_over(
+                        path,
+                        content,
+                        &conflict.remote_path,
+                        &conflict.parent_uid,
+                        &conflict.node_uid,
+                        resolution == ConflictResolution::KeepBoth,
+                    )
+                    .await?;
+                (node_uid, bytes, SyncDirection::Upload)
+            }
+        };
+
+        self.db
+            .update_node_mapping(&NodeMapping {
+                local_path: conflict.local_path.clone(),
+                remote_path: conflict.remote_path.clone(),
+                node_uid,
+                parent_node_uid: conflict.parent_uid.clone(),
+                is_directory: false,
+                updated_at: Utc::now(),
+            })
+            .await?;
+        self.db
+            .update_job_status(conflict.job_id, SyncJobStatus::Synced, None)
+            .await?;
+        self.db.delete_conflict(conflict.id).await?;
+
+        self.record_history(&HistoryEntry {
+            id: 0,
+            event_type: SyncEventType::Update,
+            direction,
+            local_path: conflict.local_path.clone(),
+            remote_path: conflict.remote_path.clone(),
+            old_local_path: None,
+            bytes: Some(bytes as i64),
+            duration_ms: started.elapsed().as_millis() as i64,
+            result: HistoryResult::Synced,
+            error: None,
+            completed_at: Utc::now(),
+        })
+        .await;
+
+        Ok(())
+    }
+
+    /// Check whether a remote file holds exactly the given content
+    ///
+    /// Sizes are compared first so only same-sized files are downloaded.
+    async fn has_same_content(&self, node: &NodeData, content: &[u8]) -> Result<bool> {
+        let Some(revision) = &node.active_revision else {
+            return Ok(false);
+        };
+
+        if revision.size != Some(content.len() as i64) {
+            return Ok(false);
+        }
+
+        // The job's reservation covers the local copy, not the remote one
+        let _memory = match &self.memory_budget {
+            Some(budget) => {
+                let bytes = content.len() as u64;
+                budget.reserve_more(bytes, bytes).await?
+            }
+            None => None,
+        };
+
+        let remote = self
+            .client
+            .download_revision(&node.uid, &revision.uid)
+            .await?;
+
+        Ok(Sha256::digest(&remote) == Sha256::digest(content))
+    }
+
+    /// Fail if another local file already owns the job's remote path
+    ///
+    /// Sanitized or renamed names can give different local names the same
+    /// remote name, and uploading both would mix up their contents.
+    async fn check_name_collision(&self, job: &SyncJob) -> Result<()> {
+        let Some(owner) = self
+            .db
+            .get_node_mapping_by_remote_path(&job.remote_path)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        if owner.local_path == job.local_path || !Path::new(&owner.local_path).exists() {
+            return Ok(());
+        }
+        Err(Error::InvalidPath(format!(
+            "{} and {} both map to the remote path {}",
+            job.local_path, owner.local_path, job.remote_path
+        )))
+    }
+
     /// Process create directory job
     async fn process_create_dir(&self, job: &SyncJob) -> Result<()> {
+        self.check_name_collision(job).await?;
         // Get parent directory from remote path
         let parent_path = PathUtils::parent(&job.remote_path)
             .ok_or_else(|| Error::InvalidPath("No parent directory".to_string()))?;
@@ -175,28 +945,22 @@ impl JobProcessor {
         // Get folder name
         let folder_name = PathUtils::filename(&job.remote_path);
 
-        // Create folder
-        let result = self.client.create_folder(&parent_id, &folder_name).await?;
-
-        if !result.success {
-            return Err(Error::Sync(
-                result.error.unwrap_or_else(|| "Unknown error".to_string()),
-            ));
-        }
+        // Create folder, or adopt one created earlier as a parent
+        let node_uid = self
+            .find_or_create_folder(&parent_id, &folder_name, &job.remote_path)
+            .await?;
 
         // Store node mapping
-        if let Some(node_uid) = result.node_uid {
-            let mapping = crate::types::NodeMapping {
-                local_path: job.local_path.clone(),
-                remote_path: job.remote_path.clone(),
-                node_uid,
-                parent_node_uid: parent_id,
-                is_directory: true,
-                updated_at: Utc::now(),
-            };
+        let mapping = crate::types::NodeMapping {
+            local_path: job.local_path.clone(),
+            remote_path: job.remote_path.clone(),
+            node_uid,
+            parent_node_uid: parent_id,
+            is_directory: true,
+            updated_at: Utc::now(),
+        };
 
-            let _ = self.db.update_node_mapping(&mapping).await;
-        }
+        let _ = self.db.update_node_mapping(&mapping).await;
 
         Ok(())
     }
@@ -215,42 +979,57 @@ impl JobProcessor {
             .get_node_mapping(&job.local_path, &job.remote_path)
             .await?;
 
-        if existing.is_none() {
+        let existing = match existing {
+            Some(existing) => existing,
             // File doesn't exist remotely, treat as create
-            return self.process_create_file(job).await;
-        }
+            None => return self.process_create_file(job).await,
+        };
 
         // Read file content
         let content = tokio::fs::read(path).await?;
 
-        // Delete old and create new (Proton Drive doesn't have a direct update)
-        let existing = existing.unwrap();
-        self.client.delete_node(&existing.node_uid).await?;
-
-        // Get parent node ID
-        let parent_id = existing.parent_node_uid;
-
         // Get file name
         let file_name = PathUtils::filename(&job.remote_path);
 
         // Detect mime type
-        let mime_type = mime_guess::from_path(path)
-            .first()
-            .map(|m| m.to_string())
-            .or_else(|| Some("application/octet-stream".to_string()));
+        let mime_type = Some(crate::mime::detect(path, &content, &self.mime_overrides));
 
-        // Create new file
+        // Upload a new revision so the node UID and version history are kept
+        let size = content.len();
+        let progress = ProgressCounter::default();
         let result = self
-            .client
-            .create_file(&parent_id, &file_name, content, mime_type.as_deref())
+            .track_progress(
+                job,
+                size as u64,
+                &progress,
+                self.client.create_revision(
+                    &existing.node_uid,
+                    &file_name,
+                    content,
+                    mime_type.as_deref(),
+                    Some(&progress),
+                ),
+            )
             .await?;
 
         if !result.success {
-            return Err(Error::Sync(
-                result.error.unwrap_or_else(|| "Unknown error".to_string()),
-            ));
+            // The node may have been deleted remotely; upload it afresh
+            if self.client.get_node(&existing.node_uid).await?.is_none() {
+                debug!(
+                    "Remote node for {} is gone, uploading as new file",
+                    job.local_path
+                );
+                self.db
+                    .delete_node_mapping(&job.local_path, &job.remote_path)
+                    .await?;
+                return self.process_create_file(job).await;
+            }
+
+            return Err(result.into_error());
         }
 
+        self.verify_upload(Some(&existing.node_uid), size).await?;
+
         Ok(())
     }
 
@@ -263,45 +1042,1038 @@ impl JobProcessor {
             .await?;
 
         if let Some(existing) = existing {
-            // Delete based on behavior
-            match self.remote_delete_behavior {
-                crate::types::RemoteDeleteBehavior::Trash => {
-                    self.client.delete_node(&existing.node_uid).await?;
-                }
-                crate::types::RemoteDeleteBehavior::Permanent => {
-                    self.client
-                        .delete_node_permanent(&existing.node_uid)
-                        .await?;
-                }
-            }
+            self.delete_remote_node(&existing.node_uid).await?;
 
             // Remove node mapping
             let _ = self
                 .db
                 .delete_node_mapping(&job.local_path, &job.remote_path)
                 .await;
+
+            // A deleted folder took its contents along
+            if existing.is_directory {
+                self.forget_contents(&job.local_path, &job.remote_path)
+                    .await?;
+            }
         }
 
         Ok(())
     }
 
+    /// Drop the mappings and file states of a deleted folder's contents
+    async fn forget_contents(&self, local_path: &str, remote_path: &str) -> Result<()> {
+        let local_prefix = format!("{}{}", local_path, std::path::MAIN_SEPARATOR);
+        for mapping in self.db.get_node_mappings_under(&local_prefix).await? {
+            if !Path::new(&mapping.remote_path).starts_with(remote_path) {
+                continue;
+            }
+            self.db
+                .delete_node_mapping(&mapping.local_path, &mapping.remote_path)
+                .await?;
+            self.db.delete_file_state(&mapping.local_path).await?;
+        }
+        Ok(())
+    }
+
+    /// Process move/rename job
+    ///
+    /// Moves or renames the existing remote node instead of deleting and
+    /// re-uploading it.
+    async fn process_move(
+        &self,
+        job: &SyncJob,
+        old_local_path: &str,
+        old_remote_path: &str,
+    ) -> Result<()> {
+        let existing = self
+            .db
+            .get_node_mapping(old_local_path, old_remote_path)
+            .await?;
+
+        let Some(existing) = existing else {
+            // Never uploaded, so upload at the new location
+            return if Path::new(&job.local_path).is_dir() {
+                self.process_create_dir(job).await
+            } else {
+                self.process_create_file(job).await
+            };
+        };
+
+        if self
+            .db
+            .get_node_mapping(&job.local_path, &job.remote_path)
+            .await?
+            .is_some()
+        {
+            // Already uploaded at the new location, so only the old copy is left
+            self.delete_remote_node(&existing.node_uid).await?;
+            return self
+                .db
+                .delete_node_mapping(old_local_path, old_remote_path)
+                .await;
+        }
+        self.check_name_collision(job).await?;
+
+        let parent_path = PathUtils::parent(&job.remote_path)
+            .ok_or_else(|| Error::InvalidPath("No parent directory".to_string()))?;
+        let parent_id = self.get_or_create_parent_node(&parent_path).await?;
+        let new_name = PathUtils::filename(&job.remote_path);
+
+        if parent_id != existing.parent_node_uid {
+            self.client
+                .move_node(&existing.node_uid, &parent_id, &new_name)
+                .await?;
+        } else if new_name != PathUtils::filename(old_remote_path) {
+            self.client
+                .rename_node(&existing.node_uid, &new_name)
+                .await?;
+        }
+
+        self.db
+            .move_node_mappings(
+                old_local_path,
+                &job.local_path,
+                old_remote_path,
+                &job.remote_path,
+                &parent_id,
+            )
+            .await?;
+
+        info!("Moved: {} -> {}", old_remote_path, job.remote_path);
+        Ok(())
+    }
+
+    /// Check that an uploaded file exists remotely with the expected size
+    ///
+    /// Only runs when `verify_uploads` is enabled.
+    async fn verify_upload(&self, node_uid: Option<&str>, size: usize) -> Result<()> {
+        if !self.verify_uploads {
+            return Ok(());
+        }
+
+        let node_uid =
+            node_uid.ok_or_else(|| Error::Sync("Upload returned no node UID".to_string()))?;
+
+        let node = self
+            .client
+            .get_node(node_uid)
+            .await?
+            .ok_or_else(|| Error::Sync(format!("Uploaded node {} not found", node_uid)))?;
+
+        let remote_size = node.active_revision.and_then(|r| r.size);
+        if remote_size != Some(size as i64) {
+            return Err(Error::Sync(format!(
+                "Upload verification failed: expected {} bytes, remote has {:?}",
+                size, remote_size
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Delete a remote node according to the configured delete behavior
+    async fn delete_remote_node(&self, node_uid: &str) -> Result<()> {
+        match self.remote_delete_behavior {
+            crate::types::RemoteDeleteBehavior::Trash => self.client.delete_node(node_uid).await,
+            crate::types::RemoteDeleteBehavior::Permanent => {
+                self.client.delete_node_permanent(node_uid).await
+            }
+        }
+    }
+
     /// Get or create parent node
-    async fn get_or_create_parent_node(&self, _remote_path: &str) -> Result<String> {
-        // Check if parent exists in mappings
-        // For simplicity, we'll just use the root ID
-        // In a full implementation, you'd walk up the path
+    ///
+    /// Walks the path from the root, using synced folders from the node
+    /// mappings, then existing remote folders, and creating the rest.
+    async fn get_or_create_parent_node(&self, remote_path: &str) -> Result<String> {
+        let mut node_id = self.client.get_root_id();
+        let mut current_path = "/".to_string();
 
-        Ok(self.client.get_root_id())
+        for segment in remote_path.split('/').filter(|s| !s.is_empty()) {
+            current_path = PathUtils::join(&current_path, segment);
+
+            node_id = match self
+                .db
+                .get_node_mapping_by_remote_path(&current_path)
+                .await?
+            {
+                Some(mapping) if mapping.is_directory => mapping.node_uid,
+                _ => {
+                    self.find_or_create_folder(&node_id, segment, &current_path)
+                        .await?
+                }
+            };
+        }
+
+        Ok(node_id)
+    }
+
+    /// Find a folder by name in a parent, creating it if it does not exist
+    ///
+    /// Each creation is recorded in the folder creation ledger first. If an
+    /// earlier attempt was interrupted, the parent is listed afresh, since
+    /// the folder may have been created without the index knowing.
+    async fn find_or_create_folder(
+        &self,
+        parent_id: &str,
+        name: &str,
+        remote_path: &str,
+    ) -> Result<String> {
+        let index = RemoteIndex::new(&self.client, &self.db);
+
+        let interrupted = self.db.is_folder_creation_pending(parent_id, name).await?;
+        let children = if interrupted {
+            index.relist_children(parent_id).await?
+        } else {
+            index.list_children(parent_id).await?
+        };
+
+        let existing = children
+            .into_iter()
+            .find(|n| PathUtils::same_name(&n.name, name) && n.node_type == FOLDER_NODE_TYPE);
+
+        if let Some(folder) = existing {
+            if interrupted {
+                self.db.finish_folder_creation(parent_id, name).await?;
+            }
+            return Ok(folder.uid);
+        }
+
+        self.db
+            .begin_folder_creation(parent_id, name, remote_path)
+            .await?;
+
+        // A request error leaves the ledger entry: the folder may exist now
+        let result = self.client.create_folder(parent_id, name).await?;
+
+        if !result.success {
+            self.db.finish_folder_creation(parent_id, name).await?;
+            return Err(result.into_error());
+        }
+
+        let node_uid = result
+            .node_uid
+            .ok_or_else(|| Error::Sync("Create folder returned no node UID".to_string()))?;
+
+        index.add_created_folder(parent_id, name, &node_uid).await?;
+        self.db.finish_folder_creation(parent_id, name).await?;
+
+        Ok(node_uid)
+    }
+
+    /// Settle folder creations interrupted by a crash or restart
+    ///
+    /// Each parent is listed afresh so folders that were created remotely
+    /// get into the index instead of being created a second time.
+    pub async fn recover_folder_creations(&self) -> Result<usize> {
+        let index = RemoteIndex::new(&self.client, &self.db);
+        let pending = self.db.get_pending_folder_creations().await?;
+
+        for (parent_id, name, remote_path) in &pending {
+            let created =
+                index.relist_children(parent_id).await?.iter().any(|n| {
+                    PathUtils::same_name(&n.name, name) && n.node_type == FOLDER_NODE_TYPE
+                });
+
+            if created {
+                info!("Adopted folder {} created before the restart", remote_path);
+            } else {
+                info!("Folder {} was not created before the restart", remote_path);
+            }
+
+            self.db.finish_folder_creation(parent_id, name).await?;
+        }
+
+        Ok(pending.len())
     }
 
     /// Refresh client session
-    pub async fn refresh_session(&mut self) -> Result<()> {
+    pub async fn refresh_session(&self) -> Result<()> {
         self.client.refresh_session().await?;
         Ok(())
     }
 
+    /// Refresh client session if the access token expires within `margin`
+    pub async fn refresh_session_if_expiring(&self, margin: Duration) -> Result<bool> {
+        self.client.refresh_if_expiring(margin).await
+    }
+
     /// Get remaining capacity
     pub fn available_capacity(&self) -> usize {
         self.semaphore.available_permits()
     }
+
+    /// Number of jobs currently being processed
+    ///
+    /// Unlike the `processing` job count, this leaves out jobs waiting for
+    /// their retry to come due.
+    pub fn jobs_in_flight(&self) -> usize {
+        self.in_flight.lock().unwrap().jobs.len()
+    }
+}
+
+/// Name for the remote side of a conflict, e.g. `report (conflicted copy 2026-10-16 1530).pdf`
+fn conflicted_name(name: &str, at: chrono::DateTime<Utc>) -> String {
+    let suffix = format!(" (conflicted copy {})", at.format("%Y-%m-%d %H%M"));
+
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => format!("{}{}{}", &name[..dot], suffix, &name[dot..]),
+        _ => format!("{}{}", name, suffix),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{RemoteDeleteBehavior, Session};
+    use tempfile::TempDir;
+    use wiremock::matchers::{body_partial_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn folder_json(uid: &str, parent: &str, name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "UID": uid,
+            "ParentLinkID": parent,
+            "Name": name,
+            "NodeType": "folder",
+            "State": 1,
+        })
+    }
+
+    async fn mock_list(server: &MockServer, parent: &str, nodes: Vec<serde_json::Value>) {
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/nodes"))
+            .and(query_param("ParentLinkID", parent))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"Code": 1000, "Nodes": nodes})),
+            )
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    async fn mock_create(server: &MockServer, parent: &str, name: &str, uid: &str) {
+        Mock::given(method("POST"))
+            .and(path("/drive/v2/nodes"))
+            .and(body_partial_json(
+                serde_json::json!({"ParentLinkID": parent, "NodeName": name}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({"Code": 1000, "Node": folder_json(uid, parent, name)}),
+            ))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    async fn processor(server: &MockServer, temp_dir: &TempDir) -> JobProcessor {
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let session = Session {
+            uid: "test_uid".to_string(),
+            access_token: "test_token".to_string(),
+            refresh_token: "test_refresh".to_string(),
+            key_password: None,
+            primary_key: None,
+            expires_at: None,
+        };
+        let client = ProtonClient::with_api_base(server.uri(), session);
+        JobProcessor::new(
+            db,
+            client,
+            1,
+            RemoteDeleteBehavior::Trash,
+            false,
+            None,
+            RetryPolicy::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_parent_resolution_creates_deep_tree() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let processor = processor(&server, &temp_dir).await;
+
+        // "A" exists remotely, everything below it is created once
+        mock_list(&server, "root", vec![folder_json("a", "root", "A")]).await;
+        mock_list(&server, "a", vec![]).await;
+        mock_create(&server, "a", "B", "b").await;
+        mock_create(&server, "b", "C", "c").await;
+        mock_create(&server, "c", "D", "d").await;
+
+        assert_eq!(
+            processor.get_or_create_parent_node("/A/B/C").await.unwrap(),
+            "c"
+        );
+
+        // Resolved again from the cache; only the missing leaf is created
+        assert_eq!(
+            processor.get_or_create_parent_node("/A/B/C").await.unwrap(),
+            "c"
+        );
+        assert_eq!(
+            processor
+                .get_or_create_parent_node("/A/B/C/D")
+                .await
+                .unwrap(),
+            "d"
+        );
+        assert_eq!(
+            processor.get_or_create_parent_node("/").await.unwrap(),
+            "root"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parent_resolution_prefers_node_mappings() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let processor = processor(&server, &temp_dir).await;
+
+        for (remote_path, uid) in [("/Docs", "docs"), ("/Docs/Work", "work")] {
+            processor
+                .db
+                .update_node_mapping(&crate::types::NodeMapping {
+                    local_path: format!("/home/user{}", remote_path),
+                    remote_path: remote_path.to_string(),
+                    node_uid: uid.to_string(),
+                    parent_node_uid: "root".to_string(),
+                    is_directory: true,
+                    updated_at: Utc::now(),
+                })
+                .await
+                .unwrap();
+        }
+
+        mock_list(&server, "work", vec![folder_json("y2026", "work", "2026")]).await;
+
+        assert_eq!(
+            processor
+                .get_or_create_parent_node("/Docs/Work/2026")
+                .await
+                .unwrap(),
+            "y2026"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_folder_creations_are_adopted() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let processor = processor(&server, &temp_dir).await;
+
+        // A previous run created "A" but died before indexing it
+        processor
+            .db
+            .begin_folder_creation("root", "A", "/A")
+            .await
+            .unwrap();
+        mock_list(&server, "root", vec![folder_json("a", "root", "A")]).await;
+
+        assert_eq!(processor.recover_folder_creations().await.unwrap(), 1);
+        assert!(processor
+            .db
+            .get_pending_folder_creations()
+            .await
+            .unwrap()
+            .is_empty());
+
+        // An unsettled "A/B" is looked up afresh despite a cached empty listing
+        mock_list(&server, "a", vec![folder_json("b", "a", "B")]).await;
+        processor
+            .db
+            .begin_folder_creation("a", "B", "/A/B")
+            .await
+            .unwrap();
+        processor.db.set_remote_children("a", &[]).await.unwrap();
+        assert_eq!(
+            processor.get_or_create_parent_node("/A/B").await.unwrap(),
+            "b"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_budget_admission() {
+        let budget = MemoryBudget::new(1);
+        let wait = std::time::Duration::from_millis(50);
+
+        // Several small uploads fit together
+        let small: Vec<_> = futures::future::join_all((0..4).map(|_| budget.reserve(200 * 1024)))
+            .await
+            .into_iter()
+            .map(|p| p.unwrap())
+            .collect();
+
+        // A large one waits until they are done
+        assert!(tokio::time::timeout(wait, budget.reserve(512 * 1024))
+            .await
+            .is_err());
+        drop(small);
+
+        // A file larger than the budget takes all of it and runs alone
+        let huge = budget.reserve(10 * 1024 * 1024).await.unwrap();
+        assert!(tokio::time::timeout(wait, budget.reserve(1)).await.is_err());
+        // Nor does it wait on itself for more
+        assert!(budget
+            .reserve_more(10 * 1024 * 1024, 10 * 1024 * 1024)
+            .await
+            .unwrap()
+            .is_none());
+        drop(huge);
+        assert!(budget.reserve(512 * 1024).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_claims_serialize_jobs_per_path() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let processor = processor(&server, &temp_dir).await;
+
+        let job = |id: i64, local_path: &str, old_local_path: Option<&str>| SyncJob {
+            id,
+            event_type: SyncEventType::Update,
+            local_path: local_path.to_string(),
+            remote_path: local_path.to_string(),
+            status: SyncJobStatus::Pending,
+            retry_at: None,
+            n_retries: 0,
+            last_error: None,
+            change_token: None,
+            old_local_path: old_local_path.map(str::to_string),
+            old_remote_path: old_local_path.map(str::to_string),
+            created_at: Utc::now(),
+            updated_at: None,
+            completed_at: None,
+            source: None,
+        };
+
+        let first = processor.try_claim(&job(1, "/a.txt", None)).unwrap();
+        assert!(processor.try_claim(&job(1, "/a.txt", None)).is_none());
+        assert!(processor.try_claim(&job(2, "/a.txt", None)).is_none());
+        assert!(processor
+            .try_claim(&job(3, "/b.txt", Some("/a.txt")))
+            .is_none());
+
+        // Other paths run alongside
+        let other = processor.try_claim(&job(4, "/c.txt", None)).unwrap();
+
+        drop(first);
+        assert!(processor.try_claim(&job(2, "/a.txt", None)).is_some());
+        drop(other);
+    }
+
+    #[tokio::test]
+    async fn test_update_uploads_new_revision() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let processor = processor(&server, &temp_dir).await;
+
+        let local_path = temp_dir.path().join("notes.txt");
+        std::fs::write(&local_path, "hello").unwrap();
+        let local_path = local_path.to_string_lossy().to_string();
+
+        processor
+            .db
+            .update_node_mapping(&crate::types::NodeMapping {
+                local_path: local_path.clone(),
+                remote_path: "/notes.txt".to_string(),
+                node_uid: "file1".to_string(),
+                parent_node_uid: "root".to_string(),
+                is_directory: false,
+                updated_at: Utc::now(),
+            })
+            .await
+            .unwrap();
+
+        Mock::given(method("POST"))
+            .and(path("/drive/v2/files/file1/revisions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({"Code": 1000, "Revision": {"ID": "rev2", "Size": 5}}),
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("DELETE"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let job = SyncJob {
+            id: 1,
+            event_type: SyncEventType::Update,
+            local_path: local_path.clone(),
+            remote_path: "/notes.txt".to_string(),
+            status: SyncJobStatus::Processing,
+            retry_at: None,
+            n_retries: 0,
+            last_error: None,
+            change_token: None,
+            old_local_path: None,
+            old_remote_path: None,
+            created_at: Utc::now(),
+            updated_at: None,
+            completed_at: None,
+            source: None,
+        };
+        processor.process_update(&job).await.unwrap();
+
+        let mapping = processor
+            .db
+            .get_node_mapping(&local_path, "/notes.txt")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(mapping.node_uid, "file1");
+    }
+
+    #[tokio::test]
+    async fn test_file_changed_during_upload_is_requeued() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let processor = processor(&server, &temp_dir).await;
+
+        let local_path = temp_dir.path().join("notes.txt");
+        std::fs::write(&local_path, "hello").unwrap();
+        let queued_token = change_token(&local_path, crate::types::ChangeDetection::Full)
+            .await
+            .unwrap();
+        let local_path = local_path.to_string_lossy().to_string();
+
+        processor
+            .db
+            .update_node_mapping(&crate::types::NodeMapping {
+                local_path: local_path.clone(),
+                remote_path: "/notes.txt".to_string(),
+                node_uid: "file1".to_string(),
+                parent_node_uid: "root".to_string(),
+                is_directory: false,
+                updated_at: Utc::now(),
+            })
+            .await
+            .unwrap();
+        Mock::given(method("POST"))
+            .and(path("/drive/v2/files/file1/revisions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({"Code": 1000, "Revision": {"ID": "rev2", "Size": 5}}),
+            ))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        processor
+            .db
+            .enqueue_job(
+                &SyncEvent {
+                    event_type: SyncEventType::Update,
+                    local_path: local_path.clone(),
+                    remote_path: "/notes.txt".to_string(),
+                    change_token: Some(queued_token),
+                    old_local_path: None,
+                    old_remote_path: None,
+                },
+                crate::types::JobPriority::Small,
+            )
+            .await
+            .unwrap();
+
+        // The file is saved again after the job was queued
+        std::fs::write(&local_path, "hello again").unwrap();
+        let current_token =
+            change_token(Path::new(&local_path), crate::types::ChangeDetection::Full)
+                .await
+                .unwrap();
+
+        let job = processor.db.get_pending_jobs(10).await.unwrap().remove(0);
+        processor.process_job(&job).await.unwrap();
+
+        let follow_up = processor.db.get_pending_jobs(10).await.unwrap();
+        assert_eq!(follow_up.len(), 1);
+        assert_eq!(follow_up[0].event_type, SyncEventType::Update);
+        assert_eq!(follow_up[0].change_token.as_ref(), Some(&current_token));
+
+        // Uploading the current content settles it
+        processor.process_job(&follow_up[0]).await.unwrap();
+        assert!(processor.db.get_pending_jobs(10).await.unwrap().is_empty());
+    }
+
+    fn file_json(uid: &str, name: &str, size: i64) -> serde_json::Value {
+        serde_json::json!({
+            "UID": uid,
+            "ParentLinkID": "root",
+            "Name": name,
+            "NodeType": "file",
+            "State": 1,
+            "ActiveRevision": {"ID": format!("{}-rev", uid), "Size": size},
+        })
+    }
+
+    fn create_job(local_path: &str, remote_path: &str) -> SyncJob {
+        SyncJob {
+            id: 1,
+            event_type: SyncEventType::CreateFile,
+            local_path: local_path.to_string(),
+            remote_path: remote_path.to_string(),
+            status: SyncJobStatus::Processing,
+            retry_at: None,
+            n_retries: 0,
+            last_error: None,
+            change_token: None,
+            old_local_path: None,
+            old_remote_path: None,
+            created_at: Utc::now(),
+            updated_at: None,
+            completed_at: None,
+            source: None,
+        }
+    }
+
+    async fn mock_name_exists(server: &MockServer) {
+        Mock::given(method("POST"))
+            .and(path("/drive/v2/files"))
+            .respond_with(
+                ResponseTemplate::new(422).set_body_json(serde_json::json!({"Code": 2500})),
+            )
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_name_conflict_adopts_identical_file() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let processor = processor(&server, &temp_dir).await;
+
+        let local_path = temp_dir.path().join("notes.txt");
+        std::fs::write(&local_path, "hello").unwrap();
+        let local_path = local_path.to_string_lossy().to_string();
+
+        mock_name_exists(&server).await;
+        mock_list(&server, "root", vec![file_json("file1", "notes.txt", 5)]).await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/files/file1/revisions/file1-rev/content"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes("hello"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        processor
+            .process_create_file(&create_job(&local_path, "/notes.txt"))
+            .await
+            .unwrap();
+
+        let mapping = processor
+            .db
+            .get_node_mapping(&local_path, "/notes.txt")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(mapping.node_uid, "file1");
+    }
+
+    #[tokio::test]
+    async fn test_colliding_remote_names_are_refused() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let processor = processor(&server, &temp_dir).await;
+
+        // Both sanitize to "/a_b.txt"
+        let uploaded = temp_dir.path().join("a_b.txt");
+        let colliding = temp_dir.path().join("a\tb.txt");
+        std::fs::write(&uploaded, "first").unwrap();
+        std::fs::write(&colliding, "second").unwrap();
+        processor
+            .db
+            .update_node_mapping(&crate::types::NodeMapping {
+                local_path: uploaded.to_string_lossy().into_owned(),
+                remote_path: "/a_b.txt".to_string(),
+                node_uid: "file1".to_string(),
+                parent_node_uid: "root".to_string(),
+                is_directory: false,
+                updated_at: Utc::now(),
+            })
+            .await
+            .unwrap();
+
+        let remote_path = PathUtils::from_local("/", Path::new("a\tb.txt"));
+        assert_eq!(remote_path, "/a_b.txt");
+        let err = processor
+            .process_create_file(&create_job(&colliding.to_string_lossy(), &remote_path))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidPath(_)));
+        assert!(err.is_permanent());
+        assert!(err.to_string().contains("a_b.txt"));
+
+        // Once the other file is gone, the name is free to take over
+        std::fs::remove_file(&uploaded).unwrap();
+        assert!(processor
+            .check_name_collision(&create_job(&colliding.to_string_lossy(), &remote_path))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_name_conflict_keeps_both_files() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let processor = processor(&server, &temp_dir).await;
+
+        let local_path = temp_dir.path().join("notes.txt");
+        std::fs::write(&local_path, "hello").unwrap();
+        let local_path = local_path.to_string_lossy().to_string();
+
+        // Different size, so the remote file is not downloaded
+        mock_name_exists(&server).await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/nodes"))
+            .and(query_param("ParentLinkID", "root"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({"Code": 1000, "Nodes": [file_json("file1", "notes.txt", 3)]}),
+            ))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/drive/v2/nodes/file1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "Node": file_json("file1", "notes (conflicted copy).txt", 3),
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/drive/v2/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "Node": file_json("file2", "notes.txt", 5),
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        processor
+            .process_create_file(&create_job(&local_path, "/notes.txt"))
+            .await
+            .unwrap();
+
+        let mapping = processor
+            .db
+            .get_node_mapping(&local_path, "/notes.txt")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(mapping.node_uid, "file2");
+    }
+
+    #[tokio::test]
+    async fn test_name_conflict_with_a_folder_is_refused() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let processor = processor(&server, &temp_dir).await;
+
+        let local_path = temp_dir.path().join("notes");
+        std::fs::write(&local_path, "hello").unwrap();
+        let local_path = local_path.to_string_lossy().to_string();
+
+        let mut folder = file_json("folder1", "notes", 0);
+        folder["NodeType"] = FOLDER_NODE_TYPE.into();
+        mock_name_exists(&server).await;
+        mock_list(&server, "root", vec![folder]).await;
+        Mock::given(method("PUT"))
+            .and(path("/drive/v2/nodes/folder1"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let err = processor
+            .process_create_file(&create_job(&local_path, "/notes"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidPath(_)));
+    }
+
+    #[tokio::test]
+    async fn test_asked_conflict_waits_for_resolution() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let processor = processor(&server, &temp_dir)
+            .await
+            .with_conflict_policy(ConflictPolicy::Ask);
+
+        let local_path = temp_dir.path().join("notes.txt");
+        std::fs::write(&local_path, "hello").unwrap();
+        let local_path = local_path.to_string_lossy().to_string();
+
+        mock_name_exists(&server).await;
+        mock_list(&server, "root", vec![file_json("file1", "notes.txt", 3)]).await;
+
+        let err = processor
+            .process_create_file(&create_job(&local_path, "/notes.txt"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Conflict(_)));
+
+        let conflicts = processor.db.get_conflicts().await.unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].node_uid, "file1");
+
+        // Keeping the local file uploads it as a new revision
+        Mock::given(method("POST"))
+            .and(path("/drive/v2/files/file1/revisions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({"Code": 1000, "Revision": {"ID": "rev2", "Size": 5}}),
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        processor
+            .resolve_conflict(
+                &conflicts[0],
+                ConflictResolution::KeepLocal,
+                temp_dir.path(),
+            )
+            .await
+            .unwrap();
+
+        assert!(processor.db.get_conflicts().await.unwrap().is_empty());
+        let mapping = processor
+            .db
+            .get_node_mapping(&local_path, "/notes.txt")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(mapping.node_uid, "file1");
+    }
+
+    #[tokio::test]
+    async fn test_uploads_beyond_quota_are_refused() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let processor = processor(&server, &temp_dir).await;
+
+        Mock::given(method("GET"))
+            .and(path("/core/v4/users"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "User": {
+                    "Email": "user@proton.me",
+                    "UsedSpace": 450,
+                    "MaxSpace": 500,
+                    "Subscribed": 0
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/drive/v2/files"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let local_path = temp_dir.path().join("video.mp4");
+        std::fs::write(&local_path, [0u8; 100]).unwrap();
+        let job = create_job(&local_path.to_string_lossy(), "/video.mp4");
+
+        let err = processor.process_job(&job).await.unwrap_err();
+        assert!(matches!(err, Error::QuotaExceeded(_)));
+
+        // Usage is cached, and reserved uploads count against it
+        let quota = &processor.quota;
+        let held = quota.reserve(&processor.client, 40).await.unwrap();
+        assert!(quota.reserve(&processor.client, 40).await.is_err());
+
+        // A failed upload gives its room back, a finished one keeps it
+        drop(held);
+        let uploaded = quota.reserve(&processor.client, 40).await.unwrap();
+        uploaded.commit();
+        assert!(quota.reserve(&processor.client, 40).await.is_err());
+        assert!(quota.reserve(&processor.client, 10).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_upload_is_blocked_without_retrying() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let mut processor = processor(&server, &temp_dir).await;
+
+        Mock::given(method("POST"))
+            .and(path("/drive/v2/files"))
+            .respond_with(
+                ResponseTemplate::new(400)
+                    .set_body_json(serde_json::json!({"Code": 2001, "Error": "Invalid file name"})),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let local_path = temp_dir.path().join("notes.txt");
+        std::fs::write(&local_path, "hello").unwrap();
+        let local_path = local_path.to_string_lossy().to_string();
+        processor
+            .db
+            .enqueue_job(
+                &SyncEvent {
+                    event_type: SyncEventType::CreateFile,
+                    local_path: local_path.clone(),
+                    remote_path: "/notes.txt".to_string(),
+                    change_token: None,
+                    old_local_path: None,
+                    old_remote_path: None,
+                },
+                crate::types::JobPriority::Small,
+            )
+            .await
+            .unwrap();
+        let job = processor.db.get_pending_jobs(10).await.unwrap().remove(0);
+
+        assert!(processor.process_job(&job).await.is_err());
+
+        let job = processor.db.get_job(job.id).await.unwrap().unwrap();
+        assert_eq!(job.status, SyncJobStatus::Blocked);
+        assert_eq!(job.n_retries, 0);
+        assert!(job.last_error.unwrap().contains("jobs retry"));
+
+        // A retry rule for the class is followed instead
+        processor
+            .retry_policy
+            .overrides
+            .insert("proton_api".to_string(), Default::default());
+        processor.db.retry_blocked_job(job.id).await.unwrap();
+        let job = processor.db.get_job(job.id).await.unwrap().unwrap();
+        assert!(processor.process_job(&job).await.is_err());
+
+        let job = processor.db.get_job(job.id).await.unwrap().unwrap();
+        assert_ne!(job.status, SyncJobStatus::Blocked);
+        assert_eq!(job.n_retries, 1);
+    }
+
+    #[test]
+    fn test_conflicted_name() {
+        let at = chrono::DateTime::parse_from_rfc3339("2026-10-16T15:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            conflicted_name("report.pdf", at),
+            "report (conflicted copy 2026-10-16 1530).pdf"
+        );
+        assert_eq!(
+            conflicted_name("Makefile", at),
+            "Makefile (conflicted copy 2026-10-16 1530)"
+        );
+        assert_eq!(
+            conflicted_name(".env", at),
+            ".env (conflicted copy 2026-10-16 1530)"
+        );
+    }
 }
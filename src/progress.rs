@@ -0,0 +1,141 @@
+//! Upload progress tracking
+//!
+//! Upload bodies are streamed in chunks through a byte counter. While an
+//! upload runs, the processor records the counter in the `job_progress`
+//! table so `status`, the dashboard and foreground mode can show it.
+
+use crate::types::JobProgress;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Size of the chunks an upload body is streamed in
+pub const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
+/// How often in-flight upload progress is written to the database
+pub const PROGRESS_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Bytes sent so far by one upload
+#[derive(Debug, Clone, Default)]
+pub struct ProgressCounter(Arc<AtomicU64>);
+
+impl ProgressCounter {
+    /// Bytes handed to the connection so far
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn add(&self, bytes: u64) {
+        self.0.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Start counting again, for an upload that is being resent
+    pub fn reset(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Wrap upload content in a body that counts bytes as they are sent
+pub fn counted_body<C>(content: C, counter: ProgressCounter) -> reqwest::Body
+where
+    C: AsRef<[u8]> + Send + Sync + 'static,
+{
+    let len = content.as_ref().len();
+    let chunks = (0..len).step_by(UPLOAD_CHUNK_SIZE).map(move |start| {
+        let chunk = content.as_ref()[start..(start + UPLOAD_CHUNK_SIZE).min(len)].to_vec();
+        counter.add(chunk.len() as u64);
+        Ok::<_, std::io::Error>(chunk)
+    });
+
+    reqwest::Body::wrap_stream(futures::stream::iter(chunks))
+}
+
+/// Render a text progress bar for an upload
+pub fn render_bar(progress: &JobProgress, width: usize) -> String {
+    let fraction = if progress.total_bytes > 0 {
+        (progress.bytes_sent as f64 / progress.total_bytes as f64).min(1.0)
+    } else {
+        1.0
+    };
+    let filled = (fraction * width as f64).round() as usize;
+
+    format!(
+        "[{}{}] {:>3}% {} / {}",
+        "#".repeat(filled),
+        ".".repeat(width - filled),
+        (fraction * 100.0).floor() as u64,
+        format_bytes(progress.bytes_sent),
+        format_bytes(progress.total_bytes)
+    )
+}
+
+/// Render a byte count with a binary unit
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_render_bar() {
+        let progress = JobProgress {
+            job_id: 1,
+            local_path: "/tmp/big.iso".to_string(),
+            bytes_sent: 1024 * 1024,
+            total_bytes: 4 * 1024 * 1024,
+        };
+
+        assert_eq!(
+            render_bar(&progress, 8),
+            "[##......]  25% 1.0 MiB / 4.0 MiB"
+        );
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(4 * 1024 * 1024 * 1024), "4.0 GiB");
+    }
+
+    #[tokio::test]
+    async fn test_counted_body_counts_every_byte() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let counter = ProgressCounter::default();
+        let len = UPLOAD_CHUNK_SIZE * 2 + 10;
+
+        reqwest::Client::new()
+            .post(server.uri())
+            .body(counted_body(vec![7u8; len], counter.clone()))
+            .send()
+            .await
+            .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests[0].body.len(), len);
+        assert_eq!(counter.get(), len as u64);
+    }
+}
@@ -1,11 +1,21 @@
 //! Proton Drive API client
 
 use crate::auth::AuthManager;
+use crate::credentials::SessionStore;
 use crate::error::{Error, Result};
-use crate::types::{CreateResult, NodeData, Session};
-use reqwest::Client;
+use crate::progress::{counted_body, ProgressCounter};
+use crate::rate_limit::{backoff_delay, retry_after, RateLimiter, MAX_RATE_LIMIT_RETRIES};
+use crate::types::{
+    AccountInfo, CreateResult, NodeData, NodeEvent, NodeEventType, NodeEvents, RemoteNaming,
+    Revision, Session, ShareUrl, TrashedNode,
+};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Proton Drive API base URL
 const DRIVE_API_BASE: &str = "https://drive-api.proton.me";
@@ -16,6 +26,44 @@ const NODES_ENDPOINT: &str = "/drive/v2/nodes";
 /// Drive files endpoint
 const FILES_ENDPOINT: &str = "/drive/v2/files";
 
+/// Drive events endpoint
+const EVENTS_ENDPOINT: &str = "/drive/v2/events";
+
+/// Drive trash endpoint
+const TRASH_ENDPOINT: &str = "/drive/v2/trash";
+
+/// API code for a name already taken in the parent folder
+const NAME_EXISTS_CODE: i32 = 2500;
+
+/// Longest file or folder name Proton Drive accepts, in bytes
+pub const MAX_NAME_BYTES: usize = 255;
+
+/// How long before the access token expires the session is refreshed
+pub const SESSION_REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// How often the access token's expiry is checked
+pub const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Time of the last successful API response, shared between clients
+#[derive(Debug, Clone, Default)]
+pub struct ApiContact(Arc<AtomicI64>);
+
+impl ApiContact {
+    /// Record a successful response now
+    fn record(&self) {
+        self.0
+            .store(chrono::Utc::now().timestamp_millis(), Ordering::Relaxed);
+    }
+
+    /// When the API last answered successfully, if ever
+    pub fn last(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        match self.0.load(Ordering::Relaxed) {
+            0 => None,
+            ms => chrono::DateTime::from_timestamp_millis(ms),
+        }
+    }
+}
+
 /// Create node request
 #[derive(Debug, Serialize)]
 struct CreateNodeRequest {
@@ -68,6 +116,24 @@ struct NodeApiResponse {
     active_revision: Option<RevisionApiResponse>,
 }
 
+impl NodeApiResponse {
+    /// Convert to the public node representation
+    fn into_node_data(self) -> NodeData {
+        NodeData {
+            uid: self.uid,
+            parent_uid: Some(self.parent_link_id),
+            name: self.name,
+            node_type: self.node_type,
+            media_type: self.mime_type,
+            active_revision: self.active_revision.map(|r| crate::types::RevisionData {
+                uid: r.id,
+                size: r.size,
+                manifest_signature: r.manifest_signature,
+            }),
+        }
+    }
+}
+
 /// Revision API response
 #[derive(Debug, Deserialize)]
 struct RevisionApiResponse {
@@ -79,6 +145,55 @@ struct RevisionApiResponse {
     manifest_signature: Option<String>,
 }
 
+/// Create revision response
+#[derive(Debug, Deserialize)]
+struct CreateRevisionResponse {
+    #[serde(rename = "Code")]
+    code: i32,
+    #[serde(rename = "Revision")]
+    revision: Option<RevisionApiResponse>,
+}
+
+/// List revisions response
+#[derive(Debug, Deserialize)]
+struct ListRevisionsResponse {
+    #[serde(rename = "Code")]
+    code: i32,
+    #[serde(rename = "Revisions")]
+    #[serde(default)]
+    revisions: Vec<RevisionListItem>,
+}
+
+/// Revision entry in a revision listing
+#[derive(Debug, Deserialize)]
+struct RevisionListItem {
+    #[serde(rename = "ID")]
+    id: String,
+    #[serde(rename = "Size")]
+    size: Option<i64>,
+    /// Unix timestamp
+    #[serde(rename = "CreateTime")]
+    create_time: Option<i64>,
+    /// 1 for the active revision
+    #[serde(rename = "State")]
+    #[serde(default)]
+    state: i32,
+}
+
+impl RevisionListItem {
+    /// Convert to the public revision representation
+    fn into_revision(self) -> Revision {
+        Revision {
+            uid: self.id,
+            size: self.size,
+            created_at: self
+                .create_time
+                .and_then(|t| chrono::DateTime::from_timestamp(t, 0)),
+            active: self.state == 1,
+        }
+    }
+}
+
 /// Delete node response
 #[derive(Debug, Deserialize)]
 struct DeleteNodeResponse {
@@ -105,6 +220,94 @@ struct RenameNodeResponse {
     node: Option<NodeApiResponse>,
 }
 
+/// Move node request
+#[derive(Debug, Serialize)]
+struct MoveNodeRequest {
+    #[serde(rename = "ParentLinkID")]
+    parent_link_id: String,
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "Signature")]
+    signature: Option<String>,
+}
+
+/// Move node response
+#[derive(Debug, Deserialize)]
+struct MoveNodeResponse {
+    #[serde(rename = "Code")]
+    code: i32,
+    #[serde(rename = "Node")]
+    node: Option<NodeApiResponse>,
+}
+
+/// Get node response
+#[derive(Debug, Deserialize)]
+struct GetNodeResponse {
+    #[serde(rename = "Code")]
+    code: i32,
+    #[serde(rename = "Node")]
+    node: Option<NodeApiResponse>,
+}
+
+/// Latest event response
+#[derive(Debug, Deserialize)]
+struct LatestEventResponse {
+    #[serde(rename = "Code")]
+    code: i32,
+    #[serde(rename = "EventID")]
+    event_id: String,
+}
+
+/// Events response
+#[derive(Debug, Deserialize)]
+struct EventsResponse {
+    #[serde(rename = "Code")]
+    code: i32,
+    #[serde(rename = "EventID")]
+    event_id: String,
+    #[serde(rename = "More")]
+    #[serde(default)]
+    more: i32,
+    #[serde(rename = "Refresh")]
+    #[serde(default)]
+    refresh: i32,
+    #[serde(rename = "Events")]
+    #[serde(default)]
+    events: Vec<EventApiResponse>,
+}
+
+/// Single event in an events response
+#[derive(Debug, Deserialize)]
+struct EventApiResponse {
+    #[serde(rename = "EventType")]
+    event_type: i32,
+    #[serde(rename = "NodeUID")]
+    node_uid: String,
+    #[serde(rename = "Node")]
+    node: Option<NodeApiResponse>,
+}
+
+impl EventApiResponse {
+    /// Convert to the public event representation
+    ///
+    /// Returns `None` for event types this client does not understand.
+    fn into_node_event(self) -> Option<NodeEvent> {
+        let event_type = match self.event_type {
+            0 => NodeEventType::Delete,
+            1 => NodeEventType::Create,
+            2 | 3 => NodeEventType::Update,
+            _ => return None,
+        };
+
+        Some(NodeEvent {
+            event_type,
+            node_uid: self.node_uid,
+            node: self.node.map(NodeApiResponse::into_node_data),
+        })
+    }
+}
+
 /// List nodes response
 #[derive(Debug, Deserialize)]
 struct ListNodesResponse {
@@ -114,38 +317,248 @@ struct ListNodesResponse {
     nodes: Vec<NodeApiResponse>,
 }
 
+/// List trash response
+#[derive(Debug, Deserialize)]
+struct ListTrashResponse {
+    #[serde(rename = "Code")]
+    code: i32,
+    #[serde(rename = "Nodes")]
+    #[serde(default)]
+    nodes: Vec<TrashedNodeApiResponse>,
+}
+
+/// Trashed node in a trash listing
+#[derive(Debug, Deserialize)]
+struct TrashedNodeApiResponse {
+    #[serde(flatten)]
+    node: NodeApiResponse,
+    /// Unix timestamp
+    #[serde(rename = "Trashed")]
+    trashed: Option<i64>,
+}
+
+/// Restore from trash request
+#[derive(Debug, Serialize)]
+struct RestoreTrashRequest<'a> {
+    #[serde(rename = "LinkIDs")]
+    link_ids: &'a [String],
+}
+
+/// Create share URL request
+#[derive(Debug, Serialize)]
+struct CreateShareUrlRequest<'a> {
+    #[serde(rename = "Password")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password: Option<&'a str>,
+    /// Unix timestamp
+    #[serde(rename = "ExpirationTime")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expiration_time: Option<i64>,
+}
+
+/// Create share URL response
+#[derive(Debug, Deserialize)]
+struct CreateShareUrlResponse {
+    #[serde(rename = "Code")]
+    code: i32,
+    #[serde(rename = "ShareURL")]
+    share_url: ShareUrlApiData,
+}
+
+/// Share URL API data
+#[derive(Debug, Deserialize)]
+struct ShareUrlApiData {
+    #[serde(rename = "URL")]
+    url: String,
+    /// Unix timestamp
+    #[serde(rename = "ExpirationTime")]
+    expiration_time: Option<i64>,
+}
+
 /// Proton Drive client
+///
+/// An expired access token is refreshed transparently: a request answered
+/// with 401 triggers one session refresh and is then sent again. Requests
+/// answered with 429 are retried after the server's `Retry-After`.
 pub struct ProtonClient {
     client: Client,
     api_base: String,
-    session: Session,
+    rate_limiter: RateLimiter,
+    /// Session in use, possibly shared with other clients
+    sessions: SessionStore,
     auth_manager: AuthManager,
+    contact: ApiContact,
 }
 
 impl ProtonClient {
     /// Create a new Proton Drive client
+    ///
+    /// Refreshed tokens are kept in memory only; use
+    /// [`ProtonClient::with_sessions`] with a store from
+    /// [`crate::credentials::load_session`] to have them saved.
     pub fn new(session: Session) -> Self {
+        Self::with_sessions(SessionStore::in_memory(session))
+    }
+
+    /// Create a client using a session shared with other clients
+    ///
+    /// A refresh by any of them is seen by all, and saved if the store
+    /// persists sessions.
+    pub fn with_sessions(sessions: SessionStore) -> Self {
         Self {
             client: Client::new(),
             api_base: DRIVE_API_BASE.to_string(),
-            session,
+            rate_limiter: RateLimiter::default(),
+            sessions,
             auth_manager: AuthManager::new(),
+            contact: ApiContact::default(),
         }
     }
 
     /// Create with custom API base
+    ///
+    /// Sessions are refreshed against the same base and kept in memory only.
     pub fn with_api_base(api_base: String, session: Session) -> Self {
         Self {
             client: Client::new(),
+            auth_manager: AuthManager::with_api_base(api_base.clone()),
             api_base,
-            session,
-            auth_manager: AuthManager::new(),
+            rate_limiter: RateLimiter::default(),
+            sessions: SessionStore::in_memory(session),
+            contact: ApiContact::default(),
         }
     }
 
+    /// Whether the API answers at all, for connectivity checks
+    pub async fn is_reachable(&self) -> bool {
+        crate::connectivity::probe(&self.client, &self.api_base).await
+    }
+
+    /// Record successful responses in a shared `ApiContact`
+    pub fn with_contact(mut self, contact: ApiContact) -> Self {
+        self.contact = contact;
+        self
+    }
+
     /// Get access token
-    fn get_token(&self) -> &str {
-        &self.session.access_token
+    fn get_token(&self) -> String {
+        self.sessions.access_token()
+    }
+
+    /// URL of an API endpoint with path segments appended
+    ///
+    /// Every URL with IDs in its path is built here, so a `/`, `%`, `#`, `?`
+    /// or newline in an ID stays inside its own segment.
+    fn endpoint(&self, endpoint: &str, segments: &[&str]) -> String {
+        let mut url = format!("{}{}", self.api_base, endpoint);
+        for segment in segments {
+            url.push('/');
+            url.push_str(&encode_path_segment(segment));
+        }
+        url
+    }
+
+    /// Send an authenticated request, refreshing the session once on 401
+    ///
+    /// `build` is called again for the replay, so request bodies must be
+    /// rebuilt rather than moved in.
+    async fn send<F>(&self, build: F) -> Result<Response>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let token = self.get_token();
+        let response = self
+            .send_rate_limited(|| Self::authorized(build(), &token))
+            .await?;
+
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        self.refresh_expired(&token).await?;
+
+        let token = self.get_token();
+        self.send_rate_limited(|| Self::authorized(build(), &token))
+            .await
+    }
+
+    /// Send a request within the client's rate limit, retrying on 429
+    ///
+    /// Waits for `Retry-After` when the server sends it and backs off
+    /// exponentially otherwise. The 429 response is returned once the
+    /// retries run out.
+    async fn send_rate_limited<F>(&self, build: F) -> Result<Response>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let mut attempt = 0;
+
+        loop {
+            let response = self.rate_limiter.run(|| build().send()).await??;
+            if response.status().is_success() {
+                self.contact.record();
+            }
+
+            if response.status() != StatusCode::TOO_MANY_REQUESTS
+                || attempt >= MAX_RATE_LIMIT_RETRIES
+            {
+                return Ok(response);
+            }
+
+            let delay = retry_after(response.headers()).unwrap_or_else(|| backoff_delay(attempt));
+            tracing::warn!(
+                "Rate limited by the Proton API, retrying in {:.1}s",
+                delay.as_secs_f64()
+            );
+
+            self.rate_limiter.pause_for(delay);
+            attempt += 1;
+        }
+    }
+
+    /// Attach the bearer token to a request
+    fn authorized(request: RequestBuilder, token: &str) -> RequestBuilder {
+        request.header("Authorization", format!("Bearer {}", token))
+    }
+
+    /// Refresh the session after `expired_token` was rejected
+    ///
+    /// Skipped if another request already replaced the token.
+    async fn refresh_expired(&self, expired_token: &str) -> Result<()> {
+        let _guard = self.sessions.lock_refresh().await;
+
+        if self.get_token() != expired_token {
+            return Ok(());
+        }
+        // Refreshed by another process, which spent our refresh token
+        if self.sessions.adopt_saved().await {
+            return Ok(());
+        }
+
+        tracing::info!("Access token expired, refreshing session");
+
+        self.refresh_locked().await.map_err(|e| match e {
+            Error::Auth(_) => Error::Auth(format!(
+                "Session expired and could not be refreshed ({}); re-login required: run `proton-drive-sync auth login`",
+                e
+            )),
+            e => e,
+        })
+    }
+
+    /// Get the logged-in account's email, plan and storage usage
+    ///
+    /// Refreshes the session once if the account endpoint rejects it.
+    pub async fn get_account_info(&self) -> Result<AccountInfo> {
+        let token = self.get_token();
+
+        match self.auth_manager.get_user(&self.session()).await {
+            Err(Error::Auth(_)) => {
+                self.refresh_expired(&token).await?;
+                self.auth_manager.get_user(&self.session()).await
+            }
+            result => result,
+        }
     }
 
     /// Create a file node
@@ -155,28 +568,30 @@ impl ProtonClient {
         name: &str,
         content: Vec<u8>,
         mime_type: Option<&str>,
+        progress: Option<&ProgressCounter>,
     ) -> Result<CreateResult> {
-        let url = format!("{}{}", self.api_base, FILES_ENDPOINT);
+        let url = self.endpoint(FILES_ENDPOINT, &[]);
+        let content: Arc<[u8]> = content.into();
 
-        let mut form = reqwest::multipart::Form::new();
+        let build_form = || {
+            let mut form = reqwest::multipart::Form::new();
 
-        form = form.text("ParentLinkID", parent_id.to_string());
-        form = form.text("NodeName", name.to_string());
-        form = form.text("NodeType", "file");
+            form = form.text("ParentLinkID", parent_id.to_string());
+            form = form.text("NodeName", name.to_string());
+            form = form.text("NodeType", "file");
 
-        if let Some(mt) = mime_type {
-            form = form.text("MIMEType", mt.to_string());
-        }
+            if let Some(mt) = mime_type {
+                form = form.text("MIMEType", mt.to_string());
+            }
 
-        let part = reqwest::multipart::Part::bytes(content).file_name(name.to_string());
-        form = form.part("File", part);
+            form.part(
+                "File",
+                upload_part(&content, progress).file_name(name.to_string()),
+            )
+        };
 
         let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.get_token()))
-            .multipart(form)
-            .send()
+            .send(|| self.client.post(&url).multipart(build_form()))
             .await;
 
         match response {
@@ -184,10 +599,14 @@ impl ProtonClient {
                 let status = resp.status();
                 if !status.is_success() {
                     let error_text = resp.text().await.unwrap_or_default();
+                    let already_exists = serde_json::from_str::<DeleteNodeResponse>(&error_text)
+                        .is_ok_and(|r| r.code == NAME_EXISTS_CODE);
                     return Ok(CreateResult {
                         success: false,
                         node_uid: None,
+                        already_exists,
                         error: Some(format!("HTTP {}: {}", status, error_text)),
+                        status: Some(status.as_u16()),
                     });
                 }
 
@@ -198,7 +617,9 @@ impl ProtonClient {
                         return Ok(CreateResult {
                             success: true,
                             node_uid: Some(node.uid),
+                            already_exists: false,
                             error: None,
+                            status: None,
                         });
                     }
                 }
@@ -206,20 +627,173 @@ impl ProtonClient {
                 Ok(CreateResult {
                     success: false,
                     node_uid: None,
+                    already_exists: create_response.code == NAME_EXISTS_CODE,
                     error: Some(format!("API error code: {}", create_response.code)),
+                    status: None,
+                })
+            }
+            Err(e @ Error::Auth(_)) => Err(e),
+            Err(e) => Ok(CreateResult {
+                success: false,
+                node_uid: None,
+                already_exists: false,
+                error: Some(e.to_string()),
+                status: None,
+            }),
+        }
+    }
+
+    /// Upload new content for an existing file as a new revision
+    ///
+    /// Keeps the node UID and the file's version history.
+    pub async fn create_revision(
+        &self,
+        node_id: &str,
+        name: &str,
+        content: Vec<u8>,
+        mime_type: Option<&str>,
+        progress: Option<&ProgressCounter>,
+    ) -> Result<CreateResult> {
+        let url = self.endpoint(FILES_ENDPOINT, &[node_id, "revisions"]);
+        let content: Arc<[u8]> = content.into();
+
+        let build_form = || {
+            let mut form = reqwest::multipart::Form::new();
+
+            if let Some(mt) = mime_type {
+                form = form.text("MIMEType", mt.to_string());
+            }
+
+            form.part(
+                "File",
+                upload_part(&content, progress).file_name(name.to_string()),
+            )
+        };
+
+        let response = self
+            .send(|| self.client.post(&url).multipart(build_form()))
+            .await;
+
+        match response {
+            Ok(resp) => {
+                let status = resp.status();
+                if !status.is_success() {
+                    let error_text = resp.text().await.unwrap_or_default();
+                    return Ok(CreateResult {
+                        success: false,
+                        node_uid: None,
+                        already_exists: false,
+                        error: Some(format!("HTTP {}: {}", status, error_text)),
+                        status: Some(status.as_u16()),
+                    });
+                }
+
+                let revision_response: CreateRevisionResponse = resp.json().await?;
+
+                if revision_response.code == 1000 && revision_response.revision.is_some() {
+                    return Ok(CreateResult {
+                        success: true,
+                        node_uid: Some(node_id.to_string()),
+                        already_exists: false,
+                        error: None,
+                        status: None,
+                    });
+                }
+
+                Ok(CreateResult {
+                    success: false,
+                    node_uid: None,
+                    already_exists: false,
+                    error: Some(format!("API error code: {}", revision_response.code)),
+                    status: None,
                 })
             }
+            Err(e @ Error::Auth(_)) => Err(e),
             Err(e) => Ok(CreateResult {
                 success: false,
                 node_uid: None,
+                already_exists: false,
                 error: Some(e.to_string()),
+                status: None,
             }),
         }
     }
 
+    /// List the stored revisions of a file, newest first
+    pub async fn list_revisions(&self, node_id: &str) -> Result<Vec<Revision>> {
+        let url = self.endpoint(FILES_ENDPOINT, &[node_id, "revisions"]);
+
+        let response = self.send(|| self.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(Error::api_failed("List revisions", response.status()));
+        }
+
+        let list_response: ListRevisionsResponse = response.json().await?;
+
+        if list_response.code != 1000 {
+            return Err(Error::api(format!(
+                "List revisions error code: {}",
+                list_response.code
+            )));
+        }
+
+        let mut revisions: Vec<Revision> = list_response
+            .revisions
+            .into_iter()
+            .map(RevisionListItem::into_revision)
+            .collect();
+        revisions.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+
+        Ok(revisions)
+    }
+
+    /// Download the content of a file revision
+    pub async fn download_revision(&self, node_id: &str, revision_id: &str) -> Result<Vec<u8>> {
+        let url = self.endpoint(
+            FILES_ENDPOINT,
+            &[node_id, "revisions", revision_id, "content"],
+        );
+
+        let response = self.send(|| self.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(Error::api_failed("Download revision", response.status()));
+        }
+
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Make an earlier revision the file's current content
+    ///
+    /// The revision that was current stays in the file's history.
+    pub async fn restore_revision(&self, node_id: &str, revision_id: &str) -> Result<()> {
+        let url = self.endpoint(
+            FILES_ENDPOINT,
+            &[node_id, "revisions", revision_id, "restore"],
+        );
+
+        let response = self.send(|| self.client.post(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(Error::api_failed("Restore revision", response.status()));
+        }
+
+        let restore_response: DeleteNodeResponse = response.json().await?;
+
+        if restore_response.code != 1000 {
+            return Err(Error::api(format!(
+                "Restore revision error code: {}",
+                restore_response.code
+            )));
+        }
+
+        Ok(())
+    }
+
     /// Create a folder node
     pub async fn create_folder(&self, parent_id: &str, name: &str) -> Result<CreateResult> {
-        let url = format!("{}{}", self.api_base, NODES_ENDPOINT);
+        let url = self.endpoint(NODES_ENDPOINT, &[]);
 
         let request = CreateNodeRequest {
             parent_link_id: parent_id.to_string(),
@@ -229,13 +803,7 @@ impl ProtonClient {
             signature: None,
         };
 
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.get_token()))
-            .json(&request)
-            .send()
-            .await;
+        let response = self.send(|| self.client.post(&url).json(&request)).await;
 
         match response {
             Ok(resp) => {
@@ -245,7 +813,9 @@ impl ProtonClient {
                     return Ok(CreateResult {
                         success: false,
                         node_uid: None,
+                        already_exists: false,
                         error: Some(format!("HTTP {}: {}", status, error_text)),
+                        status: Some(status.as_u16()),
                     });
                 }
 
@@ -256,7 +826,9 @@ impl ProtonClient {
                         return Ok(CreateResult {
                             success: true,
                             node_uid: Some(node.uid),
+                            already_exists: false,
                             error: None,
+                            status: None,
                         });
                     }
                 }
@@ -264,13 +836,18 @@ impl ProtonClient {
                 Ok(CreateResult {
                     success: false,
                     node_uid: None,
+                    already_exists: false,
                     error: Some(format!("API error code: {}", create_response.code)),
+                    status: None,
                 })
             }
+            Err(e @ Error::Auth(_)) => Err(e),
             Err(e) => Ok(CreateResult {
                 success: false,
                 node_uid: None,
+                already_exists: false,
                 error: Some(e.to_string()),
+                status: None,
             }),
         }
     }
@@ -287,32 +864,23 @@ impl ProtonClient {
 
     /// Internal delete implementation
     async fn delete_node_internal(&self, node_id: &str, permanent: bool) -> Result<()> {
-        let url = format!("{}{}/{}", self.api_base, NODES_ENDPOINT, node_id);
+        let url = self.endpoint(NODES_ENDPOINT, &[node_id]);
 
         let mut query = Vec::new();
         if permanent {
             query.push(("permanent", "true"));
         }
 
-        let response = self
-            .client
-            .delete(&url)
-            .header("Authorization", format!("Bearer {}", self.get_token()))
-            .query(&query)
-            .send()
-            .await?;
+        let response = self.send(|| self.client.delete(&url).query(&query)).await?;
 
         if !response.status().is_success() {
-            return Err(Error::ProtonApi(format!(
-                "Delete failed: {}",
-                response.status()
-            )));
+            return Err(Error::api_failed("Delete", response.status()));
         }
 
         let delete_response: DeleteNodeResponse = response.json().await?;
 
         if delete_response.code != 1000 {
-            return Err(Error::ProtonApi(format!(
+            return Err(Error::api(format!(
                 "Delete error code: {}",
                 delete_response.code
             )));
@@ -323,32 +891,23 @@ impl ProtonClient {
 
     /// Rename a node
     pub async fn rename_node(&self, node_id: &str, new_name: &str) -> Result<String> {
-        let url = format!("{}{}/{}", self.api_base, NODES_ENDPOINT, node_id);
+        let url = self.endpoint(NODES_ENDPOINT, &[node_id]);
 
         let request = RenameNodeRequest {
             name: new_name.to_string(),
             signature: None,
         };
 
-        let response = self
-            .client
-            .put(&url)
-            .header("Authorization", format!("Bearer {}", self.get_token()))
-            .json(&request)
-            .send()
-            .await?;
+        let response = self.send(|| self.client.put(&url).json(&request)).await?;
 
         if !response.status().is_success() {
-            return Err(Error::ProtonApi(format!(
-                "Rename failed: {}",
-                response.status()
-            )));
+            return Err(Error::api_failed("Rename", response.status()));
         }
 
         let rename_response: RenameNodeResponse = response.json().await?;
 
         if rename_response.code != 1000 {
-            return Err(Error::ProtonApi(format!(
+            return Err(Error::api(format!(
                 "Rename error code: {}",
                 rename_response.code
             )));
@@ -357,52 +916,270 @@ impl ProtonClient {
         Ok(rename_response.node.unwrap().uid)
     }
 
-    /// List nodes in a folder
-    pub async fn list_nodes(&self, parent_id: &str) -> Result<Vec<NodeData>> {
-        let url = format!("{}{}", self.api_base, NODES_ENDPOINT);
+    /// Move a node to another folder, renaming it in the same request
+    pub async fn move_node(
+        &self,
+        node_id: &str,
+        new_parent_id: &str,
+        new_name: &str,
+    ) -> Result<String> {
+        let url = self.endpoint(NODES_ENDPOINT, &[node_id, "move"]);
+
+        let request = MoveNodeRequest {
+            parent_link_id: new_parent_id.to_string(),
+            name: new_name.to_string(),
+            signature: None,
+        };
 
-        let response = self
-            .client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.get_token()))
-            .query(&[("ParentLinkID", parent_id)])
-            .send()
-            .await?;
+        let response = self.send(|| self.client.put(&url).json(&request)).await?;
 
         if !response.status().is_success() {
-            return Err(Error::ProtonApi(format!(
-                "List nodes failed: {}",
-                response.status()
-            )));
+            return Err(Error::api_failed("Move", response.status()));
         }
 
-        let list_response: ListNodesResponse = response.json().await?;
+        let move_response: MoveNodeResponse = response.json().await?;
 
-        if list_response.code != 1000 {
-            return Err(Error::ProtonApi(format!(
-                "List nodes error code: {}",
-                list_response.code
+        if move_response.code != 1000 {
+            return Err(Error::api(format!(
+                "Move error code: {}",
+                move_response.code
             )));
         }
 
-        Ok(list_response
-            .nodes
+        Ok(move_response
+            .node
+            .map(|node| node.uid)
+            .unwrap_or_else(|| node_id.to_string()))
+    }
+
+    /// List nodes in a folder
+    pub async fn list_nodes(&self, parent_id: &str) -> Result<Vec<NodeData>> {
+        let url = self.endpoint(NODES_ENDPOINT, &[]);
+
+        let response = self
+            .send(|| self.client.get(&url).query(&[("ParentLinkID", parent_id)]))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(Error::api_failed("List nodes", response.status()));
+        }
+
+        let list_response: ListNodesResponse = response.json().await?;
+
+        if list_response.code != 1000 {
+            return Err(Error::api(format!(
+                "List nodes error code: {}",
+                list_response.code
+            )));
+        }
+
+        Ok(list_response
+            .nodes
             .into_iter()
-            .map(|n| NodeData {
-                uid: n.uid,
-                parent_uid: Some(n.parent_link_id),
-                name: n.name,
-                node_type: n.node_type,
-                media_type: n.mime_type,
-                active_revision: n.active_revision.map(|r| crate::types::RevisionData {
-                    uid: r.id,
-                    size: r.size,
-                    manifest_signature: r.manifest_signature,
-                }),
+            .map(NodeApiResponse::into_node_data)
+            .collect())
+    }
+
+    /// Get a node by its UID
+    ///
+    /// Returns `Ok(None)` if the node no longer exists.
+    pub async fn get_node(&self, node_id: &str) -> Result<Option<NodeData>> {
+        let url = self.endpoint(NODES_ENDPOINT, &[node_id]);
+
+        let response = self.send(|| self.client.get(&url)).await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        if !response.status().is_success() {
+            return Err(Error::api_failed("Get node", response.status()));
+        }
+
+        let node_response: GetNodeResponse = response.json().await?;
+
+        if node_response.code != 1000 {
+            return Err(Error::api(format!(
+                "Get node error code: {}",
+                node_response.code
+            )));
+        }
+
+        Ok(node_response.node.map(NodeApiResponse::into_node_data))
+    }
+
+    /// Create a public link to a node
+    ///
+    /// Anyone with the link can download the node; with a password they
+    /// must also enter it.
+    pub async fn create_share_url(
+        &self,
+        node_id: &str,
+        password: Option<&str>,
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<ShareUrl> {
+        let url = self.endpoint(NODES_ENDPOINT, &[node_id, "urls"]);
+
+        let request = CreateShareUrlRequest {
+            password,
+            expiration_time: expires_at.map(|t| t.timestamp()),
+        };
+
+        let response = self.send(|| self.client.post(&url).json(&request)).await?;
+
+        if !response.status().is_success() {
+            return Err(Error::api_failed("Create share URL", response.status()));
+        }
+
+        let share_response: CreateShareUrlResponse = response.json().await?;
+
+        if share_response.code != 1000 {
+            return Err(Error::api(format!(
+                "Create share URL error code: {}",
+                share_response.code
+            )));
+        }
+
+        Ok(ShareUrl {
+            url: share_response.share_url.url,
+            expires_at: share_response
+                .share_url
+                .expiration_time
+                .and_then(|t| chrono::DateTime::from_timestamp(t, 0)),
+            password_protected: password.is_some(),
+        })
+    }
+
+    /// List the nodes in the Drive trash
+    pub async fn list_trash(&self) -> Result<Vec<TrashedNode>> {
+        let url = self.endpoint(TRASH_ENDPOINT, &[]);
+
+        let response = self.send(|| self.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(Error::api_failed("List trash", response.status()));
+        }
+
+        let list_response: ListTrashResponse = response.json().await?;
+
+        if list_response.code != 1000 {
+            return Err(Error::api(format!(
+                "List trash error code: {}",
+                list_response.code
+            )));
+        }
+
+        Ok(list_response
+            .nodes
+            .into_iter()
+            .map(|n| TrashedNode {
+                node: n.node.into_node_data(),
+                trashed_at: n
+                    .trashed
+                    .and_then(|t| chrono::DateTime::from_timestamp(t, 0)),
             })
             .collect())
     }
 
+    /// Move trashed nodes back to where they were
+    pub async fn restore_from_trash(&self, node_ids: &[String]) -> Result<()> {
+        let url = self.endpoint(TRASH_ENDPOINT, &["restore"]);
+
+        let request = RestoreTrashRequest { link_ids: node_ids };
+
+        let response = self.send(|| self.client.put(&url).json(&request)).await?;
+
+        if !response.status().is_success() {
+            return Err(Error::api_failed("Restore from trash", response.status()));
+        }
+
+        let restore_response: DeleteNodeResponse = response.json().await?;
+
+        if restore_response.code != 1000 {
+            return Err(Error::api(format!(
+                "Restore from trash error code: {}",
+                restore_response.code
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Permanently delete everything in the Drive trash
+    pub async fn empty_trash(&self) -> Result<()> {
+        let url = self.endpoint(TRASH_ENDPOINT, &[]);
+
+        let response = self.send(|| self.client.delete(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(Error::api_failed("Empty trash", response.status()));
+        }
+
+        let empty_response: DeleteNodeResponse = response.json().await?;
+
+        if empty_response.code != 1000 {
+            return Err(Error::api(format!(
+                "Empty trash error code: {}",
+                empty_response.code
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Get the ID of the most recent Drive event
+    pub async fn get_latest_event_id(&self) -> Result<String> {
+        let url = self.endpoint(EVENTS_ENDPOINT, &["latest"]);
+
+        let response = self.send(|| self.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(Error::api_failed("Latest event", response.status()));
+        }
+
+        let latest_response: LatestEventResponse = response.json().await?;
+
+        if latest_response.code != 1000 {
+            return Err(Error::api(format!(
+                "Latest event error code: {}",
+                latest_response.code
+            )));
+        }
+
+        Ok(latest_response.event_id)
+    }
+
+    /// Get the node changes made after an event
+    pub async fn get_events(&self, since_event_id: &str) -> Result<NodeEvents> {
+        let url = self.endpoint(EVENTS_ENDPOINT, &[since_event_id]);
+
+        let response = self.send(|| self.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(Error::api_failed("Get events", response.status()));
+        }
+
+        let events_response: EventsResponse = response.json().await?;
+
+        if events_response.code != 1000 {
+            return Err(Error::api(format!(
+                "Get events error code: {}",
+                events_response.code
+            )));
+        }
+
+        Ok(NodeEvents {
+            events: events_response
+                .events
+                .into_iter()
+                .filter_map(EventApiResponse::into_node_event)
+                .collect(),
+            event_id: events_response.event_id,
+            more: events_response.more != 0,
+            refresh: events_response.refresh != 0,
+        })
+    }
+
     /// Get node by path
     pub async fn get_node_by_path(&self, share_id: &str, path: &str) -> Result<Option<NodeData>> {
         // This requires walking the path from root
@@ -415,34 +1192,78 @@ impl ProtonClient {
 
         // Start from root and traverse
         let mut current_id = share_id.to_string();
+        let mut current = None;
 
         for part in parts {
             let children = self.list_nodes(&current_id).await?;
 
-            let found = children.iter().find(|n| n.name == part);
-
-            match found {
+            match children
+                .into_iter()
+                .find(|n| PathUtils::same_name(&n.name, part))
+            {
                 Some(node) => {
                     current_id = node.uid.clone();
+                    current = Some(node);
                 }
                 None => return Ok(None),
             }
         }
 
-        // Get final node
-        let children = self.list_nodes(&current_id).await?;
-        Ok(children.into_iter().next())
+        Ok(current)
     }
 
-    /// Refresh session if needed
-    pub async fn refresh_session(&mut self) -> Result<()> {
-        self.session = self.auth_manager.refresh_session(&self.session).await?;
+    /// Refresh the session, saving the new tokens if the store persists them
+    ///
+    /// A session another process refreshed and saved is adopted first, as
+    /// its refresh token is the one that still works.
+    pub async fn refresh_session(&self) -> Result<()> {
+        let _guard = self.sessions.lock_refresh().await;
+        self.sessions.adopt_saved().await;
+        self.refresh_locked().await
+    }
+
+    /// Refresh the session; the caller holds the refresh lock
+    async fn refresh_locked(&self) -> Result<()> {
+        let current = self.session();
+        let refreshed = self.auth_manager.refresh_session(&current).await?;
+
+        // The old refresh token is spent either way, so a failed save only
+        // costs a re-login after a restart
+        if let Err(e) = self.sessions.update(refreshed).await {
+            tracing::warn!("Failed to save refreshed session: {}", e);
+        }
         Ok(())
     }
 
-    /// Get session
-    pub fn session(&self) -> &Session {
-        &self.session
+    /// Refresh the session if the access token expires within `margin`
+    ///
+    /// Returns whether the session was refreshed. Shares the lock with the
+    /// refresh after a rejected token, so the two never race.
+    pub async fn refresh_if_expiring(&self, margin: Duration) -> Result<bool> {
+        let _guard = self.sessions.lock_refresh().await;
+
+        let margin = chrono::Duration::from_std(margin).unwrap_or_default();
+        if !self.session().expires_within(margin) {
+            return Ok(false);
+        }
+        // Another process may have refreshed it already
+        if self.sessions.adopt_saved().await && !self.session().expires_within(margin) {
+            return Ok(false);
+        }
+
+        tracing::info!("Access token about to expire, refreshing session");
+        self.refresh_locked().await?;
+        Ok(true)
+    }
+
+    /// Get a copy of the current session
+    pub fn session(&self) -> Session {
+        self.sessions.get()
+    }
+
+    /// Session store this client uses, to share with other clients
+    pub fn sessions(&self) -> &SessionStore {
+        &self.sessions
     }
 
     /// Get root node ID
@@ -453,6 +1274,39 @@ impl ProtonClient {
     }
 }
 
+/// Build the file part of an upload, counting bytes sent if asked to
+///
+/// Called once per attempt, so a replayed upload restarts its count.
+fn upload_part(
+    content: &Arc<[u8]>,
+    progress: Option<&ProgressCounter>,
+) -> reqwest::multipart::Part {
+    match progress {
+        Some(counter) => {
+            counter.reset();
+            let len = content.len() as u64;
+            reqwest::multipart::Part::stream_with_length(
+                counted_body(content.clone(), counter.clone()),
+                len,
+            )
+        }
+        None => reqwest::multipart::Part::bytes(content.to_vec()),
+    }
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters
+pub fn encode_path_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
 /// Path utilities for Proton Drive
 pub struct PathUtils;
 
@@ -469,23 +1323,153 @@ impl PathUtils {
         format!("{}/{}", base, name)
     }
 
+    /// Build a remote path from a remote root and a path relative to the sync directory
+    ///
+    /// Components are always joined with `/`, so Windows separators never end
+    /// up inside remote folder names, and are NFC-normalized, so a name gets
+    /// the same remote name whichever platform it was created on.
+    pub fn from_local(remote_root: &str, relative: &Path) -> String {
+        Self::from_local_named(remote_root, relative, RemoteNaming::Preserve)
+    }
+
+    /// Build a remote path like [`PathUtils::from_local`], renaming each
+    /// component by the `naming` policy
+    pub fn from_local_named(remote_root: &str, relative: &Path, naming: RemoteNaming) -> String {
+        let relative: Vec<String> = relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => Some(Self::sanitize_name(
+                    &naming.apply(&crate::paths::remote_name(&part.to_string_lossy())),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        if relative.is_empty() {
+            let root = remote_root.trim_end_matches('/');
+            return if root.is_empty() {
+                "/".to_string()
+            } else {
+                root.to_string()
+            };
+        }
+
+        Self::join(remote_root, &relative.join("/"))
+    }
+
+    /// Build a remote path like [`PathUtils::from_local_named`] for a path
+    /// in the sync directory `source`, keeping what is already synced
+    ///
+    /// `mapped` gives the remote path a local path was synced to. A synced
+    /// path keeps its remote path and a new path below a synced folder keeps
+    /// the folder's, so a `remote_naming` change or a remote name escaped for
+    /// Windows never renames what is already on Proton Drive.
+    pub fn from_local_mapped(
+        remote_root: &str,
+        source: &Path,
+        path: &Path,
+        naming: RemoteNaming,
+        mapped: impl Fn(&Path) -> Option<String>,
+    ) -> String {
+        for ancestor in path.ancestors() {
+            if ancestor == source || !ancestor.starts_with(source) {
+                break;
+            }
+            let Some(remote) = mapped(ancestor) else {
+                continue;
+            };
+            // A mapping left from a different remote root no longer applies
+            if Self::strip_root(remote_root, &remote).is_some() {
+                let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+                return Self::from_local_named(&remote, rest, naming);
+            }
+        }
+
+        let relative = path.strip_prefix(source).unwrap_or(path);
+        Self::from_local_named(remote_root, relative, naming)
+    }
+
+    /// Legal Proton Drive name for a local name
+    ///
+    /// Control characters and `/` become `_`, and an empty name, `.` or `..`
+    /// becomes `_`. A name longer than [`MAX_NAME_BYTES`] is shortened, keeping
+    /// its extension and adding a hash of the full name, so long names that
+    /// share a beginning stay distinct. Legal names are returned unchanged,
+    /// and the same name always sanitizes the same way.
+    pub fn sanitize_name(name: &str) -> String {
+        let mut sanitized: String = name
+            .chars()
+            .map(|c| if c.is_control() || c == '/' { '_' } else { c })
+            .collect();
+        if matches!(sanitized.as_str(), "" | "." | "..") {
+            sanitized = "_".to_string();
+        }
+        if sanitized.len() <= MAX_NAME_BYTES {
+            return sanitized;
+        }
+
+        let hash = hex::encode(&Sha256::digest(name.as_bytes())[..4]);
+        let extension = match sanitized.rsplit_once('.') {
+            // A long "extension" is part of the name, not a type to keep
+            Some((stem, extension)) if !stem.is_empty() && extension.len() <= 16 => {
+                format!(".{}", extension)
+            }
+            _ => String::new(),
+        };
+
+        // Cut on a character boundary, leaving room for the hash and extension
+        let budget = MAX_NAME_BYTES - extension.len() - hash.len() - 1;
+        let mut end = budget;
+        while !sanitized.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{}~{}{}", &sanitized[..end], hash, extension)
+    }
+
+    /// Path of `path` relative to `remote_root`, or `None` if it lies outside
+    ///
+    /// The inverse of [`PathUtils::from_local`]; the root itself is `""`.
+    pub fn strip_root(remote_root: &str, path: &str) -> Option<String> {
+        let root = Self::normalize(remote_root);
+        let path = Self::normalize(path);
+
+        if root == "/" {
+            return Some(path.trim_start_matches('/').to_string());
+        }
+
+        match path.strip_prefix(root.trim_end_matches('/')) {
+            Some("") => Some(String::new()),
+            Some(rest) => rest.strip_prefix('/').map(str::to_string),
+            None => None,
+        }
+    }
+
     /// Get parent path
     pub fn parent(path: &str) -> Option<String> {
-        if path == "/" {
+        let path = path.trim_end_matches('/');
+
+        if path.is_empty() {
             return None;
         }
 
-        Path::new(path)
-            .parent()
-            .map(|p| p.to_string_lossy().to_string())
+        match path.rsplit_once('/') {
+            Some(("", _)) => Some("/".to_string()),
+            Some((parent, _)) => Some(parent.to_string()),
+            None => Some(String::new()),
+        }
+    }
+
+    /// Whether two remote names are the same, whatever their Unicode normalization
+    pub fn same_name(a: &str, b: &str) -> bool {
+        a == b || crate::paths::nfc(a) == crate::paths::nfc(b)
     }
 
     /// Get file name from path
     pub fn filename(path: &str) -> String {
-        Path::new(path)
-            .file_name()
-            .map(|n| n.to_string_lossy().to_string())
-            .unwrap_or_else(|| path.to_string())
+        match path.trim_end_matches('/').rsplit('/').next() {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => path.to_string(),
+        }
     }
 
     /// Normalize path
@@ -503,6 +1487,26 @@ impl PathUtils {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::credentials::{CredentialBackend, FileBackend};
+    use wiremock::matchers::{body_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_path_utils_strip_root() {
+        assert_eq!(
+            PathUtils::strip_root("/Docs", "/Docs/a/b.txt"),
+            Some("a/b.txt".to_string())
+        );
+        assert_eq!(
+            PathUtils::strip_root("/Docs/", "/Docs"),
+            Some(String::new())
+        );
+        assert_eq!(PathUtils::strip_root("/Docs", "/Docs2/b.txt"), None);
+        assert_eq!(
+            PathUtils::strip_root("/", "/a.txt"),
+            Some("a.txt".to_string())
+        );
+    }
 
     #[test]
     fn test_path_utils_join() {
@@ -542,6 +1546,165 @@ mod tests {
         assert_eq!(PathUtils::normalize(""), "/");
     }
 
+    #[test]
+    fn test_path_utils_from_local() {
+        assert_eq!(
+            PathUtils::from_local("/Docs", Path::new("a/b.txt")),
+            "/Docs/a/b.txt"
+        );
+        assert_eq!(
+            PathUtils::from_local("/Docs/", Path::new("./a/b.txt")),
+            "/Docs/a/b.txt"
+        );
+        assert_eq!(PathUtils::from_local("/Docs/", Path::new("")), "/Docs");
+        assert_eq!(PathUtils::from_local("/", Path::new("")), "/");
+    }
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(PathUtils::sanitize_name("notes.txt"), "notes.txt");
+        assert_eq!(PathUtils::sanitize_name("a\tb\nc"), "a_b_c");
+        assert_eq!(PathUtils::sanitize_name(".."), "_");
+        assert_eq!(PathUtils::sanitize_name(""), "_");
+
+        let long = format!("{}.txt", "é".repeat(200));
+        let sanitized = PathUtils::sanitize_name(&long);
+        assert!(sanitized.len() <= MAX_NAME_BYTES);
+        assert!(sanitized.ends_with(".txt"));
+        assert_eq!(PathUtils::sanitize_name(&long), sanitized);
+
+        // Long names that differ only at the end stay distinct
+        let other = format!("{}x.txt", "é".repeat(200));
+        assert_ne!(PathUtils::sanitize_name(&other), sanitized);
+
+        let exact = "a".repeat(MAX_NAME_BYTES);
+        assert_eq!(PathUtils::sanitize_name(&exact), exact);
+    }
+
+    #[test]
+    fn test_path_utils_from_local_named() {
+        assert_eq!(
+            PathUtils::from_local_named(
+                "/Docs",
+                Path::new("Tax Returns/2024 Résumé.pdf"),
+                RemoteNaming::Slugify
+            ),
+            "/Docs/tax-returns/2024-resume.pdf"
+        );
+        assert_eq!(
+            PathUtils::from_local_named("/Docs", Path::new("a\tb/c"), RemoteNaming::StripForbidden),
+            "/Docs/ab/c"
+        );
+    }
+
+    #[test]
+    fn test_path_utils_unicode_names() {
+        // (local name, remote name): CJK, Hangul with emoji, a ZWJ emoji
+        // sequence, right-to-left scripts, and a decomposed accent
+        let names = [
+            ("報告書.pdf", "報告書.pdf"),
+            ("사진 🎉.jpg", "사진 🎉.jpg"),
+            ("👨\u{200d}👩\u{200d}👧.png", "👨\u{200d}👩\u{200d}👧.png"),
+            ("שלום.txt", "שלום.txt"),
+            ("تقرير.docx", "تقرير.docx"),
+            ("cafe\u{301}/re\u{301}sume\u{301}.md", "café/résumé.md"),
+        ];
+
+        for (local, remote) in names {
+            let remote_path = PathUtils::from_local("/Docs", Path::new(local));
+            assert_eq!(remote_path, format!("/Docs/{}", remote));
+            assert_eq!(
+                PathUtils::strip_root("/Docs", &remote_path).as_deref(),
+                Some(remote)
+            );
+            assert_eq!(
+                PathUtils::filename(&remote_path),
+                remote.rsplit('/').next().unwrap()
+            );
+        }
+
+        assert!(PathUtils::same_name("cafe\u{301}", "café"));
+        assert!(PathUtils::same_name("報告書", "報告書"));
+        assert!(!PathUtils::same_name("café", "cafe"));
+    }
+
+    #[test]
+    fn test_unicode_names_on_disk() {
+        // Whatever form the filesystem hands names back in, remote names
+        // come out composed
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let names = [
+            "報告書.pdf",
+            "사진 🎉.jpg",
+            "שלום.txt",
+            "تقرير.docx",
+            "re\u{301}sume\u{301}.md",
+        ];
+        std::fs::create_dir(root.join("草稿")).unwrap();
+        for name in names {
+            std::fs::write(root.join("草稿").join(name), b"x").unwrap();
+        }
+
+        let mut remote: Vec<String> = walkdir::WalkDir::new(root)
+            .min_depth(2)
+            .into_iter()
+            .map(|e| {
+                let entry = e.unwrap();
+                PathUtils::from_local("/Docs", entry.path().strip_prefix(root).unwrap())
+            })
+            .collect();
+        remote.sort();
+
+        let mut expected: Vec<String> = [
+            "報告書.pdf",
+            "사진 🎉.jpg",
+            "שלום.txt",
+            "تقرير.docx",
+            "résumé.md",
+        ]
+        .iter()
+        .map(|name| format!("/Docs/草稿/{}", name))
+        .collect();
+        expected.sort();
+        assert_eq!(remote, expected);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_path_utils_from_local_windows() {
+        assert_eq!(
+            PathUtils::from_local("/Docs", Path::new(r"foo\bar\baz.txt")),
+            "/Docs/foo/bar/baz.txt"
+        );
+        assert_eq!(
+            PathUtils::from_local("/Docs", Path::new(r"foo/bar\baz.txt")),
+            "/Docs/foo/bar/baz.txt"
+        );
+
+        let base = Path::new(r"C:\Users\me\Documents");
+        let full = Path::new(r"C:\Users\me\Documents\Work\plan.md");
+        assert_eq!(
+            PathUtils::from_local("/My Files", full.strip_prefix(base).unwrap()),
+            "/My Files/Work/plan.md"
+        );
+    }
+
+    #[test]
+    fn test_move_node_request_serialization() {
+        let request = MoveNodeRequest {
+            parent_link_id: "parent".to_string(),
+            name: "report.pdf".to_string(),
+            signature: None,
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"ParentLinkID": "parent", "Name": "report.pdf"})
+        );
+    }
+
     #[test]
     fn test_proton_client_default() {
         let session = Session {
@@ -550,6 +1713,7 @@ mod tests {
             refresh_token: "test_refresh".to_string(),
             key_password: None,
             primary_key: None,
+            expires_at: None,
         };
 
         let client = ProtonClient::new(session.clone());
@@ -565,6 +1729,7 @@ mod tests {
             refresh_token: "test_refresh".to_string(),
             key_password: None,
             primary_key: None,
+            expires_at: None,
         };
 
         let custom_base = "https://custom.drive.api.com";
@@ -580,6 +1745,7 @@ mod tests {
             refresh_token: "test_refresh".to_string(),
             key_password: None,
             primary_key: None,
+            expires_at: None,
         };
 
         let client = ProtonClient::new(session);
@@ -594,6 +1760,7 @@ mod tests {
             refresh_token: "test_refresh".to_string(),
             key_password: None,
             primary_key: None,
+            expires_at: None,
         };
 
         let client = ProtonClient::new(session.clone());
@@ -601,4 +1768,481 @@ mod tests {
         assert_eq!(borrowed_session.uid, "test_uid");
         assert_eq!(borrowed_session.access_token, "test_token");
     }
+
+    #[tokio::test]
+    async fn test_retries_after_rate_limit() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/events/latest"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(2)
+            .expect(2)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/events/latest"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"Code": 1000, "EventID": "event-1"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = ProtonClient::with_api_base(server.uri(), expired_session());
+        assert_eq!(client.get_latest_event_id().await.unwrap(), "event-1");
+    }
+
+    #[tokio::test]
+    async fn test_records_last_successful_contact() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/events/latest"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/events/latest"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"Code": 1000, "EventID": "event-1"})),
+            )
+            .mount(&server)
+            .await;
+
+        let contact = ApiContact::default();
+        let client = ProtonClient::with_api_base(server.uri(), expired_session())
+            .with_contact(contact.clone());
+
+        assert!(client.get_latest_event_id().await.is_err());
+        assert_eq!(contact.last(), None);
+
+        let before = chrono::Utc::now() - chrono::Duration::seconds(1);
+        client.get_latest_event_id().await.unwrap();
+        assert!(contact.last().unwrap() >= before);
+    }
+
+    fn expired_session() -> Session {
+        Session {
+            uid: "test_uid".to_string(),
+            access_token: "expired_token".to_string(),
+            refresh_token: "test_refresh".to_string(),
+            key_password: None,
+            primary_key: None,
+            expires_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refreshes_session_before_token_expires() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/core/v4/auth/refresh"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "AccessToken": "fresh_token",
+                "RefreshToken": "fresh_refresh",
+                "ExpiresIn": 3600,
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let session = Session {
+            expires_at: Some(chrono::Utc::now() + chrono::Duration::minutes(2)),
+            ..expired_session()
+        };
+        let client = ProtonClient::with_api_base(server.uri(), session);
+
+        assert!(client
+            .refresh_if_expiring(SESSION_REFRESH_MARGIN)
+            .await
+            .unwrap());
+        assert_eq!(client.get_token(), "fresh_token");
+        let expires_at = client.session().expires_at.unwrap();
+        assert!(expires_at > chrono::Utc::now() + chrono::Duration::minutes(59));
+
+        // The new token is good for an hour; no second refresh
+        assert!(!client
+            .refresh_if_expiring(SESSION_REFRESH_MARGIN)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_refreshes_session_and_replays_on_unauthorized() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/events/latest"))
+            .and(header("Authorization", "Bearer expired_token"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/core/v4/auth/refresh"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "AccessToken": "fresh_token",
+                "RefreshToken": "fresh_refresh",
+                "ExpiresIn": 3600,
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/events/latest"))
+            .and(header("Authorization", "Bearer fresh_token"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"Code": 1000, "EventID": "event-1"})),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = ProtonClient::with_api_base(server.uri(), expired_session());
+
+        assert_eq!(client.get_latest_event_id().await.unwrap(), "event-1");
+        assert_eq!(client.session().refresh_token, "fresh_refresh");
+
+        // The refreshed token is used from then on
+        assert_eq!(client.get_latest_event_id().await.unwrap(), "event-1");
+    }
+
+    #[tokio::test]
+    async fn test_clients_sharing_a_session_refresh_once() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/events/latest"))
+            .and(header("Authorization", "Bearer expired_token"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/core/v4/auth/refresh"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "AccessToken": "fresh_token",
+                "RefreshToken": "fresh_refresh",
+                "ExpiresIn": 3600,
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/events/latest"))
+            .and(header("Authorization", "Bearer fresh_token"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"Code": 1000, "EventID": "event-1"})),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let first = ProtonClient::with_api_base(server.uri(), expired_session());
+        let mut second = ProtonClient::with_api_base(server.uri(), expired_session());
+        second.sessions = first.sessions().clone();
+
+        assert_eq!(first.get_latest_event_id().await.unwrap(), "event-1");
+
+        // The second client picks up the rotated tokens instead of
+        // refreshing with the spent refresh token
+        assert_eq!(second.session().refresh_token, "fresh_refresh");
+        assert_eq!(second.get_latest_event_id().await.unwrap(), "event-1");
+    }
+
+    #[tokio::test]
+    async fn test_adopts_session_refreshed_by_another_process() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/events/latest"))
+            .and(header("Authorization", "Bearer expired_token"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/core/v4/auth/refresh"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "AccessToken": "fresh_token",
+                "RefreshToken": "fresh_refresh",
+                "ExpiresIn": 3600,
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/events/latest"))
+            .and(header("Authorization", "Bearer fresh_token"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"Code": 1000, "EventID": "event-1"})),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        // Two processes with the same credential file
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let backend = || {
+            CredentialBackend::File(FileBackend::new(
+                temp_dir.path().join("credentials.enc"),
+                b"secret".to_vec(),
+            ))
+        };
+        backend().save(&expired_session()).unwrap();
+        let process = || {
+            let mut client = ProtonClient::with_api_base(server.uri(), expired_session());
+            client.sessions = SessionStore::new(expired_session(), backend());
+            client
+        };
+        let (first, second) = (process(), process());
+
+        assert_eq!(first.get_latest_event_id().await.unwrap(), "event-1");
+
+        // The second refreshes by reading what the first saved, since its
+        // own refresh token was spent
+        assert_eq!(second.get_latest_event_id().await.unwrap(), "event-1");
+        assert_eq!(second.session().refresh_token, "fresh_refresh");
+        // An expiring session is not refreshed once the saved one is fresh
+        let expiring = Session {
+            expires_at: Some(chrono::Utc::now() + chrono::Duration::minutes(2)),
+            ..expired_session()
+        };
+        let mut third = process();
+        third.sessions = SessionStore::new(expiring, backend());
+        assert!(!third
+            .refresh_if_expiring(SESSION_REFRESH_MARGIN)
+            .await
+            .unwrap());
+        assert_eq!(third.get_token(), "fresh_token");
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_requires_relogin() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/events/latest"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/core/v4/auth/refresh"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = ProtonClient::with_api_base(server.uri(), expired_session());
+
+        let err = client.get_latest_event_id().await.unwrap_err();
+        assert!(matches!(err, Error::Auth(_)));
+        assert!(err.to_string().contains("re-login required"));
+    }
+
+    #[tokio::test]
+    async fn test_list_revisions_newest_first() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/files/node-1/revisions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "Revisions": [
+                    {"ID": "rev-old", "Size": 10, "CreateTime": 1700000000, "State": 0},
+                    {"ID": "rev-new", "Size": 12, "CreateTime": 1700003600, "State": 1}
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let client = ProtonClient::with_api_base(server.uri(), expired_session());
+
+        let revisions = client.list_revisions("node-1").await.unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].uid, "rev-new");
+        assert!(revisions[0].active);
+        assert_eq!(revisions[1].uid, "rev-old");
+        assert_eq!(revisions[1].size, Some(10));
+        assert!(!revisions[1].active);
+    }
+
+    #[tokio::test]
+    async fn test_account_info_with_paid_plan() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/core/v4/users"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "User": {
+                    "Email": "user@proton.me",
+                    "UsedSpace": 450,
+                    "MaxSpace": 500,
+                    "Subscribed": 4
+                }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/payments/v4/subscription"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "Subscription": {
+                    "Plans": [
+                        {"Title": "Extra storage", "Type": 0},
+                        {"Title": "Proton Drive Plus", "Type": 1}
+                    ]
+                }
+            })))
+            .mount(&server)
+            .await;
+
+        let client = ProtonClient::with_api_base(server.uri(), expired_session());
+
+        let account = client.get_account_info().await.unwrap();
+        assert_eq!(account.email, "user@proton.me");
+        assert_eq!(account.plan.as_deref(), Some("Proton Drive Plus"));
+        assert!(account.is_over(90));
+        assert!(!account.is_over(95));
+    }
+
+    #[tokio::test]
+    async fn test_list_and_restore_trash() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/trash"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "Nodes": [{
+                    "UID": "node-1",
+                    "ParentLinkID": "folder-1",
+                    "Name": "notes.txt",
+                    "NodeType": "file",
+                    "State": 2,
+                    "Trashed": 1700000000
+                }]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/drive/v2/trash/restore"))
+            .and(body_json(serde_json::json!({ "LinkIDs": ["node-1"] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = ProtonClient::with_api_base(server.uri(), expired_session());
+
+        let trashed = client.list_trash().await.unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].node.name, "notes.txt");
+        assert_eq!(trashed[0].node.parent_uid.as_deref(), Some("folder-1"));
+        assert!(trashed[0].trashed_at.is_some());
+
+        client
+            .restore_from_trash(&["node-1".to_string()])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_create_share_url_with_password_and_expiry() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/drive/v2/nodes/node-1/urls"))
+            .and(body_json(serde_json::json!({
+                "Password": "secret",
+                "ExpirationTime": 1700000000
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "ShareURL": {
+                    "URL": "https://drive.proton.me/urls/ABC123",
+                    "ExpirationTime": 1700000000
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = ProtonClient::with_api_base(server.uri(), expired_session());
+        let expires_at = chrono::DateTime::from_timestamp(1700000000, 0);
+
+        let share = client
+            .create_share_url("node-1", Some("secret"), expires_at)
+            .await
+            .unwrap();
+        assert_eq!(share.url, "https://drive.proton.me/urls/ABC123");
+        assert_eq!(share.expires_at, expires_at);
+        assert!(share.password_protected);
+    }
+
+    #[test]
+    fn test_encode_path_segment() {
+        assert_eq!(encode_path_segment("node-1_a.b~c"), "node-1_a.b~c");
+        assert_eq!(
+            encode_path_segment("a/b%c#d?e\nf g"),
+            "a%2Fb%25c%23d%3Fe%0Af%20g"
+        );
+        assert_eq!(encode_path_segment("résumé"), "r%C3%A9sum%C3%A9");
+    }
+
+    #[tokio::test]
+    async fn test_hostile_ids_and_names_round_trip() {
+        let server = MockServer::start().await;
+        let id = "id/with%odd#chars?\n";
+        let name = "100% done #1?\nreally.txt";
+
+        Mock::given(method("PUT"))
+            .and(path("/drive/v2/nodes/id%2Fwith%25odd%23chars%3F%0A"))
+            .and(body_json(serde_json::json!({ "Name": name })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "Node": {
+                    "UID": id,
+                    "ParentLinkID": "folder-1",
+                    "Name": name,
+                    "NodeType": "file",
+                    "State": 1
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/drive/v2/nodes/id%2Fwith%25odd%23chars%3F%0A"))
+            .and(query_param("permanent", "true"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = ProtonClient::with_api_base(server.uri(), expired_session());
+
+        assert_eq!(client.rename_node(id, name).await.unwrap(), id);
+        client.delete_node_permanent(id).await.unwrap();
+    }
 }
@@ -0,0 +1,375 @@
+//! `.protonignore` support
+//!
+//! Each sync directory may contain a `.protonignore` file at its root using
+//! gitignore syntax (negation, directory-only patterns and anchoring). This
+//! module also converts `.gitignore` files into exclude globs.
+
+use crate::error::{Error, Result};
+use crate::paths::{nfc, nfc_path};
+use crate::types::SyncDir;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Ignore file name looked up at the root of each sync directory
+pub const PROTONIGNORE_FILE: &str = ".protonignore";
+
+/// Compiled `.protonignore` rules for all sync directories
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    matchers: HashMap<PathBuf, Gitignore>,
+}
+
+impl IgnoreRules {
+    /// Create an empty rule set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load rules for every configured sync directory
+    pub fn load(sync_dirs: &[SyncDir]) -> Self {
+        let mut rules = Self::new();
+        for sync_dir in sync_dirs {
+            rules.reload(Path::new(&sync_dir.source_path));
+        }
+        rules
+    }
+
+    /// (Re)load the rules for a single sync directory
+    pub fn reload(&mut self, root: &Path) {
+        match load_matcher(root) {
+            Some(matcher) => {
+                debug!(
+                    "Loaded {} rule(s) from {}",
+                    matcher.num_ignores() + matcher.num_whitelists(),
+                    root.join(PROTONIGNORE_FILE).display()
+                );
+                self.matchers.insert(root.to_path_buf(), matcher);
+            }
+            None => {
+                self.matchers.remove(root);
+            }
+        }
+    }
+
+    /// Check if a path is ignored by the rules of the sync directory containing it
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.matchers
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .any(|(_, matcher)| is_match(matcher, path, is_dir))
+    }
+}
+
+/// Build a matcher from `<root>/.protonignore`, if present
+///
+/// Rules and the root are NFC-normalized; [`is_match`] normalizes the paths
+/// it checks the same way.
+pub fn load_matcher(root: &Path) -> Option<Gitignore> {
+    let ignore_path = root.join(PROTONIGNORE_FILE);
+    if !ignore_path.is_file() {
+        return None;
+    }
+
+    let content = match std::fs::read_to_string(&ignore_path) {
+        Ok(content) => content,
+        Err(e) => {
+            warn!("Error reading {}: {}", ignore_path.display(), e);
+            return None;
+        }
+    };
+
+    let mut builder = GitignoreBuilder::new(nfc_path(root));
+    for line in content.lines() {
+        if let Err(e) = builder.add_line(Some(ignore_path.clone()), &nfc(line)) {
+            warn!("Error parsing {}: {}", ignore_path.display(), e);
+        }
+    }
+
+    match builder.build() {
+        Ok(matcher) => Some(matcher),
+        Err(e) => {
+            warn!("Error building {}: {}", ignore_path.display(), e);
+            None
+        }
+    }
+}
+
+/// Check a path against a matcher, honouring ignored parent directories
+pub fn is_match(matcher: &Gitignore, path: &Path, is_dir: bool) -> bool {
+    let path = nfc_path(path);
+    if !path.starts_with(matcher.path()) {
+        return false;
+    }
+
+    matcher
+        .matched_path_or_any_parents(&path, is_dir)
+        .is_ignore()
+}
+
+/// Check if a path is the `.protonignore` file of a sync directory
+pub fn is_ignore_file(path: &Path) -> bool {
+    path.file_name().is_some_and(|n| n == PROTONIGNORE_FILE)
+}
+
+/// Globs converted from a repository's `.gitignore` chain
+#[derive(Debug, Default)]
+pub struct GitignoreImport {
+    pub globs: Vec<String>,
+    /// Negated (`!`) rules, which exclude globs cannot express
+    pub skipped: Vec<String>,
+    pub files: usize,
+}
+
+/// Collect exclude globs from every `.gitignore` in a repository
+///
+/// Directories that are themselves ignored are not descended into, and
+/// `.git/info/exclude` is included when present.
+pub fn import_gitignore(repo: &Path) -> Result<GitignoreImport> {
+    if !repo.is_dir() {
+        return Err(Error::InvalidPath(format!(
+            "{} is not a directory",
+            repo.display()
+        )));
+    }
+
+    let mut import = GitignoreImport::default();
+
+    let info_exclude = repo.join(".git").join("info").join("exclude");
+    if info_exclude.is_file() {
+        let content = std::fs::read_to_string(&info_exclude)?;
+        convert_gitignore(repo, &content, &mut import);
+        import.files += 1;
+    }
+
+    let walker = ignore::WalkBuilder::new(repo)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(|e| e.file_name() != ".git")
+        .build();
+
+    for entry in walker {
+        let entry = entry.map_err(|e| Error::InvalidPath(e.to_string()))?;
+        if entry.file_name() != ".gitignore" || !entry.path().is_file() {
+            continue;
+        }
+
+        let dir = entry.path().parent().unwrap_or(repo);
+        let content = std::fs::read_to_string(entry.path())?;
+        convert_gitignore(dir, &content, &mut import);
+        import.files += 1;
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    import.globs.retain(|g| seen.insert(g.clone()));
+
+    Ok(import)
+}
+
+/// Convert the rules of a single `.gitignore` located in `dir` into globs
+pub fn convert_gitignore(dir: &Path, content: &str, import: &mut GitignoreImport) {
+    let base = glob::Pattern::escape(&dir.to_string_lossy());
+    let base = base.trim_end_matches('/');
+
+    for line in content.lines() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('!') {
+            import.skipped.push(line.to_string());
+            continue;
+        }
+
+        let rule = line.strip_prefix('\\').unwrap_or(line);
+        let rule = rule.trim_end_matches('/');
+        if rule.is_empty() {
+            continue;
+        }
+
+        // A slash anywhere but the end anchors the rule to the .gitignore directory
+        let glob = if rule.contains('/') {
+            format!("{}/{}", base, rule.trim_start_matches('/'))
+        } else {
+            format!("{}/**/{}", base, rule)
+        };
+
+        import.globs.push(format!("{}/**", glob));
+        import.globs.push(glob);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write_rules(dir: &Path, rules: &str) {
+        fs::write(dir.join(PROTONIGNORE_FILE), rules).unwrap();
+    }
+
+    #[test]
+    fn test_no_ignore_file() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(load_matcher(temp_dir.path()).is_none());
+    }
+
+    #[test]
+    fn test_basic_patterns() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write_rules(root, "*.log\nbuild/\n");
+
+        let matcher = load_matcher(root).unwrap();
+        assert!(is_match(&matcher, &root.join("app.log"), false));
+        assert!(is_match(&matcher, &root.join("sub/app.log"), false));
+        assert!(is_match(&matcher, &root.join("build"), true));
+        assert!(is_match(&matcher, &root.join("build/out.bin"), false));
+        assert!(!is_match(&matcher, &root.join("build"), false));
+        assert!(!is_match(&matcher, &root.join("notes.txt"), false));
+    }
+
+    #[test]
+    fn test_unicode_patterns() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write_rules(root, "草稿/\n*.café\n!重要.café\n🎉*\n");
+
+        let matcher = load_matcher(root).unwrap();
+        assert!(is_match(&matcher, &root.join("草稿"), true));
+        assert!(is_match(&matcher, &root.join("草稿/메모.txt"), false));
+        assert!(is_match(&matcher, &root.join("notes.cafe\u{301}"), false));
+        assert!(!is_match(&matcher, &root.join("重要.cafe\u{301}"), false));
+        assert!(is_match(&matcher, &root.join("🎉 party.jpg"), false));
+        assert!(!is_match(&matcher, &root.join("تقرير.docx"), false));
+    }
+
+    #[test]
+    fn test_negation() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write_rules(root, "*.log\n!keep.log\n");
+
+        let matcher = load_matcher(root).unwrap();
+        assert!(is_match(&matcher, &root.join("app.log"), false));
+        assert!(!is_match(&matcher, &root.join("keep.log"), false));
+    }
+
+    #[test]
+    fn test_anchoring() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write_rules(root, "/secret.txt\n");
+
+        let matcher = load_matcher(root).unwrap();
+        assert!(is_match(&matcher, &root.join("secret.txt"), false));
+        assert!(!is_match(&matcher, &root.join("docs/secret.txt"), false));
+    }
+
+    #[test]
+    fn test_rules_scoped_to_sync_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = temp_dir.path().join("first");
+        let second = temp_dir.path().join("second");
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        write_rules(&first, "*.tmp\n");
+
+        let rules = IgnoreRules::load(&[
+            SyncDir {
+                source_path: first.to_string_lossy().to_string(),
+                remote_root: "/first".to_string(),
+                case_insensitive: None,
+            },
+            SyncDir {
+                source_path: second.to_string_lossy().to_string(),
+                remote_root: "/second".to_string(),
+                case_insensitive: None,
+            },
+        ]);
+
+        assert!(rules.is_ignored(&first.join("a.tmp"), false));
+        assert!(!rules.is_ignored(&second.join("a.tmp"), false));
+    }
+
+    #[test]
+    fn test_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let mut rules = IgnoreRules::new();
+
+        rules.reload(root);
+        assert!(!rules.is_ignored(&root.join("a.bak"), false));
+
+        write_rules(root, "*.bak\n");
+        rules.reload(root);
+        assert!(rules.is_ignored(&root.join("a.bak"), false));
+
+        fs::remove_file(root.join(PROTONIGNORE_FILE)).unwrap();
+        rules.reload(root);
+        assert!(!rules.is_ignored(&root.join("a.bak"), false));
+    }
+
+    #[test]
+    fn test_convert_gitignore() {
+        let mut import = GitignoreImport::default();
+        convert_gitignore(
+            Path::new("/repo"),
+            "# comment\n\ntarget/\n/dist\n*.log\n!keep.log\ndocs/_build\n",
+            &mut import,
+        );
+
+        assert_eq!(
+            import.globs,
+            vec![
+                "/repo/**/target/**",
+                "/repo/**/target",
+                "/repo/dist/**",
+                "/repo/dist",
+                "/repo/**/*.log/**",
+                "/repo/**/*.log",
+                "/repo/docs/_build/**",
+                "/repo/docs/_build",
+            ]
+        );
+        assert_eq!(import.skipped, vec!["!keep.log"]);
+    }
+
+    #[test]
+    fn test_import_gitignore_chain() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path();
+        fs::create_dir_all(repo.join("sub")).unwrap();
+        fs::create_dir_all(repo.join("target/nested")).unwrap();
+        fs::write(repo.join(".gitignore"), "target/\n").unwrap();
+        fs::write(repo.join("sub/.gitignore"), "*.o\n").unwrap();
+        // Lives inside an ignored directory, so it must not be picked up
+        fs::write(repo.join("target/nested/.gitignore"), "*.rs\n").unwrap();
+
+        let import = import_gitignore(repo).unwrap();
+        assert_eq!(import.files, 2);
+
+        let sub = glob::Pattern::escape(&repo.join("sub").to_string_lossy());
+        assert!(import.globs.contains(&format!("{}/**/*.o", sub)));
+        assert!(!import.globs.iter().any(|g| g.ends_with("*.rs")));
+
+        let matches = |p: &Path| {
+            import
+                .globs
+                .iter()
+                .any(|g| glob::Pattern::new(g).unwrap().matches_path(p))
+        };
+        assert!(matches(&repo.join("target/debug/app")));
+        assert!(matches(&repo.join("sub/deep/main.o")));
+        assert!(!matches(&repo.join("src/main.o")));
+    }
+
+    #[test]
+    fn test_is_ignore_file() {
+        assert!(is_ignore_file(Path::new("/sync/.protonignore")));
+        assert!(!is_ignore_file(Path::new("/sync/.gitignore")));
+    }
+}
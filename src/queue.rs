@@ -1,21 +1,28 @@
 //! Job queue for sync operations
 
+use crate::cache::{Cached, STATUS_CACHE_TTL};
 use crate::db::Db;
 use crate::error::Result;
-use crate::types::{SyncJob, SyncJobStatus};
+use crate::types::{SkipReason, SyncJob, SyncJobStatus};
+use serde::Serialize;
+use std::sync::Arc;
 use std::time::Duration;
-use tracing::{error, info};
 
 /// Job queue manager
 #[derive(Clone)]
 pub struct JobQueue {
     db: Db,
+    /// Status counts shared by the clones of this queue
+    status_cache: Arc<Cached<StatusCounts>>,
 }
 
 impl JobQueue {
     /// Create a new job queue
     pub fn new(db: Db) -> Self {
-        Self { db }
+        Self {
+            db,
+            status_cache: Arc::new(Cached::new(STATUS_CACHE_TTL)),
+        }
     }
 
     /// Get pending jobs
@@ -37,15 +44,29 @@ impl JobQueue {
         let processing = self.db.get_job_count(SyncJobStatus::Processing).await?;
         let synced = self.db.get_job_count(SyncJobStatus::Synced).await?;
         let blocked = self.db.get_job_count(SyncJobStatus::Blocked).await?;
+        let skipped = self
+            .db
+            .get_job_count(SyncJobStatus::Skipped(SkipReason::PermissionDenied))
+            .await?;
 
         Ok(StatusCounts {
             pending: pending as usize,
             processing: processing as usize,
             synced: synced as usize,
             blocked: blocked as usize,
+            skipped: skipped as usize,
         })
     }
 
+    /// Get job counts by status, at most a few seconds old
+    ///
+    /// For status displays polled by many readers.
+    pub async fn cached_status_counts(&self) -> Result<StatusCounts> {
+        self.status_cache
+            .get_or_refresh(|| self.get_status_counts())
+            .await
+    }
+
     /// Clean up old completed jobs
     pub async fn cleanup_old_jobs(&self, older_than: Duration) -> Result<u64> {
         self.db
@@ -53,34 +74,6 @@ impl JobQueue {
             .await
     }
 
-    /// Start background cleanup task
-    pub fn start_cleanup_task(&self, interval_duration: Duration) -> tokio::task::JoinHandle<()> {
-        let db = self.db.clone();
-        let cleanup_duration = Duration::from_secs(7 * 24 * 60 * 60); // 7 days
-
-        tokio::spawn(async move {
-            let mut ticker = tokio::time::interval(interval_duration);
-            ticker.tick().await; // Skip first tick
-
-            loop {
-                ticker.tick().await;
-
-                match db
-                    .delete_completed_jobs(chrono::Duration::from_std(cleanup_duration).unwrap())
-                    .await
-                {
-                    Ok(count) if count > 0 => {
-                        info!("Cleaned up {} old completed jobs", count);
-                    }
-                    Err(e) => {
-                        error!("Error cleaning up old jobs: {}", e);
-                    }
-                    _ => {}
-                }
-            }
-        })
-    }
-
     /// Clear stale processing jobs
     pub async fn clear_stale_processing(&self, older_than_secs: i64) -> Result<u64> {
         self.db.clear_stale_processing(older_than_secs).await
@@ -88,17 +81,214 @@ impl JobQueue {
 }
 
 /// Status counts
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
 pub struct StatusCounts {
     pub pending: usize,
     pub processing: usize,
     pub synced: usize,
     pub blocked: usize,
+    /// Set aside because the files cannot be read
+    pub skipped: usize,
 }
 
 impl StatusCounts {
     /// Total count
     pub fn total(&self) -> usize {
-        self.pending + self.processing + self.synced + self.blocked
+        self.pending + self.processing + self.synced + self.blocked + self.skipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{JobPriority, SyncEvent, SyncEventType};
+    use tempfile::TempDir;
+
+    fn event(event_type: SyncEventType, path: &str) -> SyncEvent {
+        SyncEvent {
+            event_type,
+            local_path: path.to_string(),
+            remote_path: path.to_string(),
+            change_token: None,
+            old_local_path: None,
+            old_remote_path: None,
+        }
+    }
+
+    fn moved(from: &str, to: &str) -> SyncEvent {
+        SyncEvent {
+            old_local_path: Some(from.to_string()),
+            old_remote_path: Some(from.to_string()),
+            ..event(SyncEventType::Update, to)
+        }
+    }
+
+    async fn pending(db: &Db) -> Vec<(SyncEventType, String, Option<String>)> {
+        db.get_all_jobs()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|j| j.status == SyncJobStatus::Pending)
+            .map(|j| (j.event_type, j.local_path, j.change_token))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_pending_jobs_by_priority() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let queue = JobQueue::new(db.clone());
+
+        let jobs = [
+            (
+                event(SyncEventType::CreateFile, "/huge.iso"),
+                JobPriority::Large,
+            ),
+            (
+                event(SyncEventType::CreateFile, "/notes.txt"),
+                JobPriority::Small,
+            ),
+            (
+                event(SyncEventType::CreateDir, "/photos"),
+                JobPriority::Metadata,
+            ),
+            // Queued after the upload of the same path, so it must wait for it
+            (moved("/huge.iso", "/old.iso"), JobPriority::Metadata),
+        ];
+        for (event, priority) in jobs {
+            db.enqueue_job(&event, priority).await.unwrap();
+        }
+
+        let order: Vec<String> = queue
+            .get_pending_jobs(10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|j| j.local_path)
+            .collect();
+
+        assert_eq!(order, vec!["/photos", "/notes.txt", "/huge.iso"]);
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_collapses_pending_jobs() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+
+        let with_token = |event_type, path: &str, token: &str| SyncEvent {
+            change_token: Some(token.to_string()),
+            ..event(event_type, path)
+        };
+
+        // Repeated updates keep one job with the latest token
+        for token in ["1", "2", "3"] {
+            db.enqueue_job(
+                &with_token(SyncEventType::Update, "/a.txt", token),
+                JobPriority::Small,
+            )
+            .await
+            .unwrap();
+        }
+        // A create absorbs the updates that follow it
+        db.enqueue_job(
+            &with_token(SyncEventType::CreateFile, "/b.txt", "1"),
+            JobPriority::Small,
+        )
+        .await
+        .unwrap();
+        db.enqueue_job(
+            &with_token(SyncEventType::Update, "/b.txt", "2"),
+            JobPriority::Small,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            pending(&db).await,
+            vec![
+                (
+                    SyncEventType::Update,
+                    "/a.txt".to_string(),
+                    Some("3".to_string())
+                ),
+                (
+                    SyncEventType::CreateFile,
+                    "/b.txt".to_string(),
+                    Some("2".to_string())
+                ),
+            ]
+        );
+
+        // create -> update -> delete of a never-uploaded file leaves nothing
+        let cancelled = db
+            .enqueue_job(
+                &event(SyncEventType::Delete, "/b.txt"),
+                JobPriority::Metadata,
+            )
+            .await
+            .unwrap();
+        assert_eq!(cancelled, None);
+
+        // update -> delete becomes a delete
+        db.enqueue_job(
+            &event(SyncEventType::Delete, "/a.txt"),
+            JobPriority::Metadata,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            pending(&db).await,
+            vec![(SyncEventType::Delete, "/a.txt".to_string(), None)]
+        );
+
+        // A file uploaded before keeps its delete after create -> delete
+        db.update_node_mapping(&crate::types::NodeMapping {
+            local_path: "/c.txt".to_string(),
+            remote_path: "/c.txt".to_string(),
+            node_uid: "c".to_string(),
+            parent_node_uid: "root".to_string(),
+            is_directory: false,
+            updated_at: chrono::Utc::now(),
+        })
+        .await
+        .unwrap();
+        db.enqueue_job(
+            &event(SyncEventType::CreateFile, "/c.txt"),
+            JobPriority::Small,
+        )
+        .await
+        .unwrap();
+        db.enqueue_job(
+            &event(SyncEventType::Delete, "/c.txt"),
+            JobPriority::Metadata,
+        )
+        .await
+        .unwrap();
+
+        // A job queued behind a move of the same path is not merged into
+        // the job before the move
+        db.enqueue_job(&event(SyncEventType::Update, "/d.txt"), JobPriority::Small)
+            .await
+            .unwrap();
+        db.enqueue_job(&moved("/d.txt", "/e.txt"), JobPriority::Metadata)
+            .await
+            .unwrap();
+        db.enqueue_job(
+            &event(SyncEventType::CreateFile, "/d.txt"),
+            JobPriority::Small,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            pending(&db).await,
+            vec![
+                (SyncEventType::Delete, "/a.txt".to_string(), None),
+                (SyncEventType::Delete, "/c.txt".to_string(), None),
+                (SyncEventType::Update, "/d.txt".to_string(), None),
+                (SyncEventType::Update, "/e.txt".to_string(), None),
+                (SyncEventType::CreateFile, "/d.txt".to_string(), None),
+            ]
+        );
     }
 }
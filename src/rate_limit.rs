@@ -0,0 +1,162 @@
+//! Client-side rate limiting for Proton API requests
+//!
+//! Proton answers bursts of requests with 429 Too Many Requests, usually
+//! with a `Retry-After` header. Rather than letting each job fail and spend
+//! its retry budget, the client waits as asked and retries the request. A
+//! 429 pauses every request from the client, not just the one that got it,
+//! and the number of requests in flight at once is capped.
+
+use crate::error::Result;
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+/// Requests sent to the API at the same time
+pub const MAX_CONCURRENT_REQUESTS: usize = 8;
+
+/// Times a rate-limited request is retried before the 429 is returned
+pub const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+
+/// First backoff delay when the server gives no `Retry-After`
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest backoff delay when the server gives no `Retry-After`
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Longest `Retry-After` honored, guarding against absurd server values
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(600);
+
+/// Shared request budget and pause state for one API client
+pub struct RateLimiter {
+    permits: Semaphore,
+    paused_until: Mutex<Option<Instant>>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(MAX_CONCURRENT_REQUESTS)
+    }
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `max_concurrent` requests in flight
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            permits: Semaphore::new(max_concurrent),
+            paused_until: Mutex::new(None),
+        }
+    }
+
+    /// Wait out any pause, then run `request` within the concurrency limit
+    pub async fn run<F, Fut, T>(&self, request: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = T>,
+    {
+        self.wait_for_pause().await;
+        let _permit = self.permits.acquire().await?;
+        Ok(request().await)
+    }
+
+    /// Pause all requests for `delay`, extending any pause already set
+    pub fn pause_for(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut paused_until = self.paused_until.lock().unwrap();
+        if paused_until.is_none_or(|current| current < until) {
+            *paused_until = Some(until);
+        }
+    }
+
+    /// Sleep until no pause is in effect
+    async fn wait_for_pause(&self) {
+        loop {
+            let until = *self.paused_until.lock().unwrap();
+            match until {
+                Some(until) if until > Instant::now() => {
+                    tokio::time::sleep_until(until.into()).await
+                }
+                _ => return,
+            }
+        }
+    }
+}
+
+/// Delay requested by a `Retry-After` header, in seconds or as an HTTP date
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    let delay = match value.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+            (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                .to_std()
+                .unwrap_or_default()
+        }
+    };
+
+    Some(delay.min(MAX_RETRY_AFTER))
+}
+
+/// Jittered exponential backoff for the given retry attempt, counting from 0
+///
+/// The delay doubles each attempt up to a cap, and a random point in its
+/// upper half is chosen so that clients rate-limited together spread out.
+pub fn backoff_delay(attempt: u32) -> Duration {
+    let delay = BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF);
+
+    rand::thread_rng().gen_range(delay / 2..=delay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("30"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(30)));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("86400"));
+        assert_eq!(retry_after(&headers), Some(MAX_RETRY_AFTER));
+
+        // A date in the past means retry now
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles_with_jitter() {
+        for attempt in 0..10 {
+            let cap = BASE_BACKOFF
+                .saturating_mul(2u32.pow(attempt))
+                .min(MAX_BACKOFF);
+            let delay = backoff_delay(attempt);
+            assert!(delay >= cap / 2 && delay <= cap, "attempt {}", attempt);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pause_delays_requests() {
+        let limiter = RateLimiter::new(1);
+        limiter.pause_for(Duration::from_millis(50));
+
+        let start = Instant::now();
+        limiter.run(|| async {}).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,324 @@
+//! Local index of remote Drive nodes
+//!
+//! Folder listings and node lookups are cached in the `remote_nodes` table
+//! and kept current by applying changes from the Drive events endpoint, so
+//! path resolution after a restart rarely needs to list folders again.
+
+use crate::db::Db;
+use crate::error::{Error, Result};
+use crate::proton::{PathUtils, ProtonClient};
+use crate::types::{NodeData, NodeEventType};
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// How often the daemon pulls remote changes into the index
+pub const REMOTE_EVENTS_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Maximum event pages applied in one refresh
+const MAX_EVENT_PAGES: usize = 50;
+
+/// Maximum folder depth walked when resolving a node's path
+const MAX_NODE_DEPTH: usize = 256;
+
+/// Node type of folders
+pub const FOLDER_NODE_TYPE: &str = "folder";
+
+/// Remote node lookups served from the local index where possible
+pub struct RemoteIndex<'a> {
+    client: &'a ProtonClient,
+    db: &'a Db,
+}
+
+impl<'a> RemoteIndex<'a> {
+    /// Create an index backed by a client and database
+    pub fn new(client: &'a ProtonClient, db: &'a Db) -> Self {
+        Self { client, db }
+    }
+
+    /// Apply remote changes made since the last refresh
+    ///
+    /// The first refresh only records the current event ID; the index is
+    /// filled lazily by lookups from then on. Returns the number of events
+    /// applied.
+    pub async fn refresh(&self) -> Result<usize> {
+        let mut cursor = match self.db.get_remote_event_cursor().await? {
+            Some(cursor) => cursor,
+            None => {
+                let latest = self.client.get_latest_event_id().await?;
+                self.db.set_remote_event_cursor(&latest).await?;
+                return Ok(0);
+            }
+        };
+
+        let mut applied = 0;
+
+        for _ in 0..MAX_EVENT_PAGES {
+            let page = self.client.get_events(&cursor).await?;
+
+            if page.refresh {
+                info!("Remote event history expired, rebuilding remote index");
+                self.db.clear_remote_index().await?;
+                let latest = self.client.get_latest_event_id().await?;
+                self.db.set_remote_event_cursor(&latest).await?;
+                return Ok(applied);
+            }
+
+            for event in page.events {
+                match (event.event_type, event.node) {
+                    (NodeEventType::Delete, _) | (_, None) => {
+                        self.db.delete_remote_node(&event.node_uid).await?;
+                    }
+                    (_, Some(node)) => self.db.upsert_remote_node(&node).await?,
+                }
+                applied += 1;
+            }
+
+            cursor = page.event_id;
+            self.db.set_remote_event_cursor(&cursor).await?;
+
+            if !page.more {
+                break;
+            }
+        }
+
+        if applied > 0 {
+            debug!("Applied {} remote events to the index", applied);
+        }
+
+        Ok(applied)
+    }
+
+    /// Get a node by its UID
+    ///
+    /// Returns `Ok(None)` if the node no longer exists.
+    pub async fn get_node(&self, uid: &str) -> Result<Option<NodeData>> {
+        if let Some(node) = self.db.get_remote_node(uid).await? {
+            return Ok(Some(node));
+        }
+
+        let node = self.client.get_node(uid).await?;
+        if let Some(node) = &node {
+            self.db.upsert_remote_node(node).await?;
+        }
+
+        Ok(node)
+    }
+
+    /// List the children of a folder
+    pub async fn list_children(&self, parent_uid: &str) -> Result<Vec<NodeData>> {
+        if let Some(children) = self.db.get_remote_children(parent_uid).await? {
+            return Ok(children);
+        }
+
+        let children = self.client.list_nodes(parent_uid).await?;
+        self.db.set_remote_children(parent_uid, &children).await?;
+
+        Ok(children)
+    }
+
+    /// List the children of a folder from the API, replacing any indexed listing
+    pub async fn relist_children(&self, parent_uid: &str) -> Result<Vec<NodeData>> {
+        let children = self.client.list_nodes(parent_uid).await?;
+        self.db.set_remote_children(parent_uid, &children).await?;
+
+        Ok(children)
+    }
+
+    /// Record a folder this client just created
+    ///
+    /// A new folder is known to be empty, so its listing is indexed as
+    /// complete right away.
+    pub async fn add_created_folder(&self, parent_uid: &str, name: &str, uid: &str) -> Result<()> {
+        let folder = NodeData {
+            uid: uid.to_string(),
+            parent_uid: Some(parent_uid.to_string()),
+            name: name.to_string(),
+            node_type: FOLDER_NODE_TYPE.to_string(),
+            media_type: None,
+            active_revision: None,
+        };
+
+        self.db.upsert_remote_node(&folder).await?;
+        self.db.set_remote_children(uid, &[]).await
+    }
+
+    /// Get a node by its path below the root folder
+    pub async fn get_node_by_path(&self, path: &str) -> Result<Option<NodeData>> {
+        let mut current_id = self.client.get_root_id();
+        let mut current = None;
+
+        for part in path.split('/').filter(|s| !s.is_empty()) {
+            let children = self.list_children(&current_id).await?;
+
+            match children
+                .into_iter()
+                .find(|n| PathUtils::same_name(&n.name, part))
+            {
+                Some(node) => {
+                    current_id = node.uid.clone();
+                    current = Some(node);
+                }
+                None => return Ok(None),
+            }
+        }
+
+        Ok(current)
+    }
+
+    /// Resolve the current path of a node by walking up its parents
+    ///
+    /// Returns `Ok(None)` if the node or one of its ancestors no longer exists.
+    pub async fn resolve_node_path(&self, uid: &str) -> Result<Option<String>> {
+        let root_id = self.client.get_root_id();
+        let mut names = Vec::new();
+        let mut current_id = uid.to_string();
+
+        while current_id != root_id {
+            if names.len() >= MAX_NODE_DEPTH {
+                return Err(Error::api(format!(
+                    "Node {} is nested too deeply to resolve",
+                    uid
+                )));
+            }
+
+            let node = match self.get_node(&current_id).await? {
+                Some(node) => node,
+                None => return Ok(None),
+            };
+
+            names.push(node.name);
+
+            match node.parent_uid {
+                Some(parent) if !parent.is_empty() => current_id = parent,
+                _ => break,
+            }
+        }
+
+        names.reverse();
+        Ok(Some(PathUtils::normalize(&format!("/{}", names.join("/")))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Session;
+    use tempfile::TempDir;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn node_json(uid: &str, parent: &str, name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "UID": uid,
+            "ParentLinkID": parent,
+            "Name": name,
+            "NodeType": "folder",
+            "State": 1,
+        })
+    }
+
+    fn session() -> Session {
+        Session {
+            uid: "test_uid".to_string(),
+            access_token: "test_token".to_string(),
+            refresh_token: "test_refresh".to_string(),
+            key_password: None,
+            primary_key: None,
+            expires_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lookups_served_from_index() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let client = ProtonClient::with_api_base(server.uri(), session());
+        let index = RemoteIndex::new(&client, &db);
+
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/nodes"))
+            .and(query_param("ParentLinkID", "root"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "Nodes": [node_json("docs", "root", "Documents")],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/nodes"))
+            .and(query_param("ParentLinkID", "docs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "Nodes": [node_json("work", "docs", "Work")],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        for _ in 0..2 {
+            let node = index
+                .get_node_by_path("/Documents/Work")
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(node.uid, "work");
+        }
+
+        assert_eq!(
+            index.resolve_node_path("work").await.unwrap().as_deref(),
+            Some("/Documents/Work")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_refresh_applies_events() {
+        let server = MockServer::start().await;
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let client = ProtonClient::with_api_base(server.uri(), session());
+        let index = RemoteIndex::new(&client, &db);
+
+        let docs = NodeData {
+            uid: "docs".to_string(),
+            parent_uid: Some("root".to_string()),
+            name: "Documents".to_string(),
+            node_type: "folder".to_string(),
+            media_type: None,
+            active_revision: None,
+        };
+        db.set_remote_children("root", &[docs]).await.unwrap();
+        db.set_remote_event_cursor("1").await.unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/drive/v2/events/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Code": 1000,
+                "EventID": "2",
+                "More": 0,
+                "Events": [
+                    {"EventType": 0, "NodeUID": "docs"},
+                    {"EventType": 1, "NodeUID": "pics", "Node": node_json("pics", "root", "Pictures")},
+                ],
+            })))
+            .mount(&server)
+            .await;
+
+        assert_eq!(index.refresh().await.unwrap(), 2);
+        assert_eq!(
+            db.get_remote_event_cursor().await.unwrap().as_deref(),
+            Some("2")
+        );
+
+        let names: Vec<String> = index
+            .list_children("root")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|n| n.name)
+            .collect();
+        assert_eq!(names, vec!["Pictures".to_string()]);
+    }
+}
@@ -0,0 +1,93 @@
+//! Tracking of each sync directory's remote root folder
+//!
+//! The node UID of every remote root is recorded the first time it is seen,
+//! so a root renamed or moved in the Proton web UI can be detected and
+//! re-linked instead of re-uploaded.
+
+use crate::db::Db;
+use crate::error::Result;
+use crate::proton::PathUtils;
+use crate::remote_index::RemoteIndex;
+use crate::types::SyncDir;
+use tracing::debug;
+
+/// State of a sync directory's remote root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RootStatus {
+    /// The recorded root still resolves to the configured path
+    Linked,
+    /// No remote folder exists at the configured path yet
+    Unbound,
+    /// The recorded root was renamed or moved
+    Moved {
+        node_uid: String,
+        current_path: String,
+    },
+    /// The recorded root no longer exists
+    Missing,
+}
+
+/// Check whether a sync directory's remote root is still where the config expects
+///
+/// Records the root's node UID when it is first found, or when the
+/// configured remote root has been changed on purpose. A missing root is
+/// forgotten so it can be bound again once recreated.
+pub async fn check_remote_root(
+    index: &RemoteIndex<'_>,
+    db: &Db,
+    sync_dir: &SyncDir,
+) -> Result<RootStatus> {
+    let recorded = db.get_remote_root(&sync_dir.source_path).await?;
+
+    if let Some((recorded_root, node_uid)) = recorded {
+        // Only a binding made for the configured path can have "moved"
+        if same_remote_path(&recorded_root, &sync_dir.remote_root) {
+            return match index.resolve_node_path(&node_uid).await? {
+                None => {
+                    db.clear_remote_root(&sync_dir.source_path).await?;
+                    Ok(RootStatus::Missing)
+                }
+                Some(path) if same_remote_path(&path, &sync_dir.remote_root) => {
+                    Ok(RootStatus::Linked)
+                }
+                Some(current_path) => Ok(RootStatus::Moved {
+                    node_uid,
+                    current_path,
+                }),
+            };
+        }
+
+        debug!(
+            "Remote root of {} changed from {} to {}, rebinding",
+            sync_dir.source_path, recorded_root, sync_dir.remote_root
+        );
+    }
+
+    match index.get_node_by_path(&sync_dir.remote_root).await? {
+        Some(node) => {
+            db.set_remote_root(&sync_dir.source_path, &sync_dir.remote_root, &node.uid)
+                .await?;
+            Ok(RootStatus::Linked)
+        }
+        None => Ok(RootStatus::Unbound),
+    }
+}
+
+/// Compare two remote paths, ignoring redundant slashes and Unicode normalization
+pub fn same_remote_path(a: &str, b: &str) -> bool {
+    let normalize = |p: &str| crate::paths::nfc(PathUtils::normalize(p).trim_end_matches('/'));
+    normalize(a) == normalize(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_remote_path() {
+        assert!(same_remote_path("/My Files/Docs", "/My Files/Docs"));
+        assert!(same_remote_path("My Files/Docs/", "/My Files/Docs"));
+        assert!(same_remote_path("/", ""));
+        assert!(!same_remote_path("/My Files/Docs", "/My Files/Documents"));
+    }
+}
@@ -0,0 +1,248 @@
+//! Privilege dropping and filesystem sandboxing for the daemon
+//!
+//! A daemon started as root can switch to an unprivileged user before doing
+//! any work, and on Linux confine itself with Landlock so that it can only
+//! read its sync directories and write its own data, config, cache and log
+//! directories and the sync directories' trash. Both must happen before the async runtime starts: Landlock
+//! only restricts the calling thread and threads created after it.
+
+use crate::config::ConfigManager;
+use crate::error::{Error, Result};
+use crate::paths::{get_cache_dir, get_data_dir, get_log_dir};
+use crate::types::Config;
+use std::path::{Path, PathBuf};
+
+/// System locations the daemon reads: libraries, certificates, DNS
+/// configuration, and the devices and kernel interfaces it uses
+const SYSTEM_READ_PATHS: &[&str] = &[
+    "/usr", "/lib", "/lib64", "/etc", "/proc", "/sys", "/dev", "/run",
+];
+
+/// Environment variables that would keep pointing at the old user's directories
+const USER_DIR_VARS: &[&str] = &[
+    "XDG_CONFIG_HOME",
+    "XDG_DATA_HOME",
+    "XDG_STATE_HOME",
+    "XDG_CACHE_HOME",
+    "XDG_RUNTIME_DIR",
+];
+
+/// How much of the sandbox the kernel enforces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxStatus {
+    FullyEnforced,
+    /// The kernel supports only some of the restrictions
+    PartiallyEnforced,
+    /// The kernel or platform has no Landlock support
+    NotEnforced,
+}
+
+/// Paths the sandbox leaves accessible
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxPaths {
+    /// Directories the daemon may read and write
+    pub read_write: Vec<PathBuf>,
+    /// Directories the daemon may only read
+    pub read_only: Vec<PathBuf>,
+}
+
+impl SandboxPaths {
+    /// Paths needed to sync the configured directories
+    pub fn for_config(config: &Config) -> Result<Self> {
+        let config_path = ConfigManager::config_path()?;
+
+        let mut read_write = vec![
+            get_data_dir()?,
+            get_cache_dir()?,
+            get_log_dir()?,
+            std::env::temp_dir(),
+        ];
+        if let Some(config_dir) = config_path.parent() {
+            read_write.push(config_dir.to_path_buf());
+        }
+        // Deleted files are moved to and purged from each sync dir's trash;
+        // skip missing sync dirs so the sandbox doesn't create them
+        read_write.extend(
+            config
+                .sync_dirs
+                .iter()
+                .map(|d| Path::new(&d.source_path))
+                .filter(|source| source.is_dir())
+                .map(crate::trash::trash_dir),
+        );
+
+        let mut read_only: Vec<PathBuf> = config
+            .sync_dirs
+            .iter()
+            .map(|d| PathBuf::from(&d.source_path))
+            .collect();
+        read_only.extend(SYSTEM_READ_PATHS.iter().map(PathBuf::from));
+        // Refreshed tokens are encrypted with it again
+        read_only.extend(config.credential_key_file.iter().map(PathBuf::from));
+
+        Ok(Self {
+            read_write,
+            read_only,
+        })
+    }
+}
+
+/// Switch the process to an unprivileged user
+///
+/// Sets the user's groups, group and user ID, then points `HOME` at the
+/// user's home directory so data and config paths resolve to theirs.
+#[cfg(unix)]
+pub fn drop_privileges(user: &str) -> Result<()> {
+    use std::ffi::{CStr, CString};
+
+    let name =
+        CString::new(user).map_err(|_| Error::Sandbox(format!("Invalid user name: {}", user)))?;
+
+    // SAFETY: getpwnam returns a pointer into static storage, read before any
+    // other passwd lookup can overwrite it
+    let (uid, gid, home) = unsafe {
+        let entry = libc::getpwnam(name.as_ptr());
+        if entry.is_null() {
+            return Err(Error::Sandbox(format!("Unknown user: {}", user)));
+        }
+        let home = CStr::from_ptr((*entry).pw_dir)
+            .to_string_lossy()
+            .into_owned();
+        ((*entry).pw_uid, (*entry).pw_gid, home)
+    };
+
+    // SAFETY: plain system calls with values from the passwd entry; the group
+    // list and group must be set while still privileged
+    let failed = unsafe {
+        libc::initgroups(name.as_ptr(), gid as _) != 0
+            || libc::setgid(gid) != 0
+            || libc::setuid(uid) != 0
+    };
+    if failed {
+        return Err(Error::Sandbox(format!(
+            "Failed to switch to user {}: {}",
+            user,
+            std::io::Error::last_os_error()
+        )));
+    }
+
+    // Make sure root can't be regained
+    // SAFETY: setuid has no memory safety requirements
+    if uid != 0 && unsafe { libc::setuid(0) } == 0 {
+        return Err(Error::Sandbox(format!(
+            "Privileges could not be dropped permanently for user {}",
+            user
+        )));
+    }
+
+    std::env::set_var("HOME", home);
+    for var in USER_DIR_VARS {
+        std::env::remove_var(var);
+    }
+
+    Ok(())
+}
+
+/// Switch the process to an unprivileged user
+#[cfg(not(unix))]
+pub fn drop_privileges(_user: &str) -> Result<()> {
+    Err(Error::Sandbox(
+        "Dropping privileges is only supported on Unix".to_string(),
+    ))
+}
+
+/// Confine the process to the given paths
+///
+/// Directories are created first so that rules can be attached to them;
+/// paths that don't exist are left inaccessible.
+#[cfg(target_os = "linux")]
+pub fn apply_sandbox(paths: &SandboxPaths) -> Result<SandboxStatus> {
+    use landlock::{
+        path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr,
+        RulesetStatus, ABI,
+    };
+
+    for dir in &paths.read_write {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let abi = ABI::V2;
+    let sandbox_error = |e: landlock::RulesetError| Error::Sandbox(e.to_string());
+
+    let status = Ruleset::default()
+        .handle_access(AccessFs::from_all(abi))
+        .map_err(sandbox_error)?
+        .create()
+        .map_err(sandbox_error)?
+        .add_rules(path_beneath_rules(
+            &paths.read_write,
+            AccessFs::from_all(abi),
+        ))
+        .map_err(sandbox_error)?
+        .add_rules(path_beneath_rules(
+            &paths.read_only,
+            AccessFs::from_read(abi),
+        ))
+        .map_err(sandbox_error)?
+        .restrict_self()
+        .map_err(sandbox_error)?;
+
+    Ok(match status.ruleset {
+        RulesetStatus::FullyEnforced => SandboxStatus::FullyEnforced,
+        RulesetStatus::PartiallyEnforced => SandboxStatus::PartiallyEnforced,
+        RulesetStatus::NotEnforced => SandboxStatus::NotEnforced,
+    })
+}
+
+/// Confine the process to the given paths
+#[cfg(not(target_os = "linux"))]
+pub fn apply_sandbox(_paths: &SandboxPaths) -> Result<SandboxStatus> {
+    Ok(SandboxStatus::NotEnforced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SyncDir;
+
+    #[test]
+    fn test_sync_dirs_are_read_only() {
+        let mut config = Config::default();
+        config.sync_dirs.push(SyncDir {
+            source_path: "/home/user/Documents".to_string(),
+            remote_root: "/Documents".to_string(),
+            case_insensitive: None,
+        });
+
+        let paths = SandboxPaths::for_config(&config).unwrap();
+        let documents = PathBuf::from("/home/user/Documents");
+
+        assert!(paths.read_only.contains(&documents));
+        assert!(!paths.read_write.contains(&documents));
+        assert!(paths.read_write.contains(&get_data_dir().unwrap()));
+    }
+
+    #[test]
+    fn test_trash_dirs_are_writable() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = Config::default();
+        for source in [temp_dir.path(), Path::new("/nonexistent/Documents")] {
+            config.sync_dirs.push(SyncDir {
+                source_path: source.to_string_lossy().into_owned(),
+                remote_root: "/Documents".to_string(),
+                case_insensitive: None,
+            });
+        }
+
+        let paths = SandboxPaths::for_config(&config).unwrap();
+
+        assert!(paths
+            .read_write
+            .contains(&crate::trash::trash_dir(temp_dir.path())));
+        assert!(paths.read_only.contains(&temp_dir.path().to_path_buf()));
+        assert!(!paths
+            .read_write
+            .iter()
+            .any(|p| p.starts_with("/nonexistent")));
+    }
+}
@@ -0,0 +1,187 @@
+//! Transfer statistics
+//!
+//! Completed transfers in the sync history are rolled up into daily totals
+//! per sync directory, so they outlive `"history_retention_days"`. A cursor
+//! records the last history entry included, and each entry is counted once
+//! even when the daemon and a CLI command aggregate at the same time.
+//! Transfers of paths no longer inside a sync directory are left out.
+
+use crate::db::Db;
+use crate::error::Result;
+use crate::types::{HistoryResult, SyncDir, SyncDirection, TransferStats};
+use chrono::{Local, NaiveDate};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// History entries read per aggregation step
+const AGGREGATE_BATCH_SIZE: i64 = 1000;
+
+/// Roll new sync history into the transfer statistics
+///
+/// Returns the number of history entries looked at.
+pub async fn aggregate(db: &Db, sync_dirs: &[SyncDir]) -> Result<usize> {
+    let mut aggregated = 0;
+
+    loop {
+        let from_id = db.get_transfer_stats_cursor().await?;
+        let entries = db.get_history_after(from_id, AGGREGATE_BATCH_SIZE).await?;
+        let Some(to_id) = entries.last().map(|e| e.id) else {
+            return Ok(aggregated);
+        };
+
+        let mut totals: BTreeMap<(NaiveDate, String, SyncDirection), (i64, i64)> = BTreeMap::new();
+        for entry in &entries {
+            let Some(bytes) = entry.bytes else {
+                continue;
+            };
+            if entry.result != HistoryResult::Synced {
+                continue;
+            }
+            let Some(sync_dir) = sync_dir_of(&entry.local_path, sync_dirs) else {
+                continue;
+            };
+
+            let day = entry.completed_at.with_timezone(&Local).date_naive();
+            let total = totals
+                .entry((day, sync_dir.to_string(), entry.direction))
+                .or_default();
+            total.0 += 1;
+            total.1 += bytes;
+        }
+
+        let stats: Vec<TransferStats> = totals
+            .into_iter()
+            .map(
+                |((day, sync_dir, direction), (files, bytes))| TransferStats {
+                    day,
+                    sync_dir,
+                    direction,
+                    files,
+                    bytes,
+                },
+            )
+            .collect();
+
+        if !db.add_transfer_stats(&stats, from_id, to_id).await? {
+            // Another process got there first; carry on from its cursor
+            continue;
+        }
+        aggregated += entries.len();
+    }
+}
+
+/// Source path of the innermost sync directory containing a local path
+fn sync_dir_of<'a>(local_path: &str, sync_dirs: &'a [SyncDir]) -> Option<&'a str> {
+    sync_dirs
+        .iter()
+        .map(|d| d.source_path.as_str())
+        .filter(|source| Path::new(local_path).starts_with(source))
+        .max_by_key(|source| source.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{HistoryEntry, SyncEventType};
+    use chrono::{Datelike, TimeZone, Utc};
+    use tempfile::TempDir;
+
+    fn entry(
+        local_path: &str,
+        bytes: Option<i64>,
+        result: HistoryResult,
+        day: u32,
+    ) -> HistoryEntry {
+        HistoryEntry {
+            id: 0,
+            event_type: SyncEventType::CreateFile,
+            direction: SyncDirection::Upload,
+            local_path: local_path.to_string(),
+            remote_path: "/remote".to_string(),
+            old_local_path: None,
+            bytes,
+            duration_ms: 10,
+            result,
+            error: None,
+            completed_at: Local
+                .with_ymd_and_hms(2026, 10, day, 12, 0, 0)
+                .unwrap()
+                .with_timezone(&Utc),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_counts_each_transfer_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let sync_dirs = vec![
+            SyncDir {
+                source_path: "/home/docs".to_string(),
+                remote_root: "/Docs".to_string(),
+                case_insensitive: None,
+            },
+            SyncDir {
+                source_path: "/home/photos".to_string(),
+                remote_root: "/Photos".to_string(),
+                case_insensitive: None,
+            },
+        ];
+
+        for e in [
+            entry("/home/docs/a.txt", Some(100), HistoryResult::Synced, 14),
+            entry("/home/docs/b.txt", Some(50), HistoryResult::Synced, 14),
+            entry("/home/docs/c.txt", Some(70), HistoryResult::Synced, 15),
+            entry("/home/photos/d.jpg", Some(900), HistoryResult::Synced, 15),
+            // Failed, content-less and unsynced paths are not transfers
+            entry("/home/docs/e.txt", Some(30), HistoryResult::Blocked, 15),
+            entry("/home/docs/folder", None, HistoryResult::Synced, 15),
+            entry(
+                "/home/docsarchive/f.txt",
+                Some(10),
+                HistoryResult::Synced,
+                15,
+            ),
+        ] {
+            db.record_history(&e).await.unwrap();
+        }
+
+        assert_eq!(aggregate(&db, &sync_dirs).await.unwrap(), 7);
+        assert_eq!(aggregate(&db, &sync_dirs).await.unwrap(), 0);
+
+        let since = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+        let totals: Vec<(u32, String, i64, i64)> = db
+            .get_transfer_stats(since)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.day.day(), s.sync_dir, s.files, s.bytes))
+            .collect();
+        assert_eq!(
+            totals,
+            [
+                (14, "/home/docs".to_string(), 2, 150),
+                (15, "/home/docs".to_string(), 1, 70),
+                (15, "/home/photos".to_string(), 1, 900),
+            ]
+        );
+
+        // Later transfers add to the same day
+        db.record_history(&entry(
+            "/home/docs/g.txt",
+            Some(5),
+            HistoryResult::Synced,
+            15,
+        ))
+        .await
+        .unwrap();
+        assert_eq!(aggregate(&db, &sync_dirs).await.unwrap(), 1);
+        let docs_15 = db
+            .get_transfer_stats(NaiveDate::from_ymd_opt(2026, 10, 15).unwrap())
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|s| s.sync_dir == "/home/docs")
+            .unwrap();
+        assert_eq!((docs_15.files, docs_15.bytes), (2, 75));
+    }
+}
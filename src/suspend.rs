@@ -0,0 +1,241 @@
+//! Detection of system suspend and resume
+//!
+//! A laptop resuming from sleep can be left with a file watcher backend that
+//! no longer delivers events. On Linux, logind announces sleep and wake-up
+//! with its `PrepareForSleep` DBus signal, which a `gdbus monitor` child
+//! process follows. Without it, suspend is detected by comparing clocks: the
+//! wall clock keeps counting while the system sleeps, but the monotonic clock
+//! behind `Instant` does not on Linux and macOS. A large gap between the two
+//! means the system was asleep. Manual clock changes look the same and cause
+//! a harmless extra rescan.
+
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
+#[cfg(target_os = "linux")]
+use tracing::debug;
+use walkdir::WalkDir;
+
+/// How often the clocks are compared
+pub const SUSPEND_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Wall-clock time unaccounted for by the monotonic clock that counts as a suspend
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Notices when the system was suspended between two checks
+pub struct SuspendDetector {
+    last_wall: SystemTime,
+    last_monotonic: Instant,
+}
+
+impl Default for SuspendDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SuspendDetector {
+    /// Create a detector starting from now
+    pub fn new() -> Self {
+        Self {
+            last_wall: SystemTime::now(),
+            last_monotonic: Instant::now(),
+        }
+    }
+
+    /// Check whether the system slept since the last check
+    ///
+    /// Returns roughly how long it was asleep.
+    pub fn check(&mut self) -> Option<Duration> {
+        self.check_at(SystemTime::now(), Instant::now())
+    }
+
+    fn check_at(&mut self, wall: SystemTime, monotonic: Instant) -> Option<Duration> {
+        let wall_elapsed = wall.duration_since(self.last_wall).unwrap_or_default();
+        let monotonic_elapsed = monotonic.duration_since(self.last_monotonic);
+
+        self.last_wall = wall;
+        self.last_monotonic = monotonic;
+
+        let asleep = wall_elapsed.saturating_sub(monotonic_elapsed);
+        (asleep >= SUSPEND_THRESHOLD).then_some(asleep)
+    }
+}
+
+/// A sleep transition announced by logind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepSignal {
+    /// The system is about to sleep
+    Suspending,
+    /// The system woke up
+    Resumed,
+}
+
+impl SleepSignal {
+    /// Parse a line of `gdbus monitor` output
+    ///
+    /// Signals look like
+    /// `/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (false,)`.
+    pub fn parse(line: &str) -> Option<Self> {
+        let (_, args) = line.split_once("org.freedesktop.login1.Manager.PrepareForSleep ")?;
+        match args.trim() {
+            "(true,)" => Some(Self::Suspending),
+            "(false,)" => Some(Self::Resumed),
+            _ => None,
+        }
+    }
+}
+
+/// Follow logind's sleep signals
+///
+/// Returns `None` when `gdbus` can't be started. The channel closes when the
+/// monitor exits, e.g. without a system bus; callers then fall back to
+/// [`SuspendDetector`].
+#[cfg(target_os = "linux")]
+pub fn watch_logind() -> Option<mpsc::Receiver<SleepSignal>> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let spawned = tokio::process::Command::new("gdbus")
+        .args([
+            "monitor",
+            "--system",
+            "--dest",
+            "org.freedesktop.login1",
+            "--object-path",
+            "/org/freedesktop/login1",
+        ])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .spawn();
+
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            debug!("Cannot follow logind sleep signals: {}", e);
+            return None;
+        }
+    };
+    let stdout = child.stdout.take()?;
+    let (tx, rx) = mpsc::channel(4);
+
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stdout).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if let Some(signal) = SleepSignal::parse(&line) {
+                if tx.send(signal).await.is_err() {
+                    break;
+                }
+            }
+        }
+        debug!("Stopped following logind sleep signals");
+        drop(child);
+    });
+
+    Some(rx)
+}
+
+/// Follow logind's sleep signals
+#[cfg(not(target_os = "linux"))]
+pub fn watch_logind() -> Option<mpsc::Receiver<SleepSignal>> {
+    None
+}
+
+/// Check whether anything under `root` was modified, created, removed or
+/// renamed since `since`
+///
+/// Removing or renaming an entry updates its directory's modification time,
+/// and renaming updates the entry's change time, so a tree nobody touched
+/// has nothing newer than `since`. Stops at the first change found.
+pub fn changed_since(root: &Path, since: SystemTime) -> bool {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .any(|metadata| {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                let ctime = SystemTime::UNIX_EPOCH
+                    + Duration::new(metadata.ctime().max(0) as u64, metadata.ctime_nsec() as u32);
+                if ctime >= since {
+                    return true;
+                }
+            }
+            metadata.modified().is_ok_and(|mtime| mtime >= since)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_wall_clock_gap() {
+        let wall = SystemTime::now();
+        let monotonic = Instant::now();
+        let mut detector = SuspendDetector {
+            last_wall: wall,
+            last_monotonic: monotonic,
+        };
+
+        let tick = Duration::from_secs(10);
+        assert_eq!(detector.check_at(wall + tick, monotonic + tick), None);
+
+        // An hour passed on the wall clock during a 10s monotonic tick
+        let asleep = Duration::from_secs(3600);
+        assert_eq!(
+            detector.check_at(wall + 2 * tick + asleep, monotonic + 2 * tick),
+            Some(asleep)
+        );
+
+        // A wall clock set backwards is not a suspend
+        assert_eq!(detector.check_at(wall, monotonic + 3 * tick), None);
+    }
+
+    #[test]
+    fn test_parse_sleep_signal() {
+        assert_eq!(
+            SleepSignal::parse(
+                "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (true,)"
+            ),
+            Some(SleepSignal::Suspending)
+        );
+        assert_eq!(
+            SleepSignal::parse(
+                "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (false,)"
+            ),
+            Some(SleepSignal::Resumed)
+        );
+        assert_eq!(
+            SleepSignal::parse(
+                "/org/freedesktop/login1: org.freedesktop.login1.Manager.SessionNew ('3', objectpath '/org/freedesktop/login1/session/_33')"
+            ),
+            None
+        );
+        assert_eq!(
+            SleepSignal::parse("The name org.freedesktop.login1 is owned by :1.3"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_changed_since() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let nested = root.join("a").join("b");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(nested.join("old.txt"), "old").unwrap();
+
+        // File system timestamps can lag the clock by a tick
+        std::thread::sleep(Duration::from_millis(50));
+        let asleep_at = SystemTime::now();
+        assert!(!changed_since(root, asleep_at));
+
+        std::thread::sleep(Duration::from_millis(50));
+        std::fs::remove_file(nested.join("old.txt")).unwrap();
+        assert!(changed_since(root, asleep_at));
+        assert!(!changed_since(&root.join("missing"), asleep_at));
+    }
+}
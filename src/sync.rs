@@ -1,53 +1,228 @@
 //! Sync engine
 
 use crate::config::ConfigManager;
+use crate::connectivity::ConnectivityMonitor;
+use crate::credentials::SessionStore;
 use crate::db::Db;
 use crate::error::Result;
+use crate::instance::InstanceLock;
+use crate::paths::get_data_dir;
 use crate::processor::JobProcessor;
-use crate::proton::ProtonClient;
+use crate::proton::{ApiContact, ProtonClient, SESSION_CHECK_INTERVAL, SESSION_REFRESH_MARGIN};
 use crate::queue::JobQueue;
-use crate::types::Session;
+use crate::remote_index::{RemoteIndex, REMOTE_EVENTS_INTERVAL};
+use crate::remote_root::{check_remote_root, RootStatus};
+use crate::suspend::{watch_logind, SleepSignal, SuspendDetector, SUSPEND_CHECK_INTERVAL};
+use crate::types::{HistoryEntry, NodeMapping, SkipReason, SyncDir, SyncJob, SyncJobStatus};
 use crate::watcher::FileWatcher;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::Mutex;
-use tokio::time::interval;
-use tracing::{debug, error, info};
+use std::time::{Duration, SystemTime};
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, interval_at, Instant, Interval};
+use tracing::{debug, error, info, warn};
+
+/// Skipped files named in the periodic skip report; the rest are counted
+const SKIP_REPORT_PATHS: i64 = 5;
+
+/// How often the running daemon records a heartbeat
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How often the reconciliation schedule is checked for a due scan
+const RECONCILE_CRON_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How much earlier than the system went to sleep changes are looked for
+const RESUME_SCAN_MARGIN: Duration = Duration::from_secs(2);
 
 /// Sync engine state
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SyncState {
     Idle,
     Running,
     Paused,
+    /// The Proton API is unreachable; processing resumes when it is back
+    Offline,
     Error,
 }
 
+/// Intervals and batch sizes of the daemon's background work
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    /// How often the queue is polled for pending jobs
+    pub job_poll: Duration,
+    /// Pending jobs fetched per poll
+    pub job_batch: i64,
+    /// How often local directories are rescanned
+    pub reconcile: Duration,
+    /// How often the config file is checked for changes
+    pub config_reload: Duration,
+    /// How often remote changes are pulled into the remote index
+    pub remote_events: Duration,
+    /// How often network connectivity is checked
+    pub connectivity: Duration,
+}
+
+impl Schedule {
+    /// Default schedule
+    pub const NORMAL: Self = Self {
+        job_poll: Duration::from_secs(1),
+        job_batch: 10,
+        reconcile: Duration::from_secs(5 * 60),
+        config_reload: Duration::from_secs(30),
+        remote_events: REMOTE_EVENTS_INTERVAL,
+        connectivity: Duration::from_secs(15),
+    };
+
+    /// Schedule for low-power mode: fewer wakeups, larger batches
+    pub const LOW_POWER: Self = Self {
+        job_poll: Duration::from_secs(5),
+        job_batch: 50,
+        reconcile: Duration::from_secs(30 * 60),
+        config_reload: Duration::from_secs(2 * 60),
+        remote_events: Duration::from_secs(5 * 60),
+        connectivity: Duration::from_secs(60),
+    };
+
+    /// Schedule for a config: the normal or low-power schedule, with any
+    /// intervals set under `timing` in place of its own
+    pub fn for_config(config: &crate::types::Config) -> Self {
+        let base = if config.low_power {
+            Self::LOW_POWER
+        } else {
+            Self::NORMAL
+        };
+        let timing = &config.timing;
+
+        Self {
+            job_poll: timing.job_poll.map_or(base.job_poll, |d| d.0),
+            reconcile: timing.reconcile.map_or(base.reconcile, |d| d.0),
+            config_reload: timing.config_reload.map_or(base.config_reload, |d| d.0),
+            ..base
+        }
+    }
+}
+
+/// Interval timer that follows schedule changes
+///
+/// A changed period takes effect right away, counted from the change.
+struct ScheduleTicker {
+    schedule: watch::Receiver<Schedule>,
+    period: fn(&Schedule) -> Duration,
+    interval: Interval,
+}
+
+impl ScheduleTicker {
+    /// Ticker whose first tick is one period from now
+    fn new(schedule: watch::Receiver<Schedule>, period: fn(&Schedule) -> Duration) -> Self {
+        let first = period(&schedule.borrow());
+        Self::starting_at(schedule, period, Instant::now() + first)
+    }
+
+    /// Ticker whose first tick is right away
+    fn immediate(schedule: watch::Receiver<Schedule>, period: fn(&Schedule) -> Duration) -> Self {
+        Self::starting_at(schedule, period, Instant::now())
+    }
+
+    fn starting_at(
+        schedule: watch::Receiver<Schedule>,
+        period: fn(&Schedule) -> Duration,
+        start: Instant,
+    ) -> Self {
+        let interval = interval_at(start, period(&schedule.borrow()));
+        Self {
+            schedule,
+            period,
+            interval,
+        }
+    }
+
+    /// Wait for the next tick
+    async fn tick(&mut self) {
+        loop {
+            tokio::select! {
+                _ = self.interval.tick() => return,
+                changed = self.schedule.changed() => {
+                    if changed.is_err() {
+                        // The engine is gone; keep the current period
+                        self.interval.tick().await;
+                        return;
+                    }
+
+                    let period = (self.period)(&self.schedule.borrow_and_update());
+                    if period != self.interval.period() {
+                        self.interval = interval_at(Instant::now() + period, period);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Schedule currently in effect
+    fn schedule(&self) -> Schedule {
+        *self.schedule.borrow()
+    }
+}
+
 /// Sync engine
 pub struct SyncEngine {
     db: Db,
     config: Arc<Mutex<ConfigManager>>,
-    #[allow(dead_code)]
-    session: Session,
+    /// Session shared by the engine's clients
+    sessions: SessionStore,
     state: Arc<Mutex<SyncState>>,
     watcher: Arc<Mutex<FileWatcher>>,
-    processor: Arc<Mutex<JobProcessor>>,
+    processor: Arc<JobProcessor>,
     queue: JobQueue,
+    /// Sync directories whose remote root was moved and needs re-linking
+    moved_roots: Arc<Mutex<Vec<String>>>,
+    /// Background work intervals, updated when the config is reloaded
+    schedule: Arc<watch::Sender<Schedule>>,
+    /// Keeps a second engine from working on the same queue
+    instance_lock: std::sync::Mutex<Option<InstanceLock>>,
+    lock_path: PathBuf,
+    /// Last successful response from the Proton API, for `status`
+    api_contact: ApiContact,
+    /// Background tasks that must not outlive `stop`
+    tasks: std::sync::Mutex<Vec<JoinHandle<()>>>,
 }
 
 impl SyncEngine {
     /// Create a new sync engine
-    pub async fn new(db: Db, config: Arc<Mutex<ConfigManager>>, session: Session) -> Result<Self> {
+    ///
+    /// Refreshed sessions are saved to the backend of `sessions`.
+    pub async fn new(
+        db: Db,
+        config: Arc<Mutex<ConfigManager>>,
+        sessions: SessionStore,
+    ) -> Result<Self> {
         let cfg = config.lock().await;
-        let client = ProtonClient::new(session.clone());
+        let api_contact = ApiContact::default();
+        let client =
+            ProtonClient::with_sessions(sessions.clone()).with_contact(api_contact.clone());
         let processor = JobProcessor::new(
             db.clone(),
             client,
-            cfg.get().sync_concurrency,
+            Self::effective_concurrency(cfg.get().sync_concurrency),
             cfg.get().remote_delete_behavior,
-        );
+            cfg.get().verify_uploads,
+            cfg.get().max_upload_memory,
+            cfg.get().retry.clone(),
+        )
+        .with_mime_overrides(&cfg.get().mime_overrides)
+        .with_conflict_policy(cfg.get().conflict_policy)
+        .with_small_file_threshold(cfg.get().small_file_threshold)
+        .with_change_detection(cfg.get().effective_change_detection());
 
         let watcher = FileWatcher::new(db.clone(), config.clone())?;
+        let schedule = Schedule::for_config(cfg.get());
+        if cfg.get().low_power {
+            info!("Low-power mode: polling less often and skipping content hashing");
+        }
 
         let queue = JobQueue::new(db.clone());
 
@@ -56,25 +231,86 @@ impl SyncEngine {
         Ok(Self {
             db,
             config,
-            session,
+            sessions,
             state: Arc::new(Mutex::new(SyncState::Idle)),
             watcher: Arc::new(Mutex::new(watcher)),
-            processor: Arc::new(Mutex::new(processor)),
+            processor: Arc::new(processor),
             queue,
+            moved_roots: Arc::new(Mutex::new(Vec::new())),
+            schedule: Arc::new(watch::Sender::new(schedule)),
+            instance_lock: std::sync::Mutex::new(None),
+            lock_path: get_data_dir()?.join("engine.lock"),
+            api_contact,
+            tasks: std::sync::Mutex::new(Vec::new()),
         })
     }
 
+    /// Make sure no other engine runs, until this one stops
+    ///
+    /// Fails with the other process's ID if one does. Holding the lock
+    /// already is fine.
+    pub fn lock_instance(&self) -> Result<()> {
+        let mut lock = self.instance_lock.lock().unwrap();
+        if lock.is_none() {
+            *lock = Some(InstanceLock::acquire(&self.lock_path)?);
+        }
+        Ok(())
+    }
+
+    /// Raise the open file limit and cap upload concurrency to fit it
+    fn effective_concurrency(requested: usize) -> usize {
+        let Some(limits) = crate::limits::raise_fd_limit() else {
+            return requested;
+        };
+
+        info!(
+            "Open file limit: {} (hard: {})",
+            limits.soft,
+            limits
+                .hard
+                .map_or_else(|| "unlimited".to_string(), |h| h.to_string())
+        );
+
+        let capped = limits.cap_concurrency(requested);
+        if capped < requested {
+            warn!(
+                "Open file limit of {} only allows {} concurrent uploads, lowering sync_concurrency from {}",
+                limits.soft, capped, requested
+            );
+        }
+
+        capped
+    }
+
     /// Start the sync engine
     pub async fn start(&self) -> Result<()> {
         let mut state = self.state.lock().await;
         if *state == SyncState::Running {
             return Ok(());
         }
+        self.lock_instance()?;
         *state = SyncState::Running;
         drop(state);
 
         info!("Starting sync engine");
 
+        // Progress of uploads interrupted by a previous run is meaningless now
+        self.db.clear_all_job_progress().await?;
+        self.db.requeue_claimed_jobs().await?;
+
+        // Time each new sync directory's initial upload from now
+        for sync_dir in &self.config.lock().await.get().sync_dirs {
+            self.db.begin_first_sync(&sync_dir.source_path).await?;
+        }
+
+        // Settle folder creations a previous run did not finish
+        if let Err(e) = self.processor.recover_folder_creations().await {
+            warn!("Could not recover interrupted folder creations: {}", e);
+        }
+
+        // Detect remote roots renamed or moved while we were not running
+        Self::check_remote_roots(&self.db, &self.config, &self.sessions, &self.moved_roots).await;
+
         // Start file watcher
         let mut watcher = self.watcher.lock().await;
         watcher.start().await?;
@@ -89,6 +325,36 @@ impl SyncEngine {
         // Start config reload task
         self.start_config_reload_task().await;
 
+        // Start opt-in usage metrics task
+        self.start_telemetry_task().await;
+
+        // Start remote index refresh task
+        self.start_remote_index_task().await;
+
+        // Start suspend/resume detection task
+        self.start_resume_task().await;
+
+        // Start network connectivity task
+        self.start_connectivity_task().await;
+
+        // Start first sync milestone task
+        self.start_first_sync_task().await;
+
+        // Start sleep inhibitor task
+        self.start_inhibit_task().await;
+
+        // Start session refresh task
+        self.start_session_refresh_task().await;
+
+        // Start heartbeat task
+        self.start_heartbeat_task().await;
+
+        // Start log cleanup task
+        self.start_log_cleanup_task().await;
+
+        // Log a digest of each day at midnight
+        tokio::spawn(crate::digest::run_daily_digest(self.db.clone()));
+
         // Set running flag
         self.db.set_flag("running").await?;
 
@@ -105,6 +371,10 @@ impl SyncEngine {
         *state = SyncState::Idle;
         drop(state);
 
+        for task in self.tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+
         // Stop file watcher
         let mut watcher = self.watcher.lock().await;
         watcher.stop().await?;
@@ -112,16 +382,25 @@ impl SyncEngine {
 
         // Clear running flag
         self.db.clear_flag("running").await?;
+        self.db.clear_heartbeat().await?;
+        self.instance_lock.lock().unwrap().take();
 
         info!("Sync engine stopped");
 
         Ok(())
     }
 
+    /// Refresh the session and save it, as asked by `auth status`
+    pub async fn refresh_session(&self) -> Result<()> {
+        ProtonClient::with_sessions(self.sessions.clone())
+            .refresh_session()
+            .await
+    }
+
     /// Pause the sync engine
     pub async fn pause(&self) -> Result<()> {
         let mut state = self.state.lock().await;
-        if *state != SyncState::Running {
+        if !matches!(*state, SyncState::Running | SyncState::Offline) {
             return Ok(());
         }
         *state = SyncState::Paused;
@@ -158,7 +437,7 @@ impl SyncEngine {
     /// Get status
     pub async fn get_status(&self) -> Result<SyncStatus> {
         let state = self.get_state().await;
-        let counts = self.queue.get_status_counts().await?;
+        let counts = self.queue.cached_status_counts().await?;
 
         Ok(SyncStatus {
             state,
@@ -175,13 +454,12 @@ impl SyncEngine {
         let processor = self.processor.clone();
         let state = self.state.clone();
         let _queue = self.queue.clone();
+        let moved_roots = self.moved_roots.clone();
+        let mut ticker = ScheduleTicker::new(self.schedule.subscribe(), |s| s.job_poll);
 
         tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(1));
-            interval.tick().await; // Skip first tick
-
             loop {
-                interval.tick().await;
+                ticker.tick().await;
 
                 // Check if still running
                 let current_state = *state.lock().await;
@@ -190,7 +468,7 @@ impl SyncEngine {
                 }
 
                 // Get pending jobs
-                let jobs = match db.get_pending_jobs(10).await {
+                let jobs = match db.get_pending_jobs(ticker.schedule().job_batch).await {
                     Ok(j) => j,
                     Err(e) => {
                         error!("Error getting pending jobs: {}", e);
@@ -202,29 +480,78 @@ impl SyncEngine {
                     continue;
                 }
 
-                // Process each job
-                let proc = processor.lock().await;
+                // Start each job; jobs for the same path run one at a time
+                let moved = moved_roots.lock().await.clone();
                 for job in jobs {
-                    if let Err(e) = proc.process_job(&job).await {
-                        error!("Error processing job {}: {}", job.id, e);
+                    if Self::block_if_moved(&db, &moved, &job).await {
+                        continue;
+                    }
+                    if let Err(e) = processor.spawn_job(job).await {
+                        error!("Error starting job: {}", e);
                     }
                 }
             }
         });
     }
 
+    /// Block a job under a sync directory whose remote root was moved
+    ///
+    /// Uploading it would recreate the old remote tree. Returns whether the
+    /// job was blocked.
+    async fn block_if_moved(db: &Db, moved_roots: &[String], job: &SyncJob) -> bool {
+        let Some(root) = moved_roots
+            .iter()
+            .find(|root| Path::new(&job.local_path).starts_with(root))
+        else {
+            return false;
+        };
+
+        let message = format!(
+            "Remote root of {} was moved; run `proton-drive-sync config relink-dir`",
+            root
+        );
+        if let Err(e) = db
+            .update_job_status(job.id, SyncJobStatus::Blocked, Some(&message))
+            .await
+        {
+            error!("Error blocking job {}: {}", job.id, e);
+        }
+        true
+    }
+
     /// Start periodic reconciliation task
+    ///
+    /// Scans run every reconcile interval, skipped while the queue is busy,
+    /// and whenever `timing.reconcile_cron` fires, busy or not.
     async fn start_reconciliation_task(&self) {
         let db = self.db.clone();
         let config = self.config.clone();
         let state = self.state.clone();
+        let mut ticker = ScheduleTicker::new(self.schedule.subscribe(), |s| s.reconcile);
+        let mut cron_check = interval(RECONCILE_CRON_CHECK_INTERVAL);
+        let mut cron_checked = chrono::Local::now();
 
         tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(5 * 60)); // Every 5 minutes
-            interval.tick().await; // Skip first tick
-
             loop {
-                interval.tick().await;
+                let scheduled = tokio::select! {
+                    _ = ticker.tick() => false,
+                    _ = cron_check.tick() => {
+                        let now = chrono::Local::now();
+                        let cfg = config.lock().await;
+                        let due = cfg
+                            .get()
+                            .timing
+                            .reconcile_cron
+                            .as_ref()
+                            .is_some_and(|cron| cron.fires_between(cron_checked, now));
+                        drop(cfg);
+                        cron_checked = now;
+                        if !due {
+                            continue;
+                        }
+                        true
+                    }
+                };
 
                 // Check if still running
                 let current_state = *state.lock().await;
@@ -232,6 +559,12 @@ impl SyncEngine {
                     continue;
                 }
 
+                Self::purge_trash(&config).await;
+                Self::aggregate_stats(&db, &config).await;
+                Self::prune_history(&db, &config).await;
+                Self::expire_expected_changes(&db).await;
+                Self::cleanup_completed_jobs(&db, &config).await;
+
                 // Skip if queue is busy
                 let counts = match JobQueue::new(db.clone()).get_status_counts().await {
                     Ok(c) => c,
@@ -241,85 +574,680 @@ impl SyncEngine {
                     }
                 };
 
-                if counts.pending > 100 {
+                let threshold = config.lock().await.get().timing.reconcile_busy_threshold();
+                if scheduled {
+                    info!("Running scheduled reconciliation");
+                } else if counts.pending > threshold as usize {
                     debug!("Skipping reconciliation: queue too busy");
                     continue;
                 }
 
-                // Scan each sync directory
-                let cfg = config.lock().await;
-                let sync_dirs = cfg.get().sync_dirs.clone();
-                let exclusions = cfg.get().exclude_patterns.clone();
-                drop(cfg);
-
-                for sync_dir in sync_dirs {
-                    if let Err(e) = crate::watcher::FileScanner::scan_directory(
-                        &db,
-                        &sync_dir.source_path,
-                        &sync_dir.remote_root,
-                        &exclusions,
-                    )
-                    .await
-                    {
-                        error!("Error scanning directory {}: {}", sync_dir.source_path, e);
+                Self::scan_sync_dirs(&db, &config).await;
+                let cfg = config.lock().await.get().clone();
+                if cfg.prune_remote {
+                    if let Err(e) = Self::prune_sync_dirs(&db, &cfg, false).await {
+                        error!("Error pruning deleted files: {}", e);
                     }
                 }
-
                 info!("Reconciliation scan complete");
+                Self::report_skipped(&db).await;
             }
         });
     }
 
-    /// Start config reload task
-    async fn start_config_reload_task(&self) {
+    /// Log the files set aside because they cannot be read
+    async fn report_skipped(db: &Db) {
+        let status = SyncJobStatus::Skipped(SkipReason::PermissionDenied);
+        let (total, skipped) = match tokio::try_join!(
+            db.get_job_count(status),
+            db.get_jobs_by_status(status, SKIP_REPORT_PATHS)
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                debug!("Error listing skipped files: {}", e);
+                return;
+            }
+        };
+        if total == 0 {
+            return;
+        }
+
+        let mut paths: Vec<String> = skipped.into_iter().map(|job| job.local_path).collect();
+        if total > SKIP_REPORT_PATHS {
+            paths.push(format!("and {} more", total - SKIP_REPORT_PATHS));
+        }
+        warn!(
+            "{} file(s) skipped because they cannot be read: {}. Run `proton-drive-sync jobs list --status skipped` for details",
+            total,
+            paths.join(", ")
+        );
+    }
+
+    /// Scan every sync directory for changes the watcher missed
+    async fn scan_sync_dirs(db: &Db, config: &Arc<Mutex<ConfigManager>>) {
+        let cfg = config.lock().await.get().clone();
+
+        for sync_dir in &cfg.sync_dirs {
+            Self::scan_sync_dir(db, &cfg, sync_dir).await;
+        }
+    }
+
+    /// Scan the sync directories with anything changed since `since`
+    ///
+    /// Used after a resume, when only the trees touched while the watcher
+    /// was not delivering events can have missed changes.
+    async fn scan_changed_sync_dirs(
+        db: &Db,
+        config: &Arc<Mutex<ConfigManager>>,
+        since: SystemTime,
+    ) {
+        let cfg = config.lock().await.get().clone();
+
+        for sync_dir in &cfg.sync_dirs {
+            let source = PathBuf::from(&sync_dir.source_path);
+            let changed =
+                tokio::task::spawn_blocking(move || crate::suspend::changed_since(&source, since))
+                    .await
+                    .unwrap_or(true);
+            if !changed {
+                debug!("Nothing changed in {} while asleep", sync_dir.source_path);
+                continue;
+            }
+
+            info!("Rescanning {} after resume", sync_dir.source_path);
+            Self::scan_sync_dir(db, &cfg, sync_dir).await;
+        }
+    }
+
+    /// Scan one sync directory, recording its first complete scan
+    async fn scan_sync_dir(db: &Db, cfg: &crate::types::Config, sync_dir: &SyncDir) {
+        match crate::watcher::FileScanner::scan_directory(
+            db,
+            &sync_dir.source_path,
+            &sync_dir.remote_root,
+            cfg,
+        )
+        .await
+        {
+            Ok(_) => {
+                if let Err(e) = db.mark_first_scan(&sync_dir.source_path).await {
+                    warn!("Error recording scan of {}: {}", sync_dir.source_path, e);
+                }
+            }
+            Err(e) => error!("Error scanning directory {}: {}", sync_dir.source_path, e),
+        }
+    }
+
+    /// Find files deleted locally that are still on the remote
+    ///
+    /// Queues a remote delete for each unless `dry_run` is set.
+    async fn prune_sync_dirs(
+        db: &Db,
+        cfg: &crate::types::Config,
+        dry_run: bool,
+    ) -> Result<Vec<NodeMapping>> {
+        let mut deleted = Vec::new();
+
+        for sync_dir in &cfg.sync_dirs {
+            let found = crate::watcher::FileScanner::find_deleted(
+                db,
+                &sync_dir.source_path,
+                &sync_dir.remote_root,
+            )
+            .await?;
+            if !dry_run {
+                crate::watcher::FileScanner::prune_deleted(db, &found, cfg).await?;
+            }
+            deleted.extend(found);
+        }
+
+        if !deleted.is_empty() && !dry_run {
+            info!(
+                "Queued {} remote delete(s) for locally deleted files",
+                deleted.len()
+            );
+        }
+        Ok(deleted)
+    }
+
+    /// Start first sync milestone task
+    ///
+    /// Announces each sync directory's initial upload once it has finished.
+    async fn start_first_sync_task(&self) {
+        let db = self.db.clone();
+        let state = self.state.clone();
+
+        tokio::spawn(async move {
+            let mut interval = interval(crate::first_sync::CHECK_INTERVAL);
+            interval.tick().await; // Skip first tick
+
+            loop {
+                interval.tick().await;
+
+                if *state.lock().await != SyncState::Running {
+                    continue;
+                }
+
+                match crate::first_sync::complete_finished(&db).await {
+                    Ok(completed) => {
+                        for summary in &completed {
+                            crate::first_sync::announce(summary).await;
+                        }
+                    }
+                    Err(e) => error!("Error checking first sync progress: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Start sleep inhibitor task
+    ///
+    /// Holds a lock against suspend and shutdown while jobs are uploading,
+    /// released once the queue is idle or the engine is not running.
+    async fn start_inhibit_task(&self) {
+        let db = self.db.clone();
+        let processor = self.processor.clone();
         let config = self.config.clone();
+        let state = self.state.clone();
+
+        let task = tokio::spawn(async move {
+            let mut inhibitor = crate::inhibit::SleepInhibitor::new();
+            let mut interval = interval(crate::inhibit::INHIBIT_CHECK_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                let enabled = config.lock().await.get().inhibit_sleep_during_upload;
+                let running = *state.lock().await == SyncState::Running;
+                let wanted = if enabled && running {
+                    match db.get_job_count(SyncJobStatus::Pending).await {
+                        Ok(pending) => crate::inhibit::wanted(
+                            inhibitor.is_held(),
+                            processor.jobs_in_flight(),
+                            pending as usize,
+                        ),
+                        Err(e) => {
+                            debug!("Error getting queue status: {}", e);
+                            inhibitor.is_held()
+                        }
+                    }
+                } else {
+                    false
+                };
+                inhibitor.set(wanted).await;
+            }
+        });
+        self.tasks.lock().unwrap().push(task);
+    }
+
+    /// Start session refresh task
+    ///
+    /// Refreshes the session a few minutes before the access token expires,
+    /// so long uploads are not cut off by a rejected token.
+    async fn start_session_refresh_task(&self) {
         let processor = self.processor.clone();
 
+        let task = tokio::spawn(async move {
+            let mut interval = interval(SESSION_CHECK_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                if let Err(e) = processor
+                    .refresh_session_if_expiring(SESSION_REFRESH_MARGIN)
+                    .await
+                {
+                    warn!("Failed to refresh session before expiry: {}", e);
+                }
+            }
+        });
+        self.tasks.lock().unwrap().push(task);
+    }
+
+    /// Start heartbeat task
+    ///
+    /// Records the daemon's PID and start time with a fresh timestamp, so
+    /// `status` can tell a running daemon from one that died without
+    /// shutting down. The last successful API response is saved with it.
+    async fn start_heartbeat_task(&self) {
+        let db = self.db.clone();
+        let state = self.state.clone();
+        let api_contact = self.api_contact.clone();
+        let started_at = chrono::Utc::now();
+
+        let task = tokio::spawn(async move {
+            let mut interval = interval(HEARTBEAT_INTERVAL);
+            let mut saved_contact = None;
+
+            loop {
+                interval.tick().await;
+
+                // Held while recording, so `stop` cannot clear the heartbeat
+                // before a beat that was already under way
+                let state = state.lock().await;
+                if *state == SyncState::Idle {
+                    continue;
+                }
+                if let Err(e) = db.record_heartbeat(std::process::id(), started_at).await {
+                    debug!("Error recording heartbeat: {}", e);
+                }
+                drop(state);
+
+                let contact = api_contact.last();
+                if let Some(at) = contact.filter(|_| contact != saved_contact) {
+                    match db.set_last_api_contact(at).await {
+                        Ok(()) => saved_contact = contact,
+                        Err(e) => debug!("Error recording last API contact: {}", e),
+                    }
+                }
+            }
+        });
+        self.tasks.lock().unwrap().push(task);
+    }
+
+    /// Start log cleanup task
+    ///
+    /// Compresses log files once they are no longer written to and deletes
+    /// them past the configured retention and total size.
+    async fn start_log_cleanup_task(&self) {
+        let config = self.config.clone();
+
         tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(30));
-            interval.tick().await; // Skip first tick
+            let mut interval = interval(crate::logger::LOG_CLEANUP_INTERVAL);
 
             loop {
                 interval.tick().await;
 
+                let Ok(log_dir) = crate::paths::get_log_dir() else {
+                    continue;
+                };
+                if !log_dir.exists() {
+                    continue;
+                }
+                let limits = config.lock().await.get().logs.clone();
+                let today = chrono::Utc::now().date_naive();
+
+                let cleanup = tokio::task::spawn_blocking(move || {
+                    crate::logger::clean_up(&log_dir, &limits, today)
+                })
+                .await;
+                match cleanup {
+                    Ok(Ok(cleanup)) if cleanup == Default::default() => {}
+                    Ok(Ok(cleanup)) => info!(
+                        "Compressed {} and deleted {} old log file(s)",
+                        cleanup.compressed, cleanup.deleted
+                    ),
+                    Ok(Err(e)) => warn!("Error cleaning up log files: {}", e),
+                    Err(e) => warn!("Log cleanup task failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Delete local trash entries past the retention period
+    async fn purge_trash(config: &Arc<Mutex<ConfigManager>>) {
+        let cfg = config.lock().await.get().clone();
+        let retention = chrono::Duration::days(cfg.trash_retention_days.into());
+
+        let roots = cfg.sync_dirs.iter().map(|d| Path::new(&d.source_path));
+        let trash_dirs = match crate::trash::all_trash_dirs(roots) {
+            Ok(dirs) => dirs,
+            Err(e) => {
+                warn!("Error finding trash directories: {}", e);
+                return;
+            }
+        };
+
+        for trash in &trash_dirs {
+            match crate::trash::purge_expired(trash, retention) {
+                Ok(0) => {}
+                Ok(purged) => info!(
+                    "Purged {} expired trash entries in {}",
+                    purged,
+                    trash.display()
+                ),
+                Err(e) => warn!("Error purging trash in {}: {}", trash.display(), e),
+            }
+        }
+    }
+
+    /// Roll new sync history into the transfer statistics
+    ///
+    /// Runs before the history is pruned so no transfer is missed.
+    async fn aggregate_stats(db: &Db, config: &Arc<Mutex<ConfigManager>>) {
+        let sync_dirs = config.lock().await.get().sync_dirs.clone();
+
+        if let Err(e) = crate::stats::aggregate(db, &sync_dirs).await {
+            warn!("Error aggregating transfer statistics: {}", e);
+        }
+    }
+
+    /// Delete sync history past the retention period
+    async fn prune_history(db: &Db, config: &Arc<Mutex<ConfigManager>>) {
+        let retention_days = config.lock().await.get().history_retention_days;
+
+        match db
+            .prune_history(chrono::Duration::days(retention_days.into()))
+            .await
+        {
+            Ok(0) => {}
+            Ok(pruned) => debug!("Pruned {} old sync history entries", pruned),
+            Err(e) => warn!("Error pruning sync history: {}", e),
+        }
+    }
+
+    /// Forget our own writes the watcher no longer expects events for
+    async fn expire_expected_changes(db: &Db) {
+        match db
+            .expire_expected_changes(crate::watcher::EXPECTED_CHANGE_TTL)
+            .await
+        {
+            Ok(0) => {}
+            Ok(expired) => debug!("Expired {} expected changes", expired),
+            Err(e) => warn!("Error expiring expected changes: {}", e),
+        }
+    }
+
+    /// Delete synced jobs older than the configured retention
+    async fn cleanup_completed_jobs(db: &Db, config: &Arc<Mutex<ConfigManager>>) {
+        let retention = config.lock().await.get().timing.completed_job_retention();
+
+        match JobQueue::new(db.clone()).cleanup_old_jobs(retention).await {
+            Ok(0) => {}
+            Ok(count) => info!("Cleaned up {} old completed jobs", count),
+            Err(e) => warn!("Error cleaning up old jobs: {}", e),
+        }
+    }
+
+    /// Start suspend/resume detection task
+    ///
+    /// Follows logind's sleep signals where available and otherwise watches
+    /// for a gap between the wall and monotonic clocks. After the system
+    /// wakes up the file watcher is restarted, since its backend may have
+    /// stopped delivering events, and the sync directories with anything
+    /// changed since it went to sleep are rescanned.
+    async fn start_resume_task(&self) {
+        let db = self.db.clone();
+        let config = self.config.clone();
+        let state = self.state.clone();
+        let watcher = self.watcher.clone();
+
+        tokio::spawn(async move {
+            let mut logind = watch_logind();
+            let mut detector = SuspendDetector::new();
+            let mut interval = interval(SUSPEND_CHECK_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut asleep_at = None;
+
+            loop {
+                let since = tokio::select! {
+                    signal = next_sleep_signal(&mut logind) => match signal {
+                        Some(SleepSignal::Suspending) => {
+                            asleep_at = Some(SystemTime::now());
+                            continue;
+                        }
+                        Some(SleepSignal::Resumed) => {
+                            info!("System resumed, restarting file watcher");
+                            asleep_at
+                                .take()
+                                .unwrap_or_else(|| SystemTime::now() - SUSPEND_CHECK_INTERVAL)
+                        }
+                        None => {
+                            debug!("Detecting suspend from the clocks instead of logind");
+                            logind = None;
+                            continue;
+                        }
+                    },
+                    _ = interval.tick() => {
+                        // The clocks are compared on every tick to keep them in step
+                        let Some(asleep) = detector.check() else {
+                            continue;
+                        };
+                        if logind.is_some() {
+                            continue;
+                        }
+                        info!(
+                            "System resumed after about {}s asleep, restarting file watcher",
+                            asleep.as_secs()
+                        );
+                        SystemTime::now() - asleep - SUSPEND_CHECK_INTERVAL
+                    }
+                };
+
+                // A stopped engine has no watcher to restart
+                if *state.lock().await == SyncState::Idle {
+                    continue;
+                }
+
+                let mut watcher = watcher.lock().await;
+                let restarted = match watcher.stop().await {
+                    Ok(()) => watcher.start().await,
+                    Err(e) => Err(e),
+                };
+                drop(watcher);
+
+                if let Err(e) = restarted {
+                    error!("Error restarting file watcher: {}", e);
+                }
+
+                // Leave room for file system timestamps that lag the clock
+                Self::scan_changed_sync_dirs(&db, &config, since - RESUME_SCAN_MARGIN).await;
+                info!("Post-resume scan complete");
+            }
+        });
+    }
+
+    /// Start network connectivity task
+    ///
+    /// Moves the engine to `Offline` while the Proton API is unreachable, so
+    /// jobs wait instead of failing, and back to `Running` once it answers.
+    /// A paused or stopped engine is left alone.
+    async fn start_connectivity_task(&self) {
+        let state = self.state.clone();
+        let client = ProtonClient::with_sessions(self.sessions.clone());
+        let mut ticker = ScheduleTicker::immediate(self.schedule.subscribe(), |s| s.connectivity);
+
+        let task = tokio::spawn(async move {
+            let mut monitor = ConnectivityMonitor::new();
+
+            loop {
+                ticker.tick().await;
+
+                let current_state = *state.lock().await;
+                if !matches!(current_state, SyncState::Running | SyncState::Offline) {
+                    continue;
+                }
+
+                let online = monitor.record(client.is_reachable().await);
+
+                let mut state = state.lock().await;
+                match (*state, online) {
+                    (SyncState::Running, false) => {
+                        *state = SyncState::Offline;
+                        warn!("Proton API unreachable, pausing sync until the network returns");
+                    }
+                    (SyncState::Offline, true) => {
+                        *state = SyncState::Running;
+                        info!("Network is back, resuming sync");
+                    }
+                    _ => {}
+                }
+            }
+        });
+        self.tasks.lock().unwrap().push(task);
+    }
+
+    /// Start config reload task
+    async fn start_config_reload_task(&self) {
+        let db = self.db.clone();
+        let config = self.config.clone();
+        let sessions = self.sessions.clone();
+        let moved_roots = self.moved_roots.clone();
+        let schedule = self.schedule.clone();
+        let mut ticker = ScheduleTicker::new(self.schedule.subscribe(), |s| s.config_reload);
+
+        tokio::spawn(async move {
+            loop {
+                ticker.tick().await;
+
                 let mut cfg = config.lock().await;
-                if let Ok(updated) = cfg.check_for_updates().await {
-                    if updated {
+                match cfg.check_for_updates().await {
+                    Ok(true) => {
                         info!("Configuration reloaded");
 
+                        let updated = Schedule::for_config(cfg.get());
+                        schedule.send_if_modified(|current| {
+                            let changed = *current != updated;
+                            *current = updated;
+                            changed
+                        });
+
                         // Update processor concurrency if needed
                         let new_concurrency = cfg.get().sync_concurrency;
                         drop(cfg);
 
-                        let _proc = processor.lock().await;
+                        // A relinked or changed remote root may unblock a sync directory
+                        Self::check_remote_roots(&db, &config, &sessions, &moved_roots).await;
+
                         // Note: In a full implementation, you'd update the semaphore size
                         // For now, this is a placeholder
                         info!("Processor concurrency updated to {}", new_concurrency);
                     }
+                    Ok(false) => {}
+                    Err(e) => warn!(
+                        "Ignoring config change, keeping the current settings: {}",
+                        e
+                    ),
+                }
+            }
+        });
+    }
+
+    /// Start remote index refresh task
+    async fn start_remote_index_task(&self) {
+        let db = self.db.clone();
+        let sessions = self.sessions.clone();
+        let api_contact = self.api_contact.clone();
+        let mut ticker = ScheduleTicker::new(self.schedule.subscribe(), |s| s.remote_events);
+
+        tokio::spawn(async move {
+            let client = ProtonClient::with_sessions(sessions).with_contact(api_contact);
+
+            loop {
+                ticker.tick().await;
+
+                if let Err(e) = RemoteIndex::new(&client, &db).refresh().await {
+                    debug!("Failed to refresh remote index: {}", e);
                 }
             }
         });
     }
 
+    /// Start usage metrics task
+    ///
+    /// The config is re-read on every tick, so enabling or disabling
+    /// telemetry takes effect without a restart.
+    async fn start_telemetry_task(&self) {
+        let db = self.db.clone();
+        let config = self.config.clone();
+
+        tokio::spawn(async move {
+            let mut interval = interval(crate::telemetry::TELEMETRY_INTERVAL);
+            interval.tick().await; // Skip first tick
+
+            loop {
+                interval.tick().await;
+
+                let cfg = config.lock().await.get().clone();
+                if !cfg.telemetry {
+                    continue;
+                }
+
+                let endpoint = match &cfg.telemetry_endpoint {
+                    Some(endpoint) => endpoint,
+                    None => {
+                        debug!("Telemetry enabled but no endpoint configured");
+                        continue;
+                    }
+                };
+
+                let result = match crate::telemetry::collect(&db, &cfg).await {
+                    Ok(report) => crate::telemetry::send(endpoint, &report).await,
+                    Err(e) => Err(e),
+                };
+
+                if let Err(e) = result {
+                    debug!("Failed to send telemetry: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Check every sync directory's remote root and remember the moved ones
+    async fn check_remote_roots(
+        db: &Db,
+        config: &Arc<Mutex<ConfigManager>>,
+        sessions: &SessionStore,
+        moved_roots: &Arc<Mutex<Vec<String>>>,
+    ) {
+        let sync_dirs = config.lock().await.get().sync_dirs.clone();
+        let client = ProtonClient::with_sessions(sessions.clone());
+        let remote_index = RemoteIndex::new(&client, db);
+        let mut moved = Vec::new();
+
+        // Pick up remote changes made while we were not looking
+        if let Err(e) = remote_index.refresh().await {
+            warn!("Could not refresh remote index: {}", e);
+        }
+
+        for (index, sync_dir) in sync_dirs.iter().enumerate() {
+            match check_remote_root(&remote_index, db, sync_dir).await {
+                Ok(RootStatus::Moved { current_path, .. }) => {
+                    warn!(
+                        "Remote root {} of {} was moved to {}; uploads are blocked until you run `proton-drive-sync config relink-dir {}`",
+                        sync_dir.remote_root,
+                        sync_dir.source_path,
+                        current_path,
+                        index + 1
+                    );
+                    moved.push(sync_dir.source_path.clone());
+                }
+                Ok(RootStatus::Missing) => {
+                    warn!(
+                        "Remote root {} of {} no longer exists and will be recreated",
+                        sync_dir.remote_root, sync_dir.source_path
+                    );
+                }
+                Ok(RootStatus::Linked | RootStatus::Unbound) => {}
+                Err(e) => {
+                    warn!(
+                        "Could not check remote root of {}: {}",
+                        sync_dir.source_path, e
+                    );
+                }
+            }
+        }
+
+        *moved_roots.lock().await = moved;
+    }
+
     /// Run reconciliation manually
     pub async fn reconcile(&self) -> Result<usize> {
         info!("Running manual reconciliation");
 
-        let cfg = self.config.lock().await;
-        let sync_dirs = cfg.get().sync_dirs.clone();
-        let exclusions = cfg.get().exclude_patterns.clone();
-        drop(cfg);
+        let cfg = self.config.lock().await.get().clone();
 
         let mut total = 0;
 
-        for sync_dir in sync_dirs {
+        for sync_dir in &cfg.sync_dirs {
             let count = crate::watcher::FileScanner::scan_directory(
                 &self.db,
                 &sync_dir.source_path,
                 &sync_dir.remote_root,
-                &exclusions,
+                &cfg,
             )
             .await?;
+            self.db.mark_first_scan(&sync_dir.source_path).await?;
             total += count;
         }
 
@@ -327,10 +1255,70 @@ impl SyncEngine {
 
         Ok(total)
     }
+
+    /// Process queued jobs in the foreground, without watching for changes
+    ///
+    /// Keeps going while jobs are ready, including follow-ups the jobs queue
+    /// themselves, and returns once the rest are blocked or waiting for a
+    /// retry. `on_job` is called with each job's history entry as it ends.
+    pub async fn drain(&self, mut on_job: impl FnMut(&HistoryEntry)) -> Result<()> {
+        self.lock_instance()?;
+        info!("Draining the job queue");
+        self.db.requeue_claimed_jobs().await?;
+
+        if let Err(e) = self.processor.recover_folder_creations().await {
+            warn!("Could not recover interrupted folder creations: {}", e);
+        }
+        Self::check_remote_roots(&self.db, &self.config, &self.sessions, &self.moved_roots).await;
+        let moved = self.moved_roots.lock().await.clone();
+        let batch_size = self.schedule.borrow().job_batch;
+
+        loop {
+            let jobs = self.db.get_pending_jobs(batch_size).await?;
+            if jobs.is_empty() {
+                return Ok(());
+            }
+
+            // Jobs sharing a path wait for the next batch
+            let mut claimed = HashSet::new();
+            let mut batch = Vec::new();
+            for job in jobs {
+                if Self::block_if_moved(&self.db, &moved, &job).await {
+                    continue;
+                }
+                let paths: Vec<&String> = std::iter::once(&job.local_path)
+                    .chain(job.old_local_path.as_ref())
+                    .collect();
+                if paths.iter().any(|p| claimed.contains(*p)) {
+                    continue;
+                }
+                claimed.extend(paths.into_iter().cloned());
+                if let Some(job) = self.db.claim_job(job.id).await? {
+                    batch.push(job);
+                }
+            }
+
+            let mut running: FuturesUnordered<_> = batch
+                .iter()
+                .map(|job| self.processor.process_job_recorded(job))
+                .collect();
+            while let Some(result) = running.next().await {
+                let (entry, _) = result?;
+                on_job(&entry);
+            }
+        }
+    }
+
+    /// List files deleted locally that are still on the remote, queueing
+    /// their remote deletes unless `dry_run` is set
+    pub async fn prune(&self, dry_run: bool) -> Result<Vec<NodeMapping>> {
+        let cfg = self.config.lock().await.get().clone();
+        Self::prune_sync_dirs(&self.db, &cfg, dry_run).await
+    }
 }
 
 /// Sync status
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncStatus {
     pub state: SyncState,
     pub pending_jobs: usize,
@@ -338,3 +1326,13 @@ pub struct SyncStatus {
     pub synced_jobs: usize,
     pub blocked_jobs: usize,
 }
+
+/// Wait for the next logind sleep signal, forever once there is no monitor
+async fn next_sleep_signal(
+    logind: &mut Option<tokio::sync::mpsc::Receiver<SleepSignal>>,
+) -> Option<SleepSignal> {
+    match logind {
+        Some(signals) => signals.recv().await,
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,134 @@
+//! Opt-in anonymized usage metrics
+//!
+//! Disabled unless `telemetry` is true in the config. Reports only coarse
+//! counters (version, platform, queue sizes and error classes) and never
+//! paths, file names, account details or error messages.
+
+use crate::db::Db;
+use crate::error::{Error, Result};
+use crate::queue::{JobQueue, StatusCounts};
+use crate::types::{Config, SyncJobStatus};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How often a report is sent while the daemon runs
+pub const TELEMETRY_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Timeout for delivering a report
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum blocked jobs inspected for error classes
+const ERROR_SAMPLE_LIMIT: i64 = 1000;
+
+/// Start of the message of a job blocked because its remote root moved
+const REMOTE_ROOT_MOVED: &str = "Remote root of";
+
+/// Anonymized usage report
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetryReport {
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    pub sync_dirs: usize,
+    pub queue: StatusCounts,
+    /// Blocked jobs per error class
+    pub error_classes: BTreeMap<String, u64>,
+}
+
+/// Collect a report from the current config and queue
+pub async fn collect(db: &Db, config: &Config) -> Result<TelemetryReport> {
+    let queue = JobQueue::new(db.clone()).get_status_counts().await?;
+
+    let mut error_classes = BTreeMap::new();
+    for job in db
+        .get_jobs_by_status(SyncJobStatus::Blocked, ERROR_SAMPLE_LIMIT)
+        .await?
+    {
+        let class = error_class(job.last_error.as_deref().unwrap_or_default());
+        *error_classes.entry(class.to_string()).or_insert(0) += 1;
+    }
+
+    Ok(TelemetryReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        sync_dirs: config.sync_dirs.len(),
+        queue,
+        error_classes,
+    })
+}
+
+/// Deliver a report to the configured endpoint
+pub async fn send(endpoint: &str, report: &TelemetryReport) -> Result<()> {
+    reqwest::Client::new()
+        .post(endpoint)
+        .timeout(SEND_TIMEOUT)
+        .json(report)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Reduce an error message to a coarse class without any user data
+pub fn error_class(message: &str) -> &'static str {
+    if message.starts_with(REMOTE_ROOT_MOVED) {
+        return "remote_root_moved";
+    }
+    Error::class_of(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_class() {
+        assert_eq!(
+            error_class("File not found: /home/alice/secret.txt"),
+            "file_not_found"
+        );
+        assert_eq!(
+            error_class("Proton API error: Create file failed"),
+            "proton_api"
+        );
+        assert_eq!(error_class("Timeout"), "timeout");
+        assert_eq!(
+            error_class("IO error: Permission denied (os error 13)"),
+            "permission_denied"
+        );
+        assert_eq!(
+            error_class("IO error: No space left on device (os error 28)"),
+            "io"
+        );
+        assert_eq!(
+            error_class("Proton API error: Delete failed: 429 Too Many Requests"),
+            "rate_limited"
+        );
+        assert_eq!(error_class("something unexpected"), "other");
+        assert_eq!(error_class(""), "other");
+    }
+
+    #[test]
+    fn test_report_has_no_paths() {
+        let report = TelemetryReport {
+            version: "0.1.0".to_string(),
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            sync_dirs: 2,
+            queue: StatusCounts {
+                pending: 1,
+                processing: 0,
+                synced: 5,
+                blocked: 1,
+                skipped: 0,
+            },
+            error_classes: BTreeMap::from([("io".to_string(), 1)]),
+        };
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains(r#""error_classes":{"io":1}"#));
+        assert!(!json.contains('/'));
+    }
+}
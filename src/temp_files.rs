@@ -0,0 +1,147 @@
+//! Temporary and partial files
+//!
+//! Editors, browsers, download tools and copy tools such as rsync write a
+//! file under a temporary name and rename it into place once it is complete.
+//! Uploading the temporary file would only be followed by deleting it again,
+//! so paths matching `"temp_file_patterns"` are never synced. A pattern
+//! matches file names; one ending in `/` matches folder names instead, so
+//! the contents of rsync's `.~tmp~` folder are skipped as well.
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Patterns used when `"temp_file_patterns"` is not configured
+pub const DEFAULT_TEMP_FILE_PATTERNS: &[&str] = &[
+    // Editor backups and swap files, Office lock files
+    "*~*",
+    "*.tmp",
+    "*.swp",
+    // rsync --delay-updates
+    ".~tmp~/",
+    // Partial downloads: rsync --partial, curl and wget, Firefox, Chrome,
+    // Safari and Opera
+    "*.partial",
+    "*.part",
+    "*.crdownload",
+    "*.download",
+    "*.opdownload",
+];
+
+/// Default `"temp_file_patterns"`
+pub fn default_patterns() -> Vec<String> {
+    DEFAULT_TEMP_FILE_PATTERNS
+        .iter()
+        .map(|p| p.to_string())
+        .collect()
+}
+
+/// `"temp_file_patterns"`, compiled once when the config is loaded
+///
+/// Invalid globs are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "Vec<String>", into = "Vec<String>")]
+pub struct TempFilePatterns {
+    patterns: Vec<String>,
+    files: GlobSet,
+    folders: GlobSet,
+}
+
+impl TempFilePatterns {
+    /// Compile a list of patterns
+    pub fn new(patterns: Vec<String>) -> Self {
+        let mut files = GlobSetBuilder::new();
+        let mut folders = GlobSetBuilder::new();
+        for pattern in &patterns {
+            let pattern = crate::paths::nfc(pattern);
+            let (builder, glob) = match pattern.strip_suffix('/') {
+                Some(folder) => (&mut folders, Glob::new(folder)),
+                None => (&mut files, Glob::new(&pattern)),
+            };
+            if let Ok(glob) = glob {
+                builder.add(glob);
+            }
+        }
+
+        Self {
+            patterns,
+            files: files.build().unwrap_or_else(|_| GlobSet::empty()),
+            folders: folders.build().unwrap_or_else(|_| GlobSet::empty()),
+        }
+    }
+
+    /// The patterns as configured
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Check whether a path relative to its sync directory is temporary
+    ///
+    /// The path is temporary if its own name matches, or it lies in a
+    /// folder whose name matches a folder pattern.
+    pub fn matches(&self, relative: &Path, is_dir: bool) -> bool {
+        let names: Vec<String> = relative
+            .components()
+            .map(|c| crate::paths::nfc(&c.as_os_str().to_string_lossy()))
+            .collect();
+        let Some((name, parents)) = names.split_last() else {
+            return false;
+        };
+
+        let own = if is_dir { &self.folders } else { &self.files };
+        own.is_match(name) || parents.iter().any(|p| self.folders.is_match(p))
+    }
+}
+
+impl Default for TempFilePatterns {
+    fn default() -> Self {
+        Self::new(default_patterns())
+    }
+}
+
+impl From<Vec<String>> for TempFilePatterns {
+    fn from(patterns: Vec<String>) -> Self {
+        Self::new(patterns)
+    }
+}
+
+impl From<TempFilePatterns> for Vec<String> {
+    fn from(patterns: TempFilePatterns) -> Self {
+        patterns.patterns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_temp_path() {
+        let patterns = TempFilePatterns::default();
+        let temp = |p: &str| patterns.matches(Path::new(p), false);
+
+        assert!(temp("notes.txt~"));
+        assert!(temp("~$report.docx"));
+        assert!(temp("video.mp4.part"));
+        assert!(temp("Downloads/setup.exe.crdownload"));
+        assert!(temp("backup/.~tmp~/photo.jpg"));
+        assert!(!temp("notes.txt"));
+        assert!(!temp("partial/notes.txt"));
+        assert!(temp("報告書/草稿.docx~"));
+        assert!(temp("~$re\u{301}sume\u{301}.docx"));
+        assert!(!temp("🎉/שלום.txt"));
+
+        // File patterns leave folders and their contents alone
+        assert!(!patterns.matches(Path::new("a~b"), true));
+        assert!(!temp("a~b/notes.txt"));
+        assert!(patterns.matches(Path::new("backup/.~tmp~"), true));
+
+        // Configured patterns replace the defaults
+        let custom = |patterns: &[&str], path: &str| {
+            TempFilePatterns::new(patterns.iter().map(|p| p.to_string()).collect())
+                .matches(Path::new(path), false)
+        };
+        assert!(!custom(&["*.bak"], "video.part"));
+        assert!(custom(&["*.café"], "brouillon.cafe\u{301}"));
+    }
+}
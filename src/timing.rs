@@ -0,0 +1,291 @@
+//! Configurable timing
+//!
+//! Durations in the config file are written as a number and a unit, e.g.
+//! `"500ms"`, `"30s"`, `"5m"`, `"12h"` or `"7d"`. Intervals left unset follow
+//! the normal or low-power schedule. Scheduled scans are written as
+//! five-field cron expressions in local time, e.g. `"0 3 * * *"`.
+
+use crate::error::{Error, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+const SECOND: u64 = 1000;
+const MINUTE: u64 = 60 * SECOND;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+
+/// Units accepted in durations, largest first, in milliseconds
+const UNITS: [(&str, u64); 5] = [
+    ("d", DAY),
+    ("h", HOUR),
+    ("m", MINUTE),
+    ("s", SECOND),
+    ("ms", 1),
+];
+
+/// Completed jobs are kept this long unless configured otherwise
+pub const DEFAULT_COMPLETED_JOB_RETENTION: Duration = Duration::from_millis(7 * DAY);
+
+/// Periodic scans are skipped while more jobs than this are pending
+pub const DEFAULT_RECONCILE_BUSY_THRESHOLD: u64 = 100;
+
+/// A duration written as a number and a unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ConfigDuration(pub Duration);
+
+impl FromStr for ConfigDuration {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+
+        let invalid = || {
+            Error::Config(format!(
+                "Invalid duration {:?}: expected a number and a unit (ms, s, m, h or d), e.g. \"5m\"",
+                s
+            ))
+        };
+
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+        let (_, millis) = UNITS
+            .iter()
+            .find(|(name, _)| *name == unit.trim())
+            .ok_or_else(invalid)?;
+
+        number
+            .checked_mul(*millis)
+            .map(|ms| Self(Duration::from_millis(ms)))
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for ConfigDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = self.0.as_millis() as u64;
+        let (name, millis) = UNITS
+            .iter()
+            .find(|(_, millis)| ms != 0 && ms.is_multiple_of(*millis))
+            .unwrap_or(&("s", SECOND));
+
+        write!(f, "{}{}", ms / millis, name)
+    }
+}
+
+impl Serialize for ConfigDuration {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ConfigDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// A cron expression for scheduled scans
+#[derive(Debug, Clone)]
+pub struct CronSchedule(croner::Cron);
+
+impl CronSchedule {
+    /// Whether the schedule fires after `after` and no later than `until`
+    pub fn fires_between(&self, after: DateTime<Local>, until: DateTime<Local>) -> bool {
+        self.0
+            .find_next_occurrence(&after, false)
+            .is_ok_and(|next| next <= until)
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        croner::Cron::new(s.trim()).parse().map(Self).map_err(|e| {
+            Error::Config(format!(
+                "Invalid cron schedule {:?}: {}, expected five fields, e.g. \"0 3 * * *\"",
+                s, e
+            ))
+        })
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.as_str())
+    }
+}
+
+impl PartialEq for CronSchedule {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for CronSchedule {}
+
+impl Serialize for CronSchedule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CronSchedule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Intervals and schedules of the daemon's background work
+///
+/// Changes are picked up when the config file is reloaded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimingConfig {
+    /// How often the queue is polled for pending jobs (1s, 5s in low-power mode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_poll: Option<ConfigDuration>,
+    /// How often local directories are rescanned (5m, 30m in low-power mode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconcile: Option<ConfigDuration>,
+    /// How often the config file is checked for changes (30s, 2m in low-power mode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_reload: Option<ConfigDuration>,
+    /// How long synced jobs stay in the queue before they are deleted (7d)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_job_retention: Option<ConfigDuration>,
+    /// When to scan local directories in addition to the interval, e.g. nightly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconcile_cron: Option<CronSchedule>,
+    /// Pending jobs above which a periodic scan is skipped (100)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconcile_busy_threshold: Option<u64>,
+}
+
+impl TimingConfig {
+    /// Check every configured duration against its bounds
+    pub fn validate(&self) -> Result<()> {
+        let checks = [
+            ("job_poll", self.job_poll, 100, MINUTE),
+            ("reconcile", self.reconcile, MINUTE, DAY),
+            ("config_reload", self.config_reload, SECOND, HOUR),
+            (
+                "completed_job_retention",
+                self.completed_job_retention,
+                HOUR,
+                365 * DAY,
+            ),
+        ];
+
+        for (name, value, min, max) in checks {
+            let Some(value) = value else {
+                continue;
+            };
+            let (min, max) = (
+                ConfigDuration(Duration::from_millis(min)),
+                ConfigDuration(Duration::from_millis(max)),
+            );
+            if value < min || value > max {
+                return Err(Error::Config(format!(
+                    "timing.{} must be between {} and {}, got {}",
+                    name, min, max, value
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// How long synced jobs are kept
+    pub fn completed_job_retention(&self) -> Duration {
+        self.completed_job_retention
+            .map_or(DEFAULT_COMPLETED_JOB_RETENTION, |d| d.0)
+    }
+
+    /// Pending jobs above which a periodic scan is skipped
+    pub fn reconcile_busy_threshold(&self) -> u64 {
+        self.reconcile_busy_threshold
+            .unwrap_or(DEFAULT_RECONCILE_BUSY_THRESHOLD)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn duration(s: &str) -> Duration {
+        s.parse::<ConfigDuration>().unwrap().0
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(duration("500ms"), Duration::from_millis(500));
+        assert_eq!(duration("30s"), Duration::from_secs(30));
+        assert_eq!(duration("5m"), Duration::from_secs(5 * 60));
+        assert_eq!(duration("12h"), Duration::from_secs(12 * 60 * 60));
+        assert_eq!(duration(" 7d "), Duration::from_secs(7 * 24 * 60 * 60));
+
+        for invalid in [
+            "",
+            "5",
+            "m",
+            "1.5h",
+            "-1s",
+            "5 minutes",
+            "99999999999999999d",
+        ] {
+            assert!(invalid.parse::<ConfigDuration>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_format_duration() {
+        for s in ["500ms", "1500ms", "30s", "90s", "5m", "12h", "7d", "0s"] {
+            assert_eq!(s.parse::<ConfigDuration>().unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_validate_timing() {
+        let timing: TimingConfig =
+            serde_json::from_str(r#"{"job_poll": "2s", "reconcile": "15m"}"#).unwrap();
+        assert!(timing.validate().is_ok());
+        assert_eq!(
+            timing.completed_job_retention(),
+            DEFAULT_COMPLETED_JOB_RETENTION
+        );
+
+        let timing: TimingConfig = serde_json::from_str(r#"{"reconcile": "10s"}"#).unwrap();
+        let error = timing.validate().unwrap_err().to_string();
+        assert!(error.contains("timing.reconcile must be between 1m and 1d"));
+
+        assert!(serde_json::from_str::<TimingConfig>(r#"{"job_poll": "fast"}"#).is_err());
+    }
+
+    #[test]
+    fn test_cron_schedule() {
+        let nightly: CronSchedule = "0 3 * * *".parse().unwrap();
+        assert_eq!(nightly.to_string(), "0 3 * * *");
+
+        let at = |h, m| Local.with_ymd_and_hms(2026, 3, 10, h, m, 0).unwrap();
+        assert!(nightly.fires_between(at(2, 59), at(3, 0)));
+        assert!(nightly.fires_between(at(1, 0), at(4, 0)));
+        assert!(!nightly.fires_between(at(3, 0), at(3, 1)));
+        assert!(!nightly.fires_between(at(3, 1), at(23, 59)));
+
+        for invalid in ["", "every night", "61 * * * *"] {
+            assert!(invalid.parse::<CronSchedule>().is_err(), "{}", invalid);
+        }
+        assert!(
+            serde_json::from_str::<TimingConfig>(r#"{"reconcile_cron": "0 25 * * *"}"#).is_err()
+        );
+    }
+}
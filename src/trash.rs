@@ -0,0 +1,376 @@
+//! Local trash for files removed or replaced because of remote changes
+//!
+//! Whenever a remote change is about to delete or overwrite a local file, the
+//! local copy is moved into a trash directory instead, so a sync bug can never
+//! destroy data irreversibly. The trash is shared in the data directory or,
+//! with `"trash_location": "sync_dir"`, at the root of each sync directory.
+//! Each trashed item gets its own entry directory holding a small metadata
+//! file and, in a `data` folder beside it, the item itself; entries older
+//! than the retention period are purged.
+
+use crate::error::{Error, Result};
+use crate::paths::get_data_dir;
+use crate::types::TrashLocation;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use tracing::warn;
+
+/// Trash directory created at the root of each sync directory
+pub const TRASH_DIR_NAME: &str = ".proton-drive-sync-trash";
+
+/// Metadata file inside each trash entry
+const ENTRY_META_FILE: &str = "entry.json";
+
+/// Folder inside each trash entry holding the trashed item
+const ENTRY_DATA_DIR: &str = "data";
+
+/// A file or directory in the local trash
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashEntry {
+    /// Entry name within the trash directory
+    pub id: String,
+    /// Where the item lived before it was trashed
+    pub original_path: PathBuf,
+    pub trashed_at: DateTime<Utc>,
+}
+
+/// The trash directory of a sync directory
+pub fn trash_dir(sync_root: &Path) -> PathBuf {
+    sync_root.join(TRASH_DIR_NAME)
+}
+
+/// The trash shared by all sync directories, in the data directory
+pub fn local_trash_dir() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("trash"))
+}
+
+/// The trash that files from a sync directory go to
+pub fn trash_dir_for(location: TrashLocation, sync_root: &Path) -> Result<PathBuf> {
+    match location {
+        TrashLocation::SyncDir => Ok(trash_dir(sync_root)),
+        TrashLocation::DataDir => local_trash_dir(),
+    }
+}
+
+/// Every trash that may hold entries for these sync directories
+///
+/// Includes both locations, so changing `"trash_location"` doesn't hide
+/// entries trashed before.
+pub fn all_trash_dirs<'a>(sync_roots: impl IntoIterator<Item = &'a Path>) -> Result<Vec<PathBuf>> {
+    let mut dirs: Vec<PathBuf> = sync_roots.into_iter().map(trash_dir).collect();
+    dirs.push(local_trash_dir()?);
+    Ok(dirs)
+}
+
+/// Check if a path is the trash directory or inside it
+pub fn is_in_trash(path: &Path) -> bool {
+    path.components()
+        .any(|c| matches!(c, Component::Normal(name) if name == TRASH_DIR_NAME))
+}
+
+/// Move a file or directory into a trash directory
+pub fn move_to_trash(trash: &Path, path: &Path) -> Result<TrashEntry> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| Error::InvalidPath(path.display().to_string()))?;
+
+    let trashed_at = Utc::now();
+    let id = format!(
+        "{}-{:04x}",
+        trashed_at.format("%Y%m%dT%H%M%S%.3f"),
+        rand::thread_rng().gen::<u16>()
+    );
+
+    let entry_dir = trash.join(&id);
+    let data_dir = entry_dir.join(ENTRY_DATA_DIR);
+    std::fs::create_dir_all(&data_dir)?;
+    move_path(path, &data_dir.join(name))?;
+
+    let entry = TrashEntry {
+        id,
+        original_path: path.to_path_buf(),
+        trashed_at,
+    };
+    std::fs::write(
+        entry_dir.join(ENTRY_META_FILE),
+        serde_json::to_vec_pretty(&entry)?,
+    )?;
+
+    Ok(entry)
+}
+
+/// List a trash directory, oldest first
+pub fn list(trash: &Path) -> Result<Vec<TrashEntry>> {
+    if !trash.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(trash)? {
+        let meta = entry?.path().join(ENTRY_META_FILE);
+        // Entries without metadata are left alone rather than guessed at
+        let Ok(content) = std::fs::read(&meta) else {
+            continue;
+        };
+        match serde_json::from_slice::<TrashEntry>(&content) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => warn!("Skipping trash entry {}: {}", meta.display(), e),
+        }
+    }
+
+    entries.sort_by(|a, b| a.trashed_at.cmp(&b.trashed_at).then(a.id.cmp(&b.id)));
+    Ok(entries)
+}
+
+/// Move a trashed item back to where it was
+///
+/// Fails rather than overwriting if something exists there now.
+pub fn restore(trash: &Path, id: &str) -> Result<PathBuf> {
+    let entry = list(trash)?
+        .into_iter()
+        .find(|e| e.id == id)
+        .ok_or_else(|| Error::InvalidState(format!("No trash entry {}", id)))?;
+
+    if entry.original_path.exists() {
+        return Err(Error::InvalidState(format!(
+            "{} already exists; move it away before restoring",
+            entry.original_path.display()
+        )));
+    }
+
+    let name = entry
+        .original_path
+        .file_name()
+        .ok_or_else(|| Error::InvalidPath(entry.original_path.display().to_string()))?;
+    let entry_dir = trash.join(&entry.id);
+
+    if let Some(parent) = entry.original_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    move_path(
+        &entry_dir.join(ENTRY_DATA_DIR).join(name),
+        &entry.original_path,
+    )?;
+    std::fs::remove_dir_all(entry_dir)?;
+
+    Ok(entry.original_path)
+}
+
+/// Delete trash entries older than the retention period
+///
+/// Returns the number of entries deleted.
+pub fn purge_expired(trash: &Path, retention: chrono::Duration) -> Result<usize> {
+    let cutoff = Utc::now() - retention;
+    let mut purged = 0;
+
+    for entry in list(trash)? {
+        if entry.trashed_at < cutoff {
+            std::fs::remove_dir_all(trash.join(&entry.id))?;
+            purged += 1;
+        }
+    }
+
+    Ok(purged)
+}
+
+/// Delete every entry in a trash directory
+///
+/// Returns the number of entries deleted.
+pub fn empty(trash: &Path) -> Result<usize> {
+    let entries = list(trash)?;
+
+    for entry in &entries {
+        std::fs::remove_dir_all(trash.join(&entry.id))?;
+    }
+
+    Ok(entries.len())
+}
+
+/// Rename a file or directory, copying it when the trash is on another file system
+///
+/// A copy is made under a temporary name and renamed into place once
+/// complete, so `to` never holds a partial copy, and `from` is only removed
+/// after that.
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    match std::fs::rename(from, to) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            let mut partial = to.as_os_str().to_owned();
+            partial.push(".partial");
+            let partial = PathBuf::from(partial);
+
+            if let Err(e) = copy_recursive(from, &partial) {
+                let _ = remove_path(&partial);
+                return Err(e);
+            }
+            std::fs::rename(&partial, to)?;
+            remove_path(from)
+        }
+        result => Ok(result?),
+    }
+}
+
+/// Remove a file or a directory tree
+fn remove_path(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Copy a file or a directory tree
+fn copy_recursive(from: &Path, to: &Path) -> Result<()> {
+    if from.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        std::fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_trash_and_restore() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let file = root.join("docs").join("notes.txt");
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, "keep me").unwrap();
+
+        let trash = trash_dir(root);
+
+        let entry = move_to_trash(&trash, &file).unwrap();
+        assert!(!file.exists());
+        assert!(is_in_trash(
+            &trash.join(&entry.id).join(ENTRY_DATA_DIR).join("notes.txt")
+        ));
+        assert_eq!(list(&trash).unwrap(), vec![entry.clone()]);
+
+        // Never overwrite a file that came back in the meantime
+        fs::write(&file, "newer").unwrap();
+        assert!(restore(&trash, &entry.id).is_err());
+        fs::remove_file(&file).unwrap();
+
+        assert_eq!(restore(&trash, &entry.id).unwrap(), file);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "keep me");
+        assert!(list(&trash).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trash_a_file_named_like_the_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let file = root.join(ENTRY_META_FILE);
+        fs::write(&file, "user data").unwrap();
+        let trash = trash_dir(root);
+
+        let entry = move_to_trash(&trash, &file).unwrap();
+        assert_eq!(list(&trash).unwrap(), vec![entry.clone()]);
+        assert_eq!(restore(&trash, &entry.id).unwrap(), file);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "user data");
+    }
+
+    #[test]
+    fn test_list_skips_corrupt_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let file = root.join("a.txt");
+        fs::write(&file, "a").unwrap();
+        let trash = trash_dir(root);
+        let entry = move_to_trash(&trash, &file).unwrap();
+
+        let corrupt = trash.join("corrupt");
+        fs::create_dir_all(&corrupt).unwrap();
+        fs::write(corrupt.join(ENTRY_META_FILE), "not json").unwrap();
+
+        assert_eq!(list(&trash).unwrap(), vec![entry]);
+    }
+
+    #[test]
+    fn test_purge_expired() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let file = root.join("old.txt");
+        fs::write(&file, "old").unwrap();
+        let trash = trash_dir(root);
+        move_to_trash(&trash, &file).unwrap();
+
+        assert_eq!(
+            purge_expired(&trash, chrono::Duration::days(30)).unwrap(),
+            0
+        );
+        assert_eq!(purge_expired(&trash, chrono::Duration::zero()).unwrap(), 1);
+        assert!(list(&trash).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trash_outside_sync_dir() {
+        let sync_dir = TempDir::new().unwrap();
+        let data_dir = TempDir::new().unwrap();
+        let folder = sync_dir.path().join("photos");
+        fs::create_dir_all(folder.join("2024")).unwrap();
+        fs::write(folder.join("2024").join("a.jpg"), "jpeg").unwrap();
+        let trash = data_dir.path().join("trash");
+
+        let entry = move_to_trash(&trash, &folder).unwrap();
+        assert!(!folder.exists());
+        assert!(!trash_dir(sync_dir.path()).exists());
+
+        assert_eq!(restore(&trash, &entry.id).unwrap(), folder);
+        assert_eq!(
+            fs::read_to_string(folder.join("2024").join("a.jpg")).unwrap(),
+            "jpeg"
+        );
+    }
+
+    #[test]
+    fn test_copy_recursive() {
+        let temp_dir = TempDir::new().unwrap();
+        let from = temp_dir.path().join("from");
+        fs::create_dir_all(from.join("sub")).unwrap();
+        fs::write(from.join("sub").join("b.txt"), "b").unwrap();
+        let to = temp_dir.path().join("to");
+
+        copy_recursive(&from, &to).unwrap();
+        assert_eq!(
+            fs::read_to_string(to.join("sub").join("b.txt")).unwrap(),
+            "b"
+        );
+    }
+
+    #[test]
+    fn test_trash_dir_for() {
+        let root = Path::new("/sync/docs");
+        assert_eq!(
+            trash_dir_for(TrashLocation::SyncDir, root).unwrap(),
+            trash_dir(root)
+        );
+        assert_eq!(
+            trash_dir_for(TrashLocation::DataDir, root).unwrap(),
+            local_trash_dir().unwrap()
+        );
+        assert_eq!(
+            all_trash_dirs([root]).unwrap(),
+            vec![trash_dir(root), local_trash_dir().unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_is_in_trash() {
+        assert!(is_in_trash(Path::new("/sync/.proton-drive-sync-trash")));
+        assert!(is_in_trash(Path::new("/sync/.proton-drive-sync-trash/a/b")));
+        assert!(!is_in_trash(Path::new("/sync/docs/trash.txt")));
+    }
+}
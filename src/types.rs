@@ -1,7 +1,9 @@
 //! Core types and enums for Proton Drive Sync
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::time::Duration;
 
 /// Sync event types
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -30,6 +32,8 @@ pub enum SyncJobStatus {
     Processing,
     Synced,
     Blocked,
+    /// Set aside until a scan finds the cause gone
+    Skipped(SkipReason),
 }
 
 impl std::fmt::Display for SyncJobStatus {
@@ -39,23 +43,176 @@ impl std::fmt::Display for SyncJobStatus {
             Self::Processing => write!(f, "PROCESSING"),
             Self::Synced => write!(f, "SYNCED"),
             Self::Blocked => write!(f, "BLOCKED"),
+            Self::Skipped(_) => write!(f, "SKIPPED"),
         }
     }
 }
 
-/// Remote delete behavior
+/// Why a job was skipped rather than retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SkipReason {
+    /// The file or folder cannot be read
+    PermissionDenied,
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PermissionDenied => write!(f, "PERMISSION_DENIED"),
+        }
+    }
+}
+
+impl SkipReason {
+    /// Parse a reason as stored in the database
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "PERMISSION_DENIED" => Some(Self::PermissionDenied),
+            _ => None,
+        }
+    }
+}
+
+/// What queued a job
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
+pub enum JobSource {
+    /// A file system event
+    Watcher,
+    /// A reconciliation scan
+    Scanner,
+    /// The sync engine itself, e.g. a file that changed during its upload
+    Engine,
+}
+
+impl std::fmt::Display for JobSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Watcher => write!(f, "WATCHER"),
+            Self::Scanner => write!(f, "SCANNER"),
+            Self::Engine => write!(f, "ENGINE"),
+        }
+    }
+}
+
+impl JobSource {
+    /// Parse a source as stored in the database
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "WATCHER" => Some(Self::Watcher),
+            "SCANNER" => Some(Self::Scanner),
+            "ENGINE" => Some(Self::Engine),
+            _ => None,
+        }
+    }
+}
+
+/// Remote delete behavior
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
 pub enum RemoteDeleteBehavior {
     Trash,
     Permanent,
 }
 
+/// What to do when an upload's name is already taken by different content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Rename the remote file to a conflicted copy and upload alongside it
+    #[default]
+    KeepBoth,
+    /// Upload the local file as a new revision of the remote one
+    Replace,
+    /// Leave both files as they are until decided with `conflicts resolve`
+    Ask,
+}
+
+/// How local file changes are detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeDetection {
+    /// Compare modification time and size
+    #[default]
+    Quick,
+    /// Compare a SHA-256 hash of the contents
+    Full,
+}
+
+impl ChangeDetection {
+    /// Detection mode that produced a change token
+    pub fn of_token(token: &str) -> Self {
+        if token.starts_with("sha256:") {
+            Self::Full
+        } else {
+            Self::Quick
+        }
+    }
+}
+
+/// Where the session's tokens are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialStore {
+    /// The system keyring
+    #[default]
+    Keyring,
+    /// A file in the data directory, encrypted with a passphrase or key file
+    File,
+}
+
+/// Where local files moved aside by remote changes are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashLocation {
+    /// A trash directory at the root of each sync directory
+    SyncDir,
+    /// One trash in the data directory, shared by all sync directories
+    #[default]
+    DataDir,
+}
+
+/// How local file names become remote names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteNaming {
+    /// Upload names as they are
+    #[default]
+    Preserve,
+    /// Lowercase ASCII letters, digits and dashes, keeping the extension
+    Slugify,
+    /// Drop the characters Proton Drive rejects
+    StripForbidden,
+}
+
+impl RemoteNaming {
+    /// Remote name for a local file or folder name
+    pub fn apply(self, name: &str) -> String {
+        match self {
+            Self::Preserve => name.to_string(),
+            Self::Slugify => crate::paths::slugify_name(name),
+            Self::StripForbidden => crate::paths::strip_forbidden_name(name),
+        }
+    }
+}
+
 /// Sync directory configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncDir {
     pub source_path: String,
     pub remote_root: String,
+    /// Whether names differing only in case are the same file; defaults to
+    /// the platform's usual filesystem (macOS and Windows: yes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case_insensitive: Option<bool>,
+}
+
+impl SyncDir {
+    /// Whether the sync directory's filesystem ignores case in names
+    pub fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+            .unwrap_or(cfg!(any(target_os = "macos", windows)))
+    }
 }
 
 /// Exclude pattern configuration
@@ -65,6 +222,147 @@ pub struct ExcludePattern {
     pub globs: Vec<String>,
 }
 
+impl ExcludePattern {
+    /// Check if a path is under this pattern's scope and matches one of its globs
+    ///
+    /// Paths, scopes and globs are compared in NFC form.
+    pub fn matches(&self, path: &std::path::Path) -> bool {
+        let path = crate::paths::nfc_path(path);
+        if !self.path.is_empty() && !path.starts_with(crate::paths::nfc(&self.path)) {
+            return false;
+        }
+
+        self.globs.iter().any(|glob| {
+            glob::Pattern::new(&crate::paths::nfc(glob))
+                .map(|matcher| matcher.matches_path(&path))
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// How many times a failed job is retried before it is blocked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RetryLimit {
+    Count(u32),
+    /// Retry until the job succeeds; written as `"unlimited"`
+    Unlimited(Unlimited),
+}
+
+/// Marker for [`RetryLimit::Unlimited`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unlimited {
+    Unlimited,
+}
+
+/// Retry settings for one error class, falling back to the policy defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<RetryLimit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_delay_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_delay_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jitter_percent: Option<u8>,
+}
+
+/// How failed jobs are retried
+///
+/// The delay starts at `base_delay_secs` and doubles after each attempt, up
+/// to `max_delay_secs`, then is shortened by a random amount of up to
+/// `jitter_percent` so jobs that failed together don't retry together.
+/// Overrides are keyed by error class, e.g. `invalid_path` or `rate_limited`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    #[serde(default = "default_max_retries")]
+    pub max_retries: RetryLimit,
+    #[serde(default = "default_retry_base_delay")]
+    pub base_delay_secs: u64,
+    #[serde(default = "default_retry_max_delay")]
+    pub max_delay_secs: u64,
+    #[serde(default)]
+    pub jitter_percent: u8,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, RetryOverride>,
+}
+
+fn default_max_retries() -> RetryLimit {
+    RetryLimit::Count(5)
+}
+
+fn default_retry_base_delay() -> u64 {
+    60
+}
+
+fn default_retry_max_delay() -> u64 {
+    60 * 60
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            base_delay_secs: default_retry_base_delay(),
+            max_delay_secs: default_retry_max_delay(),
+            jitter_percent: 0,
+            overrides: BTreeMap::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Check that jitter percentages are at most 100
+    pub fn validate(&self) -> crate::error::Result<()> {
+        let jitters = std::iter::once(("retry", self.jitter_percent)).chain(
+            self.overrides
+                .iter()
+                .filter_map(|(class, rule)| rule.jitter_percent.map(|j| (class.as_str(), j))),
+        );
+
+        for (name, jitter) in jitters {
+            if jitter > 100 {
+                return Err(crate::error::Error::Config(format!(
+                    "jitter_percent of {} must be at most 100, got {}",
+                    name, jitter
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Delay before retrying a job that failed `n_retries` times before
+    ///
+    /// Returns `None` once the job has used up its retries and should be
+    /// blocked.
+    pub fn retry_delay(&self, error_class: &str, n_retries: u32) -> Option<Duration> {
+        let rule = self.overrides.get(error_class).cloned().unwrap_or_default();
+
+        if let RetryLimit::Count(max) = rule.max_retries.unwrap_or(self.max_retries) {
+            if n_retries >= max {
+                return None;
+            }
+        }
+
+        let base = rule.base_delay_secs.unwrap_or(self.base_delay_secs);
+        let max_delay = rule.max_delay_secs.unwrap_or(self.max_delay_secs);
+        let jitter = rule.jitter_percent.unwrap_or(self.jitter_percent).min(100);
+        let delay = Duration::from_secs(
+            base.saturating_mul(2_u64.saturating_pow(n_retries))
+                .min(max_delay),
+        );
+
+        if jitter == 0 {
+            return Some(delay);
+        }
+        let spread = delay.mul_f64(f64::from(jitter) / 100.0);
+        Some(delay - rand::Rng::gen_range(&mut rand::thread_rng(), Duration::ZERO..=spread))
+    }
+}
+
 /// Main application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -79,6 +377,82 @@ pub struct Config {
     pub dashboard_port: u16,
     #[serde(default)]
     pub exclude_patterns: Vec<ExcludePattern>,
+    /// Opt in to anonymized usage metrics
+    #[serde(default)]
+    pub telemetry: bool,
+    /// Where usage metrics are sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telemetry_endpoint: Option<String>,
+    #[serde(default)]
+    pub change_detection: ChangeDetection,
+    /// Check each upload's size against the remote node
+    #[serde(default)]
+    pub verify_uploads: bool,
+    /// Last preset applied with `config preset apply`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// Allow a sync directory inside another; files sync to the innermost one
+    #[serde(default)]
+    pub allow_nested_sync_dirs: bool,
+    /// Memory in MiB that concurrent uploads may hold at once; unlimited if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_upload_memory: Option<u64>,
+    /// Poll less often and skip content hashing, for NAS and single-board computers
+    #[serde(default)]
+    pub low_power: bool,
+    /// Files up to this many MiB are uploaded before larger ones
+    #[serde(default = "default_small_file_threshold")]
+    pub small_file_threshold: u64,
+    /// How failed jobs are retried
+    #[serde(default, alias = "retry_policy")]
+    pub retry: RetryPolicy,
+    /// Milliseconds a path must be quiet before its file events are queued
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+    /// Days local files moved to the trash by remote changes are kept
+    #[serde(default = "default_trash_retention_days")]
+    pub trash_retention_days: u32,
+    /// Where local files moved to the trash by remote changes go
+    #[serde(default)]
+    pub trash_location: TrashLocation,
+    /// Percentage of the account's storage above which `status` warns
+    #[serde(default = "default_storage_warning_percent")]
+    pub storage_warning_percent: u8,
+    /// Content types for file extensions, overriding built-in detection
+    ///
+    /// Keys are extensions without the dot, e.g. `"md": "text/markdown"`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub mime_overrides: BTreeMap<String, String>,
+    /// What to do when an upload finds different content under its name
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    /// Globs for temporary and partial files that are never synced
+    #[serde(default)]
+    pub temp_file_patterns: crate::temp_files::TempFilePatterns,
+    /// Days entries are kept in the sync history
+    #[serde(default = "default_history_retention_days")]
+    pub history_retention_days: u32,
+    /// Delete remote files whose local copy was deleted while the daemon was stopped
+    #[serde(default)]
+    pub prune_remote: bool,
+    /// How local names are turned into remote names
+    #[serde(default)]
+    pub remote_naming: RemoteNaming,
+    /// Delay suspend and shutdown on Linux while files are uploading
+    #[serde(default = "default_inhibit_sleep_during_upload")]
+    pub inhibit_sleep_during_upload: bool,
+    /// Background work intervals and job retention
+    #[serde(default)]
+    pub timing: crate::timing::TimingConfig,
+    /// Log file size limits and retention
+    #[serde(default)]
+    pub logs: crate::logger::LogLimits,
+    /// Where the session's tokens are kept
+    #[serde(default)]
+    pub credential_store: CredentialStore,
+    /// Key file for the `file` credential store, instead of a passphrase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_key_file: Option<String>,
 }
 
 fn default_concurrency() -> usize {
@@ -97,6 +471,30 @@ fn default_dashboard_port() -> u16 {
     4242
 }
 
+pub fn default_small_file_threshold() -> u64 {
+    8
+}
+
+fn default_debounce_ms() -> u64 {
+    2000
+}
+
+fn default_trash_retention_days() -> u32 {
+    30
+}
+
+fn default_storage_warning_percent() -> u8 {
+    90
+}
+
+fn default_history_retention_days() -> u32 {
+    30
+}
+
+fn default_inhibit_sleep_during_upload() -> bool {
+    true
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -106,6 +504,49 @@ impl Default for Config {
             dashboard_host: default_dashboard_host(),
             dashboard_port: default_dashboard_port(),
             exclude_patterns: Vec::new(),
+            telemetry: false,
+            telemetry_endpoint: None,
+            change_detection: ChangeDetection::Quick,
+            verify_uploads: false,
+            preset: None,
+            allow_nested_sync_dirs: false,
+            max_upload_memory: None,
+            low_power: false,
+            small_file_threshold: default_small_file_threshold(),
+            retry: RetryPolicy::default(),
+            debounce_ms: default_debounce_ms(),
+            trash_retention_days: default_trash_retention_days(),
+            trash_location: TrashLocation::DataDir,
+            storage_warning_percent: default_storage_warning_percent(),
+            mime_overrides: BTreeMap::new(),
+            conflict_policy: ConflictPolicy::KeepBoth,
+            temp_file_patterns: Default::default(),
+            history_retention_days: default_history_retention_days(),
+            prune_remote: false,
+            remote_naming: RemoteNaming::Preserve,
+            inhibit_sleep_during_upload: default_inhibit_sleep_during_upload(),
+            timing: crate::timing::TimingConfig::default(),
+            logs: crate::logger::LogLimits::default(),
+            credential_store: CredentialStore::Keyring,
+            credential_key_file: None,
+        }
+    }
+}
+
+impl Config {
+    /// Check settings that parse but are out of range
+    pub fn validate(&self) -> crate::error::Result<()> {
+        self.timing.validate()?;
+        self.logs.validate()?;
+        self.retry.validate()
+    }
+
+    /// Change detection mode in use; low-power mode never hashes contents
+    pub fn effective_change_detection(&self) -> ChangeDetection {
+        if self.low_power {
+            ChangeDetection::Quick
+        } else {
+            self.change_detection
         }
     }
 }
@@ -125,6 +566,11 @@ pub struct SyncJob {
     pub old_local_path: Option<String>,
     pub old_remote_path: Option<String>,
     pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// What queued the job; unknown for jobs queued by older versions
+    #[serde(default)]
+    pub source: Option<JobSource>,
 }
 
 /// File state for change detection
@@ -146,6 +592,40 @@ pub struct NodeMapping {
     pub updated_at: DateTime<Utc>,
 }
 
+/// Bytes sent so far by an in-flight upload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobProgress {
+    pub job_id: i64,
+    pub local_path: String,
+    pub bytes_sent: u64,
+    pub total_bytes: u64,
+}
+
+/// Liveness record the running daemon keeps up to date
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaemonHeartbeat {
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl DaemonHeartbeat {
+    /// Whether the daemon checked in within `max_age` of `now`
+    pub fn is_fresh(&self, now: DateTime<Utc>, max_age: chrono::Duration) -> bool {
+        now - self.last_seen <= max_age
+    }
+}
+
+/// Progress of a sync directory's scan generation
+///
+/// A scan that is interrupted resumes in the same generation and skips the
+/// top-level folders it already completed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanCheckpoint {
+    pub generation: i64,
+    pub completed_dirs: HashSet<String>,
+}
+
 /// Proton Drive session data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
@@ -154,6 +634,19 @@ pub struct Session {
     pub refresh_token: String,
     pub key_password: Option<String>,
     pub primary_key: Option<String>,
+    /// When the access token expires, if the server said so
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    /// Whether the access token expires within `margin`
+    ///
+    /// A session without a known expiry is never considered expiring.
+    pub fn expires_within(&self, margin: chrono::Duration) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at - Utc::now() <= margin)
+    }
 }
 
 /// Proton Drive node data
@@ -175,6 +668,179 @@ pub struct RevisionData {
     pub manifest_signature: Option<String>,
 }
 
+/// One stored version of a remote file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revision {
+    pub uid: String,
+    pub size: Option<i64>,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Whether this is the file's current content
+    pub active: bool,
+}
+
+/// A node in the remote Drive trash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedNode {
+    #[serde(flatten)]
+    pub node: NodeData,
+    pub trashed_at: Option<DateTime<Utc>>,
+}
+
+/// Public link to a shared node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareUrl {
+    pub url: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub password_protected: bool,
+}
+
+/// Which way a sync operation moved data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncDirection {
+    /// Local changes applied to Proton Drive
+    Upload,
+    /// Remote content written locally
+    Download,
+}
+
+/// How a recorded sync operation ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryResult {
+    Synced,
+    /// Failed and scheduled for another attempt
+    Retrying,
+    /// Failed for good, or out of retries
+    Blocked,
+    /// Set aside, e.g. because the file cannot be read
+    Skipped,
+}
+
+impl std::fmt::Display for HistoryResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Synced => write!(f, "SYNCED"),
+            Self::Retrying => write!(f, "RETRYING"),
+            Self::Blocked => write!(f, "BLOCKED"),
+            Self::Skipped => write!(f, "SKIPPED"),
+        }
+    }
+}
+
+/// One completed or failed sync operation, from the `sync_history` table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Assigned by the database; ignored when recording
+    pub id: i64,
+    pub event_type: SyncEventType,
+    pub direction: SyncDirection,
+    pub local_path: String,
+    pub remote_path: String,
+    /// Previous local path of a move
+    pub old_local_path: Option<String>,
+    /// Bytes transferred, for operations that carry file content
+    pub bytes: Option<i64>,
+    pub duration_ms: i64,
+    pub result: HistoryResult,
+    pub error: Option<String>,
+    pub completed_at: DateTime<Utc>,
+}
+
+/// An upload held back because its name has different content remotely
+///
+/// Recorded under the `ask` conflict policy and settled with
+/// `conflicts resolve`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conflict {
+    pub id: i64,
+    /// Blocked job that ran into the conflict
+    pub job_id: i64,
+    pub local_path: String,
+    pub remote_path: String,
+    /// Remote file holding the other content
+    pub node_uid: String,
+    pub parent_uid: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Decision on a conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// Upload the local file as a new revision of the remote one
+    KeepLocal,
+    /// Download the remote file over the local one
+    KeepRemote,
+    /// Rename the remote file to a conflicted copy and upload alongside it
+    KeepBoth,
+}
+
+/// Summary of one day's sync history, logged at midnight
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyDigest {
+    pub day: NaiveDate,
+    /// Files uploaded, downloaded, moved or deleted
+    pub files: i64,
+    /// Bytes transferred by those operations
+    pub bytes: i64,
+    /// Failed attempts, whether retried or blocked
+    pub errors: i64,
+}
+
+/// Transfer totals of one sync directory on one day, in one direction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferStats {
+    /// Local calendar day the transfers completed on
+    pub day: NaiveDate,
+    pub sync_dir: String,
+    pub direction: SyncDirection,
+    pub files: i64,
+    pub bytes: i64,
+}
+
+/// Outcome of a sync directory's initial upload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirstSyncSummary {
+    pub sync_dir: String,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+    /// Files mirrored remotely
+    pub files: i64,
+    /// Total size of those files
+    pub bytes: i64,
+    /// Jobs left blocked
+    pub errors: i64,
+}
+
+/// Kind of change reported by the Drive events endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodeEventType {
+    Delete,
+    Create,
+    Update,
+}
+
+/// A single remote node change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeEvent {
+    pub event_type: NodeEventType,
+    pub node_uid: String,
+    /// Current node state (absent for deletions)
+    pub node: Option<NodeData>,
+}
+
+/// A page of remote node changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeEvents {
+    pub events: Vec<NodeEvent>,
+    /// Event ID to continue from
+    pub event_id: String,
+    /// More events are waiting after this page
+    pub more: bool,
+    /// The event history was lost and local state must be rebuilt
+    pub refresh: bool,
+}
+
 /// Proton Drive address data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddressData {
@@ -182,12 +848,85 @@ pub struct AddressData {
     pub receive_key: Option<String>,
 }
 
+/// Logged-in account and its storage usage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountInfo {
+    pub email: String,
+    /// Plan name, if it could be determined
+    pub plan: Option<String>,
+    /// Bytes used
+    pub used_space: u64,
+    /// Bytes available in total
+    pub max_space: u64,
+}
+
+impl AccountInfo {
+    /// Share of the storage in use, in percent
+    pub fn usage_percent(&self) -> f64 {
+        if self.max_space == 0 {
+            return 0.0;
+        }
+        self.used_space as f64 * 100.0 / self.max_space as f64
+    }
+
+    /// Whether usage has reached the given percentage
+    pub fn is_over(&self, percent: u8) -> bool {
+        self.usage_percent() >= f64::from(percent)
+    }
+}
+
 /// Create operation result
 #[derive(Debug, Clone)]
 pub struct CreateResult {
     pub success: bool,
     pub node_uid: Option<String>,
+    /// The parent already has a node with this name
+    pub already_exists: bool,
     pub error: Option<String>,
+    /// HTTP status of a rejected request
+    pub status: Option<u16>,
+}
+
+impl CreateResult {
+    /// The error of a failed create, typed by the status it was rejected with
+    pub fn into_error(self) -> crate::error::Error {
+        let message = self.error.unwrap_or_else(|| "Unknown error".to_string());
+        match self.status {
+            Some(status) => crate::error::Error::ProtonApi {
+                message,
+                status: Some(status),
+            },
+            None => crate::error::Error::Sync(message),
+        }
+    }
+}
+
+/// Processing priority of a sync job; lower runs first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum JobPriority {
+    /// Directory creation, moves and deletes: no file data to send
+    Metadata = 0,
+    /// Files up to the small file threshold
+    Small = 1,
+    /// Files above the small file threshold
+    Large = 2,
+}
+
+impl JobPriority {
+    /// Priority of an event given the size of its file, if known
+    pub fn of(event: &SyncEvent, size: Option<u64>, small_file_threshold: u64) -> Self {
+        let uploads = match event.event_type {
+            SyncEventType::CreateFile => true,
+            SyncEventType::Update => event.old_local_path.is_none(),
+            SyncEventType::CreateDir | SyncEventType::Delete => false,
+        };
+
+        match size {
+            _ if !uploads => Self::Metadata,
+            Some(size) if size > small_file_threshold => Self::Large,
+            _ => Self::Small,
+        }
+    }
 }
 
 /// Sync event for enqueuing
@@ -213,12 +952,126 @@ mod tests {
         assert_eq!(SyncEventType::Delete.to_string(), "DELETE");
     }
 
+    #[test]
+    fn test_job_priority() {
+        let event = |event_type, old_local_path: Option<&str>| SyncEvent {
+            event_type,
+            local_path: "/home/user/file".to_string(),
+            remote_path: "/file".to_string(),
+            change_token: None,
+            old_local_path: old_local_path.map(str::to_string),
+            old_remote_path: None,
+        };
+
+        let create = event(SyncEventType::CreateFile, None);
+        assert_eq!(
+            JobPriority::of(&create, Some(100), 1000),
+            JobPriority::Small
+        );
+        assert_eq!(
+            JobPriority::of(&create, Some(1001), 1000),
+            JobPriority::Large
+        );
+        assert_eq!(JobPriority::of(&create, None, 1000), JobPriority::Small);
+
+        let moved = event(SyncEventType::Update, Some("/home/user/old"));
+        assert_eq!(
+            JobPriority::of(&moved, Some(5000), 1000),
+            JobPriority::Metadata
+        );
+
+        let mkdir = event(SyncEventType::CreateDir, None);
+        assert_eq!(JobPriority::of(&mkdir, None, 1000), JobPriority::Metadata);
+        assert!(JobPriority::Metadata < JobPriority::Small);
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let policy: RetryPolicy = serde_json::from_str(
+            r#"{
+                "max_retries": 3,
+                "overrides": {
+                    "invalid_path": {"max_retries": 0},
+                    "rate_limited": {"max_retries": "unlimited", "base_delay_secs": 300}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            policy.retry_delay("timeout", 0),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            policy.retry_delay("timeout", 2),
+            Some(Duration::from_secs(240))
+        );
+        assert_eq!(policy.retry_delay("timeout", 3), None);
+        assert_eq!(policy.retry_delay("invalid_path", 0), None);
+        assert_eq!(
+            policy.retry_delay("rate_limited", 1),
+            Some(Duration::from_secs(600))
+        );
+        assert_eq!(
+            policy.retry_delay("rate_limited", 100),
+            Some(Duration::from_secs(3600))
+        );
+
+        // The default matches the historical 5 tries with a 60s base
+        let default = RetryPolicy::default();
+        assert_eq!(
+            default.retry_delay("other", 4),
+            Some(Duration::from_secs(960))
+        );
+        assert_eq!(default.retry_delay("other", 5), None);
+    }
+
+    #[test]
+    fn test_retry_jitter() {
+        let policy: RetryPolicy = serde_json::from_str(
+            r#"{
+                "jitter_percent": 20,
+                "overrides": {"rate_limited": {"jitter_percent": 0}}
+            }"#,
+        )
+        .unwrap();
+        assert!(policy.validate().is_ok());
+
+        for _ in 0..20 {
+            let delay = policy.retry_delay("timeout", 1).unwrap();
+            assert!(delay >= Duration::from_secs(96) && delay <= Duration::from_secs(120));
+        }
+        assert_eq!(
+            policy.retry_delay("rate_limited", 1),
+            Some(Duration::from_secs(120))
+        );
+
+        let invalid: RetryPolicy = serde_json::from_str(r#"{"jitter_percent": 150}"#).unwrap();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_low_power_disables_hashing() {
+        let mut config = Config {
+            change_detection: ChangeDetection::Full,
+            ..Config::default()
+        };
+        assert_eq!(config.effective_change_detection(), ChangeDetection::Full);
+
+        config.low_power = true;
+        assert_eq!(config.effective_change_detection(), ChangeDetection::Quick);
+    }
+
     #[test]
     fn test_sync_job_status_display() {
         assert_eq!(SyncJobStatus::Pending.to_string(), "PENDING");
         assert_eq!(SyncJobStatus::Processing.to_string(), "PROCESSING");
         assert_eq!(SyncJobStatus::Synced.to_string(), "SYNCED");
         assert_eq!(SyncJobStatus::Blocked.to_string(), "BLOCKED");
+        assert_eq!(
+            SyncJobStatus::Skipped(SkipReason::PermissionDenied).to_string(),
+            "SKIPPED"
+        );
     }
 
     #[test]
@@ -252,11 +1105,35 @@ mod tests {
         assert_eq!(behavior, RemoteDeleteBehavior::Permanent);
     }
 
+    #[test]
+    fn test_remote_naming() {
+        let naming: RemoteNaming = serde_json::from_str("\"strip_forbidden\"").unwrap();
+        assert_eq!(naming, RemoteNaming::StripForbidden);
+
+        let name = " Résumé: Final\tDraft (v2).PDF";
+        assert_eq!(RemoteNaming::Preserve.apply(name), name);
+        assert_eq!(
+            RemoteNaming::StripForbidden.apply(name),
+            "Résumé: FinalDraft (v2).PDF"
+        );
+        assert_eq!(
+            RemoteNaming::Slugify.apply(name),
+            "resume-final-draft-v2.pdf"
+        );
+
+        assert_eq!(RemoteNaming::StripForbidden.apply("notes. "), "notes");
+        assert_eq!(RemoteNaming::StripForbidden.apply("\n"), "_");
+        assert_eq!(RemoteNaming::Slugify.apply(".bashrc"), "bashrc");
+        assert_eq!(RemoteNaming::Slugify.apply("報告書.pdf"), "_.pdf");
+        assert_eq!(RemoteNaming::Slugify.apply("My Photos"), "my-photos");
+    }
+
     #[test]
     fn test_sync_dir() {
         let sync_dir = SyncDir {
             source_path: "/local/path".to_string(),
             remote_root: "/remote/path".to_string(),
+            case_insensitive: None,
         };
 
         let serialized = serde_json::to_string(&sync_dir).unwrap();
@@ -266,6 +1143,40 @@ mod tests {
         assert_eq!(deserialized.remote_root, "/remote/path");
     }
 
+    #[test]
+    fn test_exclude_pattern_scope() {
+        let pattern = ExcludePattern {
+            path: "/repo".to_string(),
+            globs: vec!["*.o".to_string()],
+        };
+
+        assert!(pattern.matches(std::path::Path::new("/repo/src/main.o")));
+        assert!(!pattern.matches(std::path::Path::new("/other/main.o")));
+        assert!(!pattern.matches(std::path::Path::new("/repo/src/main.c")));
+
+        let global = ExcludePattern {
+            path: "/".to_string(),
+            globs: vec!["*.tmp".to_string()],
+        };
+        assert!(global.matches(std::path::Path::new("/any/where/file.tmp")));
+    }
+
+    #[test]
+    fn test_exclude_pattern_unicode() {
+        // Composed scope and glob, decomposed path as reported by macOS
+        let pattern = ExcludePattern {
+            path: "/home/résumé".to_string(),
+            globs: vec!["**/草稿*".to_string(), "*.café".to_string()],
+        };
+
+        let path = |p: &str| std::path::PathBuf::from(p);
+        assert!(pattern.matches(&path("/home/re\u{301}sume\u{301}/草稿 1.txt")));
+        assert!(pattern.matches(&path("/home/résumé/notes.cafe\u{301}")));
+        assert!(pattern.matches(&path("/home/résumé/🎉/草稿.md")));
+        assert!(!pattern.matches(&path("/home/résumé/שלום.txt")));
+        assert!(!pattern.matches(&path("/home/resume/草稿.txt")));
+    }
+
     #[test]
     fn test_session_serialize() {
         let session = Session {
@@ -274,6 +1185,7 @@ mod tests {
             refresh_token: "test_refresh".to_string(),
             key_password: Some("password".to_string()),
             primary_key: Some("key".to_string()),
+            expires_at: None,
         };
 
         let serialized = serde_json::to_string(&session).unwrap();
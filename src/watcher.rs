@@ -2,20 +2,113 @@
 
 use crate::config::ConfigManager;
 use crate::db::Db;
+use crate::debounce::{Debouncer, NetChange, DEBOUNCE_TICK};
 use crate::error::{Error, Result};
-use crate::types::{SyncEvent, SyncEventType};
+use crate::nosync;
+use crate::protonignore::{self, IgnoreRules};
+use crate::types::{
+    ChangeDetection, JobPriority, JobSource, NodeMapping, SkipReason, SyncEvent, SyncEventType,
+    SyncJobStatus,
+};
+use notify::event::{ModifyKind, RenameMode};
 use notify::{Event, EventKind, RecursiveMode, Watcher};
-use std::path::Path;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
 use tokio::sync::Mutex;
 use tracing::{debug, error, info, warn};
 
+/// Read buffer size used when hashing file contents
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
+/// How long the watcher expects events for a file the sync engine wrote
+pub const EXPECTED_CHANGE_TTL: chrono::Duration = chrono::Duration::minutes(5);
+
+/// How long after a watcher job synced a scan still adopts it for the same change
+const WATCHER_JOB_WINDOW: chrono::Duration = chrono::Duration::minutes(10);
+
+/// File events buffered between `notify` and the event handler
+const EVENT_CHANNEL_CAPACITY: usize = 100;
+
+/// Pending jobs at which the watcher stops taking in file events
+pub const QUEUE_HIGH_WATER: i64 = 10_000;
+
+/// Pending jobs below which it takes them in again
+pub const QUEUE_LOW_WATER: i64 = 5_000;
+
+/// How often the queue depth is checked
+const QUEUE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Jobs a scan queues per transaction
+const SCAN_BATCH_SIZE: usize = 1_000;
+
+/// An interrupted scan is resumed only if it started this recently
+const SCAN_RESUME_WINDOW: chrono::Duration = chrono::Duration::days(1);
+
+/// Dropped event paths kept before the whole tree is rescanned instead
+const MAX_DROPPED_PATHS: usize = 1_000;
+
+/// Whether file events should be held back, given the pending job count
+///
+/// Intake stops at the high-water mark and resumes below the low-water
+/// mark, so a queue hovering around one limit doesn't toggle it each check.
+pub fn backpressure(paused: bool, pending: i64) -> bool {
+    if paused {
+        pending >= QUEUE_LOW_WATER
+    } else {
+        pending >= QUEUE_HIGH_WATER
+    }
+}
+
+/// Paths of file events dropped because the event channel was full
+///
+/// Shared between the `notify` callback and the event handler, which
+/// rescans the affected sync directories once it takes events in again.
+#[derive(Clone, Default)]
+pub struct DroppedEvents(Arc<std::sync::Mutex<DroppedPaths>>);
+
+#[derive(Default)]
+struct DroppedPaths {
+    paths: HashSet<PathBuf>,
+    /// Too many paths, or an event without any, were dropped
+    all: bool,
+}
+
+impl DroppedEvents {
+    /// Remember the paths of a dropped event
+    pub fn record(&self, event: &Event) {
+        let mut dropped = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if dropped.all {
+            return;
+        }
+        if event.paths.is_empty() || dropped.paths.len() + event.paths.len() > MAX_DROPPED_PATHS {
+            dropped.paths.clear();
+            dropped.all = true;
+            return;
+        }
+        dropped.paths.extend(event.paths.iter().cloned());
+    }
+
+    /// Take the dropped paths, empty meaning everything, if any event was dropped
+    pub fn take(&self) -> Option<Vec<PathBuf>> {
+        let mut dropped = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let dropped = std::mem::take(&mut *dropped);
+        (dropped.all || !dropped.paths.is_empty()).then(|| dropped.paths.into_iter().collect())
+    }
+}
+
 /// File watcher
 pub struct FileWatcher {
     watcher: Option<notify::RecommendedWatcher>,
     db: Db,
     config: Arc<Mutex<ConfigManager>>,
+    ignore_rules: Arc<Mutex<IgnoreRules>>,
     running: Arc<Mutex<bool>>,
+    /// Event handler task, awaited on stop so held events reach the queue
+    handler: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl FileWatcher {
@@ -25,7 +118,9 @@ impl FileWatcher {
             watcher: None,
             db,
             config,
+            ignore_rules: Arc::new(Mutex::new(IgnoreRules::new())),
             running: Arc::new(Mutex::new(false)),
+            handler: None,
         })
     }
 
@@ -42,20 +137,33 @@ impl FileWatcher {
 
         let config = self.config.lock().await;
         let sync_dirs = config.get().sync_dirs.clone();
+        let window = Duration::from_millis(config.get().debounce_ms);
         drop(config);
 
-        // Create watcher
-        let (tx, mut rx) = tokio::sync::mpsc::channel(100);
+        // Load .protonignore rules
+        *self.ignore_rules.lock().await = IgnoreRules::load(&sync_dirs);
+
+        // Skipped temporary file events are counted per run
+        self.db.reset_temp_events().await?;
+
+        // Create watcher; events that don't fit in the channel are rescanned later
+        let (tx, rx) = tokio::sync::mpsc::channel(EVENT_CHANNEL_CAPACITY);
+        let dropped = DroppedEvents::default();
+        let overflow = dropped.clone();
 
         let mut watcher =
             notify::recommended_watcher(move |res: std::result::Result<Event, _>| {
                 if let Ok(event) = res {
-                    let _ = tx.blocking_send(event);
+                    if let Err(tokio::sync::mpsc::error::TrySendError::Full(event)) =
+                        tx.try_send(event)
+                    {
+                        overflow.record(&event);
+                    }
                 }
             })?;
 
         // Watch each sync directory
-        for sync_dir in sync_dirs {
+        for (i, sync_dir) in sync_dirs.iter().enumerate() {
             let path = Path::new(&sync_dir.source_path);
 
             if !path.exists() {
@@ -63,6 +171,15 @@ impl FileWatcher {
                 continue;
             }
 
+            // A nested sync directory is already covered by its parent's watch
+            let covered = sync_dirs.iter().enumerate().any(|(j, other)| {
+                let other = Path::new(&other.source_path);
+                path.starts_with(other) && (other != path || j < i)
+            });
+            if covered {
+                continue;
+            }
+
             watcher.watch(path, RecursiveMode::Recursive)?;
             info!("Watching: {}", sync_dir.source_path);
         }
@@ -70,15 +187,112 @@ impl FileWatcher {
         self.watcher = Some(watcher);
 
         // Spawn event handler task
-        let db = self.db.clone();
-        let config = self.config.clone();
-        let running = self.running.clone();
+        let handler = EventHandler::new(
+            self.db.clone(),
+            self.config.clone(),
+            self.ignore_rules.clone(),
+            window,
+        )
+        .with_dropped_events(dropped);
+        self.handler = Some(tokio::spawn(handler.run(rx, self.running.clone())));
+
+        Ok(())
+    }
+
+    /// Stop watching
+    ///
+    /// Waits for the event handler to queue the events it still holds.
+    pub async fn stop(&mut self) -> Result<()> {
+        info!("Stopping file watcher");
+        *self.running.lock().await = false;
+        self.watcher = None;
+
+        if let Some(handler) = self.handler.take() {
+            if let Err(e) = handler.await {
+                error!("File event handler failed: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Find sync directory for a path
+    ///
+    /// With nested sync directories the innermost one wins; among identical
+    /// sources the first configured one does.
+    pub fn find_sync_dir<'a>(
+        path: &Path,
+        config: &'a crate::types::Config,
+    ) -> Result<Option<&'a crate::types::SyncDir>> {
+        Ok(config
+            .sync_dirs
+            .iter()
+            .rev()
+            .filter(|sync_dir| path.starts_with(&sync_dir.source_path))
+            .max_by_key(|sync_dir| Path::new(&sync_dir.source_path).components().count()))
+    }
+}
+
+/// Turns file system events into sync jobs
+///
+/// The daemon feeds it events from `notify`. The current time is passed in
+/// with each call, so a made-up event sequence can be replayed and the
+/// moment paths settle chosen by the caller.
+pub struct EventHandler {
+    db: Db,
+    config: Arc<Mutex<ConfigManager>>,
+    ignore_rules: Arc<Mutex<IgnoreRules>>,
+    debouncer: Debouncer,
+    /// Skipped temporary file events per sync directory, not yet recorded
+    temp_events: HashMap<String, i64>,
+    dropped: DroppedEvents,
+    /// Whether file events are held back because the queue is too deep
+    paused: bool,
+}
+
+impl EventHandler {
+    /// Create a handler that holds events until a path is quiet for `window`
+    pub fn new(
+        db: Db,
+        config: Arc<Mutex<ConfigManager>>,
+        ignore_rules: Arc<Mutex<IgnoreRules>>,
+        window: Duration,
+    ) -> Self {
+        Self {
+            db,
+            config,
+            ignore_rules,
+            debouncer: Debouncer::new(window),
+            temp_events: HashMap::new(),
+            dropped: DroppedEvents::default(),
+            paused: false,
+        }
+    }
+
+    /// Rescan the paths of events the watcher had to drop
+    pub fn with_dropped_events(mut self, dropped: DroppedEvents) -> Self {
+        self.dropped = dropped;
+        self
+    }
+
+    /// Handle events until the source closes or the watcher is stopped
+    ///
+    /// While the queue is too deep no events are read, so the channel fills
+    /// up and further events are dropped; their paths are rescanned once the
+    /// queue has drained. Events still held or waiting in the channel are
+    /// queued before it returns.
+    pub async fn run(
+        mut self,
+        mut events: tokio::sync::mpsc::Receiver<Event>,
+        running: Arc<Mutex<bool>>,
+    ) {
+        let mut tick = tokio::time::interval(DEBOUNCE_TICK);
+        let mut queue_check = tokio::time::interval(QUEUE_CHECK_INTERVAL);
 
-        tokio::spawn(async move {
-            while *running.lock().await {
-                match rx.recv().await {
+        while *running.lock().await {
+            tokio::select! {
+                event = events.recv(), if !self.paused => match event {
                     Some(event) => {
-                        if let Err(e) = Self::handle_event(event, &db, &config).await {
+                        if let Err(e) = self.handle_event(event, Instant::now()).await {
                             error!("Error handling file event: {}", e);
                         }
                     }
@@ -86,23 +300,91 @@ impl FileWatcher {
                         debug!("Event channel closed");
                         break;
                     }
+                },
+                _ = tick.tick() => self.flush(Instant::now()).await,
+                _ = queue_check.tick() => {
+                    if let Err(e) = self.check_queue().await {
+                        error!("Error checking the sync queue: {}", e);
+                    }
                 }
             }
-        });
+        }
 
-        Ok(())
+        while let Ok(event) = events.try_recv() {
+            if let Err(e) = self.handle_event(event, Instant::now()).await {
+                error!("Error handling file event: {}", e);
+            }
+        }
+        self.drain().await;
     }
 
-    /// Stop watching
-    pub async fn stop(&mut self) -> Result<()> {
-        info!("Stopping file watcher");
-        *self.running.lock().await = false;
-        self.watcher = None;
+    /// Pause or resume taking in events by the queue depth, and rescan the
+    /// paths of dropped events once events are taken in
+    pub async fn check_queue(&mut self) -> Result<()> {
+        let pending = self.db.get_job_count(SyncJobStatus::Pending).await?;
+        let paused = backpressure(self.paused, pending);
+        if paused != self.paused {
+            if paused {
+                warn!(
+                    "{} jobs are pending, pausing file events until the queue drains",
+                    pending
+                );
+            } else {
+                info!("{} jobs are pending, resuming file events", pending);
+            }
+            self.paused = paused;
+        }
+
+        if !self.paused {
+            if let Some(paths) = self.dropped.take() {
+                self.rescan(&paths).await?;
+            }
+        }
         Ok(())
     }
 
-    /// Handle a file system event
-    async fn handle_event(event: Event, db: &Db, config: &Arc<Mutex<ConfigManager>>) -> Result<()> {
+    /// Queue the paths settled by `now` and record skipped temporary file events
+    pub async fn flush(&mut self, now: Instant) {
+        let settled = self.debouncer.take_settled(now);
+        self.queue_settled(settled).await;
+    }
+
+    /// Queue every held path, settled or not, and record skipped temporary
+    /// file events
+    pub async fn drain(&mut self) {
+        let held = self.debouncer.take_all();
+        if !held.is_empty() {
+            info!("Queueing {} path(s) with unsettled file events", held.len());
+        }
+        self.queue_settled(held).await;
+    }
+
+    async fn queue_settled(&mut self, paths: Vec<(PathBuf, bool)>) {
+        for (path, existed_before) in paths {
+            if let Err(e) = self.handle_settled(&path, existed_before).await {
+                error!("Error handling file event: {}", e);
+            }
+        }
+
+        for (sync_dir, count) in self.temp_events.drain() {
+            if let Err(e) = self.db.add_temp_events(&sync_dir, count).await {
+                debug!("Failed to record temporary file events: {}", e);
+            }
+        }
+    }
+
+    /// Handle a file system event received at `now`
+    ///
+    /// Creates, modifications and removals are held in the debouncer until
+    /// the path settles; renames and `.protonignore` changes apply at once.
+    /// Events for temporary files are only counted. When the platform
+    /// reports lost events, the affected sync directories are rescanned.
+    pub async fn handle_event(&mut self, event: Event, now: Instant) -> Result<()> {
+        // Dropped events, e.g. an inotify queue overflow, carry no paths
+        if event.need_rescan() {
+            return self.rescan(&event.paths).await;
+        }
+
         // Skip events with no paths
         if event.paths.is_empty() {
             return Ok(());
@@ -110,148 +392,329 @@ impl FileWatcher {
 
         let path = &event.paths[0];
 
-        // Skip temporary files
-        if Self::is_temp_file(path) {
+        // Hot-reload .protonignore when it changes at a sync root
+        if protonignore::is_ignore_file(path) {
+            let cfg = self.config.lock().await;
+            let root = path.parent().and_then(|parent| {
+                cfg.get()
+                    .sync_dirs
+                    .iter()
+                    .find(|d| Path::new(&d.source_path) == parent)
+                    .map(|d| d.source_path.clone())
+            });
+            drop(cfg);
+
+            if let Some(root) = root {
+                self.ignore_rules.lock().await.reload(Path::new(&root));
+                info!("Reloaded {} for {}", protonignore::PROTONIGNORE_FILE, root);
+            }
             return Ok(());
         }
 
-        // Check if path is in a sync directory
-        let cfg = config.lock().await;
-        let sync_dir = Self::find_sync_dir(path, cfg.get())?;
+        // A rename with both ends known becomes a single move job
+        if event.kind == EventKind::Modify(ModifyKind::Name(RenameMode::Both)) {
+            if let [from, to] = event.paths.as_slice() {
+                // Earlier events for either end must be queued before the move
+                for path in [from, to] {
+                    if let Some(existed_before) = self.debouncer.take(path) {
+                        self.handle_settled(path, existed_before).await?;
+                    }
+                }
+                return self.handle_rename(from, to).await;
+            }
+            return Ok(());
+        }
 
-        if sync_dir.is_none() {
+        if let Some(sync_dir) = self.temp_file_root(path).await {
+            *self.temp_events.entry(sync_dir).or_default() += 1;
             return Ok(());
         }
 
-        // Clone the sync dir data so we can drop the lock
-        let sync_dir_data = sync_dir.unwrap().clone();
-        drop(cfg);
+        if !self.debouncer.push(path, &event.kind, now) {
+            debug!("Ignoring event kind: {:?}", event.kind);
+        }
 
-        // Determine event type
-        let event_type = match event.kind {
-            EventKind::Create(_) => {
-                if path.is_dir() {
-                    SyncEventType::CreateDir
-                } else {
-                    SyncEventType::CreateFile
-                }
+        Ok(())
+    }
+
+    /// Scan the sync directories holding any of `paths`, or all without paths
+    async fn rescan(&self, paths: &[std::path::PathBuf]) -> Result<()> {
+        let cfg = self.config.lock().await.get().clone();
+
+        for sync_dir in &cfg.sync_dirs {
+            let source = Path::new(&sync_dir.source_path);
+            let affected = paths.is_empty()
+                || paths
+                    .iter()
+                    .any(|path| path.starts_with(source) || source.starts_with(path));
+            if !affected {
+                continue;
             }
-            EventKind::Modify(_) => SyncEventType::Update,
-            EventKind::Remove(_) => SyncEventType::Delete,
-            _ => {
-                debug!("Ignoring event kind: {:?}", event.kind);
+
+            warn!("File events were lost, rescanning {}", sync_dir.source_path);
+            FileScanner::scan_directory(
+                &self.db,
+                &sync_dir.source_path,
+                &sync_dir.remote_root,
+                &cfg,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Queue the net change to a path whose events have settled
+    async fn handle_settled(&self, path: &Path, existed_before: bool) -> Result<()> {
+        let event_type = match NetChange::of(existed_before, path.exists()) {
+            NetChange::Created if path.is_dir() => SyncEventType::CreateDir,
+            NetChange::Created => SyncEventType::CreateFile,
+            NetChange::Modified => SyncEventType::Update,
+            NetChange::Removed => SyncEventType::Delete,
+            NetChange::Transient => {
+                debug!("Skipping short-lived path: {}", path.display());
                 return Ok(());
             }
         };
 
-        // Get relative path
-        let base = Path::new(&sync_dir_data.source_path);
-        let relative = path
-            .strip_prefix(base)
-            .map_err(|_| Error::InvalidPath("Path not in sync directory".to_string()))?;
-
-        let local_path = path.to_string_lossy().to_string();
-        let remote_path =
-            crate::proton::PathUtils::join(&sync_dir_data.remote_root, &relative.to_string_lossy());
-
-        // Check exclusions
-        if Self::is_excluded(path, &config.lock().await.get().exclude_patterns) {
-            debug!("Path excluded: {}", local_path);
+        let Some((_, remote_path)) = self.resolve_path(path).await? else {
             return Ok(());
-        }
-
-        // Generate change token
-        let change_token = if event_type != SyncEventType::Delete {
-            Self::generate_change_token(path).await?
-        } else {
-            None
         };
+        let local_path = path.to_string_lossy().to_string();
 
-        // Create sync event
-        let sync_event = SyncEvent {
+        let mut sync_event = SyncEvent {
             event_type,
             local_path,
             remote_path,
-            change_token,
+            change_token: None,
             old_local_path: None,
             old_remote_path: None,
         };
+        let threshold = self.config.lock().await.get().small_file_threshold;
+
+        // A name changed only in case still "exists" under its old name
+        if event_type != SyncEventType::Delete && self.is_case_insensitive(path).await {
+            if !has_exact_name(path).await {
+                debug!("Skipping {}: renamed to a different case", path.display());
+                return Ok(());
+            }
+            if let Some(previous) = case_renamed_from(&self.db, &sync_event.local_path).await? {
+                sync_event.event_type = SyncEventType::Update;
+                sync_event.old_local_path = Some(previous.local_path);
+                sync_event.old_remote_path = Some(previous.remote_path);
+            }
+        }
+
+        // Our own writes, such as downloads, are already in sync
+        if event_type != SyncEventType::Delete
+            && matches!(is_expected_change(&self.db, path).await, Ok(true))
+        {
+            debug!("Ignoring our own change to {}", path.display());
+            return Ok(());
+        }
+
+        // Generate change token
+        if event_type != SyncEventType::Delete {
+            let mode = self.config.lock().await.get().effective_change_detection();
+            let token = match check_readable(path).await {
+                Ok(()) => change_token(path, mode).await,
+                Err(e) => Err(e),
+            };
+            match token {
+                Ok(token) => {
+                    // Readable again; this event supersedes a skipped job
+                    self.db.delete_skipped_jobs(&sync_event.local_path).await?;
+                    sync_event.change_token = Some(token);
+                }
+                Err(e) if e.is_permission_denied() => {
+                    return skip_unreadable(
+                        &self.db,
+                        &sync_event,
+                        threshold,
+                        JobSource::Watcher,
+                        &e,
+                    )
+                    .await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
 
         // Enqueue the job
-        db.enqueue_job(&sync_event).await?;
+        enqueue(&self.db, &sync_event, threshold, JobSource::Watcher).await?;
 
         debug!("Enqueued job: {:?} {:?}", event_type, sync_event.local_path);
 
         Ok(())
     }
 
-    /// Check if file is temporary
-    fn is_temp_file(path: &Path) -> bool {
-        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    /// Whether the sync directory of a path ignores case in names
+    async fn is_case_insensitive(&self, path: &Path) -> bool {
+        let cfg = self.config.lock().await;
+        FileWatcher::find_sync_dir(path, cfg.get())
+            .ok()
+            .flatten()
+            .is_some_and(|d| d.is_case_insensitive())
+    }
 
-        // Skip hidden files (starting with .)
-        if file_name.starts_with('.') {
-            return true;
-        }
+    /// Enqueue a move job for a path renamed within a sync directory
+    ///
+    /// Renames into or out of a sync directory, or across sync directories,
+    /// are left to the separate old-name and new-name events, which queue a
+    /// delete and an upload.
+    async fn handle_rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let Some((to_root, remote_path)) = self.resolve_path(to).await? else {
+            return Ok(());
+        };
+        let Some((from_root, old_remote_path)) = self.resolve_path(from).await? else {
+            return Ok(());
+        };
 
-        // Skip common temporary patterns
-        if file_name.contains('~')
-            || file_name.ends_with(".tmp")
-            || file_name.ends_with(".swp")
-            || file_name.starts_with("._")
-        {
-            return true;
+        if from_root != to_root {
+            return Ok(());
         }
 
-        false
+        let sync_event = SyncEvent {
+            event_type: SyncEventType::Update,
+            local_path: to.to_string_lossy().to_string(),
+            remote_path,
+            change_token: Some(
+                change_token(
+                    to,
+                    self.config.lock().await.get().effective_change_detection(),
+                )
+                .await?,
+            ),
+            old_local_path: Some(from.to_string_lossy().to_string()),
+            old_remote_path: Some(old_remote_path),
+        };
+
+        self.db
+            .cancel_rename_halves(
+                sync_event.old_local_path.as_deref().unwrap_or_default(),
+                &sync_event.local_path,
+            )
+            .await?;
+        let threshold = self.config.lock().await.get().small_file_threshold;
+        enqueue(&self.db, &sync_event, threshold, JobSource::Watcher).await?;
+
+        debug!(
+            "Enqueued move: {:?} -> {:?}",
+            sync_event.old_local_path, sync_event.local_path
+        );
+
+        Ok(())
     }
 
-    /// Find sync directory for a path
-    fn find_sync_dir<'a>(
-        path: &Path,
-        config: &'a crate::types::Config,
-    ) -> Result<Option<&'a crate::types::SyncDir>> {
-        for sync_dir in &config.sync_dirs {
-            let base = Path::new(&sync_dir.source_path);
-            if path.starts_with(base) {
-                return Ok(Some(sync_dir));
-            }
+    /// Map a local path to its sync directory and remote path
+    ///
+    /// Returns `None` for paths that are not synced: hidden and temporary
+    /// files, paths outside every sync directory, and excluded, ignored or
+    /// opted-out paths.
+    async fn resolve_path(&self, path: &Path) -> Result<Option<(String, String)>> {
+        // Skip hidden files
+        if is_hidden_file(path) {
+            return Ok(None);
         }
-        Ok(None)
-    }
 
-    /// Check if path is excluded
-    fn is_excluded(path: &Path, patterns: &[crate::types::ExcludePattern]) -> bool {
-        let _path_str = path.to_string_lossy();
+        // Check if path is in a sync directory
+        let cfg = self.config.lock().await;
+        let sync_dir = FileWatcher::find_sync_dir(path, cfg.get())?;
 
-        for pattern in patterns {
-            for glob in &pattern.globs {
-                if let Ok(matcher) = glob::Pattern::new(glob) {
-                    if matcher.matches_path(path) {
-                        return true;
-                    }
-                }
-            }
+        if sync_dir.is_none() {
+            return Ok(None);
         }
 
-        false
-    }
+        // Clone the sync dir data so we can drop the lock
+        let sync_dir_data = sync_dir.unwrap().clone();
+        let naming = cfg.get().remote_naming;
+        drop(cfg);
+
+        // Get relative path
+        let base = Path::new(&sync_dir_data.source_path);
+        let relative = path
+            .strip_prefix(base)
+            .map_err(|_| Error::InvalidPath("Path not in sync directory".to_string()))?;
+
+        let local_path = path.to_string_lossy().to_string();
+        let remote_path = remote_path_for(
+            &self.db,
+            &sync_dir_data.source_path,
+            &sync_dir_data.remote_root,
+            path,
+            naming,
+        )
+        .await?;
+
+        // Check exclusions
+        if is_excluded(path, &self.config.lock().await.get().exclude_patterns) {
+            debug!("Path excluded: {}", local_path);
+            return Ok(None);
+        }
+
+        // A deleted path can't say whether it was a directory, which
+        // directory-only rules like `build/` depend on: ask the mapping
+        let is_dir = if path.exists() {
+            Some(path.is_dir())
+        } else {
+            self.db
+                .get_node_mapping(&local_path, &remote_path)
+                .await?
+                .map(|mapping| mapping.is_directory)
+        };
+        let ignore_rules = self.ignore_rules.lock().await;
+        let is_ignored = match is_dir {
+            Some(is_dir) => ignore_rules.is_ignored(path, is_dir),
+            // Never synced, so either kind may have been ignored
+            None => ignore_rules.is_ignored(path, false) || ignore_rules.is_ignored(path, true),
+        };
+        drop(ignore_rules);
 
-    /// Generate change token (mtime:size)
-    async fn generate_change_token(path: &Path) -> Result<Option<String>> {
-        let metadata = tokio::fs::metadata(path).await?;
+        let cfg = self.config.lock().await;
+        if cfg
+            .get()
+            .temp_file_patterns
+            .matches(relative, is_dir.unwrap_or(false))
+        {
+            debug!("Temporary file skipped: {}", local_path);
+            return Ok(None);
+        }
+        drop(cfg);
 
-        let mtime = metadata
-            .modified()
-            .map_err(Error::Io)?
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|_| Error::InvalidPath("Invalid modification time".to_string()))?
-            .as_secs();
+        if is_ignored {
+            debug!("Path ignored by .protonignore: {}", local_path);
+            return Ok(None);
+        }
 
-        let size = metadata.len();
+        if let Some(marked) = nosync::find_marked_root(base, path) {
+            debug!(
+                "Path inside opted-out directory {}: {}",
+                marked.display(),
+                local_path
+            );
+            return Ok(None);
+        }
 
-        Ok(Some(format!("{}:{}", mtime, size)))
+        Ok(Some((sync_dir_data.source_path, remote_path)))
     }
+
+    /// Sync directory of a path that is a temporary file, if it is one
+    async fn temp_file_root(&self, path: &Path) -> Option<String> {
+        let cfg = self.config.lock().await;
+        let sync_dir = FileWatcher::find_sync_dir(path, cfg.get()).ok()??;
+        let relative = path.strip_prefix(&sync_dir.source_path).ok()?;
+
+        cfg.get()
+            .temp_file_patterns
+            .matches(relative, path.is_dir())
+            .then(|| sync_dir.source_path.clone())
+    }
+}
+
+/// Check if file is hidden, including macOS `._` resource forks
+fn is_hidden_file(path: &Path) -> bool {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    file_name.starts_with('.')
 }
 
 /// File system scanner for reconciliation
@@ -259,95 +722,1468 @@ pub struct FileScanner;
 
 impl FileScanner {
     /// Scan a directory for changes
+    ///
+    /// Each folder is checkpointed once its files are queued, so an
+    /// interrupted scan resumes with the folders it had not finished.
+    /// Files deleted while unwatched are only looked for once a whole
+    /// generation has been scanned.
     pub async fn scan_directory(
         db: &Db,
         directory: &str,
         remote_root: &str,
-        exclusions: &[crate::types::ExcludePattern],
+        config: &crate::types::Config,
     ) -> Result<usize> {
-        info!("Scanning directory: {}", directory);
+        let change_detection = config.effective_change_detection();
+
+        // With identical sources only the first sync directory is scanned
+        let first_with_source = config.sync_dirs.iter().find(|d| d.source_path == directory);
+        if first_with_source.is_some_and(|d| d.remote_root != remote_root) {
+            warn!(
+                "Skipping scan of {} -> {}: an earlier sync directory has the same source",
+                directory, remote_root
+            );
+            return Ok(0);
+        }
+
+        let checkpoint = db.begin_scan(directory, SCAN_RESUME_WINDOW).await?;
+        if checkpoint.completed_dirs.is_empty() {
+            info!("Scanning directory: {}", directory);
+        } else {
+            info!(
+                "Resuming scan of {} (generation {}), {} folder(s) already done",
+                directory,
+                checkpoint.generation,
+                checkpoint.completed_dirs.len()
+            );
+        }
 
         let mut count = 0;
+        let mut filter = ScanFilter::new(directory, config);
+        let case_insensitive = first_with_source.is_some_and(|d| d.is_case_insensitive());
+        let mut case_renamed = HashSet::new();
+
+        // Jobs set aside for unreadable files, checked again as they are seen
+        let mut skipped: HashMap<String, i64> = db
+            .get_jobs_under(directory)
+            .await?
+            .into_iter()
+            .filter(|job| matches!(job.status, SyncJobStatus::Skipped(_)))
+            .map(|job| (job.local_path, job.id))
+            .collect();
+        let mut unreadable = 0;
+        let mut batch = ScanBatch::new(directory, config.small_file_threshold);
 
-        let mut entries = walkdir::WalkDir::new(directory)
+        // Stored change tokens, looked up once instead of per file; queued
+        // changes are checked again when their batch is flushed
+        let mut stored_states: HashMap<String, String> = db
+            .get_file_states_under(directory)
+            .await?
+            .into_iter()
+            .map(|state| (state.local_path, state.change_token))
+            .collect();
+
+        let entries = walkdir::WalkDir::new(directory)
             .follow_links(false)
+            .sort_by(newest_first())
             .into_iter()
-            .filter_entry(|e| !Self::is_excluded(e.path(), exclusions));
+            .filter_entry(|e| {
+                let done = e.file_type().is_dir()
+                    && checkpoint
+                        .completed_dirs
+                        .contains(e.path().to_string_lossy().as_ref());
+                !done && filter.admits(e)
+            });
+        // Folders being scanned, innermost last; the walk is done with one
+        // once it moves on to a path outside it
+        let mut open_dirs: Vec<PathBuf> = Vec::new();
 
-        while let Some(Ok(entry)) = entries.next() {
+        for entry in entries {
+            // An unreadable folder hides its contents; the rest is still scanned
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!("Cannot scan {}", e);
+                    unreadable += 1;
+                    continue;
+                }
+            };
             let path = entry.path();
 
+            while let Some(done) = open_dirs.pop_if(|dir| !path.starts_with(dir)) {
+                batch.complete_dir(done);
+            }
+            if entry.depth() > 0 && entry.file_type().is_dir() {
+                open_dirs.push(path.to_path_buf());
+            }
+
             // Skip directories themselves (we'll process their contents)
             if path.is_dir() {
                 continue;
             }
 
             let local_path = path.to_string_lossy().to_string();
-            let relative = path
-                .strip_prefix(directory)
-                .map_err(|_| Error::InvalidPath("Path not in base directory".to_string()))?;
+            let skipped_job = skipped.remove(&local_path);
 
-            let remote_path =
-                crate::proton::PathUtils::join(remote_root, &relative.to_string_lossy());
+            // Only files that need a job look up their remote path
+            let update = || async {
+                Ok::<_, Error>(SyncEvent {
+                    event_type: SyncEventType::Update,
+                    local_path: local_path.clone(),
+                    remote_path: remote_path_for(
+                        db,
+                        directory,
+                        remote_root,
+                        path,
+                        config.remote_naming,
+                    )
+                    .await?,
+                    change_token: None,
+                    old_local_path: None,
+                    old_remote_path: None,
+                })
+            };
 
             // Get current change token
-            let change_token = Self::generate_change_token(path).await?;
-
-            // Get stored file state
-            let stored_state = db.get_file_state(&local_path).await?;
+            let change_token = match change_token(path, change_detection).await {
+                Ok(token) => token,
+                Err(e) if e.is_permission_denied() => {
+                    unreadable += 1;
+                    if skipped_job.is_none() {
+                        let threshold = config.small_file_threshold;
+                        skip_unreadable(db, &update().await?, threshold, JobSource::Scanner, &e)
+                            .await?;
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
 
             // Check if file has changed
-            if let Some(stored) = stored_state {
-                if stored.change_token == change_token {
-                    continue; // No change
+            let stored = stored_states.remove(&local_path);
+            let first_seen = stored.is_none();
+            let unchanged = match stored {
+                Some(stored) if stored == change_token => true,
+                Some(stored) => {
+                    // Token from the other detection mode: compare like with like,
+                    // then switch the stored token over without re-uploading
+                    let stored_mode = ChangeDetection::of_token(&stored);
+                    let same = stored_mode != change_detection
+                        && self::change_token(path, stored_mode).await? == stored;
+                    if same {
+                        db.update_file_state(&local_path, &change_token).await?;
+                    }
+                    same
+                }
+                None => false,
+            };
+
+            if unchanged {
+                if skipped_job.is_some() {
+                    db.delete_skipped_jobs(&local_path).await?;
+                }
+                continue;
+            }
+
+            // Quick tokens only need metadata; uploading needs the content
+            if let Err(e) = check_readable(path).await {
+                if !e.is_permission_denied() {
+                    return Err(e);
+                }
+                unreadable += 1;
+                if skipped_job.is_none() {
+                    let threshold = config.small_file_threshold;
+                    skip_unreadable(db, &update().await?, threshold, JobSource::Scanner, &e)
+                        .await?;
+                }
+                continue;
+            }
+
+            // Readable again: the skipped job goes back into the queue
+            if let Some(id) = skipped_job {
+                db.requeue_skipped_job(id, &change_token).await?;
+                info!("{} can be read again, queued it", local_path);
+                count += 1;
+                continue;
+            }
+
+            // A new name that differs only in case is a rename
+            let mut sync_event = update().await?;
+            if first_seen && case_insensitive {
+                if let Some(previous) = case_renamed_from(db, &sync_event.local_path).await? {
+                    case_renamed.insert(previous.local_path.clone());
+                    sync_event.old_local_path = Some(previous.local_path);
+                    sync_event.old_remote_path = Some(previous.remote_path);
+                }
+            }
+
+            // The watcher may have queued this change already
+            if sync_event.old_local_path.is_none() {
+                if let Some(id) = db
+                    .find_watcher_job(&sync_event.local_path, &change_token, WATCHER_JOB_WINDOW)
+                    .await?
+                {
+                    warn!(
+                        "Scan found {} already queued by the watcher as job {}, adopting it",
+                        sync_event.local_path, id
+                    );
+                    continue;
                 }
             }
 
             // File is new or changed - enqueue update
-            let sync_event = SyncEvent {
-                event_type: SyncEventType::Update,
-                local_path,
-                remote_path,
-                change_token: Some(change_token),
+            sync_event.change_token = Some(change_token);
+            batch.push(db, sync_event).await?;
+        }
+        while let Some(done) = open_dirs.pop() {
+            batch.complete_dir(done);
+        }
+        batch.flush(db).await?;
+        count += batch.queued;
+
+        // Skipped files that are gone need nothing more
+        for local_path in skipped.keys() {
+            if matches!(Path::new(local_path).try_exists(), Ok(false)) {
+                db.delete_skipped_jobs(local_path).await?;
+            }
+        }
+
+        let deleted =
+            Self::queue_vanished(db, directory, remote_root, &filter, &case_renamed, config)
+                .await?;
+        count += deleted;
+
+        db.set_ignored_count(directory, filter.ignored).await?;
+        db.set_nosync_roots(directory, &filter.skipped_roots)
+            .await?;
+        db.finish_scan(directory).await?;
+
+        info!(
+            "Scan complete: {} changes detected ({} deleted), {} ignored, {} opted-out director(ies), {} unreadable",
+            count,
+            deleted,
+            filter.ignored,
+            filter.skipped_roots.len(),
+            unreadable
+        );
+        Ok(count)
+    }
+
+    /// Queue deletes for synced files that no longer exist locally
+    ///
+    /// Catches files removed while the watcher was not running. Nothing is
+    /// queued while the sync directory itself is missing, as with an
+    /// unmounted drive, and files that cannot be checked count as existing.
+    /// Files in `case_renamed` are moving to a name differing only in case.
+    async fn queue_vanished(
+        db: &Db,
+        directory: &str,
+        remote_root: &str,
+        filter: &ScanFilter<'_>,
+        case_renamed: &HashSet<String>,
+        config: &crate::types::Config,
+    ) -> Result<usize> {
+        if !Path::new(directory).is_dir() {
+            return Ok(0);
+        }
+
+        let prefix = format!("{}{}", directory, std::path::MAIN_SEPARATOR);
+        let mut batch = ScanBatch::new(directory, config.small_file_threshold);
+
+        for state in db.get_file_states_under(&prefix).await? {
+            let path = Path::new(&state.local_path);
+            if !path.starts_with(directory) {
+                continue;
+            }
+            // Nested sync directories look after their own files
+            if filter
+                .nested_roots
+                .iter()
+                .any(|root| path.starts_with(root))
+                || case_renamed.contains(&state.local_path)
+            {
+                continue;
+            }
+            if !matches!(path.try_exists(), Ok(false)) {
+                continue;
+            }
+
+            let event = SyncEvent {
+                event_type: SyncEventType::Delete,
+                local_path: state.local_path.clone(),
+                remote_path: remote_path_for(
+                    db,
+                    directory,
+                    remote_root,
+                    path,
+                    config.remote_naming,
+                )
+                .await?,
+                change_token: None,
                 old_local_path: None,
                 old_remote_path: None,
             };
+            debug!("Deleted while not watched: {}", state.local_path);
+            batch.push(db, event).await?;
+        }
+        batch.flush(db).await?;
 
-            db.enqueue_job(&sync_event).await?;
-            count += 1;
+        Ok(batch.queued)
+    }
+
+    /// Files and folders of a sync directory that are synced, parents first
+    ///
+    /// Leaves out what a scan leaves out: excluded, temporary and ignored
+    /// paths, opted-out folders and nested sync directories.
+    pub fn synced_entries(
+        directory: &str,
+        config: &crate::types::Config,
+    ) -> Vec<walkdir::DirEntry> {
+        let mut filter = ScanFilter::new(directory, config);
+
+        walkdir::WalkDir::new(directory)
+            .follow_links(false)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| filter.admits(e))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path() != Path::new(directory))
+            .collect()
+    }
+
+    /// Synced files and folders of a sync directory that were deleted locally
+    ///
+    /// Finds deletions the watcher never saw, e.g. while the daemon was
+    /// stopped. Only the topmost deleted folder is listed, since deleting it
+    /// remotely takes its contents along. Nothing is listed while the sync
+    /// directory itself is missing, as with an unmounted drive.
+    pub async fn find_deleted(
+        db: &Db,
+        directory: &str,
+        remote_root: &str,
+    ) -> Result<Vec<NodeMapping>> {
+        if !Path::new(directory).is_dir() {
+            warn!(
+                "Not looking for deleted files in {}: the directory is missing",
+                directory
+            );
+            return Ok(Vec::new());
         }
 
-        info!("Scan complete: {} changes detected", count);
+        // Orphans sort by path, so a folder comes before its contents
+        let mut deleted: Vec<NodeMapping> = Vec::new();
+        for mapping in db.get_orphan_node_mappings(&[Path::new(directory)]).await? {
+            let local = Path::new(&mapping.local_path);
+            if local == Path::new(directory)
+                || !Path::new(&mapping.remote_path).starts_with(remote_root)
+            {
+                continue;
+            }
+
+            let covered = deleted
+                .iter()
+                .any(|d| d.is_directory && local.starts_with(&d.local_path));
+            if !covered {
+                deleted.push(mapping);
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Queue remote deletes for files found by [`FileScanner::find_deleted`]
+    pub async fn prune_deleted(
+        db: &Db,
+        deleted: &[NodeMapping],
+        config: &crate::types::Config,
+    ) -> Result<usize> {
+        let mut count = 0;
+        for mapping in deleted {
+            let event = SyncEvent {
+                event_type: SyncEventType::Delete,
+                local_path: mapping.local_path.clone(),
+                remote_path: mapping.remote_path.clone(),
+                change_token: None,
+                old_local_path: None,
+                old_remote_path: None,
+            };
+            if enqueue(db, &event, config.small_file_threshold, JobSource::Scanner)
+                .await?
+                .is_some()
+            {
+                info!("Queued remote delete: {}", mapping.remote_path);
+                count += 1;
+            }
+        }
         Ok(count)
     }
+}
 
-    /// Check if path is excluded
-    fn is_excluded(path: &Path, patterns: &[crate::types::ExcludePattern]) -> bool {
-        for pattern in patterns {
-            for glob in &pattern.globs {
-                if let Ok(matcher) = glob::Pattern::new(glob) {
-                    if matcher.matches_path(path) {
-                        return true;
-                    }
+/// Whether a path's last component is on disk with exactly this case
+///
+/// On a case-insensitive filesystem `Report.txt` still opens after it was
+/// renamed to `report.txt`; only the folder listing tells them apart. A
+/// folder that cannot be listed counts as having the name.
+async fn has_exact_name(path: &Path) -> bool {
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return true;
+    };
+    let Ok(mut entries) = tokio::fs::read_dir(parent).await else {
+        return true;
+    };
+    loop {
+        match entries.next_entry().await {
+            Ok(Some(entry)) if entry.file_name() == name => return true,
+            Ok(Some(_)) => {}
+            Ok(None) => return false,
+            Err(_) => return true,
+        }
+    }
+}
+
+/// Mapping of a synced file whose name has since changed only in case
+///
+/// Given `report.txt` on a case-insensitive filesystem, returns the mapping
+/// of an earlier `Report.txt`, so the change syncs as a rename instead of a
+/// second upload.
+async fn case_renamed_from(db: &Db, local_path: &str) -> Result<Option<NodeMapping>> {
+    Ok(db
+        .get_node_mapping_ignoring_case(local_path)
+        .await?
+        .filter(|m| m.local_path != local_path))
+}
+
+/// Remote path for a path in the sync directory `source_path`
+///
+/// Reuses the remote path of the path's node mapping, or of its nearest
+/// synced folder; see [`crate::proton::PathUtils::from_local_mapped`].
+pub async fn remote_path_for(
+    db: &Db,
+    source_path: &str,
+    remote_root: &str,
+    path: &Path,
+    naming: crate::types::RemoteNaming,
+) -> Result<String> {
+    let source = Path::new(source_path);
+    let candidates: Vec<String> = path
+        .ancestors()
+        .take_while(|a| *a != source && a.starts_with(source))
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+    let mapped = db.get_remote_paths(&candidates).await?;
+
+    Ok(crate::proton::PathUtils::from_local_mapped(
+        remote_root,
+        source,
+        path,
+        naming,
+        |p| mapped.get(p.to_string_lossy().as_ref()).cloned(),
+    ))
+}
+
+/// Order entries of a folder by modification time, newest first
+///
+/// A folder's mtime changes when entries are added, removed or renamed in
+/// it, so recently active folders are scanned and queued before the rest of
+/// a large tree. Ties and entries without a readable mtime sort by name.
+/// Each entry is stat'ed once, not on every comparison.
+fn newest_first(
+) -> impl FnMut(&walkdir::DirEntry, &walkdir::DirEntry) -> std::cmp::Ordering + Send + Sync + 'static
+{
+    // Entries sorted together share a folder; the next folder starts afresh
+    let mut folder = PathBuf::new();
+    let mut mtimes: HashMap<std::ffi::OsString, Option<std::time::SystemTime>> = HashMap::new();
+
+    move |a, b| {
+        let parent = a.path().parent().unwrap_or(Path::new(""));
+        if parent != folder {
+            folder = parent.to_path_buf();
+            mtimes.clear();
+        }
+
+        let mut modified = |e: &walkdir::DirEntry| {
+            *mtimes
+                .entry(e.file_name().to_owned())
+                .or_insert_with(|| e.metadata().ok().and_then(|m| m.modified().ok()))
+        };
+        modified(b)
+            .cmp(&modified(a))
+            .then_with(|| a.file_name().cmp(b.file_name()))
+    }
+}
+
+/// Decides which paths a scan of one sync directory visits
+struct ScanFilter<'a> {
+    directory: &'a str,
+    config: &'a crate::types::Config,
+    /// Nested sync directories are scanned on their own
+    nested_roots: Vec<&'a Path>,
+    ignore_matcher: Option<ignore::gitignore::Gitignore>,
+    ignored: i64,
+    skipped_roots: Vec<String>,
+}
+
+impl<'a> ScanFilter<'a> {
+    fn new(directory: &'a str, config: &'a crate::types::Config) -> Self {
+        let nested_roots = config
+            .sync_dirs
+            .iter()
+            .map(|d| Path::new(&d.source_path))
+            .filter(|source| *source != Path::new(directory) && source.starts_with(directory))
+            .collect();
+
+        Self {
+            directory,
+            config,
+            nested_roots,
+            ignore_matcher: protonignore::load_matcher(Path::new(directory)),
+            ignored: 0,
+            skipped_roots: Vec::new(),
+        }
+    }
+
+    /// Whether to visit an entry, counting ignored paths and opted-out folders
+    fn admits(&mut self, e: &walkdir::DirEntry) -> bool {
+        if is_excluded(e.path(), &self.config.exclude_patterns) {
+            return false;
+        }
+        let relative = e.path().strip_prefix(self.directory).unwrap_or(e.path());
+        if self
+            .config
+            .temp_file_patterns
+            .matches(relative, e.file_type().is_dir())
+        {
+            return false;
+        }
+        if self.nested_roots.contains(&e.path()) {
+            return false;
+        }
+        if e.file_type().is_dir() && nosync::has_marker(e.path()) {
+            self.skipped_roots
+                .push(e.path().to_string_lossy().to_string());
+            return false;
+        }
+        let is_ignored = self
+            .ignore_matcher
+            .as_ref()
+            .is_some_and(|m| protonignore::is_match(m, e.path(), e.file_type().is_dir()));
+        if is_ignored {
+            self.ignored += 1;
+        }
+        !is_ignored
+    }
+}
+
+/// Check if path is excluded
+fn is_excluded(path: &Path, patterns: &[crate::types::ExcludePattern]) -> bool {
+    crate::trash::is_in_trash(path) || patterns.iter().any(|pattern| pattern.matches(path))
+}
+
+/// Queue a sync event, prioritized by the size of its file
+///
+/// `small_file_threshold` is in MiB.
+pub async fn enqueue(
+    db: &Db,
+    event: &SyncEvent,
+    small_file_threshold: u64,
+    source: JobSource,
+) -> Result<Option<i64>> {
+    let priority = priority_of(event, small_file_threshold).await;
+    db.enqueue_job_from(event, priority, source).await
+}
+
+/// Priority of a sync event by the size of its file, in MiB
+async fn priority_of(event: &SyncEvent, small_file_threshold: u64) -> JobPriority {
+    let size = tokio::fs::metadata(&event.local_path)
+        .await
+        .ok()
+        .map(|m| m.len());
+    JobPriority::of(
+        event,
+        size,
+        small_file_threshold.saturating_mul(1024 * 1024),
+    )
+}
+
+/// Scanner jobs queued together, a transaction per [`SCAN_BATCH_SIZE`]
+///
+/// Folders the scan finished are checkpointed with the batch holding their
+/// last jobs.
+struct ScanBatch {
+    sync_dir: String,
+    jobs: Vec<(SyncEvent, JobPriority)>,
+    completed_dirs: Vec<String>,
+    small_file_threshold: u64,
+    /// Jobs queued so far
+    queued: usize,
+}
+
+impl ScanBatch {
+    fn new(sync_dir: &str, small_file_threshold: u64) -> Self {
+        Self {
+            sync_dir: sync_dir.to_string(),
+            jobs: Vec::new(),
+            completed_dirs: Vec::new(),
+            small_file_threshold,
+            queued: 0,
+        }
+    }
+
+    /// Checkpoint a folder whose files were all added
+    fn complete_dir(&mut self, dir: PathBuf) {
+        self.completed_dirs.push(dir.to_string_lossy().into_owned());
+    }
+
+    /// Add a sync event, queueing the batch once it is full
+    async fn push(&mut self, db: &Db, event: SyncEvent) -> Result<()> {
+        let priority = priority_of(&event, self.small_file_threshold).await;
+        self.jobs.push((event, priority));
+        if self.jobs.len() >= SCAN_BATCH_SIZE {
+            self.flush(db).await?;
+        }
+        Ok(())
+    }
+
+    /// Queue the events added so far
+    ///
+    /// Changes whose token was stored since the scan compared it, as when
+    /// the watcher's job uploaded them meanwhile, are dropped.
+    async fn flush(&mut self, db: &Db) -> Result<()> {
+        let paths: Vec<String> = self
+            .jobs
+            .iter()
+            .filter(|(event, _)| event.change_token.is_some())
+            .map(|(event, _)| event.local_path.clone())
+            .collect();
+        let stored = db.get_change_tokens(&paths).await?;
+        self.jobs.retain(|(event, _)| {
+            event.change_token.is_none()
+                || stored.get(&event.local_path) != event.change_token.as_ref()
+        });
+
+        if !self.jobs.is_empty() {
+            db.enqueue_jobs_batch(&self.jobs, JobSource::Scanner)
+                .await?;
+        }
+        self.queued += self.jobs.len();
+        self.jobs.clear();
+
+        if !self.completed_dirs.is_empty() {
+            db.complete_scan_dirs(&self.sync_dir, &self.completed_dirs)
+                .await?;
+            self.completed_dirs.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Set a change aside because its path cannot be read
+///
+/// The event is queued and marked skipped, so it shows up in the skip
+/// report; a path that is already skipped keeps its job. A later scan
+/// requeues it once the path can be read.
+pub async fn skip_unreadable(
+    db: &Db,
+    event: &SyncEvent,
+    small_file_threshold: u64,
+    source: JobSource,
+    error: &Error,
+) -> Result<()> {
+    let already_skipped = db
+        .get_jobs_under(&event.local_path)
+        .await?
+        .iter()
+        .any(|job| {
+            job.local_path == event.local_path && matches!(job.status, SyncJobStatus::Skipped(_))
+        });
+    if already_skipped {
+        return Ok(());
+    }
+
+    warn!("Skipping {}: {}", event.local_path, error);
+    if let Some(id) = enqueue(db, event, small_file_threshold, source).await? {
+        let message = format!("{}. {}", error, error.hint());
+        db.update_job_status(
+            id,
+            SyncJobStatus::Skipped(SkipReason::PermissionDenied),
+            Some(&message),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Check that a file's content can be read, by opening it
+///
+/// Folders are not checked.
+async fn check_readable(path: &Path) -> Result<()> {
+    if !path.is_dir() {
+        tokio::fs::File::open(path).await?;
+    }
+    Ok(())
+}
+
+/// Write a file for the sync engine itself, so it is not uploaded back
+///
+/// The content goes to a hidden file next to `path` first. Its change token
+/// is recorded before it is renamed into place, which keeps the token, so
+/// the watcher expects the change before any event for it can arrive. Scans
+/// find the file unchanged.
+pub async fn write_own_file(
+    db: &Db,
+    path: &Path,
+    content: &[u8],
+    mode: ChangeDetection,
+) -> Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| Error::InvalidPath(path.display().to_string()))?;
+    let mut partial = std::ffi::OsString::from(".");
+    partial.push(name);
+    partial.push(".pds-partial");
+    let partial = path.with_file_name(partial);
+
+    let written = async {
+        tokio::fs::write(&partial, content).await?;
+        let token = change_token(&partial, mode).await?;
+        db.expect_change(&path.to_string_lossy(), &token).await?;
+        tokio::fs::rename(&partial, path).await?;
+        Ok::<_, Error>(token)
+    }
+    .await;
+    let token = match written {
+        Ok(token) => token,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+    };
+
+    db.update_file_state(&path.to_string_lossy(), &token).await
+}
+
+/// Whether a file has the content the sync engine recently wrote to it
+///
+/// The token is computed in the mode the expected one was, which need not
+/// be the one configured now.
+async fn is_expected_change(db: &Db, path: &Path) -> Result<bool> {
+    let Some(expected) = db
+        .get_expected_change(&path.to_string_lossy(), EXPECTED_CHANGE_TTL)
+        .await?
+    else {
+        return Ok(false);
+    };
+    let current = change_token(path, ChangeDetection::of_token(&expected)).await?;
+    Ok(current == expected)
+}
+
+/// Generate a change token
+///
+/// Quick tokens are `mtime:size`; full tokens are `sha256:<hex digest>` of
+/// the file contents.
+pub async fn change_token(path: &Path, mode: ChangeDetection) -> Result<String> {
+    match mode {
+        ChangeDetection::Quick => {
+            let metadata = tokio::fs::metadata(path).await?;
+
+            let mtime = metadata
+                .modified()
+                .map_err(Error::Io)?
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_err(|_| Error::InvalidPath("Invalid modification time".to_string()))?
+                .as_secs();
+
+            let size = metadata.len();
+
+            Ok(format!("{}:{}", mtime, size))
+        }
+        ChangeDetection::Full => {
+            let mut file = tokio::fs::File::open(path).await?;
+            let mut hasher = Sha256::new();
+            let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+
+            loop {
+                let read = file.read(&mut buffer).await?;
+                if read == 0 {
+                    break;
                 }
+                hasher.update(&buffer[..read]);
             }
+
+            Ok(format!("sha256:{}", hex::encode(hasher.finalize())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Config, SyncDir};
+    use notify::event::{CreateKind, DataChange, Flag, RemoveKind};
+    use tempfile::TempDir;
+
+    const WINDOW: Duration = Duration::from_secs(1);
+
+    /// An event handler for one sync directory, and that directory
+    async fn handler(temp_dir: &TempDir) -> (EventHandler, Db, PathBuf) {
+        let root = temp_dir.path().canonicalize().unwrap().join("sync");
+        std::fs::create_dir_all(&root).unwrap();
+
+        let db = Db::new(temp_dir.path().join("test.db")).await.unwrap();
+        let config = Config {
+            sync_dirs: vec![SyncDir {
+                source_path: root.to_string_lossy().into_owned(),
+                remote_root: "/Sync".to_string(),
+                case_insensitive: None,
+            }],
+            ..Config::default()
+        };
+        let config = ConfigManager::with_config(temp_dir.path().join("config.json"), config);
+
+        let handler = EventHandler::new(
+            db.clone(),
+            Arc::new(Mutex::new(config)),
+            Arc::new(Mutex::new(IgnoreRules::new())),
+            WINDOW,
+        );
+        (handler, db, root)
+    }
+
+    fn event(kind: EventKind, paths: &[&Path]) -> Event {
+        paths.iter().fold(Event::new(kind), |event, path| {
+            event.add_path(path.to_path_buf())
+        })
+    }
+
+    /// Queued jobs as (event type, local path, old local path)
+    async fn jobs(db: &Db) -> Vec<(SyncEventType, String, Option<String>)> {
+        db.get_all_jobs()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|job| (job.event_type, job.local_path, job.old_local_path))
+            .collect()
+    }
+
+    fn local(path: &Path) -> String {
+        path.to_string_lossy().into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_create_storm_queues_one_job_once_settled() {
+        let temp_dir = TempDir::new().unwrap();
+        let (mut handler, db, root) = handler(&temp_dir).await;
+        let file = root.join("report.txt");
+        let scratch = root.join("scratch.txt");
+        let start = Instant::now();
+
+        std::fs::write(&file, "final").unwrap();
+        handler
+            .handle_event(event(EventKind::Create(CreateKind::File), &[&file]), start)
+            .await
+            .unwrap();
+        for i in 1..20 {
+            let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+            handler
+                .handle_event(
+                    event(modify, &[&file]),
+                    start + Duration::from_millis(i * 10),
+                )
+                .await
+                .unwrap();
+        }
+
+        // Created and removed within the window: nothing to sync
+        for kind in [
+            EventKind::Create(CreateKind::File),
+            EventKind::Remove(RemoveKind::File),
+        ] {
+            handler
+                .handle_event(event(kind, &[&scratch]), start)
+                .await
+                .unwrap();
+        }
+
+        handler.flush(start + Duration::from_millis(500)).await;
+        assert!(jobs(&db).await.is_empty());
+
+        handler.flush(start + WINDOW + Duration::from_secs(1)).await;
+        assert_eq!(
+            jobs(&db).await,
+            vec![(SyncEventType::CreateFile, local(&file), None)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stopping_queues_held_events() {
+        let temp_dir = TempDir::new().unwrap();
+        let (handler, db, root) = handler(&temp_dir).await;
+        let held = root.join("held.txt");
+        let unread = root.join("unread.txt");
+        std::fs::write(&held, "held").unwrap();
+        std::fs::write(&unread, "unread").unwrap();
+
+        let (tx, rx) = tokio::sync::mpsc::channel(10);
+        let running = Arc::new(Mutex::new(true));
+        let task = tokio::spawn(handler.run(rx, running.clone()));
+
+        tx.send(event(EventKind::Create(CreateKind::File), &[&held]))
+            .await
+            .unwrap();
+        tokio::task::yield_now().await;
+        *running.lock().await = false;
+        tx.send(event(EventKind::Create(CreateKind::File), &[&unread]))
+            .await
+            .unwrap();
+
+        // The source closing ends the handler well within the settle window
+        drop(tx);
+        task.await.unwrap();
+        assert_eq!(
+            jobs(&db).await,
+            vec![
+                (SyncEventType::CreateFile, local(&held), None),
+                (SyncEventType::CreateFile, local(&unread), None)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deleting_an_ignored_directory_queues_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let (mut handler, db, root) = handler(&temp_dir).await;
+        std::fs::write(root.join(protonignore::PROTONIGNORE_FILE), "build/\n").unwrap();
+        handler.ignore_rules.lock().await.reload(&root);
+
+        let build = root.join("build");
+        let output = build.join("app.o");
+        std::fs::create_dir_all(&build).unwrap();
+        std::fs::write(&output, "object").unwrap();
+        let start = Instant::now();
+        handler
+            .handle_event(
+                event(EventKind::Create(CreateKind::Folder), &[&build]),
+                start,
+            )
+            .await
+            .unwrap();
+        handler.flush(start + WINDOW + Duration::from_secs(1)).await;
+        assert!(jobs(&db).await.is_empty());
+
+        // Once deleted, `build` is no longer known to be a directory
+        std::fs::remove_dir_all(&build).unwrap();
+        let start = Instant::now();
+        for (kind, path) in [
+            (EventKind::Remove(RemoveKind::File), &output),
+            (EventKind::Remove(RemoveKind::Folder), &build),
+        ] {
+            handler
+                .handle_event(event(kind, &[path]), start)
+                .await
+                .unwrap();
+        }
+        handler.flush(start + WINDOW + Duration::from_secs(1)).await;
+        assert!(jobs(&db).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rename_pair_queues_a_move() {
+        let temp_dir = TempDir::new().unwrap();
+        let (mut handler, db, root) = handler(&temp_dir).await;
+        let from = root.join("draft.txt");
+        let to = root.join("final.txt");
+        let now = Instant::now();
+
+        // Written, then renamed before its events settled
+        std::fs::write(&to, "text").unwrap();
+        handler
+            .handle_event(event(EventKind::Create(CreateKind::File), &[&from]), now)
+            .await
+            .unwrap();
+        handler
+            .handle_event(
+                event(
+                    EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                    &[&from, &to],
+                ),
+                now,
+            )
+            .await
+            .unwrap();
+
+        // The create of a path that is gone is dropped; only the move remains
+        handler.flush(now + WINDOW).await;
+        assert_eq!(
+            jobs(&db).await,
+            vec![(SyncEventType::Update, local(&to), Some(local(&from)))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_temporary_files_are_only_counted() {
+        let temp_dir = TempDir::new().unwrap();
+        let (mut handler, db, root) = handler(&temp_dir).await;
+        let lock_file = root.join("~$budget.xlsx");
+        let now = Instant::now();
+
+        std::fs::write(&lock_file, "lock").unwrap();
+        handler
+            .handle_event(
+                event(EventKind::Create(CreateKind::File), &[&lock_file]),
+                now,
+            )
+            .await
+            .unwrap();
+        handler.flush(now + WINDOW).await;
+
+        assert!(jobs(&db).await.is_empty());
+        assert_eq!(
+            db.get_temp_event_counts().await.unwrap(),
+            vec![(local(&root), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_overflow_rescans_sync_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let (mut handler, db, root) = handler(&temp_dir).await;
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("docs/a.txt"), "a").unwrap();
+        std::fs::write(root.join("b.txt"), "b").unwrap();
+
+        // The events for these files were lost
+        let overflow = Event::new(EventKind::Other).set_flag(Flag::Rescan);
+        handler
+            .handle_event(overflow, Instant::now())
+            .await
+            .unwrap();
+
+        let mut queued: Vec<String> = jobs(&db)
+            .await
+            .into_iter()
+            .map(|(_, path, _)| path)
+            .collect();
+        queued.sort();
+        assert_eq!(
+            queued,
+            vec![local(&root.join("b.txt")), local(&root.join("docs/a.txt"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_scan_resumes_with_unfinished_folders() {
+        let temp_dir = TempDir::new().unwrap();
+        let (handler, db, root) = handler(&temp_dir).await;
+        let config = handler.config.lock().await.get().clone();
+        let source = local(&root);
+        for dir in ["big/done", "big/todo"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join("a.txt"), dir).unwrap();
         }
-        false
+
+        // A scan stopped after finishing a folder inside a larger one
+        let checkpoint = db.begin_scan(&source, SCAN_RESUME_WINDOW).await.unwrap();
+        db.complete_scan_dirs(&source, &[local(&root.join("big/done"))])
+            .await
+            .unwrap();
+
+        FileScanner::scan_directory(&db, &source, "/Sync", &config)
+            .await
+            .unwrap();
+        let queued: Vec<String> = jobs(&db)
+            .await
+            .into_iter()
+            .map(|(_, path, _)| path)
+            .collect();
+        assert_eq!(queued, vec![local(&root.join("big/todo/a.txt"))]);
+
+        // The generation is complete; the next scan starts a new one
+        let next = db.begin_scan(&source, SCAN_RESUME_WINDOW).await.unwrap();
+        assert_eq!(next.generation, checkpoint.generation + 1);
+        assert!(next.completed_dirs.is_empty());
+
+        // An unfinished generation that is too old is not resumed
+        db.complete_scan_dirs(&source, &[local(&root.join("big"))])
+            .await
+            .unwrap();
+        let stale = db
+            .begin_scan(&source, chrono::Duration::zero())
+            .await
+            .unwrap();
+        assert_eq!(stale.generation, next.generation + 1);
+        assert!(stale.completed_dirs.is_empty());
+    }
+
+    #[test]
+    fn test_backpressure_pauses_between_water_marks() {
+        assert!(!backpressure(false, QUEUE_LOW_WATER));
+        assert!(backpressure(false, QUEUE_HIGH_WATER));
+        // Draining, but not yet below the low-water mark
+        assert!(backpressure(true, QUEUE_LOW_WATER));
+        assert!(!backpressure(true, QUEUE_LOW_WATER - 1));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_events_are_rescanned() {
+        let temp_dir = TempDir::new().unwrap();
+        let (handler, db, root) = handler(&temp_dir).await;
+        let file = root.join("copied.txt");
+        std::fs::write(&file, "copied").unwrap();
+
+        let dropped = DroppedEvents::default();
+        let mut handler = handler.with_dropped_events(dropped.clone());
+        dropped.record(&event(EventKind::Create(CreateKind::File), &[&file]));
+
+        handler.check_queue().await.unwrap();
+        assert_eq!(
+            jobs(&db).await,
+            vec![(SyncEventType::Update, local(&file), None)]
+        );
+        assert!(dropped.take().is_none());
     }
 
-    /// Generate change token
-    async fn generate_change_token(path: &Path) -> Result<String> {
-        let metadata = tokio::fs::metadata(path).await?;
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unreadable_file_is_skipped_until_readable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let (handler, db, root) = handler(&temp_dir).await;
+        let config = handler.config.lock().await.get().clone();
+        let source = local(&root);
+        let file = root.join("secret.txt");
+        std::fs::write(&file, "secret").unwrap();
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o000)).unwrap();
+
+        // Permissions do not apply to root
+        if std::fs::read(&file).is_ok() {
+            return;
+        }
+
+        // Skipped once, however often it is scanned
+        for _ in 0..2 {
+            FileScanner::scan_directory(&db, &source, "/Sync", &config)
+                .await
+                .unwrap();
+        }
+        let all = db.get_all_jobs().await.unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(
+            all[0].status,
+            SyncJobStatus::Skipped(SkipReason::PermissionDenied)
+        );
+        assert!(all[0]
+            .last_error
+            .as_deref()
+            .unwrap()
+            .contains("Permission denied"));
+
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o644)).unwrap();
+        FileScanner::scan_directory(&db, &source, "/Sync", &config)
+            .await
+            .unwrap();
+
+        let all = db.get_all_jobs().await.unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].status, SyncJobStatus::Pending);
+        assert!(all[0].change_token.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_scan_visits_recently_modified_folders_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let (handler, db, root) = handler(&temp_dir).await;
+        let config = handler.config.lock().await.get().clone();
+        let now = std::time::SystemTime::now();
+
+        for (name, age_secs) in [("archive", 3600), ("active", 0), ("backup", 60)] {
+            let dir = root.join(name);
+            std::fs::create_dir(&dir).unwrap();
+            std::fs::write(dir.join("file.txt"), name).unwrap();
+            std::fs::File::open(&dir)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age_secs))
+                .unwrap();
+        }
+
+        FileScanner::scan_directory(&db, &local(&root), "/Sync", &config)
+            .await
+            .unwrap();
+        let queued: Vec<String> = jobs(&db)
+            .await
+            .into_iter()
+            .map(|(_, path, _)| path)
+            .collect();
+        let expected: Vec<String> = ["active", "backup", "archive"]
+            .iter()
+            .map(|name| local(&root.join(name).join("file.txt")))
+            .collect();
+        assert_eq!(queued, expected);
+    }
+
+    #[tokio::test]
+    async fn test_scan_queues_deletes_for_vanished_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let (handler, db, root) = handler(&temp_dir).await;
+        let config = handler.config.lock().await.get().clone();
+        let source = local(&root);
+        let kept = root.join("kept.txt");
+        std::fs::write(&kept, "kept").unwrap();
+
+        FileScanner::scan_directory(&db, &source, "/Sync", &config)
+            .await
+            .unwrap();
+        let synced = db.get_all_jobs().await.unwrap().remove(0);
+        db.update_job_status(synced.id, SyncJobStatus::Synced, None)
+            .await
+            .unwrap();
+        db.update_file_state(&local(&kept), &synced.change_token.unwrap())
+            .await
+            .unwrap();
+        db.update_file_state(&local(&root.join("gone.txt")), "1:2")
+            .await
+            .unwrap();
+
+        let count = FileScanner::scan_directory(&db, &source, "/Sync", &config)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        let pending = db.get_pending_jobs(10).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].event_type, SyncEventType::Delete);
+        assert_eq!(pending[0].remote_path, "/Sync/gone.txt");
+
+        // A missing sync directory, e.g. an unmounted drive, deletes nothing
+        db.update_job_status(pending[0].id, SyncJobStatus::Synced, None)
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        FileScanner::scan_directory(&db, &source, "/Sync", &config)
+            .await
+            .unwrap();
+        assert!(db.get_pending_jobs(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_synced_paths_keep_their_remote_names() {
+        let temp_dir = TempDir::new().unwrap();
+        let (handler, db, root) = handler(&temp_dir).await;
+        let mut config = handler.config.lock().await.get().clone();
+        config.remote_naming = crate::types::RemoteNaming::Slugify;
+        let source = local(&root);
+        let folder = root.join("Résumé");
+        std::fs::create_dir(&folder).unwrap();
+        std::fs::write(folder.join("CV.pdf"), "cv").unwrap();
+        std::fs::write(folder.join("New Notes.txt"), "notes").unwrap();
+
+        // Synced before the policy changed
+        for (path, remote_path, is_directory) in [
+            (folder.clone(), "/Sync/Résumé", true),
+            (folder.join("CV.pdf"), "/Sync/Résumé/CV.pdf", false),
+        ] {
+            db.update_node_mapping(&NodeMapping {
+                local_path: local(&path),
+                remote_path: remote_path.to_string(),
+                node_uid: "node".to_string(),
+                parent_node_uid: "parent".to_string(),
+                is_directory,
+                updated_at: chrono::Utc::now(),
+            })
+            .await
+            .unwrap();
+        }
+
+        FileScanner::scan_directory(&db, &source, "/Sync", &config)
+            .await
+            .unwrap();
+        let mut remote_paths: Vec<String> = db
+            .get_all_jobs()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|job| job.remote_path)
+            .collect();
+        remote_paths.sort();
+        assert_eq!(
+            remote_paths,
+            ["/Sync/Résumé/CV.pdf", "/Sync/Résumé/new-notes.txt"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_adopts_watcher_job_for_the_same_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let (mut handler, db, root) = handler(&temp_dir).await;
+        let config = handler.config.lock().await.get().clone();
+        let file = root.join("notes.txt");
+        let start = Instant::now();
+
+        std::fs::write(&file, "notes").unwrap();
+        handler
+            .handle_event(event(EventKind::Create(CreateKind::File), &[&file]), start)
+            .await
+            .unwrap();
+        handler.flush(start + WINDOW + Duration::from_secs(1)).await;
+        let queued = db.get_all_jobs().await.unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].source, Some(JobSource::Watcher));
+
+        // Uploading when the reconciliation scan comes along
+        db.mark_job_processing(queued[0].id).await.unwrap();
+        let count = FileScanner::scan_directory(&db, &local(&root), "/Sync", &config)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+        assert_eq!(db.get_all_jobs().await.unwrap().len(), 1);
+
+        // A later change is the scanner's own
+        std::fs::write(&file, "more notes").unwrap();
+        FileScanner::scan_directory(&db, &local(&root), "/Sync", &config)
+            .await
+            .unwrap();
+        let queued = db.get_all_jobs().await.unwrap();
+        assert_eq!(queued.len(), 2);
+        assert_eq!(queued[1].source, Some(JobSource::Scanner));
+    }
+
+    #[tokio::test]
+    async fn test_scan_batch_drops_changes_stored_meanwhile() {
+        let temp_dir = TempDir::new().unwrap();
+        let (_handler, db, root) = handler(&temp_dir).await;
+        let file = root.join("notes.txt");
+        std::fs::write(&file, "notes").unwrap();
+
+        let mut batch = ScanBatch::new(&local(&root), 0);
+        batch
+            .push(
+                &db,
+                SyncEvent {
+                    event_type: SyncEventType::Update,
+                    local_path: local(&file),
+                    remote_path: "/Sync/notes.txt".to_string(),
+                    change_token: Some("1:5".to_string()),
+                    old_local_path: None,
+                    old_remote_path: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        // Uploaded by the watcher's job before the batch is flushed
+        db.update_file_state(&local(&file), "1:5").await.unwrap();
+        batch.flush(&db).await.unwrap();
+        assert_eq!(batch.queued, 0);
+        assert!(jobs(&db).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_own_writes_are_not_uploaded_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let (mut handler, db, root) = handler(&temp_dir).await;
+        let downloaded = root.join("downloaded.txt");
+        let edited = root.join("edited.txt");
+        let start = Instant::now();
+
+        for file in [&downloaded, &edited] {
+            write_own_file(&db, file, b"remote", ChangeDetection::Quick)
+                .await
+                .unwrap();
+        }
+        // Changed again after we wrote it
+        std::fs::write(&edited, "local edit").unwrap();
+
+        for file in [&downloaded, &edited] {
+            handler
+                .handle_event(event(EventKind::Create(CreateKind::File), &[file]), start)
+                .await
+                .unwrap();
+        }
+        handler.flush(start + WINDOW + Duration::from_secs(1)).await;
+
+        assert_eq!(
+            jobs(&db).await,
+            vec![(SyncEventType::CreateFile, local(&edited), None)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_treats_case_only_renames_as_moves() {
+        let temp_dir = TempDir::new().unwrap();
+        let (handler, db, root) = handler(&temp_dir).await;
+        let mut config = handler.config.lock().await.get().clone();
+        config.sync_dirs[0].case_insensitive = Some(true);
+        let old = local(&root.join("Report.txt"));
+        db.update_node_mapping(&NodeMapping {
+            local_path: old.clone(),
+            remote_path: "/Sync/Report.txt".to_string(),
+            node_uid: "report".to_string(),
+            parent_node_uid: "root".to_string(),
+            is_directory: false,
+            updated_at: chrono::Utc::now(),
+        })
+        .await
+        .unwrap();
+        db.update_file_state(&old, "1:2").await.unwrap();
+        let renamed = root.join("report.txt");
+        std::fs::write(&renamed, "report").unwrap();
+
+        FileScanner::scan_directory(&db, &local(&root), "/Sync", &config)
+            .await
+            .unwrap();
+        assert_eq!(
+            jobs(&db).await,
+            vec![(SyncEventType::Update, local(&renamed), Some(old))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_deleted_lists_topmost_deleted_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let (_handler, db, root) = handler(&temp_dir).await;
+        std::fs::write(root.join("kept.txt"), "kept").unwrap();
+
+        for (name, is_directory) in [
+            ("kept.txt", false),
+            ("gone.txt", false),
+            ("old", true),
+            ("old/inner.txt", false),
+        ] {
+            db.update_node_mapping(&NodeMapping {
+                local_path: local(&root.join(name)),
+                remote_path: format!("/Sync/{}", name),
+                node_uid: name.to_string(),
+                parent_node_uid: "root".to_string(),
+                is_directory,
+                updated_at: chrono::Utc::now(),
+            })
+            .await
+            .unwrap();
+        }
 
-        let mtime = metadata
-            .modified()
-            .map_err(Error::Io)?
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|_| Error::InvalidPath("Invalid modification time".to_string()))?
-            .as_secs();
+        let deleted = FileScanner::find_deleted(&db, &local(&root), "/Sync")
+            .await
+            .unwrap();
+        let remote: Vec<&str> = deleted.iter().map(|m| m.remote_path.as_str()).collect();
+        assert_eq!(remote, ["/Sync/gone.txt", "/Sync/old"]);
 
-        let size = metadata.len();
+        let config = Config::default();
+        assert_eq!(
+            FileScanner::prune_deleted(&db, &deleted, &config)
+                .await
+                .unwrap(),
+            2
+        );
+        let queued = jobs(&db).await;
+        assert_eq!(queued.len(), 2);
+        assert!(queued
+            .iter()
+            .all(|(kind, _, _)| *kind == SyncEventType::Delete));
 
-        Ok(format!("{}:{}", mtime, size))
+        // A missing sync directory, e.g. an unmounted drive, deletes nothing
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(FileScanner::find_deleted(&db, &local(&root), "/Sync")
+            .await
+            .unwrap()
+            .is_empty());
     }
 }